@@ -1,3 +1,21 @@
 fn main() {
+    // Embed the Sentry DSN from .env when present. It is optional and can be
+    // overridden at runtime, so a missing file no longer breaks the build.
+    println!("cargo:rerun-if-changed=../.env");
+    println!("cargo:rerun-if-env-changed=SENTRY_TAURI");
+    if std::env::var_os("SENTRY_TAURI").is_none() {
+        let dsn = std::fs::read_to_string("../.env")
+            .ok()
+            .and_then(|contents| {
+                contents
+                    .lines()
+                    .find_map(|line| line.strip_prefix("SENTRY_TAURI="))
+                    .map(|value| value.trim().trim_matches('"').to_string())
+            });
+        if let Some(dsn) = dsn.filter(|dsn| !dsn.is_empty()) {
+            println!("cargo:rustc-env=SENTRY_TAURI={dsn}");
+        }
+    }
+
     tauri_build::build()
 }
@@ -0,0 +1,58 @@
+# Strings the backend writes into exports and reports.
+
+-app-name = AstraNotes
+
+export-header-version-name = Version Name
+export-header-version-number = Version Number
+export-header-note-state = Note State
+export-header-labels = Labels
+export-header-author = Author
+export-header-created-at = Created At
+export-header-frame = Frame
+export-header-notes = Notes
+
+report-note-count =
+    { $count ->
+        [one] { $count } note
+       *[other] { $count } notes
+    }
+report-exported = Exported { $date }
+report-frame = Frame { $frame }
+report-page = Page { $page } of { $total }
+share-title = Review notes
+print-dialog-title = Print Notes
+recent-playlists = Recent Playlists
+menu-file = File
+menu-edit = Edit
+menu-playlist = Playlist
+menu-notes = Notes
+menu-help = Help
+menu-open-playlist = Open Playlist…
+menu-export-csv = Export as CSV…
+menu-export-pdf = Export as PDF…
+menu-print = Print…
+menu-settings = Settings…
+menu-find = Find
+menu-sync-playlists = Sync Playlists
+menu-next-version = Next Version
+menu-previous-version = Previous Version
+menu-save-draft = Save Draft
+menu-publish-selected = Publish Selected
+menu-publish-all = Publish All
+menu-copy-notes = Copy Notes
+menu-documentation = Documentation
+menu-report-issue = Report an Issue…
+menu-check-for-updates = Check for Updates…
+menu-about = About { -app-name }
+menu-undo = Undo
+menu-redo = Redo
+menu-cut = Cut
+menu-copy = Copy
+menu-paste = Paste
+menu-select-all = Select All
+menu-services = Services
+menu-hide = Hide { -app-name }
+menu-hide-others = Hide Others
+menu-show-all = Show All
+menu-close-window = Close Window
+menu-quit = Quit { -app-name }
@@ -0,0 +1,58 @@
+# Chaînes écrites par le backend dans les exports et les rapports.
+
+-app-name = AstraNotes
+
+export-header-version-name = Nom de la version
+export-header-version-number = Numéro de version
+export-header-note-state = État de la note
+export-header-labels = Étiquettes
+export-header-author = Auteur
+export-header-created-at = Créée le
+export-header-frame = Image
+export-header-notes = Notes
+
+report-note-count =
+    { $count ->
+        [one] { $count } note
+       *[other] { $count } notes
+    }
+report-exported = Exporté le { $date }
+report-frame = Image { $frame }
+report-page = Page { $page } sur { $total }
+share-title = Notes de revue
+print-dialog-title = Imprimer les notes
+recent-playlists = Playlists récentes
+menu-file = Fichier
+menu-edit = Édition
+menu-playlist = Playlist
+menu-notes = Notes
+menu-help = Aide
+menu-open-playlist = Ouvrir une playlist…
+menu-export-csv = Exporter en CSV…
+menu-export-pdf = Exporter en PDF…
+menu-print = Imprimer…
+menu-settings = Réglages…
+menu-find = Rechercher
+menu-sync-playlists = Synchroniser les playlists
+menu-next-version = Version suivante
+menu-previous-version = Version précédente
+menu-save-draft = Enregistrer le brouillon
+menu-publish-selected = Publier la sélection
+menu-publish-all = Tout publier
+menu-copy-notes = Copier les notes
+menu-documentation = Documentation
+menu-report-issue = Signaler un problème…
+menu-check-for-updates = Rechercher des mises à jour…
+menu-about = À propos de { -app-name }
+menu-undo = Annuler
+menu-redo = Rétablir
+menu-cut = Couper
+menu-copy = Copier
+menu-paste = Coller
+menu-select-all = Tout sélectionner
+menu-services = Services
+menu-hide = Masquer { -app-name }
+menu-hide-others = Masquer les autres
+menu-show-all = Tout afficher
+menu-close-window = Fermer la fenêtre
+menu-quit = Quitter { -app-name }
@@ -0,0 +1,54 @@
+# バックエンドがエクスポートとレポートに書き込む文字列。
+
+-app-name = AstraNotes
+
+export-header-version-name = バージョン名
+export-header-version-number = バージョン番号
+export-header-note-state = ノートの状態
+export-header-labels = ラベル
+export-header-author = 作成者
+export-header-created-at = 作成日時
+export-header-frame = フレーム
+export-header-notes = ノート
+
+report-note-count = ノート { $count } 件
+report-exported = エクスポート日時 { $date }
+report-frame = フレーム { $frame }
+report-page = { $page } / { $total } ページ
+share-title = レビューノート
+print-dialog-title = ノートを印刷
+recent-playlists = 最近のプレイリスト
+menu-file = ファイル
+menu-edit = 編集
+menu-playlist = プレイリスト
+menu-notes = ノート
+menu-help = ヘルプ
+menu-open-playlist = プレイリストを開く…
+menu-export-csv = CSV として書き出す…
+menu-export-pdf = PDF として書き出す…
+menu-print = プリント…
+menu-settings = 設定…
+menu-find = 検索
+menu-sync-playlists = プレイリストを同期
+menu-next-version = 次のバージョン
+menu-previous-version = 前のバージョン
+menu-save-draft = 下書きを保存
+menu-publish-selected = 選択したノートを公開
+menu-publish-all = すべて公開
+menu-copy-notes = ノートをコピー
+menu-documentation = ドキュメント
+menu-report-issue = 問題を報告…
+menu-check-for-updates = アップデートを確認…
+menu-about = { -app-name } について
+menu-undo = 取り消す
+menu-redo = やり直す
+menu-cut = カット
+menu-copy = コピー
+menu-paste = ペースト
+menu-select-all = すべてを選択
+menu-services = サービス
+menu-hide = { -app-name } を非表示
+menu-hide-others = ほかを非表示
+menu-show-all = すべてを表示
+menu-close-window = ウインドウを閉じる
+menu-quit = { -app-name } を終了
@@ -0,0 +1,39 @@
+//! VoiceOver announcements on macOS.
+
+use std::ffi::c_void;
+
+use objc2::runtime::AnyObject;
+use objc2_app_kit::{
+    NSAccessibilityAnnouncementKey, NSAccessibilityAnnouncementRequestedNotification,
+    NSAccessibilityPostNotificationWithUserInfo, NSAccessibilityPriorityKey,
+    NSAccessibilityPriorityLevel, NSWindow,
+};
+use objc2_foundation::{NSDictionary, NSNumber, NSString};
+
+/// Posts `text` as an announcement from the window.
+///
+/// # Safety
+///
+/// `window` must be the window's `NSWindow`, and this must run on the main
+/// thread.
+pub(super) unsafe fn announce(
+    window: *mut c_void,
+    text: &str,
+    priority: NSAccessibilityPriorityLevel,
+) {
+    let window = unsafe { &*window.cast::<NSWindow>() };
+    let text = NSString::from_str(text);
+    let priority = NSNumber::new_isize(priority.0);
+    let values: [&AnyObject; 2] = [&text, &priority];
+    unsafe {
+        let info = NSDictionary::from_slices(
+            &[NSAccessibilityAnnouncementKey, NSAccessibilityPriorityKey],
+            &values,
+        );
+        NSAccessibilityPostNotificationWithUserInfo(
+            window,
+            NSAccessibilityAnnouncementRequestedNotification,
+            Some(&info),
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Screen reader announcements for status changes.
+//!
+//! Live regions in the webview are read unreliably once focus has moved on,
+//! so messages such as "Publish complete, 42 notes" are posted to the
+//! platform instead: as an NSAccessibility announcement on macOS and a UI
+//! Automation notification on Windows. Linux has no equivalent that Orca
+//! reads, and WebKitGTK's live regions work there, so nothing is posted and
+//! the frontend falls back to its own.
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use serde::Deserialize;
+use tauri::WebviewWindow;
+
+use crate::error::{Error, Result};
+
+/// How urgently the screen reader should speak; higher interrupts speech.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AnnouncementPriority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+#[cfg(target_os = "macos")]
+fn post(window: &WebviewWindow, text: &str, priority: AnnouncementPriority) -> Result<bool> {
+    use objc2_app_kit::NSAccessibilityPriorityLevel;
+
+    let level = match priority {
+        AnnouncementPriority::Low => NSAccessibilityPriorityLevel::Low,
+        AnnouncementPriority::Medium => NSAccessibilityPriorityLevel::Medium,
+        AnnouncementPriority::High => NSAccessibilityPriorityLevel::High,
+    };
+    let ns_window = window.ns_window()?;
+    unsafe { macos::announce(ns_window, text, level) };
+    Ok(true)
+}
+
+#[cfg(target_os = "windows")]
+fn post(window: &WebviewWindow, text: &str, priority: AnnouncementPriority) -> Result<bool> {
+    use ::windows::Win32::UI::Accessibility::{
+        NotificationProcessing_All, NotificationProcessing_CurrentThenMostRecent,
+        NotificationProcessing_ImportantAll,
+    };
+
+    let processing = match priority {
+        AnnouncementPriority::Low => NotificationProcessing_CurrentThenMostRecent,
+        AnnouncementPriority::Medium => NotificationProcessing_All,
+        AnnouncementPriority::High => NotificationProcessing_ImportantAll,
+    };
+    windows::announce(window.hwnd()?, text, processing)
+        .map_err(|err| Error::Accessibility(format!("Failed to raise the notification: {err}")))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn post(_window: &WebviewWindow, _text: &str, _priority: AnnouncementPriority) -> Result<bool> {
+    Ok(false)
+}
+
+/// Has the screen reader speak `text`. Returns whether it was posted to the
+/// platform; when it was not, the frontend announces it in a live region.
+#[tauri::command]
+pub async fn announce(
+    window: WebviewWindow,
+    text: String,
+    priority: Option<AnnouncementPriority>,
+) -> Result<bool> {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err(Error::InvalidInput("Nothing to announce".into()));
+    }
+    let priority = priority.unwrap_or_default();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let target = window.clone();
+    // Accessibility notifications are raised for the window on its UI thread
+    window.run_on_main_thread(move || {
+        let _ = tx.send(post(&target, &text, priority));
+    })?;
+    rx.await
+        .map_err(|_| Error::Accessibility("The announcement could not be posted".into()))?
+}
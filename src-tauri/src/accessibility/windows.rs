@@ -0,0 +1,30 @@
+//! Narrator announcements through UI Automation notifications.
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Accessibility::{
+    NotificationKind_Other, NotificationProcessing, UiaClientsAreListening,
+    UiaHostProviderFromHwnd, UiaRaiseNotificationEvent,
+};
+use windows::core::{BSTR, Result};
+
+/// Groups the app's notifications, so screen readers can tell them apart.
+const ACTIVITY_ID: &str = "AstraNotes.Announcement";
+
+/// Raises `text` as a notification from the window's host provider. Returns
+/// `false` without a screen reader or other UIA client listening.
+pub(super) fn announce(hwnd: HWND, text: &str, processing: NotificationProcessing) -> Result<bool> {
+    unsafe {
+        if !UiaClientsAreListening().as_bool() {
+            return Ok(false);
+        }
+        let provider = UiaHostProviderFromHwnd(hwnd)?;
+        UiaRaiseNotificationEvent(
+            &provider,
+            NotificationKind_Other,
+            processing,
+            &BSTR::from(text),
+            &BSTR::from(ACTIVITY_ID),
+        )?;
+    }
+    Ok(true)
+}
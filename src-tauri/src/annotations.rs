@@ -0,0 +1,523 @@
+//! Burns frontend canvas annotations into stills at full resolution.
+//!
+//! The webview canvas runs out of memory on 4K+ plates, so the frontend
+//! sends its vector shapes instead and they are rasterized here. Coordinates
+//! are in canvas pixels and scaled to the image.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{Font, OutlineCurve, PxScale, ScaleFont};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::Deserialize;
+use tauri::AppHandle;
+use tiny_skia::{FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+use crate::error::{Error, Result};
+use crate::export::write_atomically;
+use crate::reports::text::TextRenderer;
+use crate::watermark::{self, WatermarkRequest};
+
+pub(crate) const LINE_HEIGHT: f32 = 1.2;
+const JPEG_QUALITY: u8 = 92;
+
+type Point = [f32; 2];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Annotation {
+    /// Freehand stroke through `points`.
+    Stroke {
+        points: Vec<Point>,
+        color: String,
+        width: f32,
+    },
+    Line {
+        from: Point,
+        to: Point,
+        color: String,
+        width: f32,
+    },
+    Arrow {
+        from: Point,
+        to: Point,
+        color: String,
+        width: f32,
+    },
+    #[serde(rename_all = "camelCase")]
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: String,
+        stroke_width: f32,
+        #[serde(default)]
+        fill: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Ellipse {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: String,
+        stroke_width: f32,
+        #[serde(default)]
+        fill: Option<String>,
+    },
+    /// Text with its top-left corner at (`x`, `y`); `\n` starts a new line.
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+        color: String,
+        size: f32,
+        #[serde(default)]
+        background: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationLayer {
+    /// Size of the canvas the shapes were drawn on; defaults to the image size.
+    #[serde(default)]
+    pub canvas_width: Option<f32>,
+    #[serde(default)]
+    pub canvas_height: Option<f32>,
+    pub annotations: Vec<Annotation>,
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`.
+pub(crate) fn parse_color(value: &str) -> Result<tiny_skia::Color> {
+    let hex = value.trim().trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+    };
+    let color = match hex.len() {
+        6 => channel(0)
+            .zip(channel(2))
+            .zip(channel(4))
+            .map(|((r, g), b)| (r, g, b, 255)),
+        8 => channel(0)
+            .zip(channel(2))
+            .zip(channel(4))
+            .zip(channel(6))
+            .map(|(((r, g), b), a)| (r, g, b, a)),
+        _ => None,
+    };
+    color
+        .map(|(r, g, b, a)| tiny_skia::Color::from_rgba8(r, g, b, a))
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid color {value}")))
+}
+
+fn paint(color: &str) -> Result<Paint<'static>> {
+    let mut paint = Paint::default();
+    paint.set_color(parse_color(color)?);
+    paint.anti_alias = true;
+    Ok(paint)
+}
+
+fn stroke(width: f32) -> Stroke {
+    Stroke {
+        width: width.max(0.5),
+        line_cap: LineCap::Round,
+        line_join: LineJoin::Round,
+        ..Stroke::default()
+    }
+}
+
+/// Outlines `lines` of text with the top-left corner at (`x`, `y`), for
+/// filling at any resolution or under a transform.
+pub(crate) fn outline_text(
+    text: &TextRenderer,
+    lines: &[&str],
+    size: f32,
+    x: f32,
+    y: f32,
+) -> Option<tiny_skia::Path> {
+    let font = text.font();
+    let scaled = font.as_scaled(PxScale::from(size));
+    let (scale_x, scale_y) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+    let line_height = size * LINE_HEIGHT;
+    let mut builder = PathBuilder::new();
+    for (index, line) in lines.iter().enumerate() {
+        let baseline = y + line_height * index as f32 + scaled.ascent();
+        let mut caret = x;
+        let mut previous = None;
+        for c in line.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            if let Some(outline) = font.outline(id) {
+                // Font units have y pointing up
+                let map = |point: ab_glyph::Point| {
+                    (caret + point.x * scale_x, baseline - point.y * scale_y)
+                };
+                let mut last = None;
+                for curve in &outline.curves {
+                    let (start, end) = match curve {
+                        OutlineCurve::Line(a, b) | OutlineCurve::Quad(a, _, b) => (*a, *b),
+                        OutlineCurve::Cubic(a, _, _, b) => (*a, *b),
+                    };
+                    if last != Some(start) {
+                        if last.is_some() {
+                            builder.close();
+                        }
+                        let (sx, sy) = map(start);
+                        builder.move_to(sx, sy);
+                    }
+                    match curve {
+                        OutlineCurve::Line(_, b) => {
+                            let (bx, by) = map(*b);
+                            builder.line_to(bx, by);
+                        }
+                        OutlineCurve::Quad(_, c, b) => {
+                            let ((cx, cy), (bx, by)) = (map(*c), map(*b));
+                            builder.quad_to(cx, cy, bx, by);
+                        }
+                        OutlineCurve::Cubic(_, c1, c2, b) => {
+                            let ((c1x, c1y), (c2x, c2y), (bx, by)) = (map(*c1), map(*c2), map(*b));
+                            builder.cubic_to(c1x, c1y, c2x, c2y, bx, by);
+                        }
+                    }
+                    last = Some(end);
+                }
+                if last.is_some() {
+                    builder.close();
+                }
+            }
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+        }
+    }
+    builder.finish()
+}
+
+struct Renderer<'a> {
+    pixmap: &'a mut Pixmap,
+    text: TextRenderer,
+    scale_x: f32,
+    scale_y: f32,
+}
+
+impl Renderer<'_> {
+    fn point(&self, [x, y]: Point) -> (f32, f32) {
+        (x * self.scale_x, y * self.scale_y)
+    }
+
+    fn stroke_path(&mut self, points: &[Point], color: &str, width: f32) -> Result<()> {
+        let mut builder = PathBuilder::new();
+        let mut points = points.iter().map(|point| self.point(*point));
+        let Some((x, y)) = points.next() else {
+            return Ok(());
+        };
+        builder.move_to(x, y);
+        let mut segments = 0;
+        for (x, y) in points {
+            builder.line_to(x, y);
+            segments += 1;
+        }
+        if segments == 0 {
+            // A single tap draws a dot
+            builder.line_to(x + 0.01, y);
+        }
+        if let Some(path) = builder.finish() {
+            let stroke = stroke(width * self.scale_x);
+            self.pixmap
+                .stroke_path(&path, &paint(color)?, &stroke, Transform::identity(), None);
+        }
+        Ok(())
+    }
+
+    fn arrow(&mut self, from: Point, to: Point, color: &str, width: f32) -> Result<()> {
+        let (x0, y0) = self.point(from);
+        let (x1, y1) = self.point(to);
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let length = dx.hypot(dy);
+        if length <= f32::EPSILON {
+            return Ok(());
+        }
+        let head = (width * self.scale_x * 4.0)
+            .max(12.0 * self.scale_x)
+            .min(length);
+        let (ux, uy) = (dx / length, dy / length);
+        // Stop the shaft short so its round cap does not poke through the tip
+        let (bx, by) = (x1 - ux * head * 0.8, y1 - uy * head * 0.8);
+        self.stroke_path(
+            &[from, [bx / self.scale_x, by / self.scale_y]],
+            color,
+            width,
+        )?;
+
+        let spread = head * 0.5;
+        let (base_x, base_y) = (x1 - ux * head, y1 - uy * head);
+        let mut builder = PathBuilder::new();
+        builder.move_to(x1, y1);
+        builder.line_to(base_x - uy * spread, base_y + ux * spread);
+        builder.line_to(base_x + uy * spread, base_y - ux * spread);
+        builder.close();
+        if let Some(path) = builder.finish() {
+            self.pixmap.fill_path(
+                &path,
+                &paint(color)?,
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    fn shape(
+        &mut self,
+        ellipse: bool,
+        bounds: [f32; 4],
+        color: &str,
+        stroke_width: f32,
+        fill: Option<&str>,
+    ) -> Result<()> {
+        let [x, y, width, height] = bounds;
+        let (x, y) = self.point([x.min(x + width), y.min(y + height)]);
+        let (width, height) = (width.abs() * self.scale_x, height.abs() * self.scale_y);
+        let Some(rect) = Rect::from_xywh(x, y, width, height) else {
+            return Ok(());
+        };
+        let path = if ellipse {
+            PathBuilder::from_oval(rect)
+        } else {
+            Some(PathBuilder::from_rect(rect))
+        };
+        let Some(path) = path else {
+            return Ok(());
+        };
+        if let Some(fill) = fill {
+            self.pixmap.fill_path(
+                &path,
+                &paint(fill)?,
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+        if stroke_width > 0.0 {
+            let stroke = stroke(stroke_width * self.scale_x);
+            self.pixmap
+                .stroke_path(&path, &paint(color)?, &stroke, Transform::identity(), None);
+        }
+        Ok(())
+    }
+
+    fn text(
+        &mut self,
+        origin: Point,
+        text: &str,
+        color: &str,
+        size: f32,
+        background: Option<&str>,
+    ) -> Result<()> {
+        let (x, y) = self.point(origin);
+        let size = size * self.scale_x;
+        let line_height = size * LINE_HEIGHT;
+        let lines: Vec<&str> = text.lines().collect();
+
+        if let Some(background) = background {
+            let width = lines
+                .iter()
+                .map(|line| self.text.width(line, size))
+                .fold(0.0, f32::max);
+            let padding = size * 0.25;
+            let height = line_height * lines.len() as f32;
+            if let Some(rect) = Rect::from_xywh(
+                x - padding,
+                y - padding,
+                width + padding * 2.0,
+                height + padding * 2.0,
+            ) {
+                self.pixmap
+                    .fill_rect(rect, &paint(background)?, Transform::identity(), None);
+            }
+        }
+
+        if let Some(path) = outline_text(&self.text, &lines, size, x, y) {
+            self.pixmap.fill_path(
+                &path,
+                &paint(color)?,
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, annotation: &Annotation) -> Result<()> {
+        match annotation {
+            Annotation::Stroke {
+                points,
+                color,
+                width,
+            } => self.stroke_path(points, color, *width),
+            Annotation::Line {
+                from,
+                to,
+                color,
+                width,
+            } => self.stroke_path(&[*from, *to], color, *width),
+            Annotation::Arrow {
+                from,
+                to,
+                color,
+                width,
+            } => self.arrow(*from, *to, color, *width),
+            Annotation::Rect {
+                x,
+                y,
+                width,
+                height,
+                color,
+                stroke_width,
+                fill,
+            } => self.shape(
+                false,
+                [*x, *y, *width, *height],
+                color,
+                *stroke_width,
+                fill.as_deref(),
+            ),
+            Annotation::Ellipse {
+                x,
+                y,
+                width,
+                height,
+                color,
+                stroke_width,
+                fill,
+            } => self.shape(
+                true,
+                [*x, *y, *width, *height],
+                color,
+                *stroke_width,
+                fill.as_deref(),
+            ),
+            Annotation::Text {
+                x,
+                y,
+                text,
+                color,
+                size,
+                background,
+            } => self.text([*x, *y], text, color, *size, background.as_deref()),
+        }
+    }
+}
+
+/// Converts `image` to a pixmap for drawing on.
+pub(crate) fn to_pixmap(image: RgbaImage) -> Result<Pixmap> {
+    let (width, height) = image.dimensions();
+    let size = tiny_skia::IntSize::from_wh(width, height)
+        .ok_or_else(|| Error::InvalidInput("Base image is empty".into()))?;
+    // tiny-skia works on premultiplied alpha
+    let mut data = image.into_raw();
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = u16::from(pixel[3]);
+        for channel in &mut pixel[..3] {
+            *channel = ((u16::from(*channel) * alpha + 127) / 255) as u8;
+        }
+    }
+    Pixmap::from_vec(data, size)
+        .ok_or_else(|| Error::InvalidInput("Base image is too large".into()))
+}
+
+pub(crate) fn from_pixmap(pixmap: Pixmap) -> Result<RgbaImage> {
+    let (width, height) = (pixmap.width(), pixmap.height());
+    let mut data = pixmap.take();
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = u16::from(pixel[3]);
+        if alpha > 0 && alpha < 255 {
+            for channel in &mut pixel[..3] {
+                *channel = ((u16::from(*channel) * 255 + alpha / 2) / alpha).min(255) as u8;
+            }
+        }
+    }
+    RgbaImage::from_raw(width, height, data)
+        .ok_or_else(|| Error::InvalidInput("Rendered image has the wrong size".into()))
+}
+
+/// Composites `layer` over `base` and returns the flattened image.
+pub fn render(base: RgbaImage, layer: &AnnotationLayer) -> Result<RgbaImage> {
+    let (width, height) = base.dimensions();
+    let mut pixmap = to_pixmap(base)?;
+
+    let mut renderer = Renderer {
+        pixmap: &mut pixmap,
+        text: TextRenderer::new(),
+        scale_x: width as f32
+            / layer
+                .canvas_width
+                .filter(|w| *w > 0.0)
+                .unwrap_or(width as f32),
+        scale_y: height as f32
+            / layer
+                .canvas_height
+                .filter(|h| *h > 0.0)
+                .unwrap_or(height as f32),
+    };
+    for annotation in &layer.annotations {
+        renderer.draw(annotation)?;
+    }
+    from_pixmap(pixmap)
+}
+
+/// Writes `image` as a PNG or JPEG, chosen by the extension of `path`.
+pub(crate) fn write(path: &Path, image: RgbaImage) -> Result<()> {
+    let format = ImageFormat::from_path(path)
+        .ok()
+        .filter(|format| matches!(format, ImageFormat::Png | ImageFormat::Jpeg))
+        .ok_or_else(|| {
+            Error::InvalidInput(format!("Unsupported image format: {}", path.display()))
+        })?;
+    write_atomically(path, |temp| {
+        match format {
+            ImageFormat::Jpeg => {
+                let file = BufWriter::new(File::create(temp)?);
+                let rgb = DynamicImage::ImageRgba8(image).into_rgb8();
+                JpegEncoder::new_with_quality(file, JPEG_QUALITY).encode_image(&rgb)?;
+            }
+            _ => image.save_with_format(temp, ImageFormat::Png)?,
+        }
+        Ok(())
+    })
+}
+
+/// Renders `layer` over the image at `base_path` and writes a PNG or JPEG
+/// to `out_path`, chosen by extension, watermarked on top when requested.
+#[tauri::command]
+pub async fn render_annotations(
+    app: AppHandle,
+    base_path: PathBuf,
+    layer: AnnotationLayer,
+    out_path: PathBuf,
+    watermark: Option<WatermarkRequest>,
+) -> Result<PathBuf> {
+    let watermark = match &watermark {
+        Some(request) => watermark::resolve(&app, request)?,
+        None => None,
+    };
+    let path = out_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let base = image::open(&base_path)?.into_rgba8();
+        let mut image = render(base, &layer)?;
+        if let Some(watermark) = watermark {
+            image = watermark.apply(image)?;
+        }
+        write(&path, image)
+    })
+    .await??;
+    Ok(out_path)
+}
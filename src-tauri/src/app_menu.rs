@@ -0,0 +1,469 @@
+//! The native menu bar: File, Edit, Playlist, Notes and Help.
+//!
+//! It is built here rather than in the webview so macOS shows the Cmd
+//! shortcuts where users look for them and screen readers can walk it. Items
+//! are forwarded to the frontend as `menu:action` events carrying the action,
+//! and the frontend reports what is open and selected with `set_menu_state`
+//! so items that need a playlist or a selection are disabled without one.
+//! Accelerators can be remapped; overrides are stored in `menu.json` in the
+//! app config directory and any action left out keeps its default.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::menu::{AboutMetadata, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tauri_plugin_global_shortcut::Shortcut;
+
+use crate::error::{Error, Result};
+use crate::i18n;
+use crate::paths;
+use crate::settings;
+
+pub const ACTION_EVENT: &str = "menu:action";
+
+const SETTINGS_FILE: &str = "menu.json";
+/// Keeps item ids apart from the tray menu's, which share the event listener.
+const ID_PREFIX: &str = "app-menu:";
+#[cfg(not(target_os = "macos"))]
+const QUIT_ID: &str = "app-menu:quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MenuAction {
+    OpenPlaylist,
+    ExportCsv,
+    ExportPdf,
+    Print,
+    Settings,
+    Find,
+    SyncPlaylists,
+    NextVersion,
+    PreviousVersion,
+    SaveDraft,
+    PublishSelected,
+    PublishAll,
+    CopyNotes,
+    Documentation,
+    ReportIssue,
+    CheckForUpdates,
+}
+
+impl MenuAction {
+    const ALL: [MenuAction; 16] = [
+        MenuAction::OpenPlaylist,
+        MenuAction::ExportCsv,
+        MenuAction::ExportPdf,
+        MenuAction::Print,
+        MenuAction::Settings,
+        MenuAction::Find,
+        MenuAction::SyncPlaylists,
+        MenuAction::NextVersion,
+        MenuAction::PreviousVersion,
+        MenuAction::SaveDraft,
+        MenuAction::PublishSelected,
+        MenuAction::PublishAll,
+        MenuAction::CopyNotes,
+        MenuAction::Documentation,
+        MenuAction::ReportIssue,
+        MenuAction::CheckForUpdates,
+    ];
+
+    /// Names the item id and its `menu-` label.
+    fn key(self) -> &'static str {
+        match self {
+            MenuAction::OpenPlaylist => "open-playlist",
+            MenuAction::ExportCsv => "export-csv",
+            MenuAction::ExportPdf => "export-pdf",
+            MenuAction::Print => "print",
+            MenuAction::Settings => "settings",
+            MenuAction::Find => "find",
+            MenuAction::SyncPlaylists => "sync-playlists",
+            MenuAction::NextVersion => "next-version",
+            MenuAction::PreviousVersion => "previous-version",
+            MenuAction::SaveDraft => "save-draft",
+            MenuAction::PublishSelected => "publish-selected",
+            MenuAction::PublishAll => "publish-all",
+            MenuAction::CopyNotes => "copy-notes",
+            MenuAction::Documentation => "documentation",
+            MenuAction::ReportIssue => "report-issue",
+            MenuAction::CheckForUpdates => "check-for-updates",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        let key = id.strip_prefix(ID_PREFIX)?;
+        Self::ALL.into_iter().find(|action| action.key() == key)
+    }
+
+    fn default_accelerator(self) -> Option<&'static str> {
+        match self {
+            MenuAction::OpenPlaylist => Some("CmdOrCtrl+O"),
+            MenuAction::ExportCsv => Some("CmdOrCtrl+E"),
+            MenuAction::ExportPdf => Some("CmdOrCtrl+Shift+E"),
+            MenuAction::Print => Some("CmdOrCtrl+P"),
+            MenuAction::Settings => Some("CmdOrCtrl+,"),
+            MenuAction::Find => Some("CmdOrCtrl+F"),
+            MenuAction::SyncPlaylists => Some("CmdOrCtrl+R"),
+            MenuAction::NextVersion => Some("CmdOrCtrl+Alt+Down"),
+            MenuAction::PreviousVersion => Some("CmdOrCtrl+Alt+Up"),
+            MenuAction::SaveDraft => Some("CmdOrCtrl+S"),
+            MenuAction::PublishSelected => Some("CmdOrCtrl+Shift+P"),
+            MenuAction::PublishAll => Some("CmdOrCtrl+Alt+Shift+P"),
+            MenuAction::CopyNotes => Some("CmdOrCtrl+Shift+C"),
+            MenuAction::Documentation => Some("F1"),
+            MenuAction::ReportIssue | MenuAction::CheckForUpdates => None,
+        }
+    }
+
+    fn enabled(self, state: &MenuState) -> bool {
+        match self {
+            MenuAction::ExportCsv
+            | MenuAction::ExportPdf
+            | MenuAction::Print
+            | MenuAction::NextVersion
+            | MenuAction::PreviousVersion
+            | MenuAction::SaveDraft => state.playlist_open,
+            MenuAction::PublishSelected | MenuAction::CopyNotes => state.notes_selected,
+            MenuAction::PublishAll => state.has_drafts,
+            _ => true,
+        }
+    }
+}
+
+/// What the frontend has open, deciding which items are enabled.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MenuState {
+    pub playlist_open: bool,
+    pub notes_selected: bool,
+    pub has_drafts: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MenuAccelerator {
+    pub action: MenuAction,
+    pub accelerator: Option<String>,
+    pub default_accelerator: Option<String>,
+}
+
+/// The items of the installed menu, kept to enable and remap them.
+#[derive(Default)]
+pub struct AppMenu {
+    items: Mutex<MenuItems>,
+    state: Mutex<MenuState>,
+}
+
+/// Saved overrides; `None` leaves an action unbound.
+type Overrides = HashMap<MenuAction, Option<String>>;
+type MenuItems = HashMap<MenuAction, MenuItem<Wry>>;
+
+fn load_overrides(app: &AppHandle) -> Result<Overrides> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+/// Every action's accelerator with `overrides` applied. Invalid or duplicate
+/// accelerators are rejected, since the menu would silently drop them.
+fn resolve(overrides: &Overrides) -> Result<HashMap<MenuAction, Option<String>>> {
+    let mut resolved = HashMap::new();
+    let mut taken: Vec<(u32, MenuAction)> = Vec::new();
+    for action in MenuAction::ALL {
+        let binding = match overrides.get(&action) {
+            Some(binding) => binding.as_deref(),
+            None => action.default_accelerator(),
+        }
+        .map(str::trim)
+        .filter(|binding| !binding.is_empty());
+        if let Some(binding) = binding {
+            // Global shortcuts and menu accelerators share the syntax
+            let id = Shortcut::from_str(binding)
+                .map_err(|err| {
+                    Error::InvalidInput(format!("Invalid accelerator {binding}: {err}"))
+                })?
+                .id();
+            if let Some((_, existing)) = taken.iter().find(|(taken, _)| *taken == id) {
+                return Err(Error::InvalidInput(format!(
+                    "{binding} is bound to both {} and {}",
+                    existing.key(),
+                    action.key()
+                )));
+            }
+            taken.push((id, action));
+        }
+        resolved.insert(action, binding.map(str::to_string));
+    }
+    Ok(resolved)
+}
+
+fn label(key: &str) -> String {
+    i18n::tr(&format!("menu-{key}"), &[])
+}
+
+fn build(
+    app: &AppHandle,
+    accelerators: &HashMap<MenuAction, Option<String>>,
+    state: &MenuState,
+) -> tauri::Result<(Menu<Wry>, MenuItems)> {
+    let items = MenuAction::ALL
+        .into_iter()
+        .map(|action| {
+            let item = MenuItem::with_id(
+                app,
+                format!("{ID_PREFIX}{}", action.key()),
+                label(action.key()),
+                action.enabled(state),
+                accelerators.get(&action).cloned().flatten(),
+            )?;
+            Ok((action, item))
+        })
+        .collect::<tauri::Result<MenuItems>>()?;
+    let item = |action: MenuAction| &items[&action];
+    let separator = || PredefinedMenuItem::separator(app);
+    let info = app.package_info();
+    let about = PredefinedMenuItem::about(
+        app,
+        Some(&label("about")),
+        Some(AboutMetadata {
+            name: Some(info.name.clone()),
+            version: Some(info.version.to_string()),
+            ..Default::default()
+        }),
+    )?;
+
+    #[cfg(target_os = "macos")]
+    let (app_menu, file) = (
+        Submenu::with_items(
+            app,
+            &info.name,
+            true,
+            &[
+                &about,
+                &separator()?,
+                item(MenuAction::Settings),
+                &separator()?,
+                &PredefinedMenuItem::services(app, Some(&label("services")))?,
+                &separator()?,
+                &PredefinedMenuItem::hide(app, Some(&label("hide")))?,
+                &PredefinedMenuItem::hide_others(app, Some(&label("hide-others")))?,
+                &PredefinedMenuItem::show_all(app, Some(&label("show-all")))?,
+                &separator()?,
+                &PredefinedMenuItem::quit(app, Some(&label("quit")))?,
+            ],
+        )?,
+        Submenu::with_items(
+            app,
+            label("file"),
+            true,
+            &[
+                item(MenuAction::OpenPlaylist),
+                &separator()?,
+                item(MenuAction::ExportCsv),
+                item(MenuAction::ExportPdf),
+                &separator()?,
+                item(MenuAction::Print),
+                &separator()?,
+                &PredefinedMenuItem::close_window(app, Some(&label("close-window")))?,
+            ],
+        )?,
+    );
+    // Quit is not a predefined item on Linux, so it is handled here everywhere
+    #[cfg(not(target_os = "macos"))]
+    let file = Submenu::with_items(
+        app,
+        label("file"),
+        true,
+        &[
+            item(MenuAction::OpenPlaylist),
+            &separator()?,
+            item(MenuAction::ExportCsv),
+            item(MenuAction::ExportPdf),
+            &separator()?,
+            item(MenuAction::Print),
+            &separator()?,
+            item(MenuAction::Settings),
+            &separator()?,
+            &MenuItem::with_id(app, QUIT_ID, label("quit"), true, None::<&str>)?,
+        ],
+    )?;
+
+    let edit = Submenu::with_items(
+        app,
+        label("edit"),
+        true,
+        &[
+            &PredefinedMenuItem::undo(app, Some(&label("undo")))?,
+            &PredefinedMenuItem::redo(app, Some(&label("redo")))?,
+            &separator()?,
+            &PredefinedMenuItem::cut(app, Some(&label("cut")))?,
+            &PredefinedMenuItem::copy(app, Some(&label("copy")))?,
+            &PredefinedMenuItem::paste(app, Some(&label("paste")))?,
+            &PredefinedMenuItem::select_all(app, Some(&label("select-all")))?,
+            &separator()?,
+            item(MenuAction::Find),
+        ],
+    )?;
+    let playlist = Submenu::with_items(
+        app,
+        label("playlist"),
+        true,
+        &[
+            item(MenuAction::SyncPlaylists),
+            &separator()?,
+            item(MenuAction::NextVersion),
+            item(MenuAction::PreviousVersion),
+        ],
+    )?;
+    let notes = Submenu::with_items(
+        app,
+        label("notes"),
+        true,
+        &[
+            item(MenuAction::SaveDraft),
+            item(MenuAction::CopyNotes),
+            &separator()?,
+            item(MenuAction::PublishSelected),
+            item(MenuAction::PublishAll),
+        ],
+    )?;
+    #[cfg(target_os = "macos")]
+    let help = Submenu::with_items(
+        app,
+        label("help"),
+        true,
+        &[
+            item(MenuAction::Documentation),
+            item(MenuAction::ReportIssue),
+            &separator()?,
+            item(MenuAction::CheckForUpdates),
+        ],
+    )?;
+    #[cfg(not(target_os = "macos"))]
+    let help = Submenu::with_items(
+        app,
+        label("help"),
+        true,
+        &[
+            item(MenuAction::Documentation),
+            item(MenuAction::ReportIssue),
+            &separator()?,
+            item(MenuAction::CheckForUpdates),
+            &separator()?,
+            &about,
+        ],
+    )?;
+
+    #[cfg(target_os = "macos")]
+    let menu = Menu::with_items(app, &[&app_menu, &file, &edit, &playlist, &notes, &help])?;
+    #[cfg(not(target_os = "macos"))]
+    let menu = Menu::with_items(app, &[&file, &edit, &playlist, &notes, &help])?;
+    Ok((menu, items))
+}
+
+fn install(app: &AppHandle, app_menu: &AppMenu) -> Result<()> {
+    let accelerators = match load_overrides(app).and_then(|overrides| resolve(&overrides)) {
+        Ok(accelerators) => accelerators,
+        Err(err) => {
+            log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}");
+            resolve(&Overrides::new())?
+        }
+    };
+    let state = *app_menu
+        .state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let (menu, items) = build(app, &accelerators, &state)?;
+    app.set_menu(menu)?;
+    *app_menu
+        .items
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = items;
+    Ok(())
+}
+
+/// Builds the menu with the saved accelerators and current labels and sets
+/// it for the app, replacing the one shown.
+pub fn rebuild(app: &AppHandle) {
+    let Some(app_menu) = app.try_state::<AppMenu>() else {
+        return;
+    };
+    if let Err(err) = install(app, &app_menu) {
+        log::warn!("Failed to build the application menu: {err}");
+    }
+}
+
+fn handle(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    #[cfg(not(target_os = "macos"))]
+    if id == QUIT_ID {
+        app.exit(0);
+        return;
+    }
+    let Some(action) = MenuAction::from_id(id) else {
+        return;
+    };
+    if let Err(err) = app.emit(ACTION_EVENT, action) {
+        log::warn!("Failed to emit {ACTION_EVENT}: {err}");
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(AppMenu::default());
+    app.on_menu_event(handle);
+    rebuild(app);
+}
+
+/// Enables the items that need what the frontend now has open.
+#[tauri::command]
+pub fn set_menu_state(menu: State<'_, AppMenu>, state: MenuState) -> Result<()> {
+    *menu
+        .state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = state;
+    let items = menu
+        .items
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    for (action, item) in items.iter() {
+        item.set_enabled(action.enabled(&state))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_menu_accelerators(app: AppHandle) -> Result<Vec<MenuAccelerator>> {
+    let accelerators = resolve(&load_overrides(&app)?)?;
+    Ok(MenuAction::ALL
+        .into_iter()
+        .map(|action| MenuAccelerator {
+            action,
+            accelerator: accelerators.get(&action).cloned().flatten(),
+            default_accelerator: action.default_accelerator().map(str::to_string),
+        })
+        .collect())
+}
+
+/// Replaces the accelerator overrides, so an empty map restores the
+/// defaults, and applies them to the menu once they all validate.
+#[tauri::command]
+pub fn set_menu_accelerators(
+    app: AppHandle,
+    menu: State<'_, AppMenu>,
+    accelerators: Overrides,
+) -> Result<Vec<MenuAccelerator>> {
+    let resolved = resolve(&accelerators)?;
+    settings::check_locked(SETTINGS_FILE, &accelerators)?;
+    {
+        let items = menu
+            .items
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (action, item) in items.iter() {
+            item.set_accelerator(resolved.get(action).cloned().flatten())?;
+        }
+    }
+    paths::save_json(&app, SETTINGS_FILE, &accelerators)?;
+    get_menu_accelerators(app)
+}
@@ -0,0 +1,199 @@
+//! Zip archives of note attachments.
+//!
+//! Files are streamed into the archive entry by entry, so bundling a review
+//! session never holds more than one buffer in memory.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::error::{Error, Result};
+use crate::export::write_atomically;
+
+pub const PROGRESS_EVENT: &str = "archive:progress";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const ZIP64_THRESHOLD: u64 = u32::MAX as u64;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveCompression {
+    Stored,
+    #[default]
+    Deflate,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ArchiveOptions {
+    pub compression: ArchiveCompression,
+    /// Compression level; the method's default when unset.
+    pub level: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveProgress {
+    pub out_zip: PathBuf,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveResult {
+    pub path: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Entry names are flat; repeated file names get a ` (2)` style suffix.
+fn entry_name(path: &Path, used: &mut HashSet<String>) -> String {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "attachment".into());
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{ext}")),
+        _ => (file_name.clone(), String::new()),
+    };
+    let mut name = file_name;
+    let mut n = 2;
+    while !used.insert(name.to_lowercase()) {
+        name = format!("{stem} ({n}){ext}");
+        n += 1;
+    }
+    name
+}
+
+struct Progress<'a> {
+    app: &'a AppHandle,
+    state: ArchiveProgress,
+    last_emit: Instant,
+}
+
+impl Progress<'_> {
+    fn emit(&mut self, force: bool) {
+        if !force && self.last_emit.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        self.last_emit = Instant::now();
+        if let Err(err) = self.app.emit(PROGRESS_EVENT, &self.state) {
+            log::warn!("Failed to emit archive progress: {err}");
+        }
+    }
+}
+
+/// Reader that reports bytes as they are pulled into the archive.
+struct CountingReader<'a, 'b, R> {
+    inner: R,
+    progress: &'a mut Progress<'b>,
+}
+
+impl<R: Read> Read for CountingReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.state.bytes_done += read as u64;
+        self.progress.emit(false);
+        Ok(read)
+    }
+}
+
+fn write_archive(
+    app: &AppHandle,
+    paths: &[PathBuf],
+    temp: &Path,
+    out_zip: &Path,
+    options: &ArchiveOptions,
+) -> Result<u64> {
+    let mut sizes = Vec::with_capacity(paths.len());
+    for path in paths {
+        let meta = fs::metadata(path)
+            .map_err(|err| Error::InvalidInput(format!("{}: {err}", path.display())))?;
+        if !meta.is_file() {
+            return Err(Error::InvalidInput(format!(
+                "{} is not a file",
+                path.display()
+            )));
+        }
+        sizes.push(meta.len());
+    }
+
+    let method = match options.compression {
+        ArchiveCompression::Stored => CompressionMethod::Stored,
+        ArchiveCompression::Deflate => CompressionMethod::Deflated,
+        ArchiveCompression::Zstd => CompressionMethod::Zstd,
+    };
+    let mut progress = Progress {
+        app,
+        state: ArchiveProgress {
+            out_zip: out_zip.to_path_buf(),
+            files_done: 0,
+            files_total: paths.len(),
+            bytes_done: 0,
+            bytes_total: sizes.iter().sum(),
+        },
+        last_emit: Instant::now(),
+    };
+    progress.emit(true);
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(temp)?));
+    let mut used = HashSet::new();
+    for (path, size) in paths.iter().zip(sizes) {
+        let entry_options = SimpleFileOptions::default()
+            .compression_method(method)
+            .compression_level(options.level)
+            .large_file(size >= ZIP64_THRESHOLD);
+        zip.start_file(entry_name(path, &mut used), entry_options)?;
+        let mut reader = CountingReader {
+            inner: File::open(path)?,
+            progress: &mut progress,
+        };
+        io::copy(&mut reader, &mut zip)?;
+        progress.state.files_done += 1;
+        progress.emit(true);
+    }
+    zip.finish()?;
+    Ok(progress.state.bytes_done)
+}
+
+/// Streams `paths` into a zip at `out_zip`, emitting `archive:progress` as it goes.
+#[tauri::command]
+pub async fn create_attachment_archive(
+    app: AppHandle,
+    paths: Vec<PathBuf>,
+    out_zip: PathBuf,
+    options: Option<ArchiveOptions>,
+) -> Result<ArchiveResult> {
+    if paths.is_empty() {
+        return Err(Error::InvalidInput("No attachments to archive".into()));
+    }
+    let options = options.unwrap_or_default();
+    let target = out_zip.clone();
+    let files = paths.len();
+    let bytes = tauri::async_runtime::spawn_blocking(move || {
+        let mut bytes = 0;
+        write_atomically(&target, |temp| {
+            bytes = write_archive(&app, &paths, temp, &target, &options)?;
+            Ok(())
+        })?;
+        Ok::<_, Error>(bytes)
+    })
+    .await??;
+    log::info!("Archived {files} attachments to {}", out_zip.display());
+    Ok(ArchiveResult {
+        path: out_zip,
+        files,
+        bytes,
+    })
+}
@@ -0,0 +1,446 @@
+//! OAuth 2 / SSO sign-in through the system browser.
+//!
+//! Profiles at SSO-mandated studios carry an identity provider configuration
+//! in `sso.json`. [`begin_sso_login`] opens the provider's authorize page with
+//! PKCE and a redirect to a one-shot listener on a random loopback port, then
+//! exchanges the returned code for tokens. Tokens live in the keychain; the
+//! access token is also stored as the profile's credential, so every request
+//! path authenticates with it unchanged. A background task refreshes tokens
+//! shortly before they expire.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::Query;
+use axum::response::Html;
+use axum::routing::get;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::network;
+use crate::paths;
+use crate::settings;
+use crate::store::profiles::Profile;
+use crate::store::{Store, now_millis};
+
+const SETTINGS_FILE: &str = "sso.json";
+pub const COMPLETED_EVENT: &str = "sso:completed";
+pub const EXPIRED_EVENT: &str = "sso:expired";
+
+const CALLBACK_PATH: &str = "/callback";
+/// How long the callback listener waits for the browser.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+/// Tokens are refreshed this long before they expire.
+const REFRESH_MARGIN_MS: i64 = 5 * 60 * 1000;
+
+const CALLBACK_PAGE: &str = "<!doctype html><html><body style=\"font-family: sans-serif\">\
+    <h3>You can close this tab and return to AstraNotes.</h3></body></html>";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SsoConfig {
+    pub authorize_url: String,
+    pub token_url: String,
+    pub client_id: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// Identity provider configuration by profile ID.
+type SsoSettings = HashMap<String, SsoConfig>;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Tokens {
+    access_token: String,
+    refresh_token: Option<String>,
+    /// Milliseconds since the epoch; `None` if the provider gave no lifetime.
+    expires_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SsoStatus {
+    pub configured: bool,
+    pub signed_in: bool,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Default)]
+pub struct Sso {
+    /// The login waiting on its callback; a new login replaces it.
+    login: Mutex<Option<JoinHandle<()>>>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<SsoSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn save_settings(app: &AppHandle, settings: &SsoSettings) -> Result<()> {
+    paths::save_json(app, SETTINGS_FILE, settings)
+}
+
+/// Keychain entry holding a profile's SSO tokens.
+pub fn token_key(profile_id: &str) -> String {
+    format!("sso-tokens:{profile_id}")
+}
+
+fn load_tokens(profile_id: &str) -> Result<Option<Tokens>> {
+    match credentials::get(&token_key(profile_id))? {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+fn save_tokens(profile: &Profile, tokens: &Tokens) -> Result<()> {
+    credentials::store(&token_key(&profile.id), &serde_json::to_string(tokens)?)?;
+    credentials::store(&profile.credential_key(), &tokens.access_token)
+}
+
+/// Parses an identity provider URL. Codes and tokens must not cross the
+/// network in the clear, so plain http is only allowed to this machine.
+fn parse_url(url: &str) -> Result<Url> {
+    let parsed =
+        Url::parse(url).map_err(|err| Error::InvalidInput(format!("Invalid URL {url}: {err}")))?;
+    let host = parsed
+        .host_str()
+        .unwrap_or_default()
+        .trim_matches(['[', ']']);
+    let loopback = host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<IpAddr>()
+            .is_ok_and(|address| address.is_loopback());
+    match parsed.scheme() {
+        "https" => Ok(parsed),
+        "http" if loopback => Ok(parsed),
+        "http" => Err(Error::InvalidInput(format!(
+            "{url} must use https unless it is on this machine"
+        ))),
+        _ => Err(Error::InvalidInput(format!("{url} is not an https URL"))),
+    }
+}
+
+/// A PKCE code verifier: 64 characters from the unreserved set.
+fn code_verifier() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+async fn request_tokens(config: &SsoConfig, form: &[(&str, &str)]) -> Result<Tokens> {
+    let response = network::client()
+        .post(&config.token_url)
+        .form(form)
+        .send()
+        .await?;
+    let status = response.status();
+    // Server trouble is retried later; only a rejection ends the session
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        if let Err(err) = response.error_for_status_ref() {
+            return Err(err.into());
+        }
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::InvalidInput(format!(
+            "Token request rejected ({status}): {}",
+            body.trim()
+        )));
+    }
+    let tokens: TokenResponse = response.json().await?;
+    Ok(Tokens {
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        expires_at: tokens
+            .expires_in
+            .map(|seconds| now_millis() + seconds * 1000),
+    })
+}
+
+/// Serves the redirect until the browser arrives or the login times out.
+async fn wait_for_callback(listener: TcpListener) -> Result<HashMap<String, String>> {
+    let (sender, mut receiver) = mpsc::channel(1);
+    let router = Router::new().route(
+        CALLBACK_PATH,
+        get(
+            move |Query(params): Query<HashMap<String, String>>| async move {
+                let _ = sender.try_send(params);
+                Html(CALLBACK_PAGE)
+            },
+        ),
+    );
+    tokio::select! {
+        params = receiver.recv() => {
+            params.ok_or_else(|| Error::InvalidInput("SSO callback listener closed".into()))
+        }
+        result = axum::serve(listener, router) => {
+            result?;
+            Err(Error::InvalidInput("SSO callback listener stopped".into()))
+        }
+        _ = tokio::time::sleep(LOGIN_TIMEOUT) => {
+            Err(Error::InvalidInput("Timed out waiting for the browser sign-in".into()))
+        }
+    }
+}
+
+struct Login {
+    profile: Profile,
+    config: SsoConfig,
+    redirect_uri: String,
+    verifier: String,
+    state: String,
+}
+
+async fn complete(listener: TcpListener, login: Login) -> Result<()> {
+    let params = wait_for_callback(listener).await?;
+    if let Some(error) = params.get("error") {
+        let description = params.get("error_description").map_or("", String::as_str);
+        return Err(Error::InvalidInput(format!(
+            "Sign-in failed: {error} {description}"
+        )));
+    }
+    if params.get("state") != Some(&login.state) {
+        return Err(Error::InvalidInput(
+            "Sign-in response did not match this login".into(),
+        ));
+    }
+    let code = params
+        .get("code")
+        .ok_or_else(|| Error::InvalidInput("Sign-in response had no code".into()))?;
+    let tokens = request_tokens(
+        &login.config,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &login.redirect_uri),
+            ("client_id", &login.config.client_id),
+            ("code_verifier", &login.verifier),
+        ],
+    )
+    .await?;
+    save_tokens(&login.profile, &tokens)?;
+    log::info!("Signed in to {} via SSO", login.profile.server_url);
+    Ok(())
+}
+
+/// Exchanges the refresh token for new tokens.
+async fn refresh(profile: &Profile, config: &SsoConfig, tokens: Tokens) -> Result<()> {
+    let refresh_token = tokens
+        .refresh_token
+        .ok_or_else(|| Error::InvalidInput("The SSO session cannot be refreshed".into()))?;
+    let mut renewed = request_tokens(
+        config,
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &refresh_token),
+            ("client_id", &config.client_id),
+        ],
+    )
+    .await?;
+    // Providers that do not rotate refresh tokens omit them from the response
+    renewed.refresh_token.get_or_insert(refresh_token);
+    save_tokens(profile, &renewed)
+}
+
+async fn refresh_expiring(app: &AppHandle) -> Result<()> {
+    let store = app.state::<Store>();
+    for (profile_id, config) in load_settings(app)? {
+        let Some(tokens) = load_tokens(&profile_id)? else {
+            continue;
+        };
+        match tokens.expires_at {
+            Some(expires_at) if expires_at - now_millis() < REFRESH_MARGIN_MS => {}
+            _ => continue,
+        }
+        let Some(profile) = store.get_profile(&profile_id)? else {
+            continue;
+        };
+        match refresh(&profile, &config, tokens).await {
+            Ok(()) => log::info!("Refreshed SSO session for {}", profile.server_url),
+            // Unreachable or failing for now; try again on the next tick
+            Err(Error::Http(err)) => log::warn!("Failed to refresh SSO session: {err}"),
+            Err(err) => {
+                log::warn!("SSO session for {} expired: {err}", profile.server_url);
+                credentials::delete(&token_key(&profile_id))?;
+                let payload = json!({ "profileId": profile_id, "error": err.to_string() });
+                if let Err(err) = app.emit(EXPIRED_EVENT, payload) {
+                    log::warn!("Failed to emit SSO expiry: {err}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Starts the refresh task.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+            if let Err(err) = refresh_expiring(&app).await {
+                log::error!("SSO refresh pass failed: {err}");
+            }
+        }
+    });
+}
+
+fn profile(store: &Store, profile_id: &str) -> Result<Profile> {
+    store
+        .get_profile(profile_id)?
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown profile {profile_id}")))
+}
+
+#[tauri::command]
+pub fn get_sso_config(app: AppHandle, profile_id: String) -> Result<Option<SsoConfig>> {
+    Ok(load_settings(&app)?.remove(&profile_id))
+}
+
+/// Sets or, with `None`, removes a profile's identity provider.
+#[tauri::command]
+pub fn set_sso_config(
+    app: AppHandle,
+    store: State<'_, Store>,
+    profile_id: String,
+    config: Option<SsoConfig>,
+) -> Result<()> {
+    profile(&store, &profile_id)?;
+    let mut settings = load_settings(&app)?;
+    match config {
+        Some(config) => {
+            parse_url(&config.authorize_url)?;
+            parse_url(&config.token_url)?;
+            if config.client_id.trim().is_empty() {
+                return Err(Error::InvalidInput("A client ID is required".into()));
+            }
+            settings.insert(profile_id, config);
+        }
+        None => {
+            settings.remove(&profile_id);
+            settings::check_locked(SETTINGS_FILE, &settings)?;
+            credentials::delete(&token_key(&profile_id))?;
+        }
+    }
+    save_settings(&app, &settings)
+}
+
+/// Opens the browser on the identity provider and returns the URL, so it can
+/// be copied if no browser opened. The result arrives as `sso:completed`.
+#[tauri::command]
+pub async fn begin_sso_login(
+    app: AppHandle,
+    store: State<'_, Store>,
+    sso: State<'_, Sso>,
+    profile_id: String,
+) -> Result<String> {
+    let profile = profile(&store, &profile_id)?;
+    let config = load_settings(&app)?.remove(&profile_id).ok_or_else(|| {
+        Error::InvalidInput(format!("Profile {} has no SSO configuration", profile.name))
+    })?;
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let redirect_uri = format!(
+        "http://127.0.0.1:{}{CALLBACK_PATH}",
+        listener.local_addr()?.port()
+    );
+    let verifier = code_verifier();
+    let state = uuid::Uuid::new_v4().to_string();
+    let mut url = parse_url(&config.authorize_url)?;
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("code_challenge", &code_challenge(&verifier))
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("state", &state);
+        if !config.scopes.is_empty() {
+            query.append_pair("scope", &config.scopes.join(" "));
+        }
+    }
+
+    let login = Login {
+        profile,
+        config,
+        redirect_uri,
+        verifier,
+        state,
+    };
+    let task_app = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let result = complete(listener, login).await;
+        if let Err(err) = &result {
+            log::warn!("SSO login for profile {profile_id} failed: {err}");
+        }
+        let payload = json!({
+            "profileId": profile_id,
+            "error": result.err().map(|err| err.to_string()),
+        });
+        if let Err(err) = task_app.emit(COMPLETED_EVENT, payload) {
+            log::warn!("Failed to emit SSO completion: {err}");
+        }
+    });
+    let previous = sso
+        .login
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .replace(task);
+    if let Some(previous) = previous {
+        previous.abort();
+    }
+
+    open::that_detached(url.as_str())?;
+    Ok(url.to_string())
+}
+
+#[tauri::command]
+pub fn get_sso_status(app: AppHandle, profile_id: String) -> Result<SsoStatus> {
+    let configured = load_settings(&app)?.contains_key(&profile_id);
+    let tokens = load_tokens(&profile_id)?;
+    Ok(SsoStatus {
+        configured,
+        signed_in: tokens.is_some(),
+        expires_at: tokens.and_then(|tokens| tokens.expires_at),
+    })
+}
+
+/// Forgets the SSO session. Returns `false` if there was none.
+#[tauri::command]
+pub fn sso_logout(store: State<'_, Store>, profile_id: String) -> Result<bool> {
+    let profile = profile(&store, &profile_id)?;
+    if !credentials::delete(&token_key(&profile_id))? {
+        return Ok(false);
+    }
+    credentials::delete(&profile.credential_key())?;
+    Ok(true)
+}
@@ -0,0 +1,391 @@
+//! User-provided Rhai scripts that run on backend events.
+//!
+//! Every `.rhai` file in the `automation` folder of the app config directory
+//! is compiled at startup. A script handles an event by defining a function
+//! of the same name taking one map argument:
+//!
+//! ```rhai
+//! fn before_publish(note) {
+//!     if note.context.playlistName.contains("CLIENT") {
+//!         add_label(note.context.labels.client);
+//!     }
+//! }
+//! ```
+//!
+//! Scripts act only through `add_label`, `remove_label`, `add_tag`,
+//! `set_content`, `reject` and `print`. Rhai has no file or network access,
+//! and each run is capped in operations and memory. A script that fails is
+//! logged and skipped rather than blocking the event.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+
+use rhai::{AST, Dynamic, Engine, Scope};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::store::publish_jobs::NewPublishJob;
+
+pub const OUTCOME_EVENT: &str = "automation:outcome";
+const SCRIPTS_DIR: &str = "automation";
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_COLLECTION_SIZE: usize = 10_000;
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationEvent {
+    BeforePublish,
+    PlaylistLoaded,
+}
+
+impl AutomationEvent {
+    fn function(self) -> &'static str {
+        match self {
+            Self::BeforePublish => "before_publish",
+            Self::PlaylistLoaded => "playlist_loaded",
+        }
+    }
+}
+
+/// Changes requested by the scripts that handled one event.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    pub event: Option<AutomationEvent>,
+    pub rejected: Option<String>,
+    pub labels_added: Vec<String>,
+    pub labels_removed: Vec<String>,
+    pub tags: Vec<String>,
+    pub content: Option<String>,
+    /// `print` output and script errors, prefixed with the script name.
+    pub log: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub events: Vec<AutomationEvent>,
+    pub error: Option<String>,
+}
+
+struct Script {
+    info: ScriptInfo,
+    ast: Option<AST>,
+}
+
+#[derive(Default)]
+pub struct Automation {
+    scripts: RwLock<Vec<Script>>,
+}
+
+fn scripts_dir(app: &AppHandle) -> Result<PathBuf> {
+    Ok(paths::config_dir(app)?.join(SCRIPTS_DIR))
+}
+
+/// An engine whose only side effects go into `outcome`.
+fn engine(name: &str, outcome: &Arc<Mutex<Outcome>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.disable_symbol("eval");
+
+    let with = |outcome: &Arc<Mutex<Outcome>>| {
+        let outcome = outcome.clone();
+        move |change: &dyn Fn(&mut Outcome)| {
+            change(
+                &mut outcome
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            );
+        }
+    };
+    let apply = with(outcome);
+    engine.register_fn("add_label", move |id: &str| {
+        apply(&|outcome| {
+            outcome.labels_removed.retain(|label| label != id);
+            if !outcome.labels_added.iter().any(|label| label == id) {
+                outcome.labels_added.push(id.to_string());
+            }
+        })
+    });
+    let apply = with(outcome);
+    engine.register_fn("remove_label", move |id: &str| {
+        apply(&|outcome| {
+            outcome.labels_added.retain(|label| label != id);
+            if !outcome.labels_removed.iter().any(|label| label == id) {
+                outcome.labels_removed.push(id.to_string());
+            }
+        })
+    });
+    let apply = with(outcome);
+    engine.register_fn("add_tag", move |tag: &str| {
+        apply(&|outcome| {
+            if !outcome.tags.iter().any(|existing| existing == tag) {
+                outcome.tags.push(tag.to_string());
+            }
+        })
+    });
+    let apply = with(outcome);
+    engine.register_fn("set_content", move |content: &str| {
+        apply(&|outcome| outcome.content = Some(content.to_string()))
+    });
+    let apply = with(outcome);
+    let script = name.to_string();
+    engine.register_fn("reject", move |reason: &str| {
+        apply(&|outcome| {
+            outcome
+                .rejected
+                .get_or_insert_with(|| format!("{script}: {reason}"));
+        })
+    });
+    let apply = with(outcome);
+    let script = name.to_string();
+    engine.on_print(move |text| {
+        log::info!("automation {script}: {text}");
+        apply(&|outcome| outcome.log.push(format!("{script}: {text}")));
+    });
+    engine
+}
+
+fn compile(path: PathBuf) -> Script {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let compiled = fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|source| {
+            engine(&name, &Arc::default())
+                .compile(source)
+                .map_err(|err| err.to_string())
+        });
+    let (ast, error) = match compiled {
+        Ok(ast) => (Some(ast), None),
+        Err(err) => {
+            log::warn!(
+                "Automation script {} failed to compile: {err}",
+                path.display()
+            );
+            (None, Some(err))
+        }
+    };
+    let events = [
+        AutomationEvent::BeforePublish,
+        AutomationEvent::PlaylistLoaded,
+    ]
+    .into_iter()
+    .filter(|event| {
+        ast.as_ref().is_some_and(|ast| {
+            ast.iter_functions()
+                .any(|function| function.name == event.function() && function.params.len() == 1)
+        })
+    })
+    .collect();
+    Script {
+        info: ScriptInfo {
+            name,
+            path,
+            events,
+            error,
+        },
+        ast,
+    }
+}
+
+impl Automation {
+    fn load(&self, app: &AppHandle) -> Result<()> {
+        let dir = scripts_dir(app)?;
+        let mut paths = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+                .collect(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        // Scripts run in file name order, so `10-labels.rhai` style prefixes work
+        paths.sort();
+        let scripts: Vec<_> = paths.into_iter().map(compile).collect();
+        log::info!("Loaded {} automation scripts", scripts.len());
+        *self
+            .scripts
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = scripts;
+        Ok(())
+    }
+
+    /// Runs every script handling `event`, in order. Each script sees the
+    /// content as left by the previous one.
+    pub fn run(&self, event: AutomationEvent, mut payload: Value) -> Outcome {
+        let scripts = self
+            .scripts
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let outcome = Arc::new(Mutex::new(Outcome {
+            event: Some(event),
+            ..Outcome::default()
+        }));
+        for script in scripts
+            .iter()
+            .filter(|script| script.info.events.contains(&event))
+        {
+            let Some(ast) = &script.ast else {
+                continue;
+            };
+            let name = &script.info.name;
+            let argument = match rhai::serde::to_dynamic(&payload) {
+                Ok(argument) => argument,
+                Err(err) => {
+                    log::error!("Automation payload for {name} is not representable: {err}");
+                    continue;
+                }
+            };
+            let result = engine(name, &outcome).call_fn::<Dynamic>(
+                &mut Scope::new(),
+                ast,
+                event.function(),
+                (argument,),
+            );
+            let mut outcome = outcome
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Err(err) = result {
+                log::error!("Automation script {name} failed: {err}");
+                outcome.log.push(format!("{name}: error: {err}"));
+            }
+            if let (Some(content), Some(map)) = (&outcome.content, payload.as_object_mut()) {
+                map.insert("content".into(), Value::String(content.clone()));
+            }
+        }
+        outcome
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+fn emit_outcome(app: &AppHandle, outcome: &Outcome) {
+    if let Err(err) = app.emit(OUTCOME_EVENT, outcome) {
+        log::warn!("Failed to emit automation outcome: {err}");
+    }
+}
+
+fn is_create(operation: &Value, entity_type: &str) -> bool {
+    operation["action"] == "create" && operation["entity_type"] == entity_type
+}
+
+/// Runs `before_publish` for a job and applies content and label changes to
+/// its operation batch. Fails if a script rejected the publish.
+pub fn before_publish(app: &AppHandle, job: &mut NewPublishJob) -> Result<()> {
+    let Some(operations) = job.operations.as_array_mut() else {
+        return Ok(());
+    };
+    let note = operations.iter().position(|op| is_create(op, "Note"));
+    let note_data = note.map(|index| operations[index]["entity_data"].clone());
+    let labels: Vec<Value> = operations
+        .iter()
+        .filter(|op| is_create(op, "NoteLabelLink"))
+        .map(|op| op["entity_data"]["label_id"].clone())
+        .collect();
+    let payload = json!({
+        "playlist_id": job.playlist_id,
+        "version_id": job.version_id,
+        "content": note_data.as_ref().map(|data| data["content"].clone()),
+        "labels": labels,
+        "context": job.context,
+    });
+
+    let outcome = app
+        .state::<Automation>()
+        .run(AutomationEvent::BeforePublish, payload);
+    emit_outcome(app, &outcome);
+    if let Some(reason) = outcome.rejected {
+        return Err(Error::InvalidInput(format!("Publish rejected by {reason}")));
+    }
+
+    if let (Some(index), Some(content)) = (note, outcome.content) {
+        operations[index]["entity_data"]["content"] = Value::String(content);
+    }
+    operations.retain(|op| {
+        !(is_create(op, "NoteLabelLink")
+            && outcome
+                .labels_removed
+                .iter()
+                .any(|label| op["entity_data"]["label_id"] == label.as_str()))
+    });
+    let note_id = note_data.as_ref().map(|data| data["id"].clone());
+    for label in outcome.labels_added {
+        let linked = operations.iter().any(|op| {
+            is_create(op, "NoteLabelLink") && op["entity_data"]["label_id"] == label.as_str()
+        });
+        if linked {
+            continue;
+        }
+        match &note_id {
+            Some(note_id) if !note_id.is_null() => operations.push(json!({
+                "action": "create",
+                "entity_type": "NoteLabelLink",
+                "entity_data": { "note_id": note_id, "label_id": label },
+            })),
+            _ => log::warn!("Cannot add label {label}: the publish batch has no note id"),
+        }
+    }
+    Ok(())
+}
+
+pub fn init(app: &AppHandle) {
+    let automation = Automation::default();
+    if let Err(err) = automation.load(app) {
+        log::warn!("Failed to load automation scripts: {err}");
+    }
+    app.manage(automation);
+}
+
+#[tauri::command]
+pub fn list_automation_scripts(automation: State<'_, Automation>) -> Vec<ScriptInfo> {
+    automation
+        .scripts
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .map(|script| script.info.clone())
+        .collect()
+}
+
+#[tauri::command]
+pub fn reload_automation_scripts(
+    app: AppHandle,
+    automation: State<'_, Automation>,
+) -> Result<Vec<ScriptInfo>> {
+    automation.load(&app)?;
+    Ok(list_automation_scripts(automation))
+}
+
+/// Runs an event raised by the frontend, such as `playlist_loaded`, and
+/// returns the requested changes for it to apply.
+#[tauri::command]
+pub async fn run_automation_event(
+    app: AppHandle,
+    event: AutomationEvent,
+    payload: Value,
+) -> Result<Outcome> {
+    let handle = app.clone();
+    let outcome = tauri::async_runtime::spawn_blocking(move || {
+        handle.state::<Automation>().run(event, payload)
+    })
+    .await?;
+    emit_outcome(&app, &outcome);
+    Ok(outcome)
+}
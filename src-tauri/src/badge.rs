@@ -0,0 +1,130 @@
+//! Unpublished draft count on the macOS dock icon and Windows taskbar button.
+//!
+//! macOS, and Linux docks that follow the Unity launcher API, show the count
+//! as a badge. Windows has no badge, so a small numbered overlay icon is
+//! drawn for the taskbar button instead. The count is read from the native
+//! draft store whenever drafts change, the same as the tray's, and nothing
+//! is shown once every draft is published.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::store::Store;
+
+pub struct DraftBadge {
+    enabled: AtomicBool,
+    /// Count last shown, so unchanged counts do not touch the OS.
+    applied: Mutex<Option<u32>>,
+}
+
+impl Default for DraftBadge {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+            applied: Mutex::default(),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+const OVERLAY_SIZE: u32 = 32;
+
+/// A red disc with the count in white, the way Windows apps draw overlays.
+#[cfg(target_os = "windows")]
+fn overlay(count: u32) -> image::RgbaImage {
+    use ab_glyph::{Font, PxScale, ScaleFont};
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    use crate::reports::text::TextRenderer;
+
+    let label = if count > 99 {
+        "99+".to_string()
+    } else {
+        count.to_string()
+    };
+    let size = if label.len() > 2 { 14.0 } else { 20.0 };
+    let mut canvas = RgbImage::from_pixel(OVERLAY_SIZE, OVERLAY_SIZE, Rgb([214, 48, 49]));
+    let renderer = TextRenderer::new();
+    let ascent = renderer.font().as_scaled(PxScale::from(size)).ascent();
+    let width = renderer.width(&label, size);
+    let center = OVERLAY_SIZE as f32 / 2.0;
+    // Digits stand about 0.7 em tall; centre them rather than the whole em box
+    renderer.draw(
+        &mut canvas,
+        &label,
+        size,
+        (center - width / 2.0).round() as i32,
+        (center + size * 0.36 - ascent).round() as i32,
+        Rgb([255, 255, 255]),
+    );
+
+    let radius = center - 0.5;
+    RgbaImage::from_fn(OVERLAY_SIZE, OVERLAY_SIZE, |x, y| {
+        let distance = (x as f32 + 0.5 - center).hypot(y as f32 + 0.5 - center);
+        let coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+        let Rgb([r, g, b]) = *canvas.get_pixel(x, y);
+        Rgba([r, g, b, (coverage * 255.0).round() as u8])
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn show(window: &WebviewWindow, count: u32) -> tauri::Result<()> {
+    let icon = (count > 0).then(|| {
+        tauri::image::Image::new_owned(overlay(count).into_raw(), OVERLAY_SIZE, OVERLAY_SIZE)
+    });
+    window.set_overlay_icon(icon)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn show(window: &WebviewWindow, count: u32) -> tauri::Result<()> {
+    window.set_badge_count((count > 0).then_some(i64::from(count)))
+}
+
+/// Re-reads the unpublished draft count and updates the badge.
+pub fn refresh(app: &AppHandle) {
+    let (Some(badge), Some(store)) = (app.try_state::<DraftBadge>(), app.try_state::<Store>())
+    else {
+        return;
+    };
+    let count = if badge.enabled.load(Ordering::SeqCst) {
+        match store.count_unpublished_drafts() {
+            Ok(count) => count,
+            Err(err) => {
+                log::warn!("Failed to count unpublished drafts: {err}");
+                return;
+            }
+        }
+    } else {
+        0
+    };
+
+    let mut applied = badge
+        .applied
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *applied == Some(count) {
+        return;
+    }
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    match show(&window, count) {
+        Ok(()) => *applied = Some(count),
+        Err(err) => log::warn!("Failed to update draft badge: {err}"),
+    }
+}
+
+/// Shows or hides the badge; it is shown by default.
+#[tauri::command]
+pub fn set_draft_badge_enabled(app: AppHandle, badge: State<'_, DraftBadge>, enabled: bool) {
+    badge.enabled.store(enabled, Ordering::SeqCst);
+    refresh(&app);
+}
+
+/// Updates the badge after drafts changed outside the draft commands.
+#[tauri::command]
+pub fn refresh_draft_badge(app: AppHandle) {
+    refresh(&app);
+}
@@ -0,0 +1,841 @@
+//! Bulk import of notes and statuses from CSV or XLSX spreadsheets.
+//!
+//! Importing has two phases. `validate_bulk_import` streams the file, finds
+//! the version name, version number, note and status columns from the
+//! header row, and checks every row against the playlist's versions and the
+//! statuses ftrack knows. The rows and their verdicts are saved under
+//! `bulk-imports/` in the data directory. `apply_bulk_import` then writes
+//! the valid rows to ftrack one at a time in the background, appending each
+//! outcome to a journal next to the saved rows. An import that was
+//! cancelled, lost its connection or was cut short by quitting carries on
+//! from the first row without an outcome, and rows that failed are retried.
+//! Progress is reported as `bulk-import:progress` events, and invalid or
+//! failed rows can be saved as a CSV error report.
+
+mod sheet;
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::export::{self, write_atomically};
+use crate::ftrack::{self, Connection, query, quoted};
+use crate::network;
+use crate::paths;
+use crate::profiles;
+use crate::store::audit::{self, AuditAction, NewAuditEntry};
+use crate::store::{Store, now_millis};
+use crate::taskbar;
+
+pub const PROGRESS_EVENT: &str = "bulk-import:progress";
+const IMPORT_DIR: &str = "bulk-imports";
+/// Rows read before giving up on a file, to bound memory and the saved rows.
+const MAX_ROWS: usize = 200_000;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+const NAME_HEADERS: &[&str] = &["version name", "version", "name"];
+const NUMBER_HEADERS: &[&str] = &["version number", "number", "v"];
+const NOTE_HEADERS: &[&str] = &["notes", "note", "comment", "comments", "feedback"];
+const STATUS_HEADERS: &[&str] = &["status", "version status"];
+
+const REPORT_HEADERS: [&str; 6] = [
+    "Line",
+    "Version Name",
+    "Version Number",
+    "Status",
+    "Result",
+    "Error",
+];
+
+/// Header names to read columns from, for sheets whose headers are not
+/// recognised. Unset columns are found from their usual names.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ColumnMapping {
+    pub version_name: Option<String>,
+    pub version_number: Option<String>,
+    pub note: Option<String>,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RowState {
+    /// Valid and not written yet.
+    Ready,
+    Invalid,
+    Applied,
+    /// Rejected by ftrack; retried when the import is applied again.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRow {
+    /// Line of a CSV file or row of a sheet, for the error report.
+    pub line: u64,
+    pub version_name: String,
+    pub version_number: Option<u32>,
+    pub note: Option<String>,
+    pub status: Option<String>,
+    pub version_id: Option<String>,
+    pub status_id: Option<String>,
+    pub state: RowState,
+    pub error: Option<String>,
+}
+
+impl ImportRow {
+    /// Marks the row invalid, keeping the first reason found.
+    fn reject(&mut self, error: String) {
+        if self.state != RowState::Invalid {
+            self.state = RowState::Invalid;
+            self.error = Some(error);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    id: String,
+    source: PathBuf,
+    playlist_id: String,
+    connection: Connection,
+    created_at: i64,
+    rows: Vec<ImportRow>,
+}
+
+/// One journal line: the outcome of writing a row.
+#[derive(Debug, Serialize, Deserialize)]
+struct Outcome {
+    row: usize,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportStatus {
+    /// Validated and not applied yet.
+    Validated,
+    Applying,
+    /// Stopped with rows still ready.
+    Paused,
+    /// Every valid row was written, or failed.
+    Completed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkImportSnapshot {
+    pub id: String,
+    pub source: PathBuf,
+    pub playlist_id: String,
+    pub created_at: i64,
+    pub status: ImportStatus,
+    pub total: usize,
+    pub ready: usize,
+    pub invalid: usize,
+    pub applied: usize,
+    pub failed: usize,
+    /// Why the last run stopped early, on the event that ends it.
+    pub error: Option<String>,
+}
+
+impl Manifest {
+    fn snapshot(&self, running: bool) -> BulkImportSnapshot {
+        let count = |state| self.rows.iter().filter(|row| row.state == state).count();
+        let (ready, applied, failed) = (
+            count(RowState::Ready),
+            count(RowState::Applied),
+            count(RowState::Failed),
+        );
+        let status = if running {
+            ImportStatus::Applying
+        } else if ready == 0 {
+            ImportStatus::Completed
+        } else if applied == 0 && failed == 0 {
+            ImportStatus::Validated
+        } else {
+            ImportStatus::Paused
+        };
+        BulkImportSnapshot {
+            id: self.id.clone(),
+            source: self.source.clone(),
+            playlist_id: self.playlist_id.clone(),
+            created_at: self.created_at,
+            status,
+            total: self.rows.len(),
+            ready,
+            invalid: count(RowState::Invalid),
+            applied,
+            failed,
+            error: None,
+        }
+    }
+}
+
+/// Cancel flags of the imports being applied.
+#[derive(Default)]
+pub struct BulkImports {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl BulkImports {
+    fn running(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.running
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn is_running(&self, id: &str) -> bool {
+        self.running().contains_key(id)
+    }
+}
+
+fn import_dir(app: &AppHandle) -> Result<PathBuf> {
+    Ok(paths::data_dir(app)?.join(IMPORT_DIR))
+}
+
+/// Import ids come from the frontend and end up in file names.
+fn check_id(id: &str) -> Result<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(Error::InvalidInput(format!("Unknown import: {id}")));
+    }
+    Ok(())
+}
+
+fn manifest_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.json"))
+}
+
+fn journal_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.log"))
+}
+
+/// Reads the saved rows and replays the journal over them.
+fn load(dir: &Path, id: &str) -> Result<Manifest> {
+    check_id(id)?;
+    let mut manifest: Manifest = match fs::read(manifest_path(dir, id)) {
+        Ok(contents) => serde_json::from_slice(&contents)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::InvalidInput(format!("Unknown import: {id}")));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let journal = match fs::read_to_string(journal_path(dir, id)) {
+        Ok(journal) => journal,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+    for line in journal.lines() {
+        // A line cut short by quitting mid-write is the only one that can fail
+        let Ok(outcome) = serde_json::from_str::<Outcome>(line) else {
+            continue;
+        };
+        if let Some(row) = manifest.rows.get_mut(outcome.row) {
+            row.state = match outcome.error {
+                None => RowState::Applied,
+                Some(_) => RowState::Failed,
+            };
+            row.error = outcome.error;
+        }
+    }
+    Ok(manifest)
+}
+
+fn normalize(header: &str) -> String {
+    header.trim().replace('_', " ").to_lowercase()
+}
+
+/// Column positions, from the header row.
+struct Columns {
+    version_name: usize,
+    version_number: Option<usize>,
+    note: Option<usize>,
+    status: Option<usize>,
+}
+
+impl Columns {
+    fn find(
+        headers: &[String],
+        chosen: Option<&str>,
+        aliases: &[&str],
+        localized: Option<&str>,
+    ) -> Result<Option<usize>> {
+        let position = |name: &str| {
+            let name = normalize(name);
+            headers.iter().position(|header| normalize(header) == name)
+        };
+        if let Some(chosen) = chosen {
+            return position(chosen)
+                .map(Some)
+                .ok_or_else(|| Error::InvalidInput(format!("The sheet has no column {chosen:?}")));
+        }
+        Ok(aliases.iter().copied().chain(localized).find_map(position))
+    }
+
+    fn detect(headers: &[String], mapping: &ColumnMapping) -> Result<Self> {
+        // Notes exported from AstraNotes carry headers in the locale they were written in
+        let localized = export::headers();
+        let version_name = Self::find(
+            headers,
+            mapping.version_name.as_deref(),
+            NAME_HEADERS,
+            Some(&localized[0]),
+        )?
+        .ok_or_else(|| {
+            Error::InvalidInput("The sheet has no Version Name column in its first row".into())
+        })?;
+        let columns = Self {
+            version_name,
+            version_number: Self::find(
+                headers,
+                mapping.version_number.as_deref(),
+                NUMBER_HEADERS,
+                Some(&localized[1]),
+            )?,
+            note: Self::find(
+                headers,
+                mapping.note.as_deref(),
+                NOTE_HEADERS,
+                Some(&localized[7]),
+            )?,
+            status: Self::find(headers, mapping.status.as_deref(), STATUS_HEADERS, None)?,
+        };
+        if columns.note.is_none() && columns.status.is_none() {
+            return Err(Error::InvalidInput(
+                "The sheet has neither a Notes nor a Status column".into(),
+            ));
+        }
+        Ok(columns)
+    }
+
+    fn row(&self, line: u64, cells: &[String]) -> ImportRow {
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|index| cells.get(index))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let mut row = ImportRow {
+            line,
+            version_name: cell(Some(self.version_name)).unwrap_or_default(),
+            version_number: None,
+            note: cell(self.note),
+            status: cell(self.status),
+            version_id: None,
+            status_id: None,
+            state: RowState::Ready,
+            error: None,
+        };
+        if row.version_name.is_empty() {
+            row.reject("The version name is empty".into());
+        }
+        if let Some(number) = cell(self.version_number) {
+            match parse_version_number(&number) {
+                Some(number) => row.version_number = Some(number),
+                None => row.reject(format!("{number:?} is not a version number")),
+            }
+        }
+        if row.note.is_none() && row.status.is_none() {
+            row.reject("The row has neither a note nor a status".into());
+        }
+        row
+    }
+}
+
+/// Accepts `3`, `v003` and the `3.0` spreadsheets make of numbers.
+fn parse_version_number(value: &str) -> Option<u32> {
+    let value = value.trim_start_matches(['v', 'V']);
+    value.parse().ok().or_else(|| {
+        let number: f64 = value.parse().ok()?;
+        (number.fract() == 0.0 && (0.0..=f64::from(u32::MAX)).contains(&number))
+            .then_some(number as u32)
+    })
+}
+
+/// Streams the file into rows, checking each for what it can without ftrack.
+fn parse(path: &Path, mapping: &ColumnMapping) -> Result<Vec<ImportRow>> {
+    let mut columns: Option<Columns> = None;
+    let mut rows = Vec::new();
+    sheet::read_rows(path, &mut |line, cells| {
+        if cells.iter().all(|cell| cell.trim().is_empty()) {
+            return Ok(());
+        }
+        let Some(found) = columns.as_ref() else {
+            columns = Some(Columns::detect(&cells, mapping)?);
+            return Ok(());
+        };
+        if rows.len() == MAX_ROWS {
+            return Err(Error::InvalidInput(format!(
+                "The sheet has more than {MAX_ROWS} rows; split it into smaller files"
+            )));
+        }
+        rows.push(found.row(line, &cells));
+        Ok(())
+    })?;
+    if rows.is_empty() {
+        return Err(Error::InvalidInput(
+            "The sheet has no rows to import".into(),
+        ));
+    }
+    Ok(rows)
+}
+
+/// Resolves each row's version within the playlist and its status by name.
+async fn check_rows(
+    client: &reqwest::Client,
+    connection: &Connection,
+    playlist_id: &str,
+    rows: &mut [ImportRow],
+) -> Result<()> {
+    let versions = query(
+        client,
+        connection,
+        format!(
+            "select id, version, asset.name from AssetVersion where lists any (id is {})",
+            quoted(playlist_id)
+        ),
+    )
+    .await?;
+    let mut by_name: HashMap<String, Vec<(Option<u32>, String)>> = HashMap::new();
+    for version in &versions {
+        let (Some(id), Some(name)) = (version["id"].as_str(), version["asset"]["name"].as_str())
+        else {
+            continue;
+        };
+        let number = version["version"].as_u64().map(|number| number as u32);
+        by_name
+            .entry(name.to_lowercase())
+            .or_default()
+            .push((number, id.to_string()));
+    }
+    let statuses: HashMap<String, String> =
+        query(client, connection, "select id, name from Status".into())
+            .await?
+            .iter()
+            .filter_map(|status| {
+                Some((
+                    status["name"].as_str()?.to_lowercase(),
+                    status["id"].as_str()?.to_string(),
+                ))
+            })
+            .collect();
+
+    for row in rows.iter_mut().filter(|row| row.state == RowState::Ready) {
+        let candidates = by_name
+            .get(&row.version_name.to_lowercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let version_id = match (row.version_number, candidates) {
+            (_, []) => Err(format!("{} is not in the playlist", row.version_name)),
+            (Some(number), candidates) => candidates
+                .iter()
+                .find(|(candidate, _)| *candidate == Some(number))
+                .map(|(_, id)| id.clone())
+                .ok_or_else(|| format!("{} v{number} is not in the playlist", row.version_name)),
+            (None, [(_, id)]) => Ok(id.clone()),
+            (None, candidates) => Err(format!(
+                "The playlist has {} versions of {}; add a version number",
+                candidates.len(),
+                row.version_name
+            )),
+        };
+        match version_id {
+            Ok(id) => row.version_id = Some(id),
+            Err(error) => row.reject(error),
+        }
+        if let Some(status) = &row.status {
+            match statuses.get(&status.to_lowercase()) {
+                Some(id) => row.status_id = Some(id.clone()),
+                None => row.reject(format!("ftrack has no status named {status}")),
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn user_id(client: &reqwest::Client, connection: &Connection) -> Result<String> {
+    let users = query(
+        client,
+        connection,
+        format!(
+            "select id from User where username is {}",
+            quoted(&connection.api_user)
+        ),
+    )
+    .await?;
+    users
+        .first()
+        .and_then(|user| user["id"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| Error::Provider(format!("ftrack user {} not found", connection.api_user)))
+}
+
+fn operations(row: &ImportRow, user_id: &str) -> Value {
+    let mut operations = Vec::new();
+    if let (Some(note), Some(version_id)) = (&row.note, &row.version_id) {
+        operations.push(json!({
+            "action": "create",
+            "entity_type": "Note",
+            "entity_data": {
+                "id": uuid::Uuid::new_v4().to_string(),
+                "content": note,
+                "parent_id": version_id,
+                "parent_type": "AssetVersion",
+                "user_id": user_id,
+            },
+        }));
+    }
+    if let (Some(status_id), Some(version_id)) = (&row.status_id, &row.version_id) {
+        operations.push(json!({
+            "action": "update",
+            "entity_type": "AssetVersion",
+            "entity_key": [version_id],
+            "entity_data": { "status_id": status_id },
+        }));
+    }
+    Value::Array(operations)
+}
+
+fn emit(app: &AppHandle, snapshot: &BulkImportSnapshot) {
+    let source = format!("bulk-import:{}", snapshot.id);
+    match snapshot.status {
+        ImportStatus::Applying => taskbar::update(
+            app,
+            &source,
+            (snapshot.applied + snapshot.failed) as u64,
+            (snapshot.total - snapshot.invalid) as u64,
+        ),
+        _ => taskbar::finish(app, &source),
+    }
+    if let Err(err) = app.emit(PROGRESS_EVENT, snapshot) {
+        log::warn!("Failed to emit bulk import progress: {err}");
+    }
+}
+
+/// Writes the ready and failed rows, journaling each outcome. Stops early
+/// when cancelled, or when ftrack cannot be reached, leaving the row ready.
+async fn apply(app: &AppHandle, manifest: &mut Manifest, cancelled: &AtomicBool) -> Result<()> {
+    let client = network::client();
+    let connection = manifest.connection.clone();
+    let pending: Vec<usize> = (0..manifest.rows.len())
+        .filter(|&index| {
+            matches!(
+                manifest.rows[index].state,
+                RowState::Ready | RowState::Failed
+            )
+        })
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let user_id = user_id(&client, &connection).await?;
+    let dir = import_dir(app)?;
+    let mut journal = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_path(&dir, &manifest.id))?;
+
+    let mut last_emit = Instant::now();
+    let mut applied = 0;
+    let mut stopped = None;
+    for index in pending {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let operations = operations(&manifest.rows[index], &user_id);
+        let error = match ftrack::call(&client, &connection, &operations).await {
+            Ok(_) => None,
+            Err(err) if err.is_retryable() => {
+                stopped = Some(err);
+                break;
+            }
+            Err(err) => Some(err.to_string()),
+        };
+        writeln!(
+            journal,
+            "{}",
+            serde_json::to_string(&Outcome {
+                row: index,
+                error: error.clone(),
+            })?
+        )?;
+        let row = &mut manifest.rows[index];
+        row.state = match error {
+            None => RowState::Applied,
+            Some(_) => RowState::Failed,
+        };
+        row.error = error;
+        if row.state == RowState::Applied {
+            applied += 1;
+        }
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            emit(app, &manifest.snapshot(true));
+            last_emit = Instant::now();
+        }
+    }
+    if applied > 0 {
+        audit::record(
+            app,
+            NewAuditEntry {
+                action: AuditAction::Update,
+                connection: Some(connection),
+                entity_type: Some("AssetVersionList".into()),
+                entity_id: Some(manifest.playlist_id.clone()),
+                summary: format!(
+                    "Imported {applied} row(s) from {}",
+                    manifest.source.display()
+                ),
+                details: json!({ "importId": manifest.id, "source": manifest.source }),
+            },
+        );
+    }
+    match stopped {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+async fn run(app: AppHandle, mut manifest: Manifest, cancelled: Arc<AtomicBool>) {
+    let result = apply(&app, &mut manifest, &cancelled).await;
+    app.state::<BulkImports>().running().remove(&manifest.id);
+    let mut snapshot = manifest.snapshot(false);
+    match result {
+        Ok(()) => log::info!(
+            "Bulk import {} stopped with {} applied, {} failed and {} ready",
+            manifest.id,
+            snapshot.applied,
+            snapshot.failed,
+            snapshot.ready
+        ),
+        Err(err) => {
+            log::warn!("Bulk import {} stopped: {err}", manifest.id);
+            snapshot.error = Some(err.to_string());
+        }
+    }
+    emit(&app, &snapshot);
+}
+
+/// Reads the sheet and checks every row against the playlist in ftrack.
+/// Nothing is written to ftrack until the import is applied.
+#[tauri::command]
+pub async fn validate_bulk_import(
+    app: AppHandle,
+    store: State<'_, Store>,
+    path: PathBuf,
+    playlist_id: String,
+    columns: Option<ColumnMapping>,
+) -> Result<BulkImportSnapshot> {
+    let connection = profiles::active_connection(&store)?;
+    let mapping = columns.unwrap_or_default();
+    let source = path.clone();
+    let mut rows = tauri::async_runtime::spawn_blocking(move || parse(&source, &mapping)).await??;
+    check_rows(&network::client(), &connection, &playlist_id, &mut rows).await?;
+
+    let manifest = Manifest {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: path,
+        playlist_id,
+        connection,
+        created_at: now_millis(),
+        rows,
+    };
+    let dir = import_dir(&app)?;
+    let snapshot = manifest.snapshot(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        fs::create_dir_all(&dir)?;
+        write_atomically(&manifest_path(&dir, &manifest.id), |temp| {
+            fs::write(temp, serde_json::to_vec(&manifest)?)?;
+            Ok(())
+        })
+    })
+    .await??;
+    log::info!(
+        "Validated bulk import {} from {}: {} ready, {} invalid",
+        snapshot.id,
+        snapshot.source.display(),
+        snapshot.ready,
+        snapshot.invalid
+    );
+    Ok(snapshot)
+}
+
+/// Starts writing an import's valid rows to ftrack, or resumes it.
+#[tauri::command]
+pub async fn apply_bulk_import(
+    app: AppHandle,
+    imports: State<'_, BulkImports>,
+    id: String,
+) -> Result<BulkImportSnapshot> {
+    let dir = import_dir(&app)?;
+    let target = id.clone();
+    let manifest = tauri::async_runtime::spawn_blocking(move || load(&dir, &target)).await??;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    {
+        let mut running = imports.running();
+        if running.contains_key(&id) {
+            return Err(Error::InvalidInput(format!(
+                "Import {id} is already being applied"
+            )));
+        }
+        running.insert(id, cancelled.clone());
+    }
+    let snapshot = manifest.snapshot(true);
+    emit(&app, &snapshot);
+    tauri::async_runtime::spawn(run(app, manifest, cancelled));
+    Ok(snapshot)
+}
+
+/// Stops applying after the row being written; applying again resumes.
+#[tauri::command]
+pub fn cancel_bulk_import(imports: State<'_, BulkImports>, id: String) -> Result<()> {
+    imports
+        .running()
+        .get(&id)
+        .ok_or_else(|| Error::InvalidInput(format!("Import {id} is not being applied")))?
+        .store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Saved imports, newest first.
+#[tauri::command]
+pub async fn list_bulk_imports(
+    app: AppHandle,
+    imports: State<'_, BulkImports>,
+) -> Result<Vec<BulkImportSnapshot>> {
+    let dir = import_dir(&app)?;
+    let mut manifests = tauri::async_runtime::spawn_blocking(move || {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(Error::from(err)),
+        };
+        let mut manifests = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            match load(&dir, id) {
+                Ok(manifest) => manifests.push(manifest),
+                Err(err) => log::warn!("Ignoring unreadable import {}: {err}", path.display()),
+            }
+        }
+        Ok(manifests)
+    })
+    .await??;
+    manifests.sort_by_key(|manifest| std::cmp::Reverse(manifest.created_at));
+    Ok(manifests
+        .iter()
+        .map(|manifest| manifest.snapshot(imports.is_running(&manifest.id)))
+        .collect())
+}
+
+/// A page of an import's rows, optionally only those in `state`.
+#[tauri::command]
+pub async fn get_bulk_import_rows(
+    app: AppHandle,
+    id: String,
+    state: Option<RowState>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<ImportRow>> {
+    let dir = import_dir(&app)?;
+    let manifest = tauri::async_runtime::spawn_blocking(move || load(&dir, &id)).await??;
+    Ok(manifest
+        .rows
+        .into_iter()
+        .filter(|row| state.is_none_or(|state| row.state == state))
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(500))
+        .collect())
+}
+
+/// Saves the invalid and failed rows as CSV. Returns the written path, or
+/// `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_bulk_import_errors(
+    app: AppHandle,
+    id: String,
+    path: Option<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    let dir = import_dir(&app)?;
+    let manifest = tauri::async_runtime::spawn_blocking(move || load(&dir, &id)).await??;
+    let stem = manifest
+        .source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "import".into());
+    let name = format!("{stem}_errors");
+    let Some(path) = export::resolve_path(&app, path, &name, "CSV", "csv").await else {
+        return Ok(None);
+    };
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        write_atomically(&target, |temp| {
+            let mut writer = ::csv::WriterBuilder::new()
+                .quote_style(::csv::QuoteStyle::Always)
+                .from_path(temp)?;
+            writer.write_record(REPORT_HEADERS)?;
+            for row in &manifest.rows {
+                let result = match row.state {
+                    RowState::Invalid => "Invalid",
+                    RowState::Failed => "Failed",
+                    RowState::Ready | RowState::Applied => continue,
+                };
+                writer.write_record([
+                    row.line.to_string().as_str(),
+                    &row.version_name,
+                    &row.version_number
+                        .map(|n| n.to_string())
+                        .unwrap_or_default(),
+                    row.status.as_deref().unwrap_or_default(),
+                    result,
+                    row.error.as_deref().unwrap_or_default(),
+                ])?;
+            }
+            writer.flush()?;
+            Ok(())
+        })
+    })
+    .await??;
+    Ok(Some(path))
+}
+
+/// Deletes a saved import and its journal. Rows already written stay in ftrack.
+#[tauri::command]
+pub fn discard_bulk_import(
+    app: AppHandle,
+    imports: State<'_, BulkImports>,
+    id: String,
+) -> Result<()> {
+    check_id(&id)?;
+    if imports.is_running(&id) {
+        return Err(Error::InvalidInput(format!(
+            "Cancel import {id} before discarding it"
+        )));
+    }
+    let dir = import_dir(&app)?;
+    for path in [manifest_path(&dir, &id), journal_path(&dir, &id)] {
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,284 @@
+//! Streaming rows out of CSV files and XLSX workbooks.
+//!
+//! Rows are handed to a callback as they are read, so a sheet of a hundred
+//! thousand rows never has to be held as a whole. Only the shared string
+//! table of a workbook is loaded up front, since cells refer to it by index.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use zip::ZipArchive;
+
+use crate::error::{Error, Result};
+
+const DEFAULT_SHEET: &str = "xl/worksheets/sheet1.xml";
+
+fn unreadable(err: impl std::fmt::Display) -> Error {
+    Error::InvalidInput(format!("Unreadable workbook: {err}"))
+}
+
+/// Calls `each` with the 1-based line or row number and the cells of every
+/// row, header included, until it fails or the file ends.
+pub(super) fn read_rows(
+    path: &Path,
+    each: &mut dyn FnMut(u64, Vec<String>) -> Result<()>,
+) -> Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "csv" | "txt" => read_csv(path, each),
+        "xlsx" | "xlsm" => read_xlsx(path, each),
+        _ => Err(Error::InvalidInput(format!(
+            "{} is not a CSV or XLSX file",
+            path.display()
+        ))),
+    }
+}
+
+fn read_csv(path: &Path, each: &mut dyn FnMut(u64, Vec<String>) -> Result<()>) -> Result<()> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+    let mut record = ::csv::StringRecord::new();
+    loop {
+        let line = reader.position().line();
+        if !reader.read_record(&mut record)? {
+            return Ok(());
+        }
+        // Spreadsheet apps often save CSV with a byte order mark
+        let cells = record
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| match index {
+                0 => cell.trim_start_matches('\u{feff}').to_string(),
+                _ => cell.to_string(),
+            })
+            .collect();
+        each(line, cells)?;
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Result<Option<String>> {
+    match element.try_get_attribute(name).map_err(unreadable)? {
+        Some(attribute) => Ok(Some(
+            attribute.unescape_value().map_err(unreadable)?.into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Appends the text of a text or entity event to `text`.
+fn push_text(event: &Event, text: &mut String) -> Result<()> {
+    match event {
+        Event::Text(content) => text.push_str(&content.xml_content().map_err(unreadable)?),
+        Event::CData(content) => text.push_str(&content.decode().map_err(unreadable)?),
+        Event::GeneralRef(reference) => {
+            if let Some(ch) = reference.resolve_char_ref().map_err(unreadable)? {
+                text.push(ch);
+            } else {
+                let name = reference.decode().map_err(unreadable)?;
+                text.push_str(quick_xml::escape::resolve_xml_entity(&name).unwrap_or_default());
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn xml_reader<R: Read>(entry: R) -> Reader<BufReader<R>> {
+    Reader::from_reader(BufReader::new(entry))
+}
+
+/// The shared string table, with rich text runs joined and phonetic hints
+/// left out.
+fn shared_strings(reader: &mut Reader<impl BufRead>) -> Result<Vec<String>> {
+    let mut strings = Vec::new();
+    let mut buf = Vec::new();
+    let mut current: Option<String> = None;
+    let mut in_text = false;
+    let mut in_phonetic = false;
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(unreadable)?;
+        match &event {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"si" => current = Some(String::new()),
+                b"t" => in_text = true,
+                b"rPh" => in_phonetic = true,
+                _ => {}
+            },
+            Event::Empty(element) if element.local_name().as_ref() == b"si" => {
+                strings.push(String::new())
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"si" => strings.extend(current.take()),
+                b"t" => in_text = false,
+                b"rPh" => in_phonetic = false,
+                _ => {}
+            },
+            Event::Eof => return Ok(strings),
+            _ => {
+                if in_text
+                    && !in_phonetic
+                    && let Some(text) = current.as_mut()
+                {
+                    push_text(&event, text)?;
+                }
+            }
+        }
+        buf.clear();
+    }
+}
+
+/// Relationship ids to targets, from a `.rels` part.
+fn relationships(reader: &mut Reader<impl BufRead>) -> Result<HashMap<String, String>> {
+    let mut targets = HashMap::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf).map_err(unreadable)? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"Relationship" =>
+            {
+                if let (Some(id), Some(target)) =
+                    (attribute(&element, b"Id")?, attribute(&element, b"Target")?)
+                {
+                    targets.insert(id, target);
+                }
+            }
+            Event::Eof => return Ok(targets),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Relationship id of the first sheet listed in the workbook.
+fn first_sheet_id(reader: &mut Reader<impl BufRead>) -> Result<Option<String>> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf).map_err(unreadable)? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"sheet" =>
+            {
+                return attribute(&element, b"r:id");
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Path of the first worksheet inside the archive.
+fn first_sheet(archive: &mut ZipArchive<BufReader<File>>) -> Result<String> {
+    let Ok(workbook) = archive.by_name("xl/workbook.xml") else {
+        return Err(unreadable("xl/workbook.xml is missing"));
+    };
+    let Some(id) = first_sheet_id(&mut xml_reader(workbook))? else {
+        return Err(unreadable("the workbook has no sheets"));
+    };
+    let targets = match archive.by_name("xl/_rels/workbook.xml.rels") {
+        Ok(rels) => relationships(&mut xml_reader(rels))?,
+        Err(_) => HashMap::new(),
+    };
+    Ok(match targets.get(&id) {
+        Some(target) => match target.strip_prefix('/') {
+            Some(absolute) => absolute.to_string(),
+            None => format!("xl/{target}"),
+        },
+        None => DEFAULT_SHEET.to_string(),
+    })
+}
+
+/// Zero-based column of a cell reference such as `AB12`.
+fn column(reference: &str) -> Option<usize> {
+    let letters = reference
+        .bytes()
+        .take_while(u8::is_ascii_alphabetic)
+        .map(|letter| usize::from(letter.to_ascii_uppercase() - b'A') + 1);
+    letters
+        .reduce(|column, letter| column * 26 + letter)
+        .map(|column| column - 1)
+}
+
+fn read_xlsx(path: &Path, each: &mut dyn FnMut(u64, Vec<String>) -> Result<()>) -> Result<()> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let strings = match archive.by_name("xl/sharedStrings.xml") {
+        Ok(entry) => shared_strings(&mut xml_reader(entry))?,
+        Err(_) => Vec::new(),
+    };
+    let sheet = first_sheet(&mut archive)?;
+    let mut reader = xml_reader(archive.by_name(&sheet)?);
+
+    let mut buf = Vec::new();
+    let mut row_number = 0;
+    let mut cells: Vec<String> = Vec::new();
+    // Column, type and text of the cell being read
+    let mut cell: Option<(usize, Option<String>, String)> = None;
+    let mut in_value = false;
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(unreadable)?;
+        match &event {
+            Event::Start(element) | Event::Empty(element) => match element.local_name().as_ref() {
+                b"row" => {
+                    row_number = match attribute(element, b"r")?.and_then(|r| r.parse().ok()) {
+                        Some(number) => number,
+                        None => row_number + 1,
+                    };
+                    cells.clear();
+                    if matches!(event, Event::Empty(_)) {
+                        each(row_number, Vec::new())?;
+                    }
+                }
+                b"c" => {
+                    let index = attribute(element, b"r")?
+                        .as_deref()
+                        .and_then(column)
+                        .unwrap_or(cells.len());
+                    if matches!(event, Event::Start(_)) {
+                        cell = Some((index, attribute(element, b"t")?, String::new()));
+                    }
+                }
+                b"v" | b"t" if cell.is_some() => in_value = matches!(event, Event::Start(_)),
+                _ => {}
+            },
+            Event::End(element) => match element.local_name().as_ref() {
+                b"v" | b"t" => in_value = false,
+                b"c" => {
+                    if let Some((index, kind, text)) = cell.take() {
+                        let value = match kind.as_deref() {
+                            Some("s") => text
+                                .trim()
+                                .parse::<usize>()
+                                .ok()
+                                .and_then(|index| strings.get(index).cloned())
+                                .unwrap_or_default(),
+                            Some("b") => (if text == "1" { "TRUE" } else { "FALSE" }).to_string(),
+                            _ => text,
+                        };
+                        if cells.len() <= index {
+                            cells.resize(index + 1, String::new());
+                        }
+                        cells[index] = value;
+                    }
+                }
+                b"row" => each(row_number, std::mem::take(&mut cells))?,
+                _ => {}
+            },
+            Event::Eof => return Ok(()),
+            _ => {
+                if in_value && let Some((_, _, text)) = cell.as_mut() {
+                    push_text(&event, text)?;
+                }
+            }
+        }
+        buf.clear();
+    }
+}
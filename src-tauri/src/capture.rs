@@ -0,0 +1,137 @@
+//! Interactive region screenshots for note attachments.
+//!
+//! Region selection is left to the tool each desktop already provides:
+//! `screencapture` on macOS, the Snipping overlay on Windows (read back from
+//! the clipboard), and the compositor's screenshot tool on Linux. Captures
+//! are written as PNGs to the app cache directory.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_shell::ShellExt;
+
+use crate::error::{Error, Result};
+use crate::paths;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capture {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Runs `program`, returning `None` if it is not installed.
+async fn run(app: &AppHandle, program: &str, args: &[&str]) -> Option<(bool, Vec<u8>)> {
+    let output = app
+        .shell()
+        .command(program)
+        .args(args)
+        .output()
+        .await
+        .ok()?;
+    Some((output.status.success(), output.stdout))
+}
+
+#[cfg(target_os = "macos")]
+async fn select_region(app: &AppHandle, out: &Path) -> Result<()> {
+    let out = out.to_string_lossy();
+    // -i: drag to select (Space toggles window mode), -x: no shutter sound
+    run(app, "screencapture", &["-i", "-x", "-t", "png", &out])
+        .await
+        .ok_or_else(|| Error::Capture("screencapture is unavailable".into()))?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+async fn select_region(app: &AppHandle, out: &Path) -> Result<()> {
+    let out = out.to_string_lossy();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        if let Some((selected, geometry)) = run(app, "slurp", &[]).await {
+            if !selected {
+                return Ok(());
+            }
+            let geometry = String::from_utf8_lossy(&geometry).trim().to_string();
+            if run(app, "grim", &["-g", &geometry, &out]).await.is_some() {
+                return Ok(());
+            }
+        }
+    }
+    let tools: [(&str, &[&str]); 4] = [
+        ("gnome-screenshot", &["-a", "-f"]),
+        ("spectacle", &["-r", "-b", "-n", "-o"]),
+        ("maim", &["-s"]),
+        ("scrot", &["-s", "-o"]),
+    ];
+    for (program, args) in tools {
+        let mut args = args.to_vec();
+        args.push(&out);
+        if run(app, program, &args).await.is_some() {
+            return Ok(());
+        }
+    }
+    Err(Error::Capture(
+        "No screenshot tool found; install gnome-screenshot, spectacle, grim and slurp, or maim"
+            .into(),
+    ))
+}
+
+#[cfg(target_os = "windows")]
+async fn select_region(app: &AppHandle, out: &Path) -> Result<()> {
+    use std::time::{Duration, Instant};
+
+    const TIMEOUT: Duration = Duration::from_secs(120);
+    const POLL: Duration = Duration::from_millis(250);
+
+    // The overlay only puts the snip on the clipboard, so wait for a new image
+    let mut clipboard = arboard::Clipboard::new()?;
+    let previous = clipboard
+        .get_image()
+        .ok()
+        .map(|image| image.bytes.into_owned());
+    run(app, "explorer.exe", &["ms-screenclip:"])
+        .await
+        .ok_or_else(|| Error::Capture("The Windows snipping overlay is unavailable".into()))?;
+
+    let started = Instant::now();
+    while started.elapsed() < TIMEOUT {
+        tokio::time::sleep(POLL).await;
+        let Ok(image) = clipboard.get_image() else {
+            continue;
+        };
+        if previous.as_deref() == Some(image.bytes.as_ref()) {
+            continue;
+        }
+        let buffer = image::RgbaImage::from_raw(
+            image.width as u32,
+            image.height as u32,
+            image.bytes.into_owned(),
+        )
+        .ok_or_else(|| Error::Capture("Unreadable clipboard image".into()))?;
+        buffer.save(out)?;
+        return Ok(());
+    }
+    Ok(())
+}
+
+/// Lets the user drag-select part of any screen and saves it as a PNG.
+/// Returns `None` if the selection was cancelled.
+#[tauri::command]
+pub async fn capture_screen_region(app: AppHandle) -> Result<Option<Capture>> {
+    let dir = paths::cache_dir(&app)?.join("captures");
+    std::fs::create_dir_all(&dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+    let path = dir.join(format!("capture-{stamp}.png"));
+
+    select_region(&app, &path).await?;
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let (width, height) = image::image_dimensions(&path)?;
+    Ok(Some(Capture {
+        path,
+        width,
+        height,
+    }))
+}
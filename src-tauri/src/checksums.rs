@@ -0,0 +1,479 @@
+//! File checksums for deliveries and for verifying them afterwards.
+//!
+//! `hash_files` hashes files and folders in parallel; `verify_manifest`
+//! re-checks a delivery against the `manifest.json` or `manifest.csv` a
+//! package was built with, or a `md5sum`-style checksum list. Both report
+//! `checksums:progress` events while they run.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use md5::{Digest, Md5};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tauri::{AppHandle, Emitter};
+use xxhash_rust::xxh3::Xxh3;
+use xxhash_rust::xxh64::Xxh64;
+
+use crate::error::{Error, Result};
+
+pub const PROGRESS_EVENT: &str = "checksums:progress";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const BUFFER_BYTES: usize = 4 * 1024 * 1024;
+/// Folders are expanded this deep at most.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Xxh64,
+    Xxh3,
+    Md5,
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Xxh64 => "xxh64",
+            Self::Xxh3 => "xxh3",
+            Self::Md5 => "md5",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "xxh64" | "xxh" => Some(Self::Xxh64),
+            "xxh3" => Some(Self::Xxh3),
+            "md5" => Some(Self::Md5),
+            "sha256" => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+}
+
+pub enum Hasher {
+    Xxh64(Box<Xxh64>),
+    Xxh3(Box<Xxh3>),
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Xxh64 => Self::Xxh64(Box::new(Xxh64::new(0))),
+            ChecksumAlgorithm::Xxh3 => Self::Xxh3(Box::new(Xxh3::new())),
+            ChecksumAlgorithm::Md5 => Self::Md5(Md5::new()),
+            ChecksumAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Xxh64(hasher) => hasher.update(bytes),
+            Self::Xxh3(hasher) => hasher.update(bytes),
+            Self::Md5(hasher) => hasher.update(bytes),
+            Self::Sha256(hasher) => hasher.update(bytes),
+        }
+    }
+
+    /// Lowercase hex digest, matching the output of `xxhsum`, `md5sum` and
+    /// `sha256sum`.
+    pub fn finish(self) -> String {
+        let hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        match self {
+            Self::Xxh64(hasher) => format!("{:016x}", hasher.digest()),
+            Self::Xxh3(hasher) => format!("{:016x}", hasher.digest()),
+            Self::Md5(hasher) => hex(&hasher.finalize()),
+            Self::Sha256(hasher) => hex(&hasher.finalize()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecksumProgress {
+    /// The ID passed by the caller, to tell concurrent runs apart.
+    pub id: Option<String>,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// Counts progress across the rayon workers and emits it at most every
+/// [`PROGRESS_INTERVAL`].
+struct Progress<'a> {
+    app: &'a AppHandle,
+    id: Option<String>,
+    files_total: usize,
+    bytes_total: u64,
+    files_done: AtomicUsize,
+    bytes_done: AtomicU64,
+    last_emit: Mutex<Instant>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(app: &'a AppHandle, id: Option<String>, files_total: usize, bytes_total: u64) -> Self {
+        Self {
+            app,
+            id,
+            files_total,
+            bytes_total,
+            files_done: AtomicUsize::new(0),
+            bytes_done: AtomicU64::new(0),
+            last_emit: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn add_bytes(&self, bytes: u64) {
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+        self.emit(true);
+    }
+
+    fn file_done(&self) {
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+        self.emit(true);
+    }
+
+    fn emit(&self, throttle: bool) {
+        if throttle {
+            let mut last_emit = self
+                .last_emit
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if last_emit.elapsed() < PROGRESS_INTERVAL {
+                return;
+            }
+            *last_emit = Instant::now();
+        }
+        let progress = ChecksumProgress {
+            id: self.id.clone(),
+            files_done: self.files_done.load(Ordering::Relaxed),
+            files_total: self.files_total,
+            bytes_done: self.bytes_done.load(Ordering::Relaxed),
+            bytes_total: self.bytes_total,
+        };
+        if let Err(err) = self.app.emit(PROGRESS_EVENT, progress) {
+            log::warn!("Failed to emit checksum progress: {err}");
+        }
+    }
+}
+
+fn hash_file(path: &Path, algorithm: ChecksumAlgorithm, progress: &Progress<'_>) -> Result<String> {
+    let mut reader = File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; BUFFER_BYTES];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        progress.add_bytes(read as u64);
+    }
+    Ok(hasher.finish())
+}
+
+/// Adds `path`, or the files under it if it is a folder, in name order.
+fn collect_files(path: &Path, depth: usize, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    if depth >= MAX_DEPTH {
+        return Ok(());
+    }
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            // Hidden files are OS metadata such as `.DS_Store`, not deliverables
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    entries.sort();
+    for entry in entries {
+        collect_files(&entry, depth + 1, files)?;
+    }
+    Ok(())
+}
+
+fn total_size(paths: &[PathBuf]) -> u64 {
+    paths
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChecksum {
+    pub path: PathBuf,
+    pub size_bytes: Option<u64>,
+    pub algorithm: ChecksumAlgorithm,
+    pub checksum: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Hashes files in parallel, expanding folders. Unreadable files are
+/// reported per file rather than failing the whole run.
+#[tauri::command]
+pub async fn hash_files(
+    app: AppHandle,
+    paths: Vec<PathBuf>,
+    algorithm: ChecksumAlgorithm,
+    id: Option<String>,
+) -> Result<Vec<FileChecksum>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        for path in &paths {
+            collect_files(path, 0, &mut files)?;
+        }
+        let progress = Progress::new(&app, id, files.len(), total_size(&files));
+        let results = files
+            .into_par_iter()
+            .map(|path| {
+                let result = hash_file(&path, algorithm, &progress);
+                progress.file_done();
+                let (checksum, error) = match result {
+                    Ok(checksum) => (Some(checksum), None),
+                    Err(err) => (None, Some(err.to_string())),
+                };
+                FileChecksum {
+                    size_bytes: fs::metadata(&path).ok().map(|metadata| metadata.len()),
+                    path,
+                    algorithm,
+                    checksum,
+                    error,
+                }
+            })
+            .collect();
+        progress.emit(false);
+        Ok(results)
+    })
+    .await?
+}
+
+/// One file listed in a manifest.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestEntry {
+    file: String,
+    #[serde(default)]
+    size_bytes: Option<u64>,
+    algorithm: String,
+    checksum: String,
+}
+
+/// Reads a delivery manifest, or a checksum list whose extension names the
+/// algorithm, such as `delivery.md5` with `md5sum` output.
+fn read_manifest(path: &Path) -> Result<Vec<ManifestEntry>> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "json" => Ok(serde_json::from_slice(&fs::read(path)?)?),
+        "csv" => {
+            let mut reader = ::csv::Reader::from_path(path)?;
+            let entries = reader
+                .deserialize()
+                .collect::<std::result::Result<_, _>>()?;
+            Ok(entries)
+        }
+        other => {
+            let algorithm = ChecksumAlgorithm::from_name(other).ok_or_else(|| {
+                Error::InvalidInput(format!("Unsupported manifest: {}", path.display()))
+            })?;
+            let contents = fs::read_to_string(path)?;
+            contents
+                .lines()
+                .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                .map(|line| {
+                    // `<digest>  <file>`, or `<digest> *<file>` for binary mode
+                    let (checksum, file) = line.split_once(' ').ok_or_else(|| {
+                        Error::InvalidInput(format!("Invalid checksum line: {line}"))
+                    })?;
+                    let file = file.trim_start_matches(' ');
+                    Ok(ManifestEntry {
+                        file: file.strip_prefix('*').unwrap_or(file).to_string(),
+                        size_bytes: None,
+                        algorithm: algorithm.name().to_string(),
+                        checksum: checksum.to_ascii_lowercase(),
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VerifyStatus {
+    Ok,
+    Missing,
+    SizeMismatch,
+    ChecksumMismatch,
+    Unreadable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedFile {
+    pub file: String,
+    pub status: VerifyStatus,
+    pub expected: String,
+    pub actual: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub manifest: PathBuf,
+    pub total: usize,
+    pub passed: usize,
+    /// Every file that failed, in manifest order.
+    pub failures: Vec<VerifiedFile>,
+}
+
+/// Where a manifest entry lives, if it stays inside the manifest folder.
+///
+/// Absolute paths and `..` are refused, as is a symlink leading out.
+fn entry_path(root: &Path, file: &str) -> Option<PathBuf> {
+    let relative = Path::new(file);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+    let path = root.join(relative);
+    if let (Ok(root), Ok(resolved)) = (root.canonicalize(), path.canonicalize())
+        && !resolved.starts_with(&root)
+    {
+        return None;
+    }
+    Some(path)
+}
+
+fn verify_entry(root: &Path, entry: &ManifestEntry, progress: &Progress<'_>) -> VerifiedFile {
+    let mut verified = VerifiedFile {
+        file: entry.file.clone(),
+        status: VerifyStatus::Ok,
+        expected: entry.checksum.clone(),
+        actual: None,
+        error: None,
+    };
+    let Some(algorithm) = ChecksumAlgorithm::from_name(&entry.algorithm) else {
+        verified.status = VerifyStatus::Unreadable;
+        verified.error = Some(format!("Unsupported algorithm {}", entry.algorithm));
+        return verified;
+    };
+    let Some(path) = entry_path(root, &entry.file) else {
+        verified.status = VerifyStatus::Unreadable;
+        verified.error = Some(format!("{} is outside the manifest folder", entry.file));
+        return verified;
+    };
+    let size = match fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            verified.status = VerifyStatus::Missing;
+            return verified;
+        }
+        Err(err) => {
+            verified.status = VerifyStatus::Unreadable;
+            verified.error = Some(err.to_string());
+            return verified;
+        }
+    };
+    if entry.size_bytes.is_some_and(|expected| expected != size) {
+        // No need to read a file that is already known to differ
+        progress.add_bytes(size);
+        verified.status = VerifyStatus::SizeMismatch;
+        return verified;
+    }
+    match hash_file(&path, algorithm, progress) {
+        Ok(actual) => {
+            if !actual.eq_ignore_ascii_case(&entry.checksum) {
+                verified.status = VerifyStatus::ChecksumMismatch;
+            }
+            verified.actual = Some(actual);
+        }
+        Err(err) => {
+            verified.status = VerifyStatus::Unreadable;
+            verified.error = Some(err.to_string());
+        }
+    }
+    verified
+}
+
+/// Re-hashes every file a manifest lists, relative to the manifest's folder.
+#[tauri::command]
+pub async fn verify_manifest(
+    app: AppHandle,
+    manifest_path: PathBuf,
+    id: Option<String>,
+) -> Result<VerifyReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let entries = read_manifest(&manifest_path)?;
+        let root = manifest_path.parent().unwrap_or(Path::new("."));
+        let mut seen = HashMap::new();
+        for entry in &entries {
+            if let Some(previous) = seen.insert(entry.file.as_str(), &entry.checksum)
+                && previous != &entry.checksum
+            {
+                return Err(Error::InvalidInput(format!(
+                    "{} is listed twice with different checksums",
+                    entry.file
+                )));
+            }
+        }
+        let paths: Vec<PathBuf> = entries
+            .iter()
+            .filter_map(|entry| entry_path(root, &entry.file))
+            .collect();
+        let progress = Progress::new(&app, id, entries.len(), total_size(&paths));
+        let verified: Vec<VerifiedFile> = entries
+            .par_iter()
+            .map(|entry| {
+                let verified = verify_entry(root, entry, &progress);
+                progress.file_done();
+                verified
+            })
+            .collect();
+        progress.emit(false);
+
+        let failures: Vec<VerifiedFile> = verified
+            .into_iter()
+            .filter(|file| file.status != VerifyStatus::Ok)
+            .collect();
+        log::info!(
+            "Verified {} files against {}: {} failed",
+            entries.len(),
+            manifest_path.display(),
+            failures.len()
+        );
+        Ok(VerifyReport {
+            total: entries.len(),
+            passed: entries.len() - failures.len(),
+            failures,
+            manifest: manifest_path,
+        })
+    })
+    .await?
+}
@@ -0,0 +1,256 @@
+//! Headless subcommands for pipeline automation.
+//!
+//! These work on the same store, profiles and keychain as the app, but never
+//! start Tauri, so they run on render farm nodes without a display. The app
+//! must have been signed in once on the machine, or in the same portable data
+//! directory, for a profile to exist. Pre-publish hooks and plugins run as in
+//! the app; publish rules need the running app and are not applied.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Subcommand, ValueEnum};
+
+use crate::error::{Error, Result};
+use crate::export::{self, NoteRow, PdfExportOptions, ReportNote};
+use crate::network;
+use crate::paths;
+use crate::pipeline_hooks;
+use crate::providers::{self, NewNote, Providers};
+use crate::store::profiles::Profile;
+use crate::store::{self, Store};
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Export a playlist's notes to a file
+    ExportNotes {
+        /// Playlist ID
+        #[arg(long)]
+        playlist: String,
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+        /// File to write
+        #[arg(long)]
+        out: PathBuf,
+        /// Title of XLSX and PDF exports, defaulting to the playlist ID
+        #[arg(long)]
+        title: Option<String>,
+        /// Include notes that were already published
+        #[arg(long)]
+        include_published: bool,
+        /// Profile ID, defaulting to the active profile
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Publish unpublished draft notes
+    PublishDrafts {
+        /// Only publish drafts in this playlist
+        #[arg(long)]
+        playlist: Option<String>,
+        /// Profile ID, defaulting to the active profile
+        #[arg(long)]
+        profile: Option<String>,
+        /// List the drafts that would be published without publishing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Format {
+    Csv,
+    Xlsx,
+    Pdf,
+}
+
+/// Runs `command` to completion, reporting errors on stderr.
+pub fn run(command: Command) -> ExitCode {
+    match tauri::async_runtime::block_on(execute(command)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn execute(command: Command) -> Result<()> {
+    let store = open_store()?;
+    if let Some(dir) = paths::standalone_config_dir()
+        && let Err(err) = network::apply_saved(&dir).await
+    {
+        eprintln!("warning: using system network settings: {err}");
+    }
+    // Profiles pinned to a certificate must not be reached without the pin
+    network::pinning::reload(&store)?;
+    let providers = Providers::default();
+    match command {
+        Command::ExportNotes {
+            playlist,
+            format,
+            out,
+            title,
+            include_published,
+            profile,
+        } => {
+            let profile = providers::resolve_profile(&store, profile.as_deref())?;
+            let title = title.unwrap_or_else(|| playlist.clone());
+            let notes =
+                playlist_notes(&store, &providers, &profile, &playlist, include_published).await?;
+            let count = notes.len();
+            let target = out.clone();
+            tauri::async_runtime::spawn_blocking(move || write(format, &target, &title, notes))
+                .await??;
+            println!("Exported {count} notes to {}", out.display());
+            Ok(())
+        }
+        Command::PublishDrafts {
+            playlist,
+            profile,
+            dry_run,
+        } => {
+            let profile = providers::resolve_profile(&store, profile.as_deref())?;
+            publish_drafts(&store, &providers, &profile, playlist.as_deref(), dry_run).await
+        }
+    }
+}
+
+fn open_store() -> Result<Store> {
+    let dir = paths::standalone_data_dir()
+        .ok_or_else(|| Error::InvalidInput("No app data directory on this system".into()))?;
+    let path = dir.join(store::FILE_NAME);
+    if !path.exists() {
+        return Err(Error::InvalidInput(format!(
+            "No AstraNotes data in {}; sign in to the app first",
+            dir.display()
+        )));
+    }
+    Store::open(&path)
+}
+
+/// The playlist's drafts in playlist order, with the version details the
+/// exports show.
+async fn playlist_notes(
+    store: &Store,
+    providers: &Providers,
+    profile: &Profile,
+    playlist_id: &str,
+    include_published: bool,
+) -> Result<Vec<ReportNote>> {
+    let drafts = store.list_drafts(Some(playlist_id))?;
+    let versions = providers.versions(profile, playlist_id).await?;
+    let notes = versions
+        .into_iter()
+        .filter_map(|version| {
+            let draft = drafts
+                .iter()
+                .find(|draft| draft.version_id == version.id && !draft.locked)?;
+            let exported = match draft.status.as_str() {
+                "draft" => true,
+                "published" => include_published,
+                _ => false,
+            };
+            exported.then(|| ReportNote {
+                row: NoteRow {
+                    version_name: version.name,
+                    version_number: version.version.unwrap_or_default(),
+                    content: draft.content.clone(),
+                    note_state: Some(draft.status.clone()),
+                    labels: Vec::new(),
+                    author: None,
+                    created_at: None,
+                    frame_number: None,
+                },
+                version_status: version.status,
+                thumbnail_path: None,
+            })
+        })
+        .collect();
+    Ok(notes)
+}
+
+fn write(format: Format, out: &Path, title: &str, notes: Vec<ReportNote>) -> Result<()> {
+    match format {
+        Format::Csv => {
+            let rows: Vec<NoteRow> = notes.into_iter().map(|note| note.row).collect();
+            export::write_notes_csv(out, &rows)
+        }
+        Format::Xlsx => {
+            let rows: Vec<NoteRow> = notes.into_iter().map(|note| note.row).collect();
+            export::write_notes_xlsx(out, title, &rows)
+        }
+        Format::Pdf => {
+            let options = PdfExportOptions {
+                include_thumbnails: false,
+                ..Default::default()
+            };
+            export::write_notes_pdf(out, title, &notes, &options)
+        }
+    }
+}
+
+async fn publish_drafts(
+    store: &Store,
+    providers: &Providers,
+    profile: &Profile,
+    playlist_id: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let drafts: Vec<_> = store
+        .list_drafts(playlist_id)?
+        .into_iter()
+        .filter(|draft| draft.status == "draft")
+        .collect();
+    let locked = drafts.iter().filter(|draft| draft.locked).count();
+    if locked > 0 {
+        eprintln!("Skipping {locked} drafts in locked vaults; unlock them in the app first");
+    }
+    let drafts: Vec<_> = drafts.into_iter().filter(|draft| !draft.locked).collect();
+    if dry_run {
+        for draft in &drafts {
+            println!("{}\t{}", draft.playlist_id, draft.version_id);
+        }
+        println!("{} drafts would be published", drafts.len());
+        return Ok(());
+    }
+
+    let notes: Vec<NewNote> = drafts
+        .iter()
+        .map(|draft| NewNote {
+            version_id: draft.version_id.clone(),
+            content: draft.content.clone(),
+            subject: None,
+        })
+        .collect();
+    // Nothing is published if a hook rejects the batch, as in the app's queue
+    pipeline_hooks::before_publish_standalone(&providers::publish_payload(profile, &notes)).await?;
+
+    let mut failed = 0;
+    for (draft, note) in drafts.iter().zip(notes) {
+        match providers.publish(profile, note).await {
+            Ok(note) => {
+                store.mark_draft_published(&draft.playlist_id, &draft.version_id)?;
+                let entry = providers::published_entry(profile, &draft.version_id, &note);
+                if let Err(err) = store.append_audit_entry(entry) {
+                    eprintln!("warning: failed to write audit log entry: {err}");
+                }
+                println!("Published note {} on version {}", note.id, draft.version_id);
+            }
+            Err(err) => {
+                failed += 1;
+                eprintln!("Failed to publish on version {}: {err}", draft.version_id);
+            }
+        }
+    }
+    println!(
+        "Published {} of {} drafts",
+        drafts.len() - failed,
+        drafts.len()
+    );
+    if failed > 0 {
+        return Err(Error::InvalidInput(format!(
+            "{failed} drafts failed to publish"
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,263 @@
+//! Native clipboard access for images and formatted notes.
+//!
+//! The webview clipboard cannot read pasted screenshots as files or put an
+//! image and formatted text on the clipboard together, which is what "copy
+//! note with thumbnail" needs to paste properly into email. Notes are copied
+//! as HTML with thumbnails embedded inline, plus a plain-text alternative for
+//! targets that do not take HTML. Both Outlook and Apple Mail paste the HTML
+//! flavor, so no RTF is written.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::{Error, Result};
+use crate::export::ReportNote;
+use crate::paths;
+
+const PASTE_DIR: &str = "clipboard";
+/// Embedded thumbnails are scaled to fit this width to keep the HTML small.
+const THUMBNAIL_WIDTH: u32 = 320;
+
+/// Holds the clipboard open, since on Linux its contents are served by
+/// this process and would vanish with the last handle.
+#[derive(Default)]
+pub struct SystemClipboard {
+    clipboard: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl SystemClipboard {
+    fn with<T>(&self, f: impl FnOnce(&mut arboard::Clipboard) -> Result<T>) -> Result<T> {
+        let mut clipboard = self
+            .clipboard
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let clipboard = match clipboard.as_mut() {
+            Some(clipboard) => clipboard,
+            None => clipboard.insert(arboard::Clipboard::new()?),
+        };
+        f(clipboard)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardImage {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\n' => escaped.push_str("<br>"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Scales a thumbnail down and encodes it as a JPEG data URI.
+fn embed_thumbnail(path: &Path) -> Result<(String, u32, u32)> {
+    let image = image::open(path)?;
+    let image = if image.width() > THUMBNAIL_WIDTH {
+        image.resize(
+            THUMBNAIL_WIDTH,
+            u32::MAX,
+            image::imageops::FilterType::Triangle,
+        )
+    } else {
+        image
+    };
+    let (width, height) = (image.width(), image.height());
+    let mut jpeg = Cursor::new(Vec::new());
+    image
+        .into_rgb8()
+        .write_to(&mut jpeg, image::ImageFormat::Jpeg)?;
+    let src = format!("data:image/jpeg;base64,{}", STANDARD.encode(jpeg.get_ref()));
+    Ok((src, width, height))
+}
+
+fn heading(note: &ReportNote) -> String {
+    let mut heading = format!("{} v{}", note.row.version_name, note.row.version_number);
+    if let Some(status) = &note.version_status {
+        let _ = write!(heading, " ({status})");
+    }
+    heading
+}
+
+fn byline(note: &ReportNote) -> Option<String> {
+    let mut parts = Vec::new();
+    if !note.row.labels.is_empty() {
+        parts.push(note.row.labels.join(", "));
+    }
+    if let Some(author) = &note.row.author {
+        parts.push(author.clone());
+    }
+    if let Some(frame) = note.row.frame_number {
+        parts.push(format!("frame {frame}"));
+    }
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// Renders notes as an email-friendly HTML fragment, laid out with tables.
+fn render_html(notes: &[ReportNote], thumbnails: bool) -> String {
+    let mut html = String::from(
+        "<div style=\"font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;font-size:14px\">",
+    );
+    for note in notes {
+        html.push_str(
+            "<table cellpadding=\"0\" cellspacing=\"0\" style=\"margin-bottom:16px\"><tr>",
+        );
+        let embedded = note
+            .thumbnail_path
+            .as_deref()
+            .filter(|_| thumbnails)
+            .and_then(|path| {
+                embed_thumbnail(path)
+                    .inspect_err(|err| {
+                        log::warn!("Not embedding thumbnail {}: {err}", path.display())
+                    })
+                    .ok()
+            });
+        if let Some((src, width, height)) = embedded {
+            let _ = write!(
+                html,
+                "<td style=\"padding-right:12px;vertical-align:top\"><img src=\"{src}\" width=\"{width}\" height=\"{height}\" alt=\"{}\"></td>",
+                escape_html(&note.row.version_name)
+            );
+        }
+        let _ = write!(
+            html,
+            "<td style=\"vertical-align:top\"><b>{}</b>",
+            escape_html(&heading(note))
+        );
+        if let Some(byline) = byline(note) {
+            let _ = write!(
+                html,
+                "<br><span style=\"color:#666666\">{}</span>",
+                escape_html(&byline)
+            );
+        }
+        let _ = write!(
+            html,
+            "<p style=\"margin:6px 0 0 0\">{}</p></td></tr></table>",
+            escape_html(note.row.content.trim())
+        );
+    }
+    html.push_str("</div>");
+    html
+}
+
+/// The notes as plain text, one block per note.
+pub(crate) fn render_text(notes: &[ReportNote]) -> String {
+    notes
+        .iter()
+        .map(|note| {
+            let mut text = heading(note);
+            if let Some(byline) = byline(note) {
+                let _ = write!(text, "\n{byline}");
+            }
+            let _ = write!(text, "\n{}", note.row.content.trim());
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Saves an image on the clipboard as a PNG for attaching, or returns
+/// `None` when the clipboard holds no image.
+#[tauri::command]
+pub async fn read_clipboard_image(app: AppHandle) -> Result<Option<ClipboardImage>> {
+    let dir = paths::cache_dir(&app)?.join(PASTE_DIR);
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = match app
+            .state::<SystemClipboard>()
+            .with(|clipboard| Ok(clipboard.get_image()))?
+        {
+            Ok(image) => image,
+            Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let (width, height) = (image.width as u32, image.height as u32);
+        let buffer = image::RgbaImage::from_raw(width, height, image.bytes.into_owned())
+            .ok_or_else(|| Error::InvalidInput("Unreadable clipboard image".into()))?;
+        std::fs::create_dir_all(&dir)?;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+        let path = dir.join(format!("paste-{stamp}.png"));
+        buffer.save_with_format(&path, image::ImageFormat::Png)?;
+        Ok(Some(ClipboardImage {
+            path,
+            width,
+            height,
+        }))
+    })
+    .await?
+}
+
+/// Deletes `path` if it is an image saved by [`read_clipboard_image`], once
+/// it has been uploaded and is no longer needed.
+pub(crate) fn remove_pasted(app: &AppHandle, path: &Path) {
+    let Ok(dir) = paths::cache_dir(app).and_then(|dir| Ok(dir.join(PASTE_DIR).canonicalize()?))
+    else {
+        return;
+    };
+    let Ok(path) = path.canonicalize() else {
+        return;
+    };
+    if path.parent() != Some(dir.as_path()) {
+        return;
+    }
+    if let Err(err) = std::fs::remove_file(&path) {
+        log::warn!("Failed to remove pasted image {}: {err}", path.display());
+    }
+}
+
+#[tauri::command]
+pub async fn write_clipboard_image(app: AppHandle, path: PathBuf) -> Result<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = image::open(&path)?.into_rgba8();
+        let data = arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: Cow::Owned(image.into_raw()),
+        };
+        app.state::<SystemClipboard>()
+            .with(|clipboard| Ok(clipboard.set_image(data)?))
+    })
+    .await?
+}
+
+/// Copies notes as formatted HTML with a plain-text alternative, with
+/// their thumbnails inline unless `include_thumbnails` is `false`.
+#[tauri::command]
+pub async fn copy_notes_to_clipboard(
+    app: AppHandle,
+    notes: Vec<ReportNote>,
+    include_thumbnails: Option<bool>,
+) -> Result<()> {
+    if notes.is_empty() {
+        return Err(Error::InvalidInput("No notes to copy".into()));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let html = render_html(&notes, include_thumbnails.unwrap_or(true));
+        let text = render_text(&notes);
+        app.state::<SystemClipboard>()
+            .with(|clipboard| Ok(clipboard.set_html(html, Some(text))?))
+    })
+    .await?
+}
@@ -0,0 +1,210 @@
+//! Adobe/Resolve `.cube` LUTs, 1D and 3D, also used to bake OCIO transforms.
+
+use std::path::Path;
+
+use image::RgbImage;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone)]
+enum Table {
+    /// Per-channel curves with `size` entries.
+    OneD(Vec<[f32; 3]>),
+    /// `size`³ lattice, red varying fastest.
+    ThreeD { size: usize, values: Vec<[f32; 3]> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Lut {
+    table: Table,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+}
+
+fn floats<const N: usize>(fields: &[&str], line: usize) -> Result<[f32; N]> {
+    let invalid = || Error::InvalidInput(format!("Invalid .cube data on line {line}"));
+    if fields.len() != N {
+        return Err(invalid());
+    }
+    let mut values = [0.0; N];
+    for (value, field) in values.iter_mut().zip(fields) {
+        *value = field.parse().map_err(|_| invalid())?;
+    }
+    Ok(values)
+}
+
+impl Lut {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut size_1d = None;
+        let mut size_3d = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut values = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[0] {
+                "LUT_1D_SIZE" => {
+                    size_1d = Some(floats::<1>(&fields[1..], line_number)?[0] as usize)
+                }
+                "LUT_3D_SIZE" => {
+                    size_3d = Some(floats::<1>(&fields[1..], line_number)?[0] as usize)
+                }
+                "DOMAIN_MIN" => domain_min = floats(&fields[1..], line_number)?,
+                "DOMAIN_MAX" => domain_max = floats(&fields[1..], line_number)?,
+                // Resolve's shorthand for a uniform domain
+                "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                    let [min, max] = floats(&fields[1..], line_number)?;
+                    domain_min = [min; 3];
+                    domain_max = [max; 3];
+                }
+                keyword if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    log::debug!("Ignoring .cube keyword {keyword}");
+                }
+                _ => values.push(floats::<3>(&fields, line_number)?),
+            }
+        }
+
+        let table = match (size_1d, size_3d) {
+            (Some(size), None) if size >= 2 && values.len() == size => Table::OneD(values),
+            (None, Some(size)) if size >= 2 && values.len() == size * size * size => {
+                Table::ThreeD { size, values }
+            }
+            _ => {
+                return Err(Error::InvalidInput(format!(
+                    "Malformed .cube LUT: {} entries for the declared size",
+                    values.len()
+                )));
+            }
+        };
+        if (0..3).any(|channel| domain_max[channel] <= domain_min[channel]) {
+            return Err(Error::InvalidInput(
+                "Malformed .cube LUT: empty domain".into(),
+            ));
+        }
+        Ok(Self {
+            table,
+            domain_min,
+            domain_max,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub(crate) fn one_d(values: Vec<[f32; 3]>, domain_min: f32, domain_max: f32) -> Self {
+        Self {
+            table: Table::OneD(values),
+            domain_min: [domain_min; 3],
+            domain_max: [domain_max; 3],
+        }
+    }
+
+    pub(crate) fn three_d(size: usize, values: Vec<[f32; 3]>) -> Self {
+        Self {
+            table: Table::ThreeD { size, values },
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+        }
+    }
+
+    /// Samples `transform` on a `size`³ lattice over the unit cube.
+    pub(crate) fn bake(size: usize, transform: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        let last = (size - 1) as f32;
+        let mut values = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    values.push(transform([
+                        r as f32 / last,
+                        g as f32 / last,
+                        b as f32 / last,
+                    ]));
+                }
+            }
+        }
+        Self::three_d(size, values)
+    }
+
+    /// Maps one RGB triplet; inputs outside the domain are clamped.
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let mut normalized = [0.0; 3];
+        for channel in 0..3 {
+            let range = self.domain_max[channel] - self.domain_min[channel];
+            normalized[channel] =
+                ((rgb[channel] - self.domain_min[channel]) / range).clamp(0.0, 1.0);
+        }
+        match &self.table {
+            Table::OneD(values) => {
+                let last = (values.len() - 1) as f32;
+                let mut out = [0.0; 3];
+                for channel in 0..3 {
+                    let position = normalized[channel] * last;
+                    let low = position.floor() as usize;
+                    let high = (low + 1).min(values.len() - 1);
+                    let t = position - low as f32;
+                    out[channel] = values[low][channel] * (1.0 - t) + values[high][channel] * t;
+                }
+                out
+            }
+            Table::ThreeD { size, values } => trilinear(*size, values, normalized),
+        }
+    }
+
+    /// Applies the LUT to an 8-bit image in place.
+    pub fn apply_to_image(&self, image: &mut RgbImage) {
+        for pixel in image.pixels_mut() {
+            let rgb = pixel.0.map(|channel| f32::from(channel) / 255.0);
+            let mapped = self.apply(rgb);
+            pixel.0 = mapped.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+}
+
+fn trilinear(size: usize, values: &[[f32; 3]], rgb: [f32; 3]) -> [f32; 3] {
+    let last = (size - 1) as f32;
+    let mut low = [0; 3];
+    let mut high = [0; 3];
+    let mut t = [0.0; 3];
+    for channel in 0..3 {
+        let position = rgb[channel] * last;
+        low[channel] = position.floor() as usize;
+        high[channel] = (low[channel] + 1).min(size - 1);
+        t[channel] = position - low[channel] as f32;
+    }
+    let at = |r: usize, g: usize, b: usize| values[r + g * size + b * size * size];
+    let lerp = |a: [f32; 3], b: [f32; 3], t: f32| {
+        [
+            a[0] + (b[0] - a[0]) * t,
+            a[1] + (b[1] - a[1]) * t,
+            a[2] + (b[2] - a[2]) * t,
+        ]
+    };
+    let c00 = lerp(
+        at(low[0], low[1], low[2]),
+        at(high[0], low[1], low[2]),
+        t[0],
+    );
+    let c10 = lerp(
+        at(low[0], high[1], low[2]),
+        at(high[0], high[1], low[2]),
+        t[0],
+    );
+    let c01 = lerp(
+        at(low[0], low[1], high[2]),
+        at(high[0], low[1], high[2]),
+        t[0],
+    );
+    let c11 = lerp(
+        at(low[0], high[1], high[2]),
+        at(high[0], high[1], high[2]),
+        t[0],
+    );
+    lerp(lerp(c00, c10, t[1]), lerp(c01, c11, t[1]), t[2])
+}
@@ -0,0 +1,217 @@
+//! Per-project color transforms for generated imagery.
+//!
+//! Thumbnails and stills are usually encoded in a camera log space and look
+//! washed out as-is. A project can be assigned a `.cube` LUT or an OCIO
+//! colorspace and display/view, which is then applied whenever thumbnails are
+//! cached or frames are extracted for it. OCIO takes precedence when both are
+//! set. Assignments are kept in `color.json` in the app config directory.
+
+pub mod lut;
+pub mod ocio;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::error::Result;
+use crate::paths;
+use lut::Lut;
+use ocio::ConfigInfo;
+
+const SETTINGS_FILE: &str = "color.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProjectColor {
+    pub lut_path: Option<PathBuf>,
+    pub ocio: Option<OcioSelection>,
+}
+
+impl ProjectColor {
+    fn is_empty(&self) -> bool {
+        self.lut_path.is_none() && self.ocio.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OcioSelection {
+    pub config_path: PathBuf,
+    /// Colorspace the source imagery is encoded in, e.g. `ACEScg`.
+    pub colorspace: String,
+    pub display: String,
+    pub view: String,
+}
+
+impl OcioSelection {
+    fn bake(&self) -> Result<Lut> {
+        let config = ocio::Config::load(&self.config_path)?;
+        Ok(config
+            .display_view(&self.colorspace, &self.display, &self.view)?
+            .bake())
+    }
+}
+
+#[derive(Default)]
+pub struct ColorManager {
+    projects: RwLock<HashMap<String, ProjectColor>>,
+    /// Parsed LUTs by path, so large 3D tables are read once.
+    luts: Mutex<HashMap<PathBuf, Arc<Lut>>>,
+    /// OCIO display/view chains baked into LUTs.
+    baked: Mutex<HashMap<OcioSelection, Arc<Lut>>>,
+}
+
+impl ColorManager {
+    pub fn load(app: &AppHandle) -> Result<Self> {
+        let projects = paths::load_json(app, SETTINGS_FILE)?;
+        Ok(Self {
+            projects: RwLock::new(projects),
+            luts: Mutex::new(HashMap::new()),
+            baked: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn project(&self, project_id: &str) -> ProjectColor {
+        self.projects
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(project_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn cached_lut(&self, path: &PathBuf) -> Result<Arc<Lut>> {
+        let mut luts = self
+            .luts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(lut) = luts.get(path) {
+            return Ok(lut.clone());
+        }
+        let lut = Arc::new(Lut::load(path)?);
+        luts.insert(path.clone(), lut.clone());
+        Ok(lut)
+    }
+
+    /// Drops parsed and baked LUTs; they are rebuilt on next use. Returns how many were dropped.
+    pub fn clear_lut_caches(&self) -> usize {
+        let mut luts = self
+            .luts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut baked = self
+            .baked
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let cleared = luts.len() + baked.len();
+        luts.clear();
+        baked.clear();
+        cleared
+    }
+
+    fn baked_lut(&self, selection: &OcioSelection) -> Result<Arc<Lut>> {
+        let mut baked = self
+            .baked
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(lut) = baked.get(selection) {
+            return Ok(lut.clone());
+        }
+        let lut = Arc::new(selection.bake()?);
+        baked.insert(selection.clone(), lut.clone());
+        Ok(lut)
+    }
+
+    /// The transform assigned to `project_id` as a LUT, if any.
+    pub fn transform_for(&self, project_id: Option<&str>) -> Result<Option<Arc<Lut>>> {
+        let Some(project_id) = project_id else {
+            return Ok(None);
+        };
+        let project = self.project(project_id);
+        match (project.ocio, project.lut_path) {
+            (Some(selection), _) => self.baked_lut(&selection).map(Some),
+            (None, Some(path)) => self.cached_lut(&path).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn update(
+        &self,
+        app: &AppHandle,
+        project_id: String,
+        change: impl FnOnce(&mut ProjectColor),
+    ) -> Result<()> {
+        let mut projects = self
+            .projects
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut updated = projects.clone();
+        let project = updated.entry(project_id.clone()).or_default();
+        change(project);
+        if project.is_empty() {
+            updated.remove(&project_id);
+        }
+        // Saved first, so a refused change is not applied either
+        paths::save_json(app, SETTINGS_FILE, &updated)?;
+        *projects = updated;
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub fn get_project_color(color: State<'_, ColorManager>, project_id: String) -> ProjectColor {
+    color.project(&project_id)
+}
+
+/// Assigns a `.cube` LUT to a project, or clears it with `None`. The LUT is
+/// parsed first so a broken file is rejected immediately. Thumbnails already
+/// cached keep their old look until evicted.
+#[tauri::command]
+pub fn set_project_lut(
+    app: AppHandle,
+    color: State<'_, ColorManager>,
+    project_id: String,
+    lut_path: Option<PathBuf>,
+) -> Result<()> {
+    if let Some(path) = &lut_path {
+        // Reload even if cached, in case the file was edited
+        let lut = Arc::new(Lut::load(path)?);
+        color
+            .luts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(path.clone(), lut);
+    }
+    color.update(&app, project_id, |project| project.lut_path = lut_path)
+}
+
+/// Lists the colorspaces, roles and display/views of an OCIO config.
+#[tauri::command]
+pub fn read_ocio_config(config_path: PathBuf) -> Result<ConfigInfo> {
+    Ok(ocio::Config::load(&config_path)?.info())
+}
+
+/// Assigns an OCIO colorspace and display/view to a project, or clears it
+/// with `None`. The chain is resolved and baked up front, so unknown names or
+/// unsupported transforms are reported here rather than on the next thumbnail.
+#[tauri::command]
+pub fn set_project_ocio(
+    app: AppHandle,
+    color: State<'_, ColorManager>,
+    project_id: String,
+    selection: Option<OcioSelection>,
+) -> Result<()> {
+    if let Some(selection) = &selection {
+        // Rebake even if cached, in case the config or its LUTs were edited
+        let lut = Arc::new(selection.bake()?);
+        color
+            .baked
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(selection.clone(), lut);
+    }
+    color.update(&app, project_id, |project| project.ocio = selection)
+}
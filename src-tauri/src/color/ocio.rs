@@ -0,0 +1,880 @@
+//! OpenColorIO config support.
+//!
+//! Parses enough of a v1 or v2 config to resolve a colorspace to display/view
+//! chain built from analytic transforms and LUT files, then bakes the chain
+//! into a 3D LUT so it runs through the same path as a plain `.cube` file.
+//! Looks are not applied, and `BuiltinTransform`s or other unsupported
+//! transforms are rejected with an error naming them.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_yaml::Value;
+
+use super::lut::Lut;
+use crate::error::{Error, Result};
+
+/// Lattice size for baked processors, the usual size for display LUTs.
+const BAKE_SIZE: usize = 33;
+/// Bound on nested `ColorSpaceTransform`s, so cyclic configs fail cleanly.
+const MAX_DEPTH: usize = 8;
+/// Rec. 709 luma weights, as used by the CDL saturation step.
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+// serde_yaml drops verbatim `!<Tag>` tags, which is how OCIO writes them
+static VERBATIM_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!<(\w+)>").expect("valid tag pattern"));
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidInput(message.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reference {
+    Scene,
+    Display,
+}
+
+#[derive(Debug)]
+struct ColorSpace {
+    name: String,
+    aliases: Vec<String>,
+    reference: Reference,
+    to_reference: Option<Value>,
+    from_reference: Option<Value>,
+}
+
+/// Maps its reference space to the display reference.
+#[derive(Debug)]
+struct ViewTransform {
+    name: String,
+    reference: Reference,
+    to_reference: Option<Value>,
+    from_reference: Option<Value>,
+}
+
+#[derive(Debug, Clone)]
+struct View {
+    name: String,
+    /// v1 views, and v2 views that name a colorspace directly.
+    colorspace: Option<String>,
+    view_transform: Option<String>,
+    display_colorspace: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    search_paths: Vec<PathBuf>,
+    roles: BTreeMap<String, String>,
+    colorspaces: Vec<ColorSpace>,
+    displays: Vec<(String, Vec<View>)>,
+    view_transforms: Vec<ViewTransform>,
+    default_view_transform: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayInfo {
+    pub name: String,
+    pub views: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigInfo {
+    pub colorspaces: Vec<String>,
+    pub roles: BTreeMap<String, String>,
+    pub displays: Vec<DisplayInfo>,
+}
+
+fn untag(value: &Value) -> (String, &Value) {
+    match value {
+        Value::Tagged(tagged) => (
+            tagged.tag.to_string().trim_start_matches('!').to_string(),
+            &tagged.value,
+        ),
+        value => (String::new(), value),
+    }
+}
+
+fn string(map: &Value, key: &str) -> Option<String> {
+    map.get(key)
+        .map(|value| untag(value).1)
+        .and_then(|value| match value {
+            Value::String(text) => Some(text.clone()),
+            Value::Number(number) => Some(number.to_string()),
+            _ => None,
+        })
+}
+
+fn number(map: &Value, key: &str) -> Option<f32> {
+    map.get(key)
+        .and_then(Value::as_f64)
+        .map(|value| value as f32)
+}
+
+fn numbers(map: &Value, key: &str) -> Option<Vec<f32>> {
+    match map.get(key)? {
+        Value::Sequence(values) => values
+            .iter()
+            .map(|value| value.as_f64().map(|value| value as f32))
+            .collect(),
+        value => value.as_f64().map(|value| vec![value as f32]),
+    }
+}
+
+/// A per-channel parameter written as a scalar or as an RGB(A) list.
+fn channels(map: &Value, key: &str, default: f32) -> Result<[f32; 3]> {
+    match numbers(map, key).as_deref() {
+        None => Ok([default; 3]),
+        Some([value]) => Ok([*value; 3]),
+        Some([r, g, b, ..]) => Ok([*r, *g, *b]),
+        Some(_) => Err(invalid(format!("Invalid OCIO parameter \"{key}\""))),
+    }
+}
+
+fn sequence(value: Option<&Value>) -> &[Value] {
+    match value.map(|value| untag(value).1) {
+        Some(Value::Sequence(values)) => values,
+        _ => &[],
+    }
+}
+
+fn is_inverse(map: &Value) -> bool {
+    string(map, "direction").is_some_and(|direction| direction == "inverse")
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text, path.parent().unwrap_or(Path::new(".")))
+    }
+
+    fn parse(text: &str, dir: &Path) -> Result<Self> {
+        let text = VERBATIM_TAG.replace_all(text, "!$1");
+        let root: Value = serde_yaml::from_str(&text)
+            .map_err(|err| invalid(format!("Invalid OCIO config: {err}")))?;
+        if root.get("ocio_profile_version").is_none() {
+            return Err(invalid(
+                "Not an OCIO config, ocio_profile_version is missing",
+            ));
+        }
+
+        let search_paths = match root.get("search_path") {
+            Some(Value::String(paths)) => paths.split(':').map(|path| dir.join(path)).collect(),
+            Some(Value::Sequence(paths)) => paths
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|path| dir.join(path))
+                .collect(),
+            _ => vec![dir.to_path_buf()],
+        };
+
+        let roles = root
+            .get("roles")
+            .and_then(Value::as_mapping)
+            .map(|roles| {
+                roles
+                    .iter()
+                    .filter_map(|(role, space)| {
+                        Some((role.as_str()?.to_string(), space.as_str()?.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut colorspaces = Vec::new();
+        for (key, reference) in [
+            ("colorspaces", Reference::Scene),
+            ("display_colorspaces", Reference::Display),
+        ] {
+            for entry in sequence(root.get(key)) {
+                let entry = untag(entry).1;
+                let Some(name) = string(entry, "name") else {
+                    return Err(invalid(format!("OCIO {key} entry without a name")));
+                };
+                let (to_key, from_key) = match reference {
+                    Reference::Scene => ("to_scene_reference", "from_scene_reference"),
+                    Reference::Display => ("to_display_reference", "from_display_reference"),
+                };
+                colorspaces.push(ColorSpace {
+                    name,
+                    aliases: sequence(entry.get("aliases"))
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect(),
+                    reference,
+                    // v1 configs only have the generic keys
+                    to_reference: entry.get(to_key).or(entry.get("to_reference")).cloned(),
+                    from_reference: entry.get(from_key).or(entry.get("from_reference")).cloned(),
+                });
+            }
+        }
+
+        let view = |entry: &Value| View {
+            name: string(entry, "name").unwrap_or_default(),
+            colorspace: string(entry, "colorspace"),
+            view_transform: string(entry, "view_transform"),
+            display_colorspace: string(entry, "display_colorspace"),
+        };
+        let shared_views: Vec<View> = sequence(root.get("shared_views"))
+            .iter()
+            .map(|entry| view(untag(entry).1))
+            .collect();
+
+        let mut displays = Vec::new();
+        if let Some(entries) = root.get("displays").and_then(Value::as_mapping) {
+            for (name, entries) in entries {
+                let Some(name) = name.as_str() else {
+                    continue;
+                };
+                let mut views = Vec::new();
+                for entry in sequence(Some(entries)) {
+                    match untag(entry) {
+                        (tag, Value::Sequence(names)) if tag == "Views" => {
+                            for shared in names.iter().filter_map(Value::as_str) {
+                                let Some(view) =
+                                    shared_views.iter().find(|view| view.name == shared)
+                                else {
+                                    return Err(invalid(format!(
+                                        "Unknown OCIO shared view \"{shared}\""
+                                    )));
+                                };
+                                let mut view = view.clone();
+                                if view.display_colorspace.as_deref() == Some("<USE_DISPLAY_NAME>")
+                                {
+                                    view.display_colorspace = Some(name.to_string());
+                                }
+                                views.push(view);
+                            }
+                        }
+                        (_, entry) => views.push(view(entry)),
+                    }
+                }
+                displays.push((name.to_string(), views));
+            }
+        }
+
+        let view_transforms = sequence(root.get("view_transforms"))
+            .iter()
+            .map(|entry| {
+                let entry = untag(entry).1;
+                let scene = entry
+                    .get("to_scene_reference")
+                    .or(entry.get("from_scene_reference"));
+                ViewTransform {
+                    name: string(entry, "name").unwrap_or_default(),
+                    reference: if scene.is_some() {
+                        Reference::Scene
+                    } else {
+                        Reference::Display
+                    },
+                    to_reference: entry
+                        .get("to_scene_reference")
+                        .or(entry.get("to_display_reference"))
+                        .cloned(),
+                    from_reference: entry
+                        .get("from_scene_reference")
+                        .or(entry.get("from_display_reference"))
+                        .cloned(),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            search_paths,
+            roles,
+            colorspaces,
+            displays,
+            view_transforms,
+            default_view_transform: string(&root, "default_view_transform"),
+        })
+    }
+
+    pub fn info(&self) -> ConfigInfo {
+        ConfigInfo {
+            colorspaces: self
+                .colorspaces
+                .iter()
+                .map(|space| space.name.clone())
+                .collect(),
+            roles: self.roles.clone(),
+            displays: self
+                .displays
+                .iter()
+                .map(|(name, views)| DisplayInfo {
+                    name: name.clone(),
+                    views: views.iter().map(|view| view.name.clone()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Looks up a colorspace by name, alias or role, ignoring case like OCIO.
+    fn colorspace(&self, name: &str) -> Result<&ColorSpace> {
+        let name = self
+            .roles
+            .iter()
+            .find(|(role, _)| role.eq_ignore_ascii_case(name))
+            .map_or(name, |(_, space)| space.as_str());
+        self.colorspaces
+            .iter()
+            .find(|space| {
+                space.name.eq_ignore_ascii_case(name)
+                    || space
+                        .aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| invalid(format!("Unknown OCIO colorspace \"{name}\"")))
+    }
+
+    fn view_transform(&self, name: &str) -> Result<&ViewTransform> {
+        self.view_transforms
+            .iter()
+            .find(|transform| transform.name == name)
+            .ok_or_else(|| invalid(format!("Unknown OCIO view transform \"{name}\"")))
+    }
+
+    /// The view transform used to cross between scene and display references
+    /// when a conversion does not name one.
+    fn default_view_transform(&self) -> Result<&ViewTransform> {
+        match &self.default_view_transform {
+            Some(name) => self.view_transform(name),
+            None => self
+                .view_transforms
+                .iter()
+                .find(|transform| transform.reference == Reference::Scene)
+                .ok_or_else(|| invalid("OCIO config has no scene-referred view transform")),
+        }
+    }
+
+    /// Builds the processor for viewing `input` on `display` through `view`.
+    pub fn display_view(&self, input: &str, display: &str, view: &str) -> Result<Processor> {
+        let Some((_, views)) = self.displays.iter().find(|(name, _)| name == display) else {
+            return Err(invalid(format!("Unknown OCIO display \"{display}\"")));
+        };
+        let Some(view) = views.iter().find(|candidate| candidate.name == view) else {
+            return Err(invalid(format!(
+                "Display \"{display}\" has no view \"{view}\""
+            )));
+        };
+
+        let mut ops = Vec::new();
+        match (
+            &view.colorspace,
+            &view.view_transform,
+            &view.display_colorspace,
+        ) {
+            (Some(colorspace), _, _) => self.convert(input, colorspace, 0, &mut ops)?,
+            (None, Some(transform), Some(display_colorspace)) => {
+                let source = self.colorspace(input)?;
+                let transform = self.view_transform(transform)?;
+                let target = self.colorspace(display_colorspace)?;
+                self.push_to_reference(source, 0, &mut ops)?;
+                if source.reference != transform.reference {
+                    self.cross(source.reference, 0, &mut ops)?;
+                }
+                self.apply_view_transform(transform, false, 0, &mut ops)?;
+                self.push_from_reference(target, 0, &mut ops)?;
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "OCIO view \"{}\" has no colorspace",
+                    view.name
+                )));
+            }
+        }
+        Ok(Processor { ops })
+    }
+
+    fn convert(&self, src: &str, dst: &str, depth: usize, ops: &mut Vec<Op>) -> Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("OCIO colorspace transforms nest too deeply"));
+        }
+        let source = self.colorspace(src)?;
+        let target = self.colorspace(dst)?;
+        self.push_to_reference(source, depth, ops)?;
+        if source.reference != target.reference {
+            self.cross(source.reference, depth, ops)?;
+        }
+        self.push_from_reference(target, depth, ops)
+    }
+
+    /// Moves from `from` to the other reference through the default view transform.
+    fn cross(&self, from: Reference, depth: usize, ops: &mut Vec<Op>) -> Result<()> {
+        let transform = self.default_view_transform()?;
+        self.apply_view_transform(transform, from == Reference::Display, depth, ops)
+    }
+
+    fn apply_view_transform(
+        &self,
+        transform: &ViewTransform,
+        inverse: bool,
+        depth: usize,
+        ops: &mut Vec<Op>,
+    ) -> Result<()> {
+        match (&transform.from_reference, &transform.to_reference) {
+            (Some(from), _) => self.transform(from, inverse, depth, ops),
+            (None, Some(to)) => self.transform(to, !inverse, depth, ops),
+            (None, None) => Ok(()),
+        }
+    }
+
+    fn push_to_reference(&self, space: &ColorSpace, depth: usize, ops: &mut Vec<Op>) -> Result<()> {
+        match (&space.to_reference, &space.from_reference) {
+            (Some(to), _) => self.transform(to, false, depth, ops),
+            (None, Some(from)) => self.transform(from, true, depth, ops),
+            (None, None) => Ok(()),
+        }
+    }
+
+    fn push_from_reference(
+        &self,
+        space: &ColorSpace,
+        depth: usize,
+        ops: &mut Vec<Op>,
+    ) -> Result<()> {
+        match (&space.from_reference, &space.to_reference) {
+            (Some(from), _) => self.transform(from, false, depth, ops),
+            (None, Some(to)) => self.transform(to, true, depth, ops),
+            (None, None) => Ok(()),
+        }
+    }
+
+    fn transform(
+        &self,
+        value: &Value,
+        inverse: bool,
+        depth: usize,
+        ops: &mut Vec<Op>,
+    ) -> Result<()> {
+        let (kind, params) = untag(value);
+        let inverse = inverse != is_inverse(params);
+        match kind.as_str() {
+            "GroupTransform" => {
+                let children = sequence(params.get("children"));
+                if inverse {
+                    for child in children.iter().rev() {
+                        self.transform(child, true, depth, ops)?;
+                    }
+                } else {
+                    for child in children {
+                        self.transform(child, false, depth, ops)?;
+                    }
+                }
+            }
+            "ColorSpaceTransform" => {
+                let (Some(src), Some(dst)) = (string(params, "src"), string(params, "dst")) else {
+                    return Err(invalid("ColorSpaceTransform needs src and dst"));
+                };
+                let (src, dst) = if inverse { (dst, src) } else { (src, dst) };
+                self.convert(&src, &dst, depth + 1, ops)?;
+            }
+            "FileTransform" => {
+                let Some(src) = string(params, "src") else {
+                    return Err(invalid("FileTransform needs src"));
+                };
+                if inverse {
+                    return Err(invalid(format!(
+                        "Inverting LUT file \"{src}\" is not supported"
+                    )));
+                }
+                ops.push(Op::Lut(Arc::new(self.load_lut(&src)?)));
+            }
+            "MatrixTransform" => {
+                let matrix = numbers(params, "matrix").unwrap_or_else(|| {
+                    vec![
+                        1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0,
+                        1.0,
+                    ]
+                });
+                let offset = numbers(params, "offset").unwrap_or_else(|| vec![0.0; 4]);
+                if matrix.len() != 16 || offset.len() != 4 {
+                    return Err(invalid("MatrixTransform needs a 4x4 matrix and 4 offsets"));
+                }
+                let m = [
+                    [matrix[0], matrix[1], matrix[2]],
+                    [matrix[4], matrix[5], matrix[6]],
+                    [matrix[8], matrix[9], matrix[10]],
+                ];
+                let offset = [offset[0], offset[1], offset[2]];
+                ops.push(if inverse {
+                    invert_matrix(m, offset)?
+                } else {
+                    Op::Matrix { m, offset }
+                });
+            }
+            "ExponentTransform" => {
+                let value = channels(params, "value", 1.0)?;
+                ops.push(Op::Exponent(if inverse {
+                    value.map(|v| 1.0 / v)
+                } else {
+                    value
+                }));
+            }
+            "ExponentWithLinearTransform" => ops.push(Op::MonCurve {
+                gamma: channels(params, "gamma", 1.0)?,
+                offset: channels(params, "offset", 0.0)?,
+                inverse,
+            }),
+            "LogTransform" | "LogAffineTransform" => ops.push(Op::Log {
+                base: number(params, "base").unwrap_or(2.0),
+                log_slope: channels(params, "logSideSlope", 1.0)?,
+                log_offset: channels(params, "logSideOffset", 0.0)?,
+                lin_slope: channels(params, "linSideSlope", 1.0)?,
+                lin_offset: channels(params, "linSideOffset", 0.0)?,
+                inverse,
+            }),
+            "CDLTransform" => ops.push(Op::Cdl {
+                slope: channels(params, "slope", 1.0)?,
+                offset: channels(params, "offset", 0.0)?,
+                power: channels(params, "power", 1.0)?,
+                saturation: number(params, "sat").unwrap_or(1.0),
+                clamp: string(params, "style").is_none_or(|style| !style.starts_with("noClamp")),
+                inverse,
+            }),
+            "RangeTransform" => {
+                let range = [
+                    number(params, "min_in_value"),
+                    number(params, "max_in_value"),
+                    number(params, "min_out_value"),
+                    number(params, "max_out_value"),
+                ];
+                let [min_in, max_in, min_out, max_out] = if inverse {
+                    [range[2], range[3], range[0], range[1]]
+                } else {
+                    range
+                };
+                let scale = match (min_in, max_in, min_out, max_out) {
+                    (Some(min_in), Some(max_in), Some(min_out), Some(max_out))
+                        if max_in != min_in =>
+                    {
+                        (max_out - min_out) / (max_in - min_in)
+                    }
+                    _ => 1.0,
+                };
+                let offset = match (min_in, min_out, max_in, max_out) {
+                    (Some(min_in), Some(min_out), _, _) => min_out - min_in * scale,
+                    (_, _, Some(max_in), Some(max_out)) => max_out - max_in * scale,
+                    _ => 0.0,
+                };
+                let clamp = string(params, "style").is_none_or(|style| style != "noClamp");
+                ops.push(Op::Range {
+                    scale,
+                    offset,
+                    lower: min_out.filter(|_| clamp),
+                    upper: max_out.filter(|_| clamp),
+                });
+            }
+            "" => return Err(invalid("OCIO transform without a type tag")),
+            kind => return Err(invalid(format!("OCIO {kind} is not supported"))),
+        }
+        Ok(())
+    }
+
+    fn load_lut(&self, src: &str) -> Result<Lut> {
+        let path = self
+            .search_paths
+            .iter()
+            .map(|dir| dir.join(src))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                invalid(format!(
+                    "LUT file \"{src}\" not found on the OCIO search path"
+                ))
+            })?;
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "cube" => Lut::load(&path),
+            "spi1d" => parse_spi1d(&fs::read_to_string(&path)?),
+            "spi3d" => parse_spi3d(&fs::read_to_string(&path)?),
+            _ => Err(invalid(format!("Unsupported LUT format \"{src}\""))),
+        }
+    }
+}
+
+fn invert_matrix(m: [[f32; 3]; 3], offset: [f32; 3]) -> Result<Op> {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let determinant = m[0][0] * cofactor(1, 2, 1, 2) - m[0][1] * cofactor(1, 2, 0, 2)
+        + m[0][2] * cofactor(1, 2, 0, 1);
+    if determinant.abs() < 1e-12 {
+        return Err(invalid("OCIO matrix cannot be inverted"));
+    }
+    let inverse = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ]
+    .map(|row| row.map(|value| value / determinant));
+    let offset = multiply(&inverse, offset).map(|value| -value);
+    Ok(Op::Matrix { m: inverse, offset })
+}
+
+fn multiply(m: &[[f32; 3]; 3], rgb: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2])
+}
+
+fn spi_error() -> Error {
+    invalid("Invalid SPI LUT file")
+}
+
+fn parse_spi1d(text: &str) -> Result<Lut> {
+    let mut domain = (0.0, 1.0);
+    let mut components = 1;
+    let mut values = Vec::new();
+    let mut in_body = false;
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if in_body {
+            if line == "}" {
+                break;
+            }
+            let fields = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<std::result::Result<Vec<f32>, _>>()
+                .map_err(|_| spi_error())?;
+            values.push(match (components, fields.as_slice()) {
+                (1, [value]) => [*value; 3],
+                (3, [r, g, b]) => [*r, *g, *b],
+                _ => return Err(spi_error()),
+            });
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("From") => {
+                let mut bound = || {
+                    fields
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(spi_error)
+                };
+                domain = (bound()?, bound()?);
+            }
+            Some("Components") => {
+                components = fields
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(spi_error)?;
+            }
+            Some("{") => in_body = true,
+            _ => {}
+        }
+    }
+    if values.len() < 2 {
+        return Err(spi_error());
+    }
+    Ok(Lut::one_d(values, domain.0, domain.1))
+}
+
+fn parse_spi3d(text: &str) -> Result<Lut> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    if !lines.next().is_some_and(|line| line.starts_with("SPILUT")) {
+        return Err(spi_error());
+    }
+    lines.next();
+    let sizes = lines
+        .next()
+        .ok_or_else(spi_error)?
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<std::result::Result<Vec<usize>, _>>()
+        .map_err(|_| spi_error())?;
+    let size = match sizes.as_slice() {
+        [r, g, b] if r == g && g == b && *r >= 2 => *r,
+        _ => return Err(spi_error()),
+    };
+    let mut values = vec![[0.0; 3]; size * size * size];
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [r, g, b, red, green, blue] = fields.as_slice() else {
+            return Err(spi_error());
+        };
+        let index = |field: &str| {
+            field
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index < size)
+                .ok_or_else(spi_error)
+        };
+        let value = |field: &str| field.parse::<f32>().map_err(|_| spi_error());
+        values[index(r)? + index(g)? * size + index(b)? * size * size] =
+            [value(red)?, value(green)?, value(blue)?];
+    }
+    Ok(Lut::three_d(size, values))
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Matrix {
+        m: [[f32; 3]; 3],
+        offset: [f32; 3],
+    },
+    Exponent([f32; 3]),
+    /// Piecewise gamma with a linear toe, e.g. the sRGB curve. Forward decodes.
+    MonCurve {
+        gamma: [f32; 3],
+        offset: [f32; 3],
+        inverse: bool,
+    },
+    /// Forward encodes linear to log.
+    Log {
+        base: f32,
+        log_slope: [f32; 3],
+        log_offset: [f32; 3],
+        lin_slope: [f32; 3],
+        lin_offset: [f32; 3],
+        inverse: bool,
+    },
+    Cdl {
+        slope: [f32; 3],
+        offset: [f32; 3],
+        power: [f32; 3],
+        saturation: f32,
+        clamp: bool,
+        inverse: bool,
+    },
+    Range {
+        scale: f32,
+        offset: f32,
+        lower: Option<f32>,
+        upper: Option<f32>,
+    },
+    Lut(Arc<Lut>),
+}
+
+fn mon_curve(x: f32, gamma: f32, offset: f32, inverse: bool) -> f32 {
+    if gamma <= 1.0 || offset <= 0.0 {
+        return if inverse {
+            x.max(0.0).powf(1.0 / gamma)
+        } else {
+            x.max(0.0).powf(gamma)
+        };
+    }
+    let encoded_break = offset / (gamma - 1.0);
+    let slope = ((gamma - 1.0) / offset).powf(gamma - 1.0) * ((1.0 + offset) / gamma).powf(gamma);
+    if inverse {
+        if x * slope >= encoded_break {
+            (1.0 + offset) * x.powf(1.0 / gamma) - offset
+        } else {
+            x * slope
+        }
+    } else if x >= encoded_break {
+        ((x + offset) / (1.0 + offset)).powf(gamma)
+    } else {
+        x / slope
+    }
+}
+
+fn saturate(rgb: [f32; 3], saturation: f32) -> [f32; 3] {
+    let luma = LUMA[0] * rgb[0] + LUMA[1] * rgb[1] + LUMA[2] * rgb[2];
+    rgb.map(|channel| luma + saturation * (channel - luma))
+}
+
+impl Op {
+    fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            Self::Matrix { m, offset } => {
+                let out = multiply(m, rgb);
+                [out[0] + offset[0], out[1] + offset[1], out[2] + offset[2]]
+            }
+            Self::Exponent(value) => [0, 1, 2].map(|c| rgb[c].max(0.0).powf(value[c])),
+            Self::MonCurve {
+                gamma,
+                offset,
+                inverse,
+            } => [0, 1, 2].map(|c| mon_curve(rgb[c], gamma[c], offset[c], *inverse)),
+            Self::Log {
+                base,
+                log_slope,
+                log_offset,
+                lin_slope,
+                lin_offset,
+                inverse,
+            } => {
+                let ln_base = base.ln();
+                [0, 1, 2].map(|c| {
+                    if *inverse {
+                        (((rgb[c] - log_offset[c]) / log_slope[c] * ln_base).exp() - lin_offset[c])
+                            / lin_slope[c]
+                    } else {
+                        let linear = (lin_slope[c] * rgb[c] + lin_offset[c]).max(f32::MIN_POSITIVE);
+                        log_slope[c] * linear.ln() / ln_base + log_offset[c]
+                    }
+                })
+            }
+            Self::Cdl {
+                slope,
+                offset,
+                power,
+                saturation,
+                clamp,
+                inverse,
+            } => {
+                let clamp01 = |value: f32| if *clamp { value.clamp(0.0, 1.0) } else { value };
+                if *inverse {
+                    let rgb = saturate(rgb.map(clamp01), 1.0 / saturation).map(clamp01);
+                    [0, 1, 2].map(|c| (rgb[c].max(0.0).powf(1.0 / power[c]) - offset[c]) / slope[c])
+                } else {
+                    let rgb = [0, 1, 2].map(|c| {
+                        clamp01(rgb[c] * slope[c] + offset[c])
+                            .max(0.0)
+                            .powf(power[c])
+                    });
+                    saturate(rgb, *saturation).map(clamp01)
+                }
+            }
+            Self::Range {
+                scale,
+                offset,
+                lower,
+                upper,
+            } => rgb.map(|value| {
+                let value = value * scale + offset;
+                let value = lower.map_or(value, |lower| value.max(lower));
+                upper.map_or(value, |upper| value.min(upper))
+            }),
+            Self::Lut(lut) => lut.apply(rgb),
+        }
+    }
+}
+
+/// A resolved chain of transforms.
+#[derive(Debug, Clone)]
+pub struct Processor {
+    ops: Vec<Op>,
+}
+
+impl Processor {
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        self.ops.iter().fold(rgb, |rgb, op| op.apply(rgb))
+    }
+
+    /// Samples the processor over the unit cube, the range of 8-bit imagery.
+    pub fn bake(&self) -> Lut {
+        Lut::bake(BAKE_SIZE, |rgb| self.apply(rgb))
+    }
+}
@@ -0,0 +1,276 @@
+//! Active connectivity checks against the configured tracker server.
+//!
+//! The frontend used to infer connectivity from whichever fetch failed last.
+//! This probes the active profile's server instead and tells apart being
+//! offline, being held by a captive portal, the server being down and the
+//! credentials having expired. Changes are emitted as `connectivity:changed`,
+//! and coming back online wakes the sync and publish workers.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::{Value, json};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use crate::ftrack::{self, ApiError};
+use crate::network;
+use crate::providers::ProviderKind;
+use crate::queue::PublishQueue;
+use crate::store::{self, now_millis};
+use crate::sync::SyncEngine;
+
+pub const CHANGED_EVENT: &str = "connectivity:changed";
+
+/// Answers plain HTTP with an empty 204; anything else means a portal
+/// rewrote the request.
+const PORTAL_PROBE_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(8);
+const ONLINE_INTERVAL: Duration = Duration::from_secs(60);
+const OFFLINE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectivityState {
+    /// Not probed yet, or there is no profile to probe.
+    Unknown,
+    Online,
+    Offline,
+    CaptivePortal,
+    /// The internet is reachable but the tracker server is not working.
+    ServerDown,
+    /// The server rejected the stored credentials.
+    AuthExpired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectivityStatus {
+    pub state: ConnectivityState,
+    pub server_url: Option<String>,
+    pub detail: Option<String>,
+    pub latency_ms: Option<u64>,
+    pub checked_at: Option<i64>,
+}
+
+pub struct ConnectivityMonitor {
+    status: Mutex<ConnectivityStatus>,
+    wake: Notify,
+}
+
+impl Default for ConnectivityMonitor {
+    fn default() -> Self {
+        Self {
+            status: Mutex::new(ConnectivityStatus {
+                state: ConnectivityState::Unknown,
+                server_url: None,
+                detail: None,
+                latency_ms: None,
+                checked_at: None,
+            }),
+            wake: Notify::new(),
+        }
+    }
+}
+
+impl ConnectivityMonitor {
+    pub fn status(&self) -> ConnectivityStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Probes again now, e.g. after a request failed unexpectedly.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+}
+
+struct Probe {
+    state: ConnectivityState,
+    detail: Option<String>,
+}
+
+impl Probe {
+    fn new(state: ConnectivityState, detail: impl Into<Option<String>>) -> Self {
+        Self {
+            state,
+            detail: detail.into(),
+        }
+    }
+}
+
+fn is_auth_error(err: &ApiError) -> bool {
+    match err {
+        ApiError::MissingApiKey => true,
+        ApiError::Status(status) => {
+            matches!(*status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+        }
+        ApiError::Server { exception, content } => {
+            let message = format!("{exception} {content}").to_lowercase();
+            ["api key", "authenticat", "unauthori", "credential"]
+                .iter()
+                .any(|needle| message.contains(needle))
+        }
+        _ => false,
+    }
+}
+
+/// Tells an unusable server apart from an unusable network.
+async fn probe_internet(client: &reqwest::Client, server_detail: String) -> Probe {
+    match client
+        .get(PORTAL_PROBE_URL)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(response) if response.status() == StatusCode::NO_CONTENT => {
+            Probe::new(ConnectivityState::ServerDown, server_detail)
+        }
+        Ok(response) => Probe::new(
+            ConnectivityState::CaptivePortal,
+            format!("Connectivity check answered {}", response.status()),
+        ),
+        Err(err) => Probe::new(ConnectivityState::Offline, err.to_string()),
+    }
+}
+
+async fn probe(app: &AppHandle) -> (Probe, Option<String>) {
+    let profile = match store::state(app).and_then(|store| store.active_profile()) {
+        Ok(Some(profile)) => profile,
+        Ok(None) => {
+            let probe = Probe::new(ConnectivityState::Unknown, "No active profile".to_string());
+            return (probe, None);
+        }
+        Err(err) => {
+            return (
+                Probe::new(ConnectivityState::Unknown, err.to_string()),
+                None,
+            );
+        }
+    };
+    let client = network::client();
+    let connection = profile.connection();
+    let server_url = Some(profile.server_url.clone());
+
+    // ShotGrid has no cheap authenticated call, so only reachability is checked
+    if profile.provider != ProviderKind::Ftrack {
+        let probe = match client
+            .head(connection.base_url())
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_server_error() => {
+                let detail = format!("Server responded with {}", response.status());
+                probe_internet(&client, detail).await
+            }
+            Ok(_) => Probe::new(ConnectivityState::Online, None),
+            Err(err) => probe_internet(&client, err.to_string()).await,
+        };
+        return (probe, server_url);
+    }
+
+    let operations = json!([{ "action": "query_server_information" }]);
+    let result = tokio::time::timeout(
+        PROBE_TIMEOUT,
+        ftrack::call(&client, &connection, &operations),
+    )
+    .await;
+    let probe = match result {
+        // A portal answering an http:// server returns a page rather than JSON
+        Ok(Ok(results)) if results.first().is_some_and(Value::is_object) => {
+            Probe::new(ConnectivityState::Online, None)
+        }
+        Ok(Ok(_)) => probe_internet(&client, "Unexpected server response".to_string()).await,
+        Ok(Err(err)) if is_auth_error(&err) => {
+            Probe::new(ConnectivityState::AuthExpired, err.to_string())
+        }
+        Ok(Err(ApiError::RateLimited(_))) => Probe::new(ConnectivityState::Online, None),
+        Ok(Err(err @ ApiError::Server { .. })) => {
+            // The server answered, it just did not like the request
+            log::warn!("Connectivity probe rejected: {err}");
+            Probe::new(ConnectivityState::Online, None)
+        }
+        Ok(Err(err)) => probe_internet(&client, err.to_string()).await,
+        Err(_) => probe_internet(&client, "Server did not respond in time".to_string()).await,
+    };
+    (probe, server_url)
+}
+
+/// Nudges the sync and publish workers, skipping any not yet started.
+pub(crate) fn wake_workers(app: &AppHandle) {
+    if let Some(engine) = app.try_state::<SyncEngine>() {
+        engine.wake();
+    }
+    if let Some(queue) = app.try_state::<PublishQueue>() {
+        queue.wake();
+    }
+}
+
+/// Probes once, records the result and reports a change of state.
+pub(crate) async fn check(app: &AppHandle) -> ConnectivityStatus {
+    let started = Instant::now();
+    let (probe, server_url) = probe(app).await;
+    let status = ConnectivityStatus {
+        state: probe.state,
+        server_url,
+        detail: probe.detail,
+        latency_ms: (probe.state == ConnectivityState::Online)
+            .then(|| started.elapsed().as_millis() as u64),
+        checked_at: Some(now_millis()),
+    };
+
+    let monitor = app.state::<ConnectivityMonitor>();
+    let previous = std::mem::replace(
+        &mut *monitor
+            .status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        status.clone(),
+    );
+    if previous.state != status.state || previous.server_url != status.server_url {
+        log::info!("Connectivity changed to {:?}", status.state);
+        if status.state == ConnectivityState::Online {
+            wake_workers(app);
+        }
+        if let Err(err) = app.emit(CHANGED_EVENT, &status) {
+            log::warn!("Failed to emit connectivity change: {err}");
+        }
+    }
+    status
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(ConnectivityMonitor::default());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let status = check(&app).await;
+            let delay = match status.state {
+                ConnectivityState::Online | ConnectivityState::Unknown => ONLINE_INTERVAL,
+                _ => OFFLINE_INTERVAL,
+            };
+            let monitor = app.state::<ConnectivityMonitor>();
+            tokio::select! {
+                _ = monitor.wake.notified() => {}
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+    });
+}
+
+/// The last probe result, without probing.
+#[tauri::command]
+pub fn get_connectivity(monitor: State<'_, ConnectivityMonitor>) -> ConnectivityStatus {
+    monitor.status()
+}
+
+/// Probes now and returns the result.
+#[tauri::command]
+pub async fn check_connectivity(app: AppHandle) -> ConnectivityStatus {
+    check(&app).await
+}
@@ -0,0 +1,57 @@
+//! Secrets stored in the platform keychain.
+//!
+//! Backed by Keychain on macOS, Credential Manager on Windows and the Secret
+//! Service on Linux, so API keys never touch webview storage.
+
+use crate::error::{Error, Result};
+
+const SERVICE: &str = "com.AstraLumen.Notes";
+
+/// Key under which the frontend stores the ftrack API key.
+pub const FTRACK_API_KEY: &str = "ftrack-api-key";
+
+fn entry(key: &str) -> Result<keyring::Entry> {
+    if key.trim().is_empty() {
+        return Err(Error::InvalidInput(
+            "Credential key must not be empty".into(),
+        ));
+    }
+    Ok(keyring::Entry::new(SERVICE, key)?)
+}
+
+pub fn store(key: &str, secret: &str) -> Result<()> {
+    entry(key)?.set_password(secret)?;
+    Ok(())
+}
+
+pub fn get(key: &str) -> Result<Option<String>> {
+    match entry(key)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Returns `false` when there was nothing stored under `key`.
+pub fn delete(key: &str) -> Result<bool> {
+    match entry(key)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[tauri::command]
+pub fn store_credential(key: String, secret: String) -> Result<()> {
+    store(&key, &secret)
+}
+
+#[tauri::command]
+pub fn get_credential(key: String) -> Result<Option<String>> {
+    get(&key)
+}
+
+#[tauri::command]
+pub fn delete_credential(key: String) -> Result<bool> {
+    delete(&key)
+}
@@ -0,0 +1,101 @@
+//! `astranotes://` deep links.
+//!
+//! Links such as `astranotes://playlist/<id>` and `astranotes://version/<id>`
+//! focus the app and are forwarded to the frontend as navigation events. The
+//! most recent link is also kept until the frontend takes it, so a link that
+//! launched the app is not lost before the webview starts listening.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+pub const NAVIGATE_EVENT: &str = "deep-link:navigate";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Target {
+    Playlist,
+    Version,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Navigation {
+    pub target: Target,
+    pub id: String,
+    pub url: String,
+}
+
+#[derive(Default)]
+pub struct PendingDeepLink(Mutex<Option<Navigation>>);
+
+impl PendingDeepLink {
+    fn slot(&self) -> std::sync::MutexGuard<'_, Option<Navigation>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(PendingDeepLink::default());
+
+    // Installers register the scheme; dev builds and loose AppImages need it done here
+    #[cfg(all(debug_assertions, any(windows, target_os = "linux")))]
+    if let Err(err) = app.deep_link().register_all() {
+        log::warn!("Failed to register deep link schemes: {err}");
+    }
+
+    let handle = app.clone();
+    app.deep_link()
+        .on_open_url(move |event| handle_urls(&handle, event.urls()));
+
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => handle_urls(app, urls),
+        Ok(None) => {}
+        Err(err) => log::warn!("Failed to read launch deep link: {err}"),
+    }
+}
+
+pub(crate) fn handle_urls(app: &AppHandle, urls: Vec<Url>) {
+    for url in urls {
+        let Some(navigation) = parse(&url) else {
+            log::warn!("Ignoring unsupported deep link: {url}");
+            continue;
+        };
+
+        *app.state::<PendingDeepLink>().slot() = Some(navigation.clone());
+        if let Err(err) = app.emit(NAVIGATE_EVENT, navigation) {
+            log::warn!("Failed to emit deep link navigation: {err}");
+        }
+        crate::focus_main_window(app);
+    }
+}
+
+fn parse(url: &Url) -> Option<Navigation> {
+    if url.scheme() != "astranotes" {
+        return None;
+    }
+    let target = match url.host_str()? {
+        "playlist" => Target::Playlist,
+        "version" => Target::Version,
+        _ => return None,
+    };
+    let id = url.path().trim_matches('/');
+    if id.is_empty() || id.contains('/') {
+        return None;
+    }
+    Some(Navigation {
+        target,
+        id: id.to_string(),
+        url: url.to_string(),
+    })
+}
+
+/// Returns and clears the most recent deep link, if one has not been handled yet.
+#[tauri::command]
+pub fn take_pending_deep_link(pending: State<'_, PendingDeepLink>) -> Option<Navigation> {
+    pending.slot().take()
+}
@@ -0,0 +1,282 @@
+//! Google Cloud Storage resumable uploads, authorized as a service account.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::Engine as _;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use reqwest::StatusCode;
+use reqwest::header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_RANGE, LOCATION, RANGE};
+use ring::rand::SystemRandom;
+use ring::signature::{RSA_PKCS1_SHA256, RsaKeyPair};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{FileState, FileUpload, Session, with_retries};
+use crate::error::{Error, Result};
+
+const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_write";
+const UPLOAD_URL: &str = "https://storage.googleapis.com/upload/storage/v1/b";
+/// Chunks must be multiples of 256 KiB, except the last.
+const CHUNK_SIZE: u64 = 64 * 256 * 1024;
+/// Tokens are refreshed this long before Google says they expire.
+const TOKEN_MARGIN: Duration = Duration::from_secs(300);
+
+/// Access tokens by service account, reused across files and uploads.
+static TOKENS: LazyLock<Mutex<HashMap<String, (String, Instant)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    #[serde(default = "default_token_uri")]
+    token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".into()
+}
+
+impl ServiceAccount {
+    /// Parses a service account key file as downloaded from the console.
+    pub(super) fn parse(json: &str) -> Result<Self> {
+        let account: Self = serde_json::from_str(json)
+            .map_err(|err| Error::InvalidInput(format!("Invalid service account key: {err}")))?;
+        account.key_pair()?;
+        Ok(account)
+    }
+
+    fn key_pair(&self) -> Result<RsaKeyPair> {
+        let base64: String = self
+            .private_key
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let der = STANDARD
+            .decode(base64.trim())
+            .map_err(|_| Error::InvalidInput("Service account private key is not PEM".into()))?;
+        RsaKeyPair::from_pkcs8(&der).map_err(|err| {
+            Error::InvalidInput(format!("Invalid service account private key: {err}"))
+        })
+    }
+
+    /// A signed JWT assertion for the OAuth token exchange.
+    fn assertion(&self) -> Result<String> {
+        let now = chrono::Utc::now().timestamp();
+        let header = URL_SAFE_NO_PAD.encode(json!({ "alg": "RS256", "typ": "JWT" }).to_string());
+        let claims = URL_SAFE_NO_PAD.encode(
+            json!({
+                "iss": self.client_email,
+                "scope": SCOPE,
+                "aud": self.token_uri,
+                "iat": now,
+                "exp": now + 3600,
+            })
+            .to_string(),
+        );
+        let message = format!("{header}.{claims}");
+        let key_pair = self.key_pair()?;
+        let mut signature = vec![0; key_pair.public().modulus_len()];
+        key_pair
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| Error::Upload("Failed to sign the service account token".into()))?;
+        Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    async fn token(&self, client: &reqwest::Client) -> Result<String> {
+        if let Some((token, expires)) = TOKENS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&self.client_email)
+            && Instant::now() < *expires
+        {
+            return Ok(token.clone());
+        }
+
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            access_token: String,
+            expires_in: u64,
+        }
+        let assertion = self.assertion()?;
+        let response = client
+            .post(&self.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?;
+        let response: TokenResponse = check(response).await?.json().await?;
+        let expires =
+            Instant::now() + Duration::from_secs(response.expires_in).saturating_sub(TOKEN_MARGIN);
+        TOKENS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(
+                self.client_email.clone(),
+                (response.access_token.clone(), expires),
+            );
+        Ok(response.access_token)
+    }
+}
+
+async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() || status == StatusCode::PERMANENT_REDIRECT {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| value["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| status.to_string());
+    Err(Error::Upload(format!(
+        "Google Cloud Storage responded with {status}: {message}"
+    )))
+}
+
+/// Bytes the server has stored, from the `Range: bytes=0-<last>` of a 308.
+fn stored_bytes(response: &reqwest::Response) -> u64 {
+    response
+        .headers()
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|range| range.rsplit('-').next())
+        .and_then(|last| last.parse::<u64>().ok())
+        .map_or(0, |last| last + 1)
+}
+
+async fn create(
+    client: &reqwest::Client,
+    token: &str,
+    bucket: &str,
+    key: &str,
+    size: u64,
+) -> Result<String> {
+    let response = client
+        .post(format!("{UPLOAD_URL}/{bucket}/o"))
+        .query(&[("uploadType", "resumable"), ("name", key)])
+        .header(AUTHORIZATION, format!("Bearer {token}"))
+        .header("X-Upload-Content-Length", size)
+        .json(&json!({ "name": key }))
+        .send()
+        .await?;
+    check(response)
+        .await?
+        .headers()
+        .get(LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| Error::Upload("Google Cloud Storage returned no upload session".into()))
+}
+
+/// Where an existing session stands: `Some(offset)` to continue from, or
+/// `None` once the object is complete. Expired sessions are an error.
+async fn status(client: &reqwest::Client, uri: &str, size: u64) -> Result<Option<u64>> {
+    let response = client
+        .put(uri)
+        .header(CONTENT_LENGTH, 0)
+        .header(CONTENT_RANGE, format!("bytes */{size}"))
+        .send()
+        .await?;
+    let response = check(response).await?;
+    Ok((response.status() == StatusCode::PERMANENT_REDIRECT).then(|| stored_bytes(&response)))
+}
+
+/// Sends one chunk and returns the new offset, or `None` when the upload is complete.
+async fn put(
+    file: &FileUpload<'_>,
+    uri: &str,
+    start: u64,
+    len: u64,
+    size: u64,
+) -> Result<Option<u64>> {
+    let range = if len == 0 {
+        format!("bytes */{size}")
+    } else {
+        format!("bytes {start}-{}/{size}", start + len - 1)
+    };
+    let response = file
+        .client
+        .put(uri)
+        .header(CONTENT_LENGTH, len)
+        .header(CONTENT_RANGE, range)
+        .body(file.body(start, len))
+        .send()
+        .await?;
+    let response = check(response).await?;
+    Ok((response.status() == StatusCode::PERMANENT_REDIRECT).then(|| stored_bytes(&response)))
+}
+
+pub(super) async fn abort(client: &reqwest::Client, uri: &str) -> Result<()> {
+    let response = client.delete(uri).send().await?;
+    // Google answers a cancelled session with 499
+    if response.status().is_success() || response.status().as_u16() == 499 {
+        return Ok(());
+    }
+    check(response).await.map(|_| ())
+}
+
+pub(super) async fn upload(
+    file: &FileUpload<'_>,
+    bucket: &str,
+    secret: &str,
+    state: &mut FileState,
+) -> Result<()> {
+    let size = state.size;
+    let resumed = match state.session.take() {
+        Some(Session::Gcs { uri }) => match status(file.client, &uri, size).await {
+            Ok(Some(offset)) => Some((uri, offset)),
+            Ok(None) => {
+                file.skip(size);
+                return Ok(());
+            }
+            Err(err) => {
+                log::info!("Restarting upload of {}: {err}", state.key);
+                None
+            }
+        },
+        _ => None,
+    };
+    let (uri, mut offset) = match resumed {
+        Some((uri, offset)) => {
+            file.skip(offset);
+            (uri, offset)
+        }
+        None => {
+            let account = ServiceAccount::parse(secret)?;
+            let token = account.token(file.client).await?;
+            (
+                create(file.client, &token, bucket, &state.key, size).await?,
+                0,
+            )
+        }
+    };
+    state.session = Some(Session::Gcs { uri: uri.clone() });
+    file.checkpoint(state);
+
+    loop {
+        if file.is_cancelled() {
+            return Err(Error::Upload("Upload cancelled".into()));
+        }
+        let len = CHUNK_SIZE.min(size - offset);
+        match with_retries(file, len, || put(file, &uri, offset, len, size)).await? {
+            Some(stored) => {
+                // The server may keep less than was sent; resend the rest
+                if stored < offset + len {
+                    file.rewind(offset + len - stored);
+                }
+                offset = stored;
+            }
+            None => return Ok(()),
+        }
+    }
+}
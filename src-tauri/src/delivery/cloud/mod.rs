@@ -0,0 +1,765 @@
+//! Uploading finished delivery packages to S3-compatible and GCS buckets.
+//!
+//! Targets are configured in `cloud-targets.json`, optionally scoped to a
+//! project, with their secret key or service account in the keychain. Files
+//! go up one at a time in parts; every finished part is recorded in the app
+//! data directory, so an upload interrupted by quitting or a lost connection
+//! resumes where it stopped with `resume_cloud_upload`. Each target can cap
+//! its bandwidth below the global transfer budget. Progress is reported as
+//! `delivery-upload:progress` events.
+
+mod gcs;
+mod s3;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use reqwest::Body;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::network;
+use crate::notifications::{self, Kind};
+use crate::paths;
+use crate::settings;
+use crate::taskbar;
+use crate::transfers::{self, Priority, Transfer, TransferKind};
+
+pub const PROGRESS_EVENT: &str = "delivery-upload:progress";
+
+const SETTINGS_FILE: &str = "cloud-targets.json";
+const STATE_DIR: &str = "cloud-uploads";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const STREAM_CHUNK: usize = 64 * 1024;
+const PART_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Backend {
+    S3 {
+        /// Defaults to AWS for the region; set for MinIO, Wasabi, R2 and the like.
+        #[serde(default)]
+        endpoint: Option<String>,
+        region: String,
+        bucket: String,
+        access_key_id: String,
+        /// Address the bucket in the path rather than the host name, as most
+        /// self-hosted servers require.
+        #[serde(default)]
+        path_style: bool,
+    },
+    Gcs {
+        bucket: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudTarget {
+    pub id: String,
+    pub name: String,
+    /// Only offered for deliveries of this project; `None` for all projects.
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(flatten)]
+    pub backend: Backend,
+    /// Key prefix packages are uploaded under, e.g. `outgoing/client`.
+    #[serde(default)]
+    pub prefix: String,
+    /// Upload speed cap in bytes per second; unlimited when unset.
+    #[serde(default)]
+    pub max_bytes_per_second: Option<u64>,
+}
+
+impl CloudTarget {
+    /// Keychain entry holding the S3 secret key or the GCS service account JSON.
+    fn credential_key(&self) -> String {
+        format!("cloud-target:{}", self.id)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct CloudSettings {
+    targets: Vec<CloudTarget>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<CloudSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn save_settings(app: &AppHandle, settings: &CloudSettings) -> Result<()> {
+    paths::save_json(app, SETTINGS_FILE, settings)
+}
+
+fn find_target(app: &AppHandle, id: &str) -> Result<CloudTarget> {
+    load_settings(app)?
+        .targets
+        .into_iter()
+        .find(|target| target.id == id)
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown upload target: {id}")))
+}
+
+/// Progress through one file's remote upload, kept so it can be resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum Session {
+    S3 {
+        upload_id: String,
+        part_size: u64,
+        parts: Vec<s3::CompletedPart>,
+    },
+    Gcs {
+        uri: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileState {
+    relative: String,
+    key: String,
+    size: u64,
+    done: bool,
+    #[serde(default)]
+    session: Option<Session>,
+}
+
+/// Everything needed to pick an upload up again after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadState {
+    id: String,
+    target_id: String,
+    package_dir: PathBuf,
+    files: Vec<FileState>,
+    created_at: i64,
+}
+
+impl UploadState {
+    fn path(app: &AppHandle, id: &str) -> Result<PathBuf> {
+        Ok(paths::data_dir(app)?
+            .join(STATE_DIR)
+            .join(format!("{id}.json")))
+    }
+
+    fn save(&self, app: &AppHandle) -> Result<()> {
+        let path = Self::path(app, &self.id)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    fn remove(app: &AppHandle, id: &str) {
+        if let Ok(path) = Self::path(app, id) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CloudUploadStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// Stopped by quitting the app; can be resumed.
+    Interrupted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudUploadSnapshot {
+    pub id: String,
+    pub target_id: String,
+    pub package_dir: PathBuf,
+    pub status: CloudUploadStatus,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub error: Option<String>,
+}
+
+impl CloudUploadSnapshot {
+    fn new(state: &UploadState, status: CloudUploadStatus) -> Self {
+        let done = state.files.iter().filter(|file| file.done);
+        Self {
+            id: state.id.clone(),
+            target_id: state.target_id.clone(),
+            package_dir: state.package_dir.clone(),
+            status,
+            files_done: done.clone().count(),
+            files_total: state.files.len(),
+            bytes_done: done.map(|file| file.size).sum(),
+            bytes_total: state.files.iter().map(|file| file.size).sum(),
+            error: None,
+        }
+    }
+}
+
+pub(crate) struct CloudUpload {
+    cancelled: AtomicBool,
+    snapshot: Mutex<CloudUploadSnapshot>,
+    state: Mutex<UploadState>,
+    last_emit: Mutex<Instant>,
+}
+
+impl CloudUpload {
+    fn new(state: UploadState, status: CloudUploadStatus) -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            snapshot: Mutex::new(CloudUploadSnapshot::new(&state, status)),
+            state: Mutex::new(state),
+            last_emit: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CloudUploadSnapshot> {
+        self.snapshot
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, UploadState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn snapshot(&self) -> CloudUploadSnapshot {
+        self.lock().clone()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Applies `f` and emits progress, at most every [`PROGRESS_INTERVAL`]
+    /// for byte-level updates.
+    fn update(&self, app: &AppHandle, throttle: bool, f: impl FnOnce(&mut CloudUploadSnapshot)) {
+        let snapshot = {
+            let mut snapshot = self.lock();
+            f(&mut snapshot);
+            snapshot.clone()
+        };
+        if throttle {
+            let mut last_emit = self
+                .last_emit
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if last_emit.elapsed() < PROGRESS_INTERVAL {
+                return;
+            }
+            *last_emit = Instant::now();
+        }
+        let source = format!("delivery-upload:{}", snapshot.id);
+        match snapshot.status {
+            CloudUploadStatus::Running => {
+                taskbar::update(app, &source, snapshot.bytes_done, snapshot.bytes_total)
+            }
+            _ => taskbar::finish(app, &source),
+        }
+        if let Err(err) = app.emit(PROGRESS_EVENT, snapshot) {
+            log::warn!("Failed to emit delivery upload progress: {err}");
+        }
+    }
+
+    /// Records a file's session so the upload can resume from it.
+    fn checkpoint(&self, app: &AppHandle, index: usize, file: &FileState) {
+        let mut state = self.state();
+        state.files[index] = file.clone();
+        if let Err(err) = state.save(app) {
+            log::warn!("Failed to save delivery upload {}: {err}", state.id);
+        }
+    }
+}
+
+/// What a backend needs to upload one file.
+pub(crate) struct FileUpload<'a> {
+    app: &'a AppHandle,
+    client: &'a reqwest::Client,
+    upload: &'a Arc<CloudUpload>,
+    throttle: &'a Arc<Transfer>,
+    path: PathBuf,
+    index: usize,
+}
+
+impl FileUpload<'_> {
+    /// Streams `len` bytes of the file from `start`, paced by the rate limit
+    /// and counted towards progress. Cancelling ends the stream with an error.
+    fn body(&self, start: u64, len: u64) -> Body {
+        let (app, upload, throttle, path) = (
+            self.app.clone(),
+            self.upload.clone(),
+            self.throttle.clone(),
+            self.path.clone(),
+        );
+        let stream = futures_util::stream::once(async move {
+            let mut file = tokio::fs::File::open(&path).await?;
+            file.seek(SeekFrom::Start(start)).await?;
+            Ok::<_, std::io::Error>(ReaderStream::with_capacity(file.take(len), STREAM_CHUNK))
+        })
+        .map(|opened| match opened {
+            Ok(stream) => stream.left_stream(),
+            Err(err) => futures_util::stream::once(async { Err(err) }).right_stream(),
+        })
+        .flatten()
+        .then(move |chunk| {
+            let (app, upload, throttle) = (app.clone(), upload.clone(), throttle.clone());
+            async move {
+                let chunk = chunk?;
+                if upload.is_cancelled() {
+                    return Err(std::io::Error::other("Upload cancelled"));
+                }
+                throttle.take(chunk.len() as u64).await;
+                upload.update(&app, true, |snapshot| {
+                    snapshot.bytes_done += chunk.len() as u64
+                });
+                Ok(chunk)
+            }
+        });
+        Body::wrap_stream(stream)
+    }
+
+    /// Counts bytes a previous session already sent.
+    fn skip(&self, bytes: u64) {
+        self.upload
+            .update(self.app, false, |snapshot| snapshot.bytes_done += bytes);
+    }
+
+    /// Takes back bytes counted for an attempt that failed.
+    fn rewind(&self, bytes: u64) {
+        self.upload.update(self.app, true, |snapshot| {
+            snapshot.bytes_done = snapshot.bytes_done.saturating_sub(bytes)
+        });
+    }
+
+    fn checkpoint(&self, file: &FileState) {
+        self.upload.checkpoint(self.app, self.index, file);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.upload.is_cancelled()
+    }
+}
+
+/// Runs `send` up to [`PART_ATTEMPTS`] times, rewinding progress between
+/// attempts.
+async fn with_retries<T, F, Fut>(file: &FileUpload<'_>, len: u64, mut send: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let before = file.upload.lock().bytes_done;
+        match send().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < PART_ATTEMPTS && !file.is_cancelled() => {
+                let sent = file.upload.lock().bytes_done.saturating_sub(before);
+                file.rewind(sent.min(len));
+                log::warn!(
+                    "Upload of {} failed (attempt {attempt}): {err}",
+                    file.path.display()
+                );
+                tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Remote key of a package file: `<prefix>/<package>/<relative>`.
+fn object_key(prefix: &str, package: &str, relative: &str) -> String {
+    [prefix.trim_matches('/'), package, relative]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Every file in the package, in name order, with `/`-separated paths.
+fn package_files(dir: &Path) -> Result<Vec<(String, u64)>> {
+    fn walk(dir: &Path, root: &Path, files: &mut Vec<(String, u64)>) -> Result<()> {
+        let mut entries: Vec<_> = fs::read_dir(dir)?.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                walk(&path, root, files)?;
+            } else if path.extension().is_none_or(|ext| ext != "part") {
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                files.push((relative, metadata.len()));
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    walk(dir, dir, &mut files)?;
+    if files.is_empty() {
+        return Err(Error::InvalidInput(format!("{} is empty", dir.display())));
+    }
+    Ok(files)
+}
+
+#[derive(Default)]
+pub struct CloudUploads {
+    uploads: Mutex<HashMap<String, Arc<CloudUpload>>>,
+}
+
+impl CloudUploads {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<CloudUpload>>> {
+        self.uploads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn get(&self, id: &str) -> Result<Arc<CloudUpload>> {
+        self.lock()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown delivery upload: {id}")))
+    }
+}
+
+/// Lists uploads a previous session left unfinished, so they can be resumed.
+pub fn init(app: &AppHandle) {
+    let Ok(dir) = paths::data_dir(app).map(|dir| dir.join(STATE_DIR)) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return;
+    };
+    let uploads = app.state::<CloudUploads>();
+    for entry in entries.flatten() {
+        let state = fs::read(entry.path())
+            .map_err(Error::from)
+            .and_then(|bytes| Ok(serde_json::from_slice::<UploadState>(&bytes)?));
+        match state {
+            Ok(state) => {
+                let id = state.id.clone();
+                let upload = CloudUpload::new(state, CloudUploadStatus::Interrupted);
+                uploads.lock().insert(id, Arc::new(upload));
+            }
+            Err(err) => {
+                log::warn!(
+                    "Removing unreadable upload state {}: {err}",
+                    entry.path().display()
+                );
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// Starts uploading a built package; used directly and by deliveries with an
+/// upload target.
+pub(crate) fn start(
+    app: &AppHandle,
+    package_dir: &Path,
+    target_id: &str,
+) -> Result<CloudUploadSnapshot> {
+    let target = find_target(app, target_id)?;
+    let package = package_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| Error::InvalidInput("Package folder has no name".into()))?;
+    let files = package_files(package_dir)?
+        .into_iter()
+        .map(|(relative, size)| FileState {
+            key: object_key(&target.prefix, &package, &relative),
+            relative,
+            size,
+            done: false,
+            session: None,
+        })
+        .collect();
+    let state = UploadState {
+        id: uuid::Uuid::new_v4().to_string(),
+        target_id: target.id.clone(),
+        package_dir: package_dir.to_path_buf(),
+        files,
+        created_at: crate::store::now_millis(),
+    };
+    state.save(app)?;
+
+    let upload = Arc::new(CloudUpload::new(state.clone(), CloudUploadStatus::Running));
+    app.state::<CloudUploads>()
+        .lock()
+        .insert(state.id.clone(), upload.clone());
+    let snapshot = upload.snapshot();
+    tauri::async_runtime::spawn(run(app.clone(), upload, target));
+    Ok(snapshot)
+}
+
+async fn upload_file(
+    file: &FileUpload<'_>,
+    target: &CloudTarget,
+    secret: &str,
+    state: &mut FileState,
+) -> Result<()> {
+    match &target.backend {
+        Backend::S3 { .. } => s3::upload(file, target, secret, state).await,
+        Backend::Gcs { bucket } => gcs::upload(file, bucket, secret, state).await,
+    }
+}
+
+/// Discards the parts of unfinished files on the server.
+async fn abort(target: &CloudTarget, state: &UploadState) {
+    let Ok(Some(secret)) = credentials::get(&target.credential_key()) else {
+        return;
+    };
+    let client = network::client();
+    for file in state.files.iter().filter(|file| !file.done) {
+        let result = match &file.session {
+            Some(Session::S3 { upload_id, .. }) => {
+                s3::abort(&client, target, &secret, &file.key, upload_id).await
+            }
+            Some(Session::Gcs { uri }) => gcs::abort(&client, uri).await,
+            None => continue,
+        };
+        if let Err(err) = result {
+            log::warn!("Failed to discard partial upload of {}: {err}", file.key);
+        }
+    }
+}
+
+async fn run(app: AppHandle, upload: Arc<CloudUpload>, target: CloudTarget) {
+    let result = transfer(&app, &upload, &target).await;
+    let id = upload.snapshot().id;
+    let status = match &result {
+        Ok(()) => CloudUploadStatus::Completed,
+        Err(_) if upload.is_cancelled() => CloudUploadStatus::Cancelled,
+        Err(_) => CloudUploadStatus::Failed,
+    };
+    if status == CloudUploadStatus::Cancelled {
+        let state = upload.state().clone();
+        abort(&target, &state).await;
+    }
+    if status != CloudUploadStatus::Failed {
+        UploadState::remove(&app, &id);
+    }
+    upload.update(&app, false, |snapshot| {
+        snapshot.status = status;
+        if let Err(err) = &result
+            && status == CloudUploadStatus::Failed
+        {
+            log::warn!("Delivery upload {id} failed: {err}");
+            snapshot.error = Some(err.to_string());
+        }
+    });
+
+    let snapshot = upload.snapshot();
+    let name = snapshot
+        .package_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (title, body) = match status {
+        CloudUploadStatus::Completed => (
+            "Delivery uploaded",
+            format!("{name} uploaded to {}", target.name),
+        ),
+        CloudUploadStatus::Failed => (
+            "Delivery upload failed",
+            format!("{name}: {}", snapshot.error.unwrap_or_default()),
+        ),
+        _ => return,
+    };
+    notifications::notify(&app, Kind::Delivery, Some(snapshot.id), title, &body);
+}
+
+async fn transfer(app: &AppHandle, upload: &Arc<CloudUpload>, target: &CloudTarget) -> Result<()> {
+    let secret = credentials::get(&target.credential_key())?
+        .ok_or_else(|| Error::Upload(format!("No credentials stored for {}", target.name)))?;
+    let client = network::client();
+    let (id, package_dir, files) = {
+        let state = upload.state();
+        (
+            state.id.clone(),
+            state.package_dir.clone(),
+            state.files.clone(),
+        )
+    };
+    let label = package_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let throttle = Arc::new(transfers::register(
+        app,
+        format!("delivery-upload:{id}"),
+        TransferKind::Upload,
+        label,
+        Priority::Normal,
+        target.max_bytes_per_second,
+    ));
+
+    for (index, mut state) in files.into_iter().enumerate() {
+        if state.done {
+            continue;
+        }
+        if upload.is_cancelled() {
+            return Err(Error::Upload("Upload cancelled".into()));
+        }
+        let path = package_dir.join(&state.relative);
+        let size = fs::metadata(&path)?.len();
+        if size != state.size {
+            // Changed since the upload started; parts already sent are stale
+            state.size = size;
+            state.session = None;
+        }
+        let file = FileUpload {
+            app,
+            client: &client,
+            upload,
+            throttle: &throttle,
+            path,
+            index,
+        };
+        upload_file(&file, target, &secret, &mut state).await?;
+        state.done = true;
+        state.session = None;
+        file.checkpoint(&state);
+        upload.update(app, false, |snapshot| snapshot.files_done += 1);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_cloud_targets(app: AppHandle, project_id: Option<String>) -> Result<Vec<CloudTarget>> {
+    let targets = load_settings(&app)?.targets;
+    Ok(match project_id {
+        Some(project_id) => targets
+            .into_iter()
+            .filter(|target| {
+                target
+                    .project_id
+                    .as_ref()
+                    .is_none_or(|id| *id == project_id)
+            })
+            .collect(),
+        None => targets,
+    })
+}
+
+/// Adds or replaces a target. `secret` is the S3 secret access key or the GCS
+/// service account JSON, and is left as stored when `None`.
+#[tauri::command]
+pub fn save_cloud_target(
+    app: AppHandle,
+    mut target: CloudTarget,
+    secret: Option<String>,
+) -> Result<CloudTarget> {
+    if target.name.trim().is_empty() {
+        return Err(Error::InvalidInput("Upload target needs a name".into()));
+    }
+    if let (Backend::Gcs { .. }, Some(secret)) = (&target.backend, &secret) {
+        gcs::ServiceAccount::parse(secret)?;
+    }
+    if target.id.is_empty() {
+        target.id = uuid::Uuid::new_v4().to_string();
+    }
+    let mut settings = load_settings(&app)?;
+    match settings
+        .targets
+        .iter_mut()
+        .find(|existing| existing.id == target.id)
+    {
+        Some(existing) => *existing = target.clone(),
+        None => settings.targets.push(target.clone()),
+    }
+    settings::check_locked(SETTINGS_FILE, &settings)?;
+    if let Some(secret) = secret.filter(|secret| !secret.is_empty()) {
+        credentials::store(&target.credential_key(), &secret)?;
+    }
+    save_settings(&app, &settings)?;
+    Ok(target)
+}
+
+#[tauri::command]
+pub fn delete_cloud_target(app: AppHandle, id: String) -> Result<bool> {
+    let mut settings = load_settings(&app)?;
+    let Some(index) = settings.targets.iter().position(|target| target.id == id) else {
+        return Ok(false);
+    };
+    let target = settings.targets.remove(index);
+    settings::check_locked(SETTINGS_FILE, &settings)?;
+    credentials::delete(&target.credential_key())?;
+    save_settings(&app, &settings)?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn start_cloud_upload(
+    app: AppHandle,
+    package_dir: PathBuf,
+    target_id: String,
+) -> Result<CloudUploadSnapshot> {
+    start(&app, &package_dir, &target_id)
+}
+
+/// Continues a failed or interrupted upload, skipping parts already sent.
+#[tauri::command]
+pub fn resume_cloud_upload(
+    app: AppHandle,
+    uploads: State<'_, CloudUploads>,
+    id: String,
+) -> Result<CloudUploadSnapshot> {
+    let previous = uploads.get(&id)?;
+    if previous.snapshot().status == CloudUploadStatus::Running {
+        return Err(Error::InvalidInput(format!("Upload {id} is still running")));
+    }
+    let state = previous.state().clone();
+    if !UploadState::path(&app, &id)?.exists() {
+        return Err(Error::InvalidInput(format!(
+            "Upload {id} cannot be resumed"
+        )));
+    }
+    let target = find_target(&app, &state.target_id)?;
+    let upload = Arc::new(CloudUpload::new(state, CloudUploadStatus::Running));
+    uploads.lock().insert(id, upload.clone());
+    let snapshot = upload.snapshot();
+    upload.update(&app, false, |_| {});
+    tauri::async_runtime::spawn(run(app, upload, target));
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub fn cancel_cloud_upload(uploads: State<'_, CloudUploads>, id: String) -> Result<()> {
+    uploads.get(&id)?.cancelled.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_cloud_uploads(uploads: State<'_, CloudUploads>) -> Vec<CloudUploadSnapshot> {
+    uploads
+        .lock()
+        .values()
+        .map(|upload| upload.snapshot())
+        .collect()
+}
@@ -0,0 +1,341 @@
+//! S3 multipart uploads, signed with AWS Signature Version 4.
+//!
+//! Works with AWS and S3-compatible servers. Part bodies are sent as
+//! `UNSIGNED-PAYLOAD`, so files are read once, while uploading.
+
+use hmac::{Hmac, Mac};
+use reqwest::header::{AUTHORIZATION, CONTENT_LENGTH, ETAG, HOST};
+use reqwest::{Body, Method, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{Backend, CloudTarget, FileState, FileUpload, Session, with_retries};
+use crate::error::{Error, Result};
+
+/// Parts are at least this big; S3 requires 5 MiB for all but the last.
+const MIN_PART_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct CompletedPart {
+    number: u32,
+    e_tag: String,
+}
+
+struct Bucket<'a> {
+    endpoint: Option<&'a str>,
+    region: &'a str,
+    bucket: &'a str,
+    access_key_id: &'a str,
+    path_style: bool,
+    secret: &'a str,
+}
+
+impl<'a> Bucket<'a> {
+    fn new(target: &'a CloudTarget, secret: &'a str) -> Result<Self> {
+        let Backend::S3 {
+            endpoint,
+            region,
+            bucket,
+            access_key_id,
+            path_style,
+        } = &target.backend
+        else {
+            return Err(Error::InvalidInput(format!(
+                "{} is not an S3 target",
+                target.name
+            )));
+        };
+        Ok(Self {
+            endpoint: endpoint.as_deref().filter(|endpoint| !endpoint.is_empty()),
+            region,
+            bucket,
+            access_key_id,
+            path_style: *path_style,
+            secret,
+        })
+    }
+
+    fn url(&self, key: &str, query: &[(&str, &str)]) -> Result<Url> {
+        let endpoint = self
+            .endpoint
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", self.region));
+        let mut url = Url::parse(endpoint.trim_end_matches('/'))
+            .map_err(|err| Error::InvalidInput(format!("Invalid S3 endpoint: {err}")))?;
+        let key = encode(key, false);
+        if self.path_style {
+            url.set_path(&format!("/{}/{key}", encode(self.bucket, true)));
+        } else {
+            let host = url
+                .host_str()
+                .ok_or_else(|| Error::InvalidInput("S3 endpoint has no host".into()))?;
+            let host = format!("{}.{host}", self.bucket);
+            url.set_host(Some(&host))
+                .map_err(|err| Error::InvalidInput(format!("Invalid S3 bucket name: {err}")))?;
+            url.set_path(&format!("/{key}"));
+        }
+        if !query.is_empty() {
+            let query = query
+                .iter()
+                .map(|(name, value)| format!("{}={}", encode(name, true), encode(value, true)))
+                .collect::<Vec<_>>()
+                .join("&");
+            url.set_query(Some(&query));
+        }
+        Ok(url)
+    }
+
+    /// Builds a signed request. `payload_hash` is the hex SHA-256 of the body,
+    /// or [`UNSIGNED_PAYLOAD`].
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: Method,
+        url: Url,
+        payload_hash: &str,
+    ) -> reqwest::RequestBuilder {
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        // The URL already holds the query in canonical form: encoded and,
+        // with the few parameters used here, in sorted order
+        let canonical_request = format!(
+            "{method}\n{}\n{}\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n\
+             host;x-amz-content-sha256;x-amz-date\n{payload_hash}",
+            url.path(),
+            url.query().unwrap_or_default(),
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = hmac(format!("AWS4{}", self.secret).as_bytes(), date.as_bytes());
+        for part in [self.region, "s3", "aws4_request"] {
+            key = hmac(&key, part.as_bytes());
+        }
+        let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={signature}",
+            self.access_key_id
+        );
+
+        client
+            .request(method, url)
+            .header(HOST, host)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(AUTHORIZATION, authorization)
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// URI-encodes as SigV4 requires: everything but unreserved characters, and
+/// `/` too unless it separates key segments.
+fn encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Pulls the text of the first `<tag>` out of an S3 XML response.
+fn xml_value(body: &str, tag: &str) -> Option<String> {
+    let start = body.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = body[start..].find(&format!("</{tag}>"))? + start;
+    Some(body[start..end].to_string())
+}
+
+async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = xml_value(&body, "Message").unwrap_or_else(|| status.to_string());
+    Err(Error::Upload(format!(
+        "S3 responded with {status}: {message}"
+    )))
+}
+
+async fn create(client: &reqwest::Client, bucket: &Bucket<'_>, key: &str) -> Result<String> {
+    let url = bucket.url(key, &[("uploads", "")])?;
+    let empty = hex(&Sha256::digest(b""));
+    let response = check(
+        bucket
+            .request(client, Method::POST, url, &empty)
+            .send()
+            .await?,
+    )
+    .await?;
+    xml_value(&response.text().await?, "UploadId")
+        .ok_or_else(|| Error::Upload("S3 did not return an upload ID".into()))
+}
+
+async fn put(
+    file: &FileUpload<'_>,
+    bucket: &Bucket<'_>,
+    url: Url,
+    start: u64,
+    len: u64,
+) -> Result<String> {
+    let response = bucket
+        .request(file.client, Method::PUT, url, UNSIGNED_PAYLOAD)
+        .header(CONTENT_LENGTH, len)
+        .body(file.body(start, len))
+        .send()
+        .await?;
+    let response = check(response).await?;
+    Ok(response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string())
+}
+
+async fn complete(
+    client: &reqwest::Client,
+    bucket: &Bucket<'_>,
+    key: &str,
+    upload_id: &str,
+    parts: &[CompletedPart],
+) -> Result<()> {
+    let mut body = String::from("<CompleteMultipartUpload>");
+    for part in parts {
+        body.push_str(&format!(
+            "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+            part.number, part.e_tag
+        ));
+    }
+    body.push_str("</CompleteMultipartUpload>");
+    let url = bucket.url(key, &[("uploadId", upload_id)])?;
+    let hash = hex(&Sha256::digest(body.as_bytes()));
+    let response = bucket
+        .request(client, Method::POST, url, &hash)
+        .body(Body::from(body))
+        .send()
+        .await?;
+    // Completion can fail after a 200, with the error in the body
+    let text = check(response).await?.text().await?;
+    match xml_value(&text, "Code") {
+        Some(code) => Err(Error::Upload(format!(
+            "S3 could not complete the upload: {code}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+pub(super) async fn abort(
+    client: &reqwest::Client,
+    target: &CloudTarget,
+    secret: &str,
+    key: &str,
+    upload_id: &str,
+) -> Result<()> {
+    let bucket = Bucket::new(target, secret)?;
+    let url = bucket.url(key, &[("uploadId", upload_id)])?;
+    let empty = hex(&Sha256::digest(b""));
+    check(
+        bucket
+            .request(client, Method::DELETE, url, &empty)
+            .send()
+            .await?,
+    )
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn upload(
+    file: &FileUpload<'_>,
+    target: &CloudTarget,
+    secret: &str,
+    state: &mut FileState,
+) -> Result<()> {
+    let bucket = Bucket::new(target, secret)?;
+    let size = state.size;
+    if size <= MIN_PART_SIZE {
+        let url = bucket.url(&state.key, &[])?;
+        with_retries(file, size, || put(file, &bucket, url.clone(), 0, size)).await?;
+        return Ok(());
+    }
+
+    let (upload_id, part_size, mut parts) = match state.session.take() {
+        Some(Session::S3 {
+            upload_id,
+            part_size,
+            parts,
+        }) => {
+            let sent: u64 = parts
+                .iter()
+                .map(|part| part_size.min(size - (u64::from(part.number) - 1) * part_size))
+                .sum();
+            file.skip(sent);
+            (upload_id, part_size, parts)
+        }
+        _ => {
+            let upload_id = create(file.client, &bucket, &state.key).await?;
+            (
+                upload_id,
+                MIN_PART_SIZE.max(size.div_ceil(MAX_PARTS)),
+                Vec::new(),
+            )
+        }
+    };
+    let record = |state: &mut FileState, parts: &[CompletedPart]| {
+        state.session = Some(Session::S3 {
+            upload_id: upload_id.clone(),
+            part_size,
+            parts: parts.to_vec(),
+        });
+        file.checkpoint(state);
+    };
+    record(state, &parts);
+
+    let count = size.div_ceil(part_size) as u32;
+    for number in 1..=count {
+        if parts.iter().any(|part| part.number == number) {
+            continue;
+        }
+        if file.is_cancelled() {
+            return Err(Error::Upload("Upload cancelled".into()));
+        }
+        let start = u64::from(number - 1) * part_size;
+        let len = part_size.min(size - start);
+        let number_text = number.to_string();
+        let url = bucket.url(
+            &state.key,
+            &[("partNumber", &number_text), ("uploadId", &upload_id)],
+        )?;
+        let e_tag = with_retries(file, len, || put(file, &bucket, url.clone(), start, len)).await?;
+        parts.push(CompletedPart { number, e_tag });
+        record(state, &parts);
+    }
+    parts.sort_by_key(|part| part.number);
+    complete(file.client, &bucket, &state.key, &upload_id, &parts).await
+}
@@ -0,0 +1,218 @@
+//! Delivery packages: renamed copies of version media plus a checksum manifest.
+//!
+//! Files are copied in parallel and hashed while they are copied, so each
+//! source is read once. Progress is reported as `delivery:progress` events.
+//! A package can go on to upload to a cloud bucket once built; see [`cloud`].
+
+pub mod cloud;
+mod package;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::checksums::ChecksumAlgorithm;
+use crate::error::{Error, Result};
+use crate::taskbar;
+
+pub const PROGRESS_EVENT: &str = "delivery:progress";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryItem {
+    pub path: PathBuf,
+    pub version_name: String,
+    #[serde(default)]
+    pub version_number: Option<u32>,
+    #[serde(default)]
+    pub shot: Option<String>,
+    #[serde(default)]
+    pub sequence: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Extra values available to the naming template as `{key}`.
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryRequest {
+    /// Folder the package directory is created in.
+    pub destination: PathBuf,
+    /// Name of the package directory.
+    pub name: String,
+    /// Relative path template, e.g. `{sequence}/{shot}/{name}_v{version}.{ext}`.
+    pub template: String,
+    pub items: Vec<DeliveryItem>,
+    #[serde(default)]
+    pub checksum: ChecksumAlgorithm,
+    #[serde(default = "default_manifests")]
+    pub manifests: Vec<ManifestFormat>,
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Cloud target to upload the package to once it is built.
+    #[serde(default)]
+    pub upload_target: Option<String>,
+}
+
+fn default_manifests() -> Vec<ManifestFormat> {
+    vec![ManifestFormat::Csv, ManifestFormat::Json]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeliveryStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliverySnapshot {
+    pub id: String,
+    pub package_dir: PathBuf,
+    pub status: DeliveryStatus,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub error: Option<String>,
+}
+
+struct Delivery {
+    cancelled: AtomicBool,
+    snapshot: Mutex<DeliverySnapshot>,
+    last_emit: Mutex<Instant>,
+}
+
+impl Delivery {
+    fn lock(&self) -> std::sync::MutexGuard<'_, DeliverySnapshot> {
+        self.snapshot
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn snapshot(&self) -> DeliverySnapshot {
+        self.lock().clone()
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Applies `f` and emits progress. Byte-level updates pass `throttle` so
+    /// parallel copies don't flood the webview.
+    fn update(&self, app: &AppHandle, throttle: bool, f: impl FnOnce(&mut DeliverySnapshot)) {
+        let snapshot = {
+            let mut snapshot = self.lock();
+            f(&mut snapshot);
+            snapshot.clone()
+        };
+        if throttle {
+            let mut last_emit = self
+                .last_emit
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if last_emit.elapsed() < PROGRESS_INTERVAL {
+                return;
+            }
+            *last_emit = Instant::now();
+        }
+        let source = format!("delivery:{}", snapshot.id);
+        match snapshot.status {
+            DeliveryStatus::Running => {
+                taskbar::update(app, &source, snapshot.bytes_done, snapshot.bytes_total)
+            }
+            _ => taskbar::finish(app, &source),
+        }
+        if let Err(err) = app.emit(PROGRESS_EVENT, snapshot) {
+            log::warn!("Failed to emit delivery progress: {err}");
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DeliveryManager {
+    deliveries: Mutex<HashMap<String, Arc<Delivery>>>,
+}
+
+/// Plans the package and starts copying in the background. Naming problems,
+/// such as two versions mapping to the same file, fail before anything is copied.
+#[tauri::command]
+pub async fn start_delivery(
+    app: AppHandle,
+    manager: State<'_, DeliveryManager>,
+    request: DeliveryRequest,
+) -> Result<DeliverySnapshot> {
+    let plan = tauri::async_runtime::spawn_blocking(move || package::plan(request)).await??;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let delivery = Arc::new(Delivery {
+        cancelled: AtomicBool::new(false),
+        snapshot: Mutex::new(DeliverySnapshot {
+            id: id.clone(),
+            package_dir: plan.package_dir.clone(),
+            status: DeliveryStatus::Running,
+            files_done: 0,
+            files_total: plan.files.len(),
+            bytes_done: 0,
+            bytes_total: plan.files.iter().map(|file| file.size).sum(),
+            error: None,
+        }),
+        last_emit: Mutex::new(Instant::now()),
+    });
+    manager
+        .deliveries
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(id, delivery.clone());
+
+    let snapshot = delivery.snapshot();
+    tauri::async_runtime::spawn(package::run(app, delivery, plan));
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub fn cancel_delivery(manager: State<'_, DeliveryManager>, id: String) -> Result<()> {
+    let delivery = manager
+        .deliveries
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown delivery: {id}")))?;
+    delivery.cancel();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_deliveries(manager: State<'_, DeliveryManager>) -> Vec<DeliverySnapshot> {
+    manager
+        .deliveries
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .values()
+        .map(|delivery| delivery.snapshot())
+        .collect()
+}
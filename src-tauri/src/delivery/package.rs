@@ -0,0 +1,335 @@
+//! Planning and copying one delivery package.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+use regex::Regex;
+use serde::Serialize;
+use tauri::AppHandle;
+use tokio::sync::Semaphore;
+
+use super::{Delivery, DeliveryItem, DeliveryRequest, DeliveryStatus, ManifestFormat, cloud};
+use crate::checksums::{ChecksumAlgorithm, Hasher};
+use crate::error::{Error, Result};
+use crate::export::{sanitize_file_name, write_atomically};
+use crate::notifications::{self, Kind};
+
+/// Files copied at once; enough to keep a NAS busy without thrashing a local disk.
+const PARALLEL_COPIES: usize = 4;
+const BUFFER_BYTES: usize = 4 * 1024 * 1024;
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{(\w+)\}").expect("valid placeholder pattern"));
+
+pub(super) struct PlannedFile {
+    source: PathBuf,
+    relative: PathBuf,
+    pub(super) size: u64,
+    item: DeliveryItem,
+}
+
+pub(super) struct Plan {
+    pub(super) package_dir: PathBuf,
+    pub(super) files: Vec<PlannedFile>,
+    checksum: ChecksumAlgorithm,
+    manifests: Vec<ManifestFormat>,
+    upload_target: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestEntry {
+    file: String,
+    source: String,
+    size_bytes: u64,
+    algorithm: &'static str,
+    checksum: String,
+    version_name: String,
+    version_number: Option<u32>,
+}
+
+/// Expands the template for one item. Substituted values may not introduce
+/// folders, only the template itself can.
+fn render(template: &str, item: &DeliveryItem) -> Result<PathBuf> {
+    let source = &item.path;
+    let ext = source
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    let stem = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+
+    let mut missing = None;
+    let rendered = PLACEHOLDER.replace_all(template, |caps: &regex::Captures<'_>| {
+        let key = &caps[1];
+        let value = match key {
+            "name" => Some(item.version_name.clone()),
+            "version" => item.version_number.map(|number| format!("{number:03}")),
+            "shot" => item.shot.clone(),
+            "sequence" => item.sequence.clone(),
+            "status" => item.status.clone(),
+            "ext" => Some(ext.to_string()),
+            "stem" => Some(stem.to_string()),
+            _ => item.fields.get(key).cloned(),
+        };
+        match value {
+            Some(value) => sanitize_file_name(&value),
+            None => {
+                missing.get_or_insert_with(|| key.to_string());
+                String::new()
+            }
+        }
+    });
+    if let Some(key) = missing {
+        return Err(Error::InvalidInput(format!(
+            "{} has no value for {{{key}}}",
+            item.version_name
+        )));
+    }
+
+    let relative = PathBuf::from(rendered.as_ref());
+    let escapes = relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)));
+    if escapes || relative.as_os_str().is_empty() {
+        return Err(Error::InvalidInput(format!(
+            "Template produced an invalid path: {rendered}"
+        )));
+    }
+    Ok(relative)
+}
+
+pub(super) fn plan(request: DeliveryRequest) -> Result<Plan> {
+    let name = sanitize_file_name(&request.name);
+    if name.is_empty() {
+        return Err(Error::InvalidInput("Delivery name is empty".into()));
+    }
+    let package_dir = request.destination.join(name);
+
+    let mut seen = HashSet::new();
+    let mut files = Vec::with_capacity(request.items.len());
+    for item in request.items {
+        let relative = render(&request.template, &item)?;
+        // Compare case-insensitively; deliveries often land on case-insensitive volumes
+        if !seen.insert(relative.to_string_lossy().to_lowercase()) {
+            return Err(Error::InvalidInput(format!(
+                "More than one file would be delivered as {}",
+                relative.display()
+            )));
+        }
+        let size = fs::metadata(&item.path)
+            .map_err(|err| Error::InvalidInput(format!("{}: {err}", item.path.display())))?
+            .len();
+        if !request.overwrite && package_dir.join(&relative).exists() {
+            return Err(Error::InvalidInput(format!(
+                "{} already exists",
+                package_dir.join(&relative).display()
+            )));
+        }
+        files.push(PlannedFile {
+            source: item.path.clone(),
+            relative,
+            size,
+            item,
+        });
+    }
+
+    Ok(Plan {
+        package_dir,
+        files,
+        checksum: request.checksum,
+        manifests: request.manifests,
+        upload_target: request.upload_target,
+    })
+}
+
+enum Copied {
+    Done(String),
+    Cancelled,
+}
+
+/// Copies through a `.part` file while hashing the bytes read.
+fn copy_file(
+    app: &AppHandle,
+    delivery: &Delivery,
+    file: &PlannedFile,
+    dest: &Path,
+    algorithm: ChecksumAlgorithm,
+) -> Result<Copied> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut part = dest.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+
+    let result = (|| {
+        let mut reader = File::open(&file.source)?;
+        let mut writer = File::create(&part)?;
+        let mut hasher = Hasher::new(algorithm);
+        let mut buffer = vec![0; BUFFER_BYTES];
+        loop {
+            if delivery.is_cancelled() {
+                return Ok(Copied::Cancelled);
+            }
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            writer.write_all(&buffer[..read])?;
+            delivery.update(app, true, |snapshot| snapshot.bytes_done += read as u64);
+        }
+        writer.sync_all()?;
+        Ok(Copied::Done(hasher.finish()))
+    })();
+
+    match result {
+        Ok(Copied::Done(checksum)) => {
+            fs::rename(&part, dest)?;
+            Ok(Copied::Done(checksum))
+        }
+        other => {
+            let _ = fs::remove_file(&part);
+            other
+        }
+    }
+}
+
+fn write_manifests(plan: &Plan, entries: &[ManifestEntry]) -> Result<()> {
+    for format in &plan.manifests {
+        match format {
+            ManifestFormat::Csv => {
+                write_atomically(&plan.package_dir.join("manifest.csv"), |temp| {
+                    let mut writer = ::csv::Writer::from_path(temp)?;
+                    for entry in entries {
+                        writer.serialize(entry)?;
+                    }
+                    writer.flush()?;
+                    Ok(())
+                })?
+            }
+            ManifestFormat::Json => {
+                write_atomically(&plan.package_dir.join("manifest.json"), |temp| {
+                    fs::write(temp, serde_json::to_vec_pretty(entries)?)?;
+                    Ok(())
+                })?
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(super) async fn run(app: AppHandle, delivery: Arc<Delivery>, plan: Plan) {
+    let plan = Arc::new(plan);
+    let semaphore = Arc::new(Semaphore::new(PARALLEL_COPIES));
+    let mut tasks = Vec::with_capacity(plan.files.len());
+    for index in 0..plan.files.len() {
+        let (app, delivery, plan, semaphore) = (
+            app.clone(),
+            delivery.clone(),
+            plan.clone(),
+            semaphore.clone(),
+        );
+        tasks.push(tauri::async_runtime::spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            tauri::async_runtime::spawn_blocking(move || {
+                let file = &plan.files[index];
+                let dest = plan.package_dir.join(&file.relative);
+                let copied = copy_file(&app, &delivery, file, &dest, plan.checksum);
+                if let Ok(Copied::Done(_)) = copied {
+                    delivery.update(&app, false, |snapshot| snapshot.files_done += 1);
+                }
+                copied
+            })
+            .await
+        }));
+    }
+
+    let mut entries = Vec::with_capacity(tasks.len());
+    let mut failure = None;
+    for (task, file) in tasks.into_iter().zip(&plan.files) {
+        let outcome = match task.await {
+            Ok(Ok(result)) => result,
+            Ok(Err(err)) => Err(err.into()),
+            Err(err) => Err(err.into()),
+        };
+        match outcome {
+            Ok(Copied::Done(checksum)) => entries.push(ManifestEntry {
+                file: file.relative.to_string_lossy().replace('\\', "/"),
+                source: file.source.display().to_string(),
+                size_bytes: file.size,
+                algorithm: plan.checksum.name(),
+                checksum,
+                version_name: file.item.version_name.clone(),
+                version_number: file.item.version_number,
+            }),
+            Ok(Copied::Cancelled) => {}
+            Err(err) => {
+                // Stop the remaining copies; one bad file invalidates the package
+                delivery
+                    .cancelled
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+                failure.get_or_insert(format!("{}: {err}", file.source.display()));
+            }
+        }
+    }
+
+    let result = match failure {
+        Some(message) => Err(message),
+        None if delivery.is_cancelled() => {
+            delivery.update(&app, false, |snapshot| {
+                snapshot.status = DeliveryStatus::Cancelled
+            });
+            return;
+        }
+        None => write_manifests(&plan, &entries).map_err(|err| err.to_string()),
+    };
+    delivery.update(&app, false, |snapshot| match result {
+        Ok(()) => snapshot.status = DeliveryStatus::Completed,
+        Err(message) => {
+            log::warn!("Delivery {} failed: {message}", snapshot.id);
+            snapshot.status = DeliveryStatus::Failed;
+            snapshot.error = Some(message);
+        }
+    });
+
+    let snapshot = delivery.snapshot();
+    let name = snapshot
+        .package_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (title, body) = match snapshot.status {
+        DeliveryStatus::Completed => (
+            "Delivery package built",
+            format!("{name}: {} files", snapshot.files_total),
+        ),
+        _ => (
+            "Delivery failed",
+            format!("{name}: {}", snapshot.error.unwrap_or_default()),
+        ),
+    };
+    notifications::notify(&app, Kind::Delivery, Some(snapshot.id), title, &body);
+
+    if let (DeliveryStatus::Completed, Some(target)) = (snapshot.status, &plan.upload_target)
+        && let Err(err) = cloud::start(&app, &snapshot.package_dir, target)
+    {
+        log::error!(
+            "Failed to start upload of {}: {err}",
+            snapshot.package_dir.display()
+        );
+        notifications::notify(
+            &app,
+            Kind::Delivery,
+            None,
+            "Delivery upload failed",
+            &err.to_string(),
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Secondary windows for the note editor and media viewer.
+//!
+//! A detached window loads the same frontend with `?window=<kind>` and reads
+//! its starting context with `get_window_context`. After that, the main
+//! window pushes updates with `route_to_windows`; they arrive as
+//! `detached:state` in every open window of that kind. When a detached
+//! window closes, the main window gets `detached:closed`. Closing the main
+//! window closes every other window too, so none is left running on its own.
+//! The first window of each kind takes the kind as its label, so its
+//! position is remembered by `window_state`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, WebviewUrl, WebviewWindowBuilder};
+
+use crate::error::{Error, Result};
+use crate::window_state;
+
+pub const STATE_EVENT: &str = "detached:state";
+pub const CLOSED_EVENT: &str = "detached:closed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowKind {
+    NoteEditor,
+    MediaViewer,
+}
+
+impl WindowKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::NoteEditor => "note-editor",
+            Self::MediaViewer => "media-viewer",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::NoteEditor => "AstraNotes - Notes",
+            Self::MediaViewer => "AstraNotes - Viewer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetachedWindow {
+    pub label: String,
+    pub kind: WindowKind,
+    pub context: Value,
+}
+
+#[derive(Default)]
+pub struct DetachedWindows {
+    windows: Mutex<HashMap<String, DetachedWindow>>,
+}
+
+impl DetachedWindows {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, DetachedWindow>> {
+        self.windows
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Called for window events. Keeps the registry in sync and closes other
+/// windows with the main one.
+pub fn on_window_event(window: &tauri::Window, event: &tauri::WindowEvent) {
+    let app = window.app_handle();
+    let Some(detached) = app.try_state::<DetachedWindows>() else {
+        return;
+    };
+    match event {
+        tauri::WindowEvent::CloseRequested { .. } if window.label() == "main" => {
+            // Includes the presentation window, which is not tracked here
+            for (label, window) in app.webview_windows() {
+                if label != "main"
+                    && let Err(err) = window.close()
+                {
+                    log::warn!("Failed to close {label}: {err}");
+                }
+            }
+        }
+        tauri::WindowEvent::Destroyed => {
+            let Some(closed) = detached.lock().remove(window.label()) else {
+                return;
+            };
+            if let Err(err) = app.emit_to("main", CLOSED_EVENT, closed) {
+                log::warn!("Failed to emit detached window close: {err}");
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Opens a window of `kind`, or focuses the open one and replaces its context
+/// unless `new_window` is set.
+#[tauri::command]
+pub async fn open_detached_window(
+    app: AppHandle,
+    detached: State<'_, DetachedWindows>,
+    kind: WindowKind,
+    context: Option<Value>,
+    new_window: Option<bool>,
+) -> Result<DetachedWindow> {
+    let context = context.unwrap_or(Value::Null);
+    let existing = detached
+        .lock()
+        .values()
+        .find(|window| window.kind == kind)
+        .map(|window| window.label.clone());
+    if let Some(label) = existing.filter(|_| !new_window.unwrap_or(false))
+        && let Some(window) = app.get_webview_window(&label)
+    {
+        let updated = DetachedWindow {
+            label: label.clone(),
+            kind,
+            context,
+        };
+        detached.lock().insert(label.clone(), updated.clone());
+        app.emit_to(
+            EventTarget::webview_window(&label),
+            STATE_EVENT,
+            &updated.context,
+        )?;
+        window.set_focus()?;
+        return Ok(updated);
+    }
+
+    let label = {
+        let windows = detached.lock();
+        let base = kind.as_str();
+        (1..)
+            .map(|n| match n {
+                1 => base.to_string(),
+                n => format!("{base}-{n}"),
+            })
+            .find(|label| !windows.contains_key(label) && app.get_webview_window(label).is_none())
+            .expect("unbounded label search")
+    };
+    let window = DetachedWindow {
+        label: label.clone(),
+        kind,
+        context,
+    };
+    detached.lock().insert(label.clone(), window.clone());
+
+    let url = WebviewUrl::App(format!("index.html?window={}", kind.as_str()).into());
+    let built = WebviewWindowBuilder::new(&app, &label, url)
+        .title(kind.title())
+        .inner_size(1100.0, 750.0)
+        .min_inner_size(480.0, 320.0)
+        .visible(false)
+        .build();
+    let webview = match built {
+        Ok(webview) => webview,
+        Err(err) => {
+            detached.lock().remove(&label);
+            return Err(err.into());
+        }
+    };
+    window_state::restore(&webview);
+    webview.show()?;
+    webview.set_focus()?;
+    Ok(window)
+}
+
+/// Starting context for the calling window.
+#[tauri::command]
+pub fn get_window_context(
+    window: tauri::WebviewWindow,
+    detached: State<'_, DetachedWindows>,
+) -> Option<DetachedWindow> {
+    detached.lock().get(window.label()).cloned()
+}
+
+#[tauri::command]
+pub fn list_detached_windows(detached: State<'_, DetachedWindows>) -> Vec<DetachedWindow> {
+    detached.lock().values().cloned().collect()
+}
+
+/// Sends `payload` as `detached:state` to every window of `kind`, or to every
+/// detached window when `kind` is `None`. Returns how many windows got it.
+#[tauri::command]
+pub fn route_to_windows(
+    app: AppHandle,
+    detached: State<'_, DetachedWindows>,
+    kind: Option<WindowKind>,
+    payload: Value,
+) -> Result<usize> {
+    let labels: Vec<String> = detached
+        .lock()
+        .values()
+        .filter(|window| kind.is_none_or(|kind| window.kind == kind))
+        .map(|window| window.label.clone())
+        .collect();
+    for label in &labels {
+        app.emit_to(EventTarget::webview_window(label), STATE_EVENT, &payload)?;
+    }
+    Ok(labels.len())
+}
+
+#[tauri::command]
+pub fn close_detached_window(app: AppHandle, label: String) -> Result<()> {
+    if label == "main" {
+        return Err(Error::InvalidInput(
+            "The main window cannot be closed here".into(),
+        ));
+    }
+    match app.get_webview_window(&label) {
+        Some(window) => Ok(window.close()?),
+        None => Err(Error::InvalidInput(format!("No window {label}"))),
+    }
+}
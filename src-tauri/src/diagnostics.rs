@@ -0,0 +1,449 @@
+//! Self-test of what support threads usually end up asking about.
+//!
+//! `run_diagnostics` checks how startup went, the tracker server and the
+//! stored credentials, the keychain, the disk cache and the free space under
+//! it, the ffmpeg tools, the update endpoint and write access to the app
+//! directories, all at once. The report is structured for the settings page to render and is
+//! kept until the next run, so `export_logs` can ship it with the logs.
+
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::connectivity::{self, ConnectivityState};
+use crate::credentials;
+use crate::error::Result;
+use crate::ftrack::cache::ApiCache;
+use crate::media::frames;
+use crate::paths;
+use crate::providers::ProviderKind;
+use crate::resources;
+use crate::startup::Startup;
+use crate::store::Store;
+use crate::thumbnail_cache::ThumbnailCache;
+use crate::updates;
+
+const MIB: u64 = 1024 * 1024;
+/// Free space under which media caching and exports start failing.
+const LOW_SPACE_BYTES: u64 = 2 * 1024 * MIB;
+const CRITICAL_SPACE_BYTES: u64 = 500 * MIB;
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(15);
+/// Written and removed again by the keychain check.
+const PROBE_CREDENTIAL: &str = "diagnostics-probe";
+const PROBE_FILE: &str = ".astranotes-write-test";
+
+/// Ordered by severity, so the report's status is the maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    /// Not applicable here, such as authentication without a profile.
+    Skipped,
+    Passed,
+    Warning,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticCheck {
+    /// Stable identifier, such as `server` or `diskSpace`.
+    pub id: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub status: CheckStatus,
+    pub app_version: String,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub portable: bool,
+    pub checks: Vec<DiagnosticCheck>,
+    pub started_at: String,
+    pub duration_ms: u64,
+}
+
+/// The last report, for the support bundle.
+#[derive(Default)]
+pub struct Diagnostics {
+    last: Mutex<Option<DiagnosticsReport>>,
+}
+
+impl Diagnostics {
+    pub fn last(&self) -> Option<DiagnosticsReport> {
+        self.last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+struct Outcome {
+    status: CheckStatus,
+    detail: String,
+}
+
+impl Outcome {
+    fn new(status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+async fn timed(id: &'static str, check: impl Future<Output = Outcome>) -> DiagnosticCheck {
+    let started = Instant::now();
+    let outcome = check.await;
+    DiagnosticCheck {
+        id,
+        status: outcome.status,
+        detail: outcome.detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Runs blocking work off the async runtime, turning any error into a failure.
+async fn blocking(app: &AppHandle, check: fn(&AppHandle) -> Result<Outcome>) -> Outcome {
+    let app = app.clone();
+    match tauri::async_runtime::spawn_blocking(move || check(&app)).await {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(err)) => Outcome::new(CheckStatus::Failed, err.to_string()),
+        Err(err) => Outcome::new(CheckStatus::Failed, err.to_string()),
+    }
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{} MB", bytes / MIB)
+}
+
+/// Probes the active profile's server, returning the reachability and
+/// authentication checks.
+async fn server(app: &AppHandle) -> (DiagnosticCheck, DiagnosticCheck) {
+    let check = |id, outcome: Outcome, duration_ms| DiagnosticCheck {
+        id,
+        status: outcome.status,
+        detail: outcome.detail,
+        duration_ms,
+    };
+    let Some(store) = app.try_state::<Store>() else {
+        let failed = || Outcome::new(CheckStatus::Failed, "The local database is not open");
+        return (
+            check("server", failed(), 0),
+            check("authentication", failed(), 0),
+        );
+    };
+    let started = Instant::now();
+    let status = connectivity::check(app).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let provider = store
+        .active_profile()
+        .ok()
+        .flatten()
+        .map(|profile| profile.provider);
+    let server = status.server_url.as_deref().unwrap_or("the server");
+    let detail = status.detail.clone().unwrap_or_default();
+
+    let (reachable, auth) = match status.state {
+        ConnectivityState::Unknown => (
+            Outcome::new(CheckStatus::Skipped, detail),
+            Outcome::new(CheckStatus::Skipped, "No active profile"),
+        ),
+        ConnectivityState::Online => {
+            let latency = status.latency_ms.unwrap_or_default();
+            let auth = if provider == Some(ProviderKind::Ftrack) {
+                Outcome::new(CheckStatus::Passed, "The server accepted the API key")
+            } else {
+                Outcome::new(CheckStatus::Skipped, "Not checked for this provider")
+            };
+            (
+                Outcome::new(
+                    CheckStatus::Passed,
+                    format!("Reached {server} in {latency} ms"),
+                ),
+                auth,
+            )
+        }
+        ConnectivityState::AuthExpired => (
+            Outcome::new(CheckStatus::Passed, format!("Reached {server}")),
+            Outcome::new(
+                CheckStatus::Failed,
+                format!("The server rejected the credentials: {detail}"),
+            ),
+        ),
+        ConnectivityState::Offline => (
+            Outcome::new(
+                CheckStatus::Failed,
+                format!("No network connection: {detail}"),
+            ),
+            Outcome::new(CheckStatus::Skipped, "The server could not be reached"),
+        ),
+        ConnectivityState::CaptivePortal => (
+            Outcome::new(
+                CheckStatus::Failed,
+                format!("A captive portal is intercepting requests: {detail}"),
+            ),
+            Outcome::new(CheckStatus::Skipped, "The server could not be reached"),
+        ),
+        ConnectivityState::ServerDown => (
+            Outcome::new(
+                CheckStatus::Failed,
+                format!("The network works but {server} does not: {detail}"),
+            ),
+            Outcome::new(CheckStatus::Skipped, "The server could not be reached"),
+        ),
+    };
+    (
+        check("server", reachable, duration_ms),
+        check("authentication", auth, duration_ms),
+    )
+}
+
+/// Reports stages of startup that failed or have not run yet.
+fn startup(app: &AppHandle) -> Outcome {
+    let Some(startup) = app.try_state::<Startup>() else {
+        return Outcome::new(CheckStatus::Failed, "Startup was not recorded");
+    };
+    let report = startup.report();
+    let failures: Vec<String> = report
+        .stages
+        .iter()
+        .filter_map(|stage| {
+            let err = stage.error.as_ref()?;
+            Some(format!("{} stage failed: {err}", stage.name))
+        })
+        .collect();
+    if !failures.is_empty() {
+        Outcome::new(CheckStatus::Failed, failures.join("; "))
+    } else if !report.ready {
+        Outcome::new(CheckStatus::Warning, "Startup has not finished yet")
+    } else {
+        Outcome::new(
+            CheckStatus::Passed,
+            format!(
+                "{} stages finished {} ms after launch",
+                report.stages.len(),
+                report
+                    .stages
+                    .iter()
+                    .map(|stage| stage.started_ms + stage.duration_ms)
+                    .max()
+                    .unwrap_or_default()
+            ),
+        )
+    }
+}
+
+/// Stores, reads back and deletes a throwaway secret.
+fn keychain(_app: &AppHandle) -> Result<Outcome> {
+    let secret = uuid::Uuid::new_v4().to_string();
+    credentials::store(PROBE_CREDENTIAL, &secret)?;
+    let read = credentials::get(PROBE_CREDENTIAL);
+    credentials::delete(PROBE_CREDENTIAL)?;
+    Ok(match read? {
+        Some(read) if read == secret => Outcome::new(CheckStatus::Passed, "Read and write work"),
+        Some(_) => Outcome::new(
+            CheckStatus::Failed,
+            "The keychain returned a different secret",
+        ),
+        None => Outcome::new(
+            CheckStatus::Failed,
+            "The keychain did not keep a stored secret",
+        ),
+    })
+}
+
+/// Reads both cache indexes and compares the cache size with its limit.
+fn cache(app: &AppHandle) -> Result<Outcome> {
+    let (Some(thumbnails), Some(api)) = (
+        app.try_state::<ThumbnailCache>(),
+        app.try_state::<ApiCache>(),
+    ) else {
+        return Ok(Outcome::new(CheckStatus::Failed, "The caches are not open"));
+    };
+    let thumbnails = thumbnails.size_bytes()?;
+    let api = api.stats()?;
+    let total = resources::dir_size(&paths::cache_dir(app)?);
+    let limit = resources::load_settings(app)?.max_cache_mb * MIB;
+    let detail = format!(
+        "{} in total, {} of thumbnails, {} in {} API responses",
+        megabytes(total),
+        megabytes(thumbnails),
+        megabytes(api.size_bytes),
+        api.entries
+    );
+    Ok(if total > limit {
+        Outcome::new(
+            CheckStatus::Warning,
+            format!("{detail}; over the {} limit", megabytes(limit)),
+        )
+    } else {
+        Outcome::new(CheckStatus::Passed, detail)
+    })
+}
+
+fn disk_space(app: &AppHandle) -> Result<Outcome> {
+    let mut lowest: Option<(u64, PathBuf)> = None;
+    for dir in [paths::cache_dir(app)?, paths::data_dir(app)?] {
+        fs::create_dir_all(&dir)?;
+        let available = fs4::available_space(&dir)?;
+        if lowest.as_ref().is_none_or(|(bytes, _)| available < *bytes) {
+            lowest = Some((available, dir));
+        }
+    }
+    let Some((available, dir)) = lowest else {
+        return Ok(Outcome::new(
+            CheckStatus::Skipped,
+            "No directories to check",
+        ));
+    };
+    let detail = format!("{} free for {}", megabytes(available), dir.display());
+    let status = if available < CRITICAL_SPACE_BYTES {
+        CheckStatus::Failed
+    } else if available < LOW_SPACE_BYTES {
+        CheckStatus::Warning
+    } else {
+        CheckStatus::Passed
+    };
+    Ok(Outcome::new(status, detail))
+}
+
+/// Runs `name -version` and reports the first line it prints.
+async fn media_tool(app: &AppHandle, name: &str) -> Outcome {
+    match frames::run(app, name, vec!["-version".into()]).await {
+        Ok(output) => {
+            let version = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .unwrap_or(name)
+                .to_string();
+            Outcome::new(CheckStatus::Passed, version)
+        }
+        Err(err) => Outcome::new(CheckStatus::Failed, err.to_string()),
+    }
+}
+
+async fn updater(app: &AppHandle) -> Outcome {
+    let channel = updates::load_settings(app)
+        .map(|settings| settings.channel)
+        .unwrap_or_default();
+    match tokio::time::timeout(UPDATE_TIMEOUT, updates::check(app)).await {
+        Ok(Ok(Some(info))) => Outcome::new(
+            CheckStatus::Passed,
+            format!("Version {} is available on {channel:?}", info.version),
+        ),
+        Ok(Ok(None)) => Outcome::new(CheckStatus::Passed, format!("Up to date on {channel:?}")),
+        Ok(Err(err)) => Outcome::new(CheckStatus::Failed, err.to_string()),
+        Err(_) => Outcome::new(
+            CheckStatus::Failed,
+            format!(
+                "The update endpoint did not answer within {}s",
+                UPDATE_TIMEOUT.as_secs()
+            ),
+        ),
+    }
+}
+
+fn try_write(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(PROBE_FILE);
+    fs::write(&probe, b"ok")?;
+    fs::remove_file(&probe)
+}
+
+fn write_access(app: &AppHandle) -> Result<Outcome> {
+    let dirs = [
+        ("config", paths::config_dir(app)?),
+        ("data", paths::data_dir(app)?),
+        ("cache", paths::cache_dir(app)?),
+        ("log", paths::log_dir(app)?),
+    ];
+    let failures: Vec<String> = dirs
+        .iter()
+        .filter_map(|(name, dir)| {
+            try_write(dir)
+                .err()
+                .map(|err| format!("{name} directory {}: {err}", dir.display()))
+        })
+        .collect();
+    Ok(if failures.is_empty() {
+        Outcome::new(
+            CheckStatus::Passed,
+            "Config, data, cache and log directories are writable",
+        )
+    } else {
+        Outcome::new(CheckStatus::Failed, failures.join("; "))
+    })
+}
+
+/// Runs every check and keeps the report for the support bundle.
+#[tauri::command]
+pub async fn run_diagnostics(
+    app: AppHandle,
+    diagnostics: State<'_, Diagnostics>,
+) -> Result<DiagnosticsReport> {
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let started = Instant::now();
+    let startup = timed("startup", async { startup(&app) }).await;
+    let ((server, authentication), keychain, cache, disk_space, ffmpeg, ffprobe, updater, write) = tokio::join!(
+        server(&app),
+        timed("keychain", blocking(&app, keychain)),
+        timed("cache", blocking(&app, cache)),
+        timed("diskSpace", blocking(&app, disk_space)),
+        timed("ffmpeg", media_tool(&app, "ffmpeg")),
+        timed("ffprobe", media_tool(&app, "ffprobe")),
+        timed("updater", updater(&app)),
+        timed("writeAccess", blocking(&app, write_access)),
+    );
+    let checks = vec![
+        startup,
+        server,
+        authentication,
+        keychain,
+        cache,
+        disk_space,
+        ffmpeg,
+        ffprobe,
+        updater,
+        write,
+    ];
+    for check in checks
+        .iter()
+        .filter(|check| check.status >= CheckStatus::Warning)
+    {
+        log::warn!(
+            "Diagnostics: {} {:?}: {}",
+            check.id,
+            check.status,
+            check.detail
+        );
+    }
+
+    let report = DiagnosticsReport {
+        status: checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(CheckStatus::Skipped),
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        portable: paths::portable_root().is_some(),
+        checks,
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    *diagnostics
+        .last
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(report.clone());
+    Ok(report)
+}
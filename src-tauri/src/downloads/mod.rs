@@ -0,0 +1,264 @@
+//! Streamed downloads with pause, resume and progress events.
+//!
+//! Each download writes to `<dest>.part` and resumes with an HTTP range
+//! request from the bytes already on disk, so multi-GB review media survives
+//! pauses and dropped connections without starting over.
+
+mod transfer;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::watch;
+
+use crate::error::{Error, Result};
+use crate::network;
+use crate::taskbar;
+use crate::transfers::Priority;
+
+pub const PROGRESS_EVENT: &str = "downloads:progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Run,
+    Pause,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadStatus {
+    Downloading,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadSnapshot {
+    pub id: String,
+    pub url: String,
+    pub dest: PathBuf,
+    pub status: DownloadStatus,
+    pub received_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub error: Option<String>,
+}
+
+struct Download {
+    control: watch::Sender<Control>,
+    priority: Priority,
+    snapshot: Mutex<DownloadSnapshot>,
+}
+
+impl Download {
+    fn snapshot(&self) -> DownloadSnapshot {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DownloadSnapshot> {
+        self.snapshot
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn part_path(&self) -> PathBuf {
+        let mut part = self.lock().dest.clone().into_os_string();
+        part.push(".part");
+        PathBuf::from(part)
+    }
+
+    fn update(&self, app: &AppHandle, f: impl FnOnce(&mut DownloadSnapshot)) {
+        let snapshot = {
+            let mut snapshot = self.lock();
+            f(&mut snapshot);
+            snapshot.clone()
+        };
+        let source = format!("download:{}", snapshot.id);
+        let total = snapshot.total_bytes.unwrap_or_default();
+        match snapshot.status {
+            DownloadStatus::Downloading => {
+                taskbar::update(app, &source, snapshot.received_bytes, total)
+            }
+            DownloadStatus::Paused => taskbar::pause(app, &source, snapshot.received_bytes, total),
+            _ => taskbar::finish(app, &source),
+        }
+        if let Err(err) = app.emit(PROGRESS_EVENT, snapshot) {
+            log::warn!("Failed to emit download progress: {err}");
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DownloadManager {
+    downloads: Mutex<HashMap<String, Arc<Download>>>,
+}
+
+impl DownloadManager {
+    fn get(&self, id: &str) -> Result<Arc<Download>> {
+        self.downloads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown download: {id}")))
+    }
+
+    /// Whether `path` is the destination of a download that finished in this session.
+    pub fn is_completed(&self, path: &std::path::Path) -> bool {
+        self.downloads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .map(|download| download.snapshot())
+            .any(|snapshot| {
+                snapshot.status == DownloadStatus::Completed
+                    && snapshot.dest.canonicalize().is_ok_and(|dest| dest == path)
+            })
+    }
+
+    /// Pauses every running download, returning their IDs.
+    pub fn pause_running(&self) -> Vec<String> {
+        let downloads = self
+            .downloads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        downloads
+            .iter()
+            .filter(|(_, download)| *download.control.borrow() == Control::Run)
+            .filter(|(_, download)| download.snapshot().status == DownloadStatus::Downloading)
+            .map(|(id, download)| {
+                download.control.send_replace(Control::Pause);
+                id.clone()
+            })
+            .collect()
+    }
+
+    /// Resumes those of `ids` still paused, returning how many were.
+    pub fn resume_paused(&self, ids: &[String]) -> usize {
+        let downloads = self
+            .downloads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut resumed = 0;
+        for download in ids.iter().filter_map(|id| downloads.get(id)) {
+            if *download.control.borrow() == Control::Pause {
+                download.control.send_replace(Control::Run);
+                resumed += 1;
+            }
+        }
+        resumed
+    }
+
+    fn spawn(&self, app: &AppHandle, download: Arc<Download>) {
+        let app = app.clone();
+        let client = network::client();
+        let control = download.control.subscribe();
+        tauri::async_runtime::spawn(transfer::run(app, client, download, control));
+    }
+}
+
+#[tauri::command]
+pub fn start_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    url: String,
+    dest: PathBuf,
+    priority: Option<Priority>,
+) -> Result<DownloadSnapshot> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let (control, _) = watch::channel(Control::Run);
+    let download = Arc::new(Download {
+        control,
+        priority: priority.unwrap_or_default(),
+        snapshot: Mutex::new(DownloadSnapshot {
+            id: id.clone(),
+            url,
+            dest,
+            status: DownloadStatus::Downloading,
+            received_bytes: 0,
+            total_bytes: None,
+            error: None,
+        }),
+    });
+
+    manager
+        .downloads
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(id, download.clone());
+    manager.spawn(&app, download.clone());
+    Ok(download.snapshot())
+}
+
+#[tauri::command]
+pub fn pause_download(manager: State<'_, DownloadManager>, id: String) -> Result<()> {
+    let download = manager.get(&id)?;
+    if download.snapshot().status != DownloadStatus::Downloading {
+        return Err(Error::InvalidInput(format!("Download {id} is not running")));
+    }
+    download.control.send_replace(Control::Pause);
+    Ok(())
+}
+
+/// Resumes a paused download, or restarts a failed one from the bytes already on disk.
+#[tauri::command]
+pub fn resume_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    id: String,
+) -> Result<()> {
+    let download = manager.get(&id)?;
+    match download.snapshot().status {
+        DownloadStatus::Paused => {
+            download.control.send_replace(Control::Run);
+        }
+        DownloadStatus::Failed => {
+            download.control.send_replace(Control::Run);
+            download.update(&app, |snapshot| {
+                snapshot.status = DownloadStatus::Downloading;
+                snapshot.error = None;
+            });
+            manager.spawn(&app, download);
+        }
+        _ => return Err(Error::InvalidInput(format!("Download {id} is not paused"))),
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn cancel_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    id: String,
+) -> Result<()> {
+    let download = manager.get(&id)?;
+    match download.snapshot().status {
+        DownloadStatus::Downloading | DownloadStatus::Paused => {
+            download.control.send_replace(Control::Cancel);
+        }
+        // No task is running, so clean up the partial file here
+        DownloadStatus::Failed => {
+            let _ = tokio::fs::remove_file(download.part_path()).await;
+            download.update(&app, |snapshot| snapshot.status = DownloadStatus::Cancelled);
+        }
+        DownloadStatus::Completed | DownloadStatus::Cancelled => {}
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_downloads(manager: State<'_, DownloadManager>) -> Vec<DownloadSnapshot> {
+    manager
+        .downloads
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .values()
+        .map(|download| download.snapshot())
+        .collect()
+}
@@ -0,0 +1,200 @@
+//! Task driving a single download through pauses and resumes.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use tauri::AppHandle;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+
+use super::{Control, Download, DownloadStatus};
+use crate::error::Result;
+use crate::notifications::{self, Kind};
+use crate::transfers::{self, Transfer, TransferKind};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+enum Outcome {
+    Complete,
+    /// Stopped because the control changed to pause or cancel.
+    Interrupted,
+}
+
+pub(super) async fn run(
+    app: AppHandle,
+    client: reqwest::Client,
+    download: Arc<Download>,
+    mut control: watch::Receiver<Control>,
+) {
+    let part = download.part_path();
+    let snapshot = download.snapshot();
+    let label = snapshot
+        .dest
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or(snapshot.url);
+    let throttle = transfers::register(
+        &app,
+        format!("download:{}", snapshot.id),
+        TransferKind::Download,
+        label,
+        download.priority,
+        None,
+    );
+    loop {
+        let current = *control.borrow_and_update();
+        match current {
+            Control::Cancel => {
+                let _ = fs::remove_file(&part).await;
+                download.update(&app, |snapshot| snapshot.status = DownloadStatus::Cancelled);
+                return;
+            }
+            Control::Pause => {
+                download.update(&app, |snapshot| snapshot.status = DownloadStatus::Paused);
+                if control.changed().await.is_err() {
+                    return;
+                }
+                continue;
+            }
+            Control::Run => {}
+        }
+
+        download.update(&app, |snapshot| {
+            snapshot.status = DownloadStatus::Downloading
+        });
+        match transfer(&app, &client, &download, &throttle, &mut control).await {
+            Ok(Outcome::Complete) => {
+                let dest = download.snapshot().dest;
+                let result = fs::rename(&part, &dest).await;
+                download.update(&app, |snapshot| match result {
+                    Ok(()) => snapshot.status = DownloadStatus::Completed,
+                    Err(err) => {
+                        snapshot.status = DownloadStatus::Failed;
+                        snapshot.error = Some(err.to_string());
+                    }
+                });
+                notify_finished(&app, &download);
+                return;
+            }
+            Ok(Outcome::Interrupted) => {}
+            Err(err) => {
+                log::warn!("Download {} failed: {err}", download.snapshot().url);
+                download.update(&app, |snapshot| {
+                    snapshot.status = DownloadStatus::Failed;
+                    snapshot.error = Some(err.to_string());
+                });
+                notify_finished(&app, &download);
+                return;
+            }
+        }
+    }
+}
+
+fn notify_finished(app: &AppHandle, download: &Download) {
+    let snapshot = download.snapshot();
+    let name = snapshot
+        .dest
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| snapshot.url.clone());
+    let (title, body) = match snapshot.status {
+        DownloadStatus::Completed => ("Download finished", name),
+        _ => (
+            "Download failed",
+            format!("{name}: {}", snapshot.error.unwrap_or_default()),
+        ),
+    };
+    notifications::notify(app, Kind::Download, Some(snapshot.id), title, &body);
+}
+
+async fn transfer(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    download: &Download,
+    throttle: &Transfer,
+    control: &mut watch::Receiver<Control>,
+) -> Result<Outcome> {
+    let snapshot = download.snapshot();
+    let (url, dest) = (snapshot.url, snapshot.dest);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let part = download.part_path();
+    let offset = fs::metadata(&part)
+        .await
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+
+    let mut request = client.get(&url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+    let response = request.send().await?;
+
+    // The server has nothing past what is already on disk
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(Outcome::Complete);
+    }
+    let response = response.error_for_status()?;
+
+    let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+    let (mut received, total) = if resumed {
+        let total = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit('/').next())
+            .and_then(|total| total.parse().ok());
+        (offset, total)
+    } else {
+        // Server ignored the range; start over
+        (0, response.content_length())
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part)
+        .await?;
+    download.update(app, |snapshot| {
+        snapshot.received_bytes = received;
+        snapshot.total_bytes = total;
+    });
+
+    let mut stream = response.bytes_stream();
+    let mut last_progress = Instant::now();
+    loop {
+        tokio::select! {
+            chunk = stream.next() => match chunk {
+                Some(chunk) => {
+                    let chunk = chunk?;
+                    throttle.take(chunk.len() as u64).await;
+                    file.write_all(&chunk).await?;
+                    received += chunk.len() as u64;
+                    if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                        last_progress = Instant::now();
+                        download.update(app, |snapshot| snapshot.received_bytes = received);
+                    }
+                }
+                None => break,
+            },
+            changed = control.changed() => {
+                if changed.is_err() || *control.borrow() != Control::Run {
+                    file.flush().await?;
+                    download.update(app, |snapshot| snapshot.received_bytes = received);
+                    return Ok(Outcome::Interrupted);
+                }
+            }
+        }
+    }
+
+    file.flush().await?;
+    download.update(app, |snapshot| snapshot.received_bytes = received);
+    Ok(Outcome::Complete)
+}
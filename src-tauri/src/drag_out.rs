@@ -0,0 +1,290 @@
+//! Dragging attachments, exports and stills out of the window into other apps.
+//!
+//! Webviews can only drag what they can see as URLs, so the frontend cancels
+//! its own drag and asks the backend to start a native one instead. Every
+//! item is materialized as a real file first: local files are used as they
+//! are, anything else is written to `drag-out/` in the app cache, where drop
+//! targets such as mail clients can still read it after the drop. The result
+//! is reported with `drag-out:finished`.
+//!
+//! A native drag only starts while the mouse button is held, so remote
+//! attachments should be materialized ahead of time with `prepare_drag_out`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::{Error, Result};
+use crate::export::{self, NoteRow};
+use crate::network;
+use crate::paths;
+use crate::thumbnail_cache::ThumbnailCache;
+use crate::transfers::{self, Priority, TransferKind};
+
+pub const FINISHED_EVENT: &str = "drag-out:finished";
+const STAGING_DIR: &str = "drag-out";
+/// Staged files are kept this long, for drop targets that copy lazily.
+const STAGING_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_ICON: &[u8] = include_bytes!("../icons/128x128.png");
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum DragSource {
+    /// A file already on disk, such as a downloaded attachment.
+    File { path: PathBuf },
+    /// A server attachment, downloaded under `file_name`.
+    Url { url: String, file_name: String },
+    /// Note rows, written out as CSV.
+    NotesCsv {
+        file_name: String,
+        rows: Vec<NoteRow>,
+    },
+    /// A cached thumbnail still.
+    Thumbnail {
+        component_id: String,
+        file_name: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DragOutcome {
+    Dropped,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Finished {
+    outcome: DragOutcome,
+    paths: Vec<PathBuf>,
+}
+
+/// A fresh directory to stage one drag's files in, so names cannot clash.
+fn staging_dir(app: &AppHandle) -> Result<PathBuf> {
+    let dir = paths::cache_dir(app)?
+        .join(STAGING_DIR)
+        .join(uuid::Uuid::new_v4().simple().to_string());
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn staged_name(file_name: &str) -> Result<String> {
+    let name = export::sanitize_file_name(file_name);
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(Error::InvalidInput(format!(
+            "Invalid file name: {file_name}"
+        )));
+    }
+    Ok(name)
+}
+
+async fn download(app: &AppHandle, url: &str, path: &Path) -> Result<()> {
+    // Someone is holding the drag, so this goes ahead of background transfers
+    let throttle = transfers::register(
+        app,
+        format!("drag-out:{}", uuid::Uuid::new_v4()),
+        TransferKind::Download,
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        Priority::High,
+        None,
+    );
+    let response = network::client()
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?;
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        throttle.take(chunk.len() as u64).await;
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(())
+}
+
+/// Turns every source into a file on disk, returning absolute paths in order.
+async fn materialize(
+    app: &AppHandle,
+    cache: &ThumbnailCache,
+    items: Vec<DragSource>,
+) -> Result<Vec<PathBuf>> {
+    if items.is_empty() {
+        return Err(Error::InvalidInput("Nothing to drag".into()));
+    }
+    let mut staging = None;
+    let mut paths = Vec::with_capacity(items.len());
+    for item in items {
+        let path = match item {
+            DragSource::File { path } => {
+                if !path.is_file() {
+                    return Err(Error::InvalidInput(format!(
+                        "{} does not exist",
+                        path.display()
+                    )));
+                }
+                path.canonicalize()?
+            }
+            DragSource::Url { url, file_name } => {
+                let dir = match &staging {
+                    Some(dir) => dir,
+                    None => staging.insert(staging_dir(app)?),
+                };
+                let path = dir.join(staged_name(&file_name)?);
+                download(app, &url, &path).await?;
+                path
+            }
+            DragSource::NotesCsv { file_name, rows } => {
+                let dir = match &staging {
+                    Some(dir) => dir,
+                    None => staging.insert(staging_dir(app)?),
+                };
+                let path = dir.join(staged_name(&file_name)?);
+                let target = path.clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    export::write_notes_csv(&target, &rows)
+                })
+                .await??;
+                path
+            }
+            DragSource::Thumbnail {
+                component_id,
+                file_name,
+            } => {
+                let cached = cache.lookup(&component_id)?.ok_or_else(|| {
+                    Error::InvalidInput(format!("No cached thumbnail for {component_id}"))
+                })?;
+                match file_name {
+                    // The cache names files by component ID, which means nothing to the user
+                    Some(file_name) => {
+                        let dir = match &staging {
+                            Some(dir) => dir,
+                            None => staging.insert(staging_dir(app)?),
+                        };
+                        let path = dir.join(staged_name(&file_name)?);
+                        fs::copy(&cached.path, &path)?;
+                        path
+                    }
+                    None => cached.path,
+                }
+            }
+        };
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Picks the drag image: the first still being dragged, or the app icon.
+fn preview(paths: &[PathBuf]) -> drag::Image {
+    let is_still = |path: &&PathBuf| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                ["png", "jpg", "jpeg", "webp"].contains(&ext.to_ascii_lowercase().as_str())
+            })
+    };
+    match paths.iter().find(is_still) {
+        Some(path) if fs::metadata(path).is_ok_and(|meta| meta.len() < 8 * 1024 * 1024) => {
+            drag::Image::File(path.clone())
+        }
+        _ => drag::Image::Raw(DEFAULT_ICON.to_vec()),
+    }
+}
+
+fn begin(window: &WebviewWindow, paths: Vec<PathBuf>) -> Result<()> {
+    let app = window.app_handle().clone();
+    let image = preview(&paths);
+    let item = drag::DragItem::Files(paths.clone());
+    let on_drop = move |result, _| {
+        let outcome = match result {
+            drag::DragResult::Dropped => DragOutcome::Dropped,
+            drag::DragResult::Cancel => DragOutcome::Cancelled,
+        };
+        let finished = Finished {
+            outcome,
+            paths: paths.clone(),
+        };
+        if let Err(err) = app.emit(FINISHED_EVENT, finished) {
+            log::warn!("Failed to emit drag result: {err}");
+        }
+    };
+    #[cfg(target_os = "linux")]
+    drag::start_drag(
+        &window.gtk_window()?,
+        item,
+        image,
+        on_drop,
+        Default::default(),
+    )?;
+    #[cfg(not(target_os = "linux"))]
+    drag::start_drag(window, item, image, on_drop, Default::default())?;
+    Ok(())
+}
+
+/// Removes staged files from earlier drags once they are old enough.
+pub fn init(app: &AppHandle) {
+    let Ok(dir) = paths::cache_dir(app).map(|dir| dir.join(STAGING_DIR)) else {
+        return;
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return;
+        };
+        let cutoff = SystemTime::now() - STAGING_TTL;
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|modified| modified < cutoff);
+            if stale && let Err(err) = fs::remove_dir_all(entry.path()) {
+                log::warn!("Failed to remove {}: {err}", entry.path().display());
+            }
+        }
+    });
+}
+
+/// Materializes `items` without starting a drag, e.g. when the pointer
+/// reaches an attachment, and returns the paths to drag later as files.
+#[tauri::command]
+pub async fn prepare_drag_out(
+    app: AppHandle,
+    cache: State<'_, ThumbnailCache>,
+    items: Vec<DragSource>,
+) -> Result<Vec<PathBuf>> {
+    materialize(&app, &cache, items).await
+}
+
+/// Starts a native drag of `items` from `window`. Call it from the
+/// webview's `dragstart` handler while the mouse button is still down.
+#[tauri::command]
+pub async fn start_drag_out(
+    app: AppHandle,
+    window: WebviewWindow,
+    cache: State<'_, ThumbnailCache>,
+    items: Vec<DragSource>,
+) -> Result<Vec<PathBuf>> {
+    let paths = materialize(&app, &cache, items).await?;
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let target = window.clone();
+    let dragged = paths.clone();
+    // The OS drag session has to be driven from the UI thread
+    window.run_on_main_thread(move || {
+        let _ = tx.send(begin(&target, dragged));
+    })?;
+    rx.await
+        .map_err(|_| Error::InvalidInput("The drag could not be started".into()))??;
+    Ok(paths)
+}
@@ -0,0 +1,121 @@
+//! Shared error type for Tauri commands.
+//!
+//! Commands return `Result<T>` so failures reach the frontend as a plain
+//! string instead of an opaque serialization error.
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Keyring(#[from] keyring::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Xlsx(#[from] rust_xlsxwriter::XlsxError),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Search(#[from] tantivy::TantivyError),
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    Watch(#[from] notify::Error),
+    #[error(transparent)]
+    Ftrack(#[from] crate::ftrack::ApiError),
+    #[error(transparent)]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error(transparent)]
+    Shortcut(#[from] tauri_plugin_global_shortcut::Error),
+    #[error(transparent)]
+    Clipboard(#[from] arboard::Error),
+    #[error(transparent)]
+    Wav(#[from] hound::Error),
+    #[error(transparent)]
+    Updater(#[from] tauri_plugin_updater::Error),
+    #[error(transparent)]
+    Drag(#[from] drag::Error),
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    Upload(String),
+    #[error("{0}")]
+    EventHub(String),
+    #[error("{0}")]
+    Media(String),
+    #[error("{0}")]
+    Capture(String),
+    #[error("{0}")]
+    Audio(String),
+    #[error("{0}")]
+    Provider(String),
+    #[error("{0}")]
+    Lan(String),
+    #[error("{0}")]
+    Share(String),
+    #[error("{0}")]
+    Print(String),
+    #[error("{0}")]
+    Accessibility(String),
+    #[error("{0}")]
+    Plugin(String),
+}
+
+impl Error {
+    /// Stable name of the variant, used to classify failed commands.
+    pub fn class(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "io",
+            Self::Http(_) => "http",
+            Self::Json(_) => "json",
+            Self::Database(_) => "database",
+            Self::Keyring(_) => "keyring",
+            Self::Csv(_) => "csv",
+            Self::Xlsx(_) => "xlsx",
+            Self::Image(_) => "image",
+            Self::Search(_) => "search",
+            Self::Zip(_) => "zip",
+            Self::Watch(_) => "watch",
+            Self::Ftrack(_) => "ftrack",
+            Self::WebSocket(_) => "websocket",
+            Self::Tauri(_) => "tauri",
+            Self::Shortcut(_) => "shortcut",
+            Self::Clipboard(_) => "clipboard",
+            Self::Wav(_) => "wav",
+            Self::Updater(_) => "updater",
+            Self::Drag(_) => "drag",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::Upload(_) => "upload",
+            Self::EventHub(_) => "event_hub",
+            Self::Media(_) => "media",
+            Self::Capture(_) => "capture",
+            Self::Audio(_) => "audio",
+            Self::Provider(_) => "provider",
+            Self::Lan(_) => "lan",
+            Self::Share(_) => "share",
+            Self::Print(_) => "print",
+            Self::Accessibility(_) => "accessibility",
+            Self::Plugin(_) => "plugin",
+        }
+    }
+}
+
+impl serde::Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        crate::instrumentation::note_error(self.class());
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,361 @@
+//! Background connection to the ftrack event hub.
+//!
+//! The hub speaks socket.io 0.9 over a WebSocket. We subscribe to
+//! `ftrack.update`, keep only note, version and playlist changes, and forward
+//! them to the frontend. The task lives in the backend, so it survives
+//! webview reloads, and reconnects with backoff when the connection drops.
+//!
+//! The same connection carries AstraNotes' own presence events between
+//! instances; see [`crate::presence`].
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+
+use crate::error::{Error, Result};
+use crate::ftrack::Connection;
+use crate::network;
+use crate::presence;
+use crate::profiles;
+use crate::store::Store;
+
+pub const EVENT: &str = "event-hub:event";
+pub const STATUS_EVENT: &str = "event-hub:status";
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HubStatus {
+    Disconnected,
+    Connecting,
+    Connected,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HubEventKind {
+    NoteAdded,
+    VersionPublished,
+    PlaylistChanged,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HubEvent {
+    pub kind: HubEventKind,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub action: String,
+    pub parent_ids: Vec<String>,
+}
+
+/// Where events published by this instance go while connected.
+struct Outgoing {
+    packets: mpsc::UnboundedSender<String>,
+    subscriber_id: String,
+    api_user: String,
+}
+
+pub struct EventHub {
+    task: Mutex<Option<JoinHandle<()>>>,
+    status: Mutex<HubStatus>,
+    outgoing: Mutex<Option<Outgoing>>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self {
+            task: Mutex::new(None),
+            status: Mutex::new(HubStatus::Disconnected),
+            outgoing: Mutex::new(None),
+        }
+    }
+}
+
+impl EventHub {
+    fn stop(&self) {
+        if let Some(task) = self
+            .task
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+        {
+            task.abort();
+        }
+        self.set_outgoing(None);
+    }
+
+    fn set_outgoing(&self, outgoing: Option<Outgoing>) {
+        *self
+            .outgoing
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = outgoing;
+    }
+}
+
+/// Publishes an event on `topic` to other hub subscribers. Returns whether
+/// it was sent, i.e. whether the hub is connected.
+pub(crate) fn publish(app: &AppHandle, topic: &str, data: Value) -> bool {
+    let hub = app.state::<EventHub>();
+    let outgoing = hub
+        .outgoing
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(outgoing) = outgoing.as_ref() else {
+        return false;
+    };
+    let packet = event_packet(topic, data, &outgoing.subscriber_id, &outgoing.api_user);
+    outgoing.packets.send(packet).is_ok()
+}
+
+fn set_status(app: &AppHandle, status: HubStatus) {
+    let hub = app.state::<EventHub>();
+    let mut current = hub
+        .status
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *current != status {
+        *current = status;
+        if let Err(err) = app.emit(STATUS_EVENT, status) {
+            log::warn!("Failed to emit event hub status: {err}");
+        }
+    }
+}
+
+async fn run(app: AppHandle, connection: Connection) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        set_status(&app, HubStatus::Connecting);
+        match session(&app, &connection).await {
+            Ok(()) => {
+                log::info!("Event hub connection closed by server");
+                backoff = Duration::from_secs(1);
+            }
+            Err(err) => log::warn!("Event hub connection failed: {err}"),
+        }
+        app.state::<EventHub>().set_outgoing(None);
+        set_status(&app, HubStatus::Disconnected);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Runs one socket.io session until the connection closes or errors.
+async fn session(app: &AppHandle, connection: &Connection) -> Result<()> {
+    let api_key = connection.api_key()?;
+    let base_url = connection.base_url();
+
+    // Handshake returns "<session id>:<heartbeat timeout>:<close timeout>:<transports>"
+    let handshake = network::client()
+        .get(format!("{base_url}/socket.io/1/"))
+        .header("ftrack-user", &connection.api_user)
+        .header("ftrack-api-key", &api_key)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let mut fields = handshake.split(':');
+    let session_id = fields
+        .next()
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| Error::EventHub(format!("Unexpected handshake: {handshake}")))?;
+    let heartbeat_timeout = fields
+        .next()
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(60));
+
+    let ws_url = format!(
+        "{}/socket.io/1/websocket/{session_id}",
+        base_url
+            .replacen("https://", "wss://", 1)
+            .replacen("http://", "ws://", 1)
+    );
+    let mut request = ws_url.into_client_request()?;
+    let headers = request.headers_mut();
+    headers.insert("ftrack-user", header_value(&connection.api_user)?);
+    headers.insert("ftrack-api-key", header_value(&api_key)?);
+
+    let socket = network::websocket::connect(request).await?;
+    let (mut write, mut read) = socket.split();
+
+    let subscriber_id = uuid::Uuid::new_v4().to_string();
+    for subscription in ["topic=ftrack.update", presence::SUBSCRIPTION] {
+        write
+            .send(Message::text(subscribe_packet(
+                connection,
+                &subscriber_id,
+                subscription,
+            )))
+            .await?;
+    }
+    let (packets, mut outgoing) = mpsc::unbounded_channel();
+    app.state::<EventHub>().set_outgoing(Some(Outgoing {
+        packets,
+        subscriber_id,
+        api_user: connection.api_user.clone(),
+    }));
+    set_status(app, HubStatus::Connected);
+    presence::connected(app);
+
+    loop {
+        // The server heartbeats well within its advertised timeout
+        let message = tokio::select! {
+            message = tokio::time::timeout(heartbeat_timeout, read.next()) => message
+                .map_err(|_| Error::EventHub("Event hub heartbeat timed out".into()))?,
+            Some(packet) = outgoing.recv() => {
+                write.send(Message::text(packet)).await?;
+                continue;
+            }
+        };
+        let Some(message) = message else {
+            return Ok(());
+        };
+        let text = match message? {
+            Message::Text(text) => text,
+            Message::Close(_) => return Ok(()),
+            _ => continue,
+        };
+
+        match text.split(':').next() {
+            Some("0") => return Ok(()),
+            Some("2") => write.send(Message::text("2::")).await?,
+            Some("5") => handle_event_packet(app, &text),
+            _ => {}
+        }
+    }
+}
+
+fn header_value(value: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(value).map_err(|err| Error::EventHub(err.to_string()))
+}
+
+fn event_packet(topic: &str, data: Value, subscriber_id: &str, api_user: &str) -> String {
+    let event = json!({
+        "id": uuid::Uuid::new_v4().to_string(),
+        "topic": topic,
+        "data": data,
+        "source": {
+            "id": subscriber_id,
+            "applicationId": "astranotes",
+            "user": { "username": api_user },
+        },
+        "target": "",
+        "inReplyToEvent": null,
+    });
+    format!("5:::{}", json!({ "name": "ftrack.event", "args": [event] }))
+}
+
+fn subscribe_packet(connection: &Connection, subscriber_id: &str, subscription: &str) -> String {
+    event_packet(
+        "ftrack.meta.subscribe",
+        json!({
+            "subscriber": { "id": subscriber_id, "applicationId": "astranotes" },
+            "subscription": subscription,
+        }),
+        subscriber_id,
+        &connection.api_user,
+    )
+}
+
+/// Parses a `5:::<json>` packet and forwards matching entity changes.
+fn handle_event_packet(app: &AppHandle, packet: &str) {
+    let Some(payload) = packet.splitn(4, ':').nth(3) else {
+        return;
+    };
+    let Ok(packet): std::result::Result<Value, _> = serde_json::from_str(payload) else {
+        return;
+    };
+    let events = packet["args"].as_array().cloned().unwrap_or_default();
+    for event in events {
+        if event["topic"] == presence::TOPIC {
+            presence::received(app, &event);
+            continue;
+        }
+        if event["topic"] != "ftrack.update" {
+            continue;
+        }
+        let entities = event["data"]["entities"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        for entity in entities {
+            if let Some(hub_event) = classify(&entity)
+                && let Err(err) = app.emit(EVENT, hub_event)
+            {
+                log::warn!("Failed to emit event hub event: {err}");
+            }
+        }
+    }
+}
+
+fn classify(entity: &Value) -> Option<HubEvent> {
+    let entity_type = entity["entityType"].as_str()?.to_lowercase();
+    let action = entity["action"].as_str().unwrap_or_default().to_string();
+    let kind = match (entity_type.as_str(), action.as_str()) {
+        ("note", "add") => HubEventKind::NoteAdded,
+        ("assetversion", "add") => HubEventKind::VersionPublished,
+        ("reviewsession" | "reviewsessionobject" | "list" | "listobject", _) => {
+            HubEventKind::PlaylistChanged
+        }
+        _ => return None,
+    };
+    let parent_ids = entity["parents"]
+        .as_array()
+        .map(|parents| {
+            parents
+                .iter()
+                .filter_map(|parent| parent["entityId"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(HubEvent {
+        kind,
+        entity_type,
+        entity_id: entity["entityId"].as_str().unwrap_or_default().to_string(),
+        action,
+        parent_ids,
+    })
+}
+
+/// Connects (or reconnects with new settings) to the event hub of
+/// `connection`, or of the active profile when omitted.
+#[tauri::command]
+pub fn connect_event_hub(
+    app: AppHandle,
+    hub: State<'_, EventHub>,
+    store: State<'_, Store>,
+    connection: Option<Connection>,
+) -> Result<()> {
+    let connection = profiles::resolve(&store, connection)?;
+    hub.stop();
+    let task = tauri::async_runtime::spawn(run(app, connection));
+    *hub.task
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(task);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn disconnect_event_hub(app: AppHandle, hub: State<'_, EventHub>) {
+    hub.stop();
+    set_status(&app, HubStatus::Disconnected);
+}
+
+#[tauri::command]
+pub fn get_event_hub_status(hub: State<'_, EventHub>) -> HubStatus {
+    *hub.status
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
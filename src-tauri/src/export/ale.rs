@@ -0,0 +1,83 @@
+//! Avid Log Exchange (ALE) writer.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::editorial::{EditorialOptions, EditorialPlaylist, Naming};
+use crate::error::Result;
+use crate::timecode::{format_timecode, parse_timecode};
+
+const COLUMNS: [&str; 8] = [
+    "Name",
+    "Tape",
+    "Start",
+    "End",
+    "Source File",
+    "Version",
+    "Status",
+    "Comments",
+];
+
+/// Tabs and line breaks would split the row, so collapse all whitespace.
+fn field(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(super) fn write(
+    path: &Path,
+    playlist: &EditorialPlaylist,
+    options: &EditorialOptions,
+) -> Result<()> {
+    let naming = Naming::new(options)?;
+    let rate = playlist.rate();
+    let tc = |frames: i64| format_timecode(frames, rate, options.drop_frame);
+
+    let mut ale = String::new();
+    let _ = write!(ale, "Heading\r\nFIELD_DELIM\tTABS\r\n");
+    let _ = write!(ale, "VIDEO_FORMAT\t{}\r\n", field(&options.video_format));
+    let _ = write!(ale, "AUDIO_FORMAT\t48khz\r\n");
+    // Whole rates are written without decimals, fractional ones as e.g. 23.976
+    let fps = format!("{:.3}", rate);
+    let fps = fps.trim_end_matches('0').trim_end_matches('.');
+    let _ = write!(ale, "FPS\t{fps}\r\n\r\n");
+    let _ = write!(ale, "Column\r\n{}\r\n\r\nData\r\n", COLUMNS.join("\t"));
+
+    for (index, version) in playlist.versions.iter().enumerate() {
+        let Some((start, duration)) = version.range() else {
+            log::warn!("Skipping {} in ALE: no frame range", version.name);
+            continue;
+        };
+        let source_in = match &version.source_timecode {
+            Some(timecode) => parse_timecode(timecode, rate)?,
+            None => start,
+        };
+        let comments = version
+            .notes
+            .iter()
+            .map(|note| field(&note.content))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let row = [
+            field(&naming.clip(options, version, index)),
+            naming.tape(options, version, index),
+            tc(source_in),
+            tc(source_in + duration),
+            version
+                .media_path
+                .as_ref()
+                .map(|path| field(&path.display().to_string()))
+                .unwrap_or_default(),
+            version
+                .version_number
+                .map(|number| number.to_string())
+                .unwrap_or_default(),
+            version.status.as_deref().map(field).unwrap_or_default(),
+            comments,
+        ];
+        let _ = write!(ale, "{}\r\n", row.join("\t"));
+    }
+
+    fs::write(path, ale)?;
+    Ok(())
+}
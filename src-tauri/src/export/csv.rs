@@ -0,0 +1,41 @@
+//! CSV writer for note rows.
+
+use std::io::Write;
+use std::path::Path;
+
+use super::{NoteRow, headers};
+use crate::error::Result;
+use crate::i18n;
+
+pub(super) fn write(path: &Path, rows: &[NoteRow]) -> Result<()> {
+    write_to(std::fs::File::create(path)?, rows)
+}
+
+pub(super) fn write_to(output: impl Write, rows: &[NoteRow]) -> Result<()> {
+    let mut writer = ::csv::WriterBuilder::new()
+        .quote_style(::csv::QuoteStyle::Always)
+        .from_writer(output);
+    writer.write_record(headers())?;
+    for row in rows {
+        let version_number = row.version_number.to_string();
+        let frame = row.frame_number.map(|f| f.to_string()).unwrap_or_default();
+        let labels = row.labels.join(", ");
+        let created_at = row
+            .created_at
+            .as_deref()
+            .map(i18n::timestamp)
+            .unwrap_or_default();
+        writer.write_record([
+            row.version_name.as_str(),
+            version_number.as_str(),
+            row.note_state.as_deref().unwrap_or_default(),
+            labels.as_str(),
+            row.author.as_deref().unwrap_or_default(),
+            created_at.as_str(),
+            frame.as_str(),
+            row.content.as_str(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! Playlist cut data shared by the editorial exports (OTIO, EDL and ALE).
+
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+
+pub(crate) const DEFAULT_FRAME_RATE: f64 = 24.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorialPlaylist {
+    pub name: String,
+    /// Timeline rate, also used for versions without their own.
+    #[serde(default)]
+    pub frame_rate: Option<f64>,
+    pub versions: Vec<EditorialVersion>,
+}
+
+impl EditorialPlaylist {
+    pub(crate) fn rate(&self) -> f64 {
+        self.frame_rate.unwrap_or(DEFAULT_FRAME_RATE)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorialVersion {
+    pub version_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version_number: Option<u32>,
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Inclusive first and last frame of the cut.
+    #[serde(default)]
+    pub frame_in: Option<i64>,
+    #[serde(default)]
+    pub frame_out: Option<i64>,
+    #[serde(default)]
+    pub frame_rate: Option<f64>,
+    /// Embedded start timecode of the media, mapped to `frame_in` when set.
+    #[serde(default)]
+    pub source_timecode: Option<String>,
+    /// Local media path; takes precedence over `media_url`.
+    #[serde(default)]
+    pub media_path: Option<PathBuf>,
+    #[serde(default)]
+    pub media_url: Option<String>,
+    #[serde(default)]
+    pub notes: Vec<EditorialNote>,
+}
+
+impl EditorialVersion {
+    /// The cut as `(first frame, duration)`, if a valid range was supplied.
+    pub(crate) fn range(&self) -> Option<(i64, i64)> {
+        match (self.frame_in, self.frame_out) {
+            (Some(frame_in), Some(frame_out)) if frame_out >= frame_in => {
+                Some((frame_in, frame_out - frame_in + 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorialNote {
+    pub content: String,
+    #[serde(default)]
+    pub frame: Option<i64>,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// A regex substitution applied to generated tape and clip names.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameRule {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EditorialOptions {
+    pub path: Option<PathBuf>,
+    /// Template for tape (reel) names. Placeholders: `{name}`, `{version}`,
+    /// `{versionId}`, `{status}` and `{index}`.
+    pub tape_name: String,
+    pub clip_name: String,
+    pub tape_rules: Vec<NameRule>,
+    pub clip_rules: Vec<NameRule>,
+    /// CMX3600 reels are traditionally limited to 8 characters.
+    pub max_tape_length: usize,
+    pub record_start: String,
+    pub drop_frame: bool,
+    /// ALE `VIDEO_FORMAT` heading, e.g. `1080` or `CUSTOM`.
+    pub video_format: String,
+}
+
+impl Default for EditorialOptions {
+    fn default() -> Self {
+        Self {
+            path: None,
+            tape_name: "{name}".into(),
+            clip_name: "{name}".into(),
+            tape_rules: Vec::new(),
+            clip_rules: Vec::new(),
+            max_tape_length: 8,
+            record_start: "01:00:00:00".into(),
+            drop_frame: false,
+            video_format: "1080".into(),
+        }
+    }
+}
+
+/// Compiled naming rules for one export.
+pub(crate) struct Naming {
+    tape_rules: Vec<(Regex, String)>,
+    clip_rules: Vec<(Regex, String)>,
+}
+
+fn compile(rules: &[NameRule]) -> Result<Vec<(Regex, String)>> {
+    rules
+        .iter()
+        .map(|rule| {
+            let regex = Regex::new(&rule.pattern).map_err(|err| {
+                Error::InvalidInput(format!("Invalid name rule {:?}: {err}", rule.pattern))
+            })?;
+            Ok((regex, rule.replacement.clone()))
+        })
+        .collect()
+}
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{(\w+)\}").expect("valid placeholder pattern"));
+
+impl Naming {
+    pub(crate) fn new(options: &EditorialOptions) -> Result<Self> {
+        Ok(Self {
+            tape_rules: compile(&options.tape_rules)?,
+            clip_rules: compile(&options.clip_rules)?,
+        })
+    }
+
+    fn render(
+        template: &str,
+        rules: &[(Regex, String)],
+        version: &EditorialVersion,
+        index: usize,
+    ) -> String {
+        let mut name = PLACEHOLDER
+            .replace_all(template, |caps: &regex::Captures<'_>| match &caps[1] {
+                "name" => version.name.clone(),
+                "version" => version
+                    .version_number
+                    .map(|number| format!("{number:03}"))
+                    .unwrap_or_default(),
+                "versionId" => version.version_id.clone(),
+                "status" => version.status.clone().unwrap_or_default(),
+                "index" => format!("{:03}", index + 1),
+                _ => caps[0].to_string(),
+            })
+            .into_owned();
+        for (regex, replacement) in rules {
+            name = regex.replace_all(&name, replacement.as_str()).into_owned();
+        }
+        name
+    }
+
+    /// Tape names are restricted to characters every NLE accepts in a reel.
+    pub(crate) fn tape(
+        &self,
+        options: &EditorialOptions,
+        version: &EditorialVersion,
+        index: usize,
+    ) -> String {
+        let name: String = Self::render(&options.tape_name, &self.tape_rules, version, index)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        name.to_ascii_uppercase()
+    }
+
+    pub(crate) fn clip(
+        &self,
+        options: &EditorialOptions,
+        version: &EditorialVersion,
+        index: usize,
+    ) -> String {
+        Self::render(&options.clip_name, &self.clip_rules, version, index)
+    }
+}
@@ -0,0 +1,96 @@
+//! CMX3600 EDL writer.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::editorial::{EditorialOptions, EditorialPlaylist, Naming};
+use crate::error::{Error, Result};
+use crate::timecode::{format_timecode, parse_timecode, supports_drop_frame};
+
+/// Single-line text for comment fields, which end at the line break.
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(super) fn write(
+    path: &Path,
+    playlist: &EditorialPlaylist,
+    options: &EditorialOptions,
+) -> Result<()> {
+    let naming = Naming::new(options)?;
+    let rate = playlist.rate();
+    let drop_frame = options.drop_frame;
+    if drop_frame && !supports_drop_frame(rate) {
+        return Err(Error::InvalidInput(format!(
+            "Drop-frame timecode is not defined at {rate:.3} fps"
+        )));
+    }
+    let tc = |frames: i64| format_timecode(frames, rate, drop_frame);
+    let mut record = parse_timecode(&options.record_start, rate)?;
+
+    let mut edl = String::new();
+    let title: String = one_line(&playlist.name).chars().take(70).collect();
+    let _ = write!(edl, "TITLE: {title}\r\n");
+    let fcm = if drop_frame {
+        "DROP FRAME"
+    } else {
+        "NON-DROP FRAME"
+    };
+    let _ = write!(edl, "FCM: {fcm}\r\n\r\n");
+
+    let mut event = 0;
+    for (index, version) in playlist.versions.iter().enumerate() {
+        let Some((start, duration)) = version.range() else {
+            log::warn!("Skipping {} in EDL: no frame range", version.name);
+            continue;
+        };
+        event += 1;
+        let mut tape = naming.tape(options, version, index);
+        if options.max_tape_length > 0 {
+            tape.truncate(options.max_tape_length);
+        }
+        let source_in = match &version.source_timecode {
+            Some(timecode) => parse_timecode(timecode, rate)?,
+            None => start,
+        };
+
+        let _ = write!(
+            edl,
+            "{event:03}  {tape:<8} V     C        {} {} {} {}\r\n",
+            tc(source_in),
+            tc(source_in + duration),
+            tc(record),
+            tc(record + duration),
+        );
+        let _ = write!(
+            edl,
+            "* FROM CLIP NAME: {}\r\n",
+            one_line(&naming.clip(options, version, index))
+        );
+        if let Some(media) = &version.media_path {
+            let _ = write!(edl, "* SOURCE FILE: {}\r\n", media.display());
+        }
+        // Avid reads `* LOC:` comments back as locators on the record side
+        let mut notes: Vec<_> = version
+            .notes
+            .iter()
+            .filter_map(|note| note.frame.map(|frame| (frame, note)))
+            .filter(|(frame, _)| (start..start + duration).contains(frame))
+            .collect();
+        notes.sort_by_key(|(frame, _)| *frame);
+        for (frame, note) in notes {
+            let _ = write!(
+                edl,
+                "* LOC: {} RED     {}\r\n",
+                tc(record + frame - start),
+                one_line(&note.content)
+            );
+        }
+        let _ = write!(edl, "\r\n");
+        record += duration;
+    }
+
+    fs::write(path, edl)?;
+    Ok(())
+}
@@ -0,0 +1,366 @@
+//! Native exports of playlist notes and editorial cut lists.
+//!
+//! Rows are sent once from the frontend and written straight to disk, so
+//! large playlists never have to be assembled as a blob in the webview.
+
+mod ale;
+mod csv;
+pub(crate) mod editorial;
+mod edl;
+mod pdf;
+mod xlsx;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::json;
+use tauri::AppHandle;
+use tauri::ipc::Response;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::error::{Error, Result};
+use crate::i18n;
+use crate::pipeline_hooks::{self, HookPoint};
+use crate::store::audit::{self, AuditAction, NewAuditEntry};
+use editorial::{EditorialOptions, EditorialPlaylist};
+
+/// A single exported note, mirroring the columns of the frontend CSV export.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRow {
+    pub version_name: String,
+    pub version_number: u32,
+    pub content: String,
+    #[serde(default)]
+    pub note_state: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub frame_number: Option<u32>,
+}
+
+/// A note in the PDF report, with the version details that only the report shows.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportNote {
+    #[serde(flatten)]
+    pub row: NoteRow,
+    #[serde(default)]
+    pub version_status: Option<String>,
+    #[serde(default)]
+    pub thumbnail_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PdfPageSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PdfOrientation {
+    #[default]
+    Portrait,
+    Landscape,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PdfExportOptions {
+    pub playlist_name: Option<String>,
+    pub path: Option<PathBuf>,
+    pub page_size: PdfPageSize,
+    pub orientation: PdfOrientation,
+    pub include_thumbnails: bool,
+}
+
+impl Default for PdfExportOptions {
+    fn default() -> Self {
+        Self {
+            playlist_name: None,
+            path: None,
+            page_size: PdfPageSize::default(),
+            orientation: PdfOrientation::default(),
+            include_thumbnails: true,
+        }
+    }
+}
+
+/// Message IDs of the localized column headers, in `HEADERS` order.
+const HEADER_MESSAGES: [&str; 8] = [
+    "export-header-version-name",
+    "export-header-version-number",
+    "export-header-note-state",
+    "export-header-labels",
+    "export-header-author",
+    "export-header-created-at",
+    "export-header-frame",
+    "export-header-notes",
+];
+
+/// Column headers in the current locale.
+pub(crate) fn headers() -> [String; 8] {
+    HEADER_MESSAGES.map(|id| i18n::tr(id, &[]))
+}
+
+pub(crate) const HEADERS: [&str; 8] = [
+    "Version Name",
+    "Version Number",
+    "Note State",
+    "Labels",
+    "Author",
+    "Created At",
+    "Frame",
+    "Notes",
+];
+
+/// Asks for a destination with a native save dialog. Returns `None` if cancelled.
+pub(crate) async fn pick_save_path(
+    app: &AppHandle,
+    default_name: &str,
+    filter_name: &str,
+    extension: &str,
+) -> Option<PathBuf> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_file_name(default_name)
+        .add_filter(filter_name, &[extension])
+        .save_file(move |path| {
+            let _ = tx.send(path.and_then(|path| path.into_path().ok()));
+        });
+    rx.await.ok().flatten()
+}
+
+/// Resolves the destination, prompting when the frontend did not supply one.
+pub(crate) async fn resolve_path(
+    app: &AppHandle,
+    path: Option<PathBuf>,
+    playlist_name: &str,
+    filter_name: &str,
+    extension: &str,
+) -> Option<PathBuf> {
+    match path {
+        Some(path) => Some(path),
+        None => {
+            let date = chrono::Local::now().format("%Y%m%d");
+            let default_name = format!("{}_{date}.{extension}", sanitize_file_name(playlist_name));
+            pick_save_path(app, &default_name, filter_name, extension).await
+        }
+    }
+}
+
+pub(crate) fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Writes through a `.part` file and renames it into place, so an interrupted
+/// export never leaves a truncated file behind under the final name.
+pub(crate) fn write_atomically(path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".part");
+    let temp = PathBuf::from(temp);
+    if let Err(err) = write(&temp) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Writes rows as CSV, for exports that do not go through a save dialog.
+pub(crate) fn write_notes_csv(path: &Path, rows: &[NoteRow]) -> Result<()> {
+    write_atomically(path, |temp| csv::write(temp, rows))
+}
+
+pub(crate) fn write_notes_xlsx(path: &Path, title: &str, rows: &[NoteRow]) -> Result<()> {
+    write_atomically(path, |temp| xlsx::write(temp, title, rows))
+}
+
+pub(crate) fn write_notes_pdf(
+    path: &Path,
+    title: &str,
+    notes: &[ReportNote],
+    options: &PdfExportOptions,
+) -> Result<()> {
+    write_atomically(path, |temp| pdf::write(temp, title, notes, options))
+}
+
+/// Audits a finished export and lets `on_export` pipeline hooks pick it up.
+pub(crate) fn exported(app: &AppHandle, format: &str, playlist_id: &str, path: &Path) {
+    audit::record(
+        app,
+        NewAuditEntry {
+            action: AuditAction::Export,
+            connection: None,
+            entity_type: Some("Playlist".into()),
+            entity_id: Some(playlist_id.to_string()),
+            summary: format!("Exported {format} to {}", path.display()),
+            details: json!({ "format": format, "path": path }),
+        },
+    );
+    pipeline_hooks::notify(
+        app,
+        HookPoint::OnExport,
+        json!({ "format": format, "playlistId": playlist_id, "path": path }),
+    );
+}
+
+/// Exports rows as CSV. Returns the written path, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_notes_csv(
+    app: AppHandle,
+    playlist_id: String,
+    playlist_name: Option<String>,
+    rows: Vec<NoteRow>,
+    path: Option<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    let name = playlist_name.unwrap_or_else(|| playlist_id.clone());
+    let Some(path) = resolve_path(&app, path, &name, "CSV", "csv").await else {
+        return Ok(None);
+    };
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || write_notes_csv(&target, &rows)).await??;
+    log::info!(
+        "Exported notes for playlist {playlist_id} to {}",
+        path.display()
+    );
+    exported(&app, "csv", &playlist_id, &path);
+    Ok(Some(path))
+}
+
+/// Renders rows as CSV without saving, returned as raw bytes for a preview.
+#[tauri::command]
+pub async fn preview_notes_csv(rows: Vec<NoteRow>) -> Result<Response> {
+    let bytes = tauri::async_runtime::spawn_blocking(move || {
+        let mut bytes = Vec::new();
+        csv::write_to(&mut bytes, &rows)?;
+        Ok::<_, Error>(bytes)
+    })
+    .await??;
+    Ok(Response::new(bytes))
+}
+
+/// Exports rows as an Excel workbook. Returns the written path, or `None` if cancelled.
+#[tauri::command]
+pub async fn export_notes_xlsx(
+    app: AppHandle,
+    playlist_id: String,
+    playlist_name: Option<String>,
+    rows: Vec<NoteRow>,
+    path: Option<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    let name = playlist_name.unwrap_or_else(|| playlist_id.clone());
+    let Some(path) = resolve_path(&app, path, &name, "Excel Workbook", "xlsx").await else {
+        return Ok(None);
+    };
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || write_notes_xlsx(&target, &name, &rows)).await??;
+    log::info!(
+        "Exported notes for playlist {playlist_id} to {}",
+        path.display()
+    );
+    exported(&app, "xlsx", &playlist_id, &path);
+    Ok(Some(path))
+}
+
+/// Exports notes as a formatted PDF report. Returns the written path, or `None` if cancelled.
+#[tauri::command]
+pub async fn export_notes_pdf(
+    app: AppHandle,
+    playlist_id: String,
+    notes: Vec<ReportNote>,
+    options: Option<PdfExportOptions>,
+) -> Result<Option<PathBuf>> {
+    let options = options.unwrap_or_default();
+    let name = options
+        .playlist_name
+        .clone()
+        .unwrap_or_else(|| playlist_id.clone());
+    let Some(path) = resolve_path(&app, options.path.clone(), &name, "PDF", "pdf").await else {
+        return Ok(None);
+    };
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || write_notes_pdf(&target, &name, &notes, &options))
+        .await??;
+    log::info!(
+        "Exported notes for playlist {playlist_id} to {}",
+        path.display()
+    );
+    exported(&app, "pdf", &playlist_id, &path);
+    Ok(Some(path))
+}
+
+/// Writes the playlist cut as a CMX3600 EDL. Returns the written path, or `None` if cancelled.
+#[tauri::command]
+pub async fn export_playlist_edl(
+    app: AppHandle,
+    playlist_id: String,
+    playlist: EditorialPlaylist,
+    options: Option<EditorialOptions>,
+) -> Result<Option<PathBuf>> {
+    let options = options.unwrap_or_default();
+    let Some(path) = resolve_path(&app, options.path.clone(), &playlist.name, "EDL", "edl").await
+    else {
+        return Ok(None);
+    };
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        write_atomically(&target, |temp| edl::write(temp, &playlist, &options))
+    })
+    .await??;
+    log::info!(
+        "Exported playlist {playlist_id} as EDL to {}",
+        path.display()
+    );
+    exported(&app, "edl", &playlist_id, &path);
+    Ok(Some(path))
+}
+
+/// Writes the playlist as an Avid ALE. Returns the written path, or `None` if cancelled.
+#[tauri::command]
+pub async fn export_playlist_ale(
+    app: AppHandle,
+    playlist_id: String,
+    playlist: EditorialPlaylist,
+    options: Option<EditorialOptions>,
+) -> Result<Option<PathBuf>> {
+    let options = options.unwrap_or_default();
+    let Some(path) = resolve_path(
+        &app,
+        options.path.clone(),
+        &playlist.name,
+        "Avid Log Exchange",
+        "ale",
+    )
+    .await
+    else {
+        return Ok(None);
+    };
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        write_atomically(&target, |temp| ale::write(temp, &playlist, &options))
+    })
+    .await??;
+    log::info!(
+        "Exported playlist {playlist_id} as ALE to {}",
+        path.display()
+    );
+    exported(&app, "ale", &playlist_id, &path);
+    Ok(Some(path))
+}
@@ -0,0 +1,181 @@
+//! PDF report writer for playlist notes.
+
+use std::fs;
+use std::path::Path;
+
+use image::imageops::FilterType;
+use pdf_writer::Ref;
+
+use super::{PdfExportOptions, PdfOrientation, PdfPageSize, ReportNote};
+use crate::error::Result;
+use crate::i18n;
+use crate::reports::pdf::{A4, Color, Document, LETTER};
+
+const MARGIN: f32 = 40.0;
+const FOOTER_HEIGHT: f32 = 20.0;
+const THUMB_WIDTH: f32 = 120.0;
+const THUMB_HEIGHT: f32 = 67.5;
+const GUTTER: f32 = 12.0;
+const HEADING_SIZE: f32 = 11.0;
+const DETAILS_SIZE: f32 = 8.5;
+const BODY_SIZE: f32 = 10.0;
+const BODY_LEADING: f32 = 13.0;
+const BLOCK_SPACING: f32 = 10.0;
+
+const TEXT: Color = (0.1, 0.1, 0.1);
+const MUTED: Color = (0.45, 0.45, 0.45);
+const RULE: Color = (0.8, 0.8, 0.8);
+
+/// Loads a thumbnail at twice its printed size so it stays sharp when zoomed.
+fn load_thumbnail(document: &mut Document, path: &Path) -> Option<(Ref, f32, f32)> {
+    let decoded = image::open(path)
+        .inspect_err(|err| log::warn!("Skipping thumbnail {}: {err}", path.display()))
+        .ok()?;
+    let scaled = decoded
+        .resize(
+            (THUMB_WIDTH * 2.0) as u32,
+            (THUMB_HEIGHT * 2.0) as u32,
+            FilterType::Triangle,
+        )
+        .to_rgb8();
+    let (width, height) = (scaled.width() as f32 / 2.0, scaled.height() as f32 / 2.0);
+    let image = document.add_image(&scaled).ok()?;
+    Some((image, width, height))
+}
+
+fn details(note: &ReportNote) -> String {
+    let row = &note.row;
+    // Frame numbers read as plain numbers, never grouped
+    let frame = row
+        .frame_number
+        .map(|frame| i18n::tr("report-frame", &[("frame", frame.to_string().into())]));
+    let labels = (!row.labels.is_empty()).then(|| row.labels.join(", "));
+    [
+        note.version_status.clone(),
+        row.note_state.clone(),
+        row.author.clone(),
+        row.created_at.as_deref().map(i18n::timestamp),
+        frame,
+        labels,
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|value| !value.is_empty())
+    .collect::<Vec<_>>()
+    .join(" · ")
+}
+
+fn render(title: &str, notes: &[ReportNote], options: &PdfExportOptions) -> Result<Vec<u8>> {
+    let (width, height) = match options.page_size {
+        PdfPageSize::A4 => A4,
+        PdfPageSize::Letter => LETTER,
+    };
+    let mut document = Document::new(match options.orientation {
+        PdfOrientation::Portrait => (width, height),
+        PdfOrientation::Landscape => (height, width),
+    });
+    let (page_width, page_height) = document.size();
+    let bottom = page_height - MARGIN - FOOTER_HEIGHT;
+    let mut pages = Vec::new();
+    let mut page = document.new_page();
+    let mut y = MARGIN;
+
+    document.text(&mut page, title, MARGIN, y, 18.0, TEXT);
+    y += 24.0;
+    let count = i18n::tr("report-note-count", &[("count", notes.len().into())]);
+    let exported = i18n::datetime(&chrono::Local::now());
+    let exported = i18n::tr("report-exported", &[("date", exported.into())]);
+    let subtitle = format!("{count} · {exported}");
+    document.text(&mut page, &subtitle, MARGIN, y, 9.0, MUTED);
+    y += 16.0;
+    page.line(MARGIN, y, page_width - MARGIN, y, RULE);
+    y += BLOCK_SPACING;
+
+    let text_x = if options.include_thumbnails {
+        MARGIN + THUMB_WIDTH + GUTTER
+    } else {
+        MARGIN
+    };
+    let text_width = page_width - MARGIN - text_x;
+    let heading_leading = HEADING_SIZE + 4.0;
+    let details_leading = DETAILS_SIZE + 6.0;
+
+    for note in notes {
+        let body = document.wrap(&note.row.content, BODY_SIZE, text_width);
+        let details = details(note);
+        let mut min_height =
+            heading_leading + details_leading + BODY_LEADING * body.len().min(2) as f32;
+        if options.include_thumbnails {
+            min_height = min_height.max(THUMB_HEIGHT);
+        }
+        // Keep the heading with its first lines; long bodies flow onto later pages
+        if y + min_height > bottom {
+            pages.push(std::mem::replace(&mut page, document.new_page()));
+            y = MARGIN;
+        }
+
+        let mut block_bottom = y;
+        let thumbnail = match &note.thumbnail_path {
+            Some(path) if options.include_thumbnails => load_thumbnail(&mut document, path),
+            _ => None,
+        };
+        if let Some((image, width, height)) = thumbnail {
+            page.image(image, MARGIN, y, width, height);
+            block_bottom = y + height;
+        }
+
+        let heading = format!("{} v{}", note.row.version_name, note.row.version_number);
+        document.text(&mut page, &heading, text_x, y, HEADING_SIZE, TEXT);
+        let mut text_y = y + heading_leading;
+        if !details.is_empty() {
+            document.text(&mut page, &details, text_x, text_y, DETAILS_SIZE, MUTED);
+            text_y += details_leading;
+        }
+        for line in &body {
+            if text_y + BODY_LEADING > bottom {
+                pages.push(std::mem::replace(&mut page, document.new_page()));
+                text_y = MARGIN;
+                block_bottom = MARGIN;
+            }
+            document.text(&mut page, line, text_x, text_y, BODY_SIZE, TEXT);
+            text_y += BODY_LEADING;
+        }
+
+        y = block_bottom.max(text_y) + BLOCK_SPACING;
+        if y < bottom {
+            page.line(MARGIN, y, page_width - MARGIN, y, RULE);
+        }
+        y += BLOCK_SPACING;
+    }
+    pages.push(page);
+
+    let total = pages.len();
+    let footer_y = page_height - MARGIN;
+    for (index, page) in pages.iter_mut().enumerate() {
+        let label = i18n::tr(
+            "report-page",
+            &[("page", (index + 1).into()), ("total", total.into())],
+        );
+        let width = document.text_width(&label, 8.0);
+        document.text(page, title, MARGIN, footer_y, 8.0, MUTED);
+        document.text(
+            page,
+            &label,
+            page_width - MARGIN - width,
+            footer_y,
+            8.0,
+            MUTED,
+        );
+    }
+    Ok(document.finish(pages))
+}
+
+pub(super) fn write(
+    path: &Path,
+    title: &str,
+    notes: &[ReportNote],
+    options: &PdfExportOptions,
+) -> Result<()> {
+    fs::write(path, render(title, notes, options)?)?;
+    Ok(())
+}
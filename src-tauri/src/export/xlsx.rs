@@ -0,0 +1,58 @@
+//! Excel writer for note rows.
+//!
+//! Uses constant-memory mode so rows are flushed to disk as they are written.
+
+use std::path::Path;
+
+use rust_xlsxwriter::{Format, FormatAlign, Workbook};
+
+use super::{NoteRow, headers};
+use crate::error::Result;
+use crate::i18n;
+
+/// Excel rejects sheet names over 31 characters or containing `[]:*?/\`.
+fn sheet_name(playlist_name: &str) -> String {
+    let name: String = playlist_name
+        .chars()
+        .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(31)
+        .collect();
+    if name.trim().is_empty() {
+        "Notes".to_string()
+    } else {
+        name
+    }
+}
+
+pub(super) fn write(path: &Path, playlist_name: &str, rows: &[NoteRow]) -> Result<()> {
+    let mut workbook = Workbook::new();
+    let header = Format::new().set_bold();
+    let wrap = Format::new().set_text_wrap().set_align(FormatAlign::Top);
+
+    let sheet = workbook.add_worksheet_with_constant_memory();
+    sheet.set_name(sheet_name(playlist_name))?;
+    sheet.set_column_width(0, 32)?;
+    sheet.set_column_width(3, 24)?;
+    sheet.set_column_width(7, 80)?;
+    for (col, title) in headers().iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, title, &header)?;
+    }
+
+    for (index, row) in rows.iter().enumerate() {
+        let r = index as u32 + 1;
+        sheet.write_string(r, 0, &row.version_name)?;
+        sheet.write_number(r, 1, row.version_number)?;
+        sheet.write_string(r, 2, row.note_state.as_deref().unwrap_or_default())?;
+        sheet.write_string(r, 3, row.labels.join(", "))?;
+        sheet.write_string(r, 4, row.author.as_deref().unwrap_or_default())?;
+        let created_at = row.created_at.as_deref().map(i18n::timestamp);
+        sheet.write_string(r, 5, created_at.unwrap_or_default())?;
+        if let Some(frame) = row.frame_number {
+            sheet.write_number(r, 6, frame)?;
+        }
+        sheet.write_string_with_format(r, 7, &row.content, &wrap)?;
+    }
+
+    workbook.save(path)?;
+    Ok(())
+}
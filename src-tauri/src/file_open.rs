@@ -0,0 +1,373 @@
+//! Files opened with AstraNotes from the OS: notes CSVs, OTIO timelines and
+//! `.astranotes` sessions.
+//!
+//! macOS delivers them as `Opened` run events; Windows and Linux pass them
+//! as arguments to the first launch or, through the single-instance plugin,
+//! to the running app. Each file is parsed and validated here, then emitted
+//! as `file-open:opened`, or as `file-open:invalid` with the reason. Until
+//! the webview first calls `take_opened_files`, opened files are kept for it
+//! instead of emitted, so each is handled once.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+#[cfg(target_os = "macos")]
+use tauri::Url;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::export;
+use crate::session::{self, SessionSummary};
+
+pub const OPENED_EVENT: &str = "file-open:opened";
+pub const INVALID_EVENT: &str = "file-open:invalid";
+/// Anything bigger is not a file this app wrote.
+const MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+const EXTENSIONS: &[&str] = &["csv", "otio", "astranotes"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedNote {
+    pub version_name: String,
+    pub version_number: Option<u32>,
+    pub content: String,
+    pub note_state: Option<String>,
+    pub labels: Vec<String>,
+    pub author: Option<String>,
+    pub created_at: Option<String>,
+    pub frame_number: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineMarker {
+    pub name: String,
+    pub comment: String,
+    pub frame: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineClip {
+    pub name: String,
+    /// Set on clips exported by AstraNotes.
+    pub version_id: Option<String>,
+    pub version_number: Option<u32>,
+    pub status: Option<String>,
+    pub markers: Vec<TimelineMarker>,
+    /// Notes without a frame, kept in the clip metadata by the exporter.
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedTimeline {
+    pub name: String,
+    pub playlist_id: Option<String>,
+    pub clips: Vec<TimelineClip>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum OpenedFile {
+    Notes {
+        path: PathBuf,
+        notes: Vec<ImportedNote>,
+    },
+    Timeline {
+        path: PathBuf,
+        timeline: ImportedTimeline,
+    },
+    Session {
+        path: PathBuf,
+        session: SessionSummary,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Invalid {
+    path: PathBuf,
+    error: String,
+}
+
+#[derive(Default)]
+struct Pending {
+    files: Vec<OpenedFile>,
+    /// Set once the frontend has taken the queue; later files are emitted.
+    listening: bool,
+}
+
+#[derive(Default)]
+pub struct OpenedFiles(Mutex<Pending>);
+
+impl OpenedFiles {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Pending> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+}
+
+/// Whether a launch argument is a file this module opens.
+pub(crate) fn is_openable(arg: &str) -> bool {
+    let path = Path::new(arg);
+    extension(path).is_some_and(|ext| EXTENSIONS.contains(&ext.as_str()))
+}
+
+fn parse_csv(path: &Path) -> Result<Vec<ImportedNote>> {
+    let mut reader = ::csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers: BTreeMap<String, usize> = reader
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (header.trim().to_string(), index))
+        .collect();
+    let [
+        version_name,
+        version_number,
+        note_state,
+        labels,
+        author,
+        created_at,
+        frame,
+        content,
+    ] = {
+        // Exports carry headers in the locale they were written in
+        let localized = export::headers();
+        std::array::from_fn(|index| {
+            headers
+                .get(export::HEADERS[index])
+                .or_else(|| headers.get(&localized[index]))
+                .copied()
+        })
+    };
+    let (Some(version_name), Some(content)) = (version_name, content) else {
+        return Err(Error::InvalidInput(
+            "Not an AstraNotes notes export: the Version Name and Notes columns are missing".into(),
+        ));
+    };
+
+    let mut notes = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let Some(name) = field(Some(version_name)) else {
+            continue;
+        };
+        notes.push(ImportedNote {
+            version_name: name,
+            version_number: field(version_number).and_then(|value| value.parse().ok()),
+            content: field(Some(content)).unwrap_or_default(),
+            note_state: field(note_state),
+            labels: field(labels)
+                .map(|labels| labels.split(',').map(|l| l.trim().to_string()).collect())
+                .unwrap_or_default(),
+            author: field(author),
+            created_at: field(created_at),
+            frame_number: field(frame).and_then(|value| value.parse().ok()),
+        });
+    }
+    Ok(notes)
+}
+
+fn schema(value: &Value) -> &str {
+    value["OTIO_SCHEMA"]
+        .as_str()
+        .and_then(|schema| schema.split('.').next())
+        .unwrap_or_default()
+}
+
+fn collect_clips(item: &Value, clips: &mut Vec<TimelineClip>) {
+    match schema(item) {
+        "Clip" => {
+            let astranotes = &item["metadata"]["astranotes"];
+            let markers = item["markers"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|marker| TimelineMarker {
+                    name: marker["name"].as_str().unwrap_or_default().to_string(),
+                    comment: marker["comment"].as_str().unwrap_or_default().to_string(),
+                    frame: marker["marked_range"]["start_time"]["value"]
+                        .as_f64()
+                        .map(|frame| frame.round() as i64),
+                })
+                .collect();
+            clips.push(TimelineClip {
+                name: item["name"].as_str().unwrap_or_default().to_string(),
+                version_id: astranotes["versionId"].as_str().map(str::to_string),
+                version_number: astranotes["versionNumber"]
+                    .as_u64()
+                    .map(|number| number as u32),
+                status: astranotes["status"].as_str().map(str::to_string),
+                markers,
+                notes: astranotes["notes"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|note| note.as_str().map(str::to_string))
+                    .collect(),
+            });
+        }
+        "Stack" | "Track" | "SerializableCollection" => {
+            for child in item["children"].as_array().into_iter().flatten() {
+                collect_clips(child, clips);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parse_otio(path: &Path) -> Result<ImportedTimeline> {
+    let value: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    if schema(&value) != "Timeline" {
+        return Err(Error::InvalidInput(format!(
+            "Not an OTIO timeline: found {}",
+            value["OTIO_SCHEMA"].as_str().unwrap_or("no schema")
+        )));
+    }
+    let mut clips = Vec::new();
+    collect_clips(&value["tracks"], &mut clips);
+    Ok(ImportedTimeline {
+        name: value["name"].as_str().unwrap_or_default().to_string(),
+        playlist_id: value["metadata"]["astranotes"]["playlistId"]
+            .as_str()
+            .map(str::to_string),
+        clips,
+    })
+}
+
+fn parse(path: &Path) -> Result<OpenedFile> {
+    let size = std::fs::metadata(path)?.len();
+    if size > MAX_FILE_BYTES {
+        return Err(Error::InvalidInput(format!(
+            "{} is too large to open ({} MB)",
+            path.display(),
+            size / (1024 * 1024)
+        )));
+    }
+    let path_buf = path.to_path_buf();
+    match extension(path).as_deref() {
+        Some("csv") => Ok(OpenedFile::Notes {
+            notes: parse_csv(path)?,
+            path: path_buf,
+        }),
+        Some("otio") => Ok(OpenedFile::Timeline {
+            timeline: parse_otio(path)?,
+            path: path_buf,
+        }),
+        Some("astranotes") => Ok(OpenedFile::Session {
+            session: session::read_summary(path)?,
+            path: path_buf,
+        }),
+        _ => Err(Error::InvalidInput(format!(
+            "AstraNotes cannot open {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Parses each file off the main thread and hands it to the frontend.
+pub(crate) fn open(app: &AppHandle, paths: Vec<PathBuf>) {
+    if paths.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for path in paths {
+            let target = path.clone();
+            let parsed = tauri::async_runtime::spawn_blocking(move || parse(&target))
+                .await
+                .map_err(Error::from)
+                .and_then(|result| result);
+            let result = match parsed {
+                Ok(opened) => {
+                    log::info!("Opened {}", path.display());
+                    let queue = app.try_state::<OpenedFiles>();
+                    let mut pending = queue.as_ref().map(|queue| queue.lock());
+                    match pending.as_mut().filter(|pending| !pending.listening) {
+                        Some(pending) => {
+                            pending.files.push(opened);
+                            Ok(())
+                        }
+                        None => app.emit(OPENED_EVENT, opened),
+                    }
+                }
+                Err(err) => {
+                    log::warn!("Cannot open {}: {err}", path.display());
+                    let invalid = Invalid {
+                        path,
+                        error: err.to_string(),
+                    };
+                    app.emit(INVALID_EVENT, invalid)
+                }
+            };
+            if let Err(err) = result {
+                log::warn!("Failed to emit opened file: {err}");
+            }
+        }
+        crate::focus_main_window(&app);
+    });
+}
+
+/// Opens the files among launch arguments, resolving relative paths against `cwd`.
+pub(crate) fn open_args<'a>(
+    app: &AppHandle,
+    args: impl IntoIterator<Item = &'a String>,
+    cwd: &Path,
+) {
+    let paths = args
+        .into_iter()
+        .filter(|arg| is_openable(arg))
+        .map(|arg| cwd.join(arg))
+        .collect();
+    open(app, paths);
+}
+
+/// Opens `file://` URLs from a macOS open-file event.
+#[cfg(target_os = "macos")]
+pub(crate) fn open_urls(app: &AppHandle, urls: &[Url]) {
+    let paths = urls
+        .iter()
+        .filter_map(|url| url.to_file_path().ok())
+        .filter(|path| path.to_str().is_some_and(is_openable))
+        .collect();
+    open(app, paths);
+}
+
+/// Opens any files the app was launched with.
+pub fn init(app: &AppHandle) {
+    app.manage(OpenedFiles::default());
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    open_args(app, &args, &cwd);
+}
+
+/// Files opened before the frontend listened. Files opened after the first
+/// call arrive as events instead.
+#[tauri::command]
+pub fn take_opened_files(opened: State<'_, OpenedFiles>) -> Vec<OpenedFile> {
+    let mut pending = opened.lock();
+    pending.listening = true;
+    std::mem::take(&mut pending.files)
+}
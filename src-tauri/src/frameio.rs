@@ -0,0 +1,247 @@
+//! Imports Frame.io review link comments as note drafts.
+//!
+//! Clients often leave their feedback on a Frame.io review link. This module
+//! reads the link's assets and comments through the v2 API, authenticated
+//! with a developer token kept in the keychain. The frontend maps each asset
+//! to a version, and [`review_import`] writes the comments into drafts.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::network;
+use crate::review_import::{self, ImportSummary, ReviewComment};
+use crate::store::Store;
+use crate::timecode::format_timecode;
+
+const API_URL: &str = "https://api.frame.io/v2";
+const TOKEN_KEY: &str = "frameio-token";
+const PAGE_SIZE: usize = 100;
+
+static REVIEW_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"/reviews/([0-9a-fA-F-]{36})").expect("valid review link pattern")
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameioAccount {
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameioAsset {
+    pub id: String,
+    pub name: String,
+    pub fps: Option<f64>,
+    pub comments: Vec<ReviewComment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameioReview {
+    pub id: String,
+    pub name: String,
+    pub assets: Vec<FrameioAsset>,
+}
+
+#[derive(Deserialize)]
+struct RawComment {
+    id: String,
+    #[serde(default)]
+    text: Option<String>,
+    /// Frame the comment was left on, counted from 0.
+    #[serde(default)]
+    timestamp: Option<f64>,
+    /// Drawing data, as a JSON-encoded string.
+    #[serde(default)]
+    annotation: Option<String>,
+    #[serde(default)]
+    owner: Option<FrameioAccount>,
+    #[serde(default)]
+    inserted_at: Option<String>,
+    #[serde(default)]
+    replies: Vec<RawComment>,
+}
+
+impl RawComment {
+    fn into_comment(self, fps: Option<f64>) -> ReviewComment {
+        let frame = self.timestamp.map(|timestamp| timestamp.round() as i64);
+        ReviewComment {
+            id: self.id,
+            author: self.owner.map(|owner| owner.name),
+            text: self.text.unwrap_or_default(),
+            frame,
+            timecode: frame
+                .zip(fps)
+                .map(|(frame, fps)| format_timecode(frame, fps, false)),
+            created_at: self.inserted_at,
+            annotation: self
+                .annotation
+                .and_then(|annotation| serde_json::from_str(&annotation).ok()),
+            image_url: None,
+            replies: self
+                .replies
+                .into_iter()
+                .map(|reply| reply.into_comment(fps))
+                .collect(),
+        }
+    }
+}
+
+fn token() -> Result<String> {
+    credentials::get(TOKEN_KEY)?
+        .ok_or_else(|| Error::InvalidInput("Sign in to Frame.io first".into()))
+}
+
+async fn get(client: &reqwest::Client, token: &str, path: &str) -> Result<reqwest::Response> {
+    let response = client
+        .get(format!("{API_URL}{path}"))
+        .bearer_auth(token)
+        .send()
+        .await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body: Value = response.json().await.unwrap_or_default();
+    let message = body["message"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("Frame.io responded with {status}"));
+    Err(Error::Provider(message))
+}
+
+/// Fetches every page of a list endpoint.
+async fn get_all<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    token: &str,
+    path: &str,
+) -> Result<Vec<T>> {
+    let separator = if path.contains('?') { '&' } else { '?' };
+    let mut items = Vec::new();
+    let mut page = 1;
+    loop {
+        let response = get(
+            client,
+            token,
+            &format!("{path}{separator}page={page}&page_size={PAGE_SIZE}"),
+        )
+        .await?;
+        let total_pages: usize = response
+            .headers()
+            .get("total-pages")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1);
+        items.extend(response.json::<Vec<T>>().await?);
+        if page >= total_pages {
+            break;
+        }
+        page += 1;
+    }
+    Ok(items)
+}
+
+/// Extracts the review link ID, following `f.io` short links first.
+async fn review_id(client: &reqwest::Client, link: &str) -> Result<String> {
+    let link = link.trim();
+    let resolved = if link.contains("://f.io/") {
+        client.get(link).send().await?.url().to_string()
+    } else {
+        link.to_string()
+    };
+    REVIEW_ID
+        .captures(&resolved)
+        .map(|captures| captures[1].to_lowercase())
+        .ok_or_else(|| Error::InvalidInput(format!("Not a Frame.io review link: {link}")))
+}
+
+async fn fetch(client: &reqwest::Client, link: &str) -> Result<FrameioReview> {
+    let token = token()?;
+    let id = review_id(client, link).await?;
+    let review: Value = get(client, &token, &format!("/review_links/{id}"))
+        .await?
+        .json()
+        .await?;
+    let items: Vec<Value> = get_all(client, &token, &format!("/review_links/{id}/items")).await?;
+
+    let mut assets = Vec::new();
+    for item in items {
+        let asset = &item["asset"];
+        let Some(asset_id) = asset["id"].as_str().or(item["asset_id"].as_str()) else {
+            continue;
+        };
+        let fps = asset["fps"].as_f64().filter(|fps| *fps > 0.0);
+        let comments: Vec<RawComment> = get_all(
+            client,
+            &token,
+            &format!("/assets/{asset_id}/comments?include=owner,replies"),
+        )
+        .await?;
+        assets.push(FrameioAsset {
+            id: asset_id.to_string(),
+            name: asset["name"].as_str().unwrap_or_default().to_string(),
+            fps,
+            comments: comments
+                .into_iter()
+                .map(|comment| comment.into_comment(fps))
+                .collect(),
+        });
+    }
+    Ok(FrameioReview {
+        id,
+        name: review["name"].as_str().unwrap_or_default().to_string(),
+        assets,
+    })
+}
+
+/// Checks a developer token against the API and stores it.
+#[tauri::command]
+pub async fn set_frameio_token(token: String) -> Result<FrameioAccount> {
+    let token = token.trim();
+    let account = get(&network::client(), token, "/me").await?.json().await?;
+    credentials::store(TOKEN_KEY, token)?;
+    Ok(account)
+}
+
+#[tauri::command]
+pub fn clear_frameio_token() -> Result<bool> {
+    credentials::delete(TOKEN_KEY)
+}
+
+/// Reads a review link's assets and comments without importing anything.
+#[tauri::command]
+pub async fn fetch_frameio_review(link: String) -> Result<FrameioReview> {
+    fetch(&network::client(), &link).await
+}
+
+#[tauri::command]
+pub async fn import_frameio_review(
+    store: State<'_, Store>,
+    link: String,
+    playlist_id: String,
+    mapping: HashMap<String, String>,
+) -> Result<ImportSummary> {
+    let review = fetch(&network::client(), &link).await?;
+    let comments: Vec<_> = review
+        .assets
+        .into_iter()
+        .map(|asset| (asset.id, asset.comments))
+        .collect();
+    let summary = review_import::into_drafts(&store, &playlist_id, &mapping, &comments)?;
+    log::info!(
+        "Imported {} Frame.io comments from {} into playlist {playlist_id}",
+        summary.imported,
+        review.name
+    );
+    Ok(summary)
+}
@@ -0,0 +1,221 @@
+//! Persistent cache of Query API responses.
+//!
+//! Each result is stored zstd-compressed as `<hash>.zst` in the app cache
+//! directory, keyed by server, user and expression. Entries outlive their
+//! TTL: an expired entry is revalidated with its `ETag`, and is still served
+//! when the server cannot be reached.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::Connection;
+use crate::error::Result;
+use crate::export::write_atomically;
+use crate::store::now_millis;
+
+const COMPRESSION_LEVEL: i32 = 3;
+/// Entries not refreshed for this long are removed at startup and by scheduled maintenance.
+const MAX_STALE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    pub stored_at: i64,
+    pub expires_at: i64,
+    pub etag: Option<String>,
+    pub value: Value,
+}
+
+impl CacheEntry {
+    pub fn new(value: Value, etag: Option<String>, ttl: Duration) -> Self {
+        let stored_at = now_millis();
+        Self {
+            stored_at,
+            expires_at: stored_at + ttl.as_millis() as i64,
+            etag,
+            value,
+        }
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.expires_at > now_millis()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Outcome {
+    /// Served without contacting the server.
+    Hit,
+    /// The server confirmed the cached entry with `304 Not Modified`.
+    Revalidated,
+    /// Fetched from the server.
+    Miss,
+    /// Served past its TTL because the server was unreachable.
+    Stale,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub entries: u64,
+    pub size_bytes: u64,
+    /// Counters since launch.
+    pub hits: u64,
+    pub revalidated: u64,
+    pub misses: u64,
+    pub stale: u64,
+}
+
+pub struct ApiCache {
+    dir: PathBuf,
+    hits: AtomicU64,
+    revalidated: AtomicU64,
+    misses: AtomicU64,
+    stale: AtomicU64,
+}
+
+impl ApiCache {
+    pub fn open(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let cache = Self {
+            dir,
+            hits: AtomicU64::new(0),
+            revalidated: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            stale: AtomicU64::new(0),
+        };
+        cache.prune();
+        Ok(cache)
+    }
+
+    pub fn key(connection: &Connection, expression: &str) -> String {
+        let source = format!(
+            "{}\0{}\0{expression}",
+            connection.base_url(),
+            connection.api_user
+        );
+        format!("{:016x}", xxh3_64(source.as_bytes()))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.zst"))
+    }
+
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        let path = self.path(key);
+        let file = File::open(&path).ok()?;
+        let entry = zstd::Decoder::new(file)
+            .map_err(serde_json::Error::io)
+            .and_then(serde_json::from_reader);
+        match entry {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                log::warn!("Dropping unreadable cache entry {}: {err}", path.display());
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: &str, entry: &CacheEntry) -> Result<()> {
+        write_atomically(&self.path(key), |temp| {
+            let mut encoder =
+                zstd::Encoder::new(BufWriter::new(File::create(temp)?), COMPRESSION_LEVEL)?;
+            serde_json::to_writer(&mut encoder, entry)?;
+            encoder.finish()?;
+            Ok(())
+        })
+    }
+
+    pub fn record(&self, outcome: Outcome) {
+        let counter = match outcome {
+            Outcome::Hit => &self.hits,
+            Outcome::Revalidated => &self.revalidated,
+            Outcome::Miss => &self.misses,
+            Outcome::Stale => &self.stale,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Removes every entry and returns how many were deleted.
+    pub fn clear(&self) -> Result<u64> {
+        let mut removed = 0;
+        for path in self.files()? {
+            match fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(err) => log::warn!("Failed to remove {}: {err}", path.display()),
+            }
+        }
+        Ok(removed)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats> {
+        let files = self.files()?;
+        let size_bytes = files
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|meta| meta.len())
+            .sum();
+        Ok(CacheStats {
+            entries: files.len() as u64,
+            size_bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            revalidated: self.revalidated.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            stale: self.stale.load(Ordering::Relaxed),
+        })
+    }
+
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if is_entry(&path) {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Removes entries not refreshed within [`MAX_STALE`] and returns how
+    /// many. Uses modification times so startup does not decode every entry.
+    pub fn prune(&self) -> u64 {
+        let Ok(files) = self.files() else {
+            return 0;
+        };
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for path in files {
+            let age = fs::metadata(&path)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok());
+            if age.is_some_and(|age| age > MAX_STALE) && fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+}
+
+fn is_entry(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "zst") && path.is_file()
+}
+
+#[tauri::command]
+pub fn clear_api_cache(cache: State<'_, ApiCache>) -> Result<u64> {
+    cache.clear()
+}
+
+#[tauri::command]
+pub fn get_api_cache_stats(cache: State<'_, ApiCache>) -> Result<CacheStats> {
+    cache.stats()
+}
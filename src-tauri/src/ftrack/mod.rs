@@ -0,0 +1,187 @@
+//! Minimal client for the ftrack JSON API.
+//!
+//! Every request is a batch of operations POSTed to `<server>/api`,
+//! authenticated with the API key held in the OS keychain for that server
+//! and user (see `profiles`).
+
+pub mod cache;
+pub mod proxy;
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, IF_NONE_MATCH, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::credentials;
+
+/// ID of the built-in `ftrack.server` location that stores uploaded components.
+pub const SERVER_LOCATION_ID: &str = "3a372bde-05bc-11e4-8908-20c9d081909b";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection {
+    pub server_url: String,
+    pub api_user: String,
+}
+
+impl Connection {
+    pub fn base_url(&self) -> &str {
+        self.server_url.trim_end_matches('/')
+    }
+
+    /// Keychain entry holding the API key for this server and user.
+    pub fn credential_key(&self) -> String {
+        format!(
+            "{}:{}@{}",
+            credentials::FTRACK_API_KEY,
+            self.api_user,
+            self.base_url()
+        )
+    }
+
+    /// Looks up the API key for this connection, falling back to the single
+    /// key stored by versions without profiles.
+    pub fn api_key(&self) -> Result<String, ApiError> {
+        for key in [self.credential_key().as_str(), credentials::FTRACK_API_KEY] {
+            if let Some(api_key) = credentials::get(key).map_err(Box::new)? {
+                return Ok(api_key);
+            }
+        }
+        Err(ApiError::MissingApiKey)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("No ftrack API key stored")]
+    MissingApiKey,
+    #[error(transparent)]
+    Credentials(#[from] Box<crate::error::Error>),
+    #[error(transparent)]
+    Transport(#[from] reqwest::Error),
+    #[error("ftrack responded with {0}")]
+    Status(StatusCode),
+    /// 429 response, with the delay requested by `Retry-After` if any.
+    #[error("ftrack rate limit exceeded")]
+    RateLimited(Option<Duration>),
+    #[error("{exception}: {content}")]
+    Server { exception: String, content: String },
+}
+
+impl ApiError {
+    /// Network errors, rate limiting and server errors are worth retrying;
+    /// anything the server rejected will fail the same way again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(_) | Self::RateLimited(_) => true,
+            Self::Status(status) => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+/// Outcome of [`call_conditional`].
+pub enum Response {
+    /// The server confirmed the `ETag` sent with the request is still current.
+    NotModified,
+    Results {
+        results: Vec<Value>,
+        etag: Option<String>,
+    },
+}
+
+/// Sends a batch of operations and returns one result per operation.
+pub async fn call(
+    client: &reqwest::Client,
+    connection: &Connection,
+    operations: &Value,
+) -> Result<Vec<Value>, ApiError> {
+    match call_conditional(client, connection, operations, None).await? {
+        Response::Results { results, .. } => Ok(results),
+        Response::NotModified => Ok(Vec::new()),
+    }
+}
+
+/// Like [`call`], but revalidates a previous response when `etag` is given.
+pub async fn call_conditional(
+    client: &reqwest::Client,
+    connection: &Connection,
+    operations: &Value,
+    etag: Option<&str>,
+) -> Result<Response, ApiError> {
+    let api_key = connection.api_key()?;
+
+    let mut request = client
+        .post(format!("{}/api", connection.base_url()))
+        .header("ftrack-api-key", api_key)
+        .header("ftrack-user", &connection.api_user);
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    let response = request.json(operations).send().await?;
+
+    let status = response.status();
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(Response::NotModified);
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        return Err(ApiError::RateLimited(retry_after));
+    }
+    if status.is_server_error() {
+        return Err(ApiError::Status(status));
+    }
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body: Value = response.json().await.unwrap_or_default();
+    if let Some(exception) = body.get("exception").and_then(Value::as_str) {
+        let content = body
+            .get("content")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        return Err(ApiError::Server {
+            exception: exception.to_string(),
+            content: content.to_string(),
+        });
+    }
+    if !status.is_success() {
+        return Err(ApiError::Status(status));
+    }
+
+    let results = match body {
+        Value::Array(results) => results,
+        other => vec![other],
+    };
+    Ok(Response::Results { results, etag })
+}
+
+/// Quotes a value for an ftrack query expression.
+pub(crate) fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Runs one query expression and returns the entities it matched.
+pub(crate) async fn query(
+    client: &reqwest::Client,
+    connection: &Connection,
+    expression: String,
+) -> Result<Vec<Value>, ApiError> {
+    let operations = json!([{ "action": "query", "expression": expression }]);
+    let results = call(client, connection, &operations).await?;
+    Ok(results
+        .into_iter()
+        .next()
+        .and_then(|result| result.get("data")?.as_array().cloned())
+        .unwrap_or_default())
+}
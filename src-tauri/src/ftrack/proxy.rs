@@ -0,0 +1,196 @@
+//! Query API proxy shared by every webview request.
+//!
+//! Loading a large playlist used to fire hundreds of parallel queries from
+//! the frontend and trip ftrack's rate limiter. Here requests wait for one of
+//! a few connection slots, a 429 pauses every caller until the server's
+//! `Retry-After` has passed, and results are kept in the [`ApiCache`].
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tauri::State;
+use tauri::ipc::Response as RawResponse;
+use tokio::sync::Semaphore;
+
+use super::cache::{ApiCache, CacheEntry, Outcome};
+use super::{ApiError, Connection, Response, call_conditional};
+use crate::error::Result;
+use crate::ipc_payload;
+use crate::network;
+use crate::profiles;
+use crate::store::Store;
+
+const MAX_CONCURRENT: usize = 4;
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryOptions {
+    /// Defaults to the active profile.
+    #[serde(default)]
+    pub connection: Option<Connection>,
+    /// How long the result may be reused without asking the server; `0`
+    /// disables caching.
+    #[serde(default)]
+    pub cache_ttl_ms: Option<u64>,
+    /// Revalidate a cached result even if it has not expired.
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+pub struct FtrackProxy {
+    slots: Semaphore,
+    /// Set after a 429 so queued requests do not immediately hit the limit again.
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl Default for FtrackProxy {
+    fn default() -> Self {
+        Self {
+            slots: Semaphore::new(MAX_CONCURRENT),
+            paused_until: Mutex::new(None),
+        }
+    }
+}
+
+impl FtrackProxy {
+    fn pause_remaining(&self) -> Option<Duration> {
+        let paused_until = *self
+            .paused_until
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        paused_until.and_then(|until| until.checked_duration_since(Instant::now()))
+    }
+
+    fn pause_for(&self, delay: Duration) {
+        let mut paused_until = self
+            .paused_until
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let until = Instant::now() + delay;
+        if paused_until.is_none_or(|current| current < until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    /// Sends `operations` once a slot is free, retrying rate limits and
+    /// transient failures with backoff.
+    pub async fn send(
+        &self,
+        connection: &Connection,
+        operations: &Value,
+        etag: Option<&str>,
+    ) -> std::result::Result<Response, ApiError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = {
+                let _slot = self.slots.acquire().await.expect("semaphore never closed");
+                if let Some(remaining) = self.pause_remaining() {
+                    tokio::time::sleep(remaining).await;
+                }
+                call_conditional(&network::client(), connection, operations, etag).await
+            };
+            match result {
+                Err(err) if err.is_retryable() && attempt < MAX_ATTEMPTS => {
+                    let backoff = (BASE_BACKOFF * 2u32.pow(attempt - 1)).min(MAX_BACKOFF);
+                    let delay = match err {
+                        ApiError::RateLimited(retry_after) => {
+                            let delay = retry_after.unwrap_or(backoff);
+                            self.pause_for(delay);
+                            delay
+                        }
+                        _ => backoff,
+                    };
+                    log::warn!("ftrack request failed (attempt {attempt}), retrying: {err}");
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Runs a Query API expression and returns ftrack's `{ data, metadata }` result.
+#[tauri::command]
+pub async fn ftrack_query(
+    proxy: State<'_, FtrackProxy>,
+    cache: State<'_, ApiCache>,
+    store: State<'_, Store>,
+    expression: String,
+    options: Option<QueryOptions>,
+) -> Result<Value> {
+    query(&proxy, &cache, &store, expression, options).await
+}
+
+/// [`ftrack_query`] returning the result as raw JSON bytes, for large playlist loads.
+#[tauri::command]
+pub async fn ftrack_query_raw(
+    proxy: State<'_, FtrackProxy>,
+    cache: State<'_, ApiCache>,
+    store: State<'_, Store>,
+    expression: String,
+    options: Option<QueryOptions>,
+) -> Result<RawResponse> {
+    let value = query(&proxy, &cache, &store, expression, options).await?;
+    ipc_payload::json(&value)
+}
+
+async fn query(
+    proxy: &FtrackProxy,
+    cache: &ApiCache,
+    store: &Store,
+    expression: String,
+    options: Option<QueryOptions>,
+) -> Result<Value> {
+    let options = options.unwrap_or_default();
+    let connection = profiles::resolve(store, options.connection)?;
+    let ttl = options
+        .cache_ttl_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_CACHE_TTL);
+    let caching = !ttl.is_zero();
+    let key = ApiCache::key(&connection, &expression);
+    let cached = if caching { cache.get(&key) } else { None };
+    if let Some(entry) = &cached
+        && entry.is_fresh()
+        && !options.refresh
+    {
+        cache.record(Outcome::Hit);
+        return Ok(entry.value.clone());
+    }
+
+    let operations = json!([{ "action": "query", "expression": expression }]);
+    let etag = cached.as_ref().and_then(|entry| entry.etag.as_deref());
+    let (entry, outcome) = match (proxy.send(&connection, &operations, etag).await, cached) {
+        (Ok(Response::NotModified), Some(entry)) => (
+            CacheEntry::new(entry.value, entry.etag, ttl),
+            Outcome::Revalidated,
+        ),
+        (Ok(Response::Results { results, etag }), _) => {
+            let value = results.into_iter().next().unwrap_or(Value::Null);
+            (CacheEntry::new(value, etag, ttl), Outcome::Miss)
+        }
+        (Err(ApiError::Transport(err)), Some(entry)) => {
+            log::warn!("Serving cached result for {expression}, ftrack unreachable: {err}");
+            cache.record(Outcome::Stale);
+            return Ok(entry.value);
+        }
+        // Only possible if the server ignores the missing `If-None-Match`
+        (Ok(Response::NotModified), None) => {
+            return Err(ApiError::Status(StatusCode::NOT_MODIFIED).into());
+        }
+        (Err(err), _) => return Err(err.into()),
+    };
+    cache.record(outcome);
+    if caching && let Err(err) = cache.put(&key, &entry) {
+        log::warn!("Failed to cache result for {expression}: {err}");
+    }
+    Ok(entry.value)
+}
@@ -0,0 +1,143 @@
+//! Number, date and plural conventions per locale.
+//!
+//! Locales are matched on their language, with a few regional overrides;
+//! unknown locales fall back to ISO dates and plain numbers.
+
+use chrono::{DateTime, Local};
+
+pub(super) enum Plural {
+    One,
+    Other,
+}
+
+struct Conventions {
+    decimal: &'static str,
+    group: &'static str,
+    date: &'static str,
+    datetime: &'static str,
+}
+
+const ISO: Conventions = Conventions {
+    decimal: ".",
+    group: "",
+    date: "%Y-%m-%d",
+    datetime: "%Y-%m-%d %H:%M",
+};
+
+fn language(locale: &str) -> &str {
+    locale.split(['-', '_']).next().unwrap_or(locale)
+}
+
+fn conventions(locale: &str) -> Conventions {
+    let region = |date, datetime| Conventions {
+        date,
+        datetime,
+        ..conventions(language(locale))
+    };
+    match locale {
+        "en-US" => {
+            return region("%m/%d/%Y", "%m/%d/%Y %-I:%M %p");
+        }
+        "de-CH" => {
+            return Conventions {
+                group: "’",
+                ..conventions("de")
+            };
+        }
+        "pt-BR" | "es-MX" => return region("%d/%m/%Y", "%d/%m/%Y %H:%M"),
+        _ => {}
+    }
+    match language(locale) {
+        "en" => Conventions {
+            decimal: ".",
+            group: ",",
+            date: "%d/%m/%Y",
+            datetime: "%d/%m/%Y %H:%M",
+        },
+        // Narrow no-break space, as CLDR has it
+        "fr" => Conventions {
+            decimal: ",",
+            group: "\u{202f}",
+            date: "%d/%m/%Y",
+            datetime: "%d/%m/%Y %H:%M",
+        },
+        "de" => Conventions {
+            decimal: ",",
+            group: ".",
+            date: "%d.%m.%Y",
+            datetime: "%d.%m.%Y %H:%M",
+        },
+        "es" | "it" | "pt" => Conventions {
+            decimal: ",",
+            group: ".",
+            date: "%d/%m/%Y",
+            datetime: "%d/%m/%Y %H:%M",
+        },
+        "nl" => Conventions {
+            decimal: ",",
+            group: ".",
+            date: "%d-%m-%Y",
+            datetime: "%d-%m-%Y %H:%M",
+        },
+        "ja" | "zh" => Conventions {
+            decimal: ".",
+            group: ",",
+            date: "%Y/%m/%d",
+            datetime: "%Y/%m/%d %H:%M",
+        },
+        "ko" => Conventions {
+            decimal: ".",
+            group: ",",
+            date: "%Y. %m. %d.",
+            datetime: "%Y. %m. %d. %H:%M",
+        },
+        _ => ISO,
+    }
+}
+
+pub(super) fn plural(locale: &str, value: f64) -> Plural {
+    let integer = value.fract() == 0.0;
+    let one = match language(locale) {
+        "ja" | "zh" | "ko" => false,
+        // French and Brazilian Portuguese count 0 and 1.5 as singular
+        "fr" => (0.0..2.0).contains(&value),
+        "pt" if locale != "pt-PT" => (0.0..2.0).contains(&value),
+        _ => integer && value == 1.0,
+    };
+    if one { Plural::One } else { Plural::Other }
+}
+
+/// Formats a number with the locale's separators, keeping up to three
+/// decimals.
+pub(crate) fn number(locale: &str, value: f64) -> String {
+    let conventions = conventions(locale);
+    let formatted = format!("{:.3}", value.abs());
+    let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+    let fraction = fraction.trim_end_matches('0');
+
+    let mut out = String::new();
+    if value < 0.0 && !formatted.trim_matches(['0', '.']).is_empty() {
+        out.push('-');
+    }
+    // Spanish leaves four-digit numbers ungrouped
+    let grouped = integer.len() > 4 || (integer.len() == 4 && language(locale) != "es");
+    for (index, digit) in integer.chars().enumerate() {
+        if grouped && index > 0 && (integer.len() - index) % 3 == 0 {
+            out.push_str(conventions.group);
+        }
+        out.push(digit);
+    }
+    if !fraction.is_empty() {
+        out.push_str(conventions.decimal);
+        out.push_str(fraction);
+    }
+    out
+}
+
+pub(crate) fn date(locale: &str, at: &DateTime<Local>) -> String {
+    at.format(conventions(locale).date).to_string()
+}
+
+pub(crate) fn datetime(locale: &str, at: &DateTime<Local>) -> String {
+    at.format(conventions(locale).datetime).to_string()
+}
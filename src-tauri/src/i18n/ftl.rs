@@ -0,0 +1,393 @@
+//! Parser and formatter for the subset of Fluent syntax the bundles use.
+//!
+//! Supported: messages and `-terms`, `.attributes` (looked up as
+//! `message.attribute`), multiline values, comments, and placeables holding
+//! `$variables`, term and message references, string literals and select
+//! expressions on plural categories or exact values. Indentation inside
+//! multiline values is not preserved.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::format::{self, Plural};
+
+#[derive(Debug, Clone)]
+pub(super) enum Element {
+    Text(String),
+    Placeable(Expr),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Expr {
+    Variable(String),
+    Term(String),
+    Message(String),
+    Literal(String),
+    Select {
+        selector: Box<Expr>,
+        variants: Vec<Variant>,
+        default: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Variant {
+    key: String,
+    value: Vec<Element>,
+}
+
+pub(super) type Pattern = Vec<Element>;
+
+/// Messages and terms of one `.ftl` file.
+#[derive(Debug, Default)]
+pub(super) struct Resource {
+    pub(super) messages: HashMap<String, Pattern>,
+    pub(super) terms: HashMap<String, Pattern>,
+}
+
+fn is_identifier(id: &str) -> bool {
+    let mut chars = id.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl Resource {
+    /// Parses a resource, skipping entries that do not parse with a warning.
+    pub(super) fn parse(source: &str, name: &str) -> Self {
+        let mut resource = Self::default();
+        // (id, raw value) of the entry being read; attributes are entries too
+        let mut entries: Vec<(String, String)> = Vec::new();
+        let mut parent: Option<String> = None;
+        for line in source.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let indented = line.starts_with([' ', '\t']);
+            if indented && let Some(attribute) = trimmed.strip_prefix('.') {
+                if let (Some(parent), Some((id, value))) = (&parent, attribute.split_once('=')) {
+                    entries.push((format!("{parent}.{}", id.trim()), value.trim().to_string()));
+                }
+                continue;
+            }
+            if indented {
+                if let Some((_, value)) = entries.last_mut() {
+                    if !value.is_empty() {
+                        value.push('\n');
+                    }
+                    value.push_str(trimmed);
+                }
+                continue;
+            }
+            match line.split_once('=') {
+                Some((id, value)) if is_identifier(id.trim().trim_start_matches('-')) => {
+                    let id = id.trim().to_string();
+                    parent = Some(id.clone());
+                    entries.push((id, value.trim().to_string()));
+                }
+                _ => {
+                    log::warn!("Skipping unreadable line in {name}: {line}");
+                    parent = None;
+                }
+            }
+        }
+
+        for (id, value) in entries {
+            let mut parser = Parser {
+                chars: value.chars().collect(),
+                pos: 0,
+            };
+            match parser.pattern(false) {
+                Some(pattern) => match id.strip_prefix('-') {
+                    Some(term) => {
+                        resource.terms.insert(term.to_string(), pattern);
+                    }
+                    None => {
+                        resource.messages.insert(id, pattern);
+                    }
+                },
+                None => log::warn!("Skipping unreadable entry {id} in {name}"),
+            }
+        }
+        resource
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_blank(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        let end = self.pos + expected.chars().count();
+        if end <= self.chars.len()
+            && self.chars[self.pos..end]
+                .iter()
+                .copied()
+                .eq(expected.chars())
+        {
+            self.pos = end;
+            return true;
+        }
+        false
+    }
+
+    /// Whether the next line starts a variant or closes the select.
+    fn at_variant_end(&self) -> bool {
+        let mut pos = self.pos;
+        while self.chars.get(pos).is_some_and(|c| c.is_whitespace()) {
+            pos += 1;
+        }
+        matches!(self.chars.get(pos), Some('[' | '*' | '}'))
+    }
+
+    /// Reads text and placeables; inside a variant, up to the next variant.
+    fn pattern(&mut self, in_variant: bool) -> Option<Pattern> {
+        let mut elements = Vec::new();
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '{' => {
+                    self.pos += 1;
+                    if !text.is_empty() {
+                        elements.push(Element::Text(std::mem::take(&mut text)));
+                    }
+                    elements.push(Element::Placeable(self.placeable()?));
+                }
+                '}' if in_variant => break,
+                '\n' if in_variant && self.at_variant_end() => break,
+                c => {
+                    self.pos += 1;
+                    text.push(c);
+                }
+            }
+        }
+        if !text.is_empty() {
+            elements.push(Element::Text(text));
+        }
+        Some(elements)
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        {
+            self.pos += 1;
+        }
+        let id: String = self.chars[start..self.pos].iter().collect();
+        (!id.is_empty()).then_some(id)
+    }
+
+    fn inline(&mut self) -> Option<Expr> {
+        self.skip_blank();
+        match self.peek()? {
+            '$' => {
+                self.pos += 1;
+                self.identifier().map(Expr::Variable)
+            }
+            '-' => {
+                self.pos += 1;
+                self.identifier().map(Expr::Term)
+            }
+            '"' => {
+                self.pos += 1;
+                let mut literal = String::new();
+                loop {
+                    match self.peek()? {
+                        '"' => break,
+                        '\\' => {
+                            self.pos += 1;
+                            literal.push(self.peek()?);
+                        }
+                        c => literal.push(c),
+                    }
+                    self.pos += 1;
+                }
+                self.pos += 1;
+                Some(Expr::Literal(literal))
+            }
+            _ => self.identifier().map(Expr::Message),
+        }
+    }
+
+    /// Reads a placeable after its opening brace, through the closing one.
+    fn placeable(&mut self) -> Option<Expr> {
+        let expr = self.inline()?;
+        self.skip_blank();
+        if self.eat("}") {
+            return Some(expr);
+        }
+        if !self.eat("->") {
+            return None;
+        }
+        let mut variants = Vec::new();
+        let mut default = None;
+        loop {
+            self.skip_blank();
+            if self.eat("}") {
+                break;
+            }
+            if self.eat("*") {
+                default = Some(variants.len());
+            }
+            if !self.eat("[") {
+                return None;
+            }
+            let start = self.pos;
+            while self.peek().is_some_and(|c| c != ']') {
+                self.pos += 1;
+            }
+            let key: String = self.chars[start..self.pos].iter().collect();
+            self.pos += 1;
+            self.skip_inline_blank();
+            let value = self.pattern(true)?;
+            variants.push(Variant {
+                key: key.trim().to_string(),
+                value: trim(value),
+            });
+        }
+        Some(Expr::Select {
+            selector: Box::new(expr),
+            default: default.or_else(|| variants.len().checked_sub(1))?,
+            variants,
+        })
+    }
+
+    fn skip_inline_blank(&mut self) {
+        while self.peek().is_some_and(|c| c == ' ' || c == '\t') {
+            self.pos += 1;
+        }
+    }
+}
+
+/// Drops the blank space around a variant's value.
+fn trim(mut pattern: Pattern) -> Pattern {
+    if let Some(Element::Text(text)) = pattern.first_mut() {
+        *text = text.trim_start().to_string();
+    }
+    if let Some(Element::Text(text)) = pattern.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+    pattern.retain(|element| !matches!(element, Element::Text(text) if text.is_empty()));
+    pattern
+}
+
+/// Looks up messages and terms across bundles, most specific first.
+pub(super) trait Lookup {
+    fn message(&self, id: &str) -> Option<&Pattern>;
+    fn term(&self, id: &str) -> Option<&Pattern>;
+    fn locale(&self) -> &str;
+}
+
+/// Placeables nest through references; deeper ones are cut off as cycles.
+const MAX_DEPTH: usize = 8;
+
+pub(super) fn format(
+    lookup: &impl Lookup,
+    pattern: &Pattern,
+    args: &HashMap<String, Value>,
+) -> String {
+    let mut out = String::new();
+    write_pattern(lookup, pattern, args, 0, &mut out);
+    out
+}
+
+fn write_pattern(
+    lookup: &impl Lookup,
+    pattern: &Pattern,
+    args: &HashMap<String, Value>,
+    depth: usize,
+    out: &mut String,
+) {
+    for element in pattern {
+        match element {
+            Element::Text(text) => out.push_str(text),
+            Element::Placeable(expr) => write_expr(lookup, expr, args, depth, out),
+        }
+    }
+}
+
+fn write_expr(
+    lookup: &impl Lookup,
+    expr: &Expr,
+    args: &HashMap<String, Value>,
+    depth: usize,
+    out: &mut String,
+) {
+    if depth >= MAX_DEPTH {
+        out.push_str("{???}");
+        return;
+    }
+    match expr {
+        Expr::Literal(text) => out.push_str(text),
+        Expr::Variable(name) => match args.get(name) {
+            Some(Value::Number(number)) => out.push_str(&format::number(
+                lookup.locale(),
+                number.as_f64().unwrap_or_default(),
+            )),
+            Some(Value::String(text)) => out.push_str(text),
+            Some(Value::Null) | None => out.push_str(&format!("{{${name}}}")),
+            Some(other) => out.push_str(&other.to_string()),
+        },
+        Expr::Term(id) => match lookup.term(id) {
+            Some(pattern) => write_pattern(lookup, pattern, args, depth + 1, out),
+            None => out.push_str(&format!("{{-{id}}}")),
+        },
+        Expr::Message(id) => match lookup.message(id) {
+            Some(pattern) => write_pattern(lookup, pattern, args, depth + 1, out),
+            None => out.push_str(&format!("{{{id}}}")),
+        },
+        Expr::Select {
+            selector,
+            variants,
+            default,
+        } => {
+            let variant = select(lookup, selector, variants, args).unwrap_or(*default);
+            write_pattern(lookup, &variants[variant].value, args, depth + 1, out);
+        }
+    }
+}
+
+fn select(
+    lookup: &impl Lookup,
+    selector: &Expr,
+    variants: &[Variant],
+    args: &HashMap<String, Value>,
+) -> Option<usize> {
+    let value = match selector {
+        Expr::Variable(name) => args.get(name)?.clone(),
+        Expr::Literal(text) => Value::String(text.clone()),
+        _ => return None,
+    };
+    let find = |key: &str| variants.iter().position(|variant| variant.key == key);
+    match value {
+        Value::Number(number) => {
+            let number = number.as_f64()?;
+            variants
+                .iter()
+                .position(|variant| variant.key.parse::<f64>().ok() == Some(number))
+                .or_else(|| {
+                    find(match format::plural(lookup.locale(), number) {
+                        Plural::One => "one",
+                        Plural::Other => "other",
+                    })
+                })
+        }
+        Value::String(text) => find(&text),
+        _ => None,
+    }
+}
@@ -0,0 +1,292 @@
+//! Translated strings and locale-aware formatting for backend output.
+//!
+//! Bundles are Fluent (`.ftl`) files. English, French and Japanese ship with
+//! the app; studios can add locales or override single messages with files
+//! in `locales/` under the app config directory, such as `locales/fr.ftl`.
+//! Lookups fall back from the region to the language to English, so a
+//! missing message never leaves a gap in an export.
+//!
+//! The locale comes from `locale.json`, or the system's when none is set.
+//! Like telemetry, the settings are read without an app handle so headless
+//! exports are localized too.
+
+mod format;
+mod ftl;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+
+use crate::error::{Error, Result};
+use crate::paths;
+use ftl::{Lookup, Pattern, Resource};
+
+const SETTINGS_FILE: &str = "locale.json";
+const BUNDLE_DIR: &str = "locales";
+pub const CHANGED_EVENT: &str = "locale:changed";
+const FALLBACK: &str = "en";
+
+const BUILT_IN: &[(&str, &str)] = &[
+    ("en", include_str!("../../locales/en.ftl")),
+    ("fr", include_str!("../../locales/fr.ftl")),
+    ("ja", include_str!("../../locales/ja.ftl")),
+];
+
+static CURRENT: LazyLock<RwLock<Arc<Translator>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(Translator::load(&resolve(&load_settings())))));
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LocaleSettings {
+    /// BCP 47 tag such as `fr-FR`; `None` follows the system.
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// The locale in use.
+    pub locale: String,
+    /// The configured locale, `None` when following the system.
+    pub configured: Option<String>,
+    pub available: Vec<String>,
+}
+
+struct Translator {
+    locale: String,
+    /// Most specific first: studio bundles before built-in ones, regions
+    /// before languages, English last.
+    resources: Vec<Resource>,
+}
+
+impl Lookup for Translator {
+    fn message(&self, id: &str) -> Option<&Pattern> {
+        self.resources
+            .iter()
+            .find_map(|resource| resource.messages.get(id))
+    }
+
+    fn term(&self, id: &str) -> Option<&Pattern> {
+        self.resources
+            .iter()
+            .find_map(|resource| resource.terms.get(id))
+    }
+
+    fn locale(&self) -> &str {
+        &self.locale
+    }
+}
+
+impl Translator {
+    fn load(locale: &str) -> Self {
+        let mut candidates = vec![locale.to_string()];
+        let language = locale.split('-').next().unwrap_or(locale);
+        for fallback in [language, FALLBACK] {
+            if !candidates.iter().any(|candidate| candidate == fallback) {
+                candidates.push(fallback.to_string());
+            }
+        }
+        let studio = bundle_dir();
+        let mut resources = Vec::new();
+        for candidate in &candidates {
+            if let Some(dir) = &studio {
+                let path = dir.join(format!("{candidate}.ftl"));
+                match fs::read_to_string(&path) {
+                    Ok(source) => resources.push(Resource::parse(&source, &path.to_string_lossy())),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => log::warn!("Failed to read {}: {err}", path.display()),
+                }
+            }
+            if let Some((name, source)) = BUILT_IN.iter().find(|(name, _)| name == candidate) {
+                resources.push(Resource::parse(source, &format!("{name}.ftl")));
+            }
+        }
+        Self {
+            locale: locale.to_string(),
+            resources,
+        }
+    }
+
+    fn translate(&self, id: &str, args: &HashMap<String, Value>) -> String {
+        match self.message(id) {
+            Some(pattern) => ftl::format(self, pattern, args),
+            None => {
+                log::warn!("No translation for {id}");
+                id.to_string()
+            }
+        }
+    }
+}
+
+fn bundle_dir() -> Option<PathBuf> {
+    paths::standalone_config_dir().map(|dir| dir.join(BUNDLE_DIR))
+}
+
+fn load_settings() -> LocaleSettings {
+    paths::load_standalone_json(SETTINGS_FILE).unwrap_or_else(|err| {
+        log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}");
+        LocaleSettings::default()
+    })
+}
+
+fn save_settings(settings: &LocaleSettings) -> Result<()> {
+    paths::save_standalone_json(SETTINGS_FILE, settings)
+}
+
+/// Normalizes `fr_FR.UTF-8` and `fr-fr` to `fr-FR`.
+fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.split(['.', '@']).next()?.replace('_', "-");
+    let mut parts = tag.split('-').filter(|part| !part.is_empty());
+    let language = parts.next()?.to_ascii_lowercase();
+    if language == "c" || language == "posix" || !language.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return None;
+    }
+    let mut out = language;
+    for part in parts {
+        out.push('-');
+        if part.len() == 2 {
+            out.push_str(&part.to_ascii_uppercase());
+        } else {
+            out.push_str(part);
+        }
+    }
+    Some(out)
+}
+
+fn system_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().as_deref().and_then(normalize))
+        .unwrap_or_else(|| FALLBACK.to_string())
+}
+
+fn resolve(settings: &LocaleSettings) -> String {
+    settings
+        .locale
+        .as_deref()
+        .and_then(normalize)
+        .unwrap_or_else(system_locale)
+}
+
+fn current() -> Arc<Translator> {
+    CURRENT
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+fn available() -> Vec<String> {
+    let mut locales: Vec<String> = BUILT_IN.iter().map(|(name, _)| name.to_string()).collect();
+    if let Some(entries) = bundle_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "ftl")
+                && let Some(locale) = path
+                    .file_stem()
+                    .and_then(|stem| normalize(&stem.to_string_lossy()))
+                && !locales.contains(&locale)
+            {
+                locales.push(locale);
+            }
+        }
+    }
+    locales.sort();
+    locales
+}
+
+fn info() -> LocaleInfo {
+    LocaleInfo {
+        locale: current().locale.clone(),
+        configured: load_settings().locale,
+        available: available(),
+    }
+}
+
+/// The locale exports are written in.
+pub(crate) fn locale() -> String {
+    current().locale.clone()
+}
+
+/// Translates a message with named arguments; numbers are formatted for the
+/// locale.
+pub(crate) fn tr(id: &str, args: &[(&str, Value)]) -> String {
+    let args = args
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect();
+    current().translate(id, &args)
+}
+
+pub(crate) fn datetime(at: &DateTime<Local>) -> String {
+    format::datetime(&locale(), at)
+}
+
+/// Reformats an RFC 3339 timestamp from the frontend for the locale, or
+/// returns it unchanged if it is not one.
+pub(crate) fn timestamp(text: &str) -> String {
+    match DateTime::parse_from_rfc3339(text.trim()) {
+        Ok(at) => datetime(&at.with_timezone(&Local)),
+        Err(_) => text.to_string(),
+    }
+}
+
+#[tauri::command]
+pub fn get_locale() -> LocaleInfo {
+    info()
+}
+
+/// Switches the locale, or back to the system's with `None`, and reloads
+/// the bundles so edited studio files take effect.
+#[tauri::command]
+pub fn set_locale(app: AppHandle, locale: Option<String>) -> Result<LocaleInfo> {
+    let configured = match locale.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(tag) => Some(
+            normalize(tag).ok_or_else(|| Error::InvalidInput(format!("Invalid locale {tag:?}")))?,
+        ),
+    };
+    let settings = LocaleSettings { locale: configured };
+    save_settings(&settings)?;
+    let translator = Arc::new(Translator::load(&resolve(&settings)));
+    *CURRENT
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = translator;
+    crate::app_menu::rebuild(&app);
+
+    let info = info();
+    if let Err(err) = app.emit(CHANGED_EVENT, &info) {
+        log::warn!("Failed to emit locale change: {err}");
+    }
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn translate(key: String, args: Option<HashMap<String, Value>>) -> String {
+    current().translate(&key, &args.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn format_number(value: f64) -> String {
+    format::number(&locale(), value)
+}
+
+/// Formats milliseconds since the epoch in local time, with the time of day
+/// unless `date_only` is set.
+#[tauri::command]
+pub fn format_date(millis: i64, date_only: Option<bool>) -> Result<String> {
+    let at = DateTime::from_timestamp_millis(millis)
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid timestamp {millis}")))?
+        .with_timezone(&Local);
+    Ok(if date_only.unwrap_or(false) {
+        format::date(&locale(), &at)
+    } else {
+        datetime(&at)
+    })
+}
@@ -0,0 +1,54 @@
+//! Session idle time from the desktop over D-Bus.
+//!
+//! GNOME reports it through Mutter's idle monitor, KDE and most other
+//! desktops through the freedesktop screensaver interface.
+
+use std::time::Duration;
+
+use tokio::sync::OnceCell;
+use zbus::Connection;
+
+static SESSION: OnceCell<Option<Connection>> = OnceCell::const_new();
+
+async fn session() -> Option<&'static Connection> {
+    SESSION
+        .get_or_init(|| async {
+            Connection::session()
+                .await
+                .inspect_err(|err| log::info!("No session bus for idle time: {err}"))
+                .ok()
+        })
+        .await
+        .as_ref()
+}
+
+pub(super) async fn idle_time() -> Option<Duration> {
+    let connection = session().await?;
+    let mutter = connection
+        .call_method(
+            Some("org.gnome.Mutter.IdleMonitor"),
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            Some("org.gnome.Mutter.IdleMonitor"),
+            "GetIdletime",
+            &(),
+        )
+        .await;
+    if let Ok(reply) = mutter
+        && let Ok(millis) = reply.body().deserialize::<u64>()
+    {
+        return Some(Duration::from_millis(millis));
+    }
+    let reply = connection
+        .call_method(
+            Some("org.freedesktop.ScreenSaver"),
+            "/org/freedesktop/ScreenSaver",
+            Some("org.freedesktop.ScreenSaver"),
+            "GetSessionIdleTime",
+            &(),
+        )
+        .await
+        .ok()?;
+    // Seconds, as the specification has it
+    let seconds: u32 = reply.body().deserialize().ok()?;
+    Some(Duration::from_secs(seconds.into()))
+}
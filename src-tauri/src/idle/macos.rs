@@ -0,0 +1,19 @@
+//! Input idle time from Quartz event sources.
+
+use std::time::Duration;
+
+/// `kCGEventSourceStateCombinedSessionState`
+const COMBINED_SESSION_STATE: i32 = 0;
+/// `kCGAnyInputEventType`
+const ANY_INPUT_EVENT: u32 = !0;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+}
+
+pub(super) fn idle_time() -> Option<Duration> {
+    let seconds =
+        unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT) };
+    Duration::try_from_secs_f64(seconds).ok()
+}
@@ -0,0 +1,229 @@
+//! System-wide input idle time.
+//!
+//! Webview focus events miss a reviewer who walked away with the editor
+//! open, so idle time is read from the OS: Quartz on macOS, the last input
+//! tick on Windows and the desktop's idle monitor over D-Bus on Linux. It is
+//! sampled every few seconds, and each threshold in `idle.json` is emitted
+//! once per idle period as `idle:threshold`, for the frontend to save drafts,
+//! release its note locks and pause sync. `idle:active` follows the first
+//! input afterwards. `get_idle_seconds` returns the current reading, or
+//! `None` where the platform cannot tell.
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::paths;
+
+const SETTINGS_FILE: &str = "idle.json";
+pub const THRESHOLD_EVENT: &str = "idle:threshold";
+pub const ACTIVE_EVENT: &str = "idle:active";
+const POLL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdleThreshold {
+    SaveDrafts,
+    ReleaseLocks,
+    PauseSync,
+}
+
+/// Seconds without input before each threshold; `None` disables it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IdleSettings {
+    pub save_drafts_after_secs: Option<u64>,
+    /// Matches how long presence keeps an untouched lock.
+    pub release_locks_after_secs: Option<u64>,
+    pub pause_sync_after_secs: Option<u64>,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self {
+            save_drafts_after_secs: Some(60),
+            release_locks_after_secs: Some(5 * 60),
+            pause_sync_after_secs: Some(15 * 60),
+        }
+    }
+}
+
+impl IdleSettings {
+    fn thresholds(&self) -> [(IdleThreshold, Option<u64>); 3] {
+        [
+            (IdleThreshold::SaveDrafts, self.save_drafts_after_secs),
+            (IdleThreshold::ReleaseLocks, self.release_locks_after_secs),
+            (IdleThreshold::PauseSync, self.pause_sync_after_secs),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThresholdReached {
+    threshold: IdleThreshold,
+    idle_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Active {
+    /// How long input had been idle, as of the last reading.
+    idle_seconds: u64,
+}
+
+#[derive(Default)]
+pub struct IdleMonitor {
+    settings: Mutex<IdleSettings>,
+    /// Thresholds emitted in the current idle period.
+    reached: Mutex<Vec<IdleThreshold>>,
+}
+
+#[cfg(target_os = "linux")]
+async fn idle_time() -> Option<Duration> {
+    linux::idle_time().await
+}
+
+#[cfg(target_os = "macos")]
+async fn idle_time() -> Option<Duration> {
+    macos::idle_time()
+}
+
+#[cfg(target_os = "windows")]
+async fn idle_time() -> Option<Duration> {
+    windows::idle_time()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+async fn idle_time() -> Option<Duration> {
+    None
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(err) = app.emit(event, payload) {
+        log::warn!("Failed to emit {event}: {err}");
+    }
+}
+
+/// Emits the thresholds `idle` has newly passed, or the end of the idle
+/// period once it drops below `last`.
+fn check(app: &AppHandle, monitor: &IdleMonitor, idle: Duration, last: Duration) {
+    let mut reached = monitor
+        .reached
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if idle < last && !reached.is_empty() {
+        reached.clear();
+        emit(
+            app,
+            ACTIVE_EVENT,
+            Active {
+                idle_seconds: last.as_secs(),
+            },
+        );
+    }
+    let thresholds = monitor
+        .settings
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .thresholds();
+    for (threshold, after) in thresholds {
+        let Some(after) = after else {
+            continue;
+        };
+        if idle.as_secs() >= after && !reached.contains(&threshold) {
+            reached.push(threshold);
+            log::info!("Idle for {}s, reached {threshold:?}", idle.as_secs());
+            emit(
+                app,
+                THRESHOLD_EVENT,
+                ThresholdReached {
+                    threshold,
+                    idle_seconds: idle.as_secs(),
+                },
+            );
+        }
+    }
+}
+
+async fn watch(app: AppHandle) {
+    let mut interval = tokio::time::interval(POLL);
+    let mut last = Duration::ZERO;
+    let mut available = true;
+    loop {
+        interval.tick().await;
+        let Some(idle) = idle_time().await else {
+            if std::mem::take(&mut available) {
+                log::info!("System idle time is unavailable, no idle events will be sent");
+            }
+            continue;
+        };
+        available = true;
+        check(&app, &app.state::<IdleMonitor>(), idle, last);
+        last = idle;
+    }
+}
+
+fn load_settings(app: &AppHandle) -> Result<IdleSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+/// Starts sampling idle time.
+pub fn init(app: &AppHandle) {
+    let monitor = IdleMonitor::default();
+    match load_settings(app) {
+        Ok(settings) => {
+            *monitor
+                .settings
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = settings
+        }
+        Err(err) => log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}"),
+    }
+    app.manage(monitor);
+    tauri::async_runtime::spawn(watch(app.clone()));
+}
+
+/// Seconds since the last keyboard or mouse input anywhere on the system.
+#[tauri::command]
+pub async fn get_idle_seconds() -> Option<u64> {
+    idle_time().await.map(|idle| idle.as_secs())
+}
+
+#[tauri::command]
+pub fn get_idle_settings(app: AppHandle) -> Result<IdleSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_idle_settings(
+    app: AppHandle,
+    monitor: State<'_, IdleMonitor>,
+    settings: IdleSettings,
+) -> Result<()> {
+    if settings
+        .thresholds()
+        .iter()
+        .any(|(_, after)| *after == Some(0))
+    {
+        return Err(Error::InvalidInput(
+            "Idle thresholds must be at least a second".into(),
+        ));
+    }
+    paths::save_json(&app, SETTINGS_FILE, &settings)?;
+    *monitor
+        .settings
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = settings;
+    Ok(())
+}
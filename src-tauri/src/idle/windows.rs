@@ -0,0 +1,22 @@
+//! Input idle time from the last input tick.
+
+use std::time::Duration;
+
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+pub(super) fn idle_time() -> Option<Duration> {
+    let mut info = LASTINPUTINFO {
+        cbSize: size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    unsafe {
+        if !GetLastInputInfo(&mut info).as_bool() {
+            return None;
+        }
+        // Both counts wrap after 49.7 days of uptime
+        Some(Duration::from_millis(
+            GetTickCount().wrapping_sub(info.dwTime).into(),
+        ))
+    }
+}
@@ -0,0 +1,489 @@
+//! Ingesting media dropped onto a window.
+//!
+//! Dropped files and folders are expanded, numbered frames are collapsed
+//! into sequences, and each resulting item is probed for resolution, frame
+//! rate and duration, with an optional thumbnail. The frontend gets one
+//! `ingest:completed` event per drop with the items to attach or create
+//! versions from, so a 500-frame render arrives as a single item. The same
+//! pipeline runs for paths picked in a dialog through `ingest_paths`.
+//! Thumbnails from earlier drops are deleted once a day old.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, DragDropEvent, Emitter, Manager, WindowEvent};
+
+use crate::error::{Error, Result};
+use crate::media::frames;
+use crate::media::metadata::{self, ImageFormat};
+use crate::media::sequence::{self, FileSequence};
+use crate::paths;
+
+const SETTINGS_FILE: &str = "ingest.json";
+pub const STARTED_EVENT: &str = "ingest:started";
+pub const COMPLETED_EVENT: &str = "ingest:completed";
+pub const FAILED_EVENT: &str = "ingest:failed";
+const THUMBNAIL_DIR: &str = "ingest";
+const THUMBNAIL_WIDTH: u32 = 320;
+/// Thumbnails only matter while their drop is being handled.
+const THUMBNAIL_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// Probes and thumbnails run this many at a time.
+const CONCURRENCY: usize = 4;
+const MAX_DEPTH: usize = 8;
+
+const MOVIE_EXTENSIONS: &[&str] = &["mov", "mp4", "m4v", "mxf", "avi", "mkv", "webm"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IngestSettings {
+    /// Whether drops onto a window are ingested at all.
+    pub enabled: bool,
+    pub generate_thumbnails: bool,
+    /// Files taken from dropped folders before giving up on the rest.
+    pub max_files: usize,
+}
+
+impl Default for IngestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            generate_thumbnails: true,
+            max_files: 20_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ItemKind {
+    Sequence,
+    Movie,
+    Image,
+    Other,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaProbe {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub duration_seconds: Option<f64>,
+    pub codec: Option<String>,
+    pub colorspace: Option<String>,
+    pub timecode: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestItem {
+    pub kind: ItemKind,
+    pub name: String,
+    /// The file itself, or the first frame of a sequence.
+    pub path: PathBuf,
+    pub sequence: Option<FileSequence>,
+    pub size_bytes: u64,
+    pub probe: Option<MediaProbe>,
+    pub thumbnail: Option<PathBuf>,
+    /// Why probing or the thumbnail failed; the item is still usable.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestBatch {
+    pub batch_id: String,
+    /// Window the files were dropped on, when they were dropped.
+    pub window: Option<String>,
+    pub items: Vec<IngestItem>,
+    /// Whether folders held more than `maxFiles` files.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Started<'a> {
+    batch_id: &'a str,
+    window: Option<&'a str>,
+    paths: &'a [PathBuf],
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Failed<'a> {
+    batch_id: &'a str,
+    window: Option<&'a str>,
+    error: String,
+}
+
+fn load_settings(app: &AppHandle) -> Result<IngestSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// Lists the files under `paths`, descending into folders. Returns whether
+/// the list stopped at `max_files`.
+fn expand(paths: &[PathBuf], max_files: usize) -> (Vec<PathBuf>, bool) {
+    let mut files = Vec::new();
+    let mut pending: Vec<(PathBuf, usize)> = paths.iter().map(|path| (path.clone(), 0)).collect();
+    pending.reverse();
+    while let Some((path, depth)) = pending.pop() {
+        if files.len() >= max_files {
+            return (files, true);
+        }
+        if path.is_file() {
+            files.push(path);
+            continue;
+        }
+        if !path.is_dir() || depth >= MAX_DEPTH {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&path) else {
+            log::warn!("Cannot read dropped folder {}", path.display());
+            continue;
+        };
+        let mut children: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| !is_hidden(path))
+            .collect();
+        children.sort();
+        pending.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+    }
+    (files, false)
+}
+
+/// Sorts expanded files into items, collapsing sequences. Stats every file,
+/// so it runs off the async runtime.
+fn classify(files: Vec<PathBuf>) -> Vec<IngestItem> {
+    let sizes: HashMap<PathBuf, u64> = files
+        .iter()
+        .map(|path| {
+            let size = fs::metadata(path)
+                .map(|meta| meta.len())
+                .unwrap_or_default();
+            (path.clone(), size)
+        })
+        .collect();
+    let (sequences, singles) = sequence::collapse(files);
+    let mut items: Vec<IngestItem> = sequences
+        .into_iter()
+        .map(|sequence| {
+            // Only frames that were found count; gaps are not stat'd again
+            let size_bytes = (sequence.first_frame..=sequence.last_frame)
+                .filter_map(|frame| sizes.get(&sequence.frame_path(frame)))
+                .take(sequence.frame_count)
+                .sum();
+            IngestItem {
+                kind: ItemKind::Sequence,
+                name: sequence.name.clone(),
+                path: sequence.first_path.clone(),
+                sequence: Some(sequence),
+                size_bytes,
+                probe: None,
+                thumbnail: None,
+                error: None,
+            }
+        })
+        .collect();
+    items.extend(singles.into_iter().map(|path| {
+        let kind = if MOVIE_EXTENSIONS.contains(&extension(&path).as_str()) {
+            ItemKind::Movie
+        } else if sequence::is_still(&path) {
+            ItemKind::Image
+        } else {
+            ItemKind::Other
+        };
+        IngestItem {
+            kind,
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            size_bytes: sizes.get(&path).copied().unwrap_or_default(),
+            path,
+            sequence: None,
+            probe: None,
+            thumbnail: None,
+            error: None,
+        }
+    }));
+    items
+}
+
+async fn ffprobe(app: &AppHandle, path: &Path) -> Result<MediaProbe> {
+    let output = frames::run(
+        app,
+        "ffprobe",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-select_streams".into(),
+            "v:0".into(),
+            "-show_entries".into(),
+            "stream=codec_name,width,height,r_frame_rate,color_space:format=duration:stream_tags=timecode".into(),
+            "-of".into(),
+            "json".into(),
+            path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let stream = &json["streams"][0];
+    let rate = stream["r_frame_rate"].as_str().and_then(|rate| {
+        let (num, den) = rate.split_once('/')?;
+        let rate = num.parse::<f64>().ok()? / den.parse::<f64>().ok()?;
+        (rate.is_finite() && rate > 0.0).then_some(rate)
+    });
+    let text = |value: &Value| value.as_str().map(str::to_string);
+    Ok(MediaProbe {
+        width: stream["width"].as_u64().map(|width| width as u32),
+        height: stream["height"].as_u64().map(|height| height as u32),
+        frame_rate: rate,
+        duration_seconds: json["format"]["duration"]
+            .as_str()
+            .and_then(|duration| duration.parse().ok()),
+        codec: text(&stream["codec_name"]),
+        colorspace: text(&stream["color_space"]),
+        timecode: text(&stream["tags"]["timecode"]),
+    })
+}
+
+async fn probe(app: &AppHandle, item: &IngestItem) -> Result<Option<MediaProbe>> {
+    let path = item.path.clone();
+    match item.kind {
+        ItemKind::Other => Ok(None),
+        ItemKind::Movie => ffprobe(app, &path).await.map(Some),
+        ItemKind::Sequence | ItemKind::Image => match extension(&path).as_str() {
+            "exr" | "dpx" => {
+                let meta =
+                    tauri::async_runtime::spawn_blocking(move || metadata::read(&path)).await??;
+                let codec = match meta.format {
+                    ImageFormat::Exr => "exr",
+                    ImageFormat::Dpx => "dpx",
+                };
+                let duration_seconds = item
+                    .sequence
+                    .as_ref()
+                    .zip(meta.frame_rate)
+                    .map(|(sequence, rate)| sequence.frame_count as f64 / rate);
+                Ok(Some(MediaProbe {
+                    width: Some(meta.width),
+                    height: Some(meta.height),
+                    frame_rate: meta.frame_rate,
+                    duration_seconds,
+                    codec: Some(codec.into()),
+                    colorspace: meta.colorspace,
+                    timecode: meta.timecode,
+                }))
+            }
+            "png" | "jpg" | "jpeg" | "webp" => {
+                let (width, height) =
+                    tauri::async_runtime::spawn_blocking(move || image::image_dimensions(&path))
+                        .await??;
+                Ok(Some(MediaProbe {
+                    width: Some(width),
+                    height: Some(height),
+                    codec: Some(extension(&item.path)),
+                    ..Default::default()
+                }))
+            }
+            _ => ffprobe(app, &path).await.map(Some),
+        },
+    }
+}
+
+/// Deletes thumbnails older than [`THUMBNAIL_MAX_AGE`].
+fn prune_thumbnails(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|modified| {
+                now.duration_since(modified)
+                    .is_ok_and(|age| age > THUMBNAIL_MAX_AGE)
+            });
+        if expired && let Err(err) = fs::remove_file(entry.path()) {
+            log::warn!("Failed to remove old ingest thumbnail: {err}");
+        }
+    }
+}
+
+async fn thumbnail(app: &AppHandle, item: &IngestItem) -> Result<Option<PathBuf>> {
+    if item.kind == ItemKind::Other {
+        return Ok(None);
+    }
+    let dir = paths::cache_dir(app)?.join(THUMBNAIL_DIR);
+    tokio::fs::create_dir_all(&dir).await?;
+    let out = dir.join(format!("{}.png", uuid::Uuid::new_v4().simple()));
+    frames::run(
+        app,
+        "ffmpeg",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-y".into(),
+            "-i".into(),
+            item.path.to_string_lossy().into_owned(),
+            "-frames:v".into(),
+            "1".into(),
+            "-vf".into(),
+            format!("scale={THUMBNAIL_WIDTH}:-2"),
+            out.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    Ok(out.is_file().then_some(out))
+}
+
+async fn enrich(app: &AppHandle, mut item: IngestItem, thumbnails: bool) -> IngestItem {
+    let mut errors = Vec::new();
+    match probe(app, &item).await {
+        Ok(probe) => item.probe = probe,
+        Err(err) => errors.push(format!("Probe failed: {err}")),
+    }
+    if thumbnails {
+        match thumbnail(app, &item).await {
+            Ok(path) => item.thumbnail = path,
+            Err(err) => errors.push(format!("Thumbnail failed: {err}")),
+        }
+    }
+    if !errors.is_empty() {
+        log::debug!("Ingesting {}: {}", item.path.display(), errors.join("; "));
+        item.error = Some(errors.join("; "));
+    }
+    item
+}
+
+async fn ingest(
+    app: &AppHandle,
+    settings: &IngestSettings,
+    batch_id: String,
+    window: Option<String>,
+    paths: Vec<PathBuf>,
+) -> Result<IngestBatch> {
+    let max_files = settings.max_files;
+    let thumbnail_dir = paths::cache_dir(app)?.join(THUMBNAIL_DIR);
+    let (items, truncated) = tauri::async_runtime::spawn_blocking(move || {
+        prune_thumbnails(&thumbnail_dir);
+        let (files, truncated) = expand(&paths, max_files);
+        (classify(files), truncated)
+    })
+    .await?;
+    let items = futures_util::stream::iter(items)
+        .map(|item| enrich(app, item, settings.generate_thumbnails))
+        .buffered(CONCURRENCY)
+        .collect()
+        .await;
+    Ok(IngestBatch {
+        batch_id,
+        window,
+        items,
+        truncated,
+    })
+}
+
+/// Ingests files dropped onto any window.
+pub fn on_window_event(window: &tauri::Window, event: &WindowEvent) {
+    let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else {
+        return;
+    };
+    if paths.is_empty() {
+        return;
+    }
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    let paths = paths.clone();
+    tauri::async_runtime::spawn(async move {
+        let settings = load_settings(&app).unwrap_or_else(|err| {
+            log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}");
+            IngestSettings::default()
+        });
+        if !settings.enabled {
+            return;
+        }
+        let batch_id = uuid::Uuid::new_v4().to_string();
+        let started = Started {
+            batch_id: &batch_id,
+            window: Some(&label),
+            paths: &paths,
+        };
+        if let Err(err) = app.emit(STARTED_EVENT, started) {
+            log::warn!("Failed to emit ingest start: {err}");
+        }
+        let result = match ingest(
+            &app,
+            &settings,
+            batch_id.clone(),
+            Some(label.clone()),
+            paths,
+        )
+        .await
+        {
+            Ok(batch) => app.emit(COMPLETED_EVENT, batch),
+            Err(err) => {
+                log::warn!("Ingesting dropped files failed: {err}");
+                let failed = Failed {
+                    batch_id: &batch_id,
+                    window: Some(&label),
+                    error: err.to_string(),
+                };
+                app.emit(FAILED_EVENT, failed)
+            }
+        };
+        if let Err(err) = result {
+            log::warn!("Failed to emit ingest result: {err}");
+        }
+    });
+}
+
+/// Runs the drop pipeline on paths picked some other way, e.g. in a dialog.
+#[tauri::command]
+pub async fn ingest_paths(app: AppHandle, paths: Vec<PathBuf>) -> Result<IngestBatch> {
+    if paths.is_empty() {
+        return Err(Error::InvalidInput("No paths to ingest".into()));
+    }
+    let settings = load_settings(&app)?;
+    ingest(
+        &app,
+        &settings,
+        uuid::Uuid::new_v4().to_string(),
+        None,
+        paths,
+    )
+    .await
+}
+
+#[tauri::command]
+pub fn get_ingest_settings(app: AppHandle) -> Result<IngestSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_ingest_settings(app: AppHandle, settings: IngestSettings) -> Result<()> {
+    if settings.max_files == 0 {
+        return Err(Error::InvalidInput("maxFiles must be at least 1".into()));
+    }
+    paths::save_json(&app, SETTINGS_FILE, &settings)
+}
@@ -0,0 +1,64 @@
+//! Keeps AstraNotes to a single running instance.
+//!
+//! A second launch, e.g. from double-clicking a playlist link, passes its
+//! arguments to the running app and exits before starting anything of its
+//! own. The running app comes to the front and emits the arguments as
+//! `instance:args`. `astranotes://` links among them are already routed by
+//! the deep-link plugin and files opened by association by `file_open`, so
+//! they are left out here.
+
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Emitter, Wry};
+
+use crate::file_open;
+
+pub const ARGS_EVENT: &str = "instance:args";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchArgs {
+    pub args: Vec<String>,
+    /// Working directory of the launch, for resolving relative paths.
+    pub cwd: String,
+}
+
+/// Arguments after the executable, without deep links or opened files.
+fn forwardable(argv: impl IntoIterator<Item = String>) -> Vec<String> {
+    argv.into_iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("astranotes:") && !file_open::is_openable(arg))
+        .collect()
+}
+
+pub fn plugin() -> TauriPlugin<Wry> {
+    tauri_plugin_single_instance::init(|app, argv, cwd| {
+        crate::focus_main_window(app);
+        forward(app, argv, cwd);
+    })
+}
+
+fn forward(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    file_open::open_args(app, argv.iter().skip(1), Path::new(&cwd));
+    let args = forwardable(argv);
+    if args.is_empty() {
+        return;
+    }
+    log::info!("Second launch forwarded arguments: {args:?}");
+    if let Err(err) = app.emit(ARGS_EVENT, LaunchArgs { args, cwd }) {
+        log::warn!("Failed to forward launch arguments: {err}");
+    }
+}
+
+/// Arguments this instance was started with, in the same shape as `instance:args`.
+#[tauri::command]
+pub fn get_launch_args() -> LaunchArgs {
+    LaunchArgs {
+        args: forwardable(std::env::args()),
+        cwd: std::env::current_dir()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,464 @@
+//! Command timings and optional OTLP export.
+//!
+//! With Tauri's `tracing` feature every IPC request opens an
+//! `ipc::request::handle` span that closes once the response is sent, so a
+//! subscriber layer sees each command's name and wall time without touching
+//! the commands themselves. Failures are classified by the [`Error`] variant
+//! returned, noted when it is serialized just before the response goes out.
+//! Timings are kept in memory for `get_command_metrics`; studios can point
+//! `tracing.json` (or `OTEL_EXPORTER_OTLP_ENDPOINT`) at an OTLP/HTTP collector
+//! to receive them as spans.
+//!
+//! [`Error`]: crate::error::Error
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tauri::{AppHandle, Manager, State};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::error::{Error, Result};
+use crate::network;
+use crate::paths;
+
+const SETTINGS_FILE: &str = "tracing.json";
+const REQUEST_SPAN: &str = "ipc::request::handle";
+const RESPOND_SPAN: &str = "ipc::request::respond";
+const DEFAULT_SERVICE_NAME: &str = "astranotes";
+/// Durations kept per command for the percentile.
+const SAMPLE_WINDOW: usize = 256;
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_BUFFERED_SPANS: usize = 2048;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TracingSettings {
+    /// OTLP/HTTP collector, e.g. `http://collector:4318`. Export is off when unset.
+    pub otlp_endpoint: Option<String>,
+    /// Extra request headers, typically an API key for a hosted collector.
+    pub headers: HashMap<String, String>,
+    pub service_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandMetric {
+    pub command: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    pub error_classes: BTreeMap<&'static str, u64>,
+}
+
+#[derive(Default)]
+struct Stats {
+    calls: u64,
+    errors: u64,
+    total: Duration,
+    max: Duration,
+    recent: VecDeque<Duration>,
+    error_classes: BTreeMap<&'static str, u64>,
+}
+
+impl Stats {
+    fn metric(&self, command: &str) -> CommandMetric {
+        let mut recent: Vec<_> = self.recent.iter().copied().collect();
+        recent.sort_unstable();
+        let p95 = recent
+            .get(((recent.len().saturating_sub(1)) as f64 * 0.95).round() as usize)
+            .copied()
+            .unwrap_or_default();
+        CommandMetric {
+            command: command.to_string(),
+            calls: self.calls,
+            errors: self.errors,
+            total_ms: millis(self.total),
+            mean_ms: millis(self.total) / self.calls.max(1) as f64,
+            p95_ms: millis(p95),
+            max_ms: millis(self.max),
+            error_classes: self.error_classes.clone(),
+        }
+    }
+}
+
+/// A command that finished, waiting to be exported.
+struct Finished {
+    command: String,
+    started_at: SystemTime,
+    duration: Duration,
+    error: Option<&'static str>,
+}
+
+static METRICS: LazyLock<Mutex<HashMap<String, Stats>>> = LazyLock::new(Default::default);
+static EXPORT_BUFFER: LazyLock<Mutex<VecDeque<Finished>>> = LazyLock::new(Default::default);
+static EXPORTING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static LAST_ERROR: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Records the class of an error about to be returned to the frontend.
+pub fn note_error(class: &'static str) {
+    LAST_ERROR.with(|last| last.set(Some(class)));
+}
+
+/// Per-request state stored on the request span.
+struct Request {
+    command: String,
+    started: Instant,
+    started_at: SystemTime,
+    error: Option<&'static str>,
+}
+
+#[derive(Default)]
+struct Fields {
+    command: Option<String>,
+    message: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "cmd" => self.command = Some(value.to_string()),
+            "message" => self.message.push_str(value),
+            name => {
+                let _ = write!(self.message, " {name}={value}");
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "message" => {
+                let _ = write!(self.message, "{value:?}");
+            }
+            name => {
+                let _ = write!(self.message, " {name}={value:?}");
+            }
+        }
+    }
+}
+
+struct CommandLayer;
+
+impl<S> Layer<S> for CommandLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        if metadata.is_span() {
+            matches!(metadata.name(), REQUEST_SPAN | RESPOND_SPAN)
+        } else {
+            *metadata.level() <= Level::INFO
+        }
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        match attrs.metadata().name() {
+            REQUEST_SPAN => {
+                let mut fields = Fields::default();
+                attrs.record(&mut fields);
+                if let Some(command) = fields.command {
+                    span.extensions_mut().insert(Request {
+                        command,
+                        started: Instant::now(),
+                        started_at: SystemTime::now(),
+                        error: None,
+                    });
+                }
+            }
+            RESPOND_SPAN => {
+                // Taken unconditionally so a stale class never leaks into a later response
+                let Some(error) = LAST_ERROR.with(Cell::take) else {
+                    return;
+                };
+                for ancestor in span.scope().skip(1) {
+                    if let Some(request) = ancestor.extensions_mut().get_mut::<Request>() {
+                        request.error = Some(error);
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if let Some(request) = span.extensions_mut().remove::<Request>() {
+            record(request);
+        }
+    }
+
+    /// Tauri logs through `tracing` once the feature is on; hand those to `log`.
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        let level = match *metadata.level() {
+            Level::ERROR => log::Level::Error,
+            Level::WARN => log::Level::Warn,
+            _ => log::Level::Info,
+        };
+        log::log!(target: metadata.target(), level, "{}", fields.message.trim_start());
+    }
+}
+
+fn record(request: Request) {
+    let duration = request.started.elapsed();
+    {
+        let mut metrics = METRICS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let stats = metrics.entry(request.command.clone()).or_default();
+        stats.calls += 1;
+        stats.total += duration;
+        stats.max = stats.max.max(duration);
+        if stats.recent.len() == SAMPLE_WINDOW {
+            stats.recent.pop_front();
+        }
+        stats.recent.push_back(duration);
+        if let Some(error) = request.error {
+            stats.errors += 1;
+            *stats.error_classes.entry(error).or_default() += 1;
+        }
+    }
+
+    if EXPORTING.load(Ordering::Relaxed) {
+        let mut buffer = EXPORT_BUFFER
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if buffer.len() == MAX_BUFFERED_SPANS {
+            buffer.pop_front();
+        }
+        buffer.push_back(Finished {
+            command: request.command,
+            started_at: request.started_at,
+            duration,
+            error: request.error,
+        });
+    }
+}
+
+pub struct Tracing {
+    settings: Mutex<TracingSettings>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<TracingSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+/// The collector's trace endpoint, from settings or the standard environment variable.
+fn traces_url(settings: &TracingSettings) -> Option<String> {
+    let endpoint = settings
+        .otlp_endpoint
+        .clone()
+        .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+        .map(|endpoint| endpoint.trim().trim_end_matches('/').to_string())
+        .filter(|endpoint| !endpoint.is_empty())?;
+    if endpoint.ends_with("/v1/traces") {
+        Some(endpoint)
+    } else {
+        Some(format!("{endpoint}/v1/traces"))
+    }
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// Encodes finished commands as an OTLP/JSON trace export request.
+fn export_body(app: &AppHandle, settings: &TracingSettings, spans: &[Finished]) -> Value {
+    let spans: Vec<_> = spans
+        .iter()
+        .map(|span| {
+            let mut attributes = vec![
+                attribute("rpc.system", "tauri"),
+                attribute("rpc.method", &span.command),
+            ];
+            if let Some(error) = span.error {
+                attributes.push(attribute("error.type", error));
+            }
+            json!({
+                "traceId": uuid::Uuid::new_v4().simple().to_string(),
+                "spanId": uuid::Uuid::new_v4().simple().to_string()[..16],
+                "name": span.command,
+                // SPAN_KIND_SERVER
+                "kind": 2,
+                "startTimeUnixNano": unix_nanos(span.started_at),
+                "endTimeUnixNano": unix_nanos(span.started_at + span.duration),
+                "attributes": attributes,
+                // STATUS_CODE_ERROR or STATUS_CODE_UNSET
+                "status": { "code": if span.error.is_some() { 2 } else { 0 } },
+            })
+        })
+        .collect();
+    let service_name = settings
+        .service_name
+        .as_deref()
+        .unwrap_or(DEFAULT_SERVICE_NAME);
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", service_name),
+                    attribute("service.version", &app.package_info().version.to_string()),
+                    attribute("os.type", std::env::consts::OS),
+                ],
+            },
+            "scopeSpans": [{
+                "scope": { "name": "astranotes.ipc" },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+async fn export_loop(app: AppHandle) {
+    let mut failing = false;
+    loop {
+        tokio::time::sleep(EXPORT_INTERVAL).await;
+        let settings = app
+            .state::<Tracing>()
+            .settings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        let Some(url) = traces_url(&settings) else {
+            continue;
+        };
+        let spans: Vec<_> = EXPORT_BUFFER
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .drain(..)
+            .collect();
+        if spans.is_empty() {
+            continue;
+        }
+
+        let mut request = network::client()
+            .post(&url)
+            .json(&export_body(&app, &settings, &spans));
+        for (name, value) in &settings.headers {
+            request = request.header(name, value);
+        }
+        match request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(_) => failing = false,
+            // Logged once per outage rather than every interval
+            Err(err) if !failing => {
+                failing = true;
+                log::warn!("Failed to export {} command spans: {err}", spans.len());
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Installs the command layer and starts the exporter.
+pub fn init(app: &AppHandle) {
+    let subscriber = tracing_subscriber::registry().with(CommandLayer);
+    if let Err(err) = tracing::subscriber::set_global_default(subscriber) {
+        log::warn!("Command tracing unavailable: {err}");
+    }
+
+    let settings = load_settings(app).unwrap_or_else(|err| {
+        log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}");
+        TracingSettings::default()
+    });
+    EXPORTING.store(traces_url(&settings).is_some(), Ordering::Relaxed);
+    app.manage(Tracing {
+        settings: Mutex::new(settings),
+    });
+    tauri::async_runtime::spawn(export_loop(app.clone()));
+}
+
+/// Per-command timings since launch, slowest in total first.
+#[tauri::command]
+pub fn get_command_metrics() -> Vec<CommandMetric> {
+    let metrics = METRICS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut metrics: Vec<_> = metrics
+        .iter()
+        .map(|(command, stats)| stats.metric(command))
+        .collect();
+    metrics.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    metrics
+}
+
+#[tauri::command]
+pub fn reset_command_metrics() {
+    METRICS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clear();
+}
+
+#[tauri::command]
+pub fn get_tracing_settings(tracing: State<'_, Tracing>) -> TracingSettings {
+    tracing
+        .settings
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+#[tauri::command]
+pub fn set_tracing_settings(
+    app: AppHandle,
+    tracing: State<'_, Tracing>,
+    settings: TracingSettings,
+) -> Result<()> {
+    if let Some(url) = traces_url(&settings) {
+        reqwest::Url::parse(&url)
+            .map_err(|err| Error::InvalidInput(format!("Invalid OTLP endpoint {url}: {err}")))?;
+    }
+    paths::save_json(&app, SETTINGS_FILE, &settings)?;
+
+    let exporting = traces_url(&settings).is_some();
+    EXPORTING.store(exporting, Ordering::Relaxed);
+    if !exporting {
+        EXPORT_BUFFER
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+    *tracing
+        .settings
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = settings;
+    Ok(())
+}
@@ -0,0 +1,20 @@
+//! Raw IPC responses for large payloads.
+//!
+//! A command returning a `Value` or `String` has its result serialized to
+//! JSON and evaluated into the webview as script, which stalls the bridge for
+//! seconds on multi-megabyte playlist dumps. A [`Response`] is delivered over
+//! the IPC custom protocol as an `ArrayBuffer` instead, without the escaping
+//! and extra copies. Commands that can return large results have a raw twin
+//! (`ftrack_query_raw`, `read_cached_thumbnail`, `preview_notes_csv`); the
+//! frontend decodes JSON ones with `TextDecoder` and `JSON.parse`, and uses
+//! image or CSV bytes as they are.
+
+use serde::Serialize;
+use tauri::ipc::Response;
+
+use crate::error::Result;
+
+/// Serializes `value` to JSON bytes for a raw response.
+pub fn json<T: Serialize + ?Sized>(value: &T) -> Result<Response> {
+    Ok(Response::new(serde_json::to_vec(value)?))
+}
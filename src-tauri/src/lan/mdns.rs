@@ -0,0 +1,401 @@
+//! Just enough multicast DNS to find hosts on the local network.
+//!
+//! Hosts answer PTR queries for [`SERVICE`] with the SRV and TXT records of
+//! their instance. Browsers send one query from an ephemeral port, which
+//! responders answer by unicast, and take the address from each reply's
+//! source. There is no caching, probing or conflict resolution: instance
+//! names are random and browsing is a one-shot scan.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+use tokio::sync::oneshot;
+
+pub(super) const SERVICE: &str = "_astranotes._tcp.local";
+const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const PORT: u16 = 5353;
+const TTL: u32 = 120;
+
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Cache-flush on answers, unicast-response on questions.
+const CLASS_FLAG: u16 = 0x8000;
+const FLAGS_RESPONSE: u16 = 0x8400;
+
+/// The instance a host announces.
+pub(super) struct Service {
+    /// Single DNS label, unique per session.
+    pub(super) instance: String,
+    pub(super) port: u16,
+    pub(super) txt: Vec<(&'static str, String)>,
+}
+
+impl Service {
+    fn instance_name(&self) -> String {
+        format!("{}.{SERVICE}", self.instance)
+    }
+
+    fn response(&self, id: u16, ttl: u32, question: bool) -> Vec<u8> {
+        let instance = self.instance_name();
+        let mut out = header(id, FLAGS_RESPONSE, u16::from(question), 3);
+        if question {
+            push_name(&mut out, SERVICE);
+            out.extend(TYPE_PTR.to_be_bytes());
+            out.extend(CLASS_IN.to_be_bytes());
+        }
+
+        let mut ptr = Vec::new();
+        push_name(&mut ptr, &instance);
+        push_record(&mut out, SERVICE, TYPE_PTR, 0, ttl, &ptr);
+
+        let mut srv = Vec::new();
+        srv.extend(0u16.to_be_bytes());
+        srv.extend(0u16.to_be_bytes());
+        srv.extend(self.port.to_be_bytes());
+        push_name(&mut srv, &format!("{}.local", self.instance));
+        push_record(&mut out, &instance, TYPE_SRV, CLASS_FLAG, ttl, &srv);
+
+        let mut txt = Vec::new();
+        for (key, value) in &self.txt {
+            let mut entry = format!("{key}={value}");
+            let mut end = entry.len().min(255);
+            while !entry.is_char_boundary(end) {
+                end -= 1;
+            }
+            entry.truncate(end);
+            txt.push(entry.len() as u8);
+            txt.extend_from_slice(entry.as_bytes());
+        }
+        push_record(&mut out, &instance, TYPE_TXT, CLASS_FLAG, ttl, &txt);
+        out
+    }
+}
+
+/// A host found while browsing.
+#[derive(Debug, Clone)]
+pub(super) struct Found {
+    pub(super) instance: String,
+    pub(super) address: Ipv4Addr,
+    pub(super) port: u16,
+    pub(super) txt: HashMap<String, String>,
+}
+
+fn header(id: u16, flags: u16, questions: u16, answers: u16) -> Vec<u8> {
+    let mut out = Vec::with_capacity(512);
+    for field in [id, flags, questions, answers, 0, 0] {
+        out.extend(field.to_be_bytes());
+    }
+    out
+}
+
+fn push_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        out.push(label.len() as u8);
+        out.extend_from_slice(label);
+    }
+    out.push(0);
+}
+
+fn push_record(out: &mut Vec<u8>, name: &str, kind: u16, flag: u16, ttl: u32, data: &[u8]) {
+    push_name(out, name);
+    out.extend(kind.to_be_bytes());
+    out.extend((CLASS_IN | flag).to_be_bytes());
+    out.extend(ttl.to_be_bytes());
+    out.extend((data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+enum Data {
+    Ptr(String),
+    Srv { port: u16 },
+    Txt(HashMap<String, String>),
+    Other,
+}
+
+struct Record {
+    name: String,
+    ttl: u32,
+    data: Data,
+}
+
+struct Packet {
+    id: u16,
+    response: bool,
+    questions: Vec<(String, u16)>,
+    records: Vec<Record>,
+}
+
+struct Reader<'a> {
+    packet: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.packet.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a name, following compression pointers.
+    fn name(&mut self) -> Option<String> {
+        let mut labels = Vec::new();
+        let mut pos = self.pos;
+        let mut jumps = 0;
+        loop {
+            let len = usize::from(*self.packet.get(pos)?);
+            if len & 0xc0 == 0xc0 {
+                let target = (len & 0x3f) << 8 | usize::from(*self.packet.get(pos + 1)?);
+                if jumps == 0 {
+                    self.pos = pos + 2;
+                }
+                jumps += 1;
+                if jumps > 16 {
+                    return None;
+                }
+                pos = target;
+            } else if len == 0 {
+                if jumps == 0 {
+                    self.pos = pos + 1;
+                }
+                return Some(labels.join("."));
+            } else {
+                let label = self.packet.get(pos + 1..pos + 1 + len)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + len;
+            }
+        }
+    }
+
+    fn record(&mut self) -> Option<Record> {
+        let name = self.name()?;
+        let kind = self.u16()?;
+        let _class = self.u16()?;
+        let ttl = self.u32()?;
+        let len = usize::from(self.u16()?);
+        let end = self.pos + len;
+        if end > self.packet.len() {
+            return None;
+        }
+        let data = match kind {
+            TYPE_PTR => Data::Ptr(self.name()?),
+            TYPE_SRV => {
+                let _priority = self.u16()?;
+                let _weight = self.u16()?;
+                Data::Srv { port: self.u16()? }
+            }
+            TYPE_TXT => {
+                let mut entries = HashMap::new();
+                while self.pos < end {
+                    let len = usize::from(self.bytes(1)?[0]);
+                    let entry = String::from_utf8_lossy(self.bytes(len)?).into_owned();
+                    if let Some((key, value)) = entry.split_once('=') {
+                        entries.insert(key.to_ascii_lowercase(), value.to_string());
+                    }
+                }
+                Data::Txt(entries)
+            }
+            _ => Data::Other,
+        };
+        self.pos = end;
+        Some(Record { name, ttl, data })
+    }
+}
+
+fn parse(packet: &[u8]) -> Option<Packet> {
+    let mut reader = Reader { packet, pos: 0 };
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    let counts = [reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?];
+    let mut questions = Vec::new();
+    for _ in 0..counts[0] {
+        let name = reader.name()?;
+        let kind = reader.u16()?;
+        let _class = reader.u16()?;
+        questions.push((name, kind));
+    }
+    let mut records = Vec::new();
+    for _ in 0..counts[1..]
+        .iter()
+        .map(|count| usize::from(*count))
+        .sum::<usize>()
+    {
+        records.push(reader.record()?);
+    }
+    Some(Packet {
+        id,
+        response: flags & 0x8000 != 0,
+        questions,
+        records,
+    })
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim_end_matches('.')
+        .eq_ignore_ascii_case(b.trim_end_matches('.'))
+}
+
+/// Answers queries for a service until stopped.
+pub(super) struct Announcer {
+    socket: UdpSocket,
+    service: Service,
+}
+
+impl Announcer {
+    /// Joins the mDNS group, sharing the port with any system responder.
+    /// Must be called from within the async runtime.
+    pub(super) fn bind(service: Service) -> io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, PORT).into())?;
+        socket.join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_multicast_ttl_v4(255)?;
+        Ok(Self {
+            socket: UdpSocket::from_std(socket.into())?,
+            service,
+        })
+    }
+
+    /// Announces the service, answers queries, and says goodbye on `stop`.
+    pub(super) async fn run(self, mut stop: oneshot::Receiver<()>) {
+        let group = SocketAddr::from((GROUP, PORT));
+        if let Err(err) = self
+            .socket
+            .send_to(&self.service.response(0, TTL, false), group)
+            .await
+        {
+            log::warn!("Failed to announce LAN session: {err}");
+        }
+
+        let mut buffer = vec![0u8; 9000];
+        loop {
+            let (len, from) = tokio::select! {
+                _ = &mut stop => break,
+                received = self.socket.recv_from(&mut buffer) => match received {
+                    Ok(received) => received,
+                    Err(err) => {
+                        log::warn!("mDNS receive failed: {err}");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                },
+            };
+            let Some(query) = parse(&buffer[..len]) else {
+                continue;
+            };
+            let asked = !query.response
+                && query.questions.iter().any(|(name, kind)| {
+                    same_name(name, SERVICE) && (*kind == TYPE_PTR || *kind == TYPE_ANY)
+                });
+            if !asked {
+                continue;
+            }
+            // Queries from other ports are one-shot and want a direct reply
+            let (reply, to) = if from.port() == PORT {
+                (self.service.response(0, TTL, false), group)
+            } else {
+                (self.service.response(query.id, TTL, true), from)
+            };
+            if let Err(err) = self.socket.send_to(&reply, to).await {
+                log::warn!("Failed to answer mDNS query from {from}: {err}");
+            }
+        }
+
+        if let Err(err) = self
+            .socket
+            .send_to(&self.service.response(0, 0, false), group)
+            .await
+        {
+            log::warn!("Failed to withdraw LAN session: {err}");
+        }
+    }
+}
+
+/// Queries the local network and collects the hosts that answer within
+/// `timeout`.
+pub(super) async fn browse(timeout: Duration) -> io::Result<Vec<Found>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_multicast_ttl_v4(255)?;
+    let mut query = header(0, 0, 1, 0);
+    push_name(&mut query, SERVICE);
+    query.extend(TYPE_PTR.to_be_bytes());
+    query.extend((CLASS_IN | CLASS_FLAG).to_be_bytes());
+    socket.send_to(&query, (GROUP, PORT)).await?;
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut found: Vec<Found> = Vec::new();
+    let mut buffer = vec![0u8; 9000];
+    loop {
+        let (len, from) =
+            match tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await {
+                Err(_) => break,
+                Ok(received) => received?,
+            };
+        let SocketAddr::V4(from) = from else {
+            continue;
+        };
+        let Some(packet) = parse(&buffer[..len]) else {
+            continue;
+        };
+        if !packet.response {
+            continue;
+        }
+        for record in &packet.records {
+            let Data::Ptr(instance) = &record.data else {
+                continue;
+            };
+            if !same_name(&record.name, SERVICE)
+                || record.ttl == 0
+                || found.iter().any(|host| same_name(&host.instance, instance))
+            {
+                continue;
+            }
+            let records = || {
+                packet
+                    .records
+                    .iter()
+                    .filter(|record| same_name(&record.name, instance))
+            };
+            let Some(port) = records().find_map(|record| match record.data {
+                Data::Srv { port } => Some(port),
+                _ => None,
+            }) else {
+                continue;
+            };
+            let txt = records()
+                .find_map(|record| match &record.data {
+                    Data::Txt(txt) => Some(txt.clone()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            found.push(Found {
+                instance: instance.clone(),
+                address: *from.ip(),
+                port,
+                txt,
+            });
+        }
+    }
+    Ok(found)
+}
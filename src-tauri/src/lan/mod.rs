@@ -0,0 +1,703 @@
+//! Read-only review sessions shared over the local network.
+//!
+//! One instance hosts: it listens for TLS connections on an ephemeral port
+//! and announces itself over mDNS. Others browse for hosts, connect trusting
+//! only the key the host advertised, and present the six-digit join code the
+//! host shows. From then on viewers receive the playlist order, the version
+//! on screen and each published note as events. Nothing flows back, so a
+//! viewer cannot change the host's session.
+//!
+//! The protocol is newline-delimited JSON. The viewer opens with `hello`;
+//! the host answers with a `snapshot` of the session, then sends `update`s,
+//! a `ping` when idle, and `ended` when it stops.
+
+mod mdns;
+mod tls;
+
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ring::rand::{SecureRandom, SystemRandom};
+use rustls::pki_types::ServerName;
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, oneshot, watch};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use crate::error::{Error, Result};
+use crate::store::now_millis;
+use crate::store::publish_jobs::PublishJob;
+
+pub const SNAPSHOT_EVENT: &str = "lan:snapshot";
+pub const UPDATE_EVENT: &str = "lan:update";
+pub const STATUS_EVENT: &str = "lan:status";
+
+/// Notes kept for viewers who join late.
+const MAX_NOTES: usize = 500;
+const MAX_MESSAGE: u64 = 4 * 1024 * 1024;
+/// Wrong join codes tolerated before the host turns everyone away.
+const MAX_FAILED_JOINS: u32 = 20;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+/// Viewers give up on a host that has been silent this long.
+const HOST_TIMEOUT: Duration = Duration::from_secs(45);
+const DEFAULT_DISCOVERY: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistOrder {
+    pub playlist_id: String,
+    pub name: String,
+    pub version_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedNote {
+    pub version_id: String,
+    pub playlist_id: String,
+    pub content: String,
+    pub published_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LanUpdate {
+    PlaylistOrder(PlaylistOrder),
+    CurrentVersion { version_id: Option<String> },
+    NotePublished(SharedNote),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanSnapshot {
+    pub session_name: String,
+    pub playlist: Option<PlaylistOrder>,
+    pub current_version_id: Option<String>,
+    /// Oldest first.
+    pub notes: Vec<SharedNote>,
+}
+
+impl LanSnapshot {
+    fn apply(&mut self, update: &LanUpdate) {
+        match update {
+            LanUpdate::PlaylistOrder(order) => self.playlist = Some(order.clone()),
+            LanUpdate::CurrentVersion { version_id } => {
+                self.current_version_id = version_id.clone();
+            }
+            LanUpdate::NotePublished(note) => {
+                self.notes.push(note.clone());
+                if self.notes.len() > MAX_NOTES {
+                    self.notes.drain(..self.notes.len() - MAX_NOTES);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Message {
+    Hello { code: String, name: String },
+    Snapshot { snapshot: LanSnapshot },
+    Update { update: LanUpdate },
+    Rejected { reason: String },
+    Ping,
+    Ended,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Viewer {
+    #[serde(skip)]
+    id: String,
+    pub name: String,
+    pub address: String,
+    pub joined_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostedSession {
+    pub name: String,
+    pub code: String,
+    pub port: u16,
+    /// Key pin viewers check; shown so it can be compared by eye.
+    pub pin: String,
+    /// Whether the session is announced over mDNS; if not, viewers must
+    /// enter the address.
+    pub discoverable: bool,
+    pub viewers: Vec<Viewer>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoinedSession {
+    #[serde(skip)]
+    id: String,
+    pub name: String,
+    pub address: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanStatus {
+    pub hosting: Option<HostedSession>,
+    pub joined: Option<JoinedSession>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredSession {
+    pub name: String,
+    pub address: String,
+    pub port: u16,
+    pub pin: String,
+    pub version: Option<String>,
+}
+
+/// State shared between the host and its connections.
+struct Shared {
+    name: String,
+    code: String,
+    state: Mutex<LanSnapshot>,
+    updates: broadcast::Sender<LanUpdate>,
+    viewers: Mutex<Vec<Viewer>>,
+    failed_joins: AtomicU32,
+    ended: watch::Sender<bool>,
+}
+
+impl Shared {
+    /// Applies and broadcasts under the state lock, so a viewer subscribing
+    /// at the same time gets each update exactly once.
+    fn publish(&self, update: LanUpdate) {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.apply(&update);
+        // No receivers just means no one has joined yet
+        let _ = self.updates.send(update);
+    }
+
+    fn subscribe(&self) -> (LanSnapshot, broadcast::Receiver<LanUpdate>) {
+        let state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        (state.clone(), self.updates.subscribe())
+    }
+
+    fn admit(&self, code: &str) -> std::result::Result<(), String> {
+        if self.failed_joins.load(Ordering::Relaxed) >= MAX_FAILED_JOINS {
+            return Err("Too many wrong join codes; ask the host to restart the session".into());
+        }
+        if code.trim() != self.code {
+            self.failed_joins.fetch_add(1, Ordering::Relaxed);
+            return Err("Wrong join code".into());
+        }
+        Ok(())
+    }
+
+    fn viewers(&self) -> std::sync::MutexGuard<'_, Vec<Viewer>> {
+        self.viewers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+struct Host {
+    shared: Arc<Shared>,
+    port: u16,
+    pin: String,
+    listener: JoinHandle<()>,
+    announcer: Option<oneshot::Sender<()>>,
+}
+
+impl Host {
+    fn status(&self) -> HostedSession {
+        HostedSession {
+            name: self.shared.name.clone(),
+            code: self.shared.code.clone(),
+            port: self.port,
+            pin: self.pin.clone(),
+            discoverable: self.announcer.is_some(),
+            viewers: self.shared.viewers().clone(),
+        }
+    }
+
+    fn stop(mut self) {
+        self.shared.ended.send_replace(true);
+        self.listener.abort();
+        if let Some(announcer) = self.announcer.take() {
+            let _ = announcer.send(());
+        }
+    }
+}
+
+struct Joined {
+    session: JoinedSession,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct LanSharing {
+    host: Mutex<Option<Host>>,
+    joined: Mutex<Option<Joined>>,
+}
+
+impl LanSharing {
+    fn host(&self) -> std::sync::MutexGuard<'_, Option<Host>> {
+        self.host
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn joined(&self) -> std::sync::MutexGuard<'_, Option<Joined>> {
+        self.joined
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn status(&self) -> LanStatus {
+        LanStatus {
+            hosting: self.host().as_ref().map(Host::status),
+            joined: self.joined().as_ref().map(|joined| joined.session.clone()),
+        }
+    }
+}
+
+fn emit_status(app: &AppHandle) {
+    let status = app.state::<LanSharing>().status();
+    if let Err(err) = app.emit(STATUS_EVENT, &status) {
+        log::warn!("Failed to emit LAN status: {err}");
+    }
+}
+
+fn host_name() -> String {
+    sysinfo::System::host_name().unwrap_or_else(|| "AstraNotes".into())
+}
+
+fn join_code() -> Result<String> {
+    let mut bytes = [0u8; 4];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| Error::Lan("Failed to generate a join code".into()))?;
+    Ok(format!("{:06}", u32::from_be_bytes(bytes) % 1_000_000))
+}
+
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Message>> {
+    let mut line = String::new();
+    let read = (&mut *reader)
+        .take(MAX_MESSAGE)
+        .read_line(&mut line)
+        .await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && read as u64 == MAX_MESSAGE {
+        return Err(Error::Lan("LAN message too large".into()));
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Message) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn timed<T>(future: impl Future<Output = Result<T>>, what: &str) -> Result<T> {
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, future)
+        .await
+        .map_err(|_| Error::Lan(format!("Timed out {what}")))?
+}
+
+async fn accept(app: AppHandle, listener: TcpListener, acceptor: TlsAcceptor, shared: Arc<Shared>) {
+    loop {
+        let (stream, address) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                log::warn!("Failed to accept LAN viewer: {err}");
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                continue;
+            }
+        };
+        let app = app.clone();
+        let acceptor = acceptor.clone();
+        let shared = shared.clone();
+        tauri::async_runtime::spawn(async move {
+            let address = address.ip().to_string();
+            if let Err(err) = serve(&app, acceptor, stream, &address, &shared).await {
+                log::info!("LAN viewer {address} disconnected: {err}");
+            }
+        });
+    }
+}
+
+async fn serve(
+    app: &AppHandle,
+    acceptor: TlsAcceptor,
+    stream: TcpStream,
+    address: &str,
+    shared: &Shared,
+) -> Result<()> {
+    let stream = timed(
+        async { Ok(acceptor.accept(stream).await?) },
+        "negotiating TLS",
+    )
+    .await?;
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = tokio::io::BufReader::new(reader);
+    let (code, name) = match timed(read_message(&mut reader), "waiting for hello").await? {
+        Some(Message::Hello { code, name }) => (code, name),
+        _ => return Err(Error::Lan("Viewer did not say hello".into())),
+    };
+    if let Err(reason) = shared.admit(&code) {
+        write_message(
+            &mut writer,
+            &Message::Rejected {
+                reason: reason.clone(),
+            },
+        )
+        .await?;
+        return Err(Error::Lan(reason));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    shared.viewers().push(Viewer {
+        id: id.clone(),
+        name,
+        address: address.to_string(),
+        joined_at: now_millis(),
+    });
+    emit_status(app);
+    let result = stream_updates(shared, reader, writer).await;
+    shared.viewers().retain(|viewer| viewer.id != id);
+    emit_status(app);
+    result
+}
+
+async fn stream_updates(
+    shared: &Shared,
+    mut reader: impl AsyncRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let mut ended = shared.ended.subscribe();
+    let (snapshot, mut updates) = shared.subscribe();
+    write_message(&mut writer, &Message::Snapshot { snapshot }).await?;
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.reset();
+    // Viewers send nothing after hello; reading only notices them leave
+    let mut discard = [0u8; 256];
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(update) => write_message(&mut writer, &Message::Update { update }).await?,
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let (snapshot, fresh) = shared.subscribe();
+                    updates = fresh;
+                    write_message(&mut writer, &Message::Snapshot { snapshot }).await?;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = ended.changed() => break,
+            _ = ping.tick() => write_message(&mut writer, &Message::Ping).await?,
+            read = reader.read(&mut discard) => {
+                if read? == 0 {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    write_message(&mut writer, &Message::Ended).await?;
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// Forwards a joined session's messages until it ends.
+async fn follow(app: AppHandle, id: String, mut reader: impl AsyncBufRead + Unpin) {
+    let reason = loop {
+        let message = match tokio::time::timeout(HOST_TIMEOUT, read_message(&mut reader)).await {
+            Err(_) => break "The host stopped responding".to_string(),
+            Ok(Err(err)) => break err.to_string(),
+            Ok(Ok(message)) => message,
+        };
+        let emitted = match message {
+            Some(Message::Snapshot { snapshot }) => app.emit(SNAPSHOT_EVENT, &snapshot),
+            Some(Message::Update { update }) => app.emit(UPDATE_EVENT, &update),
+            Some(Message::Ended) | None => break "The host ended the session".to_string(),
+            Some(_) => Ok(()),
+        };
+        if let Err(err) = emitted {
+            log::warn!("Failed to emit LAN session update: {err}");
+        }
+    };
+    log::info!("Left LAN session: {reason}");
+
+    let lan = app.state::<LanSharing>();
+    let mut joined = lan.joined();
+    if joined
+        .as_ref()
+        .is_some_and(|joined| joined.session.id == id)
+    {
+        *joined = None;
+    }
+    drop(joined);
+    emit_status(&app);
+}
+
+/// Shares a published note with viewers of the hosted session, if any.
+pub fn published(app: &AppHandle, job: &PublishJob) {
+    let lan = app.state::<LanSharing>();
+    let host = lan.host();
+    let Some(host) = host.as_ref() else {
+        return;
+    };
+    let content = job.operations.as_array().and_then(|operations| {
+        operations
+            .iter()
+            .find(|op| op["action"] == "create" && op["entity_type"] == "Note")
+            .and_then(|op| op["entity_data"]["content"].as_str())
+    });
+    let Some(content) = content else {
+        return;
+    };
+    host.shared.publish(LanUpdate::NotePublished(SharedNote {
+        version_id: job.version_id.clone(),
+        playlist_id: job.playlist_id.clone(),
+        content: content.to_string(),
+        published_at: now_millis(),
+    }));
+}
+
+/// Starts hosting, or returns the session already hosted.
+#[tauri::command]
+pub async fn start_lan_session(
+    app: AppHandle,
+    lan: State<'_, LanSharing>,
+    name: Option<String>,
+) -> Result<HostedSession> {
+    if lan.joined().is_some() {
+        return Err(Error::Lan(
+            "Leave the joined session before hosting one".into(),
+        ));
+    }
+    let mut host = lan.host();
+    if let Some(host) = host.as_ref() {
+        return Ok(host.status());
+    }
+
+    let identity = tls::identity()?;
+    let listener = std::net::TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    listener.set_nonblocking(true)?;
+    let listener = TcpListener::from_std(listener)?;
+    let port = listener.local_addr()?.port();
+    let name = name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(host_name);
+    let (ended, _) = watch::channel(false);
+    let shared = Arc::new(Shared {
+        name: name.clone(),
+        code: join_code()?,
+        state: Mutex::new(LanSnapshot {
+            session_name: name.clone(),
+            ..LanSnapshot::default()
+        }),
+        updates: broadcast::channel(256).0,
+        viewers: Mutex::new(Vec::new()),
+        failed_joins: AtomicU32::new(0),
+        ended,
+    });
+
+    let service = mdns::Service {
+        instance: uuid::Uuid::new_v4().simple().to_string(),
+        port,
+        txt: vec![
+            ("name", name),
+            ("pin", identity.pin.clone()),
+            ("version", env!("CARGO_PKG_VERSION").to_string()),
+        ],
+    };
+    // Viewers can still join by address when mDNS is unavailable
+    let announcer = match mdns::Announcer::bind(service) {
+        Ok(announcer) => {
+            let (stop, stopped) = oneshot::channel();
+            tauri::async_runtime::spawn(announcer.run(stopped));
+            Some(stop)
+        }
+        Err(err) => {
+            log::warn!("LAN session will not be discoverable: {err}");
+            None
+        }
+    };
+    let listener = tauri::async_runtime::spawn(accept(
+        app.clone(),
+        listener,
+        TlsAcceptor::from(identity.config),
+        shared.clone(),
+    ));
+
+    let hosted = Host {
+        shared,
+        port,
+        pin: identity.pin,
+        listener,
+        announcer,
+    };
+    let status = hosted.status();
+    *host = Some(hosted);
+    drop(host);
+    log::info!("Hosting LAN session on port {port}");
+    emit_status(&app);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn stop_lan_session(app: AppHandle, lan: State<'_, LanSharing>) {
+    let host = lan.host().take();
+    if let Some(host) = host {
+        host.stop();
+        emit_status(&app);
+    }
+}
+
+/// Sends the host's playlist order or current version to viewers. Does
+/// nothing when not hosting, so the frontend can call it unconditionally.
+#[tauri::command]
+pub fn publish_lan_update(lan: State<'_, LanSharing>, update: LanUpdate) -> Result<()> {
+    if matches!(update, LanUpdate::NotePublished(_)) {
+        return Err(Error::InvalidInput(
+            "Notes are shared when they publish".into(),
+        ));
+    }
+    if let Some(host) = lan.host().as_ref() {
+        host.shared.publish(update);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn discover_lan_sessions(timeout_ms: Option<u64>) -> Result<Vec<DiscoveredSession>> {
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_DISCOVERY)
+        .min(Duration::from_secs(10));
+    let found = mdns::browse(timeout).await?;
+    Ok(found
+        .into_iter()
+        .filter_map(|mut host| {
+            Some(DiscoveredSession {
+                pin: host.txt.remove("pin")?,
+                name: host.txt.remove("name").unwrap_or(host.instance),
+                address: host.address.to_string(),
+                port: host.port,
+                version: host.txt.remove("version"),
+            })
+        })
+        .collect())
+}
+
+/// Joins a hosted session and returns its current state; later changes
+/// arrive as events.
+#[tauri::command]
+pub async fn join_lan_session(
+    app: AppHandle,
+    lan: State<'_, LanSharing>,
+    address: String,
+    port: u16,
+    pin: String,
+    code: String,
+    name: Option<String>,
+) -> Result<LanSnapshot> {
+    if lan.host().is_some() {
+        return Err(Error::Lan(
+            "Stop hosting before joining another session".into(),
+        ));
+    }
+    leave(&app, &lan);
+
+    let address = address.trim().to_string();
+    let connector = TlsConnector::from(tls::client_config(pin.trim())?);
+    let server_name =
+        ServerName::try_from(tls::SERVER_NAME).map_err(|err| Error::Lan(err.to_string()))?;
+    let stream = timed(
+        async { Ok(TcpStream::connect((address.as_str(), port)).await?) },
+        "connecting to the host",
+    )
+    .await?;
+    let stream = timed(
+        async { Ok(connector.connect(server_name, stream).await?) },
+        "negotiating TLS",
+    )
+    .await?;
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = tokio::io::BufReader::new(reader);
+    let hello = Message::Hello {
+        code: code.trim().to_string(),
+        name: name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(host_name),
+    };
+    write_message(&mut writer, &hello).await?;
+    let snapshot = match timed(read_message(&mut reader), "waiting for the host").await? {
+        Some(Message::Snapshot { snapshot }) => snapshot,
+        Some(Message::Rejected { reason }) => return Err(Error::Lan(reason)),
+        Some(Message::Ended) | None => {
+            return Err(Error::Lan("The host ended the session".into()));
+        }
+        Some(_) => return Err(Error::Lan("Unexpected reply from the host".into())),
+    };
+
+    let session = JoinedSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: snapshot.session_name.clone(),
+        address,
+        port,
+    };
+    let id = session.id.clone();
+    let task_app = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        // Keep the write half alive so the connection stays open
+        let _writer = writer;
+        follow(task_app, id, reader).await;
+    });
+    *lan.joined() = Some(Joined { session, task });
+    emit_status(&app);
+    Ok(snapshot)
+}
+
+fn leave(app: &AppHandle, lan: &LanSharing) {
+    let joined = lan.joined().take();
+    if let Some(joined) = joined {
+        joined.task.abort();
+        emit_status(app);
+    }
+}
+
+#[tauri::command]
+pub fn leave_lan_session(app: AppHandle, lan: State<'_, LanSharing>) {
+    leave(&app, &lan);
+}
+
+#[tauri::command]
+pub fn get_lan_status(lan: State<'_, LanSharing>) -> LanStatus {
+    lan.status()
+}
@@ -0,0 +1,182 @@
+//! Throwaway TLS identity for a hosted session.
+//!
+//! Each session gets a fresh Ed25519 key and a minimal self-signed
+//! certificate. Viewers do not check it against any CA: they accept exactly
+//! the key whose pin the host advertised, the same `sha256/…` form profiles
+//! pin tracker servers with.
+
+use std::sync::Arc;
+
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, ServerConfig, SignatureScheme,
+};
+
+use crate::error::{Error, Result};
+use crate::network::pinning;
+
+/// Name the host presents; viewers verify the key, not the name.
+pub(super) const SERVER_NAME: &str = "astranotes.local";
+const SUBJECT: &str = "AstraNotes LAN session";
+const ED25519_OID: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+const COMMON_NAME_OID: &[u8] = &[0x06, 0x03, 0x55, 0x04, 0x03];
+
+fn tls_error(err: impl std::fmt::Display) -> Error {
+    Error::Lan(format!("TLS setup failed: {err}"))
+}
+
+/// A DER element with `tag` around `content`.
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    der(0x30, &parts.concat())
+}
+
+fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    der(0x03, &[&[0u8][..], bytes].concat())
+}
+
+fn utc_time(at: chrono::DateTime<chrono::Utc>) -> Vec<u8> {
+    der(0x17, at.format("%y%m%d%H%M%SZ").to_string().as_bytes())
+}
+
+/// A self-signed X.509 v3 certificate for the key pair.
+fn certificate(key: &Ed25519KeyPair, serial: [u8; 16]) -> Vec<u8> {
+    let algorithm = sequence(&[ED25519_OID]);
+    let name = sequence(&[&der(
+        0x31,
+        &sequence(&[COMMON_NAME_OID, &der(0x0c, SUBJECT.as_bytes())]),
+    )]);
+    let now = chrono::Utc::now();
+    let validity = sequence(&[
+        &utc_time(now - chrono::Duration::days(1)),
+        &utc_time(now + chrono::Duration::days(30)),
+    ]);
+    let public_key = sequence(&[&algorithm, &bit_string(key.public_key().as_ref())]);
+    // A leading zero byte keeps the serial positive
+    let serial = der(0x02, &[&[0u8][..], &serial].concat());
+    let tbs = sequence(&[
+        &der(0xa0, &der(0x02, &[2])),
+        &serial,
+        &algorithm,
+        &name,
+        &validity,
+        &name,
+        &public_key,
+    ]);
+    let signature = key.sign(&tbs);
+    sequence(&[&tbs, &algorithm, &bit_string(signature.as_ref())])
+}
+
+pub(super) struct Identity {
+    pub(super) config: Arc<ServerConfig>,
+    pub(super) pin: String,
+}
+
+pub(super) fn identity() -> Result<Identity> {
+    let rng = SystemRandom::new();
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).map_err(tls_error)?;
+    let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).map_err(tls_error)?;
+    let certificate = certificate(&key, *uuid::Uuid::new_v4().as_bytes());
+    let pin = pinning::spki_pin(&certificate)?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![CertificateDer::from(certificate)],
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pkcs8.as_ref().to_vec())),
+        )
+        .map_err(tls_error)?;
+    Ok(Identity {
+        config: Arc::new(config),
+        pin,
+    })
+}
+
+#[derive(Debug)]
+struct PinnedKey {
+    pin: String,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinnedKey {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let pin = pinning::spki_pin(end_entity)
+            .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        if pin == self.pin {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// Client configuration that trusts only the host with `pin`.
+pub(super) fn client_config(pin: &str) -> Result<Arc<ClientConfig>> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = PinnedKey {
+        pin: pin.to_string(),
+        algorithms: provider.signature_verification_algorithms,
+    };
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
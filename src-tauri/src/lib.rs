@@ -1,24 +1,599 @@
+mod accessibility;
+mod annotations;
+mod app_menu;
+mod archive;
+mod auth;
+mod automation;
+mod badge;
+mod bulk_import;
+mod capture;
+mod checksums;
+pub mod cli;
+mod clipboard;
+mod color;
+mod connectivity;
+mod credentials;
+mod deep_link;
+mod delivery;
+mod detached;
+mod diagnostics;
+mod downloads;
+mod drag_out;
+mod error;
+mod event_hub;
+mod export;
+mod file_open;
+mod frameio;
+mod ftrack;
+mod i18n;
+mod idle;
+mod ingest;
+mod instance;
+mod instrumentation;
+mod ipc_payload;
+mod lan;
+mod logging;
+mod media;
+mod network;
+mod notifications;
+mod otio;
+mod palette;
+mod pathmap;
+mod paths;
+mod pipeline_hooks;
+mod player_integration;
+mod plugins;
+mod power;
+mod presence;
+mod presentation;
+mod print;
+mod profiles;
+mod providers;
+mod qc;
+mod queue;
+mod recent;
+mod recovery;
+mod reports;
+mod resources;
+mod review_import;
+mod rules;
+mod scheduler;
+mod search;
+mod session;
+mod settings;
+mod share;
+mod shortcuts;
+mod spellcheck;
+mod startup;
+mod store;
+mod sync;
+mod syncsketch;
+mod taskbar;
+mod telemetry;
+mod templates;
+mod text_diff;
+mod thumbnail_cache;
+mod thumbnail_prefetch;
+mod timecode;
+mod transcode;
+mod transcription;
+mod transfers;
+mod tray;
+mod updates;
+mod uploads;
+mod voice_notes;
+mod watcher;
+mod watermark;
+mod webhooks;
+mod window_state;
+
+use tauri::webview::PageLoadEvent;
+use tauri::{AppHandle, Manager, RunEvent, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+pub use paths::{PORTABLE_DIR, set_data_dir};
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let started = std::time::Instant::now();
     // Generate Tauri context
-    let ctx = tauri::generate_context!();
+    let mut ctx = tauri::generate_context!();
+    // Portable windows are built in setup, where their webview data can be
+    // redirected; the config only takes paths relative to the user profile
+    let portable = paths::webview_dir().is_some();
+    if portable {
+        for window in &mut ctx.config_mut().app.windows {
+            window.create = false;
+        }
+    }
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before initializing anything
+        .plugin(instance::plugin())
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
-            }
+        .plugin(shortcuts::plugin())
+        .register_asynchronous_uri_scheme_protocol(media::SCHEME, media::handle)
+        .register_asynchronous_uri_scheme_protocol(thumbnail_cache::SCHEME, thumbnail_cache::handle)
+        .setup(move |app| {
+            app.manage(startup::Startup::new(started));
+            startup::stage(app.handle(), "core", || {
+                if portable {
+                    paths::create_windows(app.handle())?;
+                }
+                // Managed values are written first so telemetry and the rest read them
+                settings::init(app.handle());
+                app.handle().plugin(logging::plugin(app.handle())?)?;
+                // Started here rather than in main, so a second instance has already exited
+                if let Some(guard) = telemetry::init() {
+                    app.manage(guard);
+                }
+                instrumentation::init(app.handle());
+                network::init(app.handle());
+                window_state::init(app.handle());
+                Ok(())
+            })?;
+            startup::stage(app.handle(), "state", || {
+                let cache_dir = paths::cache_dir(app.handle())?;
+                app.manage(auth::Sso::default());
+                app.manage(badge::DraftBadge::default());
+                app.manage(bulk_import::BulkImports::default());
+                app.manage(clipboard::SystemClipboard::default());
+                app.manage(
+                    color::ColorManager::load(app.handle()).unwrap_or_else(|err| {
+                        log::warn!("Ignoring invalid color.json: {err}");
+                        color::ColorManager::default()
+                    }),
+                );
+                app.manage(delivery::DeliveryManager::default());
+                app.manage(delivery::cloud::CloudUploads::default());
+                app.manage(detached::DetachedWindows::default());
+                app.manage(diagnostics::Diagnostics::default());
+                app.manage(downloads::DownloadManager::default());
+                app.manage(event_hub::EventHub::default());
+                app.manage(ftrack::proxy::FtrackProxy::default());
+                app.manage(lan::LanSharing::default());
+                app.manage(media::MediaRegistry::default());
+                app.manage(media::sequence_scan::SequenceScans::default());
+                app.manage(notifications::Notifications::default());
+                app.manage(palette::Palette::default());
+                app.manage(pathmap::PathMap::load(app.handle()).unwrap_or_else(|err| {
+                    log::warn!("Ignoring invalid pathmap.json: {err}");
+                    pathmap::PathMap::default()
+                }));
+                app.manage(pipeline_hooks::PipelineHooks::default());
+                app.manage(plugins::Plugins::default());
+                app.manage(providers::Providers::default());
+                app.manage(rules::Rules::default());
+                app.manage(spellcheck::SpellChecker::default());
+                app.manage(taskbar::TaskbarProgress::default());
+                app.manage(transcode::Transcodes::default());
+                // The protocol handler serves from it as soon as the page loads
+                app.manage(thumbnail_cache::ThumbnailCache::load(
+                    app.handle(),
+                    cache_dir.join("thumbnails"),
+                )?);
+                app.manage(voice_notes::VoiceRecorder::default());
+
+                // Launch URLs and files must be caught before the event loop runs
+                deep_link::init(app.handle());
+                file_open::init(app.handle());
+                shortcuts::init(app.handle());
+                // Built before the window shows, so it never appears without a menu
+                app_menu::init(app.handle());
+                Ok(())
+            })?;
+            startup::defer(app.handle(), start_deferred);
             Ok(())
         })
-        .run(ctx)
-        .expect("error while running tauri application");
+        .on_page_load(|webview, payload| {
+            if payload.event() == PageLoadEvent::Finished && webview.label() == "main" {
+                startup::painted(webview.app_handle());
+            }
+        })
+        .on_window_event(|window, event| {
+            window_state::track(window, event);
+            detached::on_window_event(window, event);
+            ingest::on_window_event(window, event);
+            if let WindowEvent::Focused(true) = event
+                && window.label() == "main"
+            {
+                notifications::window_focused(window.app_handle());
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            thumbnail_cache::cache_thumbnail,
+            thumbnail_cache::get_cached_thumbnail,
+            thumbnail_cache::evict_cache,
+            store::drafts::save_draft,
+            store::drafts::list_drafts,
+            store::drafts::restore_draft,
+            store::drafts::delete_draft,
+            credentials::store_credential,
+            credentials::get_credential,
+            credentials::delete_credential,
+            queue::enqueue_publish_jobs,
+            queue::list_publish_jobs,
+            queue::retry_publish_job,
+            queue::cancel_publish_job,
+            queue::clear_finished_publish_jobs,
+            telemetry::get_telemetry_settings,
+            telemetry::set_telemetry_enabled,
+            deep_link::take_pending_deep_link,
+            export::export_notes_csv,
+            export::export_notes_xlsx,
+            export::export_notes_pdf,
+            export::export_playlist_edl,
+            export::export_playlist_ale,
+            downloads::start_download,
+            downloads::pause_download,
+            downloads::resume_download,
+            downloads::cancel_download,
+            downloads::list_downloads,
+            uploads::upload_attachment,
+            event_hub::connect_event_hub,
+            event_hub::disconnect_event_hub,
+            event_hub::get_event_hub_status,
+            search::index_notes,
+            search::search_notes,
+            media::register_media,
+            media::unregister_media,
+            media::frames::extract_frame,
+            media::metadata::read_image_metadata,
+            reports::contact_sheet::generate_contact_sheet,
+            otio::export_playlist_otio,
+            delivery::start_delivery,
+            delivery::cancel_delivery,
+            delivery::list_deliveries,
+            archive::create_attachment_archive,
+            player_integration::get_player_settings,
+            player_integration::set_player_settings,
+            player_integration::open_in_player,
+            profiles::add_profile,
+            profiles::list_profiles,
+            profiles::get_active_profile,
+            profiles::switch_profile,
+            profiles::remove_profile,
+            pathmap::get_path_mappings,
+            pathmap::set_path_mappings,
+            pathmap::map_path,
+            pathmap::resolve_local_path,
+            watcher::add_watch,
+            watcher::remove_watch,
+            watcher::list_watches,
+            ftrack::proxy::ftrack_query,
+            ftrack::cache::clear_api_cache,
+            ftrack::cache::get_api_cache_stats,
+            sync::queue_mutation,
+            sync::list_mutations,
+            sync::get_sync_status,
+            sync::sync_now,
+            sync::resolve_mutation_conflict,
+            sync::clear_applied_mutations,
+            sync::conflicts::set_note_base,
+            sync::conflicts::get_note_conflict,
+            sync::conflicts::resolve_note_conflict,
+            store::revisions::list_note_revisions,
+            store::revisions::diff_note_revisions,
+            shortcuts::get_shortcut_settings,
+            shortcuts::set_shortcut_settings,
+            capture::capture_screen_region,
+            annotations::render_annotations,
+            color::get_project_color,
+            color::set_project_lut,
+            color::read_ocio_config,
+            color::set_project_ocio,
+            media::compare::compare_images,
+            media::similarity::register_playlist_thumbnails,
+            media::similarity::find_similar_versions,
+            voice_notes::start_voice_note,
+            voice_notes::stop_voice_note,
+            voice_notes::cancel_voice_note,
+            voice_notes::get_voice_note_level,
+            transcription::get_transcription_settings,
+            transcription::set_transcription_settings,
+            transcription::transcribe_audio,
+            palette::register_candidates,
+            palette::release_candidates,
+            palette::fuzzy_match,
+            spellcheck::check_text,
+            spellcheck::suggest_spelling,
+            spellcheck::add_to_dictionary,
+            spellcheck::get_spellcheck_settings,
+            spellcheck::set_spellcheck_settings,
+            templates::list_templates,
+            templates::save_template,
+            templates::delete_template,
+            templates::expand_template,
+            templates::export_template,
+            templates::import_template,
+            automation::list_automation_scripts,
+            automation::reload_automation_scripts,
+            automation::run_automation_event,
+            pipeline_hooks::get_pipeline_hook_settings,
+            pipeline_hooks::set_pipeline_hook_settings,
+            pipeline_hooks::list_hook_runs,
+            pipeline_hooks::run_pipeline_hooks,
+            webhooks::get_webhook_settings,
+            webhooks::set_webhook_settings,
+            webhooks::set_webhook_secret,
+            webhooks::get_webhook_status,
+            providers::check_provider_connection,
+            providers::list_provider_playlists,
+            providers::list_provider_versions,
+            providers::list_provider_notes,
+            providers::publish_provider_note,
+            frameio::set_frameio_token,
+            frameio::clear_frameio_token,
+            frameio::fetch_frameio_review,
+            frameio::import_frameio_review,
+            syncsketch::set_syncsketch_credentials,
+            syncsketch::clear_syncsketch_credentials,
+            syncsketch::fetch_syncsketch_session,
+            syncsketch::import_syncsketch_session,
+            syncsketch::push_syncsketch_notes,
+            notifications::get_notification_settings,
+            notifications::set_notification_settings,
+            taskbar::set_taskbar_progress,
+            taskbar::clear_taskbar_progress,
+            instance::get_launch_args,
+            window_state::reset_window_state,
+            detached::open_detached_window,
+            detached::get_window_context,
+            detached::list_detached_windows,
+            detached::route_to_windows,
+            detached::close_detached_window,
+            presentation::list_displays,
+            presentation::open_presentation_window,
+            presentation::update_presentation,
+            presentation::set_presentation_passthrough,
+            presentation::close_presentation_window,
+            updates::get_update_settings,
+            updates::set_update_settings,
+            updates::set_update_channel,
+            updates::check_for_updates,
+            updates::install_update,
+            updates::rollback_update,
+            updates::pin_version,
+            logging::get_log_tail,
+            logging::export_logs,
+            instrumentation::get_command_metrics,
+            instrumentation::reset_command_metrics,
+            instrumentation::get_tracing_settings,
+            instrumentation::set_tracing_settings,
+            recovery::save_recovery_snapshot,
+            recovery::get_recovery_snapshot,
+            recovery::dismiss_recovery_snapshot,
+            telemetry::set_telemetry_sample_rates,
+            telemetry::sentry_add_breadcrumb,
+            telemetry::sentry_set_user,
+            telemetry::sentry_capture_message,
+            resources::get_resource_stats,
+            resources::get_resource_settings,
+            resources::set_resource_settings,
+            thumbnail_prefetch::set_thumbnail_prefetch,
+            thumbnail_prefetch::cancel_thumbnail_prefetch,
+            thumbnail_prefetch::get_thumbnail_prefetch_status,
+            ftrack::proxy::ftrack_query_raw,
+            export::preview_notes_csv,
+            thumbnail_cache::read_cached_thumbnail,
+            network::get_network_settings,
+            network::set_network_settings,
+            network::set_proxy_password,
+            network::check_network,
+            network::pinning::pin_profile_certificate,
+            network::pinning::verify_profile_certificate,
+            network::pinning::clear_profile_pin,
+            auth::get_sso_config,
+            auth::set_sso_config,
+            auth::begin_sso_login,
+            auth::get_sso_status,
+            auth::sso_logout,
+            connectivity::get_connectivity,
+            connectivity::check_connectivity,
+            scheduler::get_sync_schedule,
+            scheduler::set_sync_schedule,
+            scheduler::get_schedule_status,
+            power::get_power_status,
+            power::get_power_settings,
+            power::set_power_settings,
+            drag_out::prepare_drag_out,
+            drag_out::start_drag_out,
+            ingest::ingest_paths,
+            ingest::get_ingest_settings,
+            ingest::set_ingest_settings,
+            clipboard::read_clipboard_image,
+            clipboard::write_clipboard_image,
+            clipboard::copy_notes_to_clipboard,
+            file_open::take_opened_files,
+            session::export_session,
+            session::import_session,
+            settings::get_managed_settings,
+            settings::export_settings,
+            settings::import_settings,
+            checksums::hash_files,
+            checksums::verify_manifest,
+            delivery::cloud::list_cloud_targets,
+            delivery::cloud::save_cloud_target,
+            delivery::cloud::delete_cloud_target,
+            delivery::cloud::start_cloud_upload,
+            delivery::cloud::resume_cloud_upload,
+            delivery::cloud::cancel_cloud_upload,
+            delivery::cloud::list_cloud_uploads,
+            transfers::get_transfer_settings,
+            transfers::set_transfer_settings,
+            transfers::list_transfers,
+            transfers::set_transfer_priority,
+            media::filmstrip::generate_filmstrip,
+            media::sequence_scan::scan_sequences,
+            watermark::get_watermark_settings,
+            watermark::set_watermark_template,
+            watermark::watermark_still,
+            watermark::watermark_clip,
+            timecode::frames_to_timecode,
+            timecode::timecode_to_frames,
+            timecode::frames_to_seconds,
+            timecode::seconds_to_frames,
+            timecode::convert_timecode,
+            timecode::read_media_timecode,
+            rules::get_rules,
+            rules::dry_run_rules,
+            rules::list_rule_runs,
+            store::audit::query_audit_log,
+            store::audit::export_audit_log,
+            store::vault::list_vaults,
+            store::vault::enable_vault,
+            store::vault::disable_vault,
+            store::vault::unlock_vault,
+            store::vault::lock_vault,
+            i18n::get_locale,
+            i18n::set_locale,
+            i18n::translate,
+            i18n::format_number,
+            i18n::format_date,
+            lan::start_lan_session,
+            lan::stop_lan_session,
+            lan::publish_lan_update,
+            lan::discover_lan_sessions,
+            lan::join_lan_session,
+            lan::leave_lan_session,
+            lan::get_lan_status,
+            presence::acquire_note_lock,
+            presence::release_note_lock,
+            presence::list_note_locks,
+            share::share,
+            print::print_notes_report,
+            startup::get_startup_report,
+            badge::set_draft_badge_enabled,
+            badge::refresh_draft_badge,
+            recent::record_recent_playlist,
+            recent::list_recent_playlists,
+            recent::clear_recent_playlists,
+            app_menu::set_menu_state,
+            app_menu::get_menu_accelerators,
+            app_menu::set_menu_accelerators,
+            accessibility::announce,
+            idle::get_idle_seconds,
+            idle::get_idle_settings,
+            idle::set_idle_settings,
+            thumbnail_cache::get_cache_settings,
+            thumbnail_cache::set_cache_settings,
+            resources::get_cache_breakdown,
+            bulk_import::validate_bulk_import,
+            bulk_import::apply_bulk_import,
+            bulk_import::cancel_bulk_import,
+            bulk_import::list_bulk_imports,
+            bulk_import::get_bulk_import_rows,
+            bulk_import::export_bulk_import_errors,
+            bulk_import::discard_bulk_import,
+            qc::run_qc,
+            qc::get_qc_settings,
+            qc::set_qc_settings,
+            transcode::enqueue_transcode,
+            transcode::list_transcodes,
+            transcode::cancel_transcode,
+            transcode::clear_finished_transcodes,
+            plugins::list_plugins,
+            plugins::enable_plugin,
+            plugins::invoke_plugin_command,
+            plugins::list_plugin_export_formats,
+            plugins::export_with_plugin,
+            diagnostics::run_diagnostics,
+        ])
+        .build(ctx)
+        .expect("error while running tauri application")
+        .run(|app, event| match event {
+            RunEvent::Exit => {
+                app.state::<thumbnail_cache::ThumbnailCache>().flush();
+                recovery::clean_exit(app);
+            }
+            #[cfg(target_os = "macos")]
+            RunEvent::Opened { urls } => file_open::open_urls(app, &urls),
+            _ => {}
+        });
+}
+
+/// Opens the stores and starts the background services, after the first paint.
+fn start_deferred(app: &AppHandle) -> error::Result<()> {
+    let storage = startup::stage(app, "storage", || {
+        let cache_dir = paths::cache_dir(app)?;
+        let data_dir = paths::data_dir(app)?;
+        app.manage(ftrack::cache::ApiCache::open(cache_dir.join("api-cache"))?);
+        app.manage(transfers::TransferManager::load(app)?);
+        app.manage(store::Store::open(&data_dir.join(store::FILE_NAME))?);
+        app.manage(search::SearchIndex::open(&data_dir.join("search-index"))?);
+        Ok(())
+    });
+    // Nothing past this point works without the stores
+    if let Err(err) = storage {
+        app.dialog()
+            .message(format!(
+                "AstraNotes could not open its local data and will close.\n\n{err}"
+            ))
+            .title("AstraNotes failed to start")
+            .kind(MessageDialogKind::Error)
+            .blocking_show();
+        app.exit(1);
+        return Err(err);
+    }
+    startup::stage(app, "services", || {
+        // One service failing must not keep the rest from starting
+        let mut failed = Vec::new();
+        let mut start = |name: &'static str, result: error::Result<()>| {
+            if let Err(err) = result {
+                log::error!("Failed to start {name}: {err}");
+                failed.push(name);
+            }
+        };
+        network::pinning::init(app);
+        start("recovery", recovery::init(app));
+        automation::init(app);
+        start("publish queue", queue::init(app));
+        start("tray", tray::init(app).map_err(Into::into));
+        badge::refresh(app);
+        start("file watcher", watcher::init(app));
+        sync::init(app);
+        webhooks::init(app);
+        updates::init(app);
+        resources::init(app);
+        thumbnail_prefetch::init(app);
+        auth::init(app);
+        connectivity::init(app);
+        start("scheduler", scheduler::init(app));
+        power::init(app);
+        drag_out::init(app);
+        delivery::cloud::init(app);
+        transfers::init(app);
+        presence::init(app);
+        print::init(app);
+        recent::init(app);
+        idle::init(app);
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(error::Error::InvalidInput(format!(
+                "Failed to start {}",
+                failed.join(", ")
+            )))
+        }
+    })
+}
+
+/// Brings the main window to the front, restoring it if minimized or hidden.
+pub(crate) fn focus_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let result = window
+        .unminimize()
+        .and(window.show())
+        .and(window.set_focus());
+    if let Err(err) = result {
+        log::warn!("Failed to focus main window: {err}");
+    }
 }
@@ -0,0 +1,250 @@
+//! Always-on file logging.
+//!
+//! Production builds used to log nothing, which left remote support to
+//! guesswork. Records now go to `astranotes.log` in the app log directory,
+//! rotated at 5 MiB with four older files kept, and to stdout in debug builds.
+//! Messages are scrubbed before they are written: e-mail addresses,
+//! credentials in URLs, tokens and keys, and the user's home directory.
+//! `export_logs` zips the logs with environment details, and the last
+//! diagnostics report if one was run, for a support ticket.
+
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Serialize;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_log::{Target, TargetKind};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::diagnostics::{Diagnostics, DiagnosticsReport};
+use crate::error::{Error, Result};
+use crate::export::write_atomically;
+use crate::paths;
+use crate::updates;
+
+const LOG_FILE: &str = "astranotes.log";
+const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+const ROTATED_FILES: usize = 4;
+const MAX_TAIL_LINES: usize = 10_000;
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").expect("valid email pattern")
+});
+static URL_CREDENTIALS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b([a-z][a-z0-9+.-]*://)[^/\s:@]+:[^/\s@]+@")
+        .expect("valid URL credentials pattern")
+});
+static SECRET_PARAM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\b(api[_-]?key|access_token|token|secret|password|signature)=[^&\s"']+"#)
+        .expect("valid secret parameter pattern")
+});
+static AUTH_SCHEME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(bearer|apikey)\s+[A-Za-z0-9._~+/:=-]+").expect("valid auth scheme pattern")
+});
+static HOME_DIR: LazyLock<Option<String>> = LazyLock::new(|| {
+    dirs::home_dir()
+        .map(|home| home.to_string_lossy().into_owned())
+        .filter(|home| home.len() > 1)
+});
+
+/// Strips personal data and secrets from a log message.
+pub fn scrub(message: &str) -> Cow<'_, str> {
+    let mut message = Cow::Borrowed(message);
+    for (pattern, replacement) in [
+        (&*URL_CREDENTIALS, "${1}<redacted>@"),
+        (&*EMAIL, "<email>"),
+        (&*SECRET_PARAM, "${1}=<redacted>"),
+        (&*AUTH_SCHEME, "${1} <redacted>"),
+    ] {
+        if let Cow::Owned(scrubbed) = pattern.replace_all(&message, replacement) {
+            message = Cow::Owned(scrubbed);
+        }
+    }
+    match HOME_DIR.as_deref() {
+        Some(home) if message.contains(home) => Cow::Owned(message.replace(home, "~")),
+        _ => message,
+    }
+}
+
+fn rotated_path(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("astranotes.{n}.log"))
+}
+
+/// Log files from newest to oldest.
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    std::iter::once(dir.join(LOG_FILE))
+        .chain((1..=ROTATED_FILES).map(|n| rotated_path(dir, n)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Appends to the current log file, rotating it once it reaches the size cap.
+struct RotatingFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..ROTATED_FILES).rev() {
+            let from = rotated_path(&self.dir, n);
+            if from.exists() {
+                fs::rename(from, rotated_path(&self.dir, n + 1))?;
+            }
+        }
+        fs::rename(self.dir.join(LOG_FILE), rotated_path(&self.dir, 1))?;
+        *self = Self::open(&self.dir)?;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// The log plugin, writing scrubbed records to the rotating log file.
+pub fn plugin(app: &AppHandle) -> Result<TauriPlugin<Wry>> {
+    let file = RotatingFile::open(&paths::log_dir(app)?)?;
+    let mut builder = tauri_plugin_log::Builder::default()
+        .level(log::LevelFilter::Info)
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}[{}][{}] {}",
+                chrono::Utc::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                record.level(),
+                record.target(),
+                scrub(&message.to_string())
+            ))
+        })
+        .clear_targets()
+        .target(Target::new(TargetKind::Dispatch(
+            fern::Dispatch::new().chain(Box::new(file) as Box<dyn Write + Send>),
+        )));
+    if cfg!(debug_assertions) {
+        builder = builder.target(Target::new(TargetKind::Stdout));
+    }
+    Ok(builder.build())
+}
+
+/// Returns the last `lines` lines logged, oldest first.
+#[tauri::command]
+pub fn get_log_tail(app: AppHandle, lines: usize) -> Result<Vec<String>> {
+    let wanted = lines.min(MAX_TAIL_LINES);
+    let mut tail = Vec::new();
+    for path in log_files(&paths::log_dir(&app)?) {
+        if tail.len() >= wanted {
+            break;
+        }
+        let contents = fs::read_to_string(&path)?;
+        let remaining = wanted - tail.len();
+        tail.extend(contents.lines().rev().take(remaining).map(str::to_string));
+    }
+    tail.reverse();
+    Ok(tail)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Environment {
+    app_version: String,
+    tauri_version: &'static str,
+    webview_version: Option<String>,
+    os: &'static str,
+    os_family: &'static str,
+    arch: &'static str,
+    update_channel: Option<updates::UpdateChannel>,
+    exported_at: String,
+}
+
+fn environment(app: &AppHandle) -> Environment {
+    Environment {
+        app_version: app.package_info().version.to_string(),
+        tauri_version: tauri::VERSION,
+        webview_version: tauri::webview_version().ok(),
+        os: std::env::consts::OS,
+        os_family: std::env::consts::FAMILY,
+        arch: std::env::consts::ARCH,
+        update_channel: updates::load_settings(app)
+            .ok()
+            .map(|settings| settings.channel),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+fn write_bundle(
+    temp: &Path,
+    logs: &[PathBuf],
+    environment: &Environment,
+    diagnostics: Option<&DiagnosticsReport>,
+) -> Result<()> {
+    let mut zip = ZipWriter::new(io::BufWriter::new(File::create(temp)?));
+    let options = SimpleFileOptions::default();
+    zip.start_file("environment.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(environment)?)?;
+    if let Some(report) = diagnostics {
+        zip.start_file("diagnostics.json", options)?;
+        zip.write_all(&serde_json::to_vec_pretty(report)?)?;
+    }
+    for path in logs {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        zip.start_file(name.to_string_lossy(), options)?;
+        io::copy(&mut File::open(path)?, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Bundles the log files, environment details and the last diagnostics
+/// report into a zip at `dest_zip`.
+#[tauri::command]
+pub async fn export_logs(app: AppHandle, dest_zip: PathBuf) -> Result<PathBuf> {
+    let logs = log_files(&paths::log_dir(&app)?);
+    if logs.is_empty() {
+        return Err(Error::InvalidInput("No logs have been written yet".into()));
+    }
+    let environment = environment(&app);
+    let diagnostics = app.state::<Diagnostics>().last();
+    let target = dest_zip.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        write_atomically(&target, |temp| {
+            write_bundle(temp, &logs, &environment, diagnostics.as_ref())
+        })
+    })
+    .await??;
+    log::info!("Exported logs to {}", dest_zip.display());
+    Ok(dest_zip)
+}
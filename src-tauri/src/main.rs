@@ -1,26 +1,64 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-fn main() {
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(name = "astranotes", version, about)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Args {
+    /// Keep all app data in this directory instead of the user profile
+    #[arg(long, global = true, value_name = "PATH")]
+    data_dir: Option<PathBuf>,
+    /// Run without a window and exit when done
+    #[command(subcommand)]
+    command: Option<app_lib::cli::Command>,
+    /// Files and links to open, forwarded to the app
+    #[arg(hide = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    open: Vec<std::ffi::OsString>,
+}
+
+/// `--data-dir` or a data directory next to the executable selects portable
+/// mode, keeping all app data out of the user profile.
+fn portable_data_dir(args: &Args) -> Option<PathBuf> {
+    if let Some(dir) = &args.data_dir {
+        return Some(std::path::absolute(dir).unwrap_or_else(|_| dir.clone()));
+    }
+    let exe = std::env::current_exe().ok()?;
+    let dir = exe.parent()?.join(app_lib::PORTABLE_DIR);
+    dir.is_dir().then_some(dir)
+}
+
+/// Release builds on Windows have no console of their own, so headless
+/// output goes to the terminal that started them.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+    // SAFETY: no preconditions; failure just leaves the process without a console
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+fn main() -> ExitCode {
+    #[cfg(windows)]
+    attach_console();
+    let args = Args::parse();
+
     // In development mode, load from .env file
     #[cfg(debug_assertions)]
     dotenv::dotenv().ok();
 
-    // For both dev and production, embed the value from .env
-    let dsn = include_str!("../../.env")
-        .lines()
-        .find(|line| line.starts_with("SENTRY_TAURI="))
-        .and_then(|line| line.split('=').nth(1))
-        .map(|value| value.trim())
-        .map(|value| value.trim_matches('"'))
-        .expect("SENTRY_TAURI must be set in .env");
-
-    let _guard = sentry::init((
-        dsn,
-        sentry::ClientOptions {
-            release: sentry::release_name!(),
-            ..Default::default()
-        },
-    ));
-
-    app_lib::run();
+    if let Some(dir) = portable_data_dir(&args) {
+        app_lib::set_data_dir(dir);
+    }
+    match args.command {
+        Some(command) => app_lib::cli::run(command),
+        None => {
+            app_lib::run();
+            ExitCode::SUCCESS
+        }
+    }
 }
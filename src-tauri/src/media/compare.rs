@@ -0,0 +1,231 @@
+//! Pixel differences between two versions of a still.
+//!
+//! Both images are decoded to 8-bit RGBA and compared row by row on the rayon
+//! pool, producing a visualisation of what changed plus per-channel error
+//! statistics. If the second image has a different resolution it is resized
+//! to the first, since resubmissions are sometimes delivered at proxy size.
+
+use std::path::{Path, PathBuf};
+
+use image::imageops::FilterType;
+use image::{ImageFormat, RgbaImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::export::write_atomically;
+use crate::paths;
+
+/// Channel delta above which a pixel counts as changed, to ignore encoder noise.
+const CHANGE_THRESHOLD: u8 = 8;
+/// Amplification for `Absolute` mode, so small differences are visible.
+const ABSOLUTE_GAIN: u16 = 4;
+/// Heat-map stops from no difference to maximum difference.
+const HEAT_STOPS: [[f32; 3]; 5] = [
+    [0.0, 0.0, 0.0],
+    [80.0, 18.0, 123.0],
+    [221.0, 58.0, 60.0],
+    [252.0, 200.0, 40.0],
+    [255.0, 255.0, 255.0],
+];
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompareMode {
+    /// Largest channel difference mapped onto a color ramp.
+    Heatmap,
+    /// Per-channel absolute difference, amplified.
+    Absolute,
+    /// The first image dimmed, with changed pixels highlighted in red.
+    Overlay,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelStats {
+    pub channel: &'static str,
+    /// Mean absolute difference, 0-255.
+    pub mean: f64,
+    pub max: u8,
+    pub rmse: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageComparison {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// Whether the second image was resized to match the first.
+    pub resized: bool,
+    pub channels: Vec<ChannelStats>,
+    pub changed_pixels: u64,
+    pub changed_ratio: f64,
+    /// Peak signal-to-noise ratio in dB; `None` when the images are identical.
+    pub psnr: Option<f64>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    sum: [u64; 3],
+    sum_squares: [u64; 3],
+    max: [u8; 3],
+    changed: u64,
+}
+
+impl Totals {
+    fn merge(mut self, other: Self) -> Self {
+        for channel in 0..3 {
+            self.sum[channel] += other.sum[channel];
+            self.sum_squares[channel] += other.sum_squares[channel];
+            self.max[channel] = self.max[channel].max(other.max[channel]);
+        }
+        self.changed += other.changed;
+        self
+    }
+}
+
+fn heat(t: f32) -> [u8; 3] {
+    let position = t.clamp(0.0, 1.0) * (HEAT_STOPS.len() - 1) as f32;
+    let low = (position.floor() as usize).min(HEAT_STOPS.len() - 2);
+    let t = position - low as f32;
+    [0, 1, 2]
+        .map(|c| (HEAT_STOPS[low][c] + (HEAT_STOPS[low + 1][c] - HEAT_STOPS[low][c]) * t) as u8)
+}
+
+fn visualise(mode: CompareMode, a: &[u8], delta: [u8; 3]) -> [u8; 3] {
+    let largest = delta.into_iter().max().unwrap_or(0);
+    match mode {
+        // Square root keeps subtle changes from disappearing into black
+        CompareMode::Heatmap => heat((f32::from(largest) / 255.0).sqrt()),
+        CompareMode::Absolute => {
+            delta.map(|value| (u16::from(value) * ABSOLUTE_GAIN).min(255) as u8)
+        }
+        CompareMode::Overlay => {
+            let luma =
+                (0.2126 * f32::from(a[0]) + 0.7152 * f32::from(a[1]) + 0.0722 * f32::from(a[2]))
+                    * 0.4;
+            if largest > CHANGE_THRESHOLD {
+                let t = (f32::from(largest) / 64.0).min(1.0);
+                [
+                    (luma + (255.0 - luma) * t) as u8,
+                    (luma * (1.0 - t)) as u8,
+                    (luma * (1.0 - t)) as u8,
+                ]
+            } else {
+                [luma as u8; 3]
+            }
+        }
+    }
+}
+
+/// Compares `b` against `a`, which must have the same dimensions.
+fn compare(a: &RgbaImage, b: &RgbaImage, mode: CompareMode) -> (RgbaImage, Totals) {
+    let (width, height) = a.dimensions();
+    let row_bytes = width as usize * 4;
+    let mut out = vec![0; row_bytes * height as usize];
+    let totals = out
+        .par_chunks_exact_mut(row_bytes)
+        .zip(a.as_raw().par_chunks_exact(row_bytes))
+        .zip(b.as_raw().par_chunks_exact(row_bytes))
+        .map(|((out, a), b)| {
+            let mut totals = Totals::default();
+            for ((out, a), b) in out
+                .chunks_exact_mut(4)
+                .zip(a.chunks_exact(4))
+                .zip(b.chunks_exact(4))
+            {
+                let delta = [0, 1, 2].map(|c| a[c].abs_diff(b[c]));
+                for (channel, value) in delta.into_iter().enumerate() {
+                    totals.sum[channel] += u64::from(value);
+                    totals.sum_squares[channel] += u64::from(value) * u64::from(value);
+                    totals.max[channel] = totals.max[channel].max(value);
+                }
+                if delta.into_iter().any(|value| value > CHANGE_THRESHOLD) {
+                    totals.changed += 1;
+                }
+                let [r, g, b] = visualise(mode, a, delta);
+                out.copy_from_slice(&[r, g, b, 255]);
+            }
+            totals
+        })
+        .reduce(Totals::default, Totals::merge);
+    let image = RgbaImage::from_raw(width, height, out).expect("buffer matches dimensions");
+    (image, totals)
+}
+
+fn summarise(
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    resized: bool,
+    totals: Totals,
+) -> ImageComparison {
+    let pixels = (u64::from(width) * u64::from(height)).max(1) as f64;
+    let channels = ["red", "green", "blue"]
+        .into_iter()
+        .enumerate()
+        .map(|(index, channel)| ChannelStats {
+            channel,
+            mean: totals.sum[index] as f64 / pixels,
+            max: totals.max[index],
+            rmse: (totals.sum_squares[index] as f64 / pixels).sqrt(),
+        })
+        .collect();
+    let mse = totals.sum_squares.iter().sum::<u64>() as f64 / (pixels * 3.0);
+    ImageComparison {
+        path,
+        width,
+        height,
+        resized,
+        channels,
+        changed_pixels: totals.changed,
+        changed_ratio: totals.changed as f64 / pixels,
+        psnr: (mse > 0.0).then(|| 10.0 * (255.0 * 255.0 / mse).log10()),
+    }
+}
+
+fn compare_files(
+    path_a: &Path,
+    path_b: &Path,
+    mode: CompareMode,
+    out: &Path,
+) -> Result<ImageComparison> {
+    let a = image::open(path_a)?.into_rgba8();
+    let mut b = image::open(path_b)?.into_rgba8();
+    let (width, height) = a.dimensions();
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidInput(format!(
+            "{} is empty",
+            path_a.display()
+        )));
+    }
+    let resized = b.dimensions() != (width, height);
+    if resized {
+        b = image::imageops::resize(&b, width, height, FilterType::Triangle);
+    }
+
+    let (difference, totals) = compare(&a, &b, mode);
+    write_atomically(out, |temp| {
+        difference.save_with_format(temp, ImageFormat::Png)?;
+        Ok(())
+    })?;
+    Ok(summarise(out.to_path_buf(), width, height, resized, totals))
+}
+
+/// Compares two stills, writing the difference image to the app cache.
+#[tauri::command]
+pub async fn compare_images(
+    app: AppHandle,
+    path_a: PathBuf,
+    path_b: PathBuf,
+    mode: CompareMode,
+) -> Result<ImageComparison> {
+    let dir = paths::cache_dir(&app)?.join("comparisons");
+    std::fs::create_dir_all(&dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+    let out = dir.join(format!("compare-{stamp}.png"));
+    tauri::async_runtime::spawn_blocking(move || compare_files(&path_a, &path_b, mode, &out))
+        .await?
+}
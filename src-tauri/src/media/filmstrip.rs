@@ -0,0 +1,175 @@
+//! Filmstrip previews: evenly spaced frames of a movie tiled into one strip.
+//!
+//! Each frame is taken with its own input seek, so only a few GOPs around
+//! every sample are decoded rather than the whole movie, and ffmpeg picks a
+//! hardware decoder when one is available. Strips are cached in the app cache
+//! directory, keyed by the movie's path, size and modification time, so a
+//! re-rendered version gets a fresh strip.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use tauri::AppHandle;
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::frames;
+use crate::error::{Error, Result};
+use crate::paths;
+
+const CACHE_DIR: &str = "filmstrips";
+const MAX_FRAMES: u32 = 60;
+const DEFAULT_HEIGHT: u32 = 90;
+const MAX_HEIGHT: u32 = 540;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Filmstrip {
+    pub path: PathBuf,
+    pub frames: u32,
+    /// Width of one tile; frame `n` starts at `n * frame_width`.
+    pub frame_width: u32,
+    pub frame_height: u32,
+    /// Source time of each tile.
+    pub times_seconds: Vec<f64>,
+    pub cached: bool,
+}
+
+fn cache_key(path: &Path, frames: u32, height: u32) -> Result<String> {
+    let meta = std::fs::metadata(path)?;
+    let modified = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let source = format!(
+        "{}|{}|{modified}|{frames}|{height}",
+        path.display(),
+        meta.len()
+    );
+    Ok(format!("{:016x}", xxh3_64(source.as_bytes())))
+}
+
+async fn probe_duration(app: &AppHandle, path: &Path) -> Result<f64> {
+    let output = frames::run(
+        app,
+        "ffprobe",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-show_entries".into(),
+            "format=duration".into(),
+            "-of".into(),
+            "default=noprint_wrappers=1:nokey=1".into(),
+            path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    let duration = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    duration
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .ok_or_else(|| Error::Media(format!("Unreadable duration {duration:?}")))
+}
+
+/// The middle of each of `frames` equal slices, which keeps clear of slates
+/// and fades to black at either end.
+fn sample_times(duration: f64, frames: u32) -> Vec<f64> {
+    (0..frames)
+        .map(|index| duration * (f64::from(index) + 0.5) / f64::from(frames))
+        .collect()
+}
+
+fn ffmpeg_args(path: &Path, times: &[f64], height: u32, out: &Path, hardware: bool) -> Vec<String> {
+    let mut args = vec!["-v".into(), "error".into(), "-y".into()];
+    for time in times {
+        if hardware {
+            args.extend(["-hwaccel".into(), "auto".into()]);
+        }
+        args.extend([
+            "-ss".into(),
+            format!("{time:.3}"),
+            "-i".into(),
+            path.to_string_lossy().into_owned(),
+        ]);
+    }
+    let mut filter: String = (0..times.len())
+        .map(|index| format!("[{index}:v]scale=-2:{height},setsar=1[f{index}];"))
+        .collect();
+    if times.len() > 1 {
+        let inputs: String = (0..times.len())
+            .map(|index| format!("[f{index}]"))
+            .collect();
+        filter.push_str(&format!("{inputs}hstack=inputs={}[strip]", times.len()));
+    } else {
+        filter.push_str("[f0]null[strip]");
+    }
+    args.extend([
+        "-filter_complex".into(),
+        filter,
+        "-map".into(),
+        "[strip]".into(),
+        "-frames:v".into(),
+        "1".into(),
+        "-q:v".into(),
+        "4".into(),
+        "-f".into(),
+        "image2".into(),
+        out.to_string_lossy().into_owned(),
+    ]);
+    args
+}
+
+/// Renders a strip of `frames` evenly spaced frames of the movie at `path`,
+/// each `height` pixels tall (90 by default), or returns the cached one.
+#[tauri::command]
+pub async fn generate_filmstrip(
+    app: AppHandle,
+    path: PathBuf,
+    frames: u32,
+    height: Option<u32>,
+) -> Result<Filmstrip> {
+    if !path.is_file() {
+        return Err(Error::InvalidInput(format!("{} not found", path.display())));
+    }
+    if !(1..=MAX_FRAMES).contains(&frames) {
+        return Err(Error::InvalidInput(format!(
+            "A filmstrip has 1 to {MAX_FRAMES} frames"
+        )));
+    }
+    let height = height.unwrap_or(DEFAULT_HEIGHT).clamp(16, MAX_HEIGHT) & !1;
+
+    let dir = paths::cache_dir(&app)?.join(CACHE_DIR);
+    tokio::fs::create_dir_all(&dir).await?;
+    let key = cache_key(&path, frames, height)?;
+    let out = dir.join(format!("{key}.jpg"));
+
+    let duration = probe_duration(&app, &path).await?;
+    let times = sample_times(duration, frames);
+    let cached = out.is_file();
+    if !cached {
+        // Rendered aside so a strip cut short never looks cached
+        let partial = dir.join(format!("{key}.part.jpg"));
+        let hardware = ffmpeg_args(&path, &times, height, &partial, true);
+        if let Err(err) = frames::run(&app, "ffmpeg", hardware).await {
+            log::debug!(
+                "Hardware decode of {} failed, retrying in software: {err}",
+                path.display()
+            );
+            let software = ffmpeg_args(&path, &times, height, &partial, false);
+            frames::run(&app, "ffmpeg", software).await?;
+        }
+        tokio::fs::rename(&partial, &out).await?;
+    }
+
+    let (width, frame_height) = image::image_dimensions(&out)?;
+    Ok(Filmstrip {
+        path: out,
+        frames,
+        frame_width: width / frames,
+        frame_height,
+        times_seconds: times,
+        cached,
+    })
+}
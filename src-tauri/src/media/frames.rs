@@ -0,0 +1,157 @@
+//! Frame-accurate still extraction through ffmpeg.
+//!
+//! ffmpeg and ffprobe are taken from `ASTRANOTES_FFMPEG_DIR` when set and
+//! from `PATH` otherwise, so studios can point at their pipeline build.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::process::Output;
+
+use crate::color::ColorManager;
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedFrame {
+    pub path: PathBuf,
+    pub frame: u32,
+    pub time_seconds: f64,
+    pub frame_rate: f64,
+}
+
+pub(crate) fn tool(name: &str) -> String {
+    match std::env::var_os("ASTRANOTES_FFMPEG_DIR") {
+        Some(dir) => Path::new(&dir).join(name).to_string_lossy().into_owned(),
+        None => name.to_string(),
+    }
+}
+
+pub(crate) async fn run(app: &AppHandle, name: &str, args: Vec<String>) -> Result<Output> {
+    let output = app
+        .shell()
+        .command(tool(name))
+        .args(args)
+        .output()
+        .await
+        .map_err(|err| Error::Media(format!("Failed to run {name}: {err}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail = stderr.lines().last().unwrap_or("unknown error");
+        return Err(Error::Media(format!("{name} failed: {detail}")));
+    }
+    Ok(output)
+}
+
+/// Reads the frame rate of the first video stream, e.g. `24000/1001`.
+pub(crate) async fn probe_frame_rate(app: &AppHandle, path: &Path) -> Result<f64> {
+    let output = run(
+        app,
+        "ffprobe",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-select_streams".into(),
+            "v:0".into(),
+            "-show_entries".into(),
+            "stream=r_frame_rate".into(),
+            "-of".into(),
+            "default=noprint_wrappers=1:nokey=1".into(),
+            path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    let rate = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let parsed = match rate.split_once('/') {
+        Some((num, den)) => num
+            .parse::<f64>()
+            .ok()
+            .zip(den.parse::<f64>().ok())
+            .map(|(num, den)| num / den),
+        None => rate.parse().ok(),
+    };
+    parsed
+        .filter(|fps| fps.is_finite() && *fps > 0.0)
+        .ok_or_else(|| Error::Media(format!("Unreadable frame rate {rate:?}")))
+}
+
+/// Writes frame `frame` (zero-based) of the movie at `path` to `out_png`,
+/// applying the LUT of `project_id` when one is assigned.
+#[tauri::command]
+pub async fn extract_frame(
+    app: AppHandle,
+    path: PathBuf,
+    frame: u32,
+    out_png: PathBuf,
+    project_id: Option<String>,
+) -> Result<ExtractedFrame> {
+    if !path.is_file() {
+        return Err(Error::InvalidInput(format!("{} not found", path.display())));
+    }
+    if let Some(parent) = out_png.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    // ffmpeg succeeds without writing when seeking past the end, so an older
+    // PNG at the target must not be mistaken for this frame
+    match tokio::fs::remove_file(&out_png).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    let frame_rate = probe_frame_rate(&app, &path).await?;
+    let time_seconds = f64::from(frame) / frame_rate;
+    // Seeking half a frame early keeps float rounding from landing on the
+    // next frame; input seeking with decoding stays frame-accurate
+    let seek = (time_seconds - 0.5 / frame_rate).max(0.0);
+
+    run(
+        &app,
+        "ffmpeg",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-y".into(),
+            "-ss".into(),
+            format!("{seek:.6}"),
+            "-i".into(),
+            path.to_string_lossy().into_owned(),
+            "-frames:v".into(),
+            "1".into(),
+            "-f".into(),
+            "image2".into(),
+            "-c:v".into(),
+            "png".into(),
+            out_png.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    if !out_png.is_file() {
+        return Err(Error::Media(format!(
+            "Frame {frame} is past the end of {}",
+            path.display()
+        )));
+    }
+    if let Some(lut) = app
+        .state::<ColorManager>()
+        .transform_for(project_id.as_deref())?
+    {
+        let out = out_png.clone();
+        tauri::async_runtime::spawn_blocking(move || -> Result<()> {
+            let mut image = image::open(&out)?.into_rgb8();
+            lut.apply_to_image(&mut image);
+            image.save_with_format(&out, image::ImageFormat::Png)?;
+            Ok(())
+        })
+        .await??;
+    }
+
+    Ok(ExtractedFrame {
+        path: out_png,
+        frame,
+        time_seconds,
+        frame_rate,
+    })
+}
@@ -0,0 +1,361 @@
+//! Header metadata for rendered frames (OpenEXR and DPX).
+//!
+//! Only headers are read, so this stays fast on multi-hundred-MB frames. EXR
+//! goes through the `exr` crate; DPX is simple enough to read by offset.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use exr::meta::MetaData;
+use exr::meta::attribute::{AttributeValue, Chromaticities, Text, TimeCode};
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageFormat {
+    Exr,
+    Dpx,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageMetadata {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub pixel_aspect: Option<f32>,
+    pub timecode: Option<String>,
+    pub frame_rate: Option<f64>,
+    /// Frame number from the header, falling back to the `name.1001.exr` file name.
+    pub frame_number: Option<u32>,
+    pub colorspace: Option<String>,
+    pub camera: BTreeMap<String, String>,
+    pub attributes: BTreeMap<String, String>,
+}
+
+fn frame_from_file_name(path: &Path) -> Option<u32> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits: String = stem
+        .chars()
+        .rev()
+        .take_while(char::is_ascii_digit)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    digits.parse().ok()
+}
+
+fn format_timecode(hours: u8, minutes: u8, seconds: u8, frame: u8, drop_frame: bool) -> String {
+    let separator = if drop_frame { ';' } else { ':' };
+    format!("{hours:02}:{minutes:02}:{seconds:02}{separator}{frame:02}")
+}
+
+fn read_exr(path: &Path) -> Result<ImageMetadata> {
+    let meta = MetaData::read_from_file(path, false)
+        .map_err(|err| Error::Media(format!("Invalid EXR header: {err}")))?;
+    let header = meta
+        .headers
+        .first()
+        .ok_or_else(|| Error::Media("EXR file has no layers".into()))?;
+    let shared = &header.shared_attributes;
+    let own = &header.own_attributes;
+    let size = shared.display_window.size;
+
+    let text = |value: &Option<Text>| value.as_ref().map(Text::to_string);
+    let mut attributes = BTreeMap::new();
+    let mut camera = BTreeMap::new();
+    for (key, value) in [
+        ("layerName", text(&own.layer_name)),
+        ("owner", text(&own.owner)),
+        ("comments", text(&own.comments)),
+        ("captureDate", text(&own.capture_date)),
+        ("software", text(&own.software_name)),
+        ("view", text(&own.view_name)),
+    ] {
+        if let Some(value) = value {
+            attributes.insert(key.to_string(), value);
+        }
+    }
+    for (key, value) in [
+        ("focus", own.focus),
+        ("exposure", own.exposure),
+        ("aperture", own.aperture),
+        ("isoSpeed", own.iso_speed),
+    ] {
+        if let Some(value) = value {
+            camera.insert(key.to_string(), value.to_string());
+        }
+    }
+    if let Some(code) = &own.film_key_code {
+        camera.insert(
+            "keyCode".into(),
+            format!(
+                "{:02} {:02} {:06} {:04}",
+                code.film_manufacturer_code, code.film_type, code.film_roll_prefix, code.count
+            ),
+        );
+    }
+
+    let mut custom_colorspace = None;
+    for (key, value) in shared.other.iter().chain(&own.other) {
+        let key = key.to_string();
+        let Some(value) = exr_value(value) else {
+            continue;
+        };
+        let lower = key.to_ascii_lowercase();
+        if lower.contains("colorspace") || lower.contains("colorinterop") {
+            custom_colorspace.get_or_insert_with(|| value.clone());
+        }
+        if lower.contains("camera") || lower.contains("lens") {
+            camera.insert(key, value);
+        } else {
+            attributes.insert(key, value);
+        }
+    }
+
+    let frame_rate = own
+        .frames_per_second
+        .filter(|(_, den)| *den > 0)
+        .map(|(num, den)| f64::from(num) / f64::from(den));
+
+    Ok(ImageMetadata {
+        format: ImageFormat::Exr,
+        width: size.width() as u32,
+        height: size.height() as u32,
+        pixel_aspect: Some(shared.pixel_aspect),
+        timecode: shared.time_code.as_ref().map(exr_timecode),
+        frame_rate,
+        frame_number: frame_from_file_name(path),
+        colorspace: custom_colorspace.or_else(|| Some(exr_primaries(shared.chromaticities))),
+        camera,
+        attributes,
+    })
+}
+
+fn exr_timecode(code: &TimeCode) -> String {
+    format_timecode(
+        code.hours,
+        code.minutes,
+        code.seconds,
+        code.frame,
+        code.drop_frame,
+    )
+}
+
+fn exr_value(value: &AttributeValue) -> Option<String> {
+    match value {
+        AttributeValue::Text(text) => Some(text.to_string()),
+        AttributeValue::TextVector(texts) => Some(
+            texts
+                .iter()
+                .map(Text::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        AttributeValue::F32(value) => Some(value.to_string()),
+        AttributeValue::F64(value) => Some(value.to_string()),
+        AttributeValue::I32(value) => Some(value.to_string()),
+        AttributeValue::Rational((num, den)) => Some(format!("{num}/{den}")),
+        AttributeValue::TimeCode(code) => Some(exr_timecode(code)),
+        _ => None,
+    }
+}
+
+/// Names well-known primaries; EXR defaults to linear Rec.709 when unset.
+fn exr_primaries(chromaticities: Option<Chromaticities>) -> String {
+    let Some(chroma) = chromaticities else {
+        return "Linear Rec.709".into();
+    };
+    let close = |a: f32, b: f32| (a - b).abs() < 0.002;
+    let red = (chroma.red.x(), chroma.red.y());
+    let name = if close(red.0, 0.64) && close(red.1, 0.33) {
+        "Linear Rec.709"
+    } else if close(red.0, 0.7347) && close(red.1, 0.2653) {
+        "ACES2065-1 (AP0)"
+    } else if close(red.0, 0.713) && close(red.1, 0.293) {
+        "ACEScg (AP1)"
+    } else if close(red.0, 0.708) && close(red.1, 0.292) {
+        "Linear Rec.2020"
+    } else if close(red.0, 0.68) && close(red.1, 0.32) {
+        "Linear P3"
+    } else {
+        return format!(
+            "Custom primaries (R {:.4},{:.4} G {:.4},{:.4} B {:.4},{:.4} W {:.4},{:.4})",
+            red.0,
+            red.1,
+            chroma.green.x(),
+            chroma.green.y(),
+            chroma.blue.x(),
+            chroma.blue.y(),
+            chroma.white.x(),
+            chroma.white.y()
+        );
+    };
+    name.into()
+}
+
+/// Generic, industry and TV headers of a SMPTE 268M file.
+const DPX_HEADER_LEN: usize = 2048;
+
+struct DpxHeader {
+    bytes: Vec<u8>,
+    big_endian: bool,
+}
+
+impl DpxHeader {
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let raw: [u8; 4] = self.bytes.get(offset..offset + 4)?.try_into().ok()?;
+        let value = if self.big_endian {
+            u32::from_be_bytes(raw)
+        } else {
+            u32::from_le_bytes(raw)
+        };
+        // All-ones marks an undefined field
+        (value != u32::MAX).then_some(value)
+    }
+
+    fn f32(&self, offset: usize) -> Option<f32> {
+        let bits = self.u32(offset)?;
+        Some(f32::from_bits(bits)).filter(|value| value.is_finite() && *value > 0.0)
+    }
+
+    fn u8(&self, offset: usize) -> Option<u8> {
+        self.bytes
+            .get(offset)
+            .copied()
+            .filter(|value| *value != u8::MAX)
+    }
+
+    fn text(&self, offset: usize, len: usize) -> Option<String> {
+        let raw = self.bytes.get(offset..offset + len)?;
+        let end = raw.iter().position(|byte| *byte == 0).unwrap_or(len);
+        let value = String::from_utf8_lossy(&raw[..end]).trim().to_string();
+        (!value.is_empty()).then_some(value)
+    }
+}
+
+fn read_dpx(path: &Path) -> Result<ImageMetadata> {
+    let mut bytes = Vec::with_capacity(DPX_HEADER_LEN);
+    File::open(path)?
+        .take(DPX_HEADER_LEN as u64)
+        .read_to_end(&mut bytes)?;
+    let big_endian = match bytes.get(0..4) {
+        Some(b"SDPX") => true,
+        Some(b"XPDS") => false,
+        _ => return Err(Error::Media("Not a DPX file".into())),
+    };
+    if bytes.len() < DPX_HEADER_LEN {
+        return Err(Error::Media("Truncated DPX header".into()));
+    }
+    let header = DpxHeader { bytes, big_endian };
+
+    let mut attributes = BTreeMap::new();
+    for (key, offset, len) in [
+        ("fileName", 36, 100),
+        ("creationTime", 136, 24),
+        ("creator", 160, 100),
+        ("project", 260, 200),
+        ("copyright", 460, 200),
+        ("sourceFileName", 1432, 100),
+        ("sourceTime", 1532, 24),
+        ("filmFormat", 1680, 32),
+        ("frameId", 1732, 32),
+        ("slate", 1764, 100),
+    ] {
+        if let Some(value) = header.text(offset, len) {
+            attributes.insert(key.to_string(), value);
+        }
+    }
+    let mut camera = BTreeMap::new();
+    for (key, offset, len) in [("inputDevice", 1556, 32), ("inputSerial", 1588, 32)] {
+        if let Some(value) = header.text(offset, len) {
+            camera.insert(key.to_string(), value);
+        }
+    }
+    if let Some(angle) = header.f32(1728) {
+        camera.insert("shutterAngle".into(), angle.to_string());
+    }
+
+    // The TV header frame rate is preferred; film scans often only fill the film header
+    let frame_rate = header.f32(1940).or_else(|| header.f32(1724)).map(f64::from);
+    let pixel_aspect = header
+        .u32(1628)
+        .zip(header.u32(1632))
+        .filter(|(_, vertical)| *vertical > 0)
+        .map(|(horizontal, vertical)| horizontal as f32 / vertical as f32);
+
+    Ok(ImageMetadata {
+        format: ImageFormat::Dpx,
+        width: header.u32(772).unwrap_or_default(),
+        height: header.u32(776).unwrap_or_default(),
+        pixel_aspect,
+        timecode: header.u32(1920).map(dpx_timecode),
+        frame_rate,
+        frame_number: header.u32(1712).or_else(|| frame_from_file_name(path)),
+        // First image element: five 4-byte fields and the descriptor precede it
+        colorspace: header.u8(780 + 21).map(dpx_transfer).map(str::to_string),
+        camera,
+        attributes,
+    })
+}
+
+/// Decodes the SMPTE 12M BCD timecode word.
+fn dpx_timecode(word: u32) -> String {
+    let bcd = |shift: u32, mask: u32| {
+        let byte = (word >> shift) & mask;
+        ((byte >> 4) * 10 + (byte & 0x0F)) as u8
+    };
+    // Bit 6 of the frames byte is the drop-frame flag
+    let drop_frame = word & 0x40 != 0;
+    format_timecode(
+        bcd(24, 0x3F),
+        bcd(16, 0x7F),
+        bcd(8, 0x7F),
+        bcd(0, 0x3F),
+        drop_frame,
+    )
+}
+
+fn dpx_transfer(code: u8) -> &'static str {
+    match code {
+        1 => "Printing density",
+        2 => "Linear",
+        3 => "Logarithmic",
+        4 => "Unspecified video",
+        5 => "SMPTE 274M",
+        6 => "Rec.709",
+        7 => "Rec.601 (625 line)",
+        8 => "Rec.601 (525 line)",
+        9 => "NTSC composite",
+        10 => "PAL composite",
+        11 => "Z linear",
+        12 => "Z homogeneous",
+        13 => "ADX",
+        _ => "User defined",
+    }
+}
+
+pub(crate) fn read(path: &Path) -> Result<ImageMetadata> {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match ext.as_deref() {
+        Some("exr") => read_exr(path),
+        Some("dpx") => read_dpx(path),
+        _ => Err(Error::InvalidInput(format!(
+            "Unsupported image format: {}",
+            path.display()
+        ))),
+    }
+}
+
+#[tauri::command]
+pub async fn read_image_metadata(path: PathBuf) -> Result<ImageMetadata> {
+    tauri::async_runtime::spawn_blocking(move || read(&path)).await?
+}
@@ -0,0 +1,248 @@
+//! Local review media: the `astra-media://` protocol, ffmpeg helpers,
+//! filmstrip previews, image header parsing, sequence detection and
+//! scanning, still comparison and duplicate detection.
+//!
+//! The protocol serves cached files with Range support. Files are registered
+//! explicitly and addressed by an opaque token, so the webview can stream and
+//! seek local movies without widening the asset scope.
+
+pub mod compare;
+pub mod filmstrip;
+pub mod frames;
+pub mod metadata;
+pub mod sequence;
+pub mod sequence_scan;
+pub mod similarity;
+
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::http::{Request, Response, StatusCode, header};
+use tauri::{AppHandle, Manager, State, UriSchemeContext, UriSchemeResponder, Wry};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::downloads::DownloadManager;
+use crate::error::{Error, Result};
+use crate::paths;
+
+pub const SCHEME: &str = "astra-media";
+
+/// Upper bound for one response; the player requests the next range as it plays.
+const MAX_CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Default)]
+pub struct MediaRegistry {
+    files: Mutex<HashMap<String, PathBuf>>,
+}
+
+impl MediaRegistry {
+    fn resolve(&self, token: &str) -> Option<PathBuf> {
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(token)
+            .cloned()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaSource {
+    pub token: String,
+    pub url: String,
+}
+
+fn media_url(token: &str) -> String {
+    // Windows webviews only allow custom schemes through the http://<scheme>.localhost form
+    if cfg!(windows) {
+        format!("http://{SCHEME}.localhost/{token}")
+    } else {
+        format!("{SCHEME}://localhost/{token}")
+    }
+}
+
+/// Only files the app itself wrote may be served: anything under the app
+/// cache or data directories, or the destination of a completed download.
+fn is_servable(app: &AppHandle, path: &Path) -> bool {
+    let roots = [paths::cache_dir(app), paths::data_dir(app)];
+    let in_app_dir = roots
+        .into_iter()
+        .flatten()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| path.starts_with(root));
+    in_app_dir || app.state::<DownloadManager>().is_completed(path)
+}
+
+pub(crate) fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match ext.as_deref() {
+        Some("mp4" | "m4v") => "video/mp4",
+        Some("mov") => "video/quicktime",
+        Some("webm") => "video/webm",
+        Some("mkv") => "video/x-matroska",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Parses a single `bytes=` range into an inclusive `(start, end)` pair.
+/// Returns `Err(())` when the range cannot be satisfied for `len` bytes.
+fn parse_range(value: &str, len: u64) -> std::result::Result<(u64, u64), ()> {
+    let spec = value.trim().strip_prefix("bytes=").ok_or(())?;
+    // Multi-range requests are answered with the first range only
+    let spec = spec.split(',').next().ok_or(())?.trim();
+    let (start, end) = spec.split_once('-').ok_or(())?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().map_err(|_| ())?;
+            if suffix == 0 {
+                return Err(());
+            }
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        (start, "") => (start.parse().map_err(|_| ())?, len.saturating_sub(1)),
+        (start, end) => {
+            let end: u64 = end.parse().map_err(|_| ())?;
+            (
+                start.parse().map_err(|_| ())?,
+                end.min(len.saturating_sub(1)),
+            )
+        }
+    };
+    if start >= len || start > end {
+        return Err(());
+    }
+    Ok((start, end))
+}
+
+fn empty(status: StatusCode) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .body(Vec::new())
+        .unwrap_or_default()
+}
+
+async fn serve(app: &AppHandle, request: &Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+    let token = request.uri().path().trim_start_matches('/');
+    let Some(path) = app.state::<MediaRegistry>().resolve(token) else {
+        return Ok(empty(StatusCode::NOT_FOUND));
+    };
+
+    let mut file = tokio::fs::File::open(&path).await?;
+    let len = file.metadata().await?.len();
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+
+    let (status, start, end) = match range {
+        Some(range) => match parse_range(range, len) {
+            Ok((start, end)) => {
+                let end = end.min(start + MAX_CHUNK_BYTES - 1);
+                (StatusCode::PARTIAL_CONTENT, start, end)
+            }
+            Err(()) => {
+                return Ok(Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+                    .body(Vec::new())
+                    .unwrap_or_default());
+            }
+        },
+        // Small files are returned whole; larger ones start as a first chunk
+        // so players switch to range requests
+        None if len <= MAX_CHUNK_BYTES => (StatusCode::OK, 0, len.saturating_sub(1)),
+        None => (StatusCode::PARTIAL_CONTENT, 0, MAX_CHUNK_BYTES - 1),
+    };
+
+    let mut body = vec![
+        0;
+        if len == 0 {
+            0
+        } else {
+            (end - start + 1) as usize
+        }
+    ];
+    file.seek(SeekFrom::Start(start)).await?;
+    file.read_exact(&mut body).await?;
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type(&path))
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_LENGTH, body.len());
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"));
+    }
+    Ok(response.body(body).unwrap_or_default())
+}
+
+/// Protocol handler passed to `register_asynchronous_uri_scheme_protocol`.
+pub fn handle(
+    ctx: UriSchemeContext<'_, Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let response = serve(&app, &request).await.unwrap_or_else(|err| {
+            log::warn!("Failed to serve {}: {err}", request.uri());
+            empty(StatusCode::INTERNAL_SERVER_ERROR)
+        });
+        responder.respond(response);
+    });
+}
+
+/// Registers a local file for playback and returns the URL to load it from.
+#[tauri::command]
+pub fn register_media(
+    app: AppHandle,
+    registry: State<'_, MediaRegistry>,
+    path: PathBuf,
+) -> Result<MediaSource> {
+    let path = path.canonicalize()?;
+    if !path.is_file() || !is_servable(&app, &path) {
+        return Err(Error::InvalidInput(format!(
+            "{} is not cached media",
+            path.display()
+        )));
+    }
+
+    let mut files = registry
+        .files
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let token = files
+        .iter()
+        .find_map(|(token, registered)| (*registered == path).then(|| token.clone()))
+        .unwrap_or_else(|| {
+            let token = uuid::Uuid::new_v4().simple().to_string();
+            files.insert(token.clone(), path);
+            token
+        });
+    Ok(MediaSource {
+        url: media_url(&token),
+        token,
+    })
+}
+
+#[tauri::command]
+pub fn unregister_media(registry: State<'_, MediaRegistry>, token: String) -> bool {
+    registry
+        .files
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .remove(&token)
+        .is_some()
+}
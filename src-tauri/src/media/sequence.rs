@@ -0,0 +1,160 @@
+//! Collapsing numbered frames into image sequences.
+//!
+//! `shot_v001.1001.exr` through `shot_v001.1500.exr` become one sequence with
+//! the printf-style pattern `shot_v001.%04d.exr`. Only still formats are
+//! considered, and the frame number must be the last run of digits in the
+//! name, set off by `.`, `_` or `-`, so `shot_v001.exr` next to
+//! `shot_v002.exr` stays two files rather than a two-frame sequence.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// Listing every gap in a badly broken render is not useful past this.
+const MAX_MISSING: usize = 1000;
+
+pub(crate) const STILL_EXTENSIONS: &[&str] = &[
+    "exr", "dpx", "cin", "tif", "tiff", "png", "jpg", "jpeg", "tga", "jp2", "sgi", "rgb", "hdr",
+    "webp",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSequence {
+    pub directory: PathBuf,
+    /// File name pattern with the frame number as `%0Nd`, or `%d` when unpadded.
+    pub pattern: String,
+    /// Name without frame number or extension, e.g. `shot_v001`.
+    pub name: String,
+    pub first_frame: u32,
+    pub last_frame: u32,
+    pub frame_count: usize,
+    pub padding: usize,
+    /// Frames absent between the first and last, up to a limit.
+    pub missing_frames: Vec<u32>,
+    pub first_path: PathBuf,
+}
+
+impl FileSequence {
+    /// Path of `frame`, whether or not it exists.
+    pub fn frame_path(&self, frame: u32) -> PathBuf {
+        let (prefix, rest) = self.pattern.split_once('%').unwrap_or((&self.pattern, ""));
+        let suffix = rest.split_once('d').map_or("", |(_, suffix)| suffix);
+        self.directory.join(format!(
+            "{prefix}{frame:0width$}{suffix}",
+            width = self.padding
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    directory: PathBuf,
+    prefix: String,
+    suffix: String,
+}
+
+struct Frame {
+    number: u32,
+    digits: usize,
+    path: PathBuf,
+}
+
+pub(crate) fn is_still(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| STILL_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Splits `name.1001.exr` into `("name.", "1001", ".exr")`.
+fn split_frame(file_name: &str) -> Option<(&str, &str, &str)> {
+    let dot = file_name.rfind('.')?;
+    let (stem, suffix) = file_name.split_at(dot);
+    let digits_start = stem
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_ascii_digit())
+        .last()
+        .map(|(index, _)| index)?;
+    let (prefix, digits) = stem.split_at(digits_start);
+    let separated = prefix.is_empty() || prefix.ends_with(['.', '_', '-']);
+    separated.then_some((prefix, digits, suffix))
+}
+
+/// Groups `paths` into sequences, returning them along with every path
+/// that is not part of one, both in name order.
+pub fn collapse(paths: Vec<PathBuf>) -> (Vec<FileSequence>, Vec<PathBuf>) {
+    let mut groups: BTreeMap<Key, Vec<Frame>> = BTreeMap::new();
+    let mut singles = Vec::new();
+    for path in paths {
+        let parsed = is_still(&path)
+            .then(|| path.file_name().and_then(|name| name.to_str()))
+            .flatten()
+            .and_then(split_frame)
+            .and_then(|(prefix, digits, suffix)| {
+                let number = digits.parse().ok()?;
+                let key = Key {
+                    directory: path.parent()?.to_path_buf(),
+                    prefix: prefix.to_string(),
+                    suffix: suffix.to_string(),
+                };
+                Some((key, number, digits.len()))
+            });
+        match parsed {
+            Some((key, number, digits)) => groups.entry(key).or_default().push(Frame {
+                number,
+                digits,
+                path,
+            }),
+            None => singles.push(path),
+        }
+    }
+
+    let mut sequences = Vec::new();
+    for (key, mut frames) in groups {
+        if frames.len() < 2 {
+            singles.extend(frames.into_iter().map(|frame| frame.path));
+            continue;
+        }
+        frames.sort_by_key(|frame| frame.number);
+        frames.dedup_by_key(|frame| frame.number);
+        let digits = frames[0].digits;
+        // Mixed lengths mean the numbers are not padded, e.g. 998, 999, 1000
+        let padding = if frames.iter().all(|frame| frame.digits == digits) {
+            digits
+        } else {
+            0
+        };
+        let first_frame = frames[0].number;
+        let last_frame = frames[frames.len() - 1].number;
+        let mut missing_frames = Vec::new();
+        for pair in frames.windows(2) {
+            for number in pair[0].number + 1..pair[1].number {
+                if missing_frames.len() == MAX_MISSING {
+                    break;
+                }
+                missing_frames.push(number);
+            }
+        }
+        let spec = if padding > 0 {
+            format!("%0{padding}d")
+        } else {
+            "%d".to_string()
+        };
+        let name = key.prefix.trim_end_matches(['.', '_', '-']).to_string();
+        sequences.push(FileSequence {
+            pattern: format!("{}{spec}{}", key.prefix, key.suffix),
+            name,
+            first_frame,
+            last_frame,
+            frame_count: frames.len(),
+            padding,
+            missing_frames,
+            first_path: frames.swap_remove(0).path,
+            directory: key.directory,
+        });
+    }
+    singles.sort();
+    (sequences, singles)
+}
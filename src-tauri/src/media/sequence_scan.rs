@@ -0,0 +1,342 @@
+//! Scanning directories for image sequences, for the frame browser and for
+//! QC of incoming deliveries.
+//!
+//! Each scan reports every sequence with its frame range, the frames missing
+//! from it and per-sequence size statistics. Frames far smaller than the
+//! rest of their sequence are flagged, since a truncated or black render
+//! usually shows up that way. Scans are cached per directory and a watch on
+//! the directory drops the cached result as soon as anything in it changes,
+//! emitting `sequences:changed` so an open browser can rescan.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use super::sequence::{self, FileSequence};
+use crate::error::{Error, Result};
+use crate::store::now_millis;
+
+pub const CHANGED_EVENT: &str = "sequences:changed";
+
+const MAX_DEPTH: usize = 8;
+const MAX_CACHED: usize = 64;
+/// Frames below this share of their sequence's median size are flagged.
+const UNDERSIZED_RATIO: f64 = 0.5;
+/// Listing every flagged frame in a badly broken render is not useful past this.
+const MAX_FLAGGED: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceStats {
+    #[serde(flatten)]
+    pub sequence: FileSequence,
+    /// Every frame absent from the range, even past the listed ones.
+    pub missing_count: u32,
+    pub total_bytes: u64,
+    pub min_frame_bytes: u64,
+    pub max_frame_bytes: u64,
+    pub median_frame_bytes: u64,
+    /// Zero-byte frames.
+    pub empty_frames: Vec<u32>,
+    /// Frames well below the median size, which often means a bad render.
+    pub undersized_frames: Vec<u32>,
+    /// Newest frame modification, in milliseconds since the epoch.
+    pub modified_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceScan {
+    pub directory: PathBuf,
+    pub recursive: bool,
+    pub sequences: Vec<SequenceStats>,
+    /// Files that are not part of any sequence.
+    pub other_files: Vec<PathBuf>,
+    pub scanned_at: i64,
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Changed {
+    directory: PathBuf,
+    recursive: bool,
+}
+
+struct FileInfo {
+    size: u64,
+    modified: Option<i64>,
+}
+
+/// Lists files under `dir`, skipping hidden ones such as `.DS_Store`.
+fn list_files(
+    dir: &Path,
+    recursive: bool,
+    depth: usize,
+    files: &mut HashMap<PathBuf, FileInfo>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            if recursive && depth + 1 < MAX_DEPTH {
+                list_files(&entry.path(), recursive, depth + 1, files)?;
+            }
+            continue;
+        }
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_millis() as i64);
+        files.insert(
+            entry.path(),
+            FileInfo {
+                size: meta.len(),
+                modified,
+            },
+        );
+    }
+    Ok(())
+}
+
+fn stats(sequence: FileSequence, files: &HashMap<PathBuf, FileInfo>) -> SequenceStats {
+    let frames: Vec<(u32, &FileInfo)> = (sequence.first_frame..=sequence.last_frame)
+        .filter_map(|frame| Some((frame, files.get(&sequence.frame_path(frame))?)))
+        .collect();
+    let mut sizes: Vec<u64> = frames.iter().map(|(_, info)| info.size).collect();
+    sizes.sort_unstable();
+    let median = sizes.get(sizes.len() / 2).copied().unwrap_or_default();
+    let threshold = (median as f64 * UNDERSIZED_RATIO) as u64;
+    let flagged = |test: &dyn Fn(u64) -> bool| {
+        frames
+            .iter()
+            .filter(|(_, info)| test(info.size))
+            .map(|(frame, _)| *frame)
+            .take(MAX_FLAGGED)
+            .collect()
+    };
+    let span = sequence.last_frame - sequence.first_frame + 1;
+    SequenceStats {
+        missing_count: span.saturating_sub(sequence.frame_count as u32),
+        total_bytes: sizes.iter().sum(),
+        min_frame_bytes: sizes.first().copied().unwrap_or_default(),
+        max_frame_bytes: sizes.last().copied().unwrap_or_default(),
+        median_frame_bytes: median,
+        empty_frames: flagged(&|size| size == 0),
+        undersized_frames: flagged(&|size| size > 0 && size < threshold),
+        modified_at: frames.iter().filter_map(|(_, info)| info.modified).max(),
+        sequence,
+    }
+}
+
+fn scan(dir: &Path, recursive: bool) -> Result<SequenceScan> {
+    let mut files = HashMap::new();
+    list_files(dir, recursive, 0, &mut files)?;
+    let (sequences, other_files) = sequence::collapse(files.keys().cloned().collect());
+    Ok(SequenceScan {
+        directory: dir.to_path_buf(),
+        recursive,
+        sequences: sequences
+            .into_iter()
+            .map(|sequence| stats(sequence, &files))
+            .collect(),
+        other_files,
+        scanned_at: now_millis(),
+        cached: false,
+    })
+}
+
+#[derive(Default)]
+struct Scans {
+    scans: Mutex<HashMap<(PathBuf, bool), SequenceScan>>,
+    /// Bumped on every change, so a scan that raced one is not cached.
+    generation: AtomicU64,
+}
+
+impl Scans {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(PathBuf, bool), SequenceScan>> {
+        self.scans
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+struct Watcher {
+    watcher: RecommendedWatcher,
+    /// Watched directories, and whether each watch is recursive.
+    watched: HashMap<PathBuf, bool>,
+}
+
+#[derive(Default)]
+pub struct SequenceScans {
+    scans: Arc<Scans>,
+    watcher: Mutex<Option<Watcher>>,
+}
+
+/// Drops every scan covering a path in `event`.
+fn invalidate(app: &AppHandle, scans: &Scans, event: Event) {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+    scans.generation.fetch_add(1, Ordering::Relaxed);
+    let stale: Vec<(PathBuf, bool)> = {
+        let mut scans = scans.lock();
+        let stale: Vec<_> = scans
+            .keys()
+            .filter(|(dir, recursive)| {
+                event.paths.iter().any(|path| {
+                    if *recursive {
+                        path.starts_with(dir)
+                    } else {
+                        path.parent() == Some(dir.as_path())
+                    }
+                })
+            })
+            .cloned()
+            .collect();
+        for key in &stale {
+            scans.remove(key);
+        }
+        stale
+    };
+    for (directory, recursive) in stale {
+        let changed = Changed {
+            directory,
+            recursive,
+        };
+        if let Err(err) = app.emit(CHANGED_EVENT, changed) {
+            log::warn!("Failed to emit sequence change: {err}");
+        }
+    }
+}
+
+impl SequenceScans {
+    fn watch(&self, app: &AppHandle, dir: &Path, recursive: bool) -> Result<()> {
+        let mut watcher = self
+            .watcher
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let watcher = match &mut *watcher {
+            Some(watcher) => watcher,
+            None => {
+                let (app, scans) = (app.clone(), self.scans.clone());
+                watcher.insert(Watcher {
+                    watcher: notify::recommended_watcher(move |result: notify::Result<Event>| {
+                        match result {
+                            Ok(event) => invalidate(&app, &scans, event),
+                            Err(err) => log::warn!("Sequence watcher error: {err}"),
+                        }
+                    })?,
+                    watched: HashMap::new(),
+                })
+            }
+        };
+        // Watching again replaces the mode, so a recursive watch is kept for
+        // the recursive scan that may still be cached
+        match watcher.watched.get(dir) {
+            Some(true) => return Ok(()),
+            Some(false) if !recursive => return Ok(()),
+            _ => {}
+        }
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watcher.watch(dir, mode)?;
+        watcher.watched.insert(dir.to_path_buf(), recursive);
+        Ok(())
+    }
+
+    fn unwatch(&self, dir: &Path) {
+        if let Some(watcher) = self
+            .watcher
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_mut()
+        {
+            watcher.watched.remove(dir);
+            let _ = watcher.watcher.unwatch(dir);
+        }
+    }
+
+    /// Caches `scan` unless anything changed since `generation`.
+    fn insert(&self, scan: SequenceScan, generation: u64) {
+        let evicted = {
+            let mut scans = self.scans.lock();
+            if self.scans.generation.load(Ordering::Relaxed) != generation {
+                return;
+            }
+            scans.insert((scan.directory.clone(), scan.recursive), scan);
+            let oldest = (scans.len() > MAX_CACHED)
+                .then(|| {
+                    scans
+                        .iter()
+                        .min_by_key(|(_, scan)| scan.scanned_at)
+                        .map(|(key, _)| key.clone())
+                })
+                .flatten();
+            oldest.filter(|key| {
+                scans.remove(key);
+                // The other mode of the same directory may still be cached
+                !scans.keys().any(|(dir, _)| *dir == key.0)
+            })
+        };
+        if let Some((dir, _)) = evicted {
+            self.unwatch(&dir);
+        }
+    }
+}
+
+/// Lists the image sequences in `dir`, and in its subdirectories when
+/// `recursive`, with frame ranges, gaps and size statistics.
+#[tauri::command]
+pub async fn scan_sequences(
+    app: AppHandle,
+    scans: State<'_, SequenceScans>,
+    dir: PathBuf,
+    recursive: Option<bool>,
+) -> Result<SequenceScan> {
+    if !dir.is_dir() {
+        return Err(Error::InvalidInput(format!(
+            "{} is not a directory",
+            dir.display()
+        )));
+    }
+    let dir = dir.canonicalize()?;
+    let recursive = recursive.unwrap_or(false);
+    if let Some(scan) = scans.scans.lock().get(&(dir.clone(), recursive)) {
+        return Ok(SequenceScan {
+            cached: true,
+            ..scan.clone()
+        });
+    }
+
+    // Watched first, so a change during the scan is not missed
+    let generation = scans.scans.generation.load(Ordering::Relaxed);
+    let watched = scans
+        .watch(&app, &dir, recursive)
+        .inspect_err(|err| log::warn!("Not watching {} for sequence changes: {err}", dir.display()))
+        .is_ok();
+    let path = dir.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || scan(&path, recursive)).await?;
+    if watched {
+        // Without a watch, a cached scan could never be invalidated
+        if let Ok(scan) = &result {
+            scans.insert(scan.clone(), generation);
+        }
+    }
+    result
+}
@@ -0,0 +1,176 @@
+//! Perceptual hashes for spotting duplicate or unchanged submissions.
+//!
+//! Each version's cached thumbnail gets a difference hash (gradient between
+//! neighbouring pixels) and a DCT-based perceptual hash. Both are 64-bit, so
+//! similarity is the Hamming distance between them; re-encodes and small
+//! resizes stay within a few bits, while real changes move many.
+
+use std::f32::consts::PI;
+
+use image::DynamicImage;
+use image::imageops::FilterType;
+use rayon::prelude::*;
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::Result;
+use crate::store::Store;
+use crate::store::version_thumbnails::VersionThumbnail;
+use crate::thumbnail_cache::ThumbnailCache;
+
+/// Side of the downscaled image the perceptual hash's DCT runs over.
+const DCT_SIZE: usize = 32;
+/// Side of the low-frequency block kept from the DCT.
+const HASH_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarPair {
+    pub version_a: String,
+    pub version_b: String,
+    pub phash_distance: u32,
+    pub dhash_distance: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarityReport {
+    /// Most similar first.
+    pub pairs: Vec<SimilarPair>,
+    /// Versions skipped because their thumbnail is not cached yet.
+    pub missing: Vec<String>,
+}
+
+fn dhash(image: &DynamicImage) -> u64 {
+    let small = image
+        .resize_exact(HASH_SIZE as u32 + 1, HASH_SIZE as u32, FilterType::Triangle)
+        .into_luma8();
+    let mut hash = 0;
+    for y in 0..HASH_SIZE as u32 {
+        for x in 0..HASH_SIZE as u32 {
+            let brighter = small.get_pixel(x + 1, y)[0] > small.get_pixel(x, y)[0];
+            hash = (hash << 1) | u64::from(brighter);
+        }
+    }
+    hash
+}
+
+fn dct_1d(input: &[f32; DCT_SIZE]) -> [f32; DCT_SIZE] {
+    let mut output = [0.0; DCT_SIZE];
+    for (k, out) in output.iter_mut().enumerate() {
+        *out = input
+            .iter()
+            .enumerate()
+            .map(|(n, value)| value * (PI / DCT_SIZE as f32 * (n as f32 + 0.5) * k as f32).cos())
+            .sum();
+    }
+    output
+}
+
+fn phash(image: &DynamicImage) -> u64 {
+    let small = image
+        .resize_exact(DCT_SIZE as u32, DCT_SIZE as u32, FilterType::Triangle)
+        .into_luma8();
+    let mut rows = [[0.0; DCT_SIZE]; DCT_SIZE];
+    for (y, row) in rows.iter_mut().enumerate() {
+        let mut pixels = [0.0; DCT_SIZE];
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = f32::from(small.get_pixel(x as u32, y as u32)[0]);
+        }
+        *row = dct_1d(&pixels);
+    }
+    // Only the low-frequency columns are needed after the row pass
+    let mut low = [[0.0; HASH_SIZE]; HASH_SIZE];
+    for x in 0..HASH_SIZE {
+        let column = dct_1d(&rows.map(|row| row[x]));
+        for (y, row) in low.iter_mut().enumerate() {
+            row[x] = column[y];
+        }
+    }
+    let coefficients = low.concat();
+
+    // The DC term only reflects overall brightness, so it is left out of the median
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    coefficients
+        .iter()
+        .fold(0, |hash, value| (hash << 1) | u64::from(*value > median))
+}
+
+/// Records which thumbnail belongs to each version of a playlist, replacing
+/// the previous list. Pushed by the frontend as playlists load.
+#[tauri::command]
+pub fn register_playlist_thumbnails(
+    store: State<'_, Store>,
+    playlist_id: String,
+    versions: Vec<VersionThumbnail>,
+) -> Result<()> {
+    store.set_version_thumbnails(&playlist_id, &versions)
+}
+
+/// Pairs of versions in `playlist_id` whose thumbnails' perceptual hashes
+/// differ by at most `threshold` bits out of 64.
+#[tauri::command]
+pub async fn find_similar_versions(
+    store: State<'_, Store>,
+    cache: State<'_, ThumbnailCache>,
+    playlist_id: String,
+    threshold: u32,
+) -> Result<SimilarityReport> {
+    let rows = store.list_version_thumbnails(&playlist_id)?;
+
+    let mut missing = Vec::new();
+    let mut pending = Vec::new();
+    for row in rows.iter().filter(|row| row.hashes.is_none()) {
+        match cache.lookup(&row.component_id)? {
+            Some(thumbnail) => pending.push((row.component_id.clone(), thumbnail.path)),
+            None => missing.push(row.version_id.clone()),
+        }
+    }
+    let computed = tauri::async_runtime::spawn_blocking(move || {
+        pending
+            .into_par_iter()
+            .filter_map(|(component_id, path)| match image::open(&path) {
+                Ok(image) => Some((component_id, (dhash(&image), phash(&image)))),
+                Err(err) => {
+                    log::warn!("Failed to hash thumbnail {}: {err}", path.display());
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await?;
+    for (component_id, (dhash, phash)) in &computed {
+        store.set_thumbnail_hashes(component_id, *dhash, *phash)?;
+    }
+
+    let hashed: Vec<_> = rows
+        .into_iter()
+        .filter_map(|row| {
+            let hashes = row.hashes.or_else(|| {
+                computed
+                    .iter()
+                    .find(|(component_id, _)| *component_id == row.component_id)
+                    .map(|(_, hashes)| *hashes)
+            });
+            hashes.map(|hashes| (row.version_id, hashes))
+        })
+        .collect();
+    let mut pairs = Vec::new();
+    for (index, (version_a, (dhash_a, phash_a))) in hashed.iter().enumerate() {
+        for (version_b, (dhash_b, phash_b)) in &hashed[index + 1..] {
+            let phash_distance = (phash_a ^ phash_b).count_ones();
+            if phash_distance <= threshold {
+                pairs.push(SimilarPair {
+                    version_a: version_a.clone(),
+                    version_b: version_b.clone(),
+                    phash_distance,
+                    dhash_distance: (dhash_a ^ dhash_b).count_ones(),
+                });
+            }
+        }
+    }
+    pairs.sort_by_key(|pair| (pair.phash_distance, pair.dhash_distance));
+    Ok(SimilarityReport { pairs, missing })
+}
@@ -0,0 +1,379 @@
+//! Outbound HTTP configuration shared by every backend request.
+//!
+//! Studios often sit behind an authenticating proxy or a PAC file and
+//! inspect TLS with their own CA, so all clients are built here from
+//! `network.json`: system, direct, manual or PAC proxying, plus extra root
+//! certificates from a PEM bundle. The manual proxy password lives in the
+//! keychain. Callers take [`client`] at the point of use so settings changes
+//! apply without a restart, and WebSockets connect through [`websocket`]. Profiles can additionally pin their server's key,
+//! see [`pinning`].
+
+mod pac;
+pub mod pinning;
+pub(crate) mod websocket;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use reqwest::{Certificate, ClientBuilder, NoProxy, Proxy, Url};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::settings;
+use pac::PacResolver;
+use websocket::Tunnel;
+
+const SETTINGS_FILE: &str = "network.json";
+const PROXY_PASSWORD_KEY: &str = "proxy-password";
+const PAC_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "mode",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ProxySettings {
+    /// Environment variables and the OS proxy configuration.
+    #[default]
+    System,
+    /// Always connect directly, ignoring any system proxy.
+    Direct,
+    Manual {
+        url: String,
+        #[serde(default)]
+        username: Option<String>,
+        /// Comma-separated hosts, domains and CIDR ranges that bypass the proxy.
+        #[serde(default)]
+        no_proxy: Option<String>,
+    },
+    /// A PAC script at an `http(s)://` or `file://` URL, or a local path.
+    Pac { url: String },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkSettings {
+    #[serde(default)]
+    pub proxy: ProxySettings,
+    /// PEM bundle of additional root certificates, e.g. a studio CA.
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+}
+
+#[derive(Clone)]
+enum Route {
+    System,
+    Direct,
+    Manual {
+        proxy: Box<Proxy>,
+        /// The same proxy for WebSocket connections.
+        tunnel: Tunnel,
+        no_proxy: Option<String>,
+        /// The proxy URL as shown in diagnostics, without credentials.
+        display: String,
+    },
+    Pac(Arc<PacResolver>),
+}
+
+#[derive(Clone)]
+struct Config {
+    route: Route,
+    certificates: Vec<Certificate>,
+    ca_pem: Option<Vec<u8>>,
+    /// Set while certificate pins are enforced.
+    tls: Option<rustls::ClientConfig>,
+}
+
+impl Config {
+    fn apply(&self, mut builder: ClientBuilder) -> ClientBuilder {
+        for certificate in &self.certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(tls) = &self.tls {
+            builder = builder.use_preconfigured_tls(tls.clone());
+        }
+        match &self.route {
+            Route::System => builder,
+            Route::Direct => builder.no_proxy(),
+            Route::Manual { proxy, .. } => builder.proxy(Proxy::clone(proxy)),
+            Route::Pac(resolver) => {
+                let resolver = resolver.clone();
+                builder.proxy(Proxy::custom(move |url| resolver.proxy_for(url)))
+            }
+        }
+    }
+
+    /// Where a request to `url` would go, for diagnostics.
+    fn describe(&self, url: &Url) -> String {
+        let proxy = match &self.route {
+            Route::System => return "system".into(),
+            Route::Direct => None,
+            Route::Manual { display, .. } => return display.clone(),
+            Route::Pac(resolver) => resolver.proxy_for(url),
+        };
+        proxy.map_or_else(|| "direct".into(), |proxy| proxy.to_string())
+    }
+}
+
+static CONFIG: LazyLock<RwLock<Arc<Config>>> = LazyLock::new(|| {
+    RwLock::new(Arc::new(Config {
+        route: Route::System,
+        certificates: Vec::new(),
+        ca_pem: None,
+        tls: None,
+    }))
+});
+
+static INSTALL: Mutex<()> = Mutex::new(());
+
+static CLIENT: LazyLock<RwLock<reqwest::Client>> =
+    LazyLock::new(|| RwLock::new(reqwest::Client::new()));
+
+/// The shared client for the current network settings. Cheap to clone.
+pub fn client() -> reqwest::Client {
+    CLIENT
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Applies the current proxy and certificates to a client built elsewhere,
+/// such as the updater's.
+pub fn configure(builder: ClientBuilder) -> ClientBuilder {
+    current_config().apply(builder)
+}
+
+fn current_config() -> Arc<Config> {
+    CONFIG
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+fn load_settings(app: &AppHandle) -> Result<NetworkSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn save_settings(app: &AppHandle, settings: &NetworkSettings) -> Result<()> {
+    paths::save_json(app, SETTINGS_FILE, settings)
+}
+
+fn load_ca_bundle(settings: &NetworkSettings) -> Result<Option<(Vec<u8>, Vec<Certificate>)>> {
+    let Some(path) = &settings.ca_bundle else {
+        return Ok(None);
+    };
+    let pem = fs::read(path)?;
+    let certificates = Certificate::from_pem_bundle(&pem)?;
+    if certificates.is_empty() {
+        return Err(Error::InvalidInput(format!(
+            "No certificates found in {}",
+            path.display()
+        )));
+    }
+    Ok(Some((pem, certificates)))
+}
+
+async fn fetch_pac(location: &str, certificates: &[Certificate]) -> Result<String> {
+    let url = match Url::parse(location) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        Ok(url) if url.scheme() == "file" => {
+            let path = url
+                .to_file_path()
+                .map_err(|()| Error::InvalidInput(format!("Invalid PAC file URL {location}")))?;
+            return Ok(tokio::fs::read_to_string(path).await?);
+        }
+        // Anything else, including Windows drive paths, is a local file
+        _ => return Ok(tokio::fs::read_to_string(location).await?),
+    };
+    // The script decides the proxy, so it has to be reachable without one
+    let mut builder = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(PAC_FETCH_TIMEOUT);
+    for certificate in certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    let response = builder.build()?.get(url).send().await?.error_for_status()?;
+    Ok(response.text().await?)
+}
+
+async fn load_config(settings: &NetworkSettings) -> Result<Config> {
+    let (ca_pem, certificates) = load_ca_bundle(settings)?.unzip();
+    let certificates = certificates.unwrap_or_default();
+    let route = match &settings.proxy {
+        ProxySettings::System => Route::System,
+        ProxySettings::Direct => Route::Direct,
+        ProxySettings::Manual {
+            url,
+            username,
+            no_proxy,
+        } => {
+            let mut proxy = Proxy::all(url.as_str())?;
+            let parsed = Url::parse(url)
+                .map_err(|err| Error::InvalidInput(format!("Invalid proxy URL {url}: {err}")))?;
+            let mut tunnel = Tunnel::new(parsed.clone(), None);
+            if let Some(username) = username.as_deref().filter(|name| !name.is_empty()) {
+                let password = credentials::get(PROXY_PASSWORD_KEY)?.unwrap_or_default();
+                proxy = proxy.basic_auth(username, &password);
+                tunnel = Tunnel::new(parsed.clone(), Some((username, &password)));
+            }
+            if let Some(list) = no_proxy {
+                proxy = proxy.no_proxy(NoProxy::from_string(list));
+            }
+            let mut display = parsed;
+            let _ = display.set_password(None);
+            Route::Manual {
+                proxy: Box::new(proxy),
+                tunnel,
+                no_proxy: no_proxy.clone(),
+                display: display.to_string(),
+            }
+        }
+        ProxySettings::Pac { url } => {
+            let script = fetch_pac(url, &certificates).await?;
+            let resolver =
+                tauri::async_runtime::spawn_blocking(move || PacResolver::new(script)).await??;
+            Route::Pac(Arc::new(resolver))
+        }
+    };
+    Ok(Config {
+        route,
+        certificates,
+        ca_pem,
+        tls: None,
+    })
+}
+
+/// Installs `config`, or rebuilds the current one when `None`, with TLS for
+/// the pins in place at that moment.
+fn install(config: Option<Config>) -> Result<()> {
+    // Serialized so a settings change and a pin change cannot overwrite each
+    // other with a stale view of the pins
+    let _guard = INSTALL
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut config = config.unwrap_or_else(|| Config::clone(&current_config()));
+    config.tls = pinning::tls_config(config.ca_pem.as_deref())?;
+    let client = config.apply(reqwest::Client::builder()).build()?;
+    *CONFIG
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(config);
+    *CLIENT
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = client;
+    Ok(())
+}
+
+/// Rebuilds the shared client after the pinned hosts changed, which also
+/// drops pooled connections verified under the old pins.
+fn refresh_tls() -> Result<()> {
+    install(None)
+}
+
+/// The pin of the key `url` presents, verified as if nothing were pinned.
+async fn observe_pin(url: &str) -> Result<String> {
+    let mut config = Config::clone(&current_config());
+    config.tls = None;
+    let client = config
+        .apply(reqwest::Client::builder())
+        .tls_info(true)
+        .timeout(CHECK_TIMEOUT)
+        .build()?;
+    let response = client.head(url).send().await?;
+    let certificate = response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .ok_or_else(|| Error::InvalidInput(format!("{url} presented no certificate")))?;
+    pinning::spki_pin(certificate)
+}
+
+/// Builds and installs clients for `settings`, leaving the current ones in
+/// place if anything is invalid.
+async fn apply(settings: &NetworkSettings) -> Result<()> {
+    install(Some(load_config(settings).await?))
+}
+
+/// Applies the saved settings in the background. Requests made before they
+/// are in place use the system configuration.
+pub fn init(app: &AppHandle) {
+    let settings = match load_settings(app) {
+        Ok(settings) => settings,
+        Err(err) => {
+            log::error!("Ignoring invalid network settings: {err}");
+            return;
+        }
+    };
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = apply(&settings).await {
+            log::error!("Failed to apply network settings, using system defaults: {err}");
+        }
+    });
+}
+
+/// Applies the settings saved in `config_dir` and waits for them, for the
+/// headless CLI, which has no app handle.
+pub(crate) async fn apply_saved(config_dir: &Path) -> Result<()> {
+    apply(&paths::read_json(&config_dir.join(SETTINGS_FILE))?).await
+}
+
+#[tauri::command]
+pub fn get_network_settings(app: AppHandle) -> Result<NetworkSettings> {
+    load_settings(&app)
+}
+
+/// Validates and applies `settings` before saving them, so a broken proxy or
+/// PAC file is reported instead of cutting the app off.
+#[tauri::command]
+pub async fn set_network_settings(app: AppHandle, settings: NetworkSettings) -> Result<()> {
+    settings::check_locked(SETTINGS_FILE, &settings)?;
+    apply(&settings).await?;
+    save_settings(&app, &settings)
+}
+
+/// Stores the manual proxy password in the keychain, or removes it with `None`.
+#[tauri::command]
+pub async fn set_proxy_password(app: AppHandle, password: Option<String>) -> Result<()> {
+    match password.filter(|password| !password.is_empty()) {
+        Some(password) => credentials::store(PROXY_PASSWORD_KEY, &password)?,
+        None => {
+            credentials::delete(PROXY_PASSWORD_KEY)?;
+        }
+    }
+    apply(&load_settings(&app)?).await
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkCheck {
+    /// `direct`, `system` or the proxy URL the request went through.
+    pub route: String,
+    pub status: u16,
+    pub elapsed_ms: u64,
+}
+
+/// Requests `url` with the current settings, to troubleshoot proxy and CA setup.
+#[tauri::command]
+pub async fn check_network(url: String) -> Result<NetworkCheck> {
+    let parsed =
+        Url::parse(&url).map_err(|err| Error::InvalidInput(format!("Invalid URL {url}: {err}")))?;
+    let config = current_config();
+    let route = tauri::async_runtime::spawn_blocking({
+        let parsed = parsed.clone();
+        move || config.describe(&parsed)
+    })
+    .await?;
+    let started = Instant::now();
+    let response = client().head(parsed).timeout(CHECK_TIMEOUT).send().await?;
+    Ok(NetworkCheck {
+        route,
+        status: response.status().as_u16(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
@@ -0,0 +1,313 @@
+//! Proxy auto-config (PAC) evaluation.
+//!
+//! The script runs in an embedded JavaScript engine on its own thread, since
+//! the engine is not `Send`. Results are cached per scheme and host, so a
+//! script is evaluated once per origin rather than once per request. reqwest
+//! asks for the proxy from inside the async runtime, so a cache miss hands
+//! its worker's other tasks to the rest of the runtime while it waits.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use boa_engine::prelude::*;
+use boa_engine::{JsArgs, JsResult, NativeFunction, Source, js_string};
+use reqwest::Url;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::error::{Error, Result};
+
+/// How long a request waits on the script before going direct.
+const EVALUATE_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_CACHED_HOSTS: usize = 1024;
+
+/// The standard PAC helpers that need no native support.
+const HELPERS: &str = r#"
+function isPlainHostName(host) {
+    return host.indexOf('.') < 0;
+}
+function dnsDomainIs(host, domain) {
+    host = host.toLowerCase();
+    domain = domain.toLowerCase();
+    return host.length >= domain.length
+        && host.substring(host.length - domain.length) === domain;
+}
+function localHostOrDomainIs(host, hostdom) {
+    return host === hostdom || hostdom.lastIndexOf(host + '.', 0) === 0;
+}
+function isResolvable(host) {
+    return dnsResolve(host) !== null;
+}
+function dnsDomainLevels(host) {
+    return host.split('.').length - 1;
+}
+function convertAddr(ip) {
+    var parts = ip.split('.');
+    return ((parts[0] << 24) | (parts[1] << 16) | (parts[2] << 8) | parts[3]) >>> 0;
+}
+function isInNet(host, pattern, mask) {
+    var ip = /^\d+\.\d+\.\d+\.\d+$/.test(host) ? host : dnsResolve(host);
+    if (!ip) {
+        return false;
+    }
+    var bits = convertAddr(mask);
+    return ((convertAddr(ip) & bits) >>> 0) === ((convertAddr(pattern) & bits) >>> 0);
+}
+function shExpMatch(str, shexp) {
+    var pattern = shexp
+        .replace(/[.+^${}()|[\]\\]/g, '\\$&')
+        .replace(/\*/g, '.*')
+        .replace(/\?/g, '.');
+    return new RegExp('^' + pattern + '$').test(str);
+}
+var PAC_DAYS = ['SUN', 'MON', 'TUE', 'WED', 'THU', 'FRI', 'SAT'];
+function weekdayRange(wd1, wd2, gmt) {
+    if (wd2 === 'GMT') {
+        gmt = wd2;
+        wd2 = undefined;
+    }
+    var now = new Date();
+    var day = gmt === 'GMT' ? now.getUTCDay() : now.getDay();
+    var start = PAC_DAYS.indexOf(wd1);
+    var end = wd2 === undefined ? start : PAC_DAYS.indexOf(wd2);
+    return start <= end ? day >= start && day <= end : day >= start || day <= end;
+}
+function timeRange() {
+    var args = Array.prototype.slice.call(arguments);
+    var gmt = args[args.length - 1] === 'GMT';
+    if (gmt) {
+        args.pop();
+    }
+    var now = new Date();
+    var hour = gmt ? now.getUTCHours() : now.getHours();
+    var minute = gmt ? now.getUTCMinutes() : now.getMinutes();
+    var second = gmt ? now.getUTCSeconds() : now.getSeconds();
+    var current = hour * 3600 + minute * 60 + second;
+    var start, end;
+    if (args.length === 1) {
+        return hour === args[0];
+    } else if (args.length === 2) {
+        start = args[0] * 3600;
+        end = args[1] * 3600;
+    } else if (args.length === 4) {
+        start = args[0] * 3600 + args[1] * 60;
+        end = args[2] * 3600 + args[3] * 60;
+    } else if (args.length === 6) {
+        start = args[0] * 3600 + args[1] * 60 + args[2];
+        end = args[3] * 3600 + args[4] * 60 + args[5];
+    } else {
+        return false;
+    }
+    return start <= end ? current >= start && current < end : current >= start || current < end;
+}
+// Date ranges are rare in studio PAC files and are treated as always matching
+function dateRange() {
+    return true;
+}
+"#;
+
+struct Request {
+    url: String,
+    host: String,
+    reply: mpsc::Sender<String>,
+}
+
+/// A loaded PAC script, shared by every client built from the same settings.
+pub struct PacResolver {
+    requests: Mutex<mpsc::Sender<Request>>,
+    cache: Mutex<HashMap<String, Option<Url>>>,
+}
+
+impl PacResolver {
+    /// Starts the script thread, failing if the script does not evaluate or
+    /// defines no `FindProxyForURL`.
+    pub fn new(script: String) -> Result<Self> {
+        let (requests, receiver) = mpsc::channel::<Request>();
+        let (ready, loaded) = mpsc::channel();
+        thread::Builder::new().name("pac".into()).spawn(move || {
+            let mut context = match load(&script) {
+                Ok(context) => {
+                    let _ = ready.send(Ok(()));
+                    context
+                }
+                Err(err) => {
+                    let _ = ready.send(Err(err));
+                    return;
+                }
+            };
+            for request in receiver {
+                let result =
+                    find_proxy(&mut context, &request.url, &request.host).unwrap_or_else(|err| {
+                        log::warn!("PAC script failed for {}: {err}", request.host);
+                        "DIRECT".to_string()
+                    });
+                let _ = request.reply.send(result);
+            }
+        })?;
+        loaded
+            .recv()
+            .map_err(|_| Error::InvalidInput("PAC script thread exited".into()))??;
+        Ok(Self {
+            requests: Mutex::new(requests),
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The proxy for `url`, or `None` to connect directly.
+    pub fn proxy_for(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?;
+        let key = format!("{}://{host}", url.scheme());
+        if let Some(cached) = self
+            .cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&key)
+        {
+            return cached.clone();
+        }
+        let evaluate = || self.evaluate(url, host, key);
+        match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(evaluate)
+            }
+            _ => evaluate(),
+        }
+    }
+
+    /// Runs the script for `url` and caches the result under `key`.
+    fn evaluate(&self, url: &Url, host: &str, key: String) -> Option<Url> {
+        let (reply, result) = mpsc::channel();
+        let request = Request {
+            url: url.to_string(),
+            host: host.to_string(),
+            reply,
+        };
+        let sent = self
+            .requests
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .send(request);
+        let proxy = match sent
+            .ok()
+            .and_then(|()| result.recv_timeout(EVALUATE_TIMEOUT).ok())
+        {
+            Some(result) => parse_result(&result),
+            None => {
+                log::warn!("PAC script did not answer for {host}, connecting directly");
+                return None;
+            }
+        };
+
+        let mut cache = self
+            .cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if cache.len() >= MAX_CACHED_HOSTS {
+            cache.clear();
+        }
+        cache.insert(key, proxy.clone());
+        proxy
+    }
+}
+
+fn load(script: &str) -> Result<Context> {
+    let mut context = Context::default();
+    let natives: [(JsString, NativeFunctionPointer); 2] = [
+        (js_string!("dnsResolve"), dns_resolve),
+        (js_string!("myIpAddress"), my_ip_address),
+    ];
+    for (name, function) in natives {
+        context
+            .register_global_callable(name, 0, NativeFunction::from_fn_ptr(function))
+            .map_err(script_error)?;
+    }
+    for source in [HELPERS, script] {
+        context
+            .eval(Source::from_bytes(source))
+            .map_err(script_error)?;
+    }
+    let defined = context
+        .eval(Source::from_bytes("typeof FindProxyForURL === 'function'"))
+        .map_err(script_error)?;
+    if !defined.to_boolean() {
+        return Err(Error::InvalidInput(
+            "PAC script does not define FindProxyForURL".into(),
+        ));
+    }
+    Ok(context)
+}
+
+type NativeFunctionPointer = fn(&JsValue, &[JsValue], &mut Context) -> JsResult<JsValue>;
+
+fn script_error(err: JsError) -> Error {
+    Error::InvalidInput(format!("PAC script error: {err}"))
+}
+
+fn find_proxy(context: &mut Context, url: &str, host: &str) -> Result<String> {
+    // JSON string literals are valid JavaScript string literals
+    let call = format!(
+        "FindProxyForURL({}, {})",
+        serde_json::to_string(url)?,
+        serde_json::to_string(host)?
+    );
+    let result = context
+        .eval(Source::from_bytes(&call))
+        .map_err(script_error)?;
+    let result = result.to_string(context).map_err(script_error)?;
+    Ok(result.to_std_string_escaped())
+}
+
+/// Takes the first usable entry of a result like `PROXY a:8080; DIRECT`.
+fn parse_result(result: &str) -> Option<Url> {
+    for entry in result
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let (kind, address) = entry.split_once(char::is_whitespace).unwrap_or((entry, ""));
+        let scheme = match kind.to_ascii_uppercase().as_str() {
+            "DIRECT" => return None,
+            "PROXY" | "HTTP" => "http",
+            "HTTPS" => "https",
+            _ => {
+                log::debug!("Skipping unsupported PAC entry {entry}");
+                continue;
+            }
+        };
+        match Url::parse(&format!("{scheme}://{}", address.trim())) {
+            Ok(url) => return Some(url),
+            Err(err) => log::warn!("Skipping invalid PAC entry {entry}: {err}"),
+        }
+    }
+    None
+}
+
+fn dns_resolve(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let host = args
+        .get_or_undefined(0)
+        .to_string(context)?
+        .to_std_string_escaped();
+    let address = (host.as_str(), 0)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.find(|address| address.is_ipv4()));
+    Ok(match address {
+        Some(address) => JsValue::from(JsString::from(address.ip().to_string().as_str())),
+        None => JsValue::null(),
+    })
+}
+
+fn my_ip_address(_: &JsValue, _: &[JsValue], _: &mut Context) -> JsResult<JsValue> {
+    // Connecting a UDP socket sends nothing; it only picks the outbound interface
+    let address = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("8.8.8.8:80")?;
+            socket.local_addr()
+        })
+        .map(|address| address.ip())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]));
+    Ok(JsValue::from(JsString::from(address.to_string().as_str())))
+}
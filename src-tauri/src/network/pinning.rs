@@ -0,0 +1,265 @@
+//! Public key pinning for tracker servers.
+//!
+//! A profile's pin is the SHA-256 of its server's SubjectPublicKeyInfo,
+//! recorded on first use. While any pin is set, the shared client verifies
+//! TLS with rustls: pinned hosts are accepted only when the presented key
+//! matches, before any request (and API key) is sent, and other hosts are
+//! checked against the bundled Mozilla roots plus the configured CA bundle.
+//! WebSocket connections always verify this way, pinned or not.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::Url;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::store::Store;
+use crate::store::profiles::Profile;
+
+const PIN_PREFIX: &str = "sha256/";
+
+/// Accepted pins by lowercase host name.
+static PINS: LazyLock<RwLock<HashMap<String, HashSet<String>>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+fn host_of(server_url: &str) -> Result<String> {
+    let url = Url::parse(server_url)
+        .map_err(|err| Error::InvalidInput(format!("Invalid server URL {server_url}: {err}")))?;
+    if url.scheme() != "https" {
+        return Err(Error::InvalidInput(format!(
+            "Only https servers can be pinned, not {server_url}"
+        )));
+    }
+    url.host_str()
+        .map(str::to_ascii_lowercase)
+        .ok_or_else(|| Error::InvalidInput(format!("No host in {server_url}")))
+}
+
+/// The pin for a DER-encoded end-entity certificate.
+pub(crate) fn spki_pin(certificate: &[u8]) -> Result<String> {
+    let der = CertificateDer::from(certificate);
+    let parsed = webpki::EndEntityCert::try_from(&der)
+        .map_err(|err| Error::InvalidInput(format!("Unreadable server certificate: {err}")))?;
+    let digest = Sha256::digest(parsed.subject_public_key_info().as_ref());
+    Ok(format!("{PIN_PREFIX}{}", STANDARD.encode(digest)))
+}
+
+/// Whether any host is pinned, i.e. clients need the pinning verifier.
+pub(super) fn active() -> bool {
+    !PINS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .is_empty()
+}
+
+#[derive(Debug)]
+struct PinningVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let host = server_name.to_str().to_ascii_lowercase();
+        let pins = PINS.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(expected) = pins.get(&host) else {
+            return self.inner.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            );
+        };
+        let pin = spki_pin(end_entity)
+            .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        if expected.contains(&pin) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            log::error!("Certificate pin mismatch for {host}: server presented {pin}");
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// TLS configuration enforcing the current pins, or `None` when nothing is
+/// pinned and the platform TLS stack can be used as before.
+pub(super) fn tls_config(ca_pem: Option<&[u8]>) -> Result<Option<ClientConfig>> {
+    if !active() {
+        return Ok(None);
+    }
+    let mut config = verifying_config(ca_pem)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Some(config))
+}
+
+/// TLS configuration for WebSocket connections, which cannot use the
+/// platform stack and so always verify with rustls, pins included.
+pub(super) fn websocket_tls_config(ca_pem: Option<&[u8]>) -> Result<ClientConfig> {
+    verifying_config(ca_pem)
+}
+
+/// Pins where set, otherwise the bundled roots plus the CA bundle.
+fn verifying_config(ca_pem: Option<&[u8]>) -> Result<ClientConfig> {
+    let tls_error =
+        |err: &dyn std::fmt::Display| Error::InvalidInput(format!("TLS setup failed: {err}"));
+
+    let mut roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    if let Some(pem) = ca_pem {
+        for certificate in CertificateDer::pem_slice_iter(pem) {
+            let certificate = certificate.map_err(|err| tls_error(&err))?;
+            roots.add(certificate).map_err(|err| tls_error(&err))?;
+        }
+    }
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(|err| tls_error(&err))?;
+    let verifier = PinningVerifier {
+        inner,
+        algorithms: provider.signature_verification_algorithms,
+    };
+    Ok(ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| tls_error(&err))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth())
+}
+
+/// Loads every profile's pin and rebuilds the shared client to enforce them.
+pub fn reload(store: &Store) -> Result<()> {
+    let mut pins: HashMap<String, HashSet<String>> = HashMap::new();
+    for profile in store.list_profiles()? {
+        let Some(pin) = profile.certificate_pin else {
+            continue;
+        };
+        match host_of(&profile.server_url) {
+            Ok(host) => {
+                pins.entry(host).or_default().insert(pin);
+            }
+            Err(err) => log::warn!("Ignoring pin of profile {}: {err}", profile.id),
+        }
+    }
+    *PINS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = pins;
+    super::refresh_tls()
+}
+
+pub fn init(app: &AppHandle) {
+    if let Err(err) = reload(&app.state::<Store>()) {
+        log::error!("Failed to load certificate pins: {err}");
+    }
+}
+
+fn profile(store: &Store, profile_id: &str) -> Result<Profile> {
+    store
+        .get_profile(profile_id)?
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown profile {profile_id}")))
+}
+
+/// Connects to the profile's server and records its key (trust on first
+/// use). The connection is verified as usual, without credentials. Returns
+/// the recorded pin.
+#[tauri::command]
+pub async fn pin_profile_certificate(
+    store: State<'_, Store>,
+    profile_id: String,
+) -> Result<String> {
+    let profile = profile(&store, &profile_id)?;
+    if let Some(pin) = profile.certificate_pin {
+        return Err(Error::InvalidInput(format!(
+            "Profile {} is already pinned to {pin}; clear the pin first",
+            profile.name
+        )));
+    }
+    host_of(&profile.server_url)?;
+    let pin = super::observe_pin(&profile.server_url).await?;
+    store.set_profile_pin(&profile_id, Some(&pin))?;
+    reload(&store)?;
+    log::info!("Pinned {} to {pin}", profile.server_url);
+    Ok(pin)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinCheck {
+    pub pinned: Option<String>,
+    pub presented: String,
+    pub matches: bool,
+}
+
+/// Compares the key the server presents now with the recorded pin, e.g.
+/// after a certificate renewal, without enforcing it.
+#[tauri::command]
+pub async fn verify_profile_certificate(
+    store: State<'_, Store>,
+    profile_id: String,
+) -> Result<PinCheck> {
+    let profile = profile(&store, &profile_id)?;
+    host_of(&profile.server_url)?;
+    let presented = super::observe_pin(&profile.server_url).await?;
+    Ok(PinCheck {
+        matches: profile.certificate_pin.as_ref() == Some(&presented),
+        pinned: profile.certificate_pin,
+        presented,
+    })
+}
+
+/// Removes a profile's pin. Returns `false` if it had none.
+#[tauri::command]
+pub fn clear_profile_pin(store: State<'_, Store>, profile_id: String) -> Result<bool> {
+    let profile = profile(&store, &profile_id)?;
+    if profile.certificate_pin.is_none() {
+        return Ok(false);
+    }
+    store.set_profile_pin(&profile_id, None)?;
+    reload(&store)?;
+    log::info!("Cleared certificate pin for {}", profile.server_url);
+    Ok(true)
+}
@@ -0,0 +1,225 @@
+//! WebSocket connections under the same network settings as HTTP.
+//!
+//! reqwest cannot carry a WebSocket, so connections are opened here: through
+//! the configured proxy with an HTTP `CONNECT` tunnel, and over rustls with
+//! the pinning verifier and the CA bundle. With the system proxy setting,
+//! the `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` environment
+//! variables are honoured; the OS proxy configuration is not read.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ipnet::IpNet;
+use reqwest::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+
+use super::{Config, Route, current_config, pinning};
+use crate::error::{Error, Result};
+
+/// Longest proxy response header accepted for a `CONNECT`.
+const MAX_CONNECT_RESPONSE: usize = 16 * 1024;
+
+pub type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// An HTTP proxy to tunnel through.
+#[derive(Clone)]
+pub(super) struct Tunnel {
+    url: Url,
+    /// Value of the `Proxy-Authorization` header, if the proxy needs one.
+    authorization: Option<String>,
+}
+
+impl Tunnel {
+    pub(super) fn new(url: Url, credentials: Option<(&str, &str)>) -> Self {
+        let authorization = credentials.map(|(username, password)| {
+            format!(
+                "Basic {}",
+                STANDARD.encode(format!("{username}:{password}"))
+            )
+        });
+        Self { url, authorization }
+    }
+
+    /// A proxy URL that may carry its own credentials, as in the environment.
+    fn from_url(mut url: Url) -> Self {
+        let username = url.username().to_string();
+        let password = url.password().unwrap_or_default().to_string();
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        let credentials = (!username.is_empty()).then_some((username.as_str(), password.as_str()));
+        Self::new(url, credentials)
+    }
+}
+
+/// Whether `host` is covered by a comma-separated `NO_PROXY` style list of
+/// hosts, domains and CIDR ranges.
+fn bypasses(list: &str, host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    let address: Option<IpAddr> = host.parse().ok();
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            if let Some(address) = address {
+                return match entry.parse::<IpNet>() {
+                    Ok(net) => net.contains(&address),
+                    Err(_) => entry.parse::<IpAddr>().is_ok_and(|entry| entry == address),
+                };
+            }
+            let domain = entry.trim_start_matches("*.").trim_start_matches('.');
+            let domain = domain.to_ascii_lowercase();
+            host == domain || host.ends_with(&format!(".{domain}"))
+        })
+}
+
+fn env(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .filter(|value| !value.trim().is_empty())
+}
+
+fn system_tunnel(url: &Url, host: &str) -> Option<Tunnel> {
+    if env(&["NO_PROXY", "no_proxy"]).is_some_and(|list| bypasses(&list, host)) {
+        return None;
+    }
+    let names: &[&str] = if url.scheme() == "wss" {
+        &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+    } else {
+        &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+    };
+    let value = env(names)?;
+    // Proxies given without a scheme are plain HTTP, as curl assumes
+    let value = if value.contains("://") {
+        value
+    } else {
+        format!("http://{value}")
+    };
+    match Url::parse(&value) {
+        Ok(proxy) => Some(Tunnel::from_url(proxy)),
+        Err(err) => {
+            log::warn!("Ignoring invalid proxy {value}: {err}");
+            None
+        }
+    }
+}
+
+/// The proxy a WebSocket to `url` goes through, if any.
+async fn tunnel_for(config: &Config, url: &Url, host: &str) -> Result<Option<Tunnel>> {
+    Ok(match &config.route {
+        Route::System => system_tunnel(url, host),
+        Route::Direct => None,
+        Route::Manual {
+            tunnel, no_proxy, ..
+        } => match no_proxy {
+            Some(list) if bypasses(list, host) => None,
+            _ => Some(tunnel.clone()),
+        },
+        Route::Pac(resolver) => {
+            // PAC scripts are written for the HTTP schemes
+            let mut http_url = url.clone();
+            let scheme = if url.scheme() == "wss" {
+                "https"
+            } else {
+                "http"
+            };
+            let _ = http_url.set_scheme(scheme);
+            let resolver = resolver.clone();
+            tauri::async_runtime::spawn_blocking(move || resolver.proxy_for(&http_url))
+                .await?
+                .map(Tunnel::from_url)
+        }
+    })
+}
+
+/// Opens a TCP connection to `host:port` through an HTTP `CONNECT` tunnel.
+async fn connect_through(tunnel: &Tunnel, host: &str, port: u16) -> Result<TcpStream> {
+    let proxy = &tunnel.url;
+    if proxy.scheme() != "http" {
+        return Err(Error::InvalidInput(format!(
+            "WebSockets can only be tunnelled through http:// proxies, not {proxy}"
+        )));
+    }
+    let proxy_host = proxy
+        .host_str()
+        .ok_or_else(|| Error::InvalidInput(format!("No host in proxy {proxy}")))?;
+    let proxy_port = proxy.port_or_known_default().unwrap_or(80);
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+
+    let target = if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(authorization) = &tunnel.authorization {
+        request.push_str(&format!("Proxy-Authorization: {authorization}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read byte by byte so nothing past the header is taken from the tunnel
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            return Err(Error::InvalidInput(format!(
+                "Proxy {proxy} sent an oversized CONNECT response"
+            )));
+        }
+        let mut byte = [0u8];
+        if stream.read(&mut byte).await? == 0 {
+            return Err(Error::InvalidInput(format!(
+                "Proxy {proxy} closed the connection during CONNECT"
+            )));
+        }
+        response.push(byte[0]);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(Error::InvalidInput(format!(
+            "Proxy {proxy} refused the tunnel: {status_line}"
+        )));
+    }
+    Ok(stream)
+}
+
+/// Opens a WebSocket with the current proxy, CA bundle and certificate pins.
+pub(crate) async fn connect(request: Request) -> Result<Socket> {
+    let url = Url::parse(&request.uri().to_string())
+        .map_err(|err| Error::InvalidInput(format!("Invalid WebSocket URL: {err}")))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| Error::InvalidInput(format!("No host in {url}")))?
+        .trim_matches(['[', ']'])
+        .to_string();
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| Error::InvalidInput(format!("No port for {url}")))?;
+
+    let config = current_config();
+    let stream = match tunnel_for(&config, &url, &host).await? {
+        Some(tunnel) => connect_through(&tunnel, &host, port).await?,
+        None => TcpStream::connect((host.as_str(), port)).await?,
+    };
+    // Nagle only delays small frames such as heartbeats
+    let _ = stream.set_nodelay(true);
+    let connector = match url.scheme() {
+        "wss" => Connector::Rustls(Arc::new(pinning::websocket_tls_config(
+            config.ca_pem.as_deref(),
+        )?)),
+        _ => Connector::Plain,
+    };
+    let (socket, _) =
+        tokio_tungstenite::client_async_tls_with_config(request, stream, None, Some(connector))
+            .await?;
+    Ok(socket)
+}
@@ -0,0 +1,138 @@
+//! OS notifications when background jobs finish.
+//!
+//! Publishing, delivery builds and downloads keep running after the user has
+//! switched to another app, so their outcome is reported natively. Desktop
+//! platforms don't tell us when a notification is clicked, but clicking one
+//! brings the app to the front. So when the main window gains focus shortly
+//! after a notification, that notification's target is emitted as
+//! `notifications:activated` and the frontend can open the matching view.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::error::Result;
+use crate::paths;
+
+const SETTINGS_FILE: &str = "notifications.json";
+pub const ACTIVATED_EVENT: &str = "notifications:activated";
+/// How long after a notification focusing the window counts as clicking it.
+const ACTIVATION_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Kind {
+    Publish,
+    Delivery,
+    Download,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Skip notifications while the main window is focused.
+    pub only_when_unfocused: bool,
+    pub publish: bool,
+    pub delivery: bool,
+    pub download: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            only_when_unfocused: true,
+            publish: true,
+            delivery: true,
+            download: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    fn allows(&self, kind: Kind) -> bool {
+        self.enabled
+            && match kind {
+                Kind::Publish => self.publish,
+                Kind::Delivery => self.delivery,
+                Kind::Download => self.download,
+            }
+    }
+}
+
+/// View the frontend should open for a notification.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub kind: Kind,
+    /// Job, delivery or download ID, if the notification is about one.
+    pub id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Notifications {
+    last: Mutex<Option<(Instant, Target)>>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<NotificationSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn main_window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+/// Shows a notification unless the settings turn this kind off.
+pub fn notify(app: &AppHandle, kind: Kind, id: Option<String>, title: &str, body: &str) {
+    let settings = match load_settings(app) {
+        Ok(settings) => settings,
+        Err(err) => {
+            log::warn!("Failed to read notification settings: {err}");
+            NotificationSettings::default()
+        }
+    };
+    if !settings.allows(kind) || (settings.only_when_unfocused && main_window_focused(app)) {
+        return;
+    }
+    if let Err(err) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("Failed to show notification: {err}");
+        return;
+    }
+    *app.state::<Notifications>()
+        .last
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+        Some((Instant::now(), Target { kind, id }));
+}
+
+/// Called when the main window gains focus.
+pub fn window_focused(app: &AppHandle) {
+    let last = app
+        .state::<Notifications>()
+        .last
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take();
+    if let Some((shown_at, target)) = last
+        && shown_at.elapsed() < ACTIVATION_WINDOW
+        && let Err(err) = app.emit(ACTIVATED_EVENT, target)
+    {
+        log::warn!("Failed to emit notification activation: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn get_notification_settings(app: AppHandle) -> Result<NotificationSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_notification_settings(app: AppHandle, settings: NotificationSettings) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &settings)
+}
@@ -0,0 +1,179 @@
+//! OpenTimelineIO export of review playlists.
+//!
+//! Each version becomes a clip on a single video track, in playlist order,
+//! with its notes as markers so editorial sees the review in context.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{Value, json};
+use tauri::{AppHandle, Url};
+
+use crate::error::Result;
+use crate::export::editorial::{EditorialNote, EditorialPlaylist, EditorialVersion};
+use crate::export::{self, resolve_path, write_atomically};
+
+fn rational_time(value: i64, rate: f64) -> Value {
+    json!({
+        "OTIO_SCHEMA": "RationalTime.1",
+        "rate": rate,
+        "value": value as f64,
+    })
+}
+
+fn time_range(start: i64, duration: i64, rate: f64) -> Value {
+    json!({
+        "OTIO_SCHEMA": "TimeRange.1",
+        "start_time": rational_time(start, rate),
+        "duration": rational_time(duration, rate),
+    })
+}
+
+fn media_reference(version: &EditorialVersion, available_range: &Value) -> Value {
+    let target_url = version
+        .media_path
+        .as_ref()
+        .and_then(|path| Url::from_file_path(path).ok())
+        .map(String::from)
+        .or_else(|| version.media_url.clone());
+    match target_url {
+        Some(target_url) => json!({
+            "OTIO_SCHEMA": "ExternalReference.1",
+            "name": version.name,
+            "target_url": target_url,
+            "available_range": available_range,
+            "available_image_bounds": null,
+            "metadata": {},
+        }),
+        None => json!({
+            "OTIO_SCHEMA": "MissingReference.1",
+            "name": version.name,
+            "available_range": available_range,
+            "available_image_bounds": null,
+            "metadata": {},
+        }),
+    }
+}
+
+fn marker(note: &EditorialNote, frame: i64, rate: f64) -> Value {
+    let name = match &note.author {
+        Some(author) => format!("Note by {author}"),
+        None => "Note".to_string(),
+    };
+    json!({
+        "OTIO_SCHEMA": "Marker.2",
+        "name": name,
+        "color": "RED",
+        "marked_range": time_range(frame, 1, rate),
+        "comment": note.content,
+        "metadata": {},
+    })
+}
+
+fn clip(version: &EditorialVersion, timeline_rate: f64) -> Value {
+    let rate = version.frame_rate.unwrap_or(timeline_rate);
+    let range = version.range();
+    let source_range = range.map_or(Value::Null, |(start, duration)| {
+        time_range(start, duration, rate)
+    });
+
+    // Notes without a frame are pinned to the first frame of the clip
+    let markers: Vec<Value> = version
+        .notes
+        .iter()
+        .filter_map(|note| {
+            let frame = note.frame.or(range.map(|(start, _)| start))?;
+            Some(marker(note, frame, rate))
+        })
+        .collect();
+    let unanchored: Vec<&str> = version
+        .notes
+        .iter()
+        .filter(|note| note.frame.is_none() && range.is_none())
+        .map(|note| note.content.as_str())
+        .collect();
+
+    json!({
+        "OTIO_SCHEMA": "Clip.2",
+        "name": version.name,
+        "source_range": source_range,
+        "media_references": {
+            "DEFAULT_MEDIA": media_reference(version, &source_range),
+        },
+        "active_media_reference_key": "DEFAULT_MEDIA",
+        "effects": [],
+        "markers": markers,
+        "enabled": true,
+        "metadata": {
+            "astranotes": {
+                "versionId": version.version_id,
+                "versionNumber": version.version_number,
+                "status": version.status,
+                "notes": unanchored,
+            },
+        },
+    })
+}
+
+pub(crate) fn timeline(playlist_id: &str, playlist: &EditorialPlaylist) -> Value {
+    let rate = playlist.rate();
+    let clips: Vec<Value> = playlist
+        .versions
+        .iter()
+        .map(|version| clip(version, rate))
+        .collect();
+    json!({
+        "OTIO_SCHEMA": "Timeline.1",
+        "name": playlist.name,
+        "global_start_time": null,
+        "metadata": {
+            "astranotes": { "playlistId": playlist_id },
+        },
+        "tracks": {
+            "OTIO_SCHEMA": "Stack.1",
+            "name": "tracks",
+            "source_range": null,
+            "effects": [],
+            "markers": [],
+            "enabled": true,
+            "metadata": {},
+            "children": [{
+                "OTIO_SCHEMA": "Track.1",
+                "name": "Review",
+                "kind": "Video",
+                "source_range": null,
+                "effects": [],
+                "markers": [],
+                "enabled": true,
+                "metadata": {},
+                "children": clips,
+            }],
+        },
+    })
+}
+
+/// Writes the playlist as an `.otio` timeline. Returns the written path, or `None` if cancelled.
+#[tauri::command]
+pub async fn export_playlist_otio(
+    app: AppHandle,
+    playlist_id: String,
+    playlist: EditorialPlaylist,
+    path: Option<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    let Some(path) = resolve_path(&app, path, &playlist.name, "OpenTimelineIO", "otio").await
+    else {
+        return Ok(None);
+    };
+    let document = serde_json::to_vec_pretty(&timeline(&playlist_id, &playlist))?;
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        write_atomically(&target, |temp| Ok(fs::write(temp, &document)?))
+    })
+    .await??;
+    log::info!(
+        "Exported playlist {playlist_id} as OTIO to {}",
+        path.display()
+    );
+    export::exported(&app, "otio", &playlist_id, &path);
+    Ok(Some(path))
+}
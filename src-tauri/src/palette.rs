@@ -0,0 +1,158 @@
+//! Fuzzy matching for the command palette.
+//!
+//! The frontend registers each candidate list (playlists, versions, shots)
+//! once and gets back a handle; per-keystroke queries then only send the
+//! query text. Candidates are kept pre-converted for nucleo so a query over
+//! tens of thousands of names stays well inside a frame.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::{Config, Matcher, Utf32String};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::error::{Error, Result};
+
+const DEFAULT_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    pub id: String,
+    pub label: String,
+    /// Passed back untouched, e.g. `playlist` or `shot`.
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzyMatch {
+    pub id: String,
+    pub label: String,
+    pub kind: Option<String>,
+    pub score: u32,
+    /// Character positions in `label` that matched, for highlighting.
+    pub indices: Vec<u32>,
+}
+
+struct CandidateSet {
+    candidates: Vec<Candidate>,
+    haystacks: Vec<Utf32String>,
+}
+
+pub struct Palette {
+    sets: Mutex<HashMap<String, Arc<CandidateSet>>>,
+    matcher: Mutex<Matcher>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            sets: Mutex::new(HashMap::new()),
+            matcher: Mutex::new(Matcher::new(Config::DEFAULT)),
+        }
+    }
+}
+
+impl Palette {
+    fn set(&self, handle: &str) -> Result<Arc<CandidateSet>> {
+        self.sets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(handle)
+            .cloned()
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown candidate set: {handle}")))
+    }
+}
+
+/// Stores `candidates` for matching. Passing an existing `handle` replaces
+/// that set in place; otherwise a new handle is returned.
+#[tauri::command]
+pub fn register_candidates(
+    palette: State<'_, Palette>,
+    candidates: Vec<Candidate>,
+    handle: Option<String>,
+) -> String {
+    let haystacks = candidates
+        .iter()
+        .map(|candidate| Utf32String::from(candidate.label.as_str()))
+        .collect();
+    let handle = handle.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    palette
+        .sets
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(
+            handle.clone(),
+            Arc::new(CandidateSet {
+                candidates,
+                haystacks,
+            }),
+        );
+    handle
+}
+
+#[tauri::command]
+pub fn release_candidates(palette: State<'_, Palette>, candidates_handle: String) -> bool {
+    palette
+        .sets
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .remove(&candidates_handle)
+        .is_some()
+}
+
+/// Best matches for `query`, highest score first. An empty query returns
+/// the first candidates in registration order.
+#[tauri::command]
+pub fn fuzzy_match(
+    palette: State<'_, Palette>,
+    query: String,
+    candidates_handle: String,
+    limit: Option<usize>,
+) -> Result<Vec<FuzzyMatch>> {
+    let set = palette.set(&candidates_handle)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    let pattern = Pattern::parse(&query, CaseMatching::Smart, Normalization::Smart);
+    let mut matcher = palette
+        .matcher
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut scored: Vec<(u32, usize)> = set
+        .haystacks
+        .iter()
+        .enumerate()
+        .filter_map(|(index, haystack)| {
+            pattern
+                .score(haystack.slice(..), &mut matcher)
+                .map(|score| (score, index))
+        })
+        .collect();
+    // Stable on ties, so equal scores keep registration order
+    scored.sort_by_key(|&(score, _)| Reverse(score));
+    scored.truncate(limit);
+
+    let matches = scored
+        .into_iter()
+        .map(|(score, index)| {
+            let mut indices = Vec::new();
+            pattern.indices(set.haystacks[index].slice(..), &mut matcher, &mut indices);
+            indices.sort_unstable();
+            indices.dedup();
+            let candidate = &set.candidates[index];
+            FuzzyMatch {
+                id: candidate.id.clone(),
+                label: candidate.label.clone(),
+                kind: candidate.kind.clone(),
+                score,
+                indices,
+            }
+        })
+        .collect();
+    Ok(matches)
+}
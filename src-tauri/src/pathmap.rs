@@ -0,0 +1,194 @@
+//! Translation of file paths between studio platforms.
+//!
+//! ftrack stores paths as seen by whoever published them. Each mapping lists
+//! the same storage root as mounted on Linux, Windows and macOS, and paths are
+//! rewritten by swapping the longest matching root. Mappings are kept in
+//! `pathmap.json` in the app config directory.
+
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::error::Result;
+use crate::paths;
+
+const SETTINGS_FILE: &str = "pathmap.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Linux,
+    Windows,
+    Macos,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Self::Windows
+        } else if cfg!(target_os = "macos") {
+            Self::Macos
+        } else {
+            Self::Linux
+        }
+    }
+}
+
+/// One storage root as mounted on each platform, e.g. `/mnt/proj`,
+/// `P:\proj` and `/Volumes/proj`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathMapping {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub linux: Option<String>,
+    #[serde(default)]
+    pub windows: Option<String>,
+    #[serde(default)]
+    pub macos: Option<String>,
+}
+
+impl PathMapping {
+    fn root(&self, platform: Platform) -> Option<&str> {
+        match platform {
+            Platform::Linux => self.linux.as_deref(),
+            Platform::Windows => self.windows.as_deref(),
+            Platform::Macos => self.macos.as_deref(),
+        }
+        .filter(|root| !root.trim().is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedPath {
+    pub path: PathBuf,
+    pub mapped: bool,
+    pub exists: bool,
+}
+
+/// Forward slashes and no trailing separator, so roots compare uniformly.
+fn normalize(path: &str) -> String {
+    let path = path.trim().replace('\\', "/");
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Returns the part of `path` after `root`, if `root` is a whole-component prefix.
+fn strip_root<'a>(path: &'a str, root: &str, case_insensitive: bool) -> Option<&'a str> {
+    let prefix = path.get(..root.len())?;
+    let matches = if case_insensitive {
+        prefix.eq_ignore_ascii_case(root)
+    } else {
+        prefix == root
+    };
+    let rest = &path[root.len()..];
+    (matches && (rest.is_empty() || rest.starts_with('/') || root.ends_with('/'))).then_some(rest)
+}
+
+fn map_with(mappings: &[PathMapping], path: &str, target: Platform) -> Option<String> {
+    let normalized = normalize(path);
+    let mut best: Option<(usize, &PathMapping, &str)> = None;
+    for mapping in mappings {
+        for platform in [Platform::Linux, Platform::Windows, Platform::Macos] {
+            let Some(root) = mapping.root(platform) else {
+                continue;
+            };
+            let root = normalize(root);
+            // Windows drive letters and UNC shares are case-insensitive
+            let rest = strip_root(&normalized, &root, platform == Platform::Windows);
+            if let Some(rest) = rest
+                && best.is_none_or(|(len, _, _)| root.len() > len)
+            {
+                best = Some((root.len(), mapping, rest));
+            }
+        }
+    }
+
+    let (_, mapping, rest) = best?;
+    let target_root = normalize(mapping.root(target)?);
+    let rest = rest.trim_start_matches('/');
+    let mapped = match (target_root.ends_with('/'), rest.is_empty()) {
+        (_, true) => target_root,
+        (true, false) => format!("{target_root}{rest}"),
+        (false, false) => format!("{target_root}/{rest}"),
+    };
+    Some(match target {
+        Platform::Windows => mapped.replace('/', "\\"),
+        _ => mapped,
+    })
+}
+
+#[derive(Default)]
+pub struct PathMap {
+    mappings: RwLock<Vec<PathMapping>>,
+}
+
+impl PathMap {
+    pub fn load(app: &AppHandle) -> Result<Self> {
+        let mappings = paths::load_json(app, SETTINGS_FILE)?;
+        Ok(Self {
+            mappings: RwLock::new(mappings),
+        })
+    }
+
+    pub fn mappings(&self) -> Vec<PathMapping> {
+        self.mappings
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Rewrites `path` for `target`. Paths outside every mapping are returned unchanged.
+    pub fn map(&self, path: &str, target: Platform) -> Option<String> {
+        let mappings = self
+            .mappings
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        map_with(&mappings, path, target)
+    }
+
+    pub fn resolve_local(&self, path: &str) -> ResolvedPath {
+        let mapped = self.map(path, Platform::current());
+        let resolved = PathBuf::from(mapped.as_deref().unwrap_or(path));
+        ResolvedPath {
+            exists: resolved.exists(),
+            mapped: mapped.is_some(),
+            path: resolved,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_path_mappings(pathmap: State<'_, PathMap>) -> Vec<PathMapping> {
+    pathmap.mappings()
+}
+
+#[tauri::command]
+pub fn set_path_mappings(
+    app: AppHandle,
+    pathmap: State<'_, PathMap>,
+    mappings: Vec<PathMapping>,
+) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &mappings)?;
+    *pathmap
+        .mappings
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = mappings;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn map_path(pathmap: State<'_, PathMap>, path: String, target_platform: Platform) -> String {
+    pathmap.map(&path, target_platform).unwrap_or(path)
+}
+
+#[tauri::command]
+pub fn resolve_local_path(pathmap: State<'_, PathMap>, path: String) -> ResolvedPath {
+    pathmap.resolve_local(&path)
+}
@@ -0,0 +1,163 @@
+//! Where the app keeps its files, with support for portable deployments.
+//!
+//! Normally everything lives in the per-user directories Tauri resolves.
+//! In portable mode, chosen by `main` through [`set_data_dir`], the config,
+//! data, cache and log directories are subdirectories of one data directory
+//! instead, so the app can run from a network share without touching the
+//! user profile. Credentials stay in the OS keychain either way.
+//!
+//! Code that runs without an app handle, such as telemetry and the headless
+//! CLI, resolves the same directories through the `standalone_*` functions.
+//!
+//! Settings files in the config directory are read and written through
+//! [`load_json`] and [`save_json`], which refuses changes to values the
+//! administrator locked.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tauri::{AppHandle, Manager, Runtime, WebviewWindowBuilder};
+
+use crate::error::Result;
+use crate::export;
+use crate::settings;
+
+/// Matches `identifier` in `tauri.conf.json`, which names the per-user
+/// directories.
+const APP_IDENTIFIER: &str = "com.AstraLumen.Notes";
+
+/// Portable mode is used when a directory with this name sits next to the
+/// executable.
+pub const PORTABLE_DIR: &str = "AstraNotes Data";
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Switches to portable mode. Must be called before the app starts; later
+/// calls are ignored.
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR.set(dir);
+}
+
+/// The portable data directory, if running in portable mode.
+pub(crate) fn portable_root() -> Option<&'static Path> {
+    DATA_DIR.get().map(PathBuf::as_path)
+}
+
+fn standalone(subdir: &str, default: fn() -> Option<PathBuf>) -> Option<PathBuf> {
+    match portable_root() {
+        Some(root) => Some(root.join(subdir)),
+        None => default().map(|dir| dir.join(APP_IDENTIFIER)),
+    }
+}
+
+/// The config directory, for code that runs without an app handle.
+pub(crate) fn standalone_config_dir() -> Option<PathBuf> {
+    standalone("config", dirs::config_dir)
+}
+
+/// The data directory, for code that runs without an app handle.
+pub(crate) fn standalone_data_dir() -> Option<PathBuf> {
+    standalone("data", dirs::data_dir)
+}
+
+fn resolve(
+    subdir: &str,
+    default: impl FnOnce() -> tauri::Result<PathBuf>,
+) -> tauri::Result<PathBuf> {
+    match portable_root() {
+        Some(root) => Ok(root.join(subdir)),
+        None => default(),
+    }
+}
+
+pub(crate) fn config_dir<R: Runtime>(app: &impl Manager<R>) -> tauri::Result<PathBuf> {
+    resolve("config", || app.path().app_config_dir())
+}
+
+pub(crate) fn data_dir<R: Runtime>(app: &impl Manager<R>) -> tauri::Result<PathBuf> {
+    resolve("data", || app.path().app_data_dir())
+}
+
+pub(crate) fn cache_dir<R: Runtime>(app: &impl Manager<R>) -> tauri::Result<PathBuf> {
+    resolve("cache", || app.path().app_cache_dir())
+}
+
+pub(crate) fn log_dir<R: Runtime>(app: &impl Manager<R>) -> tauri::Result<PathBuf> {
+    resolve("logs", || app.path().app_log_dir())
+}
+
+/// Reads a JSON file, or the default value if it does not exist yet.
+pub(crate) fn read_json<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Writes `value` as pretty JSON, replacing the file in one step so a crash
+/// never leaves it half written.
+pub(crate) fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let bytes = serde_json::to_vec_pretty(value)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    export::write_atomically(path, |temp| Ok(fs::write(temp, &bytes)?))
+}
+
+/// Reads the settings file `file` from the config directory.
+pub(crate) fn load_json<R: Runtime, T: DeserializeOwned + Default>(
+    app: &impl Manager<R>,
+    file: &str,
+) -> Result<T> {
+    read_json(&config_dir(app)?.join(file))
+}
+
+/// Writes the settings file `file` to the config directory.
+pub(crate) fn save_json<R: Runtime>(
+    app: &impl Manager<R>,
+    file: &str,
+    value: &impl Serialize,
+) -> Result<()> {
+    settings::check_locked(file, value)?;
+    write_json(&config_dir(app)?.join(file), value)
+}
+
+/// [`load_json`] for code that runs without an app handle.
+pub(crate) fn load_standalone_json<T: DeserializeOwned + Default>(file: &str) -> Result<T> {
+    match standalone_config_dir() {
+        Some(dir) => read_json(&dir.join(file)),
+        None => Ok(T::default()),
+    }
+}
+
+/// [`save_json`] for code that runs without an app handle.
+pub(crate) fn save_standalone_json(file: &str, value: &impl Serialize) -> Result<()> {
+    settings::check_locked(file, value)?;
+    match standalone_config_dir() {
+        Some(dir) => write_json(&dir.join(file), value),
+        None => Ok(()),
+    }
+}
+
+/// Webview storage, when it should not use the platform default.
+pub(crate) fn webview_dir() -> Option<PathBuf> {
+    portable_root().map(|root| root.join("webview"))
+}
+
+/// Builds the windows from the config with their webview storage in the
+/// portable data directory, in place of Tauri creating them.
+pub(crate) fn create_windows<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    let Some(dir) = webview_dir() else {
+        return Ok(());
+    };
+    for config in app.config().app.windows.clone() {
+        WebviewWindowBuilder::from_config(app, &config)?
+            .data_directory(dir.join(&config.label))
+            .build()?;
+    }
+    Ok(())
+}
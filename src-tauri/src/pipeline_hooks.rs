@@ -0,0 +1,354 @@
+//! Studio Python scripts run at fixed points: before and after a publish,
+//! and after an export.
+//!
+//! Each hook is a subprocess that receives `{"hook": ..., "payload": ...}` as
+//! JSON on stdin and may answer with one JSON object on stdout, such as
+//! `{"ok": false, "message": "Shot is locked"}`. Anything on stderr is kept
+//! as the run's log. Only pre-publish hooks can stop anything; a hook that
+//! crashes or times out stops the publish only if it is marked `required`.
+//! Hooks are configured per machine in `pipeline_hooks.json`.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::plugins;
+use crate::store::now_millis;
+
+const SETTINGS_FILE: &str = "pipeline_hooks.json";
+pub const RUN_EVENT: &str = "pipeline-hooks:run";
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+/// Completed runs kept for the hook log view.
+const HISTORY_LEN: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookPoint {
+    PrePublish,
+    PostPublish,
+    OnExport,
+}
+
+impl HookPoint {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::PrePublish => "pre_publish",
+            Self::PostPublish => "post_publish",
+            Self::OnExport => "on_export",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookConfig {
+    pub point: HookPoint,
+    pub script: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Whether a failing pre-publish hook blocks the publish.
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HookSettings {
+    /// Interpreter for all hooks; `python3` (`python` on Windows) by default.
+    pub python: Option<PathBuf>,
+    pub hooks: Vec<HookConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RunStatus {
+    Succeeded,
+    Rejected,
+    Failed,
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookRun {
+    pub point: HookPoint,
+    pub script: PathBuf,
+    pub status: RunStatus,
+    pub message: Option<String>,
+    /// Parsed stdout, if the script answered.
+    pub response: Option<Value>,
+    pub log: Vec<String>,
+    pub started_at: i64,
+    pub duration_ms: u64,
+}
+
+#[derive(Default)]
+pub struct PipelineHooks {
+    history: Mutex<VecDeque<HookRun>>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<HookSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn interpreter(settings: &HookSettings) -> PathBuf {
+    settings
+        .python
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(if cfg!(windows) { "python" } else { "python3" }))
+}
+
+async fn execute(
+    python: &Path,
+    hook: &HookConfig,
+    input: &[u8],
+) -> std::result::Result<(Option<Value>, Vec<String>), (RunStatus, String, Vec<String>)> {
+    let failed = |message: String| (RunStatus::Failed, message, Vec::new());
+    let mut child = Command::new(python)
+        .arg(&hook.script)
+        .args(&hook.args)
+        .env("ASTRANOTES_HOOK", hook.point.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| failed(format!("Could not start {}: {err}", python.display())))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let timeout = Duration::from_secs(hook.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let run = async {
+        // A script that exits without reading stdin is not an error
+        let _ = stdin.write_all(input).await;
+        drop(stdin);
+        child.wait_with_output().await
+    };
+    let output = match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => return Err(failed(err.to_string())),
+        Err(_) => {
+            return Err((
+                RunStatus::TimedOut,
+                format!("Timed out after {}s", timeout.as_secs()),
+                Vec::new(),
+            ));
+        }
+    };
+
+    let log: Vec<String> = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if !output.status.success() {
+        let detail = log
+            .last()
+            .cloned()
+            .unwrap_or_else(|| output.status.to_string());
+        return Err((RunStatus::Failed, detail, log));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok((None, log));
+    }
+    match serde_json::from_str(stdout.trim()) {
+        Ok(response) => Ok((Some(response), log)),
+        Err(err) => Err((
+            RunStatus::Failed,
+            format!("Invalid JSON on stdout: {err}"),
+            log,
+        )),
+    }
+}
+
+/// Runs one hook without recording it in the history.
+async fn run_script(python: &Path, hook: &HookConfig, payload: &Value) -> HookRun {
+    let input = json!({ "hook": hook.point, "payload": payload }).to_string();
+    let started_at = now_millis();
+    let started = Instant::now();
+    let (status, message, response, log) = match execute(python, hook, input.as_bytes()).await {
+        Ok((response, log)) => {
+            let rejected = response
+                .as_ref()
+                .and_then(|response| response.get("ok"))
+                .and_then(Value::as_bool)
+                == Some(false);
+            let message = response
+                .as_ref()
+                .and_then(|response| response.get("message"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let status = if rejected {
+                RunStatus::Rejected
+            } else {
+                RunStatus::Succeeded
+            };
+            (status, message, response, log)
+        }
+        Err((status, message, log)) => (status, Some(message), None, log),
+    };
+
+    let run = HookRun {
+        point: hook.point,
+        script: hook.script.clone(),
+        status,
+        message,
+        response,
+        log,
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    if run.status != RunStatus::Succeeded {
+        log::warn!(
+            "Pipeline hook {} {:?}: {}",
+            run.script.display(),
+            run.status,
+            run.message.as_deref().unwrap_or_default()
+        );
+    }
+    run
+}
+
+async fn run_hook(app: &AppHandle, python: &Path, hook: &HookConfig, payload: &Value) -> HookRun {
+    let run = run_script(python, hook, payload).await;
+    {
+        let hooks = app.state::<PipelineHooks>();
+        let mut history = hooks
+            .history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if history.len() == HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(run.clone());
+    }
+    if let Err(err) = app.emit(RUN_EVENT, &run) {
+        log::warn!("Failed to emit pipeline hook run: {err}");
+    }
+    run
+}
+
+/// Runs every enabled hook for `point` in configured order.
+pub async fn run(app: &AppHandle, point: HookPoint, payload: &Value) -> Result<Vec<HookRun>> {
+    let settings = load_settings(app)?;
+    let python = interpreter(&settings);
+    let mut runs = Vec::new();
+    for hook in settings
+        .hooks
+        .iter()
+        .filter(|hook| hook.enabled && hook.point == point)
+    {
+        runs.push(run_hook(app, &python, hook, payload).await);
+    }
+    Ok(runs)
+}
+
+/// Runs pre-publish hooks, then plugin handlers, and fails if one rejected
+/// the batch, or a hook failed while marked required.
+pub async fn before_publish(app: &AppHandle, payload: &Value) -> Result<()> {
+    let settings = load_settings(app)?;
+    let python = interpreter(&settings);
+    for hook in pre_publish(&settings) {
+        allow_publish(hook, run_hook(app, &python, hook, payload).await)?;
+    }
+    plugins::before_publish(app, payload).await
+}
+
+/// [`before_publish`] for the headless commands, which keep no run history.
+pub(crate) async fn before_publish_standalone(payload: &Value) -> Result<()> {
+    let settings: HookSettings = paths::load_standalone_json(SETTINGS_FILE)?;
+    let python = interpreter(&settings);
+    for hook in pre_publish(&settings) {
+        allow_publish(hook, run_script(&python, hook, payload).await)?;
+    }
+    plugins::before_publish_standalone(payload).await
+}
+
+fn pre_publish(settings: &HookSettings) -> impl Iterator<Item = &HookConfig> {
+    settings
+        .hooks
+        .iter()
+        .filter(|hook| hook.enabled && hook.point == HookPoint::PrePublish)
+}
+
+fn allow_publish(hook: &HookConfig, run: HookRun) -> Result<()> {
+    let blocking = match run.status {
+        RunStatus::Succeeded => false,
+        RunStatus::Rejected => true,
+        RunStatus::Failed | RunStatus::TimedOut => hook.required,
+    };
+    if !blocking {
+        return Ok(());
+    }
+    let name = hook
+        .script
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Err(Error::InvalidInput(format!(
+        "Publish blocked by {name}: {}",
+        run.message.unwrap_or_else(|| "no reason given".into())
+    )))
+}
+
+/// Fires `point` hooks and plugin handlers in the background, for points
+/// that cannot block.
+pub fn notify(app: &AppHandle, point: HookPoint, payload: Value) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = run(&app, point, &payload).await {
+            log::warn!("Failed to run {point:?} hooks: {err}");
+        }
+        plugins::dispatch(&app, point, &payload).await;
+    });
+}
+
+#[tauri::command]
+pub fn get_pipeline_hook_settings(app: AppHandle) -> Result<HookSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_pipeline_hook_settings(app: AppHandle, settings: HookSettings) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &settings)
+}
+
+/// Recent hook runs, newest first.
+#[tauri::command]
+pub fn list_hook_runs(hooks: State<'_, PipelineHooks>) -> Vec<HookRun> {
+    hooks
+        .history
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .rev()
+        .cloned()
+        .collect()
+}
+
+/// Runs the hooks for `point` with a sample payload, for testing from settings.
+#[tauri::command]
+pub async fn run_pipeline_hooks(
+    app: AppHandle,
+    point: HookPoint,
+    payload: Value,
+) -> Result<Vec<HookRun>> {
+    run(&app, point, &payload).await
+}
@@ -0,0 +1,229 @@
+//! "Open in RV / mrv2" for a selection of versions.
+//!
+//! The selection is written as a session file the player understands (an RV
+//! `.rv` GTO session, or an OTIO timeline for mrv2) and the player is started
+//! on it. Player paths are configured per machine in `players.json`.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::process::CommandEvent;
+
+use crate::error::{Error, Result};
+use crate::export::editorial::EditorialPlaylist;
+use crate::otio;
+use crate::pathmap::PathMap;
+use crate::paths;
+
+const SETTINGS_FILE: &str = "players.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Player {
+    Rv,
+    Mrv2,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlayerSettings {
+    pub rv_path: Option<PathBuf>,
+    pub mrv2_path: Option<PathBuf>,
+    /// Player used when the frontend does not pick one.
+    pub default_player: Option<Player>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<PlayerSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+/// The configured binary, or the usual install location, or a name on `PATH`.
+fn player_binary(player: Player, settings: &PlayerSettings) -> PathBuf {
+    let (configured, mac_bundle, name) = match player {
+        Player::Rv => (
+            &settings.rv_path,
+            "/Applications/RV.app/Contents/MacOS/RV",
+            "rv",
+        ),
+        Player::Mrv2 => (
+            &settings.mrv2_path,
+            "/Applications/mrv2.app/Contents/MacOS/mrv2",
+            "mrv2",
+        ),
+    };
+    if let Some(path) = configured {
+        return path.clone();
+    }
+    if cfg!(target_os = "macos") && Path::new(mac_bundle).exists() {
+        return PathBuf::from(mac_bundle);
+    }
+    PathBuf::from(name)
+}
+
+fn gto_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Builds an RV session with one source group per version, sequenced in order.
+fn rv_session(playlist: &EditorialPlaylist) -> Result<String> {
+    let mut session = String::from(
+        r#"GTOa (4)
+
+rv : RVSession (4)
+{
+    session
+    {
+        string viewNode = "defaultSequence"
+    }
+}
+"#,
+    );
+
+    let mut groups = Vec::new();
+    for (index, version) in playlist.versions.iter().enumerate() {
+        let Some(media) = &version.media_path else {
+            log::warn!("Skipping {} in RV session: no local media", version.name);
+            continue;
+        };
+        let group = format!("sourceGroup{index:06}");
+        let cut = match (version.frame_in, version.frame_out) {
+            (Some(frame_in), Some(frame_out)) => format!(
+                r#"
+    cut
+    {{
+        int in = {frame_in}
+        int out = {frame_out}
+    }}
+"#
+            ),
+            _ => String::new(),
+        };
+        let _ = write!(
+            session,
+            r#"
+{group} : RVSourceGroup (1)
+{{
+    ui
+    {{
+        string name = {name}
+    }}
+}}
+
+{group}_source : RVFileSource (1)
+{{
+    media
+    {{
+        string movie = {movie}
+    }}
+{cut}}}
+"#,
+            name = gto_string(&version.name),
+            movie = gto_string(&media.to_string_lossy()),
+        );
+        groups.push(group);
+    }
+    if groups.is_empty() {
+        return Err(Error::InvalidInput(
+            "None of the selected versions have local media".into(),
+        ));
+    }
+
+    let lhs: Vec<String> = groups.iter().map(|group| gto_string(group)).collect();
+    let rhs = vec![gto_string("defaultSequence"); groups.len()];
+    let _ = write!(
+        session,
+        r#"
+connections : connection (1)
+{{
+    evaluation
+    {{
+        string lhs = [ {lhs} ]
+        string rhs = [ {rhs} ]
+    }}
+}}
+"#,
+        lhs = lhs.join(" "),
+        rhs = rhs.join(" "),
+    );
+    Ok(session)
+}
+
+fn write_session(app: &AppHandle, player: Player, playlist: &EditorialPlaylist) -> Result<PathBuf> {
+    let dir = paths::cache_dir(app)?.join("player-sessions");
+    fs::create_dir_all(&dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let (contents, ext) = match player {
+        Player::Rv => (rv_session(playlist)?.into_bytes(), "rv"),
+        // mrv2 opens OTIO timelines as playlists
+        Player::Mrv2 => (
+            serde_json::to_vec_pretty(&otio::timeline("", playlist))?,
+            "otio",
+        ),
+    };
+    let path = dir.join(format!("astranotes-{stamp}.{ext}"));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+#[tauri::command]
+pub fn get_player_settings(app: AppHandle) -> Result<PlayerSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_player_settings(app: AppHandle, settings: PlayerSettings) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &settings)
+}
+
+/// Writes a session for `playlist` and opens it in `player`. Media paths are
+/// mapped to local mounts first. Returns the session file.
+#[tauri::command]
+pub async fn open_in_player(
+    app: AppHandle,
+    mut playlist: EditorialPlaylist,
+    player: Option<Player>,
+) -> Result<PathBuf> {
+    let settings = load_settings(&app)?;
+    let player = player.or(settings.default_player).unwrap_or(Player::Rv);
+    let pathmap = app.state::<PathMap>();
+    for version in &mut playlist.versions {
+        if let Some(media) = &version.media_path {
+            version.media_path = Some(pathmap.resolve_local(&media.to_string_lossy()).path);
+        }
+    }
+    let session = write_session(&app, player, &playlist)?;
+    let binary = player_binary(player, &settings);
+
+    let (mut events, _child) = app
+        .shell()
+        .command(binary.to_string_lossy().into_owned())
+        .arg(session.to_string_lossy().into_owned())
+        .spawn()
+        .map_err(|err| {
+            Error::InvalidInput(format!(
+                "Could not start {}: {err}. Check the player path in settings.",
+                binary.display()
+            ))
+        })?;
+    log::info!("Opened {} in {}", session.display(), binary.display());
+
+    // Drain the player's output so its pipes never fill up while it runs
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = events.recv().await {
+            match event {
+                CommandEvent::Stderr(line) => {
+                    log::debug!("player: {}", String::from_utf8_lossy(&line).trim_end())
+                }
+                CommandEvent::Terminated(status) => {
+                    log::info!("Player exited with {:?}", status.code)
+                }
+                _ => {}
+            }
+        }
+    });
+    Ok(session)
+}
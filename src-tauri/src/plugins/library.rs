@@ -0,0 +1,105 @@
+//! The C ABI a plugin library exports.
+//!
+//! ```c
+//! uint32_t astranotes_plugin_abi(void);
+//! char *astranotes_plugin_call(const char *request);
+//! void astranotes_plugin_free(char *response);
+//! ```
+//!
+//! Requests and responses are NUL-terminated UTF-8 JSON. A response is
+//! `{"ok": <value>}` or `{"error": "<message>"}`, and is handed back to the
+//! plugin to free once read. Calls may arrive from several threads at once,
+//! and a plugin must not let a panic or exception unwind into the host.
+
+use std::ffi::{CStr, CString, c_char};
+use std::path::Path;
+
+use libloading::Library;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+/// ABI version the host speaks, returned by `astranotes_plugin_abi`.
+pub(super) const ABI_VERSION: u32 = 1;
+
+type AbiFn = unsafe extern "C" fn() -> u32;
+type CallFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+pub(super) struct PluginLibrary {
+    call: CallFn,
+    free: FreeFn,
+    /// Keeps the code behind `call` and `free` mapped.
+    _library: Library,
+}
+
+fn failed(path: &Path, err: impl std::fmt::Display) -> Error {
+    Error::Plugin(format!("Could not load {}: {err}", path.display()))
+}
+
+impl PluginLibrary {
+    /// Loads the library and checks it speaks [`ABI_VERSION`].
+    ///
+    /// # Safety
+    ///
+    /// Loading runs the library's initializers, and the host trusts its
+    /// exports to match the signatures above. Only call this for a library
+    /// the user has enabled.
+    pub(super) unsafe fn load(path: &Path) -> Result<Self> {
+        let library = unsafe { Library::new(path) }.map_err(|err| failed(path, err))?;
+        let (abi, call, free) = unsafe {
+            let abi = *library
+                .get::<AbiFn>(b"astranotes_plugin_abi\0")
+                .map_err(|err| failed(path, err))?;
+            let call = *library
+                .get::<CallFn>(b"astranotes_plugin_call\0")
+                .map_err(|err| failed(path, err))?;
+            let free = *library
+                .get::<FreeFn>(b"astranotes_plugin_free\0")
+                .map_err(|err| failed(path, err))?;
+            (abi, call, free)
+        };
+        let version = unsafe { abi() };
+        if version != ABI_VERSION {
+            return Err(failed(
+                path,
+                format!("plugin ABI {version} is not supported, expected {ABI_VERSION}"),
+            ));
+        }
+        Ok(Self {
+            call,
+            free,
+            _library: library,
+        })
+    }
+
+    /// Sends one request and returns the plugin's `ok` value.
+    pub(super) fn call(&self, request: &Value) -> Result<Value> {
+        let request = CString::new(request.to_string())
+            .map_err(|_| Error::InvalidInput("Plugin request contains a NUL byte".into()))?;
+        let response = unsafe { (self.call)(request.as_ptr()) };
+        if response.is_null() {
+            return Err(Error::Plugin("Plugin returned no response".into()));
+        }
+        let text = unsafe { CStr::from_ptr(response) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { (self.free)(response) };
+
+        let mut response: Value = serde_json::from_str(&text)
+            .map_err(|err| Error::Plugin(format!("Invalid plugin response: {err}")))?;
+        if let Some(message) = response.get("error") {
+            let message = match message {
+                Value::String(message) => message.clone(),
+                other => other.to_string(),
+            };
+            return Err(Error::Plugin(message));
+        }
+        match response.get_mut("ok") {
+            Some(value) => Ok(value.take()),
+            None => Err(Error::Plugin(
+                "Plugin response has neither ok nor error".into(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,607 @@
+//! Studio-built extensions loaded as native libraries.
+//!
+//! Each plugin is a directory under `plugins/` in the config directory with a
+//! `plugin.json` manifest and a library per platform speaking the ABI in
+//! [`library`]. A plugin can add commands the frontend calls through
+//! [`invoke_plugin_command`], export formats for playlist notes, and handlers
+//! for the pipeline hook points.
+//!
+//! Plugins are off until enabled from settings. Enabling grants the
+//! capabilities the manifest asks for at that moment, and the host only
+//! routes commands, exports and events to a plugin whose grants cover them.
+//! A plugin whose manifest later asks for more stays unloaded until it is
+//! enabled again. Capabilities scope what AstraNotes hands a plugin; once
+//! loaded, the library runs with the same rights as the app itself, so only
+//! install plugins you trust.
+
+mod library;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::export;
+use crate::paths;
+use crate::pipeline_hooks::HookPoint;
+use library::PluginLibrary;
+
+const SETTINGS_FILE: &str = "plugins.json";
+const PLUGINS_DIR: &str = "plugins";
+const MANIFEST_FILE: &str = "plugin.json";
+
+/// What a plugin may have the host route to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    /// Commands the frontend can invoke by name.
+    Commands,
+    /// Export formats offered next to CSV, Excel and PDF.
+    ExportFormats,
+    /// Pipeline hook points, including a veto over publishes.
+    Events,
+}
+
+/// Library file per platform, relative to the plugin directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PluginLibraries {
+    pub macos: Option<PathBuf>,
+    pub windows: Option<PathBuf>,
+    pub linux: Option<PathBuf>,
+}
+
+impl PluginLibraries {
+    fn current(&self) -> Option<&Path> {
+        let library = if cfg!(target_os = "macos") {
+            &self.macos
+        } else if cfg!(windows) {
+            &self.windows
+        } else {
+            &self.linux
+        };
+        library.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginCommand {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginExportFormat {
+    pub id: String,
+    /// Shown in the export menu and the save dialog filter.
+    pub name: String,
+    pub extension: String,
+}
+
+/// Contents of `plugin.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub library: PluginLibraries,
+    #[serde(default)]
+    pub capabilities: BTreeSet<Capability>,
+    #[serde(default)]
+    pub commands: Vec<PluginCommand>,
+    #[serde(default)]
+    pub export_formats: Vec<PluginExportFormat>,
+    #[serde(default)]
+    pub events: Vec<HookPoint>,
+}
+
+impl Manifest {
+    /// Checks that everything the plugin registers is covered by a
+    /// capability it asks for.
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("The manifest has no id".into());
+        }
+        let required = [
+            (!self.commands.is_empty(), Capability::Commands, "commands"),
+            (
+                !self.export_formats.is_empty(),
+                Capability::ExportFormats,
+                "export formats",
+            ),
+            (!self.events.is_empty(), Capability::Events, "events"),
+        ];
+        for (used, capability, what) in required {
+            if used && !self.capabilities.contains(&capability) {
+                return Err(format!(
+                    "The manifest declares {what} without the {capability:?} capability"
+                ));
+            }
+        }
+        if self.library.current().is_none() {
+            return Err("The plugin has no library for this platform".into());
+        }
+        Ok(())
+    }
+}
+
+/// Capabilities granted when a plugin was enabled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Grant {
+    version: String,
+    capabilities: BTreeSet<Capability>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct PluginSettings {
+    enabled: BTreeMap<String, Grant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PluginStatus {
+    Disabled,
+    Enabled,
+    /// Enabled once, but the manifest now asks for capabilities that were
+    /// not granted.
+    NeedsApproval,
+    /// The manifest could not be read or is inconsistent.
+    Invalid,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    pub id: String,
+    pub dir: PathBuf,
+    pub status: PluginStatus,
+    pub manifest: Option<Manifest>,
+    pub granted: BTreeSet<Capability>,
+    pub error: Option<String>,
+}
+
+/// A plugin found on disk.
+struct Installed {
+    id: String,
+    dir: PathBuf,
+    manifest: std::result::Result<Manifest, String>,
+}
+
+impl Installed {
+    fn status(&self, grant: Option<&Grant>) -> PluginStatus {
+        match (&self.manifest, grant) {
+            (Err(_), _) => PluginStatus::Invalid,
+            (Ok(_), None) => PluginStatus::Disabled,
+            (Ok(manifest), Some(grant)) if manifest.capabilities.is_subset(&grant.capabilities) => {
+                PluginStatus::Enabled
+            }
+            (Ok(_), Some(_)) => PluginStatus::NeedsApproval,
+        }
+    }
+
+    fn info(&self, settings: &PluginSettings) -> PluginInfo {
+        let grant = settings.enabled.get(&self.id);
+        PluginInfo {
+            id: self.id.clone(),
+            dir: self.dir.clone(),
+            status: self.status(grant),
+            manifest: self.manifest.as_ref().ok().cloned(),
+            granted: grant
+                .map(|grant| grant.capabilities.clone())
+                .unwrap_or_default(),
+            error: self.manifest.as_ref().err().cloned(),
+        }
+    }
+}
+
+/// An enabled plugin, ready to be called.
+struct Active {
+    id: String,
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl Active {
+    fn library_path(&self) -> PathBuf {
+        self.dir
+            .join(self.manifest.library.current().unwrap_or(Path::new("")))
+    }
+}
+
+/// Libraries loaded so far, by plugin id. They stay loaded until the plugin
+/// is disabled.
+#[derive(Default)]
+pub struct Plugins {
+    loaded: Mutex<HashMap<String, Arc<PluginLibrary>>>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<PluginSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn save_settings(app: &AppHandle, settings: &PluginSettings) -> Result<()> {
+    paths::save_json(app, SETTINGS_FILE, settings)
+}
+
+fn read_manifest(dir: &Path) -> std::result::Result<Manifest, String> {
+    let contents = fs::read_to_string(dir.join(MANIFEST_FILE)).map_err(|err| err.to_string())?;
+    let manifest: Manifest = serde_json::from_str(&contents).map_err(|err| err.to_string())?;
+    manifest.validate()?;
+    Ok(manifest)
+}
+
+/// Every plugin directory, sorted by id. A directory whose manifest cannot
+/// be read is listed under its folder name.
+fn installed(app: &AppHandle) -> Result<Vec<Installed>> {
+    installed_in(&paths::config_dir(app)?)
+}
+
+fn installed_in(config_dir: &Path) -> Result<Vec<Installed>> {
+    let root = config_dir.join(PLUGINS_DIR);
+    let entries = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut plugins: Vec<Installed> = Vec::new();
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if !dir.is_dir() {
+            continue;
+        }
+        let mut manifest = read_manifest(&dir);
+        let id = match &manifest {
+            Ok(manifest) => manifest.id.clone(),
+            Err(_) => entry.file_name().to_string_lossy().into_owned(),
+        };
+        if plugins.iter().any(|plugin| plugin.id == id) {
+            manifest = Err(format!("Another plugin already uses the id {id}"));
+        }
+        plugins.push(Installed { id, dir, manifest });
+    }
+    plugins.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(plugins)
+}
+
+/// Enabled plugins whose grants still cover their manifest.
+fn active(app: &AppHandle) -> Result<Vec<Active>> {
+    Ok(enabled(&load_settings(app)?, installed(app)?))
+}
+
+fn enabled(settings: &PluginSettings, installed: Vec<Installed>) -> Vec<Active> {
+    installed
+        .into_iter()
+        .filter(|plugin| plugin.status(settings.enabled.get(&plugin.id)) == PluginStatus::Enabled)
+        .filter_map(|plugin| {
+            Some(Active {
+                id: plugin.id,
+                dir: plugin.dir,
+                manifest: plugin.manifest.ok()?,
+            })
+        })
+        .collect()
+}
+
+fn find_active(app: &AppHandle, id: &str) -> Result<Active> {
+    active(app)?
+        .into_iter()
+        .find(|plugin| plugin.id == id)
+        .ok_or_else(|| Error::InvalidInput(format!("Plugin {id} is not enabled")))
+}
+
+fn library(app: &AppHandle, plugin: &Active) -> Result<Arc<PluginLibrary>> {
+    let plugins = app.state::<Plugins>();
+    let mut loaded = plugins
+        .loaded
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(library) = loaded.get(&plugin.id) {
+        return Ok(library.clone());
+    }
+    // SAFETY: only enabled plugins get here, which the user has chosen to trust
+    let library = Arc::new(unsafe { PluginLibrary::load(&plugin.library_path()) }?);
+    log::info!("Loaded plugin {} {}", plugin.id, plugin.manifest.version);
+    loaded.insert(plugin.id.clone(), library.clone());
+    Ok(library)
+}
+
+/// Sends `request` to the plugin off the async runtime, with the details
+/// every request carries added.
+async fn call(app: &AppHandle, plugin: &Active, request: Value) -> Result<Value> {
+    let library = library(app, plugin)?;
+    let app_version = app.package_info().version.to_string();
+    send(library, plugin, &app_version, request).await
+}
+
+async fn send(
+    library: Arc<PluginLibrary>,
+    plugin: &Active,
+    app_version: &str,
+    mut request: Value,
+) -> Result<Value> {
+    request["host"] = json!({
+        "appVersion": app_version,
+        "pluginDir": plugin.dir,
+    });
+    tauri::async_runtime::spawn_blocking(move || library.call(&request)).await?
+}
+
+fn event_request(point: HookPoint, payload: &Value) -> Value {
+    json!({ "type": "event", "event": point.as_str(), "payload": payload })
+}
+
+fn subscribed(point: HookPoint) -> impl Fn(&Active) -> bool {
+    move |plugin| plugin.manifest.events.contains(&point)
+}
+
+/// Hands a hook point to every plugin subscribed to it. Failures are logged.
+pub(crate) async fn dispatch(app: &AppHandle, point: HookPoint, payload: &Value) {
+    let plugins = match active(app) {
+        Ok(plugins) => plugins,
+        Err(err) => {
+            log::warn!("Failed to list plugins for {point:?}: {err}");
+            return;
+        }
+    };
+    for plugin in plugins.iter().filter(|plugin| subscribed(point)(plugin)) {
+        if let Err(err) = call(app, plugin, event_request(point, payload)).await {
+            log::warn!("Plugin {} failed on {point:?}: {err}", plugin.id);
+        }
+    }
+}
+
+/// Offers a publish to plugins subscribed to `pre_publish`. A plugin stops
+/// it by answering with an error.
+pub(crate) async fn before_publish(app: &AppHandle, payload: &Value) -> Result<()> {
+    let point = HookPoint::PrePublish;
+    for plugin in active(app)?
+        .iter()
+        .filter(|plugin| subscribed(point)(plugin))
+    {
+        if let Err(err) = call(app, plugin, event_request(point, payload)).await {
+            return Err(blocked(plugin, err));
+        }
+    }
+    Ok(())
+}
+
+/// [`before_publish`] for the headless commands. Each library is loaded for
+/// this call only.
+pub(crate) async fn before_publish_standalone(payload: &Value) -> Result<()> {
+    let Some(config_dir) = paths::standalone_config_dir() else {
+        return Ok(());
+    };
+    let settings: PluginSettings = paths::read_json(&config_dir.join(SETTINGS_FILE))?;
+    let point = HookPoint::PrePublish;
+    for plugin in enabled(&settings, installed_in(&config_dir)?)
+        .iter()
+        .filter(|plugin| subscribed(point)(plugin))
+    {
+        let request = event_request(point, payload);
+        // SAFETY: only enabled plugins get here, which the user has chosen to trust
+        let result = match unsafe { PluginLibrary::load(&plugin.library_path()) } {
+            Ok(library) => {
+                send(
+                    Arc::new(library),
+                    plugin,
+                    env!("CARGO_PKG_VERSION"),
+                    request,
+                )
+                .await
+            }
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            return Err(blocked(plugin, err));
+        }
+    }
+    Ok(())
+}
+
+fn blocked(plugin: &Active, err: Error) -> Error {
+    Error::InvalidInput(format!(
+        "Publish blocked by {}: {err}",
+        plugin.manifest.name
+    ))
+}
+
+/// Installed plugins with their manifests and grants.
+#[tauri::command]
+pub fn list_plugins(app: AppHandle) -> Result<Vec<PluginInfo>> {
+    let settings = load_settings(&app)?;
+    Ok(installed(&app)?
+        .iter()
+        .map(|plugin| plugin.info(&settings))
+        .collect())
+}
+
+/// Enables a plugin, granting the capabilities its manifest asks for, or
+/// disables it. Enabling loads the library first, so a plugin that does not
+/// match this platform or ABI is never marked enabled.
+#[tauri::command]
+pub fn enable_plugin(
+    app: AppHandle,
+    plugins: State<'_, Plugins>,
+    id: String,
+    enabled: bool,
+) -> Result<PluginInfo> {
+    let installed = installed(&app)?;
+    let Some(plugin) = installed.iter().find(|plugin| plugin.id == id) else {
+        return Err(Error::InvalidInput(format!("No plugin with id {id}")));
+    };
+    // A reload picks up a rebuilt library
+    plugins
+        .loaded
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .remove(&id);
+
+    let mut settings = load_settings(&app)?;
+    if enabled {
+        let manifest = plugin.manifest.clone().map_err(Error::InvalidInput)?;
+        let active = Active {
+            id: id.clone(),
+            dir: plugin.dir.clone(),
+            manifest,
+        };
+        library(&app, &active)?;
+        settings.enabled.insert(
+            id.clone(),
+            Grant {
+                version: active.manifest.version.clone(),
+                capabilities: active.manifest.capabilities.clone(),
+            },
+        );
+        log::info!("Enabled plugin {id}");
+    } else {
+        settings.enabled.remove(&id);
+        log::info!("Disabled plugin {id}");
+    }
+    save_settings(&app, &settings)?;
+    Ok(plugin.info(&settings))
+}
+
+/// Runs a command a plugin registered. `args` is passed through as is.
+#[tauri::command]
+pub async fn invoke_plugin_command(
+    app: AppHandle,
+    plugin_id: String,
+    command: String,
+    args: Option<Value>,
+) -> Result<Value> {
+    let plugin = find_active(&app, &plugin_id)?;
+    if !plugin
+        .manifest
+        .commands
+        .iter()
+        .any(|registered| registered.name == command)
+    {
+        return Err(Error::InvalidInput(format!(
+            "Plugin {plugin_id} has no command {command}"
+        )));
+    }
+    call(
+        &app,
+        &plugin,
+        json!({
+            "type": "command",
+            "command": command,
+            "args": args.unwrap_or(Value::Null),
+        }),
+    )
+    .await
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableExportFormat {
+    pub plugin_id: String,
+    #[serde(flatten)]
+    pub format: PluginExportFormat,
+}
+
+/// Export formats of every enabled plugin, for the export menu.
+#[tauri::command]
+pub fn list_plugin_export_formats(app: AppHandle) -> Result<Vec<AvailableExportFormat>> {
+    Ok(active(&app)?
+        .into_iter()
+        .flat_map(|plugin| {
+            let plugin_id = plugin.id;
+            plugin
+                .manifest
+                .export_formats
+                .into_iter()
+                .map(move |format| AvailableExportFormat {
+                    plugin_id: plugin_id.clone(),
+                    format,
+                })
+        })
+        .collect())
+}
+
+/// Exports rows in a plugin's format. The plugin writes the file at the path
+/// it is given, which is renamed into place once it answers. Returns the
+/// written path, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_with_plugin(
+    app: AppHandle,
+    plugin_id: String,
+    format: String,
+    playlist_id: String,
+    playlist_name: Option<String>,
+    rows: Vec<Value>,
+    path: Option<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    let plugin = find_active(&app, &plugin_id)?;
+    let Some(export_format) = plugin
+        .manifest
+        .export_formats
+        .iter()
+        .find(|candidate| candidate.id == format)
+        .cloned()
+    else {
+        return Err(Error::InvalidInput(format!(
+            "Plugin {plugin_id} has no export format {format}"
+        )));
+    };
+    let name = playlist_name.unwrap_or_else(|| playlist_id.clone());
+    let Some(path) = export::resolve_path(
+        &app,
+        path,
+        &name,
+        &export_format.name,
+        &export_format.extension,
+    )
+    .await
+    else {
+        return Ok(None);
+    };
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".part");
+    let temp = PathBuf::from(temp);
+    let request = json!({
+        "type": "export",
+        "format": format,
+        "path": temp,
+        "playlistId": playlist_id,
+        "playlistName": name,
+        "rows": rows,
+    });
+    let written = call(&app, &plugin, request).await.and_then(|_| {
+        if temp.is_file() {
+            Ok(())
+        } else {
+            Err(Error::Plugin(format!(
+                "Plugin {plugin_id} did not write {}",
+                temp.display()
+            )))
+        }
+    });
+    if let Err(err) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    fs::rename(&temp, &path)?;
+    log::info!(
+        "Exported notes for playlist {playlist_id} with plugin {plugin_id} to {}",
+        path.display()
+    );
+    export::exported(&app, &format!("{plugin_id}:{format}"), &playlist_id, &path);
+    Ok(Some(path))
+}
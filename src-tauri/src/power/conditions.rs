@@ -0,0 +1,119 @@
+//! Power source and network cost, so background work can wait for mains
+//! power and an unmetered connection. Both return `None` when the platform
+//! cannot tell, which callers treat as unrestricted.
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+async fn output(program: &str, args: &[&str]) -> Option<String> {
+    let mut command = tokio::process::Command::new(program);
+    command.args(args);
+    // Keep PowerShell from flashing a console window
+    #[cfg(target_os = "windows")]
+    command.creation_flags(0x0800_0000);
+    let output = command.output().await.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+pub async fn on_battery() -> Option<bool> {
+    let mut entries = tokio::fs::read_dir("/sys/class/power_supply").await.ok()?;
+    let mut has_battery = false;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let kind = tokio::fs::read_to_string(path.join("type"))
+            .await
+            .unwrap_or_default();
+        match kind.trim() {
+            "Mains" | "USB" => {
+                let online = tokio::fs::read_to_string(path.join("online"))
+                    .await
+                    .unwrap_or_default();
+                if online.trim() == "1" {
+                    return Some(false);
+                }
+            }
+            "Battery" => has_battery = true,
+            _ => {}
+        }
+    }
+    has_battery.then_some(true)
+}
+
+#[cfg(target_os = "macos")]
+pub async fn on_battery() -> Option<bool> {
+    let status = output("pmset", &["-g", "batt"]).await?;
+    if status.contains("'Battery Power'") {
+        Some(true)
+    } else if status.contains("'AC Power'") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub async fn on_battery() -> Option<bool> {
+    let status = output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "(Get-CimInstance -ClassName Win32_Battery).BatteryStatus",
+        ],
+    )
+    .await?;
+    // No battery prints nothing; 1 means discharging
+    Some(status.lines().any(|line| line.trim() == "1"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub async fn on_battery() -> Option<bool> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+pub async fn is_metered() -> Option<bool> {
+    // NetworkManager's NMMetered: 1 yes, 2 no, 3 guessed yes, 4 guessed no
+    let metered = output(
+        "busctl",
+        &[
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ],
+    )
+    .await?;
+    match metered.as_str() {
+        "u 1" | "u 3" => Some(true),
+        "u 2" | "u 4" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub async fn is_metered() -> Option<bool> {
+    let cost = output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType",
+        ],
+    )
+    .await?;
+    match cost.as_str() {
+        "Unrestricted" => Some(false),
+        "Fixed" | "Variable" => Some(true),
+        _ => None,
+    }
+}
+
+/// macOS offers no command-line view of whether a network is expensive.
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub async fn is_metered() -> Option<bool> {
+    None
+}
@@ -0,0 +1,46 @@
+//! Sleep notices from systemd-logind.
+//!
+//! A delay inhibitor holds off sleep for a few seconds after
+//! `PrepareForSleep`, long enough to pause downloads cleanly; it is released
+//! once they are and taken again on waking.
+
+use futures_util::StreamExt;
+use tauri::AppHandle;
+use zbus::zvariant::OwnedFd;
+use zbus::{Connection, Proxy};
+
+pub(super) async fn run(app: AppHandle) -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )
+    .await?;
+    let mut signals = manager.receive_signal("PrepareForSleep").await?;
+    let mut inhibitor = inhibit(&manager).await;
+    while let Some(message) = signals.next().await {
+        let sleeping: bool = message.body().deserialize()?;
+        if sleeping {
+            super::suspend(&app);
+            // Closing the descriptor lets the system go to sleep
+            drop(inhibitor.take());
+        } else {
+            super::resume(&app, None);
+            inhibitor = inhibit(&manager).await;
+        }
+    }
+    Ok(())
+}
+
+async fn inhibit(manager: &Proxy<'_>) -> Option<OwnedFd> {
+    manager
+        .call(
+            "Inhibit",
+            &("sleep", "AstraNotes", "Pausing downloads and sync", "delay"),
+        )
+        .await
+        .inspect_err(|err| log::warn!("Failed to take a sleep inhibitor: {err}"))
+        .ok()
+}
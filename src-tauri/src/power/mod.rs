@@ -0,0 +1,344 @@
+//! System sleep, wake and power source handling.
+//!
+//! Laptops used to wake to broken downloads and a burst of connection errors
+//! from jobs that carried on as if nothing had happened. Going to sleep now
+//! pauses running downloads, holds the sync and publish workers and stops
+//! watcher events; on Linux logind gives notice through a delay inhibitor.
+//! Waking is noticed from logind or from the wall clock jumping past a tick,
+//! which works everywhere. Work stays held until connectivity has been probed
+//! again, then downloads resume from their partial files, the workers wake
+//! and watches are re-registered. Changes are emitted as `power:suspended`,
+//! `power:resumed` and `power:battery`.
+//!
+//! When `throttleOnBattery` is set in `power.json`, running on battery also
+//! limits background prefetching.
+
+pub mod conditions;
+#[cfg(target_os = "linux")]
+mod logind;
+
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::connectivity::{self, ConnectivityMonitor, ConnectivityState};
+use crate::downloads::DownloadManager;
+use crate::error::Result;
+use crate::paths;
+use crate::store::now_millis;
+use crate::watcher::FileWatcher;
+
+const SETTINGS_FILE: &str = "power.json";
+pub const SUSPENDED_EVENT: &str = "power:suspended";
+pub const RESUMED_EVENT: &str = "power:resumed";
+pub const BATTERY_EVENT: &str = "power:battery";
+const TICK: Duration = Duration::from_secs(5);
+/// A tick taking this much longer than [`TICK`] by the wall clock means the system slept.
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+const BATTERY_INTERVAL: Duration = Duration::from_secs(60);
+/// How long work stays held after waking while the server is unreachable.
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(90);
+const SETTLE_POLL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PowerSettings {
+    pub throttle_on_battery: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerState {
+    #[default]
+    Awake,
+    Suspended,
+    /// Awake again, holding work until the connection is back.
+    Resuming,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerStatus {
+    pub state: PowerState,
+    /// `None` when the platform cannot tell.
+    pub on_battery: Option<bool>,
+    pub throttled: bool,
+    pub suspended_at: Option<i64>,
+    pub resumed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Resumed {
+    #[serde(flatten)]
+    status: PowerStatus,
+    slept_ms: Option<u64>,
+    resumed_downloads: usize,
+}
+
+#[derive(Default)]
+struct Inner {
+    state: PowerState,
+    on_battery: Option<bool>,
+    suspended_at: Option<i64>,
+    resumed_at: Option<i64>,
+    /// Downloads paused for sleep, resumed once work is released.
+    paused_downloads: Vec<String>,
+    /// Bumped on every suspend, so a stale settle does not release work.
+    generation: u64,
+}
+
+#[derive(Default)]
+pub struct PowerMonitor {
+    inner: Mutex<Inner>,
+    settings: Mutex<PowerSettings>,
+}
+
+impl PowerMonitor {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn throttle_on_battery(&self) -> bool {
+        self.settings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .throttle_on_battery
+    }
+
+    pub fn status(&self) -> PowerStatus {
+        let throttle = self.throttle_on_battery();
+        let inner = self.lock();
+        PowerStatus {
+            state: inner.state,
+            on_battery: inner.on_battery,
+            throttled: throttle && inner.on_battery == Some(true),
+            suspended_at: inner.suspended_at,
+            resumed_at: inner.resumed_at,
+        }
+    }
+}
+
+/// Whether background work should wait because the system is asleep or just woke.
+pub fn held(app: &AppHandle) -> bool {
+    app.try_state::<PowerMonitor>()
+        .is_some_and(|monitor| monitor.lock().state != PowerState::Awake)
+}
+
+/// Whether background work should run at reduced rate to save battery.
+pub fn throttled(app: &AppHandle) -> bool {
+    app.try_state::<PowerMonitor>()
+        .is_some_and(|monitor| monitor.status().throttled)
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(err) = app.emit(event, payload) {
+        log::warn!("Failed to emit {event}: {err}");
+    }
+}
+
+/// Pauses work for sleep. Returns `false` when it already was.
+fn hold(app: &AppHandle, monitor: &PowerMonitor) -> bool {
+    let mut inner = monitor.lock();
+    if inner.state == PowerState::Suspended {
+        return false;
+    }
+    inner.state = PowerState::Suspended;
+    inner.suspended_at = Some(now_millis());
+    inner.generation += 1;
+    let paused = app.state::<DownloadManager>().pause_running();
+    inner.paused_downloads.extend(paused);
+    if let Some(watcher) = app.try_state::<FileWatcher>() {
+        watcher.suspend();
+    }
+    true
+}
+
+pub(crate) fn suspend(app: &AppHandle) {
+    let monitor = app.state::<PowerMonitor>();
+    if hold(app, &monitor) {
+        log::info!("System is going to sleep, pausing background work");
+        emit(app, SUSPENDED_EVENT, monitor.status());
+    }
+}
+
+/// Starts releasing held work. `slept` is the gap seen on the wall clock,
+/// for when the system slept without notice.
+pub(crate) fn resume(app: &AppHandle, slept: Option<Duration>) {
+    let monitor = app.state::<PowerMonitor>();
+    // Copied out so the guard is dropped before `hold` locks again
+    let state = monitor.lock().state;
+    match state {
+        PowerState::Suspended => {}
+        // Connections did not survive the sleep, so stop the downloads using them
+        PowerState::Awake if slept.is_some() => {
+            hold(app, &monitor);
+        }
+        _ => return,
+    }
+    let (generation, slept_ms) = {
+        let mut inner = monitor.lock();
+        let now = now_millis();
+        inner.state = PowerState::Resuming;
+        inner.resumed_at = Some(now);
+        let slept_ms = match slept {
+            Some(slept) => slept.as_millis() as u64,
+            None => inner
+                .suspended_at
+                .map_or(0, |at| now.saturating_sub(at).max(0) as u64),
+        };
+        (inner.generation, slept_ms)
+    };
+    log::info!("System woke after {}s", slept_ms / 1000);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        settle(&app, generation).await;
+        release(&app, generation, slept_ms);
+    });
+}
+
+/// Waits for a fresh successful probe, or gives up after [`SETTLE_TIMEOUT`].
+async fn settle(app: &AppHandle, generation: u64) {
+    let resumed_at = now_millis();
+    let connectivity = app.state::<ConnectivityMonitor>();
+    connectivity.wake();
+    check_battery(app).await;
+    let deadline = tokio::time::Instant::now() + SETTLE_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        if app.state::<PowerMonitor>().lock().generation != generation {
+            return;
+        }
+        let status = connectivity.status();
+        if status.state == ConnectivityState::Online
+            && status.checked_at.is_some_and(|at| at >= resumed_at)
+        {
+            return;
+        }
+        tokio::time::sleep(SETTLE_POLL).await;
+    }
+    log::warn!("Server still unreachable after waking, resuming background work anyway");
+}
+
+fn release(app: &AppHandle, generation: u64, slept_ms: u64) {
+    let monitor = app.state::<PowerMonitor>();
+    let paused = {
+        let mut inner = monitor.lock();
+        // Went back to sleep while waiting
+        if inner.generation != generation || inner.state != PowerState::Resuming {
+            return;
+        }
+        inner.state = PowerState::Awake;
+        std::mem::take(&mut inner.paused_downloads)
+    };
+    if let Some(watcher) = app.try_state::<FileWatcher>() {
+        watcher.resume();
+    }
+    let resumed_downloads = app.state::<DownloadManager>().resume_paused(&paused);
+    connectivity::wake_workers(app);
+    emit(
+        app,
+        RESUMED_EVENT,
+        Resumed {
+            status: monitor.status(),
+            slept_ms: Some(slept_ms),
+            resumed_downloads,
+        },
+    );
+}
+
+async fn check_battery(app: &AppHandle) {
+    let on_battery = conditions::on_battery().await;
+    let monitor = app.state::<PowerMonitor>();
+    let changed = {
+        let mut inner = monitor.lock();
+        std::mem::replace(&mut inner.on_battery, on_battery) != on_battery
+    };
+    if changed {
+        log::info!("Power source changed, on battery: {on_battery:?}");
+        emit(app, BATTERY_EVENT, monitor.status());
+    }
+}
+
+/// Notices sleep after the fact from the wall clock racing ahead of a tick.
+async fn watch_clock(app: AppHandle) {
+    let mut last = SystemTime::now();
+    loop {
+        tokio::time::sleep(TICK).await;
+        let now = SystemTime::now();
+        let elapsed = now.duration_since(last).unwrap_or_default();
+        last = now;
+        if elapsed > TICK + SLEEP_THRESHOLD {
+            resume(&app, Some(elapsed - TICK));
+        }
+    }
+}
+
+fn load_settings(app: &AppHandle) -> Result<PowerSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+/// Starts watching for sleep, wake and power source changes.
+pub fn init(app: &AppHandle) {
+    let monitor = PowerMonitor::default();
+    match load_settings(app) {
+        Ok(settings) => {
+            *monitor
+                .settings
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = settings
+        }
+        Err(err) => log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}"),
+    }
+    app.manage(monitor);
+
+    tauri::async_runtime::spawn(watch_clock(app.clone()));
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            check_battery(&handle).await;
+            tokio::time::sleep(BATTERY_INTERVAL).await;
+        }
+    });
+    #[cfg(target_os = "linux")]
+    {
+        let handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = logind::run(handle).await {
+                log::info!("No sleep notices from logind, relying on the clock: {err}");
+            }
+        });
+    }
+}
+
+#[tauri::command]
+pub fn get_power_status(monitor: State<'_, PowerMonitor>) -> PowerStatus {
+    monitor.status()
+}
+
+#[tauri::command]
+pub fn get_power_settings(app: AppHandle) -> Result<PowerSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_power_settings(
+    app: AppHandle,
+    monitor: State<'_, PowerMonitor>,
+    settings: PowerSettings,
+) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &settings)?;
+    let was_throttled = monitor.status().throttled;
+    *monitor
+        .settings
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = settings;
+    let status = monitor.status();
+    if status.throttled != was_throttled {
+        emit(&app, BATTERY_EVENT, status);
+    }
+    Ok(())
+}
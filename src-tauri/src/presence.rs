@@ -0,0 +1,346 @@
+//! Who is writing a note on which version.
+//!
+//! A reviewer takes a soft lock on a version while drafting a note on it.
+//! Locks travel between instances as `astranotes.presence` events on the
+//! ftrack event hub, so they reach everyone on the same server. Nothing
+//! enforces them: acquiring a version someone else holds reports the holder
+//! instead, and `force` takes it anyway.
+//!
+//! Locks expire unless renewed. Ours renew while the draft is being touched
+//! and lapse after [`IDLE_TIMEOUT`] without one, so a crashed or forgotten
+//! instance frees its versions on its own.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::event_hub;
+use crate::store::now_millis;
+
+pub const TOPIC: &str = "astranotes.presence";
+pub(crate) const SUBSCRIPTION: &str = "topic=astranotes.presence";
+pub const CHANGED_EVENT: &str = "presence:changed";
+
+/// How long a lock lasts without renewal.
+const LOCK_TTL: Duration = Duration::from_secs(60);
+const RENEW_INTERVAL: Duration = Duration::from_secs(20);
+/// Our locks are released after this long without a touch.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Tells our own events apart from other instances' on the hub.
+static CLIENT_ID: LazyLock<String> = LazyLock::new(|| uuid::Uuid::new_v4().to_string());
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteLock {
+    pub version_id: String,
+    /// ftrack username of the holder; `None` for our own locks.
+    pub user: Option<String>,
+    pub mine: bool,
+    pub acquired_at: i64,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockResult {
+    pub acquired: bool,
+    /// Other reviewers holding the version.
+    pub holders: Vec<NoteLock>,
+}
+
+struct Held {
+    acquired_at: i64,
+    touched_at: i64,
+}
+
+struct Remote {
+    user: Option<String>,
+    acquired_at: i64,
+    /// In our clock, from the TTL the holder sent.
+    expires_at: i64,
+}
+
+#[derive(Default)]
+pub struct Presence {
+    held: Mutex<HashMap<String, Held>>,
+    /// Keyed by (version id, client id).
+    remote: Mutex<HashMap<(String, String), Remote>>,
+}
+
+impl Presence {
+    fn held(&self) -> std::sync::MutexGuard<'_, HashMap<String, Held>> {
+        self.held
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn remote(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), Remote>> {
+        self.remote
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn holders(&self, version_id: &str, now: i64) -> Vec<NoteLock> {
+        self.remote()
+            .iter()
+            .filter(|((version, _), lock)| version == version_id && lock.expires_at > now)
+            .map(|((version, _), lock)| NoteLock {
+                version_id: version.clone(),
+                user: lock.user.clone(),
+                mine: false,
+                acquired_at: lock.acquired_at,
+                expires_at: lock.expires_at,
+            })
+            .collect()
+    }
+
+    fn locks(&self) -> Vec<NoteLock> {
+        let now = now_millis();
+        let idle = IDLE_TIMEOUT.as_millis() as i64;
+        let mut locks: Vec<NoteLock> = self
+            .held()
+            .iter()
+            .map(|(version_id, held)| NoteLock {
+                version_id: version_id.clone(),
+                user: None,
+                mine: true,
+                acquired_at: held.acquired_at,
+                expires_at: held.touched_at + idle,
+            })
+            .collect();
+        let mut versions: Vec<String> = self
+            .remote()
+            .keys()
+            .map(|(version, _)| version.clone())
+            .collect();
+        versions.sort();
+        versions.dedup();
+        for version in versions {
+            locks.extend(self.holders(&version, now));
+        }
+        locks.sort_by(|a, b| {
+            a.version_id
+                .cmp(&b.version_id)
+                .then(a.acquired_at.cmp(&b.acquired_at))
+        });
+        locks
+    }
+}
+
+fn announce(app: &AppHandle, action: &str, version_id: &str, acquired_at: i64) {
+    event_hub::publish(
+        app,
+        TOPIC,
+        json!({
+            "action": action,
+            "versionId": version_id,
+            "clientId": *CLIENT_ID,
+            "acquiredAt": acquired_at,
+            "ttlMs": LOCK_TTL.as_millis() as u64,
+        }),
+    );
+}
+
+fn emit_changed(app: &AppHandle) {
+    let Some(presence) = app.try_state::<Presence>() else {
+        return;
+    };
+    let locks = presence.locks();
+    if let Err(err) = app.emit(CHANGED_EVENT, &locks) {
+        log::warn!("Failed to emit presence change: {err}");
+    }
+}
+
+/// Renews our locks and lets idle and expired ones go.
+fn sweep(app: &AppHandle) {
+    let presence = app.state::<Presence>();
+    let now = now_millis();
+    let idle = IDLE_TIMEOUT.as_millis() as i64;
+    let mut changed = false;
+
+    let mut renew = Vec::new();
+    let mut released = Vec::new();
+    presence.held().retain(|version_id, held| {
+        if now - held.touched_at > idle {
+            released.push(version_id.clone());
+            false
+        } else {
+            renew.push((version_id.clone(), held.acquired_at));
+            true
+        }
+    });
+    for version_id in &released {
+        log::info!("Released idle note lock on {version_id}");
+        announce(app, "release", version_id, now);
+        changed = true;
+    }
+    for (version_id, acquired_at) in &renew {
+        announce(app, "lock", version_id, *acquired_at);
+    }
+
+    let mut remote = presence.remote();
+    let before = remote.len();
+    remote.retain(|_, lock| lock.expires_at > now);
+    changed |= remote.len() != before;
+    drop(remote);
+    if changed {
+        emit_changed(app);
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(Presence::default());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(RENEW_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweep(&app);
+        }
+    });
+}
+
+/// Re-announces our locks once the hub (re)connects, so others need not
+/// wait for the next renewal.
+pub(crate) fn connected(app: &AppHandle) {
+    // The hub can connect before presence has started
+    let Some(presence) = app.try_state::<Presence>() else {
+        return;
+    };
+    let held: Vec<(String, i64)> = presence
+        .held()
+        .iter()
+        .map(|(version_id, held)| (version_id.clone(), held.acquired_at))
+        .collect();
+    for (version_id, acquired_at) in held {
+        announce(app, "lock", &version_id, acquired_at);
+    }
+}
+
+/// Applies a presence event from another instance.
+pub(crate) fn received(app: &AppHandle, event: &Value) {
+    let data = &event["data"];
+    let (Some(client_id), Some(version_id)) =
+        (data["clientId"].as_str(), data["versionId"].as_str())
+    else {
+        return;
+    };
+    if client_id == *CLIENT_ID {
+        return;
+    }
+    let key = (version_id.to_string(), client_id.to_string());
+    let Some(presence) = app.try_state::<Presence>() else {
+        return;
+    };
+    match data["action"].as_str() {
+        Some("lock") => {
+            let now = now_millis();
+            // Never trust a holder to keep a version for long without renewing
+            let ttl = data["ttlMs"]
+                .as_i64()
+                .unwrap_or(LOCK_TTL.as_millis() as i64)
+                .clamp(0, IDLE_TIMEOUT.as_millis() as i64);
+            presence.remote().insert(
+                key,
+                Remote {
+                    user: event["source"]["user"]["username"]
+                        .as_str()
+                        .map(str::to_string),
+                    acquired_at: data["acquiredAt"].as_i64().unwrap_or(now),
+                    expires_at: now + ttl,
+                },
+            );
+        }
+        Some("release") => {
+            presence.remote().remove(&key);
+        }
+        _ => return,
+    }
+    emit_changed(app);
+}
+
+/// Takes the soft lock on a version, or renews ours. When someone else holds
+/// it, nothing is taken unless `force` is set; either way the holders are
+/// returned so the frontend can say who is writing.
+#[tauri::command]
+pub fn acquire_note_lock(
+    app: AppHandle,
+    presence: State<'_, Presence>,
+    version_id: String,
+    force: Option<bool>,
+) -> Result<LockResult> {
+    let version_id = version_id.trim().to_string();
+    if version_id.is_empty() {
+        return Err(Error::InvalidInput("A version id is required".into()));
+    }
+    let now = now_millis();
+    let holders = presence.holders(&version_id, now);
+    let mut held = presence.held();
+    if let Some(lock) = held.get_mut(&version_id) {
+        lock.touched_at = now;
+        return Ok(LockResult {
+            acquired: true,
+            holders,
+        });
+    }
+    if !holders.is_empty() && !force.unwrap_or(false) {
+        return Ok(LockResult {
+            acquired: false,
+            holders,
+        });
+    }
+    held.insert(
+        version_id.clone(),
+        Held {
+            acquired_at: now,
+            touched_at: now,
+        },
+    );
+    drop(held);
+    announce(&app, "lock", &version_id, now);
+    emit_changed(&app);
+    Ok(LockResult {
+        acquired: true,
+        holders,
+    })
+}
+
+/// Releases our lock on a version, or all of them without one.
+#[tauri::command]
+pub fn release_note_lock(
+    app: AppHandle,
+    presence: State<'_, Presence>,
+    version_id: Option<String>,
+) {
+    let released: Vec<String> = {
+        let mut held = presence.held();
+        match version_id {
+            Some(version_id) => held
+                .remove_entry(version_id.trim())
+                .map(|(version_id, _)| version_id)
+                .into_iter()
+                .collect(),
+            None => held.drain().map(|(version_id, _)| version_id).collect(),
+        }
+    };
+    if released.is_empty() {
+        return;
+    }
+    let now = now_millis();
+    for version_id in &released {
+        announce(&app, "release", version_id, now);
+    }
+    emit_changed(&app);
+}
+
+/// Every live lock, ours and other reviewers', by version.
+#[tauri::command]
+pub fn list_note_locks(presence: State<'_, Presence>) -> Vec<NoteLock> {
+    presence.locks()
+}
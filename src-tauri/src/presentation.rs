@@ -0,0 +1,149 @@
+//! Full-screen presentation window for client-facing review.
+//!
+//! The window opens on a chosen display with no decorations and stays on top.
+//! It is fed only through [`PresentationFrame`], which has fields for media
+//! and version names and nothing else, so internal note text cannot reach a
+//! client's screen by mistake. With input passthrough on, clicks fall through
+//! to whatever is behind the window. That suits a presenter driving the main
+//! window on the same display.
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Emitter, Manager, Monitor, PhysicalPosition, WebviewUrl, WebviewWindowBuilder,
+};
+
+use crate::error::{Error, Result};
+
+const LABEL: &str = "presentation";
+pub const FRAME_EVENT: &str = "presentation:frame";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Display {
+    pub index: usize,
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub primary: bool,
+    /// Whether the main window is on this display.
+    pub current: bool,
+}
+
+/// What the presentation window may show.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresentationFrame {
+    #[serde(default)]
+    pub media_url: Option<String>,
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    #[serde(default)]
+    pub version_name: Option<String>,
+    #[serde(default)]
+    pub frame: Option<u32>,
+    #[serde(default)]
+    pub playing: bool,
+}
+
+fn same(a: &Monitor, b: &Monitor) -> bool {
+    a.name() == b.name() && a.position() == b.position()
+}
+
+fn monitors(app: &AppHandle) -> Result<Vec<Monitor>> {
+    Ok(app.available_monitors()?)
+}
+
+#[tauri::command]
+pub fn list_displays(app: AppHandle) -> Result<Vec<Display>> {
+    let primary = app.primary_monitor()?;
+    let current = app
+        .get_webview_window("main")
+        .and_then(|window| window.current_monitor().ok().flatten());
+    Ok(monitors(&app)?
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| Display {
+            index,
+            name: monitor.name().cloned(),
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+            scale_factor: monitor.scale_factor(),
+            primary: primary
+                .as_ref()
+                .is_some_and(|primary| same(primary, monitor)),
+            current: current
+                .as_ref()
+                .is_some_and(|current| same(current, monitor)),
+        })
+        .collect())
+}
+
+/// Opens the presentation window full screen on display `index` from
+/// [`list_displays`], or moves it there if it is already open.
+#[tauri::command]
+pub async fn open_presentation_window(
+    app: AppHandle,
+    index: usize,
+    passthrough: Option<bool>,
+) -> Result<()> {
+    let monitor = monitors(&app)?
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| Error::InvalidInput(format!("No display {index}")))?;
+
+    let window = match app.get_webview_window(LABEL) {
+        Some(window) => {
+            // Leave full screen first, or the move is ignored on some platforms
+            window.set_fullscreen(false)?;
+            window
+        }
+        None => WebviewWindowBuilder::new(
+            &app,
+            LABEL,
+            WebviewUrl::App("index.html?window=presentation".into()),
+        )
+        .title("AstraNotes - Presentation")
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .visible(false)
+        .build()?,
+    };
+    let PhysicalPosition { x, y } = *monitor.position();
+    window.set_position(PhysicalPosition::new(x, y))?;
+    window.set_fullscreen(true)?;
+    window.set_ignore_cursor_events(passthrough.unwrap_or(false))?;
+    window.show()?;
+    Ok(())
+}
+
+/// Sends what to show next. Does nothing when the window is closed.
+#[tauri::command]
+pub fn update_presentation(app: AppHandle, frame: PresentationFrame) -> Result<bool> {
+    if app.get_webview_window(LABEL).is_none() {
+        return Ok(false);
+    }
+    app.emit_to(LABEL, FRAME_EVENT, frame)?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn set_presentation_passthrough(app: AppHandle, enabled: bool) -> Result<()> {
+    let window = app
+        .get_webview_window(LABEL)
+        .ok_or_else(|| Error::InvalidInput("Presentation window is not open".into()))?;
+    Ok(window.set_ignore_cursor_events(enabled)?)
+}
+
+#[tauri::command]
+pub fn close_presentation_window(app: AppHandle) -> Result<()> {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        window.close()?;
+    }
+    Ok(())
+}
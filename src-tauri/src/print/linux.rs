@@ -0,0 +1,156 @@
+//! The GTK print dialog, sending the report to the chosen printer as a PDF.
+//!
+//! The Unix print dialog and print jobs live in libgtk-3 but are not covered
+//! by the gtk bindings, so the few entry points needed are declared here.
+
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::path::Path;
+use std::ptr;
+
+use gtk::glib::translate::{FromGlibPtrFull, FromGlibPtrNone, ToGlibPtr};
+use gtk::prelude::*;
+use gtk::{ffi, glib};
+
+#[repr(C)]
+struct GtkPrinter {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct GtkPrintJob {
+    _private: [u8; 0],
+}
+
+type JobComplete = unsafe extern "C" fn(*mut GtkPrintJob, *mut c_void, *const glib::ffi::GError);
+
+#[link(name = "gtk-3")]
+unsafe extern "C" {
+    fn gtk_print_unix_dialog_new(
+        title: *const c_char,
+        parent: *mut ffi::GtkWindow,
+    ) -> *mut ffi::GtkWidget;
+    fn gtk_print_unix_dialog_set_settings(
+        dialog: *mut ffi::GtkWidget,
+        settings: *mut ffi::GtkPrintSettings,
+    );
+    fn gtk_print_unix_dialog_get_settings(
+        dialog: *mut ffi::GtkWidget,
+    ) -> *mut ffi::GtkPrintSettings;
+    fn gtk_print_unix_dialog_set_page_setup(
+        dialog: *mut ffi::GtkWidget,
+        page_setup: *mut ffi::GtkPageSetup,
+    );
+    fn gtk_print_unix_dialog_get_page_setup(dialog: *mut ffi::GtkWidget) -> *mut ffi::GtkPageSetup;
+    fn gtk_print_unix_dialog_get_selected_printer(dialog: *mut ffi::GtkWidget) -> *mut GtkPrinter;
+    fn gtk_printer_accepts_pdf(printer: *mut GtkPrinter) -> glib::ffi::gboolean;
+    fn gtk_print_job_new(
+        title: *const c_char,
+        printer: *mut GtkPrinter,
+        settings: *mut ffi::GtkPrintSettings,
+        page_setup: *mut ffi::GtkPageSetup,
+    ) -> *mut GtkPrintJob;
+    fn gtk_print_job_set_source_file(
+        job: *mut GtkPrintJob,
+        filename: *const c_char,
+        error: *mut *mut glib::ffi::GError,
+    ) -> glib::ffi::gboolean;
+    fn gtk_print_job_send(
+        job: *mut GtkPrintJob,
+        callback: Option<JobComplete>,
+        user_data: *mut c_void,
+        dnotify: glib::ffi::GDestroyNotify,
+    );
+}
+
+unsafe extern "C" fn sent(
+    _job: *mut GtkPrintJob,
+    _user_data: *mut c_void,
+    error: *const glib::ffi::GError,
+) {
+    if !error.is_null() {
+        let message = unsafe { CStr::from_ptr((*error).message) };
+        log::warn!("Print job failed: {}", message.to_string_lossy());
+    }
+}
+
+/// Takes the message out of a `GError` and frees it.
+unsafe fn take_error(error: *mut glib::ffi::GError) -> String {
+    let message = unsafe { CStr::from_ptr((*error).message) }
+        .to_string_lossy()
+        .into_owned();
+    unsafe { glib::ffi::g_error_free(error) };
+    message
+}
+
+/// Shows the print dialog over `parent` and prints the PDF at `path` on the
+/// printer chosen, on paper `paper` (a GTK paper name such as `iso_a4`).
+/// Returns whether a job was sent. Must run on the main thread.
+pub(super) fn print(
+    parent: &gtk::Window,
+    title: &str,
+    dialog_title: &str,
+    path: &Path,
+    paper: &str,
+    landscape: bool,
+) -> Result<bool, String> {
+    let orientation = if landscape {
+        gtk::PageOrientation::Landscape
+    } else {
+        gtk::PageOrientation::Portrait
+    };
+    let paper = gtk::PaperSize::new(Some(paper));
+    let settings = gtk::PrintSettings::new();
+    settings.set_orientation(orientation);
+    settings.set_paper_size(&paper);
+    let page_setup = gtk::PageSetup::new();
+    page_setup.set_orientation(orientation);
+    page_setup.set_paper_size(&paper);
+
+    let dialog_title = CString::new(dialog_title).unwrap_or_default();
+    let title = CString::new(title).unwrap_or_default();
+    let file = CString::new(path.to_string_lossy().as_bytes())
+        .map_err(|_| "The report path is not printable".to_string())?;
+
+    unsafe {
+        let widget = gtk_print_unix_dialog_new(dialog_title.as_ptr(), parent.to_glib_none().0);
+        let dialog = gtk::Dialog::from_glib_none(widget.cast::<ffi::GtkDialog>());
+        gtk_print_unix_dialog_set_settings(widget, settings.to_glib_none().0);
+        gtk_print_unix_dialog_set_page_setup(widget, page_setup.to_glib_none().0);
+        let response = dialog.run();
+        let result = if response != gtk::ResponseType::Ok {
+            Ok(false)
+        } else {
+            let printer = gtk_print_unix_dialog_get_selected_printer(widget);
+            if printer.is_null() {
+                Err("No printer was selected".to_string())
+            } else if gtk_printer_accepts_pdf(printer) == glib::ffi::GFALSE {
+                Err("The selected printer does not accept PDF documents".to_string())
+            } else {
+                let settings =
+                    gtk::PrintSettings::from_glib_full(gtk_print_unix_dialog_get_settings(widget));
+                let page_setup =
+                    gtk::PageSetup::from_glib_none(gtk_print_unix_dialog_get_page_setup(widget));
+                let job = gtk_print_job_new(
+                    title.as_ptr(),
+                    printer,
+                    settings.to_glib_none().0,
+                    page_setup.to_glib_none().0,
+                );
+                let mut error = ptr::null_mut();
+                let result = if gtk_print_job_set_source_file(job, file.as_ptr(), &mut error)
+                    == glib::ffi::GFALSE
+                {
+                    Err(take_error(error))
+                } else {
+                    // The backend holds its own reference while the job spools
+                    gtk_print_job_send(job, Some(sent), ptr::null_mut(), None);
+                    Ok(true)
+                };
+                glib::gobject_ffi::g_object_unref(job.cast());
+                result
+            }
+        };
+        dialog.destroy();
+        result
+    }
+}
@@ -0,0 +1,77 @@
+//! The macOS print panel, printing the report through PDFKit.
+
+use std::ffi::c_void;
+use std::path::Path;
+
+use objc2::rc::{Allocated, Retained};
+use objc2::runtime::{AnyClass, AnyObject};
+use objc2::{msg_send, sel};
+use objc2_app_kit::{NSPaperOrientation, NSPrintInfo, NSPrintOperation, NSView};
+use objc2_foundation::{NSCopying, NSSize, NSString, NSURL};
+
+#[link(name = "PDFKit", kind = "framework")]
+unsafe extern "C" {}
+
+/// `kPDFPrintPageScaleDownToFit`: shrink pages larger than the paper only.
+const SCALE_DOWN_TO_FIT: isize = 2;
+
+/// Opens the print panel as a sheet on the view's window for the PDF at
+/// `path`, laid out on `paper` points in the given orientation.
+///
+/// # Safety
+///
+/// `view` must be the window's content view, and this must run on the main
+/// thread.
+pub(super) unsafe fn print(
+    view: *mut c_void,
+    path: &Path,
+    paper: (f64, f64),
+    landscape: bool,
+) -> Result<(), String> {
+    let view = unsafe { &*view.cast::<NSView>() };
+    let window = view.window().ok_or("The window is not on screen")?;
+    let class = AnyClass::get(c"PDFDocument").ok_or("PDFKit is not available")?;
+    let url = NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy()));
+    let document: Option<Retained<AnyObject>> = unsafe {
+        let allocated: Allocated<AnyObject> = msg_send![class, alloc];
+        msg_send![allocated, initWithURL: &*url]
+    };
+    let document = document.ok_or("The report could not be opened for printing")?;
+
+    // A copy, so the choices made here do not become the app's defaults
+    let info = NSPrintInfo::sharedPrintInfo().copy();
+    info.setPaperSize(NSSize::new(paper.0, paper.1));
+    info.setOrientation(if landscape {
+        NSPaperOrientation::Landscape
+    } else {
+        NSPaperOrientation::Portrait
+    });
+    let responds: bool = unsafe {
+        msg_send![
+            &document,
+            respondsToSelector: sel!(printOperationForPrintInfo:scalingMode:autoRotate:)
+        ]
+    };
+    if !responds {
+        return Err("This version of macOS cannot print PDF documents".into());
+    }
+    let operation: Option<Retained<NSPrintOperation>> = unsafe {
+        msg_send![
+            &document,
+            printOperationForPrintInfo: &*info,
+            scalingMode: SCALE_DOWN_TO_FIT,
+            autoRotate: true
+        ]
+    };
+    let operation = operation.ok_or("The print operation could not be created")?;
+    operation.setShowsPrintPanel(true);
+    unsafe {
+        operation.runOperationModalForWindow_delegate_didRunSelector_contextInfo(
+            &window,
+            None,
+            None,
+            std::ptr::null_mut(),
+        );
+    }
+    Ok(())
+}
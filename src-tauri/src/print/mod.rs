@@ -0,0 +1,171 @@
+//! Printing note reports through the OS print dialog.
+//!
+//! Webview printing reflows the report, so the report is rendered with the
+//! PDF export layout into `print/` in the app cache and that file is printed
+//! instead: through PDFKit's print panel on macOS, the PDF handler's print
+//! verb on Windows and the GTK print dialog on Linux. Page size and
+//! orientation set up the layout and preset the dialog.
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::{Error, Result};
+use crate::export::{self, PdfExportOptions, PdfOrientation, PdfPageSize, ReportNote};
+use crate::paths;
+
+const PRINT_DIR: &str = "print";
+/// Rendered reports are kept this long, for print spoolers that read lazily.
+const PRINT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrintOptions {
+    pub playlist_name: Option<String>,
+    pub page_size: PdfPageSize,
+    pub orientation: PdfOrientation,
+    pub include_thumbnails: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            playlist_name: None,
+            page_size: PdfPageSize::default(),
+            orientation: PdfOrientation::default(),
+            include_thumbnails: true,
+        }
+    }
+}
+
+/// Removes reports rendered for earlier prints once they are old enough.
+pub fn init(app: &AppHandle) {
+    let Ok(dir) = paths::cache_dir(app).map(|dir| dir.join(PRINT_DIR)) else {
+        return;
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return;
+        };
+        let cutoff = SystemTime::now() - PRINT_TTL;
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|modified| modified < cutoff);
+            if stale && let Err(err) = fs::remove_file(entry.path()) {
+                log::warn!("Failed to remove {}: {err}", entry.path().display());
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn show(window: &WebviewWindow, _title: &str, path: &Path, options: &PrintOptions) -> Result<()> {
+    let (width, height) = match options.page_size {
+        PdfPageSize::A4 => crate::reports::pdf::A4,
+        PdfPageSize::Letter => crate::reports::pdf::LETTER,
+    };
+    let landscape = options.orientation == PdfOrientation::Landscape;
+    let view = window.ns_view()?;
+    unsafe { macos::print(view, path, (width.into(), height.into()), landscape) }
+        .map_err(Error::Print)
+}
+
+#[cfg(target_os = "windows")]
+fn show(window: &WebviewWindow, _title: &str, path: &Path, _options: &PrintOptions) -> Result<()> {
+    windows::print(window.hwnd()?, path).map_err(Error::Print)
+}
+
+#[cfg(target_os = "linux")]
+fn show(window: &WebviewWindow, title: &str, path: &Path, options: &PrintOptions) -> Result<()> {
+    use gtk::prelude::*;
+
+    let paper = match options.page_size {
+        PdfPageSize::A4 => "iso_a4",
+        PdfPageSize::Letter => "na_letter",
+    };
+    let parent = window.gtk_window()?;
+    let sent = linux::print(
+        parent.upcast_ref(),
+        title,
+        &crate::i18n::tr("print-dialog-title", &[]),
+        path,
+        paper,
+        options.orientation == PdfOrientation::Landscape,
+    )
+    .map_err(Error::Print)?;
+    if sent {
+        log::info!("Sent {} to the printer", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn show(
+    _window: &WebviewWindow,
+    _title: &str,
+    _path: &Path,
+    _options: &PrintOptions,
+) -> Result<()> {
+    Err(Error::Print(
+        "Printing is not supported on this platform".into(),
+    ))
+}
+
+/// Renders notes as the PDF report and opens the print dialog for it.
+#[tauri::command]
+pub async fn print_notes_report(
+    app: AppHandle,
+    window: WebviewWindow,
+    playlist_id: String,
+    notes: Vec<ReportNote>,
+    options: Option<PrintOptions>,
+) -> Result<()> {
+    if notes.is_empty() {
+        return Err(Error::InvalidInput("No notes to print".into()));
+    }
+    let options = options.unwrap_or_default();
+    let title = options
+        .playlist_name
+        .clone()
+        .unwrap_or_else(|| playlist_id.clone());
+    let dir = paths::cache_dir(&app)?.join(PRINT_DIR);
+    let path = dir.join(format!("{}.pdf", uuid::Uuid::new_v4().simple()));
+    let pdf_options = PdfExportOptions {
+        playlist_name: options.playlist_name.clone(),
+        path: None,
+        page_size: options.page_size,
+        orientation: options.orientation,
+        include_thumbnails: options.include_thumbnails,
+    };
+    let target = path.clone();
+    let name = title.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        fs::create_dir_all(&dir)?;
+        export::write_notes_pdf(&target, &name, &notes, &pdf_options)
+    })
+    .await??;
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let target = window.clone();
+    let document = path.clone();
+    // Print dialogs are modal to the window and belong to the UI thread
+    window.run_on_main_thread(move || {
+        let _ = tx.send(show(&target, &title, &document, &options));
+    })?;
+    rx.await
+        .map_err(|_| Error::Print("The print dialog could not be shown".into()))??;
+    export::exported(&app, "print", &playlist_id, &path);
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! Printing on Windows, through the `print` verb of the PDF handler.
+
+use std::path::Path;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Shell::{SE_ERR_NOASSOC, ShellExecuteW};
+use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+use windows::core::{HSTRING, PCWSTR, w};
+
+/// Hands the PDF to the default handler's print dialog, or opens it in the
+/// handler when it cannot print directly. Must run on the window's UI
+/// thread, where COM is initialized.
+pub(super) fn print(hwnd: HWND, path: &Path) -> Result<(), String> {
+    let file = HSTRING::from(path.as_os_str());
+    let execute = |verb: PCWSTR| {
+        let instance = unsafe {
+            ShellExecuteW(
+                Some(hwnd),
+                verb,
+                &file,
+                PCWSTR::null(),
+                PCWSTR::null(),
+                SW_SHOWNORMAL,
+            )
+        };
+        // Values above 32 mean success; below are error codes
+        match instance.0 as usize {
+            code if code > 32 => Ok(()),
+            code => Err(code as u32),
+        }
+    };
+    match execute(w!("print")) {
+        Err(SE_ERR_NOASSOC) => {
+            log::info!("The PDF handler cannot print; opening the report instead");
+            execute(w!("open"))
+        }
+        result => result,
+    }
+    .map_err(|code| format!("Failed to hand the report to the PDF viewer (error {code})"))
+}
@@ -0,0 +1,149 @@
+//! Saved tracker accounts, so people working for several studios can switch
+//! servers without re-entering credentials.
+//!
+//! A profile is an ftrack or ShotGrid account (see `providers`). Its API key
+//! is stored in the keychain under [`Profile::credential_key`]. Commands that
+//! talk to ftrack fall back to the active profile when the frontend does not
+//! pass a connection.
+
+use serde::Deserialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::auth;
+use crate::connectivity::ConnectivityMonitor;
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::ftrack::Connection;
+use crate::network;
+use crate::providers::ProviderKind;
+use crate::store::Store;
+use crate::store::profiles::Profile;
+
+pub const ACTIVE_CHANGED_EVENT: &str = "profiles:active-changed";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewProfile {
+    pub name: String,
+    #[serde(default)]
+    pub provider: ProviderKind,
+    pub server_url: String,
+    /// ftrack username, or the ShotGrid script name.
+    pub api_user: String,
+    /// ftrack API key, or the ShotGrid script key.
+    pub api_key: String,
+}
+
+/// Connection of the active profile, which must be an ftrack account.
+pub fn active_connection(store: &Store) -> Result<Connection> {
+    match store.active_profile()? {
+        Some(profile) if profile.provider == ProviderKind::Ftrack => Ok(profile.connection()),
+        Some(profile) => Err(Error::InvalidInput(format!(
+            "Active profile \"{}\" is not an ftrack account",
+            profile.name
+        ))),
+        None => Err(Error::InvalidInput("No active ftrack profile".into())),
+    }
+}
+
+/// Uses `connection` when given, otherwise the active profile's.
+pub fn resolve(store: &Store, connection: Option<Connection>) -> Result<Connection> {
+    match connection {
+        Some(connection) => Ok(connection),
+        None => active_connection(store),
+    }
+}
+
+fn emit_active(app: &AppHandle, store: &Store) {
+    if let Some(monitor) = app.try_state::<ConnectivityMonitor>() {
+        monitor.wake();
+    }
+    match store.active_profile() {
+        Ok(profile) => {
+            let _ = app.emit(ACTIVE_CHANGED_EVENT, profile);
+        }
+        Err(err) => log::error!("Failed to read active profile: {err}"),
+    }
+}
+
+/// Saves a profile and its API key. The first profile becomes active.
+#[tauri::command]
+pub fn add_profile(
+    app: AppHandle,
+    store: State<'_, Store>,
+    profile: NewProfile,
+) -> Result<Profile> {
+    let name = profile.name.trim();
+    let server_url = profile.server_url.trim().trim_end_matches('/');
+    let api_user = profile.api_user.trim();
+    if name.is_empty() || server_url.is_empty() || api_user.is_empty() {
+        return Err(Error::InvalidInput(
+            "Profile name, server URL and API user are required".into(),
+        ));
+    }
+    let duplicate = store
+        .list_profiles()?
+        .into_iter()
+        .find(|existing| existing.server_url == server_url && existing.api_user == api_user);
+    if let Some(existing) = duplicate {
+        return Err(Error::InvalidInput(format!(
+            "{api_user} on {server_url} is already saved as \"{}\"",
+            existing.name
+        )));
+    }
+
+    let mut saved = store.insert_profile(name, profile.provider, server_url, api_user)?;
+    if let Err(err) = credentials::store(&saved.credential_key(), &profile.api_key) {
+        store.delete_profile(&saved.id)?;
+        return Err(err);
+    }
+    if store.active_profile()?.is_none() {
+        store.set_active_profile(&saved.id)?;
+        saved.active = true;
+        emit_active(&app, &store);
+    }
+    Ok(saved)
+}
+
+#[tauri::command]
+pub fn list_profiles(store: State<'_, Store>) -> Result<Vec<Profile>> {
+    store.list_profiles()
+}
+
+#[tauri::command]
+pub fn get_active_profile(store: State<'_, Store>) -> Result<Option<Profile>> {
+    store.active_profile()
+}
+
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, store: State<'_, Store>, id: String) -> Result<Profile> {
+    if !store.set_active_profile(&id)? {
+        return Err(Error::InvalidInput(format!("Unknown profile {id}")));
+    }
+    emit_active(&app, &store);
+    store
+        .get_profile(&id)?
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown profile {id}")))
+}
+
+/// Deletes a profile and its API key. Removing the active profile activates
+/// the next one, if any.
+#[tauri::command]
+pub fn remove_profile(app: AppHandle, store: State<'_, Store>, id: String) -> Result<bool> {
+    let Some(profile) = store.get_profile(&id)? else {
+        return Ok(false);
+    };
+    credentials::delete(&profile.credential_key())?;
+    credentials::delete(&auth::token_key(&id))?;
+    store.delete_profile(&id)?;
+    if profile.certificate_pin.is_some() {
+        network::pinning::reload(&store)?;
+    }
+    if profile.active {
+        if let Some(next) = store.list_profiles()?.first() {
+            store.set_active_profile(&next.id)?;
+        }
+        emit_active(&app, &store);
+    }
+    Ok(true)
+}
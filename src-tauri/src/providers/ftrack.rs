@@ -0,0 +1,167 @@
+//! ftrack side of the provider commands, built on [`crate::ftrack::call`].
+
+use serde_json::{Value, json};
+
+use super::{NewNote, Note, Playlist, Version};
+use crate::error::{Error, Result};
+use crate::ftrack::{self, Connection, query, quoted};
+
+fn text(entity: &Value, key: &str) -> Option<String> {
+    entity.get(key)?.as_str().map(str::to_string)
+}
+
+fn nested(entity: &Value, path: &[&str]) -> Option<String> {
+    let value = path.iter().try_fold(entity, |value, key| value.get(key))?;
+    value.as_str().map(str::to_string)
+}
+
+/// Datetimes are encoded as `{"__type__": "datetime", "value": "..."}`.
+fn date(entity: &Value, key: &str) -> Option<String> {
+    nested(entity, &[key, "value"])
+}
+
+async fn user_id(client: &reqwest::Client, connection: &Connection) -> Result<String> {
+    let users = query(
+        client,
+        connection,
+        format!(
+            "select id from User where username is {}",
+            quoted(&connection.api_user)
+        ),
+    )
+    .await?;
+    users
+        .first()
+        .and_then(|user| text(user, "id"))
+        .ok_or_else(|| Error::Provider(format!("ftrack user {} not found", connection.api_user)))
+}
+
+pub(super) async fn check(client: &reqwest::Client, connection: &Connection) -> Result<()> {
+    user_id(client, connection).await.map(|_| ())
+}
+
+pub(super) async fn playlists(
+    client: &reqwest::Client,
+    connection: &Connection,
+    project_id: Option<&str>,
+) -> Result<Vec<Playlist>> {
+    let filter = project_id
+        .map(|id| format!(" where project_id is {}", quoted(id)))
+        .unwrap_or_default();
+    let lists = query(
+        client,
+        connection,
+        format!("select id, name, project_id, date from AssetVersionList{filter} order by date descending"),
+    )
+    .await?;
+    Ok(lists
+        .iter()
+        .map(|list| Playlist {
+            id: text(list, "id").unwrap_or_default(),
+            name: text(list, "name").unwrap_or_default(),
+            description: None,
+            project_id: text(list, "project_id"),
+            updated_at: date(list, "date"),
+        })
+        .collect())
+}
+
+fn version(entity: &Value) -> Version {
+    Version {
+        id: text(entity, "id").unwrap_or_default(),
+        name: nested(entity, &["asset", "name"]).unwrap_or_default(),
+        version: entity
+            .get("version")
+            .and_then(Value::as_u64)
+            .map(|version| version as u32),
+        entity: nested(entity, &["asset", "parent", "name"]),
+        status: nested(entity, &["status", "name"]),
+        author: nested(entity, &["user", "username"]),
+        thumbnail_url: None,
+        created_at: date(entity, "date"),
+    }
+}
+
+pub(super) async fn versions(
+    client: &reqwest::Client,
+    connection: &Connection,
+    playlist_id: &str,
+) -> Result<Vec<Version>> {
+    let versions = query(
+        client,
+        connection,
+        format!(
+            "select id, version, asset.name, asset.parent.name, status.name, user.username, date \
+             from AssetVersion where lists any (id is {})",
+            quoted(playlist_id)
+        ),
+    )
+    .await?;
+    Ok(versions.iter().map(version).collect())
+}
+
+fn note(entity: &Value) -> Note {
+    Note {
+        id: text(entity, "id").unwrap_or_default(),
+        version_id: text(entity, "parent_id"),
+        subject: None,
+        content: text(entity, "content").unwrap_or_default(),
+        author: nested(entity, &["author", "username"]),
+        created_at: date(entity, "date"),
+    }
+}
+
+pub(super) async fn notes(
+    client: &reqwest::Client,
+    connection: &Connection,
+    version_ids: &[String],
+) -> Result<Vec<Note>> {
+    let ids = version_ids
+        .iter()
+        .map(|id| quoted(id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let notes = query(
+        client,
+        connection,
+        format!(
+            "select id, content, parent_id, author.username, date \
+             from Note where parent_id in ({ids}) order by date descending"
+        ),
+    )
+    .await?;
+    Ok(notes.iter().map(note).collect())
+}
+
+pub(super) async fn publish(
+    client: &reqwest::Client,
+    connection: &Connection,
+    new: NewNote,
+) -> Result<Note> {
+    let user_id = user_id(client, connection).await?;
+    let id = uuid::Uuid::new_v4().to_string();
+    ftrack::call(
+        client,
+        connection,
+        &json!([{
+            "action": "create",
+            "entity_type": "Note",
+            "entity_data": {
+                "id": id,
+                "content": new.content,
+                "parent_id": new.version_id,
+                "parent_type": "AssetVersion",
+                "user_id": user_id,
+            },
+        }]),
+    )
+    .await?;
+    Ok(Note {
+        id,
+        version_id: Some(new.version_id),
+        subject: None,
+        content: new.content,
+        author: Some(connection.api_user.clone()),
+        created_at: None,
+    })
+}
@@ -0,0 +1,249 @@
+//! Tracker-neutral access to playlists, versions and notes.
+//!
+//! Profiles point at either ftrack or ShotGrid (Flow Production Tracking).
+//! These commands take an optional profile ID, fall back to the active
+//! profile, and return the same shapes whichever tracker answers. That lets
+//! one review view cover studios that run both. IDs are plain strings;
+//! ShotGrid's integer IDs are converted at the edge.
+
+mod ftrack;
+pub mod shotgrid;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tauri::{AppHandle, State};
+
+use crate::error::{Error, Result};
+use crate::network;
+use crate::pipeline_hooks;
+use crate::store::Store;
+use crate::store::audit::{self, AuditAction, NewAuditEntry};
+use crate::store::profiles::Profile;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    Ftrack,
+    ShotGrid,
+}
+
+impl ProviderKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Ftrack => "ftrack",
+            Self::ShotGrid => "shotgrid",
+        }
+    }
+
+    pub(crate) fn from_db(value: &str) -> Self {
+        match value {
+            "shotgrid" => Self::ShotGrid,
+            _ => Self::Ftrack,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Playlist {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub project_id: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Version {
+    pub id: String,
+    pub name: String,
+    pub version: Option<u32>,
+    /// Shot or asset the version belongs to.
+    pub entity: Option<String>,
+    pub status: Option<String>,
+    pub author: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Note {
+    pub id: String,
+    pub version_id: Option<String>,
+    pub subject: Option<String>,
+    pub content: String,
+    pub author: Option<String>,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewNote {
+    pub version_id: String,
+    pub content: String,
+    /// Used by ShotGrid, which requires one; ftrack notes have no subject.
+    #[serde(default)]
+    pub subject: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Providers {
+    shotgrid: shotgrid::Sessions,
+}
+
+impl Providers {
+    pub(crate) async fn versions(
+        &self,
+        profile: &Profile,
+        playlist_id: &str,
+    ) -> Result<Vec<Version>> {
+        let client = network::client();
+        match profile.provider {
+            ProviderKind::Ftrack => {
+                ftrack::versions(&client, &profile.connection(), playlist_id).await
+            }
+            ProviderKind::ShotGrid => {
+                shotgrid::versions(&client, &self.shotgrid, profile, playlist_id).await
+            }
+        }
+    }
+
+    pub(crate) async fn publish(&self, profile: &Profile, note: NewNote) -> Result<Note> {
+        if note.content.trim().is_empty() {
+            return Err(Error::InvalidInput("Note content must not be empty".into()));
+        }
+        let client = network::client();
+        match profile.provider {
+            ProviderKind::Ftrack => ftrack::publish(&client, &profile.connection(), note).await,
+            ProviderKind::ShotGrid => {
+                shotgrid::publish(&client, &self.shotgrid, profile, note).await
+            }
+        }
+    }
+}
+
+pub(crate) fn resolve_profile(store: &Store, profile_id: Option<&str>) -> Result<Profile> {
+    let profile = match profile_id {
+        Some(id) => store.get_profile(id)?,
+        None => store.active_profile()?,
+    };
+    profile.ok_or_else(|| match profile_id {
+        Some(id) => Error::InvalidInput(format!("Unknown profile {id}")),
+        None => Error::InvalidInput("No active profile".into()),
+    })
+}
+
+/// What pre-publish hooks receive for notes published outside the queue.
+pub(crate) fn publish_payload(profile: &Profile, notes: &[NewNote]) -> Value {
+    json!({ "profileId": profile.id, "provider": profile.provider, "notes": notes })
+}
+
+/// The audit entry for a note published outside the queue.
+pub(crate) fn published_entry(profile: &Profile, version_id: &str, note: &Note) -> NewAuditEntry {
+    NewAuditEntry {
+        action: AuditAction::Publish,
+        connection: Some(profile.connection()),
+        entity_type: Some(match profile.provider {
+            ProviderKind::Ftrack => "AssetVersion".into(),
+            ProviderKind::ShotGrid => "Version".into(),
+        }),
+        entity_id: Some(version_id.to_string()),
+        summary: "Published note".into(),
+        details: json!({ "noteId": note.id, "provider": profile.provider }),
+    }
+}
+
+/// The `id` of a JSON record as a string, whether it came as a number or not.
+pub(crate) fn id(record: &Value) -> String {
+    match &record["id"] {
+        Value::Number(id) => id.to_string(),
+        Value::String(id) => id.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Checks that the profile's credentials are accepted.
+#[tauri::command]
+pub async fn check_provider_connection(
+    store: State<'_, Store>,
+    providers: State<'_, Providers>,
+    profile_id: Option<String>,
+) -> Result<()> {
+    let profile = resolve_profile(&store, profile_id.as_deref())?;
+    let client = network::client();
+    match profile.provider {
+        ProviderKind::Ftrack => ftrack::check(&client, &profile.connection()).await,
+        ProviderKind::ShotGrid => shotgrid::check(&client, &providers.shotgrid, &profile).await,
+    }
+}
+
+#[tauri::command]
+pub async fn list_provider_playlists(
+    store: State<'_, Store>,
+    providers: State<'_, Providers>,
+    profile_id: Option<String>,
+    project_id: Option<String>,
+) -> Result<Vec<Playlist>> {
+    let profile = resolve_profile(&store, profile_id.as_deref())?;
+    let client = network::client();
+    let project_id = project_id.as_deref();
+    match profile.provider {
+        ProviderKind::Ftrack => ftrack::playlists(&client, &profile.connection(), project_id).await,
+        ProviderKind::ShotGrid => {
+            shotgrid::playlists(&client, &providers.shotgrid, &profile, project_id).await
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn list_provider_versions(
+    store: State<'_, Store>,
+    providers: State<'_, Providers>,
+    profile_id: Option<String>,
+    playlist_id: String,
+) -> Result<Vec<Version>> {
+    let profile = resolve_profile(&store, profile_id.as_deref())?;
+    providers.versions(&profile, &playlist_id).await
+}
+
+#[tauri::command]
+pub async fn list_provider_notes(
+    store: State<'_, Store>,
+    providers: State<'_, Providers>,
+    profile_id: Option<String>,
+    version_ids: Vec<String>,
+) -> Result<Vec<Note>> {
+    if version_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let profile = resolve_profile(&store, profile_id.as_deref())?;
+    let client = network::client();
+    match profile.provider {
+        ProviderKind::Ftrack => ftrack::notes(&client, &profile.connection(), &version_ids).await,
+        ProviderKind::ShotGrid => {
+            shotgrid::notes(&client, &providers.shotgrid, &profile, &version_ids).await
+        }
+    }
+}
+
+/// Publishes a note on a version straight away, bypassing the publish queue.
+/// Pre-publish hooks still run and can stop it.
+#[tauri::command]
+pub async fn publish_provider_note(
+    app: AppHandle,
+    store: State<'_, Store>,
+    providers: State<'_, Providers>,
+    profile_id: Option<String>,
+    note: NewNote,
+) -> Result<Note> {
+    let profile = resolve_profile(&store, profile_id.as_deref())?;
+    let payload = publish_payload(&profile, std::slice::from_ref(&note));
+    pipeline_hooks::before_publish(&app, &payload).await?;
+    let version_id = note.version_id.clone();
+    let published = providers.publish(&profile, note).await?;
+    audit::record(&app, published_entry(&profile, &version_id, &published));
+    Ok(published)
+}
@@ -0,0 +1,410 @@
+//! ShotGrid (Flow Production Tracking) REST API adapter.
+//!
+//! Profiles authenticate as an API script: `api_user` holds the script name
+//! and the keychain holds its key. Access tokens are cached per site and
+//! script until shortly before they expire. When a request comes back 401,
+//! the token is fetched again once before the request fails.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use super::{NewNote, Note, Playlist, Version, id};
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::store::profiles::Profile;
+
+/// Content type for `_search` bodies whose filters use the array syntax.
+const ARRAY_FILTERS: &str = "application/vnd+shotgun.api3_array+json";
+const PAGE_SIZE: usize = 500;
+/// Refresh tokens this long before ShotGrid would reject them.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+static VERSION_NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:^|[_.\-])v(\d+)").expect("valid version number pattern"));
+
+/// Keychain entry holding the script key for `script_name` on `site`.
+pub fn credential_key(site: &str, script_name: &str) -> String {
+    format!(
+        "shotgrid-script-key:{script_name}@{}",
+        site.trim_end_matches('/')
+    )
+}
+
+struct Token {
+    access_token: String,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+pub struct Sessions {
+    tokens: Mutex<HashMap<String, Token>>,
+}
+
+impl Sessions {
+    fn cached(&self, key: &str) -> Option<String> {
+        let tokens = self
+            .tokens
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        tokens
+            .get(key)
+            .filter(|token| token.expires_at > Instant::now())
+            .map(|token| token.access_token.clone())
+    }
+
+    fn forget(&self, key: &str) {
+        self.tokens
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(key);
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+fn base_url(profile: &Profile) -> &str {
+    profile.server_url.trim_end_matches('/')
+}
+
+async fn token(client: &reqwest::Client, sessions: &Sessions, profile: &Profile) -> Result<String> {
+    let key = profile.credential_key();
+    if let Some(token) = sessions.cached(&key) {
+        return Ok(token);
+    }
+    let script_key = credentials::get(&key)?.ok_or_else(|| {
+        Error::InvalidInput(format!(
+            "No ShotGrid script key stored for {}",
+            profile.name
+        ))
+    })?;
+    let response = client
+        .post(format!("{}/api/v1/auth/access_token", base_url(profile)))
+        .header(ACCEPT, "application/json")
+        .form(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", profile.api_user.as_str()),
+            ("client_secret", script_key.as_str()),
+        ])
+        .send()
+        .await?;
+    let token: TokenResponse = checked(response).await?.json().await?;
+    let expires_at =
+        Instant::now() + Duration::from_secs(token.expires_in).saturating_sub(EXPIRY_MARGIN);
+    sessions
+        .tokens
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(
+            key,
+            Token {
+                access_token: token.access_token.clone(),
+                expires_at,
+            },
+        );
+    Ok(token.access_token)
+}
+
+/// Turns ShotGrid's `{"errors": [{"title", "detail"}]}` bodies into errors.
+async fn checked(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body: Value = response.json().await.unwrap_or_default();
+    let message = body["errors"]
+        .as_array()
+        .and_then(|errors| errors.first())
+        .map(|error| {
+            let title = error["title"].as_str().unwrap_or_default();
+            match error["detail"].as_str() {
+                Some(detail) if !detail.is_empty() => format!("{title}: {detail}"),
+                _ => title.to_string(),
+            }
+        })
+        .filter(|message| !message.is_empty())
+        .unwrap_or_else(|| format!("ShotGrid responded with {status}"));
+    Err(Error::Provider(message))
+}
+
+async fn request(
+    client: &reqwest::Client,
+    sessions: &Sessions,
+    profile: &Profile,
+    method: Method,
+    path: &str,
+    body: Option<(&str, &Value)>,
+) -> Result<Value> {
+    let url = format!("{}/api/v1{path}", base_url(profile));
+    let mut retried = false;
+    loop {
+        let token = token(client, sessions, profile).await?;
+        let mut builder = client
+            .request(method.clone(), &url)
+            .bearer_auth(token)
+            .header(ACCEPT, "application/json");
+        if let Some((content_type, body)) = body {
+            builder = builder
+                .header(CONTENT_TYPE, content_type)
+                .body(body.to_string());
+        }
+        let response = builder.send().await?;
+        if response.status() == StatusCode::UNAUTHORIZED && !retried {
+            sessions.forget(&profile.credential_key());
+            retried = true;
+            continue;
+        }
+        return Ok(checked(response).await?.json().await?);
+    }
+}
+
+/// Runs a `_search`, following pages until a short one comes back.
+async fn search(
+    client: &reqwest::Client,
+    sessions: &Sessions,
+    profile: &Profile,
+    entity: &str,
+    filters: Value,
+    fields: &[&str],
+    sort: &str,
+) -> Result<Vec<Value>> {
+    let mut records = Vec::new();
+    for number in 1.. {
+        let body = json!({
+            "filters": filters,
+            "fields": fields,
+            "sort": sort,
+            "page": { "size": PAGE_SIZE, "number": number },
+        });
+        let response = request(
+            client,
+            sessions,
+            profile,
+            Method::POST,
+            &format!("/entity/{entity}/_search"),
+            Some((ARRAY_FILTERS, &body)),
+        )
+        .await?;
+        let page = match response {
+            Value::Object(mut object) => match object.remove("data") {
+                Some(Value::Array(page)) => page,
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        let done = page.len() < PAGE_SIZE;
+        records.extend(page);
+        if done {
+            break;
+        }
+    }
+    Ok(records)
+}
+
+fn parse_id(value: &str) -> Result<i64> {
+    value
+        .parse()
+        .map_err(|_| Error::InvalidInput(format!("Invalid ShotGrid ID {value}")))
+}
+
+fn attribute(record: &Value, key: &str) -> Option<String> {
+    record["attributes"][key].as_str().map(str::to_string)
+}
+
+/// Display name of a single-entity field such as `user` or `entity`.
+fn linked_name(record: &Value, key: &str) -> Option<String> {
+    record["relationships"][key]["data"]["name"]
+        .as_str()
+        .map(str::to_string)
+}
+
+pub(super) async fn check(
+    client: &reqwest::Client,
+    sessions: &Sessions,
+    profile: &Profile,
+) -> Result<()> {
+    sessions.forget(&profile.credential_key());
+    token(client, sessions, profile).await.map(|_| ())
+}
+
+pub(super) async fn playlists(
+    client: &reqwest::Client,
+    sessions: &Sessions,
+    profile: &Profile,
+    project_id: Option<&str>,
+) -> Result<Vec<Playlist>> {
+    let filters = match project_id {
+        Some(project_id) => {
+            json!([["project", "is", { "type": "Project", "id": parse_id(project_id)? }]])
+        }
+        None => json!([]),
+    };
+    let records = search(
+        client,
+        sessions,
+        profile,
+        "playlists",
+        filters,
+        &["code", "description", "project", "updated_at"],
+        "-updated_at",
+    )
+    .await?;
+    Ok(records
+        .iter()
+        .map(|record| Playlist {
+            id: id(record),
+            name: attribute(record, "code").unwrap_or_default(),
+            description: attribute(record, "description"),
+            project_id: Some(id(&record["relationships"]["project"]["data"]))
+                .filter(|id| !id.is_empty()),
+            updated_at: attribute(record, "updated_at"),
+        })
+        .collect())
+}
+
+pub(super) async fn versions(
+    client: &reqwest::Client,
+    sessions: &Sessions,
+    profile: &Profile,
+    playlist_id: &str,
+) -> Result<Vec<Version>> {
+    let playlist = json!({ "type": "Playlist", "id": parse_id(playlist_id)? });
+    let records = search(
+        client,
+        sessions,
+        profile,
+        "versions",
+        json!([["playlists", "is", playlist]]),
+        &[
+            "code",
+            "entity",
+            "sg_status_list",
+            "user",
+            "image",
+            "created_at",
+        ],
+        "code",
+    )
+    .await?;
+    Ok(records
+        .iter()
+        .map(|record| {
+            let name = attribute(record, "code").unwrap_or_default();
+            Version {
+                id: id(record),
+                version: version_number(&name),
+                name,
+                entity: linked_name(record, "entity"),
+                status: attribute(record, "sg_status_list"),
+                author: linked_name(record, "user"),
+                thumbnail_url: attribute(record, "image"),
+                created_at: attribute(record, "created_at"),
+            }
+        })
+        .collect())
+}
+
+/// ShotGrid has no version number field; studios encode it as `_v012` in the code.
+fn version_number(code: &str) -> Option<u32> {
+    VERSION_NUMBER
+        .captures_iter(code)
+        .last()
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+fn note(record: &Value) -> Note {
+    let version_id = record["relationships"]["note_links"]["data"]
+        .as_array()
+        .and_then(|links| links.iter().find(|link| link["type"] == "Version"))
+        .map(id);
+    Note {
+        id: id(record),
+        version_id,
+        subject: attribute(record, "subject"),
+        content: attribute(record, "content").unwrap_or_default(),
+        author: linked_name(record, "user"),
+        created_at: attribute(record, "created_at"),
+    }
+}
+
+pub(super) async fn notes(
+    client: &reqwest::Client,
+    sessions: &Sessions,
+    profile: &Profile,
+    version_ids: &[String],
+) -> Result<Vec<Note>> {
+    let versions = version_ids
+        .iter()
+        .map(|id| Ok(json!({ "type": "Version", "id": parse_id(id)? })))
+        .collect::<Result<Vec<_>>>()?;
+    let records = search(
+        client,
+        sessions,
+        profile,
+        "notes",
+        json!([["note_links", "in", versions]]),
+        &["subject", "content", "user", "note_links", "created_at"],
+        "-created_at",
+    )
+    .await?;
+    Ok(records.iter().map(note).collect())
+}
+
+pub(super) async fn publish(
+    client: &reqwest::Client,
+    sessions: &Sessions,
+    profile: &Profile,
+    new: NewNote,
+) -> Result<Note> {
+    let version_id = parse_id(&new.version_id)?;
+    // Notes must belong to a project, so take the version's
+    let version = request(
+        client,
+        sessions,
+        profile,
+        Method::GET,
+        &format!("/entity/versions/{version_id}?fields=code,project"),
+        None,
+    )
+    .await?;
+    let project = &version["data"]["relationships"]["project"]["data"];
+    if project.is_null() {
+        return Err(Error::Provider(format!(
+            "ShotGrid version {version_id} has no project"
+        )));
+    }
+    let subject = new.subject.unwrap_or_else(|| {
+        let code = version["data"]["attributes"]["code"]
+            .as_str()
+            .unwrap_or_default();
+        format!("Note on {code}")
+    });
+    let body = json!({
+        "project": { "type": "Project", "id": project["id"] },
+        "subject": subject,
+        "content": new.content,
+        "note_links": [{ "type": "Version", "id": version_id }],
+    });
+    let created = request(
+        client,
+        sessions,
+        profile,
+        Method::POST,
+        "/entity/notes",
+        Some(("application/json", &body)),
+    )
+    .await?;
+    let mut note = note(&created["data"]);
+    note.version_id.get_or_insert(new.version_id);
+    Ok(note)
+}
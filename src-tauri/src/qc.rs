@@ -0,0 +1,527 @@
+//! Technical QC of version media against expected specs.
+//!
+//! Studios describe delivery specs as named profiles in `qc.json` in the
+//! config directory: resolution, frame rate and colorspace, plus a default
+//! profile per project. Each version to check brings its media path and
+//! the frame range it is expected to cover, usually the shot's cut range
+//! from the tracker. Movies are probed with ffprobe and image sequences are
+//! collapsed from the frame on disk and read through their headers, so a
+//! short or gappy render fails before a client sees it. `run_qc` reports
+//! `qc:progress` after each version and returns every check's result.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+
+use crate::error::{Error, Result};
+use crate::media::frames;
+use crate::media::metadata;
+use crate::media::sequence::{self, FileSequence};
+use crate::paths;
+use crate::store::now_millis;
+
+const SETTINGS_FILE: &str = "qc.json";
+pub const PROGRESS_EVENT: &str = "qc:progress";
+/// Probes run this many at a time.
+const CONCURRENCY: usize = 4;
+/// Frame rates closer than this are the same, e.g. 23.976 and 24000/1001.
+const FRAME_RATE_TOLERANCE: f64 = 0.01;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QcProfile {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    /// Compared ignoring case and punctuation, so `Rec.709` matches `rec709`.
+    pub colorspace: Option<String>,
+    /// Whether gaps in an image sequence are tolerated.
+    pub allow_missing_frames: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QcSettings {
+    pub profiles: BTreeMap<String, QcProfile>,
+    /// Profile used for a project's versions when `run_qc` names none.
+    pub projects: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QcVersion {
+    pub version_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// A movie, any frame of an image sequence, or the sequence's folder.
+    pub path: PathBuf,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub first_frame: Option<u32>,
+    #[serde(default)]
+    pub last_frame: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckKind {
+    Resolution,
+    FrameRate,
+    FrameRange,
+    MissingFrames,
+    Colorspace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// The media does not say, e.g. a PNG sequence has no frame rate.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QcCheck {
+    pub kind: CheckKind,
+    pub status: CheckStatus,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    pub message: Option<String>,
+}
+
+/// What was found on disk.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QcMedia {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub colorspace: Option<String>,
+    /// Only image sequences carry frame numbers.
+    pub first_frame: Option<u32>,
+    pub last_frame: Option<u32>,
+    pub frame_count: Option<u64>,
+    pub missing_frames: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QcResult {
+    pub version_id: String,
+    pub name: Option<String>,
+    pub path: PathBuf,
+    pub profile: Option<String>,
+    pub passed: bool,
+    pub media: Option<QcMedia>,
+    pub checks: Vec<QcCheck>,
+    /// Why the media could not be probed; the version fails.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QcReport {
+    pub run_id: String,
+    pub results: Vec<QcResult>,
+    pub passed: usize,
+    pub failed: usize,
+    pub started_at: i64,
+    pub finished_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Progress<'a> {
+    run_id: &'a str,
+    done: usize,
+    total: usize,
+    result: &'a QcResult,
+}
+
+fn load_settings(app: &AppHandle) -> Result<QcSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+/// The sequence `path` belongs to, or the longest one in `path` when it is
+/// a folder.
+fn find_sequence(path: &Path) -> Result<Option<FileSequence>> {
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(Path::new("."))
+    };
+    let files: Vec<PathBuf> = fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|file| file.is_file() && sequence::is_still(file))
+        .collect();
+    let (sequences, _) = sequence::collapse(files);
+    if path.is_dir() {
+        return Ok(sequences
+            .into_iter()
+            .max_by_key(|sequence| sequence.frame_count));
+    }
+    Ok(sequences.into_iter().find(|sequence| {
+        (sequence.first_frame..=sequence.last_frame).any(|frame| sequence.frame_path(frame) == path)
+    }))
+}
+
+async fn probe_movie(app: &AppHandle, path: &Path) -> Result<QcMedia> {
+    let output = frames::run(
+        app,
+        "ffprobe",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-select_streams".into(),
+            "v:0".into(),
+            "-show_entries".into(),
+            "stream=width,height,r_frame_rate,nb_frames,color_space:format=duration".into(),
+            "-of".into(),
+            "json".into(),
+            path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let stream = &json["streams"][0];
+    if stream.is_null() {
+        return Err(Error::Media(format!(
+            "{} has no video stream",
+            path.display()
+        )));
+    }
+    let frame_rate = stream["r_frame_rate"].as_str().and_then(|rate| {
+        let (num, den) = rate.split_once('/')?;
+        let rate = num.parse::<f64>().ok()? / den.parse::<f64>().ok()?;
+        (rate.is_finite() && rate > 0.0).then_some(rate)
+    });
+    // Not every container records a frame count; the duration is a close second
+    let frame_count = stream["nb_frames"]
+        .as_str()
+        .and_then(|count| count.parse().ok())
+        .or_else(|| {
+            let duration: f64 = json["format"]["duration"].as_str()?.parse().ok()?;
+            Some((duration * frame_rate?).round() as u64)
+        });
+    Ok(QcMedia {
+        width: stream["width"].as_u64().map(|width| width as u32),
+        height: stream["height"].as_u64().map(|height| height as u32),
+        frame_rate,
+        colorspace: stream["color_space"].as_str().map(str::to_string),
+        frame_count,
+        ..Default::default()
+    })
+}
+
+fn probe_sequence(path: &Path) -> Result<QcMedia> {
+    let (first, mut media) = match find_sequence(path)? {
+        Some(sequence) => {
+            let media = QcMedia {
+                first_frame: Some(sequence.first_frame),
+                last_frame: Some(sequence.last_frame),
+                frame_count: Some(sequence.frame_count as u64),
+                missing_frames: sequence.missing_frames,
+                ..Default::default()
+            };
+            (sequence.first_path, media)
+        }
+        // A lone still has no frame range to check
+        None if path.is_file() => (path.to_path_buf(), QcMedia::default()),
+        None => {
+            return Err(Error::Media(format!(
+                "No image sequence found in {}",
+                path.display()
+            )));
+        }
+    };
+    let extension = first
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "exr" | "dpx" => {
+            let meta = metadata::read(&first)?;
+            media.width = Some(meta.width);
+            media.height = Some(meta.height);
+            media.frame_rate = meta.frame_rate;
+            media.colorspace = meta.colorspace;
+        }
+        _ => {
+            let (width, height) = image::image_dimensions(&first)?;
+            media.width = Some(width);
+            media.height = Some(height);
+        }
+    }
+    Ok(media)
+}
+
+/// Lowercase letters and digits only, for comparing colorspace names.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn check(
+    kind: CheckKind,
+    passed: Option<bool>,
+    expected: String,
+    actual: Option<String>,
+    message: Option<String>,
+) -> QcCheck {
+    QcCheck {
+        kind,
+        status: match passed {
+            Some(true) => CheckStatus::Passed,
+            Some(false) => CheckStatus::Failed,
+            None => CheckStatus::Unknown,
+        },
+        expected: Some(expected),
+        actual,
+        message,
+    }
+}
+
+fn evaluate(version: &QcVersion, profile: Option<&QcProfile>, media: &QcMedia) -> Vec<QcCheck> {
+    let mut checks = Vec::new();
+    let spec = profile.cloned().unwrap_or_default();
+    if spec.width.is_some() || spec.height.is_some() {
+        let expected = format!(
+            "{}x{}",
+            spec.width.map_or("*".into(), |width| width.to_string()),
+            spec.height.map_or("*".into(), |height| height.to_string())
+        );
+        let actual = media.width.zip(media.height);
+        let passed = actual.map(|(width, height)| {
+            spec.width.is_none_or(|expected| expected == width)
+                && spec.height.is_none_or(|expected| expected == height)
+        });
+        checks.push(check(
+            CheckKind::Resolution,
+            passed,
+            expected,
+            actual.map(|(width, height)| format!("{width}x{height}")),
+            None,
+        ));
+    }
+    if let Some(expected) = spec.frame_rate {
+        let passed = media
+            .frame_rate
+            .map(|rate| (rate - expected).abs() < FRAME_RATE_TOLERANCE);
+        checks.push(check(
+            CheckKind::FrameRate,
+            passed,
+            format!("{expected:.3}"),
+            media.frame_rate.map(|rate| format!("{rate:.3}")),
+            None,
+        ));
+    }
+    if let Some(expected) = &spec.colorspace {
+        let passed = media
+            .colorspace
+            .as_ref()
+            .map(|actual| normalize(actual) == normalize(expected));
+        checks.push(check(
+            CheckKind::Colorspace,
+            passed,
+            expected.clone(),
+            media.colorspace.clone(),
+            None,
+        ));
+    }
+    if let (Some(first), Some(last)) = (version.first_frame, version.last_frame) {
+        let expected = format!("{first}-{last}");
+        let expected_count = u64::from(last.saturating_sub(first)) + 1;
+        let (passed, actual, message) = match (media.first_frame, media.last_frame) {
+            (Some(actual_first), Some(actual_last)) => {
+                let message = if actual_first > first || actual_last < last {
+                    Some(format!(
+                        "Short by {} frame(s)",
+                        actual_first.saturating_sub(first) + last.saturating_sub(actual_last)
+                    ))
+                } else if actual_first < first || actual_last > last {
+                    Some("Longer than the expected range".to_string())
+                } else {
+                    None
+                };
+                (
+                    Some(message.is_none()),
+                    Some(format!("{actual_first}-{actual_last}")),
+                    message,
+                )
+            }
+            // Movies only tell how many frames they have
+            _ => match media.frame_count {
+                Some(count) => (
+                    Some(count == expected_count),
+                    Some(format!("{count} frame(s)")),
+                    (count != expected_count)
+                        .then(|| format!("Expected {expected_count} frame(s)")),
+                ),
+                None => (None, None, None),
+            },
+        };
+        checks.push(check(
+            CheckKind::FrameRange,
+            passed,
+            expected,
+            actual,
+            message,
+        ));
+    }
+    if media.first_frame.is_some() {
+        let missing = &media.missing_frames;
+        let message = (!missing.is_empty()).then(|| {
+            let listed: Vec<String> = missing.iter().take(20).map(u32::to_string).collect();
+            let more = if missing.len() > 20 { ", …" } else { "" };
+            format!("Missing {}{more}", listed.join(", "))
+        });
+        checks.push(QcCheck {
+            kind: CheckKind::MissingFrames,
+            status: if missing.is_empty() || spec.allow_missing_frames {
+                CheckStatus::Passed
+            } else {
+                CheckStatus::Failed
+            },
+            expected: Some("0".into()),
+            actual: Some(missing.len().to_string()),
+            message,
+        });
+    }
+    checks
+}
+
+async fn run_one(
+    app: &AppHandle,
+    settings: &QcSettings,
+    profile: Option<&str>,
+    version: QcVersion,
+) -> QcResult {
+    let profile_name = profile.map(str::to_string).or_else(|| {
+        version
+            .project_id
+            .as_ref()
+            .and_then(|project| settings.projects.get(project))
+            .cloned()
+    });
+    let spec = profile_name
+        .as_ref()
+        .and_then(|name| settings.profiles.get(name));
+    let probed = if !version.path.exists() {
+        Err(Error::Media(format!(
+            "{} not found",
+            version.path.display()
+        )))
+    } else if version.path.is_file() && !sequence::is_still(&version.path) {
+        probe_movie(app, &version.path).await
+    } else {
+        let path = version.path.clone();
+        tauri::async_runtime::spawn_blocking(move || probe_sequence(&path))
+            .await
+            .map_err(Error::from)
+            .and_then(|result| result)
+    };
+    let (media, checks, error) = match probed {
+        Ok(media) => {
+            let checks = evaluate(&version, spec, &media);
+            (Some(media), checks, None)
+        }
+        Err(err) => (None, Vec::new(), Some(err.to_string())),
+    };
+    QcResult {
+        passed: error.is_none() && checks.iter().all(|c| c.status != CheckStatus::Failed),
+        version_id: version.version_id,
+        name: version.name,
+        path: version.path,
+        profile: profile_name,
+        media,
+        checks,
+        error,
+    }
+}
+
+/// Probes each version's media and checks it against `profile`, or the
+/// profile assigned to the version's project when none is named.
+#[tauri::command]
+pub async fn run_qc(
+    app: AppHandle,
+    versions: Vec<QcVersion>,
+    profile: Option<String>,
+) -> Result<QcReport> {
+    if versions.is_empty() {
+        return Err(Error::InvalidInput("No versions to check".into()));
+    }
+    let settings = load_settings(&app)?;
+    if let Some(name) = &profile
+        && !settings.profiles.contains_key(name)
+    {
+        return Err(Error::InvalidInput(format!("Unknown QC profile {name}")));
+    }
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let started_at = now_millis();
+    let total = versions.len();
+    let mut done = 0;
+    let mut stream = futures_util::stream::iter(versions)
+        .map(|version| run_one(&app, &settings, profile.as_deref(), version))
+        .buffered(CONCURRENCY);
+    let mut results = Vec::with_capacity(total);
+    while let Some(result) = stream.next().await {
+        done += 1;
+        let progress = Progress {
+            run_id: &run_id,
+            done,
+            total,
+            result: &result,
+        };
+        if let Err(err) = app.emit(PROGRESS_EVENT, progress) {
+            log::warn!("Failed to emit QC progress: {err}");
+        }
+        results.push(result);
+    }
+    let passed = results.iter().filter(|result| result.passed).count();
+    log::info!(
+        "QC run {run_id}: {passed} of {total} version(s) passed{}",
+        profile
+            .as_ref()
+            .map(|name| format!(" profile {name}"))
+            .unwrap_or_default()
+    );
+    Ok(QcReport {
+        run_id,
+        failed: total - passed,
+        passed,
+        results,
+        started_at,
+        finished_at: now_millis(),
+    })
+}
+
+#[tauri::command]
+pub fn get_qc_settings(app: AppHandle) -> Result<QcSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_qc_settings(app: AppHandle, settings: QcSettings) -> Result<()> {
+    if let Some((project, profile)) = settings
+        .projects
+        .iter()
+        .find(|(_, profile)| !settings.profiles.contains_key(*profile))
+    {
+        return Err(Error::InvalidInput(format!(
+            "Project {project} uses unknown QC profile {profile}"
+        )));
+    }
+    paths::save_json(&app, SETTINGS_FILE, &settings)
+}
@@ -0,0 +1,124 @@
+//! Background note-publish queue.
+//!
+//! Jobs are persisted in the native store before they are acknowledged, so a
+//! publish interrupted by quitting the app resumes on the next launch.
+
+mod worker;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use crate::automation;
+use crate::error::{Error, Result};
+use crate::pipeline_hooks;
+use crate::profiles;
+use crate::store::Store;
+use crate::store::publish_jobs::{JobCounts, NewPublishJob, PublishJob};
+
+pub const PROGRESS_EVENT: &str = "publish-queue:progress";
+
+#[derive(Default)]
+pub struct PublishQueue {
+    wake: Notify,
+}
+
+impl PublishQueue {
+    /// Wakes the worker so newly due jobs are picked up immediately.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    pub job: PublishJob,
+    pub counts: JobCounts,
+}
+
+/// Requeues jobs interrupted by the previous session and starts the worker.
+pub fn init(app: &AppHandle) -> Result<()> {
+    app.manage(PublishQueue::default());
+
+    let recovered = app.state::<Store>().recover_publish_jobs()?;
+    if recovered > 0 {
+        log::info!("Resuming {recovered} interrupted publish jobs");
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { worker::run(app).await });
+    Ok(())
+}
+
+pub(crate) fn emit_progress(app: &AppHandle, job: &PublishJob) {
+    let counts = match app.state::<Store>().publish_job_counts() {
+        Ok(counts) => counts,
+        Err(err) => {
+            log::warn!("Failed to count publish jobs: {err}");
+            JobCounts::default()
+        }
+    };
+    let event = ProgressEvent {
+        job: job.clone(),
+        counts,
+    };
+    if let Err(err) = app.emit(PROGRESS_EVENT, event) {
+        log::warn!("Failed to emit publish progress: {err}");
+    }
+}
+
+#[tauri::command]
+pub async fn enqueue_publish_jobs(
+    app: AppHandle,
+    store: State<'_, Store>,
+    queue: State<'_, PublishQueue>,
+    mut jobs: Vec<NewPublishJob>,
+) -> Result<Vec<PublishJob>> {
+    if jobs.iter().any(|job| job.server_url.is_empty()) {
+        let active = profiles::active_connection(&store)?;
+        for job in jobs.iter_mut().filter(|job| job.server_url.is_empty()) {
+            job.server_url = active.server_url.clone();
+            job.api_user = active.api_user.clone();
+        }
+    }
+    // Nothing is queued if any job is rejected, so a batch publishes all or nothing
+    for job in &mut jobs {
+        automation::before_publish(&app, job)?;
+    }
+    pipeline_hooks::before_publish(&app, &serde_json::json!({ "jobs": jobs })).await?;
+    let jobs = store.insert_publish_jobs(jobs)?;
+    queue.wake();
+    Ok(jobs)
+}
+
+#[tauri::command]
+pub fn list_publish_jobs(store: State<'_, Store>) -> Result<Vec<PublishJob>> {
+    store.list_publish_jobs()
+}
+
+#[tauri::command]
+pub fn retry_publish_job(
+    store: State<'_, Store>,
+    queue: State<'_, PublishQueue>,
+    id: String,
+) -> Result<()> {
+    if !store.requeue_publish_job(&id)? {
+        return Err(Error::InvalidInput(format!(
+            "Publish job {id} is not failed or cancelled"
+        )));
+    }
+    queue.wake();
+    Ok(())
+}
+
+/// Cancels a job that has not started yet. Running jobs cannot be cancelled.
+#[tauri::command]
+pub fn cancel_publish_job(store: State<'_, Store>, id: String) -> Result<bool> {
+    store.cancel_publish_job(&id)
+}
+
+#[tauri::command]
+pub fn clear_finished_publish_jobs(store: State<'_, Store>) -> Result<usize> {
+    store.clear_finished_publish_jobs()
+}
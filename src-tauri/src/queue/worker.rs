@@ -0,0 +1,188 @@
+//! Worker loop that drains the publish queue one job at a time.
+
+use std::time::Duration;
+
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+
+use super::{PublishQueue, emit_progress};
+use crate::badge;
+use crate::ftrack::{self, Connection};
+use crate::lan;
+use crate::network;
+use crate::notifications::{self, Kind};
+use crate::pipeline_hooks::{self, HookPoint};
+use crate::power;
+use crate::rules;
+use crate::store::audit::{self, AuditAction, NewAuditEntry};
+use crate::store::publish_jobs::{JobStatus, PublishJob};
+use crate::store::{Store, now_millis};
+use crate::taskbar;
+use crate::tray;
+
+const MAX_ATTEMPTS: u32 = 8;
+const BASE_BACKOFF_MS: i64 = 2_000;
+const MAX_BACKOFF_MS: i64 = 5 * 60 * 1000;
+const IDLE_POLL: Duration = Duration::from_secs(60);
+const TASKBAR_SOURCE: &str = "publish";
+
+/// Outcomes since the queue was last idle, reported once it drains.
+#[derive(Default)]
+struct Batch {
+    completed: u32,
+    failed: u32,
+    last_error: Option<String>,
+}
+
+impl Batch {
+    fn record(&mut self, app: &AppHandle, job: &PublishJob) {
+        match job.status {
+            JobStatus::Completed => self.completed += 1,
+            JobStatus::Failed => {
+                self.failed += 1;
+                self.last_error.clone_from(&job.last_error);
+            }
+            _ => {}
+        }
+        self.report(app);
+    }
+
+    fn report(&self, app: &AppHandle) {
+        if let Ok(counts) = app.state::<Store>().publish_job_counts() {
+            let done = u64::from(self.completed + self.failed);
+            let remaining = u64::from(counts.pending + counts.running);
+            taskbar::update(app, TASKBAR_SOURCE, done, done + remaining);
+        }
+    }
+
+    /// Notifies and resets once nothing is pending, including retries.
+    fn finish_if_idle(&mut self, app: &AppHandle) {
+        match app.state::<Store>().publish_job_counts() {
+            Ok(counts) if counts.pending == 0 && counts.running == 0 => {}
+            _ => return,
+        }
+        taskbar::finish(app, TASKBAR_SOURCE);
+        if self.completed + self.failed == 0 {
+            return;
+        }
+        let batch = std::mem::take(self);
+        let notes = |count: u32| if count == 1 { "note" } else { "notes" };
+        let (title, body) = if batch.failed == 0 {
+            (
+                "Publish finished".to_string(),
+                format!("Published {} {}", batch.completed, notes(batch.completed)),
+            )
+        } else {
+            let mut body = format!(
+                "{} {} failed, {} published",
+                batch.failed,
+                notes(batch.failed),
+                batch.completed
+            );
+            if let Some(error) = batch.last_error {
+                body.push_str(&format!(": {error}"));
+            }
+            ("Publish finished with errors".to_string(), body)
+        };
+        notifications::notify(app, Kind::Publish, None, &title, &body);
+    }
+}
+
+pub(super) async fn run(app: AppHandle) {
+    let mut batch = Batch::default();
+    loop {
+        let store = app.state::<Store>();
+        // Woken again once the system is awake and back online
+        if !power::held(&app) {
+            match store.claim_publish_job() {
+                Ok(Some(job)) => {
+                    emit_progress(&app, &job);
+                    batch.report(&app);
+                    if let Some(job) = process(&app, &network::client(), job).await {
+                        batch.record(&app, &job);
+                    }
+                    continue;
+                }
+                Ok(None) => batch.finish_if_idle(&app),
+                Err(err) => log::error!("Failed to claim publish job: {err}"),
+            }
+        }
+
+        let delay = match store.next_publish_attempt_at() {
+            Ok(Some(at)) => {
+                let wait = (at - now_millis()).max(0) as u64;
+                Duration::from_millis(wait).min(IDLE_POLL)
+            }
+            _ => IDLE_POLL,
+        };
+        let queue = app.state::<PublishQueue>();
+        tokio::select! {
+            _ = queue.wake.notified() => {}
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+/// Runs one job and returns it as recorded afterwards.
+async fn process(app: &AppHandle, client: &reqwest::Client, job: PublishJob) -> Option<PublishJob> {
+    let store = app.state::<Store>();
+    let connection = Connection {
+        server_url: job.server_url.clone(),
+        api_user: job.api_user.clone(),
+    };
+    let result = match ftrack::call(client, &connection, &job.operations).await {
+        Ok(_) => store
+            .update_publish_job(&job.id, JobStatus::Completed, None, None)
+            .and_then(|()| store.mark_draft_published(&job.playlist_id, &job.version_id))
+            .inspect(|()| {
+                audit::record(
+                    app,
+                    NewAuditEntry {
+                        action: AuditAction::Publish,
+                        connection: Some(connection.clone()),
+                        entity_type: Some("AssetVersion".into()),
+                        entity_id: Some(job.version_id.clone()),
+                        summary: "Published note".into(),
+                        details: json!({ "jobId": job.id, "playlistId": job.playlist_id }),
+                    },
+                );
+                tray::refresh(app);
+                badge::refresh(app);
+                pipeline_hooks::notify(app, HookPoint::PostPublish, json!({ "job": job }));
+                rules::notify(app, &job);
+                lan::published(app, &job);
+            }),
+        Err(err) if err.is_retryable() && job.attempts < MAX_ATTEMPTS => {
+            let message = err.to_string();
+            let next_attempt_at = now_millis() + backoff_ms(job.attempts);
+            log::warn!(
+                "Publish job {} failed (attempt {}), retrying: {message}",
+                job.id,
+                job.attempts
+            );
+            store.update_publish_job(
+                &job.id,
+                JobStatus::Pending,
+                Some(&message),
+                Some(next_attempt_at),
+            )
+        }
+        Err(err) => {
+            let message = err.to_string();
+            log::error!("Publish job {} failed: {message}", job.id);
+            store.update_publish_job(&job.id, JobStatus::Failed, Some(&message), None)
+        }
+    };
+    if let Err(err) = result {
+        log::error!("Failed to record publish job {}: {err}", job.id);
+    }
+
+    let job = store.get_publish_job(&job.id).ok().flatten()?;
+    emit_progress(app, &job);
+    Some(job)
+}
+
+fn backoff_ms(attempts: u32) -> i64 {
+    let exponent = attempts.saturating_sub(1).min(16);
+    (BASE_BACKOFF_MS << exponent).min(MAX_BACKOFF_MS)
+}
@@ -0,0 +1,103 @@
+//! The dock menu on macOS.
+//!
+//! tao owns the application delegate and has no dock menu, so
+//! `applicationDockMenu:` and the items' action are added to the delegate's
+//! class at runtime. Everything here runs on the main thread.
+
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, AnyObject, Imp, Sel};
+use objc2::{MainThreadMarker, MainThreadOnly, ffi, sel};
+use objc2_app_kit::{NSApplication, NSMenu, NSMenuItem};
+use objc2_foundation::NSString;
+
+type DockMenuImp = unsafe extern "C-unwind" fn(*mut AnyObject, Sel, *mut AnyObject) -> *mut NSMenu;
+type ActionImp = unsafe extern "C-unwind" fn(*mut AnyObject, Sel, *mut NSMenuItem);
+
+thread_local! {
+    static MENU: RefCell<Option<Retained<NSMenu>>> = const { RefCell::new(None) };
+}
+
+/// Called with the index of the chosen item.
+static ON_SELECT: OnceLock<Box<dyn Fn(usize) + Send + Sync>> = OnceLock::new();
+
+unsafe extern "C-unwind" fn dock_menu(
+    _this: *mut AnyObject,
+    _cmd: Sel,
+    _sender: *mut AnyObject,
+) -> *mut NSMenu {
+    MENU.with_borrow(|menu| {
+        menu.as_ref().map_or(std::ptr::null_mut(), |menu| {
+            Retained::as_ptr(menu).cast_mut()
+        })
+    })
+}
+
+unsafe extern "C-unwind" fn open_recent(_this: *mut AnyObject, _cmd: Sel, sender: *mut NSMenuItem) {
+    let Some(item) = (unsafe { sender.as_ref() }) else {
+        return;
+    };
+    if let (Some(on_select), Ok(index)) = (ON_SELECT.get(), usize::try_from(item.tag())) {
+        on_select(index);
+    }
+}
+
+fn delegate(mtm: MainThreadMarker) -> Option<Retained<AnyObject>> {
+    let delegate = NSApplication::sharedApplication(mtm).delegate()?;
+    Some(delegate.into())
+}
+
+/// Adds the dock menu methods to the application delegate.
+pub(super) fn install(on_select: impl Fn(usize) + Send + Sync + 'static) {
+    let Some(mtm) = MainThreadMarker::new() else {
+        log::warn!("The dock menu must be installed on the main thread");
+        return;
+    };
+    if ON_SELECT.set(Box::new(on_select)).is_err() {
+        return;
+    }
+    let Some(delegate) = delegate(mtm) else {
+        log::warn!("No application delegate to attach the dock menu to");
+        return;
+    };
+    let class: *const AnyClass = delegate.class();
+    unsafe {
+        ffi::class_addMethod(
+            class.cast_mut(),
+            sel!(applicationDockMenu:),
+            std::mem::transmute::<DockMenuImp, Imp>(dock_menu),
+            c"@@:@".as_ptr(),
+        );
+        ffi::class_addMethod(
+            class.cast_mut(),
+            sel!(astranotesOpenRecent:),
+            std::mem::transmute::<ActionImp, Imp>(open_recent),
+            c"v@:@".as_ptr(),
+        );
+    }
+}
+
+/// Replaces the dock menu's items with `titles`.
+pub(super) fn set_items(titles: &[String]) {
+    let Some(mtm) = MainThreadMarker::new() else {
+        return;
+    };
+    let delegate = delegate(mtm);
+    let menu = NSMenu::new(mtm);
+    for (index, title) in titles.iter().enumerate() {
+        let item = unsafe {
+            NSMenuItem::initWithTitle_action_keyEquivalent(
+                NSMenuItem::alloc(mtm),
+                &NSString::from_str(title),
+                Some(sel!(astranotesOpenRecent:)),
+                &NSString::new(),
+            )
+        };
+        item.setTag(index as isize);
+        unsafe { item.setTarget(delegate.as_deref()) };
+        menu.addItem(&item);
+    }
+    MENU.set((!titles.is_empty()).then_some(menu));
+}
@@ -0,0 +1,193 @@
+//! Recently opened playlists in the Windows jump list and the macOS dock menu.
+//!
+//! The frontend records each playlist it opens; the list is kept in
+//! `recent-playlists.json` in the data directory. Jump list entries relaunch
+//! the app with an `astranotes://playlist/<id>` link, which the single
+//! instance plugin hands to the running instance, and dock menu items open
+//! the same link directly, so a selection reaches the frontend as a
+//! `deep-link:navigate` event either way.
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State, Url};
+
+use crate::deep_link;
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::store::now_millis;
+
+const FILE_NAME: &str = "recent-playlists.json";
+const MAX_RECENT: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentPlaylist {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub opened_at: i64,
+}
+
+impl RecentPlaylist {
+    fn link(&self) -> String {
+        format!("astranotes://playlist/{}", self.id)
+    }
+}
+
+#[derive(Default)]
+pub struct RecentPlaylists(Mutex<Vec<RecentPlaylist>>);
+
+impl RecentPlaylists {
+    fn list(&self) -> std::sync::MutexGuard<'_, Vec<RecentPlaylist>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn file_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(paths::data_dir(app)?.join(FILE_NAME))
+}
+
+fn save(app: &AppHandle, list: &[RecentPlaylist]) -> Result<()> {
+    paths::write_json(&file_path(app)?, &list)
+}
+
+/// Opens the recent playlist at `index` as if its deep link had been followed.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn open(app: &AppHandle, index: usize) {
+    let Some(playlist) = app.state::<RecentPlaylists>().list().get(index).cloned() else {
+        return;
+    };
+    match Url::parse(&playlist.link()) {
+        Ok(url) => deep_link::handle_urls(app, vec![url]),
+        Err(err) => log::warn!("Unusable playlist id {}: {err}", playlist.id),
+    }
+}
+
+/// Mirrors the list into the jump list or dock menu.
+fn apply(app: &AppHandle) {
+    let list = app.state::<RecentPlaylists>().list().clone();
+    #[cfg(target_os = "macos")]
+    let result = app.run_on_main_thread(move || {
+        let titles: Vec<String> = list.into_iter().map(|playlist| playlist.name).collect();
+        macos::set_items(&titles);
+    });
+    #[cfg(target_os = "windows")]
+    let result = {
+        let handle = app.clone();
+        app.run_on_main_thread(move || {
+            let entries: Vec<(String, String)> = list
+                .iter()
+                .map(|playlist| (playlist.name.clone(), playlist.link()))
+                .collect();
+            let category = crate::i18n::tr("recent-playlists", &[]);
+            match windows::set_jump_list(&category, &entries) {
+                Ok(removed) if !removed.is_empty() => forget(&handle, &removed),
+                Ok(_) => {}
+                Err(err) => log::warn!("Failed to update the jump list: {err}"),
+            }
+        })
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let result: tauri::Result<()> = {
+        let _ = list;
+        Ok(())
+    };
+    if let Err(err) = result {
+        log::warn!("Failed to update recent playlists: {err}");
+    }
+}
+
+/// Drops playlists the user removed from the jump list.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn forget(app: &AppHandle, links: &[String]) {
+    let recent = app.state::<RecentPlaylists>();
+    let mut list = recent.list();
+    list.retain(|playlist| !links.contains(&playlist.link()));
+    if let Err(err) = save(app, &list) {
+        log::warn!("Failed to save recent playlists: {err}");
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let list: Vec<RecentPlaylist> = file_path(app)
+        .and_then(|path| paths::read_json(&path))
+        .unwrap_or_else(|err| {
+            log::warn!("Ignoring unreadable {FILE_NAME}: {err}");
+            Vec::new()
+        });
+    app.manage(RecentPlaylists(Mutex::new(list)));
+
+    #[cfg(target_os = "macos")]
+    {
+        let handle = app.clone();
+        let installed = app.run_on_main_thread(move || {
+            let opener = handle.clone();
+            macos::install(move |index| open(&opener, index));
+        });
+        if let Err(err) = installed {
+            log::warn!("Failed to install the dock menu: {err}");
+        }
+    }
+    apply(app);
+}
+
+/// Moves a playlist to the top of the recent list, adding it if needed.
+#[tauri::command]
+pub fn record_recent_playlist(
+    app: AppHandle,
+    recent: State<'_, RecentPlaylists>,
+    id: String,
+    name: String,
+) -> Result<Vec<RecentPlaylist>> {
+    let id = id.trim().to_string();
+    if id.is_empty() || id.contains('/') {
+        return Err(Error::InvalidInput("A playlist id is required".into()));
+    }
+    let name = match name.trim() {
+        "" => id.clone(),
+        name => name.to_string(),
+    };
+    let snapshot = {
+        let mut list = recent.list();
+        list.retain(|playlist| playlist.id != id);
+        list.insert(
+            0,
+            RecentPlaylist {
+                id,
+                name,
+                opened_at: now_millis(),
+            },
+        );
+        list.truncate(MAX_RECENT);
+        save(&app, &list)?;
+        list.clone()
+    };
+    apply(&app);
+    Ok(snapshot)
+}
+
+/// Recent playlists, most recently opened first.
+#[tauri::command]
+pub fn list_recent_playlists(recent: State<'_, RecentPlaylists>) -> Vec<RecentPlaylist> {
+    recent.list().clone()
+}
+
+#[tauri::command]
+pub fn clear_recent_playlists(app: AppHandle, recent: State<'_, RecentPlaylists>) -> Result<()> {
+    {
+        let mut list = recent.list();
+        list.clear();
+        save(&app, &list)?;
+    }
+    apply(&app);
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! The Windows jump list.
+//!
+//! Each entry is a shell link that relaunches the app with a playlist deep
+//! link; the single-instance plugin hands it to the running instance.
+
+use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+use windows::Win32::System::Com::StructuredStorage::PROPVARIANT;
+use windows::Win32::System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance};
+use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+use windows::Win32::UI::Shell::{
+    DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink,
+};
+use windows::core::{HSTRING, Interface, Result};
+
+/// Arguments of the links the user removed from the list.
+fn removed_arguments(removed: &IObjectArray) -> Result<Vec<String>> {
+    let mut arguments = Vec::new();
+    for index in 0..unsafe { removed.GetCount()? } {
+        let link: IShellLinkW = unsafe { removed.GetAt(index)? };
+        let mut buffer = [0u16; 1024];
+        unsafe { link.GetArguments(&mut buffer)? };
+        let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+        arguments.push(String::from_utf16_lossy(&buffer[..len]));
+    }
+    Ok(arguments)
+}
+
+/// Replaces the jump list's `category` with `(title, arguments)` entries and
+/// returns the arguments of entries the user has removed since the last
+/// update, which must be left out. Must run on a thread with COM set up,
+/// such as the UI thread.
+pub(super) fn set_jump_list(category: &str, entries: &[(String, String)]) -> Result<Vec<String>> {
+    let exe = HSTRING::from(std::env::current_exe()?.as_os_str());
+    unsafe {
+        let list: ICustomDestinationList =
+            CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut slots = 0u32;
+        let removed: IObjectArray = list.BeginList(&mut slots)?;
+        let removed = removed_arguments(&removed)?;
+
+        let collection: IObjectCollection =
+            CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+        let entries = entries
+            .iter()
+            .filter(|(_, arguments)| !removed.contains(arguments))
+            .take(slots as usize);
+        for (title, arguments) in entries {
+            let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+            link.SetPath(&exe)?;
+            link.SetArguments(&HSTRING::from(arguments.as_str()))?;
+            link.SetIconLocation(&exe, 0)?;
+            link.SetDescription(&HSTRING::from(title.as_str()))?;
+            // The list shows the title property, not the description
+            let properties: IPropertyStore = link.cast()?;
+            properties.SetValue(&PKEY_Title, &PROPVARIANT::from(title.as_str()))?;
+            properties.Commit()?;
+            collection.AddObject(&link)?;
+        }
+
+        let items: IObjectArray = collection.cast()?;
+        if items.GetCount()? > 0 {
+            list.AppendCategory(&HSTRING::from(category), &items)?;
+        }
+        list.CommitList()?;
+        Ok(removed)
+    }
+}
@@ -0,0 +1,101 @@
+//! Crash recovery for frontend state.
+//!
+//! The frontend periodically saves its critical state (open playlist and
+//! unsaved drafts) with `save_recovery_snapshot`. A session marker in the app
+//! data directory is written at startup and removed on a clean exit, so a
+//! marker left behind means the previous run crashed or was killed; only then
+//! does `get_recovery_snapshot` offer the last snapshot back.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::store::Store;
+use crate::store::recovery::RecoverySnapshot;
+
+const SESSION_MARKER: &str = "session.lock";
+const MAX_SNAPSHOT_BYTES: usize = 8 * 1024 * 1024;
+
+pub struct Recovery {
+    unclean_exit: AtomicBool,
+}
+
+fn marker_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(paths::data_dir(app)?.join(SESSION_MARKER))
+}
+
+/// Detects an unclean previous exit and marks this session as running.
+pub fn init(app: &AppHandle) -> Result<()> {
+    let path = marker_path(app)?;
+    let unclean_exit = path.exists() && app.state::<Store>().has_recovery_snapshot()?;
+    if unclean_exit {
+        log::warn!("Previous session did not exit cleanly; recovery snapshot available");
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, std::process::id().to_string())?;
+    app.manage(Recovery {
+        unclean_exit: AtomicBool::new(unclean_exit),
+    });
+    Ok(())
+}
+
+/// Clears the session marker and snapshots once the app exits normally.
+pub fn clean_exit(app: &AppHandle) {
+    if app.try_state::<Recovery>().is_none() {
+        return;
+    }
+    if let Err(err) = app.state::<Store>().clear_recovery_snapshots() {
+        log::warn!("Failed to clear recovery snapshots: {err}");
+    }
+    match marker_path(app).map(fs::remove_file) {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Ok(Err(err)) => log::warn!("Failed to remove session marker: {err}"),
+        Err(err) => log::warn!("Failed to resolve session marker: {err}"),
+    }
+}
+
+#[tauri::command]
+pub fn save_recovery_snapshot(
+    store: State<'_, Store>,
+    state: Value,
+    project_id: Option<String>,
+) -> Result<Option<i64>> {
+    let state = serde_json::to_string(&state)?;
+    if state.len() > MAX_SNAPSHOT_BYTES {
+        return Err(Error::InvalidInput(format!(
+            "Recovery snapshot is {} bytes; the limit is {MAX_SNAPSHOT_BYTES}",
+            state.len()
+        )));
+    }
+    store.save_recovery_snapshot(&state, project_id.as_deref())
+}
+
+/// The last snapshot from a session that ended uncleanly, if any.
+#[tauri::command]
+pub fn get_recovery_snapshot(
+    store: State<'_, Store>,
+    recovery: State<'_, Recovery>,
+) -> Result<Option<RecoverySnapshot>> {
+    if !recovery.unclean_exit.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+    store.latest_recovery_snapshot()
+}
+
+/// Discards the snapshot once it has been restored or declined.
+#[tauri::command]
+pub fn dismiss_recovery_snapshot(
+    store: State<'_, Store>,
+    recovery: State<'_, Recovery>,
+) -> Result<()> {
+    recovery.unclean_exit.store(false, Ordering::Relaxed);
+    store.clear_recovery_snapshots()
+}
@@ -0,0 +1,192 @@
+//! Grid of playlist thumbnails with version names and statuses burned in.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage};
+use serde::Deserialize;
+use tauri::AppHandle;
+
+use super::text::TextRenderer;
+use super::{OutputFormat, pdf};
+use crate::annotations;
+use crate::error::Result;
+use crate::export::{self, write_atomically};
+
+const BACKGROUND: Rgb<u8> = Rgb([30, 30, 30]);
+const PLACEHOLDER: Rgb<u8> = Rgb([55, 55, 55]);
+const TEXT: Rgb<u8> = Rgb([235, 235, 235]);
+const MUTED_TEXT: Rgb<u8> = Rgb([160, 160, 160]);
+const PADDING: u32 = 16;
+const STATUS_BAR_HEIGHT: u32 = 4;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactSheetThumbnail {
+    /// Missing or unreadable thumbnails are drawn as an empty frame.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    pub version_name: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Status colour as `#rrggbb`, as reported by ftrack.
+    #[serde(default)]
+    pub status_color: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContactSheetLayout {
+    pub columns: u32,
+    pub cell_width: u32,
+    /// Thumbnail aspect ratio as width / height.
+    pub aspect_ratio: f32,
+    pub title: Option<String>,
+}
+
+impl Default for ContactSheetLayout {
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            cell_width: 320,
+            aspect_ratio: 16.0 / 9.0,
+            title: None,
+        }
+    }
+}
+
+fn fill(canvas: &mut RgbImage, x: u32, y: u32, width: u32, height: u32, color: Rgb<u8>) {
+    for py in y..(y + height).min(canvas.height()) {
+        for px in x..(x + width).min(canvas.width()) {
+            canvas.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn render(thumbnails: &[ContactSheetThumbnail], layout: &ContactSheetLayout) -> RgbImage {
+    let text = TextRenderer::new();
+    let columns = layout.columns.clamp(1, 16);
+    let rows = (thumbnails.len() as u32).div_ceil(columns).max(1);
+    let cell_width = layout.cell_width.clamp(64, 2048);
+    let thumb_height = (cell_width as f32 / layout.aspect_ratio.clamp(0.25, 4.0)).round() as u32;
+    let name_size = (cell_width as f32 / 18.0).clamp(12.0, 28.0);
+    let status_size = name_size * 0.8;
+    let caption_height = STATUS_BAR_HEIGHT + (name_size + status_size) as u32 + PADDING;
+    let cell_height = thumb_height + caption_height;
+    let title_size = name_size * 1.6;
+    let header_height = match layout.title {
+        Some(_) => title_size as u32 + PADDING * 2,
+        None => PADDING,
+    };
+
+    let width = PADDING + columns * (cell_width + PADDING);
+    let height = header_height + rows * (cell_height + PADDING);
+    let mut canvas = RgbImage::from_pixel(width, height, BACKGROUND);
+
+    if let Some(title) = &layout.title {
+        let title = text.fit(title, title_size, (width - PADDING * 2) as f32);
+        text.draw(
+            &mut canvas,
+            &title,
+            title_size,
+            PADDING as i32,
+            PADDING as i32,
+            TEXT,
+        );
+    }
+
+    for (index, thumbnail) in thumbnails.iter().enumerate() {
+        let index = index as u32;
+        let x = PADDING + (index % columns) * (cell_width + PADDING);
+        let y = header_height + (index / columns) * (cell_height + PADDING);
+
+        fill(&mut canvas, x, y, cell_width, thumb_height, PLACEHOLDER);
+        let decoded = thumbnail.path.as_deref().and_then(|path| {
+            image::open(path)
+                .inspect_err(|err| log::warn!("Skipping thumbnail {}: {err}", path.display()))
+                .ok()
+        });
+        if let Some(decoded) = decoded {
+            let scaled = decoded
+                .resize(cell_width, thumb_height, FilterType::Triangle)
+                .to_rgb8();
+            let offset_x = x + (cell_width - scaled.width()) / 2;
+            let offset_y = y + (thumb_height - scaled.height()) / 2;
+            imageops::replace(&mut canvas, &scaled, offset_x.into(), offset_y.into());
+        }
+
+        let status_color = thumbnail
+            .status_color
+            .as_deref()
+            .and_then(|color| annotations::parse_color(color).ok())
+            .map(|color| {
+                let color = color.to_color_u8();
+                Rgb([color.red(), color.green(), color.blue()])
+            })
+            .unwrap_or(PLACEHOLDER);
+        fill(
+            &mut canvas,
+            x,
+            y + thumb_height,
+            cell_width,
+            STATUS_BAR_HEIGHT,
+            status_color,
+        );
+
+        let caption_y = y + thumb_height + STATUS_BAR_HEIGHT + PADDING / 4;
+        let max_width = cell_width as f32;
+        let name = text.fit(&thumbnail.version_name, name_size, max_width);
+        text.draw(
+            &mut canvas,
+            &name,
+            name_size,
+            x as i32,
+            caption_y as i32,
+            TEXT,
+        );
+        if let Some(status) = &thumbnail.status {
+            let status = text.fit(status, status_size, max_width);
+            let status_y = caption_y + name_size as u32;
+            text.draw(
+                &mut canvas,
+                &status,
+                status_size,
+                x as i32,
+                status_y as i32,
+                MUTED_TEXT,
+            );
+        }
+    }
+    canvas
+}
+
+fn write(path: &Path, canvas: &RgbImage, format: OutputFormat) -> Result<()> {
+    write_atomically(path, |temp| match format {
+        OutputFormat::Png => Ok(canvas.save_with_format(temp, image::ImageFormat::Png)?),
+        OutputFormat::Pdf => Ok(fs::write(temp, pdf::image_page(canvas)?)?),
+    })
+}
+
+/// Composites `thumbnails` into a grid and writes it as PNG or PDF, chosen by
+/// the extension of `out_path`.
+#[tauri::command]
+pub async fn generate_contact_sheet(
+    app: AppHandle,
+    playlist_id: String,
+    thumbnails: Vec<ContactSheetThumbnail>,
+    layout: Option<ContactSheetLayout>,
+    out_path: PathBuf,
+) -> Result<PathBuf> {
+    let format = OutputFormat::from_path(&out_path)?;
+    let layout = layout.unwrap_or_default();
+    let path = out_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let canvas = render(&thumbnails, &layout);
+        write(&path, &canvas, format)
+    })
+    .await??;
+    log::info!("Wrote contact sheet to {}", out_path.display());
+    export::exported(&app, "contact-sheet", &playlist_id, &out_path);
+    Ok(out_path)
+}
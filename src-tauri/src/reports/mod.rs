@@ -0,0 +1,35 @@
+//! Printable summaries of a review session.
+//!
+//! Compositing happens natively so large playlists are not limited by the
+//! webview's canvas size and memory.
+
+pub mod contact_sheet;
+pub(crate) mod pdf;
+pub(crate) mod text;
+
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    Png,
+    Pdf,
+}
+
+impl OutputFormat {
+    pub(crate) fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("png") => Ok(Self::Png),
+            Some("pdf") => Ok(Self::Pdf),
+            _ => Err(Error::InvalidInput(format!(
+                "Unsupported report format: {}",
+                path.display()
+            ))),
+        }
+    }
+}
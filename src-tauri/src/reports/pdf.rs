@@ -0,0 +1,282 @@
+//! Small PDF writer shared by the raster and note reports.
+//!
+//! Text uses the bundled Ubuntu font embedded as a CID font, so notes in any
+//! script it covers stay selectable and searchable in the output.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use ab_glyph::{Font, FontRef};
+use image::RgbImage;
+use image::codecs::jpeg::JpegEncoder;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+
+use crate::error::Result;
+
+const JPEG_QUALITY: u8 = 90;
+const FONT_NAME: Name<'static> = Name(b"Ubuntu-Light");
+const FONT_RESOURCE: Name<'static> = Name(b"F1");
+const IDENTITY: SystemInfo<'static> = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+pub(crate) const A4: (f32, f32) = (595.0, 842.0);
+pub(crate) const LETTER: (f32, f32) = (612.0, 792.0);
+
+/// An RGB colour with components in `0.0..=1.0`.
+pub(crate) type Color = (f32, f32, f32);
+
+/// Drawing commands for one page. Coordinates are in points from the
+/// top-left corner, unlike PDF's native bottom-left origin.
+pub(crate) struct Page {
+    content: Content,
+    images: Vec<Ref>,
+    height: f32,
+}
+
+impl Page {
+    pub(crate) fn image(&mut self, image: Ref, x: f32, y: f32, width: f32, height: f32) {
+        let name = format!("Im{}", self.images.len());
+        self.images.push(image);
+        self.content.save_state();
+        self.content
+            .transform([width, 0.0, 0.0, height, x, self.height - y - height]);
+        self.content.x_object(Name(name.as_bytes()));
+        self.content.restore_state();
+    }
+
+    pub(crate) fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: Color) {
+        self.content.set_stroke_rgb(color.0, color.1, color.2);
+        self.content.set_line_width(0.5);
+        self.content.move_to(x1, self.height - y1);
+        self.content.line_to(x2, self.height - y2);
+        self.content.stroke();
+    }
+}
+
+pub(crate) struct Document {
+    pdf: Pdf,
+    next_id: i32,
+    font: FontRef<'static>,
+    glyphs: BTreeMap<u16, char>,
+    size: (f32, f32),
+}
+
+impl Document {
+    pub(crate) fn new(size: (f32, f32)) -> Self {
+        Self {
+            pdf: Pdf::new(),
+            // 1 and 2 are reserved for the catalog and page tree
+            next_id: 3,
+            font: FontRef::try_from_slice(epaint_default_fonts::UBUNTU_LIGHT)
+                .expect("bundled font is valid"),
+            glyphs: BTreeMap::new(),
+            size,
+        }
+    }
+
+    fn alloc(&mut self) -> Ref {
+        let id = Ref::new(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    pub(crate) fn size(&self) -> (f32, f32) {
+        self.size
+    }
+
+    pub(crate) fn new_page(&self) -> Page {
+        Page {
+            content: Content::new(),
+            images: Vec::new(),
+            height: self.size.1,
+        }
+    }
+
+    /// Embeds `image` as a JPEG and returns a handle for [`Page::image`].
+    pub(crate) fn add_image(&mut self, image: &RgbImage) -> Result<Ref> {
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(Cursor::new(&mut jpeg), JPEG_QUALITY).encode_image(image)?;
+        let id = self.alloc();
+        let mut xobject = self.pdf.image_xobject(id, &jpeg);
+        xobject.filter(Filter::DctDecode);
+        xobject.width(image.width() as i32);
+        xobject.height(image.height() as i32);
+        xobject.color_space().device_rgb();
+        xobject.bits_per_component(8);
+        xobject.finish();
+        Ok(id)
+    }
+
+    /// Width of `text` in points at `size`.
+    pub(crate) fn text_width(&self, text: &str, size: f32) -> f32 {
+        let units_per_em = self.font.units_per_em().unwrap_or(1000.0);
+        text.chars()
+            .map(|c| self.font.h_advance_unscaled(self.font.glyph_id(c)))
+            .sum::<f32>()
+            * size
+            / units_per_em
+    }
+
+    /// Greedy word wrap; words wider than `max_width` are broken by character.
+    pub(crate) fn wrap(&self, text: &str, size: f32, max_width: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{line} {word}")
+                };
+                if self.text_width(&candidate, size) <= max_width {
+                    line = candidate;
+                    continue;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                for c in word.chars() {
+                    line.push(c);
+                    if self.text_width(&line, size) > max_width && line.chars().count() > 1 {
+                        line.pop();
+                        lines.push(std::mem::replace(&mut line, c.to_string()));
+                    }
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Draws one line of text with its top at `y`.
+    pub(crate) fn text(
+        &mut self,
+        page: &mut Page,
+        text: &str,
+        x: f32,
+        y: f32,
+        size: f32,
+        color: Color,
+    ) {
+        let mut encoded = Vec::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            let id = self.font.glyph_id(c).0;
+            self.glyphs.entry(id).or_insert(c);
+            encoded.extend_from_slice(&id.to_be_bytes());
+        }
+        let units_per_em = self.font.units_per_em().unwrap_or(1000.0);
+        let baseline = y + self.font.ascent_unscaled() * size / units_per_em;
+
+        page.content.begin_text();
+        page.content.set_fill_rgb(color.0, color.1, color.2);
+        page.content.set_font(FONT_RESOURCE, size);
+        page.content.next_line(x, page.height - baseline);
+        page.content.show(Str(&encoded));
+        page.content.end_text();
+    }
+
+    fn write_font(&mut self) -> Ref {
+        let font_id = self.alloc();
+        let cid_id = self.alloc();
+        let descriptor_id = self.alloc();
+        let file_id = self.alloc();
+        let cmap_id = self.alloc();
+        let font = self.font.clone();
+        let scale = 1000.0 / font.units_per_em().unwrap_or(1000.0);
+
+        let mut type0 = self.pdf.type0_font(font_id);
+        type0.base_font(FONT_NAME);
+        type0.encoding_predefined(Name(b"Identity-H"));
+        type0.descendant_font(cid_id);
+        type0.to_unicode(cmap_id);
+        type0.finish();
+
+        let mut cid = self.pdf.cid_font(cid_id);
+        cid.subtype(CidFontType::Type2);
+        cid.base_font(FONT_NAME);
+        cid.system_info(IDENTITY);
+        cid.font_descriptor(descriptor_id);
+        cid.cid_to_gid_map_predefined(Name(b"Identity"));
+        let mut widths = cid.widths();
+        for &id in self.glyphs.keys() {
+            let advance = font.h_advance_unscaled(ab_glyph::GlyphId(id)) * scale;
+            widths.consecutive(id, [advance]);
+        }
+        widths.finish();
+        cid.finish();
+
+        let ascent = font.ascent_unscaled() * scale;
+        let descent = font.descent_unscaled() * scale;
+        let mut descriptor = self.pdf.font_descriptor(descriptor_id);
+        descriptor.name(FONT_NAME);
+        descriptor.flags(FontFlags::NON_SYMBOLIC);
+        descriptor.bbox(Rect::new(-200.0, descent, 1200.0, ascent));
+        descriptor.italic_angle(0.0);
+        descriptor.ascent(ascent);
+        descriptor.descent(descent);
+        descriptor.cap_height(ascent * 0.7);
+        descriptor.stem_v(80.0);
+        descriptor.font_file2(file_id);
+        descriptor.finish();
+
+        let data = epaint_default_fonts::UBUNTU_LIGHT;
+        self.pdf
+            .stream(file_id, data)
+            .pair(Name(b"Length1"), data.len() as i32);
+
+        let mut cmap = UnicodeCmap::new(Name(b"Custom"), IDENTITY);
+        for (&id, &c) in &self.glyphs {
+            cmap.pair(id, c);
+        }
+        self.pdf.cmap(cmap_id, &cmap.finish());
+        font_id
+    }
+
+    pub(crate) fn finish(mut self, pages: Vec<Page>) -> Vec<u8> {
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let font_id = (!self.glyphs.is_empty()).then(|| self.write_font());
+
+        let mut page_ids = Vec::with_capacity(pages.len());
+        for page in pages {
+            let page_id = self.alloc();
+            let content_id = self.alloc();
+            page_ids.push(page_id);
+            self.pdf.stream(content_id, &page.content.finish());
+
+            let mut writer = self.pdf.page(page_id);
+            writer.media_box(Rect::new(0.0, 0.0, self.size.0, self.size.1));
+            writer.parent(page_tree_id);
+            writer.contents(content_id);
+            let mut resources = writer.resources();
+            if let Some(font_id) = font_id {
+                resources.fonts().pair(FONT_RESOURCE, font_id);
+            }
+            let mut x_objects = resources.x_objects();
+            for (index, image) in page.images.iter().enumerate() {
+                x_objects.pair(Name(format!("Im{index}").as_bytes()), *image);
+            }
+        }
+
+        self.pdf.catalog(catalog_id).pages(page_tree_id);
+        self.pdf
+            .pages(page_tree_id)
+            .count(page_ids.len() as i32)
+            .kids(page_ids);
+        self.pdf.finish()
+    }
+}
+
+/// Wraps `image` in a one-page PDF sized to the image at 72 dpi.
+pub(crate) fn image_page(image: &RgbImage) -> Result<Vec<u8>> {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let mut document = Document::new((width, height));
+    let image = document.add_image(image)?;
+    let mut page = document.new_page();
+    page.image(image, 0.0, 0.0, width, height);
+    Ok(document.finish(vec![page]))
+}
@@ -0,0 +1,94 @@
+//! Minimal text rasterization for burn-ins.
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use image::{Rgb, RgbImage};
+
+pub(crate) struct TextRenderer {
+    font: FontRef<'static>,
+}
+
+impl TextRenderer {
+    pub(crate) fn new() -> Self {
+        let font = FontRef::try_from_slice(epaint_default_fonts::UBUNTU_LIGHT)
+            .expect("bundled font is valid");
+        Self { font }
+    }
+
+    pub(crate) fn font(&self) -> &FontRef<'static> {
+        &self.font
+    }
+
+    pub(crate) fn width(&self, text: &str, size: f32) -> f32 {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                width += scaled.kern(previous, id);
+            }
+            width += scaled.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    }
+
+    /// Shortens `text` with an ellipsis so it fits within `max_width` pixels.
+    pub(crate) fn fit(&self, text: &str, size: f32, max_width: f32) -> String {
+        if self.width(text, size) <= max_width {
+            return text.to_string();
+        }
+        let mut chars: Vec<char> = text.chars().collect();
+        while !chars.is_empty() {
+            chars.pop();
+            let candidate = format!("{}…", chars.iter().collect::<String>().trim_end());
+            if self.width(&candidate, size) <= max_width {
+                return candidate;
+            }
+        }
+        String::new()
+    }
+
+    /// Draws `text` with its top-left corner at (`x`, `y`).
+    pub(crate) fn draw(
+        &self,
+        canvas: &mut RgbImage,
+        text: &str,
+        size: f32,
+        x: i32,
+        y: i32,
+        color: Rgb<u8>,
+    ) {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        let baseline = y as f32 + scaled.ascent();
+        let mut caret = x as f32;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(size, point(caret, baseline));
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+
+            let Some(outlined) = self.font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px >= canvas.width() as i32 || py >= canvas.height() as i32 {
+                    return;
+                }
+                let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+                for (channel, target) in pixel.0.iter_mut().zip(color.0) {
+                    let blended =
+                        f32::from(*channel) * (1.0 - coverage) + f32::from(target) * coverage;
+                    *channel = blended.round() as u8;
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! Resource monitor with automatic cache trimming.
+//!
+//! Long review sessions used to grow without bound, so a background task
+//! samples the process's resident memory and open file handles along with the
+//! size of the disk cache. Crossing a threshold emits `resources:warning` once
+//! (until usage drops back below) and trims what the backend owns: parsed LUTs
+//! when memory runs high; least recently used thumbnails, then the oldest
+//! generated media and then the API cache when the disk cache is over budget. The frontend is expected to drop
+//! its own caches on the memory warning. `get_cache_breakdown` splits the disk
+//! cache by directory and the thumbnails by project, for managing it by hand.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::color::ColorManager;
+use crate::error::{Error, Result};
+use crate::ftrack::cache::ApiCache;
+use crate::paths;
+use crate::thumbnail_cache::{ProjectUsage, ThumbnailCache};
+
+const SETTINGS_FILE: &str = "resources.json";
+pub const WARNING_EVENT: &str = "resources:warning";
+const MIB: u64 = 1024 * 1024;
+/// Trimming aims this far under the cache limit so it does not run every interval.
+const CACHE_TRIM_TARGET: f64 = 0.8;
+/// Cache directories of media the app generates and can generate again:
+/// transcodes, filmstrips, burned-in watermarks, comparisons, captures,
+/// ingest thumbnails, pasted images, transcription audio, print previews and
+/// player sessions.
+const MEDIA_CACHE_DIRS: [&str; 10] = [
+    "transcodes",
+    "filmstrips",
+    "watermarks",
+    "comparisons",
+    "captures",
+    "ingest",
+    "clipboard",
+    "transcription",
+    "print",
+    "player-sessions",
+];
+/// Generated media this recent may still be in use, so trimming keeps it.
+const MEDIA_MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResourceSettings {
+    pub max_memory_mb: u64,
+    pub max_cache_mb: u64,
+    pub max_open_files: usize,
+    pub interval_secs: u64,
+}
+
+impl Default for ResourceSettings {
+    fn default() -> Self {
+        Self {
+            max_memory_mb: 2048,
+            max_cache_mb: 4096,
+            max_open_files: 2048,
+            interval_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceStats {
+    pub memory_bytes: u64,
+    pub virtual_memory_bytes: u64,
+    pub cpu_percent: f32,
+    /// Unavailable on macOS.
+    pub open_files: Option<usize>,
+    pub open_files_limit: Option<usize>,
+    pub cache_bytes: u64,
+    pub thumbnail_cache_bytes: u64,
+    pub api_cache_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheTypeUsage {
+    /// The cache directory, such as `thumbnails` or `api-cache`, or `other`
+    /// for loose files.
+    pub kind: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheBreakdown {
+    pub total_bytes: u64,
+    pub limit_bytes: u64,
+    /// Largest first.
+    pub by_type: Vec<CacheTypeUsage>,
+    /// Thumbnails by the project they were fetched for, largest first.
+    pub by_project: Vec<ProjectUsage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Resource {
+    Memory,
+    Cache,
+    OpenFiles,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Warning {
+    resource: Resource,
+    value: u64,
+    limit: u64,
+    /// What was done about it, when anything could be.
+    action: Option<String>,
+}
+
+pub struct ResourceMonitor {
+    system: Mutex<System>,
+    pid: Option<Pid>,
+    /// Resources currently over their limit, so each crossing warns once.
+    over: Mutex<HashSet<Resource>>,
+}
+
+impl Default for ResourceMonitor {
+    fn default() -> Self {
+        Self {
+            system: Mutex::new(System::new()),
+            pid: sysinfo::get_current_pid().ok(),
+            over: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+pub(crate) fn load_settings(app: &AppHandle) -> Result<ResourceSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn measure(app: &AppHandle) -> Result<ResourceStats> {
+    let monitor = app.state::<ResourceMonitor>();
+    let (memory_bytes, virtual_memory_bytes, cpu_percent, open_files, open_files_limit) = {
+        let mut system = monitor
+            .system
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let pid = monitor
+            .pid
+            .ok_or_else(|| Error::InvalidInput("Process ID unavailable".into()))?;
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            false,
+            ProcessRefreshKind::nothing().with_memory().with_cpu(),
+        );
+        let process = system
+            .process(pid)
+            .ok_or_else(|| Error::InvalidInput("Process not found".into()))?;
+        (
+            process.memory(),
+            process.virtual_memory(),
+            process.cpu_usage(),
+            process.open_files(),
+            process.open_files_limit(),
+        )
+    };
+
+    let cache_dir = paths::cache_dir(app)?;
+    Ok(ResourceStats {
+        memory_bytes,
+        virtual_memory_bytes,
+        cpu_percent,
+        open_files,
+        open_files_limit,
+        cache_bytes: dir_size(&cache_dir),
+        thumbnail_cache_bytes: app.state::<ThumbnailCache>().size_bytes()?,
+        // The API cache opens after startup; until then it holds nothing
+        api_cache_bytes: match app.try_state::<ApiCache>() {
+            Some(cache) => cache.stats()?.size_bytes,
+            None => 0,
+        },
+    })
+}
+
+fn breakdown(app: &AppHandle) -> Result<CacheBreakdown> {
+    let cache_dir = paths::cache_dir(app)?;
+    let mut by_type = Vec::new();
+    let mut other = 0;
+    for entry in fs::read_dir(&cache_dir)?.flatten() {
+        match entry.metadata() {
+            Ok(meta) if meta.is_dir() => by_type.push(CacheTypeUsage {
+                kind: entry.file_name().to_string_lossy().into_owned(),
+                bytes: dir_size(&entry.path()),
+            }),
+            Ok(meta) => other += meta.len(),
+            Err(_) => {}
+        }
+    }
+    if other > 0 {
+        by_type.push(CacheTypeUsage {
+            kind: "other".into(),
+            bytes: other,
+        });
+    }
+    by_type.sort_by_key(|usage| std::cmp::Reverse(usage.bytes));
+    let settings = load_settings(app).unwrap_or_default();
+    Ok(CacheBreakdown {
+        total_bytes: by_type.iter().map(|usage| usage.bytes).sum(),
+        limit_bytes: settings.max_cache_mb * MIB,
+        by_type,
+        by_project: app.state::<ThumbnailCache>().usage_by_project()?,
+    })
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<(PathBuf, u64, SystemTime)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        match entry.metadata() {
+            Ok(meta) if meta.is_dir() => collect_files(&entry.path(), files),
+            Ok(meta) => files.push((
+                entry.path(),
+                meta.len(),
+                meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            )),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Deletes the oldest generated media until `excess` bytes are freed.
+/// Returns the number of files removed and the bytes freed.
+fn trim_media(cache_dir: &Path, excess: u64) -> (usize, u64) {
+    let mut files = Vec::new();
+    for name in MEDIA_CACHE_DIRS {
+        collect_files(&cache_dir.join(name), &mut files);
+    }
+    let now = SystemTime::now();
+    files.retain(|(_, _, modified)| {
+        now.duration_since(*modified)
+            .is_ok_and(|age| age >= MEDIA_MIN_AGE)
+    });
+    files.sort_by_key(|(_, _, modified)| *modified);
+
+    let (mut removed, mut freed) = (0, 0);
+    for (path, size, _) in files {
+        if freed >= excess {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                removed += 1;
+                freed += size;
+                // Drops per-job directories once emptied; fails harmlessly otherwise
+                if let Some(parent) = path.parent()
+                    && !MEDIA_CACHE_DIRS
+                        .iter()
+                        .any(|name| parent == cache_dir.join(name))
+                {
+                    let _ = fs::remove_dir(parent);
+                }
+            }
+            Err(err) => log::warn!("Failed to trim {}: {err}", path.display()),
+        }
+    }
+    (removed, freed)
+}
+
+/// Frees disk cache until the total is comfortably below `limit`.
+fn trim_cache(app: &AppHandle, stats: &ResourceStats, limit: u64) -> Result<String> {
+    let target = (limit as f64 * CACHE_TRIM_TARGET) as u64;
+    let mut excess = stats.cache_bytes.saturating_sub(target);
+    let evicted = app
+        .state::<ThumbnailCache>()
+        .evict(stats.thumbnail_cache_bytes.saturating_sub(excess))?;
+    let mut action = format!(
+        "Evicted {} thumbnails ({} MiB)",
+        evicted.evicted,
+        evicted.freed_bytes / MIB
+    );
+    excess = excess.saturating_sub(evicted.freed_bytes);
+    if excess > 0 {
+        let (removed, freed) = trim_media(&paths::cache_dir(app)?, excess);
+        if removed > 0 {
+            action.push_str(&format!(
+                ", removed {removed} generated media files ({} MiB)",
+                freed / MIB
+            ));
+        }
+        excess = excess.saturating_sub(freed);
+    }
+    if excess > 0
+        && stats.api_cache_bytes > 0
+        && let Some(cache) = app.try_state::<ApiCache>()
+    {
+        let removed = cache.clear()?;
+        action.push_str(&format!(" and cleared {removed} API cache entries"));
+    }
+    Ok(action)
+}
+
+fn trim_memory(app: &AppHandle) -> String {
+    let cleared = app.state::<ColorManager>().clear_lut_caches();
+    format!("Released {cleared} cached LUTs")
+}
+
+/// Warns on each resource that just crossed its limit and trims what it can.
+fn check(app: &AppHandle, settings: &ResourceSettings, stats: &ResourceStats) {
+    let readings = [
+        (
+            Resource::Memory,
+            stats.memory_bytes,
+            settings.max_memory_mb * MIB,
+        ),
+        (
+            Resource::Cache,
+            stats.cache_bytes,
+            settings.max_cache_mb * MIB,
+        ),
+        (
+            Resource::OpenFiles,
+            stats.open_files.unwrap_or_default() as u64,
+            settings.max_open_files as u64,
+        ),
+    ];
+    let monitor = app.state::<ResourceMonitor>();
+    for (resource, value, limit) in readings {
+        let crossed = {
+            let mut over = monitor
+                .over
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if value <= limit {
+                over.remove(&resource);
+                continue;
+            }
+            over.insert(resource)
+        };
+
+        // The cache is trimmed whenever it is over, since that is fully in our hands
+        let action = match resource {
+            Resource::Memory if crossed => Some(trim_memory(app)),
+            Resource::Cache => match trim_cache(app, stats, limit) {
+                Ok(action) => Some(action),
+                Err(err) => {
+                    log::warn!("Failed to trim cache: {err}");
+                    None
+                }
+            },
+            _ => None,
+        };
+        if !crossed {
+            continue;
+        }
+        log::warn!(
+            "{resource:?} usage {value} is over the limit of {limit}; {}",
+            action.as_deref().unwrap_or("no action taken")
+        );
+        let warning = Warning {
+            resource,
+            value,
+            limit,
+            action,
+        };
+        if let Err(err) = app.emit(WARNING_EVENT, &warning) {
+            log::warn!("Failed to emit resource warning: {err}");
+        }
+    }
+}
+
+/// Starts the monitoring loop.
+pub fn init(app: &AppHandle) {
+    app.manage(ResourceMonitor::default());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let settings = load_settings(&app).unwrap_or_default();
+            let handle = app.clone();
+            let result = tauri::async_runtime::spawn_blocking(move || {
+                let stats = measure(&handle)?;
+                check(&handle, &settings, &stats);
+                Ok::<_, Error>(settings.interval_secs)
+            })
+            .await;
+            let interval = match result {
+                Ok(Ok(interval)) => interval,
+                Ok(Err(err)) => {
+                    log::warn!("Resource check failed: {err}");
+                    ResourceSettings::default().interval_secs
+                }
+                Err(err) => {
+                    log::warn!("Resource check panicked: {err}");
+                    ResourceSettings::default().interval_secs
+                }
+            };
+            tokio::time::sleep(Duration::from_secs(interval.max(5))).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_resource_stats(app: AppHandle) -> Result<ResourceStats> {
+    tauri::async_runtime::spawn_blocking(move || measure(&app)).await?
+}
+
+#[tauri::command]
+pub async fn get_cache_breakdown(app: AppHandle) -> Result<CacheBreakdown> {
+    tauri::async_runtime::spawn_blocking(move || breakdown(&app)).await?
+}
+
+#[tauri::command]
+pub fn get_resource_settings(app: AppHandle) -> Result<ResourceSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_resource_settings(
+    app: AppHandle,
+    monitor: State<'_, ResourceMonitor>,
+    settings: ResourceSettings,
+) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &settings)?;
+    // Re-evaluate every resource against the new limits
+    monitor
+        .over
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clear();
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! Turns comments pulled from external review tools into note drafts.
+//!
+//! Each review tool fetches its comments and converts them to
+//! [`ReviewComment`]s. They are then written as text into the draft for the
+//! version they were mapped to. Lines already present in a draft are skipped,
+//! so importing the same review twice does not duplicate anything.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::store::Store;
+use crate::store::drafts::Draft;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewComment {
+    pub id: String,
+    pub author: Option<String>,
+    pub text: String,
+    pub frame: Option<i64>,
+    pub timecode: Option<String>,
+    pub created_at: Option<String>,
+    /// Drawing data in the source tool's own format.
+    pub annotation: Option<Value>,
+    /// Rendered sketch, when the tool provides one.
+    pub image_url: Option<String>,
+    pub replies: Vec<ReviewComment>,
+}
+
+impl ReviewComment {
+    fn lines(&self, reply: bool, lines: &mut Vec<String>) {
+        let mut line = String::new();
+        if reply {
+            line.push_str("  ↳ ");
+        } else {
+            match (&self.timecode, self.frame) {
+                (Some(timecode), Some(frame)) => {
+                    line.push_str(&format!("[{timecode} / f{frame}] "))
+                }
+                (None, Some(frame)) => line.push_str(&format!("[f{frame}] ")),
+                (Some(timecode), None) => line.push_str(&format!("[{timecode}] ")),
+                (None, None) => {}
+            }
+        }
+        if let Some(author) = &self.author {
+            line.push_str(author);
+            line.push_str(": ");
+        }
+        line.push_str(self.text.trim());
+        if let Some(url) = &self.image_url {
+            line.push_str(&format!(" (sketch: {url})"));
+        }
+        lines.push(line);
+        for reply in &self.replies {
+            reply.lines(true, lines);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub drafts: Vec<Draft>,
+    pub imported: usize,
+    /// Comments on unmapped items, or already in the draft.
+    pub skipped: usize,
+}
+
+/// Appends `comments`, keyed by source item, to the drafts of the versions
+/// `mapping` assigns those items to.
+pub fn into_drafts(
+    store: &Store,
+    playlist_id: &str,
+    mapping: &HashMap<String, String>,
+    comments: &[(String, Vec<ReviewComment>)],
+) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    for (item_id, item_comments) in comments {
+        let Some(version_id) = mapping.get(item_id) else {
+            summary.skipped += item_comments.len();
+            continue;
+        };
+        let existing = store
+            .get_draft(playlist_id, version_id)?
+            .filter(|draft| draft.status != "published");
+        let mut content = existing
+            .as_ref()
+            .map(|draft| draft.content.clone())
+            .unwrap_or_default();
+        let mut added = 0;
+        for comment in item_comments {
+            let mut lines = Vec::new();
+            comment.lines(false, &mut lines);
+            let block = lines.join("\n");
+            if content.contains(&block) {
+                summary.skipped += 1;
+                continue;
+            }
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(&block);
+            added += 1;
+        }
+        if added == 0 {
+            continue;
+        }
+        let label_id = existing.and_then(|draft| draft.label_id);
+        summary.drafts.push(store.save_draft(
+            playlist_id,
+            version_id,
+            &content,
+            label_id.as_deref(),
+            None,
+        )?);
+        summary.imported += added;
+    }
+    Ok(summary)
+}
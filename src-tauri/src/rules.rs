@@ -0,0 +1,446 @@
+//! Declarative status rules evaluated after each publish.
+//!
+//! Studios describe rules in `rules.toml` (or `rules.json`) in the config
+//! directory, for example:
+//!
+//! ```toml
+//! [[rules]]
+//! name = "Approved label approves the version"
+//! labelsAny = ["Approved"]
+//! actions = [{ action = "setStatus", target = "version", status = "Approved" }]
+//! ```
+//!
+//! A rule matches when every condition it sets holds for the published note;
+//! a rule without conditions matches every publish. Matching rules only ever
+//! run after ftrack accepted the note, and a failing rule never fails the
+//! publish. With `dryRun` set, rules are evaluated and logged but nothing is
+//! written to ftrack. Applied status changes also go to the audit log.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::Mutex;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::ftrack::{self, Connection, query, quoted};
+use crate::network;
+use crate::paths;
+use crate::store::audit::{self, AuditAction, NewAuditEntry};
+use crate::store::publish_jobs::PublishJob;
+use crate::store::{self, now_millis};
+
+const TOML_FILE: &str = "rules.toml";
+const JSON_FILE: &str = "rules.json";
+pub const RUN_EVENT: &str = "rules:run";
+/// Rule runs kept for the rule log view.
+const HISTORY_LEN: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Target {
+    /// The version the note was published on.
+    Version,
+    /// The task the version belongs to.
+    Task,
+}
+
+impl Target {
+    fn entity_type(self) -> &'static str {
+        match self {
+            Self::Version => "AssetVersion",
+            Self::Task => "Task",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum Action {
+    SetStatus { target: Target, status: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub name: String,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// The note carries at least one of these labels, by name.
+    #[serde(default)]
+    pub labels_any: Vec<String>,
+    /// The note carries all of these labels, by name.
+    #[serde(default)]
+    pub labels_all: Vec<String>,
+    /// Regex the note text must match.
+    #[serde(default)]
+    pub content_matches: Option<String>,
+    /// The version's current status is one of these, by name.
+    #[serde(default)]
+    pub version_status: Vec<String>,
+    pub actions: Vec<Action>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RuleSet {
+    /// Evaluate and log rules without changing anything in ftrack.
+    pub dry_run: bool,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedAction {
+    pub target: Target,
+    pub entity_id: String,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleRun {
+    pub rule: String,
+    pub job_id: String,
+    pub version_id: String,
+    pub actions: Vec<AppliedAction>,
+    pub dry_run: bool,
+    pub error: Option<String>,
+    pub at: i64,
+}
+
+#[derive(Default)]
+pub struct Rules {
+    history: Mutex<VecDeque<RuleRun>>,
+}
+
+/// What the rules can see about a published note.
+struct Published {
+    content: String,
+    labels: Vec<String>,
+    version_status: Option<String>,
+    task_id: Option<String>,
+}
+
+fn load(app: &AppHandle) -> Result<RuleSet> {
+    let dir = paths::config_dir(app)?;
+    let rules = match fs::read_to_string(dir.join(TOML_FILE)) {
+        Ok(contents) => toml::from_str(&contents)
+            .map_err(|err| Error::InvalidInput(format!("Invalid {TOML_FILE}: {err}")))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            paths::read_json(&dir.join(JSON_FILE))?
+        }
+        Err(err) => return Err(err.into()),
+    };
+    validate(&rules)?;
+    Ok(rules)
+}
+
+fn validate(rules: &RuleSet) -> Result<()> {
+    for rule in &rules.rules {
+        if let Some(pattern) = &rule.content_matches {
+            Regex::new(pattern).map_err(|err| {
+                Error::InvalidInput(format!(
+                    "Rule {:?} has an invalid pattern: {err}",
+                    rule.name
+                ))
+            })?;
+        }
+        if rule.actions.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "Rule {:?} has no actions",
+                rule.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn is_create(operation: &Value, entity_type: &str) -> bool {
+    operation["action"] == "create" && operation["entity_type"] == entity_type
+}
+
+async fn published(
+    client: &reqwest::Client,
+    connection: &Connection,
+    job: &PublishJob,
+) -> Result<Published> {
+    let operations = job.operations.as_array().cloned().unwrap_or_default();
+    let content = operations
+        .iter()
+        .find(|op| is_create(op, "Note"))
+        .and_then(|op| op["entity_data"]["content"].as_str())
+        .unwrap_or_default()
+        .to_string();
+    let label_ids: Vec<String> = operations
+        .iter()
+        .filter(|op| is_create(op, "NoteLabelLink"))
+        .filter_map(|op| op["entity_data"]["label_id"].as_str())
+        .map(quoted)
+        .collect();
+
+    let labels = if label_ids.is_empty() {
+        Vec::new()
+    } else {
+        query(
+            client,
+            connection,
+            format!(
+                "select name from NoteLabel where id in ({})",
+                label_ids.join(", ")
+            ),
+        )
+        .await?
+        .iter()
+        .filter_map(|label| label["name"].as_str().map(str::to_string))
+        .collect()
+    };
+    let versions = query(
+        client,
+        connection,
+        format!(
+            "select status.name, task_id from AssetVersion where id is {}",
+            quoted(&job.version_id)
+        ),
+    )
+    .await?;
+    let version = versions.first();
+    Ok(Published {
+        content,
+        labels,
+        version_status: version
+            .and_then(|version| version["status"]["name"].as_str())
+            .map(str::to_string),
+        task_id: version
+            .and_then(|version| version["task_id"].as_str())
+            .map(str::to_string),
+    })
+}
+
+fn matches(rule: &Rule, note: &Published) -> bool {
+    let has = |label: &String| note.labels.iter().any(|l| l.eq_ignore_ascii_case(label));
+    if !rule.labels_any.is_empty() && !rule.labels_any.iter().any(has) {
+        return false;
+    }
+    if !rule.labels_all.iter().all(has) {
+        return false;
+    }
+    if let Some(pattern) = &rule.content_matches {
+        // Patterns are checked when the rules are loaded
+        if !Regex::new(pattern).is_ok_and(|regex| regex.is_match(&note.content)) {
+            return false;
+        }
+    }
+    if !rule.version_status.is_empty() {
+        let Some(current) = &note.version_status else {
+            return false;
+        };
+        if !rule
+            .version_status
+            .iter()
+            .any(|status| status.eq_ignore_ascii_case(current))
+        {
+            return false;
+        }
+    }
+    true
+}
+
+/// Resolves one rule's actions to concrete entities, and applies them
+/// unless `dry_run` is set.
+async fn apply(
+    client: &reqwest::Client,
+    connection: &Connection,
+    job: &PublishJob,
+    note: &Published,
+    rule: &Rule,
+    statuses: &[Value],
+    dry_run: bool,
+) -> Result<Vec<AppliedAction>> {
+    let mut applied = Vec::new();
+    let mut operations = Vec::new();
+    for action in &rule.actions {
+        let Action::SetStatus { target, status } = action;
+        let status_id = statuses
+            .iter()
+            .find(|candidate| {
+                candidate["name"]
+                    .as_str()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(status))
+            })
+            .and_then(|candidate| candidate["id"].as_str())
+            .ok_or_else(|| Error::InvalidInput(format!("ftrack has no status named {status}")))?;
+        let entity_id = match target {
+            Target::Version => job.version_id.clone(),
+            Target::Task => note.task_id.clone().ok_or_else(|| {
+                Error::InvalidInput(format!("Version {} has no task", job.version_id))
+            })?,
+        };
+        operations.push(json!({
+            "action": "update",
+            "entity_type": target.entity_type(),
+            "entity_key": [entity_id],
+            "entity_data": { "status_id": status_id },
+        }));
+        applied.push(AppliedAction {
+            target: *target,
+            entity_id,
+            status: status.clone(),
+        });
+    }
+    if !dry_run {
+        ftrack::call(client, connection, &Value::Array(operations)).await?;
+    }
+    Ok(applied)
+}
+
+/// Evaluates all enabled rules against a published job and returns one run
+/// per matching rule. Nothing is written to ftrack when `dry_run` is set.
+async fn evaluate(
+    client: &reqwest::Client,
+    rules: &RuleSet,
+    job: &PublishJob,
+    dry_run: bool,
+) -> Result<Vec<RuleRun>> {
+    let enabled: Vec<&Rule> = rules.rules.iter().filter(|rule| rule.enabled).collect();
+    if enabled.is_empty() {
+        return Ok(Vec::new());
+    }
+    let connection = Connection {
+        server_url: job.server_url.clone(),
+        api_user: job.api_user.clone(),
+    };
+    let note = published(client, &connection, job).await?;
+    let matched: Vec<&Rule> = enabled
+        .into_iter()
+        .filter(|rule| matches(rule, &note))
+        .collect();
+    if matched.is_empty() {
+        return Ok(Vec::new());
+    }
+    let statuses = query(client, &connection, "select id, name from Status".into()).await?;
+
+    let mut runs = Vec::new();
+    for rule in matched {
+        let result = apply(client, &connection, job, &note, rule, &statuses, dry_run).await;
+        let (actions, error) = match result {
+            Ok(actions) => (actions, None),
+            Err(err) => (Vec::new(), Some(err.to_string())),
+        };
+        runs.push(RuleRun {
+            rule: rule.name.clone(),
+            job_id: job.id.clone(),
+            version_id: job.version_id.clone(),
+            actions,
+            dry_run,
+            error,
+            at: now_millis(),
+        });
+    }
+    Ok(runs)
+}
+
+fn record(app: &AppHandle, job: &PublishJob, run: &RuleRun) {
+    if !run.dry_run {
+        for action in &run.actions {
+            audit::record(
+                app,
+                NewAuditEntry {
+                    action: AuditAction::StatusChange,
+                    connection: Some(Connection {
+                        server_url: job.server_url.clone(),
+                        api_user: job.api_user.clone(),
+                    }),
+                    entity_type: Some(action.target.entity_type().into()),
+                    entity_id: Some(action.entity_id.clone()),
+                    summary: format!("Rule {:?} set status to {}", run.rule, action.status),
+                    details: json!({ "rule": run.rule, "jobId": run.job_id }),
+                },
+            );
+        }
+    }
+    match &run.error {
+        Some(error) => log::warn!("Rule {:?} failed on job {}: {error}", run.rule, run.job_id),
+        None => log::info!(
+            "Rule {:?} {} {} action(s) on version {}",
+            run.rule,
+            if run.dry_run {
+                "would apply"
+            } else {
+                "applied"
+            },
+            run.actions.len(),
+            run.version_id
+        ),
+    }
+    {
+        let rules = app.state::<Rules>();
+        let mut history = rules
+            .history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if history.len() == HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(run.clone());
+    }
+    if let Err(err) = app.emit(RUN_EVENT, run) {
+        log::warn!("Failed to emit rule run: {err}");
+    }
+}
+
+/// Evaluates the rules for a job that just published, in the background.
+pub fn notify(app: &AppHandle, job: &PublishJob) {
+    let app = app.clone();
+    let job = job.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = match load(&app) {
+            Ok(rules) => evaluate(&network::client(), &rules, &job, rules.dry_run).await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(runs) => runs.iter().for_each(|run| record(&app, &job, run)),
+            Err(err) => log::warn!("Failed to evaluate rules for job {}: {err}", job.id),
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_rules(app: AppHandle) -> Result<RuleSet> {
+    load(&app)
+}
+
+/// Evaluates the rules against a past publish job without changing anything
+/// in ftrack, and records the result like any other run.
+#[tauri::command]
+pub async fn dry_run_rules(app: AppHandle, job_id: String) -> Result<Vec<RuleRun>> {
+    let job = store::state(&app)?
+        .get_publish_job(&job_id)?
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown publish job {job_id}")))?;
+    let rules = load(&app)?;
+    let runs = evaluate(&network::client(), &rules, &job, true).await?;
+    for run in &runs {
+        record(&app, &job, run);
+    }
+    Ok(runs)
+}
+
+/// Recent rule runs, newest first.
+#[tauri::command]
+pub fn list_rule_runs(rules: State<'_, Rules>) -> Vec<RuleRun> {
+    rules
+        .history
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .rev()
+        .cloned()
+        .collect()
+}
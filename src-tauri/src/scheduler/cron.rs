@@ -0,0 +1,153 @@
+//! Five-field cron expressions: minute, hour, day of month, month, weekday.
+//!
+//! Fields take `*`, values, ranges and steps, as in `*/15`, `1-5` or
+//! `0,30`. Weekdays run from 0 (Sunday) to 7 (Sunday again). As in classic
+//! cron, when both day fields are restricted a day matching either one runs.
+//! `@hourly`, `@daily` and `@weekly` are accepted as shorthands.
+
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, Local, NaiveDateTime, TimeZone, Timelike};
+
+use crate::error::Error;
+
+/// Far enough ahead to find every valid expression, including 29 February.
+const SEARCH_DAYS: i64 = 4 * 366;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step in {part}"))?;
+                if step == 0 {
+                    return Err(format!("zero step in {part}"));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            let start = start
+                .parse()
+                .map_err(|_| format!("invalid value in {part}"))?;
+            let end = end
+                .parse()
+                .map_err(|_| format!("invalid value in {part}"))?;
+            (start, end)
+        } else {
+            let value = range.parse().map_err(|_| format!("invalid value {part}"))?;
+            // `5/10` means from 5 to the end in steps of 10
+            (value, if step > 1 { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("{part} is outside {min}-{max}"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl FromStr for Cron {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self, Error> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            other => other,
+        };
+        let invalid = |reason: String| {
+            Error::InvalidInput(format!("Invalid schedule {expression}: {reason}"))
+        };
+        let fields: Vec<_> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid("expected five fields".into()));
+        };
+        let weekdays = parse_field(weekday, 0, 7).map_err(invalid)?;
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59).map_err(invalid)?,
+            hours: parse_field(hour, 0, 23).map_err(invalid)? as u32,
+            days: parse_field(day, 1, 31).map_err(invalid)? as u32,
+            months: parse_field(month, 1, 12).map_err(invalid)? as u16,
+            // Fold 7 onto Sunday
+            weekdays: ((weekdays | (weekdays >> 7)) & 0x7f) as u8,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+}
+
+impl Cron {
+    fn day_matches(&self, time: &NaiveDateTime) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().num_days_from_sunday()) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        }
+    }
+
+    /// The first matching minute strictly after `after`, in milliseconds
+    /// since the epoch and local time.
+    pub fn next_after(&self, after: i64) -> Option<i64> {
+        let after = Local.timestamp_millis_opt(after).single()?.naive_local();
+        let limit = after + Duration::days(SEARCH_DAYS);
+        let mut time = (after + Duration::minutes(1))
+            .with_second(0)?
+            .with_nanosecond(0)?;
+        while time <= limit {
+            if self.months & (1 << time.month()) == 0 {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = time
+                    .date()
+                    .with_day(1)?
+                    .with_month(month)?
+                    .with_year(year)?
+                    .and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.day_matches(&time) {
+                time = (time.date() + Duration::days(1)).and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+                continue;
+            }
+            // Times skipped by a daylight saving change do not exist locally
+            if let Some(local) = Local.from_local_datetime(&time).earliest() {
+                return Some(local.timestamp_millis());
+            }
+            time += Duration::minutes(1);
+        }
+        None
+    }
+}
@@ -0,0 +1,386 @@
+//! Scheduled background work: playlist refresh, thumbnail prefetch and
+//! cache maintenance.
+//!
+//! Each task runs on a cron expression from `schedule.json`. Tasks that
+//! contact the server wait out quiet hours and, when set to, battery power
+//! and metered connections; a run held back that way happens as soon as it
+//! is allowed. Runs missed while the app was closed happen once at the next
+//! launch. The frontend owns playlist state, so refresh and prefetch are
+//! requested from it with `scheduler:run`; cache maintenance runs here.
+
+mod cron;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use crate::error::{Error, Result};
+use crate::ftrack::cache::ApiCache;
+use crate::paths;
+use crate::power::conditions;
+use crate::store::now_millis;
+use crate::thumbnail_cache::ThumbnailCache;
+use cron::Cron;
+
+const SETTINGS_FILE: &str = "schedule.json";
+/// Last run of each task, kept in the data directory.
+const STATE_FILE: &str = "schedule-state.json";
+pub const RUN_EVENT: &str = "scheduler:run";
+const TICK: Duration = Duration::from_secs(30);
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduledTask {
+    PlaylistRefresh,
+    ThumbnailPrefetch,
+    CacheMaintenance,
+}
+
+impl ScheduledTask {
+    const ALL: [Self; 3] = [
+        Self::PlaylistRefresh,
+        Self::ThumbnailPrefetch,
+        Self::CacheMaintenance,
+    ];
+
+    /// Whether the task contacts the server, and so is subject to holds.
+    fn uses_network(self) -> bool {
+        !matches!(self, Self::CacheMaintenance)
+    }
+
+    fn default_schedule(self) -> TaskSchedule {
+        let cron = match self {
+            Self::PlaylistRefresh => "0 9 * * *",
+            Self::ThumbnailPrefetch => "15 9 * * *",
+            Self::CacheMaintenance => "0 3 * * *",
+        };
+        TaskSchedule {
+            enabled: true,
+            cron: cron.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskSchedule {
+    pub enabled: bool,
+    pub cron: String,
+}
+
+/// Local `HH:MM` times; a window may span midnight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+impl QuietHours {
+    fn parse(&self) -> Result<(NaiveTime, NaiveTime)> {
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|_| Error::InvalidInput(format!("Quiet hours time {time} is not HH:MM")))
+        };
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    fn contains(&self, now: NaiveTime) -> bool {
+        let Ok((start, end)) = self.parse() else {
+            return false;
+        };
+        if start <= end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncSchedule {
+    pub enabled: bool,
+    pub tasks: BTreeMap<ScheduledTask, TaskSchedule>,
+    pub quiet_hours: Option<QuietHours>,
+    pub pause_on_battery: bool,
+    pub pause_on_metered: bool,
+}
+
+impl Default for SyncSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tasks: ScheduledTask::ALL
+                .into_iter()
+                .map(|task| (task, task.default_schedule()))
+                .collect(),
+            quiet_hours: None,
+            pause_on_battery: true,
+            pause_on_metered: true,
+        }
+    }
+}
+
+/// Why a due task has not run yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Hold {
+    QuietHours,
+    Battery,
+    Metered,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatus {
+    pub task: ScheduledTask,
+    pub enabled: bool,
+    pub last_run_at: Option<i64>,
+    pub next_run_at: Option<i64>,
+    pub held_by: Option<Hold>,
+}
+
+#[derive(Default)]
+pub struct Scheduler {
+    wake: Notify,
+    last_runs: Mutex<HashMap<ScheduledTask, i64>>,
+    holds: Mutex<HashMap<ScheduledTask, Hold>>,
+}
+
+fn state_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(paths::data_dir(app)?.join(STATE_FILE))
+}
+
+fn load_settings(app: &AppHandle) -> Result<SyncSchedule> {
+    let mut schedule: SyncSchedule = paths::load_json(app, SETTINGS_FILE)?;
+    for task in ScheduledTask::ALL {
+        schedule
+            .tasks
+            .entry(task)
+            .or_insert_with(|| task.default_schedule());
+    }
+    Ok(schedule)
+}
+
+fn save_settings(app: &AppHandle, schedule: &SyncSchedule) -> Result<()> {
+    paths::save_json(app, SETTINGS_FILE, schedule)
+}
+
+impl Scheduler {
+    fn load(app: &AppHandle) -> Result<Self> {
+        let last_runs = paths::read_json(&state_path(app)?).unwrap_or_else(|err| {
+            log::warn!("Ignoring unreadable schedule state: {err}");
+            HashMap::new()
+        });
+        Ok(Self {
+            last_runs: Mutex::new(last_runs),
+            ..Default::default()
+        })
+    }
+
+    fn last_run(&self, task: ScheduledTask) -> Option<i64> {
+        self.last_runs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&task)
+            .copied()
+    }
+
+    fn record_run(&self, app: &AppHandle, task: ScheduledTask, at: i64) -> Result<()> {
+        let last_runs = {
+            let mut last_runs = self
+                .last_runs
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            last_runs.insert(task, at);
+            last_runs.clone()
+        };
+        paths::write_json(&state_path(app)?, &last_runs)
+    }
+
+    fn set_hold(&self, task: ScheduledTask, hold: Option<Hold>) {
+        let mut holds = self
+            .holds
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match hold {
+            Some(hold) => holds.insert(task, hold),
+            None => holds.remove(&task),
+        };
+    }
+}
+
+async fn current_hold(schedule: &SyncSchedule) -> Option<Hold> {
+    if schedule
+        .quiet_hours
+        .as_ref()
+        .is_some_and(|quiet| quiet.contains(Local::now().time()))
+    {
+        return Some(Hold::QuietHours);
+    }
+    if schedule.pause_on_battery && conditions::on_battery().await == Some(true) {
+        return Some(Hold::Battery);
+    }
+    if schedule.pause_on_metered && conditions::is_metered().await == Some(true) {
+        return Some(Hold::Metered);
+    }
+    None
+}
+
+fn maintain_caches(app: &AppHandle) -> Result<String> {
+    let thumbnails = app.state::<ThumbnailCache>();
+    let evicted = thumbnails.evict(thumbnails.max_bytes())?;
+    let pruned = app.state::<ApiCache>().prune();
+    Ok(format!(
+        "Evicted {} thumbnails ({} MiB) and {pruned} stale API cache entries",
+        evicted.evicted,
+        evicted.freed_bytes / MIB
+    ))
+}
+
+fn run_task(app: &AppHandle, task: ScheduledTask, now: i64) {
+    let detail = match task {
+        ScheduledTask::CacheMaintenance => match maintain_caches(app) {
+            Ok(detail) => Some(detail),
+            Err(err) => {
+                log::error!("Scheduled cache maintenance failed: {err}");
+                Some(err.to_string())
+            }
+        },
+        ScheduledTask::PlaylistRefresh | ScheduledTask::ThumbnailPrefetch => None,
+    };
+    log::info!("Running scheduled {task:?}");
+    let payload = json!({ "task": task, "ranAt": now, "detail": detail });
+    if let Err(err) = app.emit(RUN_EVENT, payload) {
+        log::warn!("Failed to emit scheduled run: {err}");
+    }
+}
+
+async fn tick(app: &AppHandle) -> Result<()> {
+    let schedule = load_settings(app)?;
+    if !schedule.enabled {
+        return Ok(());
+    }
+    let scheduler = app.state::<Scheduler>();
+    let now = now_millis();
+    // Checked at most once per tick, and only when a network task is due
+    let mut hold = None;
+    for (&task, config) in &schedule.tasks {
+        if !config.enabled {
+            scheduler.set_hold(task, None);
+            continue;
+        }
+        let cron = match config.cron.parse::<Cron>() {
+            Ok(cron) => cron,
+            Err(err) => {
+                log::warn!("Skipping scheduled {task:?}: {err}");
+                continue;
+            }
+        };
+        // A task seen for the first time is scheduled from now, not from 1970
+        let Some(last_run) = scheduler.last_run(task) else {
+            scheduler.record_run(app, task, now)?;
+            continue;
+        };
+        if cron.next_after(last_run).is_none_or(|due| due > now) {
+            continue;
+        }
+        if task.uses_network() {
+            let current = match hold {
+                Some(current) => current,
+                None => *hold.insert(current_hold(&schedule).await),
+            };
+            scheduler.set_hold(task, current);
+            if current.is_some() {
+                continue;
+            }
+        }
+        run_task(app, task, now);
+        scheduler.record_run(app, task, now)?;
+    }
+    Ok(())
+}
+
+pub fn init(app: &AppHandle) -> Result<()> {
+    app.manage(Scheduler::load(app)?);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(err) = tick(&app).await {
+                log::error!("Scheduler tick failed: {err}");
+            }
+            let scheduler = app.state::<Scheduler>();
+            tokio::select! {
+                _ = scheduler.wake.notified() => {}
+                _ = tokio::time::sleep(TICK) => {}
+            }
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_sync_schedule(app: AppHandle) -> Result<SyncSchedule> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_sync_schedule(
+    app: AppHandle,
+    scheduler: State<'_, Scheduler>,
+    config: SyncSchedule,
+) -> Result<()> {
+    for task in config.tasks.values() {
+        task.cron.parse::<Cron>()?;
+    }
+    if let Some(quiet) = &config.quiet_hours {
+        quiet.parse()?;
+    }
+    save_settings(&app, &config)?;
+    scheduler.wake.notify_one();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_schedule_status(
+    app: AppHandle,
+    scheduler: State<'_, Scheduler>,
+) -> Result<Vec<TaskStatus>> {
+    let schedule = load_settings(&app)?;
+    let now = now_millis();
+    let holds = scheduler
+        .holds
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    Ok(schedule
+        .tasks
+        .iter()
+        .map(|(&task, config)| {
+            let enabled = schedule.enabled && config.enabled;
+            let last_run_at = scheduler.last_run(task);
+            let next_run_at = config
+                .cron
+                .parse::<Cron>()
+                .ok()
+                .filter(|_| enabled)
+                .and_then(|cron| cron.next_after(last_run_at.unwrap_or(now)));
+            TaskStatus {
+                task,
+                enabled,
+                last_run_at,
+                next_run_at,
+                held_by: holds.get(&task).copied(),
+            }
+        })
+        .collect())
+}
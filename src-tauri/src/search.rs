@@ -0,0 +1,291 @@
+//! Local full-text index of cached notes.
+//!
+//! The frontend pushes notes in batches as it loads them, and queries go
+//! through tantivy so search stays instant across thousands of notes.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, TermQuery};
+use tantivy::schema::{
+    Field, IndexRecordOption, STORED, STRING, Schema, TEXT, TantivyDocument, Value,
+};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, Term};
+use tauri::State;
+
+use crate::error::Result;
+use crate::store::Store;
+
+const WRITER_HEAP_BYTES: usize = 50 * 1024 * 1024;
+const DEFAULT_LIMIT: usize = 50;
+
+/// A note as pushed by the frontend for indexing.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedNote {
+    pub note_id: String,
+    #[serde(default)]
+    pub playlist_id: Option<String>,
+    #[serde(default)]
+    pub version_id: Option<String>,
+    pub version_name: String,
+    pub content: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchFilters {
+    pub playlist_id: Option<String>,
+    pub version_id: Option<String>,
+    pub label: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub note_id: String,
+    pub playlist_id: Option<String>,
+    pub version_id: Option<String>,
+    pub version_name: String,
+    pub content: String,
+    pub author: Option<String>,
+    pub labels: Vec<String>,
+    pub created_at: Option<String>,
+    pub score: f32,
+    /// Content excerpt with matches wrapped in `<b>` tags.
+    pub snippet: String,
+}
+
+#[derive(Clone, Copy)]
+struct Fields {
+    note_id: Field,
+    playlist_id: Field,
+    version_id: Field,
+    version_name: Field,
+    content: Field,
+    author: Field,
+    labels: Field,
+    label_key: Field,
+    created_at: Field,
+}
+
+impl Fields {
+    fn schema() -> (Schema, Self) {
+        let mut builder = Schema::builder();
+        let fields = Self {
+            note_id: builder.add_text_field("note_id", STRING | STORED),
+            playlist_id: builder.add_text_field("playlist_id", STRING | STORED),
+            version_id: builder.add_text_field("version_id", STRING | STORED),
+            version_name: builder.add_text_field("version_name", TEXT | STORED),
+            content: builder.add_text_field("content", TEXT | STORED),
+            author: builder.add_text_field("author", TEXT | STORED),
+            labels: builder.add_text_field("labels", TEXT | STORED),
+            // Untokenized copy of each label for exact filtering
+            label_key: builder.add_text_field("label_key", STRING),
+            created_at: builder.add_text_field("created_at", STORED),
+        };
+        (builder.build(), fields)
+    }
+}
+
+/// Shared handle to the on-disk index. Cheap to clone into blocking tasks.
+#[derive(Clone)]
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Arc<Mutex<IndexWriter>>,
+    fields: Fields,
+}
+
+impl SearchIndex {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let (schema, fields) = Fields::schema();
+        let index = Index::open_or_create(
+            MmapDirectory::open(dir).map_err(tantivy::TantivyError::from)?,
+            schema,
+        )?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .try_into()?;
+        let writer = index.writer(WRITER_HEAP_BYTES)?;
+        Ok(Self {
+            index,
+            reader,
+            writer: Arc::new(Mutex::new(writer)),
+            fields,
+        })
+    }
+
+    /// Adds or replaces notes by id and commits. Returns the number indexed.
+    pub fn index_notes(&self, notes: &[IndexedNote]) -> Result<usize> {
+        let f = self.fields;
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for note in notes {
+            writer.delete_term(Term::from_field_text(f.note_id, &note.note_id));
+
+            let mut doc = TantivyDocument::new();
+            doc.add_text(f.note_id, &note.note_id);
+            doc.add_text(f.version_name, &note.version_name);
+            doc.add_text(f.content, &note.content);
+            if let Some(playlist_id) = &note.playlist_id {
+                doc.add_text(f.playlist_id, playlist_id);
+            }
+            if let Some(version_id) = &note.version_id {
+                doc.add_text(f.version_id, version_id);
+            }
+            if let Some(author) = &note.author {
+                doc.add_text(f.author, author);
+            }
+            if let Some(created_at) = &note.created_at {
+                doc.add_text(f.created_at, created_at);
+            }
+            for label in &note.labels {
+                doc.add_text(f.labels, label);
+                doc.add_text(f.label_key, label);
+            }
+            writer.add_document(doc)?;
+        }
+        writer.commit()?;
+        // Make the batch visible to the next search instead of waiting for the reload delay
+        self.reader.reload()?;
+        Ok(notes.len())
+    }
+
+    /// Drops every note of the given playlists and commits.
+    pub fn remove_playlists(&self, playlist_ids: &[String]) -> Result<()> {
+        if playlist_ids.is_empty() {
+            return Ok(());
+        }
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for playlist_id in playlist_ids {
+            writer.delete_term(Term::from_field_text(self.fields.playlist_id, playlist_id));
+        }
+        writer.commit()?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    pub fn search(&self, query: &str, filters: &SearchFilters) -> Result<Vec<SearchHit>> {
+        let f = self.fields;
+        let searcher = self.reader.searcher();
+
+        let mut parser = QueryParser::for_index(
+            &self.index,
+            vec![f.content, f.version_name, f.author, f.labels],
+        );
+        parser.set_field_boost(f.version_name, 2.0);
+        // Tolerate single-character typos on the free-text fields; quoted
+        // phrases are still matched exactly by the parser
+        for field in [f.content, f.version_name, f.author] {
+            parser.set_field_fuzzy(field, true, 1, true);
+        }
+        let text_query: Box<dyn Query> = if query.trim().is_empty() {
+            Box::new(AllQuery)
+        } else {
+            parser.parse_query_lenient(query).0
+        };
+
+        let mut clauses = vec![(Occur::Must, text_query.box_clone())];
+        let filter_terms = [
+            (f.playlist_id, &filters.playlist_id),
+            (f.version_id, &filters.version_id),
+            (f.label_key, &filters.label),
+        ];
+        for (field, value) in filter_terms {
+            if let Some(value) = value {
+                clauses.push((
+                    Occur::Must,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(field, value),
+                        IndexRecordOption::Basic,
+                    )),
+                ));
+            }
+        }
+        let combined = BooleanQuery::new(clauses);
+
+        let limit = filters.limit.unwrap_or(DEFAULT_LIMIT).max(1);
+        let top_docs = searcher.search(&combined, &TopDocs::with_limit(limit))?;
+        let snippets = SnippetGenerator::create(&searcher, &*text_query, f.content)?;
+
+        let first = |doc: &TantivyDocument, field| {
+            doc.get_first(field)
+                .and_then(|value| value.as_str())
+                .map(str::to_string)
+        };
+        top_docs
+            .into_iter()
+            .map(|(score, address)| {
+                let doc: TantivyDocument = searcher.doc(address)?;
+                Ok(SearchHit {
+                    note_id: first(&doc, f.note_id).unwrap_or_default(),
+                    playlist_id: first(&doc, f.playlist_id),
+                    version_id: first(&doc, f.version_id),
+                    version_name: first(&doc, f.version_name).unwrap_or_default(),
+                    content: first(&doc, f.content).unwrap_or_default(),
+                    author: first(&doc, f.author),
+                    labels: doc
+                        .get_all(f.labels)
+                        .filter_map(|value| value.as_str().map(str::to_string))
+                        .collect(),
+                    created_at: first(&doc, f.created_at),
+                    score,
+                    snippet: snippets.snippet_from_doc(&doc).to_html(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Notes of vaulted projects are skipped, as the index stores content in
+/// plain text.
+#[tauri::command]
+pub async fn index_notes(
+    index: State<'_, SearchIndex>,
+    store: State<'_, Store>,
+    batch: Vec<IndexedNote>,
+) -> Result<usize> {
+    let mut notes = Vec::with_capacity(batch.len());
+    for note in batch {
+        let vaulted = match &note.playlist_id {
+            Some(playlist_id) => store.vault_project(playlist_id)?.is_some(),
+            None => false,
+        };
+        if !vaulted {
+            notes.push(note);
+        }
+    }
+    let index = index.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || index.index_notes(&notes)).await?
+}
+
+#[tauri::command]
+pub async fn search_notes(
+    index: State<'_, SearchIndex>,
+    query: String,
+    filters: Option<SearchFilters>,
+) -> Result<Vec<SearchHit>> {
+    let index = index.inner().clone();
+    let filters = filters.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || index.search(&query, &filters)).await?
+}
@@ -0,0 +1,322 @@
+//! Shareable review sessions in `.astranotes` files.
+//!
+//! A session is zstd-compressed JSON holding what the frontend hands over
+//! (open playlists, selection, labels and its own preferences) along with
+//! the note drafts for those playlists and a fixed set of portable settings
+//! files. Connection, credential and machine-specific settings are never
+//! included. Files carry a format version and are migrated step by step when
+//! read, so sessions written by older releases keep opening.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, State};
+
+use crate::error::{Error, Result};
+use crate::export;
+use crate::paths;
+use crate::settings;
+use crate::store::Store;
+use crate::store::drafts::Draft;
+
+const FORMAT: &str = "astranotes-session";
+const VERSION: u32 = 1;
+const EXTENSION: &str = "astranotes";
+const COMPRESSION_LEVEL: i32 = 9;
+/// Decompressed sessions larger than this are refused.
+const MAX_SESSION_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Settings that mean the same on another machine.
+const PORTABLE_SETTINGS: &[&str] = &[
+    "shortcuts.json",
+    "spellcheck.json",
+    "notifications.json",
+    "pathmap.json",
+    "players.json",
+    "color.json",
+    "ingest.json",
+];
+
+/// Upgrades a session from the version at its index to the next one.
+const MIGRATIONS: &[fn(&mut Value)] = &[];
+
+/// What the frontend owns; kept as it sent it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionState {
+    pub playlists: Vec<Value>,
+    pub selection: Value,
+    pub labels: Vec<Value>,
+    pub preferences: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionDraft {
+    playlist_id: String,
+    version_id: String,
+    content: String,
+    label_id: Option<String>,
+    updated_at: i64,
+}
+
+impl From<Draft> for SessionDraft {
+    fn from(draft: Draft) -> Self {
+        Self {
+            playlist_id: draft.playlist_id,
+            version_id: draft.version_id,
+            content: draft.content,
+            label_id: draft.label_id,
+            updated_at: draft.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionFile {
+    format: String,
+    version: u32,
+    created_at: i64,
+    app_version: String,
+    state: SessionState,
+    drafts: Vec<SessionDraft>,
+    settings: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    pub version: u32,
+    pub created_at: i64,
+    pub app_version: String,
+    pub playlists: usize,
+    pub drafts: usize,
+    pub settings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedSession {
+    pub summary: SessionSummary,
+    pub state: SessionState,
+    pub drafts_imported: usize,
+    /// Drafts left alone because the local copy is newer.
+    pub drafts_skipped: usize,
+    pub settings_applied: Vec<String>,
+    /// Settings are read at startup, so applied ones need a restart.
+    pub restart_required: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImportOptions {
+    /// Replace local settings with the session's.
+    pub apply_settings: bool,
+    /// Replace local drafts even when they are newer.
+    pub overwrite_drafts: bool,
+}
+
+impl SessionFile {
+    fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            version: self.version,
+            created_at: self.created_at,
+            app_version: self.app_version.clone(),
+            playlists: self.state.playlists.len(),
+            drafts: self.drafts.len(),
+            settings: self.settings.keys().cloned().collect(),
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<SessionFile> {
+    let decoder = zstd::Decoder::new(fs::File::open(path)?)
+        .map_err(|err| Error::InvalidInput(format!("Not a session file: {err}")))?;
+    let mut json = Vec::new();
+    decoder
+        .take(MAX_SESSION_BYTES + 1)
+        .read_to_end(&mut json)
+        .map_err(|err| Error::InvalidInput(format!("Corrupt session file: {err}")))?;
+    if json.len() as u64 > MAX_SESSION_BYTES {
+        return Err(Error::InvalidInput("Session file is too large".into()));
+    }
+    let mut value: Value = serde_json::from_slice(&json)?;
+    if value["format"] != FORMAT {
+        return Err(Error::InvalidInput("Not an AstraNotes session".into()));
+    }
+    let version = value["version"]
+        .as_u64()
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| Error::InvalidInput("Session file has no version".into()))?;
+    if version == 0 || version > VERSION {
+        return Err(Error::InvalidInput(format!(
+            "Session version {version} needs a newer AstraNotes"
+        )));
+    }
+    for migrate in &MIGRATIONS[version as usize - 1..] {
+        migrate(&mut value);
+    }
+    value["version"] = VERSION.into();
+    Ok(serde_json::from_value(value)?)
+}
+
+fn write(path: &Path, session: &SessionFile) -> Result<()> {
+    let json = serde_json::to_vec(session)?;
+    export::write_atomically(path, |temp| {
+        let mut encoder = zstd::Encoder::new(fs::File::create(temp)?, COMPRESSION_LEVEL)?;
+        encoder.write_all(&json)?;
+        encoder.finish()?.sync_all()?;
+        Ok(())
+    })
+}
+
+/// Header details of a session file, for showing before importing.
+pub(crate) fn read_summary(path: &Path) -> Result<SessionSummary> {
+    Ok(read(path)?.summary())
+}
+
+/// IDs of the playlists in the frontend's state.
+fn playlist_ids(state: &SessionState) -> HashSet<String> {
+    state
+        .playlists
+        .iter()
+        .filter_map(|playlist| match playlist {
+            Value::String(id) => Some(id.clone()),
+            playlist => playlist["id"].as_str().map(str::to_string),
+        })
+        .collect()
+}
+
+fn collect_settings(app: &AppHandle) -> Result<BTreeMap<String, Value>> {
+    let dir = paths::config_dir(app)?;
+    let mut settings = BTreeMap::new();
+    for name in PORTABLE_SETTINGS {
+        match fs::read_to_string(dir.join(name)) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(value) => {
+                    settings.insert(name.to_string(), value);
+                }
+                Err(err) => log::warn!("Leaving invalid {name} out of the session: {err}"),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(settings)
+}
+
+fn apply_settings(app: &AppHandle, settings: &BTreeMap<String, Value>) -> Result<Vec<String>> {
+    let dir = paths::config_dir(app)?;
+    let mut applied = Vec::new();
+    for (name, value) in settings {
+        // Never let a session write anything outside the portable set
+        if !PORTABLE_SETTINGS.contains(&name.as_str()) {
+            log::warn!("Ignoring {name} in session");
+            continue;
+        }
+        paths::write_json(&dir.join(name), value)?;
+        applied.push(name.clone());
+    }
+    let restored = settings::restore_locked(app)?;
+    if !restored.is_empty() {
+        log::info!(
+            "Restored managed settings over the session: {}",
+            restored.join(", ")
+        );
+    }
+    Ok(applied)
+}
+
+/// Writes a session file. Returns the written path, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_session(
+    app: AppHandle,
+    store: State<'_, Store>,
+    state: SessionState,
+    path: Option<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    let Some(path) =
+        export::resolve_path(&app, path, "session", "AstraNotes session", EXTENSION).await
+    else {
+        return Ok(None);
+    };
+    let playlists = playlist_ids(&state);
+    let drafts = store
+        .list_drafts(None)?
+        .into_iter()
+        .filter(|draft| {
+            draft.status != "empty" && !draft.locked && playlists.contains(&draft.playlist_id)
+        })
+        .map(SessionDraft::from)
+        .collect();
+    let session = SessionFile {
+        format: FORMAT.into(),
+        version: VERSION,
+        created_at: crate::store::now_millis(),
+        app_version: app.package_info().version.to_string(),
+        state,
+        drafts,
+        settings: collect_settings(&app)?,
+    };
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || write(&target, &session)).await??;
+    log::info!("Exported session to {}", path.display());
+    Ok(Some(path))
+}
+
+/// Reads a session file, restores its drafts and optionally its settings,
+/// and returns the frontend's part for it to restore.
+#[tauri::command]
+pub async fn import_session(
+    app: AppHandle,
+    store: State<'_, Store>,
+    path: PathBuf,
+    options: Option<ImportOptions>,
+) -> Result<ImportedSession> {
+    let options = options.unwrap_or_default();
+    let session = tauri::async_runtime::spawn_blocking(move || read(&path)).await??;
+
+    let mut drafts_imported = 0;
+    let mut drafts_skipped = 0;
+    for draft in &session.drafts {
+        let local = store.get_draft(&draft.playlist_id, &draft.version_id)?;
+        let newer_locally = local.is_some_and(|local| {
+            local.updated_at > draft.updated_at && local.content != draft.content
+        });
+        if newer_locally && !options.overwrite_drafts {
+            drafts_skipped += 1;
+            continue;
+        }
+        store.save_draft(
+            &draft.playlist_id,
+            &draft.version_id,
+            &draft.content,
+            draft.label_id.as_deref(),
+            None,
+        )?;
+        drafts_imported += 1;
+    }
+    if drafts_imported > 0 {
+        crate::tray::refresh(&app);
+        crate::badge::refresh(&app);
+    }
+
+    let settings_applied = if options.apply_settings {
+        apply_settings(&app, &session.settings)?
+    } else {
+        Vec::new()
+    };
+    Ok(ImportedSession {
+        summary: session.summary(),
+        restart_required: !settings_applied.is_empty(),
+        state: session.state,
+        drafts_imported,
+        drafts_skipped,
+        settings_applied,
+    })
+}
@@ -0,0 +1,408 @@
+//! Moving settings between machines and enforcing admin-managed values.
+//!
+//! `export_settings` bundles the portable settings files in the config
+//! directory into one JSON document signed with an Ed25519 key kept in the
+//! OS keychain; `import_settings` checks the signature before writing
+//! anything back, and only accepts files signed by this installation or by a
+//! key the administrator trusts. Settings that run programs, load plugins or
+//! decide where data and updates come from are never moved this way. Secrets
+//! live in the keychain and are never part of an export.
+//!
+//! IT can place a `managed.json` at a system-wide path. Its `defaults` fill
+//! in keys the user has not set, while its `locked` values always win and
+//! are written back over user changes at every launch and on import, and
+//! saving a different value for them is refused. Only settings files that
+//! hold a JSON object can be managed. It can also preset the server URL shown at sign-in and list other machines'
+//! keys in `trustedSigners` so their exports can be imported.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use ring::rand::SystemRandom;
+use ring::signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use tauri::{AppHandle, Manager, State};
+
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::export;
+use crate::paths;
+use crate::providers::ProviderKind;
+
+const FORMAT: &str = "astranotes-settings";
+const VERSION: u32 = 1;
+const SIGNING_KEY: &str = "settings-signing-key";
+/// Window geometry is specific to this machine's displays.
+const EXCLUDED: &[&str] = &["window-state.json"];
+/// Preferences safe to take from another machine. Hooks, players, plugins,
+/// schedules, webhooks, network, sign-in, tracing, telemetry and update
+/// settings stay out, since they run programs or pick where data goes.
+const PORTABLE_SETTINGS: &[&str] = &[
+    "cache.json",
+    "color.json",
+    "idle.json",
+    "ingest.json",
+    "locale.json",
+    "menu.json",
+    "notifications.json",
+    "pathmap.json",
+    "power.json",
+    "qc.json",
+    "resources.json",
+    "shortcuts.json",
+    "spellcheck.json",
+    "transfers.json",
+    "watermarks.json",
+];
+/// Settings files holding a list rather than an object, which keys cannot
+/// be merged into.
+const LIST_SETTINGS: &[&str] = &["pathmap.json"];
+
+static MANAGED: OnceLock<ManagedSettings> = OnceLock::new();
+
+#[cfg(target_os = "linux")]
+fn managed_path() -> Option<PathBuf> {
+    Some(PathBuf::from("/etc/astranotes/managed.json"))
+}
+
+#[cfg(target_os = "macos")]
+fn managed_path() -> Option<PathBuf> {
+    Some(PathBuf::from(
+        "/Library/Application Support/AstraNotes/managed.json",
+    ))
+}
+
+#[cfg(target_os = "windows")]
+fn managed_path() -> Option<PathBuf> {
+    std::env::var_os("ProgramData")
+        .map(|dir| PathBuf::from(dir).join("AstraNotes").join("managed.json"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn managed_path() -> Option<PathBuf> {
+    None
+}
+
+/// Settings files keyed by name, e.g. `telemetry.json`, each holding the
+/// keys to set in it.
+type SettingsFiles = BTreeMap<String, Map<String, Value>>;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ManagedSettings {
+    pub defaults: SettingsFiles,
+    pub locked: SettingsFiles,
+    pub server_url: Option<String>,
+    pub provider: Option<ProviderKind>,
+    /// Base64 Ed25519 public keys whose exports may be imported, besides
+    /// this installation's own.
+    pub trusted_signers: Vec<String>,
+    /// Where these were read from, when a managed file exists.
+    #[serde(skip_deserializing)]
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedSettings {
+    format: String,
+    version: u32,
+    created_at: i64,
+    app_version: String,
+    settings: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsImport {
+    pub applied: Vec<String>,
+    /// `file#key` entries kept at their managed value.
+    pub locked: Vec<String>,
+    /// Base64 public key the file was signed with.
+    pub signer: String,
+    /// Whether this installation signed it.
+    pub own_signature: bool,
+    /// Settings are read at startup, so imported ones need a restart.
+    pub restart_required: bool,
+}
+
+fn load_managed() -> ManagedSettings {
+    let Some(path) = managed_path() else {
+        return ManagedSettings::default();
+    };
+    match fs::read_to_string(&path) {
+        Ok(contents) => match serde_json::from_str::<ManagedSettings>(&contents) {
+            Ok(mut managed) => {
+                eprintln!("Using managed settings from {}", path.display());
+                managed.source = Some(path);
+                managed
+            }
+            Err(err) => {
+                eprintln!(
+                    "Ignoring invalid managed settings {}: {err}",
+                    path.display()
+                );
+                ManagedSettings::default()
+            }
+        },
+        Err(_) => ManagedSettings::default(),
+    }
+}
+
+/// Settings files are plain names; anything else could escape the directory.
+fn valid_name(name: &str) -> bool {
+    name.ends_with(".json")
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && !EXCLUDED.contains(&name)
+}
+
+/// Whether keys of `name` can be managed.
+fn manageable(name: &str) -> bool {
+    valid_name(name) && !LIST_SETTINGS.contains(&name)
+}
+
+fn portable(name: &str) -> bool {
+    PORTABLE_SETTINGS.contains(&name)
+}
+
+fn read_file(path: &Path) -> Result<Value> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(json!({})),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Sets every key of `values` that `object` lacks or, with `force`, every key.
+/// Returns the keys whose value changed.
+fn merge(object: &mut Map<String, Value>, values: &Map<String, Value>, force: bool) -> Vec<String> {
+    let mut changed = Vec::new();
+    for (key, value) in values {
+        let current = object.get(key);
+        if current == Some(value) || (!force && current.is_some()) {
+            continue;
+        }
+        object.insert(key.clone(), value.clone());
+        changed.push(key.clone());
+    }
+    changed
+}
+
+/// Writes defaults and locked values into the settings files. Returns the
+/// `file#key` entries that had to be changed back to a locked value.
+fn enforce(dir: &Path, managed: &ManagedSettings) -> Result<Vec<String>> {
+    let mut reverted = Vec::new();
+    let names: std::collections::BTreeSet<&String> = managed
+        .defaults
+        .keys()
+        .chain(managed.locked.keys())
+        .collect();
+    for name in names {
+        if !manageable(name) {
+            eprintln!("Ignoring managed settings for {name}");
+            continue;
+        }
+        let path = dir.join(name);
+        let Value::Object(mut object) = read_file(&path)? else {
+            eprintln!("Ignoring managed settings for {name}, which is not an object");
+            continue;
+        };
+        let original = object.clone();
+        if let Some(defaults) = managed.defaults.get(name) {
+            merge(&mut object, defaults, false);
+        }
+        if let Some(locked) = managed.locked.get(name) {
+            let changed = merge(&mut object, locked, true);
+            reverted.extend(changed.into_iter().map(|key| format!("{name}#{key}")));
+        }
+        if object != original {
+            paths::write_json(&path, &object)?;
+        }
+    }
+    Ok(reverted)
+}
+
+fn signing_key() -> Result<Ed25519KeyPair> {
+    let invalid = |_| Error::InvalidInput("Settings signing key is invalid".into());
+    if let Some(stored) = credentials::get(SIGNING_KEY)? {
+        let pkcs8 = STANDARD
+            .decode(stored)
+            .map_err(|_| Error::InvalidInput("Settings signing key is not valid base64".into()))?;
+        return Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(invalid);
+    }
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .map_err(|_| Error::InvalidInput("Failed to generate a signing key".into()))?;
+    credentials::store(SIGNING_KEY, &STANDARD.encode(pkcs8.as_ref()))?;
+    Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).map_err(invalid)
+}
+
+/// Whether the administrator locked `key` in the settings file `file`.
+pub(crate) fn is_locked(file: &str, key: &str) -> bool {
+    MANAGED
+        .get()
+        .and_then(|managed| managed.locked.get(file))
+        .is_some_and(|locked| locked.contains_key(key))
+}
+
+/// Refuses to save `value` as `file` if it changes a locked key.
+pub(crate) fn check_locked(file: &str, value: &impl Serialize) -> Result<()> {
+    let Some(locked) = MANAGED.get().and_then(|managed| managed.locked.get(file)) else {
+        return Ok(());
+    };
+    let value = serde_json::to_value(value)?;
+    for (key, locked) in locked {
+        if value.get(key) != Some(locked) {
+            return Err(Error::InvalidInput(format!(
+                "{key} in {file} is locked by your administrator"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Writes locked values back after settings files were replaced wholesale,
+/// e.g. from a session. Returns the `file#key` entries that were restored.
+pub(crate) fn restore_locked(app: &AppHandle) -> Result<Vec<String>> {
+    match MANAGED.get() {
+        Some(managed) if managed.source.is_some() => enforce(&paths::config_dir(app)?, managed),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Applies managed settings before anything else reads its settings. This
+/// runs ahead of the logger, so problems go to stderr.
+pub fn init(app: &AppHandle) {
+    let managed = load_managed();
+    if managed.source.is_some() {
+        match paths::config_dir(app) {
+            Ok(dir) => match enforce(&dir, &managed) {
+                Ok(reverted) if !reverted.is_empty() => {
+                    eprintln!("Restored managed settings: {}", reverted.join(", "))
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!("Failed to apply managed settings: {err}"),
+            },
+            Err(err) => eprintln!("Failed to apply managed settings: {err}"),
+        }
+    }
+    let _ = MANAGED.set(managed.clone());
+    app.manage(managed);
+}
+
+/// Managed defaults, locks and presets, so the UI can disable locked controls.
+#[tauri::command]
+pub fn get_managed_settings(managed: State<'_, ManagedSettings>) -> ManagedSettings {
+    managed.inner().clone()
+}
+
+/// Writes all settings to a signed file. Returns the written path, or `None`
+/// if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_settings(app: AppHandle, path: Option<PathBuf>) -> Result<Option<PathBuf>> {
+    let Some(path) =
+        export::resolve_path(&app, path, "astranotes-settings", "Settings", "json").await
+    else {
+        return Ok(None);
+    };
+    let dir = paths::config_dir(&app)?;
+    let mut settings = BTreeMap::new();
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !portable(&name) || !entry.path().is_file() {
+                continue;
+            }
+            match read_file(&entry.path()) {
+                Ok(value) => {
+                    settings.insert(name, value);
+                }
+                Err(err) => log::warn!("Leaving invalid {name} out of the export: {err}"),
+            }
+        }
+    }
+
+    let signed = serde_json::to_value(SignedSettings {
+        format: FORMAT.into(),
+        version: VERSION,
+        created_at: crate::store::now_millis(),
+        app_version: app.package_info().version.to_string(),
+        settings,
+    })?;
+    let key = signing_key()?;
+    let signature = key.sign(&serde_json::to_vec(&signed)?);
+    let document = json!({
+        "signed": signed,
+        "signer": STANDARD.encode(key.public_key().as_ref()),
+        "signature": STANDARD.encode(signature.as_ref()),
+    });
+    let bytes = serde_json::to_vec_pretty(&document)?;
+    export::write_atomically(&path, |temp| Ok(fs::write(temp, &bytes)?))?;
+    log::info!("Exported settings to {}", path.display());
+    Ok(Some(path))
+}
+
+/// Verifies a settings export and writes its files, keeping locked values.
+#[tauri::command]
+pub fn import_settings(
+    app: AppHandle,
+    managed: State<'_, ManagedSettings>,
+    path: PathBuf,
+) -> Result<SettingsImport> {
+    let document: Value = serde_json::from_slice(&fs::read(&path)?)?;
+    let decode = |field: &str| {
+        document[field]
+            .as_str()
+            .and_then(|value| STANDARD.decode(value).ok())
+            .ok_or_else(|| Error::InvalidInput(format!("Settings file has no valid {field}")))
+    };
+    let signer = decode("signer")?;
+    let signature = decode("signature")?;
+    UnparsedPublicKey::new(&ED25519, &signer)
+        .verify(&serde_json::to_vec(&document["signed"])?, &signature)
+        .map_err(|_| {
+            Error::InvalidInput("Settings file signature does not match its contents".into())
+        })?;
+    let signer = STANDARD.encode(&signer);
+    // Only look up an existing key; a missing one must not be generated here
+    let own_signature = credentials::get(SIGNING_KEY)?.is_some()
+        && signing_key().is_ok_and(|key| STANDARD.encode(key.public_key().as_ref()) == signer);
+    if !own_signature && !managed.trusted_signers.contains(&signer) {
+        return Err(Error::InvalidInput(
+            "Settings file was not signed by this installation or a key your administrator trusts"
+                .into(),
+        ));
+    }
+
+    let signed: SignedSettings = serde_json::from_value(document["signed"].clone())?;
+    if signed.format != FORMAT || signed.version > VERSION {
+        return Err(Error::InvalidInput(format!(
+            "Unsupported settings file version {}",
+            signed.version
+        )));
+    }
+    let dir = paths::config_dir(&app)?;
+    let mut applied = Vec::new();
+    for (name, value) in signed.settings {
+        if !portable(&name) {
+            log::warn!("Ignoring {name} in settings import");
+            continue;
+        }
+        paths::write_json(&dir.join(&name), &value)?;
+        applied.push(name);
+    }
+    let locked = enforce(&dir, &managed)?;
+    log::info!("Imported settings from {}", path.display());
+    Ok(SettingsImport {
+        restart_required: !applied.is_empty(),
+        applied,
+        locked,
+        signer,
+        own_signature,
+    })
+}
@@ -0,0 +1,61 @@
+//! The macOS share picker.
+
+use std::ffi::c_void;
+use std::path::PathBuf;
+
+use objc2::AllocAnyThread;
+use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
+use objc2_app_kit::{NSSharingServicePicker, NSView};
+use objc2_foundation::{NSArray, NSPoint, NSRect, NSRectEdge, NSSize, NSString, NSURL};
+
+use super::ShareAnchor;
+
+/// Shows the picker for the text and files, pointing at `anchor` or else at
+/// the top of the window.
+///
+/// # Safety
+///
+/// `view` must be the window's content view, and this must run on the main
+/// thread.
+pub(super) unsafe fn share(
+    view: *mut c_void,
+    text: Option<&str>,
+    files: &[PathBuf],
+    anchor: Option<ShareAnchor>,
+) {
+    let view = unsafe { &*view.cast::<NSView>() };
+    let mut items: Vec<Retained<AnyObject>> = Vec::new();
+    if let Some(text) = text {
+        items.push(Retained::into_super(Retained::into_super(
+            NSString::from_str(text),
+        )));
+    }
+    for file in files {
+        let url = NSURL::fileURLWithPath(&NSString::from_str(&file.to_string_lossy()));
+        items.push(Retained::into_super(Retained::into_super(url)));
+    }
+    let items = NSArray::from_retained_slice(&items);
+
+    let bounds = view.bounds();
+    let anchor = anchor.unwrap_or(ShareAnchor {
+        x: bounds.size.width / 2.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    });
+    // The frontend measures from the top; unflipped views count from the bottom
+    let y = if view.isFlipped() {
+        anchor.y
+    } else {
+        bounds.size.height - anchor.y - anchor.height
+    };
+    let rect = NSRect::new(
+        NSPoint::new(anchor.x, y),
+        NSSize::new(anchor.width.max(1.0), anchor.height.max(1.0)),
+    );
+
+    let picker =
+        unsafe { NSSharingServicePicker::initWithItems(NSSharingServicePicker::alloc(), &items) };
+    picker.showRelativeToRect_ofView_preferredEdge(rect, view, NSRectEdge::MinY);
+}
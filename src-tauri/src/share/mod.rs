@@ -0,0 +1,162 @@
+//! Handing exports and note summaries to other apps through the OS share UI.
+//!
+//! macOS shows its share picker next to the button that asked, and Windows
+//! its Share UI for the window. Linux has no share sheet, so the text and
+//! files go to the default mail client through `xdg-email` instead.
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use tauri::WebviewWindow;
+
+use crate::clipboard;
+use crate::error::{Error, Result};
+use crate::export::ReportNote;
+use crate::i18n;
+
+/// Where the share button sits in the window, in logical pixels from the
+/// top left. The macOS picker points at it.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub struct ShareAnchor {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareRequest {
+    /// Subject of the share; defaults to a generic one.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Notes to summarize as text, the way they are copied to the clipboard.
+    #[serde(default)]
+    pub notes: Vec<ReportNote>,
+    /// Free text shared after the notes.
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Exported files to attach.
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    #[serde(default)]
+    pub anchor: Option<ShareAnchor>,
+}
+
+/// The notes' summary followed by the free text, or `None` without either.
+fn compose(notes: &[ReportNote], text: Option<&str>) -> Option<String> {
+    let parts: Vec<String> = [
+        (!notes.is_empty()).then(|| clipboard::render_text(notes)),
+        text.map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
+#[cfg(target_os = "macos")]
+async fn show(
+    window: &WebviewWindow,
+    _title: String,
+    text: Option<String>,
+    files: Vec<PathBuf>,
+    anchor: Option<ShareAnchor>,
+) -> Result<()> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let target = window.clone();
+    // AppKit views may only be touched from the main thread
+    window.run_on_main_thread(move || {
+        let shown = target.ns_view().map(|view| unsafe {
+            macos::share(view, text.as_deref(), &files, anchor);
+        });
+        let _ = tx.send(shown);
+    })?;
+    rx.await
+        .map_err(|_| Error::Share("The share picker could not be shown".into()))??;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+async fn show(
+    window: &WebviewWindow,
+    title: String,
+    text: Option<String>,
+    files: Vec<PathBuf>,
+    _anchor: Option<ShareAnchor>,
+) -> Result<()> {
+    let payload = tauri::async_runtime::spawn_blocking(move || {
+        windows::payload(&title, text.as_deref(), &files)
+    })
+    .await?
+    .map_err(|err| Error::Share(format!("Failed to prepare the share: {err}")))?;
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let target = window.clone();
+    // The Share UI belongs to the window's UI thread
+    window.run_on_main_thread(move || {
+        let shown = target.hwnd().map_err(Error::from).and_then(|hwnd| {
+            windows::show(hwnd, payload)
+                .map_err(|err| Error::Share(format!("Failed to open the Share UI: {err}")))
+        });
+        let _ = tx.send(shown);
+    })?;
+    rx.await
+        .map_err(|_| Error::Share("The Share UI could not be shown".into()))?
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+async fn show(
+    _window: &WebviewWindow,
+    title: String,
+    text: Option<String>,
+    files: Vec<PathBuf>,
+    _anchor: Option<ShareAnchor>,
+) -> Result<()> {
+    let mut command = tokio::process::Command::new("xdg-email");
+    command.arg("--utf8").arg("--subject").arg(&title);
+    if let Some(text) = &text {
+        command.arg("--body").arg(text);
+    }
+    for file in &files {
+        command.arg("--attach").arg(file);
+    }
+    let status = command
+        .status()
+        .await
+        .map_err(|err| Error::Share(format!("Failed to run xdg-email: {err}")))?;
+    if !status.success() {
+        return Err(Error::Share(format!("xdg-email exited with {status}")));
+    }
+    Ok(())
+}
+
+/// Shares exported files and a text summary of notes with another app.
+#[tauri::command]
+pub async fn share(window: WebviewWindow, request: ShareRequest) -> Result<()> {
+    let text = compose(&request.notes, request.text.as_deref());
+    for file in &request.files {
+        if !file.is_file() {
+            return Err(Error::InvalidInput(format!(
+                "{} does not exist",
+                file.display()
+            )));
+        }
+    }
+    if text.is_none() && request.files.is_empty() {
+        return Err(Error::InvalidInput("Nothing to share".into()));
+    }
+    let title = request
+        .title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| i18n::tr("share-title", &[]));
+    show(&window, title, text, request.files, request.anchor).await
+}
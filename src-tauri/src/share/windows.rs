@@ -0,0 +1,75 @@
+//! The Windows Share UI, through `DataTransferManager`.
+
+use std::cell::Cell;
+use std::path::PathBuf;
+
+use windows::ApplicationModel::DataTransfer::{DataRequestedEventArgs, DataTransferManager};
+use windows::Foundation::TypedEventHandler;
+use windows::Storage::{IStorageItem, StorageFile};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Shell::IDataTransferManagerInterop;
+use windows::core::{AgileReference, HSTRING, Interface, Ref, Result, factory};
+use windows_collections::IIterable;
+
+/// What the Share UI hands to the chosen app.
+pub(super) struct Payload {
+    title: HSTRING,
+    text: Option<HSTRING>,
+    /// Agile so the payload can move to the UI thread.
+    files: Vec<AgileReference<IStorageItem>>,
+}
+
+/// Opens the files as storage items. Blocks, so call it off the UI thread.
+pub(super) fn payload(title: &str, text: Option<&str>, files: &[PathBuf]) -> Result<Payload> {
+    let files = files
+        .iter()
+        .map(|path| {
+            StorageFile::GetFileFromPathAsync(&HSTRING::from(path.as_os_str()))?
+                .get()?
+                .cast::<IStorageItem>()
+                .and_then(|item| AgileReference::new(&item))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Payload {
+        title: HSTRING::from(title),
+        text: text.map(HSTRING::from),
+        files,
+    })
+}
+
+thread_local! {
+    /// The handler of the last share, replaced by the next one.
+    static HANDLER: Cell<Option<i64>> = const { Cell::new(None) };
+}
+
+/// Shows the Share UI for the window. Must run on the window's UI thread.
+pub(super) fn show(hwnd: HWND, payload: Payload) -> Result<()> {
+    let interop = factory::<DataTransferManager, IDataTransferManagerInterop>()?;
+    let manager: DataTransferManager = unsafe { interop.GetForWindow(hwnd)? };
+    if let Some(token) = HANDLER.take() {
+        manager.RemoveDataRequested(token)?;
+    }
+    let handler = TypedEventHandler::new(
+        move |_: Ref<DataTransferManager>, args: Ref<DataRequestedEventArgs>| {
+            let Some(args) = args.as_ref() else {
+                return Ok(());
+            };
+            let data = args.Request()?.Data()?;
+            data.Properties()?.SetTitle(&payload.title)?;
+            if let Some(text) = &payload.text {
+                data.SetText(text)?;
+            }
+            if !payload.files.is_empty() {
+                let items = payload
+                    .files
+                    .iter()
+                    .map(|file| file.resolve().map(Some))
+                    .collect::<Result<Vec<_>>>()?;
+                data.SetStorageItemsReadOnly(&IIterable::from(items))?;
+            }
+            Ok(())
+        },
+    );
+    HANDLER.set(Some(manager.DataRequested(&handler)?));
+    unsafe { interop.ShowShareUIForWindow(hwnd) }
+}
@@ -0,0 +1,187 @@
+//! System-wide keyboard shortcuts, so a note can be started from RV or any
+//! other app without switching windows.
+//!
+//! Bindings use the accelerator syntax (`CommandOrControl+Alt+N`) and are
+//! stored in `shortcuts.json` in the app config directory. Actions other than
+//! focusing the window are forwarded to the frontend as events.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::settings;
+
+pub const QUICK_NOTE_EVENT: &str = "shortcuts:quick-note";
+pub const PUBLISH_SELECTED_EVENT: &str = "shortcuts:publish-selected";
+
+const SETTINGS_FILE: &str = "shortcuts.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutAction {
+    FocusApp,
+    QuickNote,
+    PublishSelected,
+}
+
+/// `None` leaves an action unbound.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ShortcutSettings {
+    pub focus_app: Option<String>,
+    pub quick_note: Option<String>,
+    pub publish_selected: Option<String>,
+}
+
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        Self {
+            focus_app: Some("CommandOrControl+Alt+A".into()),
+            quick_note: Some("CommandOrControl+Alt+N".into()),
+            publish_selected: Some("CommandOrControl+Alt+P".into()),
+        }
+    }
+}
+
+impl ShortcutSettings {
+    fn bindings(&self) -> [(ShortcutAction, Option<&str>); 3] {
+        [
+            (ShortcutAction::FocusApp, self.focus_app.as_deref()),
+            (ShortcutAction::QuickNote, self.quick_note.as_deref()),
+            (
+                ShortcutAction::PublishSelected,
+                self.publish_selected.as_deref(),
+            ),
+        ]
+    }
+}
+
+/// Registered shortcut IDs and the actions they trigger.
+#[derive(Default)]
+pub struct Shortcuts {
+    actions: Mutex<HashMap<u32, ShortcutAction>>,
+}
+
+pub fn plugin() -> TauriPlugin<Wry> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(handle)
+        .build()
+}
+
+fn handle(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state != ShortcutState::Pressed {
+        return;
+    }
+    let action = app
+        .state::<Shortcuts>()
+        .actions
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&shortcut.id())
+        .copied();
+    let event = match action {
+        Some(ShortcutAction::FocusApp) => None,
+        Some(ShortcutAction::QuickNote) => Some(QUICK_NOTE_EVENT),
+        Some(ShortcutAction::PublishSelected) => Some(PUBLISH_SELECTED_EVENT),
+        None => return,
+    };
+    crate::focus_main_window(app);
+    if let Some(event) = event
+        && let Err(err) = app.emit(event, ())
+    {
+        log::warn!("Failed to emit {event}: {err}");
+    }
+}
+
+fn load_settings(app: &AppHandle) -> Result<ShortcutSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+/// Replaces the registered shortcuts with `settings`. Invalid or duplicate
+/// bindings are rejected before anything is unregistered.
+fn apply(app: &AppHandle, settings: &ShortcutSettings) -> Result<()> {
+    let mut parsed: Vec<(Shortcut, ShortcutAction)> = Vec::new();
+    for (action, binding) in settings.bindings() {
+        let Some(binding) = binding.map(str::trim).filter(|binding| !binding.is_empty()) else {
+            continue;
+        };
+        let shortcut = Shortcut::from_str(binding)
+            .map_err(|err| Error::InvalidInput(format!("Invalid shortcut {binding}: {err}")))?;
+        if parsed
+            .iter()
+            .any(|(existing, _)| existing.id() == shortcut.id())
+        {
+            return Err(Error::InvalidInput(format!(
+                "{binding} is bound to more than one action"
+            )));
+        }
+        parsed.push((shortcut, action));
+    }
+
+    let global_shortcut = app.global_shortcut();
+    global_shortcut.unregister_all()?;
+    let shortcuts = app.state::<Shortcuts>();
+    let mut actions = shortcuts
+        .actions
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    actions.clear();
+    let mut failed = Vec::new();
+    for (shortcut, action) in parsed {
+        match global_shortcut.register(shortcut) {
+            Ok(()) => {
+                actions.insert(shortcut.id(), action);
+            }
+            // Usually another app already owns the combination
+            Err(err) => {
+                log::warn!("Failed to register shortcut {shortcut}: {err}");
+                failed.push(shortcut.to_string());
+            }
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!(
+            "Shortcuts already in use: {}",
+            failed.join(", ")
+        )))
+    }
+}
+
+/// Registers the saved shortcuts. A binding taken by another app is logged
+/// rather than failing startup.
+pub fn init(app: &AppHandle) {
+    app.manage(Shortcuts::default());
+    let settings = load_settings(app).unwrap_or_else(|err| {
+        log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}");
+        ShortcutSettings::default()
+    });
+    if let Err(err) = apply(app, &settings) {
+        log::warn!("Some global shortcuts are unavailable: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn get_shortcut_settings(app: AppHandle) -> Result<ShortcutSettings> {
+    load_settings(&app)
+}
+
+/// Registers `settings` and saves them once every binding is in place.
+/// Otherwise the saved shortcuts are restored.
+#[tauri::command]
+pub fn set_shortcut_settings(app: AppHandle, settings: ShortcutSettings) -> Result<()> {
+    settings::check_locked(SETTINGS_FILE, &settings)?;
+    if let Err(err) = apply(&app, &settings) {
+        let _ = load_settings(&app).and_then(|saved| apply(&app, &saved));
+        return Err(err);
+    }
+    paths::save_json(&app, SETTINGS_FILE, &settings)
+}
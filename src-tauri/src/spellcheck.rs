@@ -0,0 +1,275 @@
+//! Spell checking that knows studio vocabulary.
+//!
+//! Words are checked against a Hunspell `.aff`/`.dic` pair plus a personal
+//! dictionary and any number of plain wordlists (shot codes, character and
+//! asset names). Tokens containing digits or underscores, and all-caps
+//! acronyms, are treated as codes and never flagged. Everything is loaded on
+//! first use and reloaded when the settings change.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use spellbook::Dictionary;
+use tauri::{AppHandle, State};
+
+use crate::error::{Error, Result};
+use crate::paths;
+
+const SETTINGS_FILE: &str = "spellcheck.json";
+const PERSONAL_DICTIONARY: &str = "dictionary.txt";
+const MAX_SUGGESTIONS: usize = 5;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpellcheckSettings {
+    /// Path to the `.dic` file; the `.aff` file must sit next to it.
+    pub dictionary_path: Option<PathBuf>,
+    /// Plain text files with one accepted word per line.
+    pub wordlists: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Misspelling {
+    pub word: String,
+    /// UTF-16 offsets into the checked text, matching JavaScript strings.
+    pub start: usize,
+    pub end: usize,
+}
+
+struct Lexicon {
+    dictionary: Option<Dictionary>,
+    /// Lowercased personal and wordlist entries.
+    words: HashSet<String>,
+}
+
+#[derive(Default)]
+pub struct SpellChecker {
+    lexicon: RwLock<Option<Lexicon>>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<SpellcheckSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn read_words(path: &PathBuf, words: &mut HashSet<String>) -> Result<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    words.extend(
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_lowercase),
+    );
+    Ok(())
+}
+
+impl Lexicon {
+    fn load(app: &AppHandle) -> Result<Self> {
+        let settings = load_settings(app)?;
+        let dictionary = match &settings.dictionary_path {
+            Some(dic_path) => {
+                let dic = fs::read_to_string(dic_path)?;
+                let aff = fs::read_to_string(dic_path.with_extension("aff"))?;
+                let dictionary = Dictionary::new(&aff, &dic).map_err(|err| {
+                    Error::InvalidInput(format!("Invalid dictionary {}: {err}", dic_path.display()))
+                })?;
+                Some(dictionary)
+            }
+            None => None,
+        };
+        let mut words = HashSet::new();
+        read_words(
+            &paths::config_dir(app)?.join(PERSONAL_DICTIONARY),
+            &mut words,
+        )?;
+        for wordlist in &settings.wordlists {
+            if let Err(err) = read_words(wordlist, &mut words) {
+                log::warn!("Skipping wordlist {}: {err}", wordlist.display());
+            }
+        }
+        Ok(Self { dictionary, words })
+    }
+
+    fn is_code(word: &str) -> bool {
+        word.chars().any(|c| c.is_ascii_digit() || c == '_')
+            || (word.chars().count() > 1 && word.chars().all(|c| !c.is_lowercase()))
+    }
+
+    fn accepts(&self, word: &str) -> bool {
+        let Some(dictionary) = &self.dictionary else {
+            return true;
+        };
+        if Self::is_code(word) || self.words.contains(&word.to_lowercase()) {
+            return true;
+        }
+        // Dictionaries spell contractions with a straight apostrophe
+        if dictionary.check(&word.replace('\u{2019}', "'")) {
+            return true;
+        }
+        // Hyphenated compounds pass when every part does
+        word.contains('-')
+            && word
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .all(|part| self.accepts(part))
+    }
+}
+
+impl SpellChecker {
+    fn with_lexicon<T>(&self, app: &AppHandle, read: impl FnOnce(&Lexicon) -> T) -> Result<T> {
+        {
+            let lexicon = self
+                .lexicon
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(lexicon) = lexicon.as_ref() {
+                return Ok(read(lexicon));
+            }
+        }
+        let mut lexicon = self
+            .lexicon
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if lexicon.is_none() {
+            *lexicon = Some(Lexicon::load(app)?);
+        }
+        Ok(read(lexicon.as_ref().expect("lexicon was just loaded")))
+    }
+
+    fn reset(&self) {
+        *self
+            .lexicon
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '\'' | '\u{2019}' | '-' | '_')
+}
+
+/// Splits `text` into words with their UTF-16 ranges.
+fn words(text: &str) -> Vec<(&str, usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut utf16 = 0;
+    let mut push = |from: (usize, usize), to: (usize, usize)| {
+        let word = &text[from.0..to.0];
+        let trimmed = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let leading = &word[..word.len() - trimmed.len()];
+        let trimmed = trimmed.trim_end_matches(|c: char| !c.is_alphanumeric());
+        if !trimmed.is_empty() {
+            let begin = from.1 + leading.encode_utf16().count();
+            words.push((trimmed, begin, begin + trimmed.encode_utf16().count()));
+        }
+    };
+    for (index, c) in text.char_indices() {
+        match (is_word_char(c), start) {
+            (true, None) => start = Some((index, utf16)),
+            (false, Some(from)) => {
+                push(from, (index, utf16));
+                start = None;
+            }
+            _ => {}
+        }
+        utf16 += c.len_utf16();
+    }
+    if let Some(from) = start {
+        push(from, (text.len(), utf16));
+    }
+    words
+}
+
+/// Flags misspelled words. Returns nothing until a dictionary is configured.
+#[tauri::command]
+pub async fn check_text(
+    app: AppHandle,
+    checker: State<'_, SpellChecker>,
+    text: String,
+) -> Result<Vec<Misspelling>> {
+    checker.with_lexicon(&app, |lexicon| {
+        words(&text)
+            .into_iter()
+            .filter(|(word, _, _)| !lexicon.accepts(word))
+            .map(|(word, start, end)| Misspelling {
+                word: word.to_string(),
+                start,
+                end,
+            })
+            .collect()
+    })
+}
+
+#[tauri::command]
+pub async fn suggest_spelling(
+    app: AppHandle,
+    checker: State<'_, SpellChecker>,
+    word: String,
+) -> Result<Vec<String>> {
+    checker.with_lexicon(&app, |lexicon| {
+        let mut suggestions = Vec::new();
+        if let Some(dictionary) = &lexicon.dictionary {
+            dictionary.suggest(&word, &mut suggestions);
+        }
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    })
+}
+
+/// Adds `word` to the personal dictionary.
+#[tauri::command]
+pub fn add_to_dictionary(
+    app: AppHandle,
+    checker: State<'_, SpellChecker>,
+    word: String,
+) -> Result<()> {
+    let word = word.trim();
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err(Error::InvalidInput(format!(
+            "Invalid dictionary word: {word}"
+        )));
+    }
+    let dir = paths::config_dir(&app)?;
+    fs::create_dir_all(&dir)?;
+    let mut file = fs::File::options()
+        .create(true)
+        .append(true)
+        .open(dir.join(PERSONAL_DICTIONARY))?;
+    writeln!(file, "{word}")?;
+
+    if let Some(lexicon) = checker
+        .lexicon
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .as_mut()
+    {
+        lexicon.words.insert(word.to_lowercase());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_spellcheck_settings(app: AppHandle) -> Result<SpellcheckSettings> {
+    load_settings(&app)
+}
+
+/// Saves the dictionary and wordlists; they are reloaded on the next check.
+#[tauri::command]
+pub fn set_spellcheck_settings(
+    app: AppHandle,
+    checker: State<'_, SpellChecker>,
+    settings: SpellcheckSettings,
+) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &settings)?;
+    checker.reset();
+    Ok(())
+}
@@ -0,0 +1,179 @@
+//! Staged startup and its timings.
+//!
+//! Setup only does what the first paint needs: plugins, settings, logging
+//! and the managed state that window events and protocol handlers read.
+//! Opening the stores and starting background services waits until the main
+//! window has loaded, or [`PAINT_TIMEOUT`] when it does not show, such as
+//! when starting in the tray. Each stage is timed, logged and emitted as
+//! `startup:stage`, and `startup:ready` follows the last one, so the frontend
+//! can show progress until the commands backed by the stores are usable.
+//! `get_startup_report` returns the same timings after the fact.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use crate::error::Result;
+
+pub const STAGE_EVENT: &str = "startup:stage";
+pub const READY_EVENT: &str = "startup:ready";
+/// How long deferred work waits for the main window to load.
+const PAINT_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageTiming {
+    pub name: String,
+    /// Since the process started.
+    pub started_ms: u64,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupReport {
+    pub stages: Vec<StageTiming>,
+    /// Whether every stage has run, successfully or not.
+    pub ready: bool,
+    pub failed: bool,
+    /// When the main window finished loading, since the process started.
+    pub first_paint_ms: Option<u64>,
+    pub elapsed_ms: u64,
+}
+
+pub struct Startup {
+    started: Instant,
+    stages: Mutex<Vec<StageTiming>>,
+    first_paint: Mutex<Option<u64>>,
+    painted: Notify,
+    ready: AtomicBool,
+    failed: AtomicBool,
+}
+
+impl Startup {
+    /// Timings count from `started`, taken as early in the process as possible.
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            stages: Mutex::default(),
+            first_paint: Mutex::default(),
+            painted: Notify::new(),
+            ready: AtomicBool::new(false),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    fn since_start(&self, at: Instant) -> u64 {
+        at.duration_since(self.started).as_millis() as u64
+    }
+
+    pub fn report(&self) -> StartupReport {
+        StartupReport {
+            stages: self
+                .stages
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone(),
+            ready: self.ready.load(Ordering::SeqCst),
+            failed: self.failed.load(Ordering::SeqCst),
+            first_paint_ms: *self
+                .first_paint
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            elapsed_ms: self.since_start(Instant::now()),
+        }
+    }
+}
+
+/// Runs one stage of startup, recording how long it took and whether it failed.
+pub fn stage<T>(app: &AppHandle, name: &str, run: impl FnOnce() -> Result<T>) -> Result<T> {
+    let begun = Instant::now();
+    let result = run();
+    let startup = app.state::<Startup>();
+    let timing = StageTiming {
+        name: name.to_string(),
+        started_ms: startup.since_start(begun),
+        duration_ms: begun.elapsed().as_millis() as u64,
+        error: result.as_ref().err().map(ToString::to_string),
+    };
+    match &timing.error {
+        Some(err) => log::error!(
+            "Startup stage {name} failed after {} ms: {err}",
+            timing.duration_ms
+        ),
+        None => log::info!(
+            "Startup stage {name} took {} ms (at {} ms)",
+            timing.duration_ms,
+            timing.started_ms
+        ),
+    }
+    if let Err(err) = app.emit(STAGE_EVENT, &timing) {
+        log::warn!("Failed to emit startup stage: {err}");
+    }
+    startup
+        .stages
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(timing);
+    result
+}
+
+/// Runs `deferred` off the main thread once the main window has loaded.
+pub fn defer(app: &AppHandle, deferred: fn(&AppHandle) -> Result<()>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let startup = app.state::<Startup>();
+        if tokio::time::timeout(PAINT_TIMEOUT, startup.painted.notified())
+            .await
+            .is_err()
+        {
+            log::info!("Main window has not loaded; continuing startup without it");
+        }
+        let handle = app.clone();
+        let failed = match tauri::async_runtime::spawn_blocking(move || deferred(&handle)).await {
+            Ok(result) => result.is_err(),
+            Err(err) => {
+                log::error!("Deferred startup panicked: {err}");
+                true
+            }
+        };
+        startup.failed.store(failed, Ordering::SeqCst);
+        startup.ready.store(true, Ordering::SeqCst);
+        let report = startup.report();
+        log::info!(
+            "Startup finished in {} ms{}",
+            report.elapsed_ms,
+            if failed { " with errors" } else { "" }
+        );
+        if let Err(err) = app.emit(READY_EVENT, &report) {
+            log::warn!("Failed to emit startup ready: {err}");
+        }
+    });
+}
+
+/// Notes that the main window finished loading, releasing deferred startup.
+pub fn painted(app: &AppHandle) {
+    let Some(startup) = app.try_state::<Startup>() else {
+        return;
+    };
+    let mut first_paint = startup
+        .first_paint
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if first_paint.is_none() {
+        *first_paint = Some(startup.since_start(Instant::now()));
+        startup.painted.notify_one();
+    }
+}
+
+/// Stage timings so far, for a splash screen that missed the events or for
+/// diagnosing a slow start.
+#[tauri::command]
+pub fn get_startup_report(startup: State<'_, Startup>) -> StartupReport {
+    startup.report()
+}
@@ -0,0 +1,318 @@
+//! Append-only log of significant actions, for accountability during review.
+//!
+//! Each entry records who acted, as the profile and ftrack user, on which
+//! entity. SQLite triggers reject updates and deletes, so entries can only
+//! ever be added.
+
+use std::path::PathBuf;
+
+use rusqlite::{Row, params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, State};
+
+use super::{Store, now_millis};
+use crate::error::Result;
+use crate::export;
+use crate::ftrack::Connection;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE audit_log (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        at          INTEGER NOT NULL,
+        action      TEXT NOT NULL,
+        profile_id  TEXT,
+        server_url  TEXT,
+        api_user    TEXT,
+        entity_type TEXT,
+        entity_id   TEXT,
+        summary     TEXT NOT NULL,
+        details     TEXT
+    );
+    CREATE INDEX audit_log_at ON audit_log (at);
+    CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+    BEGIN SELECT RAISE(ABORT, 'The audit log is append-only'); END;
+    CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+    BEGIN SELECT RAISE(ABORT, 'The audit log is append-only'); END;
+";
+
+/// Entries returned by one query when the filter sets no limit.
+const DEFAULT_LIMIT: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditAction {
+    Publish,
+    StatusChange,
+    /// Any other change synced to the server.
+    Update,
+    Export,
+    DeleteDraft,
+}
+
+impl AuditAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Publish => "publish",
+            Self::StatusChange => "statusChange",
+            Self::Update => "update",
+            Self::Export => "export",
+            Self::DeleteDraft => "deleteDraft",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "publish" => Self::Publish,
+            "statusChange" => Self::StatusChange,
+            "export" => Self::Export,
+            "deleteDraft" => Self::DeleteDraft,
+            _ => Self::Update,
+        }
+    }
+}
+
+/// An action to record. Without a connection, the entry is attributed to
+/// the active profile.
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub action: AuditAction,
+    pub connection: Option<Connection>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub summary: String,
+    pub details: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub at: i64,
+    pub action: AuditAction,
+    pub profile_id: Option<String>,
+    pub server_url: Option<String>,
+    pub api_user: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub summary: String,
+    pub details: Value,
+}
+
+impl AuditEntry {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let action: String = row.get("action")?;
+        let details: Option<String> = row.get("details")?;
+        Ok(Self {
+            id: row.get("id")?,
+            at: row.get("at")?,
+            action: AuditAction::parse(&action),
+            profile_id: row.get("profile_id")?,
+            server_url: row.get("server_url")?,
+            api_user: row.get("api_user")?,
+            entity_type: row.get("entity_type")?,
+            entity_id: row.get("entity_id")?,
+            summary: row.get("summary")?,
+            details: details
+                .and_then(|details| serde_json::from_str(&details).ok())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Every set field narrows the query; entries come back newest first.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditFilter {
+    pub actions: Vec<AuditAction>,
+    pub profile_id: Option<String>,
+    pub api_user: Option<String>,
+    pub entity_id: Option<String>,
+    /// Inclusive lower bound in milliseconds since the epoch.
+    pub from: Option<i64>,
+    /// Exclusive upper bound in milliseconds since the epoch.
+    pub to: Option<i64>,
+    /// Case-insensitive text the summary must contain.
+    pub search: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+impl Store {
+    pub fn append_audit_entry(&self, entry: NewAuditEntry) -> Result<AuditEntry> {
+        let conn = self.conn();
+        let (profile_id, connection) = match entry.connection {
+            Some(connection) => {
+                let profile_id = conn
+                    .query_row(
+                        "SELECT id FROM profiles WHERE server_url = ?1 AND api_user = ?2",
+                        params![connection.server_url, connection.api_user],
+                        |row| row.get(0),
+                    )
+                    .ok();
+                (profile_id, Some(connection))
+            }
+            None => conn
+                .query_row(
+                    "SELECT id, server_url, api_user FROM profiles WHERE active = 1",
+                    [],
+                    |row| {
+                        Ok((
+                            Some(row.get(0)?),
+                            Some(Connection {
+                                server_url: row.get(1)?,
+                                api_user: row.get(2)?,
+                            }),
+                        ))
+                    },
+                )
+                .unwrap_or_default(),
+        };
+        let details = (!entry.details.is_null()).then(|| entry.details.to_string());
+        let at = now_millis();
+        conn.execute(
+            "INSERT INTO audit_log (at, action, profile_id, server_url, api_user,
+                entity_type, entity_id, summary, details)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                at,
+                entry.action.as_str(),
+                profile_id,
+                connection.as_ref().map(|c| &c.server_url),
+                connection.as_ref().map(|c| &c.api_user),
+                entry.entity_type,
+                entry.entity_id,
+                entry.summary,
+                details,
+            ],
+        )?;
+        Ok(AuditEntry {
+            id: conn.last_insert_rowid(),
+            at,
+            action: entry.action,
+            profile_id,
+            server_url: connection.as_ref().map(|c| c.server_url.clone()),
+            api_user: connection.map(|c| c.api_user),
+            entity_type: entry.entity_type,
+            entity_id: entry.entity_id,
+            summary: entry.summary,
+            details: entry.details,
+        })
+    }
+
+    pub fn query_audit_log(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+        let actions = (!filter.actions.is_empty()).then(|| {
+            Value::from_iter(filter.actions.iter().map(|action| action.as_str())).to_string()
+        });
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM audit_log
+             WHERE (?1 IS NULL OR action IN (SELECT value FROM json_each(?1)))
+               AND (?2 IS NULL OR profile_id = ?2)
+               AND (?3 IS NULL OR api_user = ?3)
+               AND (?4 IS NULL OR entity_id = ?4)
+               AND (?5 IS NULL OR at >= ?5)
+               AND (?6 IS NULL OR at < ?6)
+               AND (?7 IS NULL OR instr(lower(summary), lower(?7)) > 0)
+             ORDER BY id DESC
+             LIMIT ?8 OFFSET ?9",
+        )?;
+        let entries = stmt
+            .query_map(
+                params![
+                    actions,
+                    filter.profile_id,
+                    filter.api_user,
+                    filter.entity_id,
+                    filter.from,
+                    filter.to,
+                    filter.search,
+                    filter.limit.unwrap_or(DEFAULT_LIMIT),
+                    filter.offset.unwrap_or_default(),
+                ],
+                AuditEntry::from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+}
+
+/// Records an action, logging instead of failing when it cannot be written.
+pub(crate) fn record(app: &AppHandle, entry: NewAuditEntry) {
+    if let Err(err) = super::state(app).and_then(|store| store.append_audit_entry(entry)) {
+        log::error!("Failed to write audit log entry: {err}");
+    }
+}
+
+const CSV_HEADERS: [&str; 9] = [
+    "Time",
+    "Action",
+    "Profile",
+    "Server",
+    "User",
+    "Entity Type",
+    "Entity",
+    "Summary",
+    "Details",
+];
+
+fn write_csv(path: &std::path::Path, entries: &[AuditEntry]) -> Result<()> {
+    let mut writer = ::csv::WriterBuilder::new()
+        .quote_style(::csv::QuoteStyle::Always)
+        .from_path(path)?;
+    writer.write_record(CSV_HEADERS)?;
+    for entry in entries {
+        let at = chrono::DateTime::from_timestamp_millis(entry.at)
+            .map(|at| at.to_rfc3339())
+            .unwrap_or_default();
+        let details = if entry.details.is_null() {
+            String::new()
+        } else {
+            entry.details.to_string()
+        };
+        writer.write_record([
+            at.as_str(),
+            entry.action.as_str(),
+            entry.profile_id.as_deref().unwrap_or_default(),
+            entry.server_url.as_deref().unwrap_or_default(),
+            entry.api_user.as_deref().unwrap_or_default(),
+            entry.entity_type.as_deref().unwrap_or_default(),
+            entry.entity_id.as_deref().unwrap_or_default(),
+            entry.summary.as_str(),
+            details.as_str(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn query_audit_log(store: State<'_, Store>, filter: AuditFilter) -> Result<Vec<AuditEntry>> {
+    store.query_audit_log(&filter)
+}
+
+/// Exports the matching entries as CSV, ignoring the filter's limit. Returns
+/// the written path, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_audit_log(
+    app: AppHandle,
+    filter: AuditFilter,
+    path: Option<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    let Some(path) = export::resolve_path(&app, path, "audit_log", "CSV", "csv").await else {
+        return Ok(None);
+    };
+    let filter = AuditFilter {
+        limit: Some(u32::MAX),
+        offset: None,
+        ..filter
+    };
+    let entries = super::state(&app)?.query_audit_log(&filter)?;
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        export::write_atomically(&target, |temp| write_csv(temp, &entries))
+    })
+    .await??;
+    log::info!("Exported audit log to {}", path.display());
+    Ok(Some(path))
+}
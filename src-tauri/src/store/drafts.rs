@@ -0,0 +1,266 @@
+//! Note drafts keyed by playlist and version.
+
+use rusqlite::{OptionalExtension, Row, params};
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, State};
+
+use super::audit::{self, AuditAction, NewAuditEntry};
+use super::{Store, now_millis};
+use crate::badge;
+use crate::error::Result;
+use crate::tray;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE drafts (
+        playlist_id TEXT NOT NULL,
+        version_id  TEXT NOT NULL,
+        content     TEXT NOT NULL,
+        label_id    TEXT,
+        status      TEXT NOT NULL,
+        updated_at  INTEGER NOT NULL,
+        PRIMARY KEY (playlist_id, version_id)
+    );
+    CREATE INDEX drafts_updated_at ON drafts (updated_at);
+";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    pub playlist_id: String,
+    pub version_id: String,
+    pub content: String,
+    pub label_id: Option<String>,
+    pub status: String,
+    pub updated_at: i64,
+    pub project_id: Option<String>,
+    /// The draft is in a locked vault; `content` is empty until it is unlocked.
+    pub locked: bool,
+}
+
+impl Draft {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<(Self, bool)> {
+        let draft = Self {
+            playlist_id: row.get("playlist_id")?,
+            version_id: row.get("version_id")?,
+            content: row.get("content")?,
+            label_id: row.get("label_id")?,
+            status: row.get("status")?,
+            updated_at: row.get("updated_at")?,
+            project_id: row.get("project_id")?,
+            locked: false,
+        };
+        Ok((draft, row.get("sealed")?))
+    }
+}
+
+impl Store {
+    /// Opens the text of a draft read with [`Draft::from_row`].
+    fn open_draft(&self, (mut draft, sealed): (Draft, bool)) -> Draft {
+        match self.vault.open(
+            draft.project_id.as_deref(),
+            &draft.playlist_id,
+            &draft.version_id,
+            &draft.content,
+            sealed,
+        ) {
+            Some(content) => draft.content = content,
+            None => {
+                draft.content.clear();
+                draft.locked = true;
+            }
+        }
+        draft
+    }
+
+    /// Saves a draft. Without a `project_id`, the draft keeps the project it
+    /// was saved with before; drafts of projects with a vault are sealed.
+    pub fn save_draft(
+        &self,
+        playlist_id: &str,
+        version_id: &str,
+        content: &str,
+        label_id: Option<&str>,
+        project_id: Option<&str>,
+    ) -> Result<Draft> {
+        // Mirror DraftManager: whitespace-only content is stored as an empty draft
+        let content = content.trim();
+        let status = if content.is_empty() { "empty" } else { "draft" };
+        // Without a project, inherit the draft's own, else another draft of the playlist's
+        let project_id = match project_id {
+            Some(project_id) => Some(project_id.to_string()),
+            None => self
+                .conn()
+                .query_row(
+                    "SELECT project_id FROM drafts
+                     WHERE playlist_id = ?1 AND project_id IS NOT NULL
+                     ORDER BY version_id = ?2 DESC LIMIT 1",
+                    params![playlist_id, version_id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten(),
+        };
+        let (stored, sealed) =
+            self.vault
+                .seal(project_id.as_deref(), playlist_id, version_id, content)?;
+        let draft = Draft {
+            playlist_id: playlist_id.to_string(),
+            version_id: version_id.to_string(),
+            content: content.to_string(),
+            label_id: label_id.map(str::to_string),
+            status: status.to_string(),
+            updated_at: now_millis(),
+            project_id,
+            locked: false,
+        };
+
+        self.conn().execute(
+            "INSERT INTO drafts (playlist_id, version_id, content, label_id, status, updated_at,
+                project_id, sealed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT (playlist_id, version_id) DO UPDATE SET
+                content = excluded.content,
+                label_id = excluded.label_id,
+                status = excluded.status,
+                updated_at = excluded.updated_at,
+                project_id = excluded.project_id,
+                sealed = excluded.sealed",
+            params![
+                draft.playlist_id,
+                draft.version_id,
+                stored,
+                draft.label_id,
+                draft.status,
+                draft.updated_at,
+                draft.project_id,
+                sealed,
+            ],
+        )?;
+        self.record_revision(
+            &draft.playlist_id,
+            &draft.version_id,
+            &draft.content,
+            draft.label_id.as_deref(),
+            draft.project_id.as_deref(),
+        )?;
+        Ok(draft)
+    }
+
+    pub fn get_draft(&self, playlist_id: &str, version_id: &str) -> Result<Option<Draft>> {
+        let draft = self
+            .conn()
+            .query_row(
+                "SELECT * FROM drafts WHERE playlist_id = ?1 AND version_id = ?2",
+                params![playlist_id, version_id],
+                Draft::from_row,
+            )
+            .optional()?;
+        Ok(draft.map(|draft| self.open_draft(draft)))
+    }
+
+    /// Lists drafts for one playlist, or across all playlists when `playlist_id` is `None`.
+    pub fn list_drafts(&self, playlist_id: Option<&str>) -> Result<Vec<Draft>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM drafts
+             WHERE ?1 IS NULL OR playlist_id = ?1
+             ORDER BY updated_at DESC",
+        )?;
+        let drafts = stmt
+            .query_map(params![playlist_id], Draft::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(drafts
+            .into_iter()
+            .map(|draft| self.open_draft(draft))
+            .collect())
+    }
+
+    pub fn count_unpublished_drafts(&self) -> Result<u32> {
+        let count = self.conn().query_row(
+            "SELECT COUNT(*) FROM drafts WHERE status = 'draft'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    pub fn mark_draft_published(&self, playlist_id: &str, version_id: &str) -> Result<()> {
+        self.conn().execute(
+            "UPDATE drafts SET status = 'published', updated_at = ?3
+             WHERE playlist_id = ?1 AND version_id = ?2",
+            params![playlist_id, version_id, now_millis()],
+        )?;
+        self.mark_revision_published(playlist_id, version_id)
+    }
+
+    pub fn delete_draft(&self, playlist_id: &str, version_id: &str) -> Result<bool> {
+        let deleted = self.conn().execute(
+            "DELETE FROM drafts WHERE playlist_id = ?1 AND version_id = ?2",
+            params![playlist_id, version_id],
+        )?;
+        Ok(deleted > 0)
+    }
+}
+
+#[tauri::command]
+pub fn save_draft(
+    app: AppHandle,
+    store: State<'_, Store>,
+    playlist_id: String,
+    version_id: String,
+    content: String,
+    label_id: Option<String>,
+    project_id: Option<String>,
+) -> Result<Draft> {
+    let draft = store.save_draft(
+        &playlist_id,
+        &version_id,
+        &content,
+        label_id.as_deref(),
+        project_id.as_deref(),
+    )?;
+    tray::refresh(&app);
+    badge::refresh(&app);
+    Ok(draft)
+}
+
+#[tauri::command]
+pub fn list_drafts(store: State<'_, Store>, playlist_id: Option<String>) -> Result<Vec<Draft>> {
+    store.list_drafts(playlist_id.as_deref())
+}
+
+#[tauri::command]
+pub fn restore_draft(
+    store: State<'_, Store>,
+    playlist_id: String,
+    version_id: String,
+) -> Result<Option<Draft>> {
+    store.get_draft(&playlist_id, &version_id)
+}
+
+#[tauri::command]
+pub fn delete_draft(
+    app: AppHandle,
+    store: State<'_, Store>,
+    playlist_id: String,
+    version_id: String,
+) -> Result<bool> {
+    let deleted = store.delete_draft(&playlist_id, &version_id)?;
+    if deleted {
+        audit::record(
+            &app,
+            NewAuditEntry {
+                action: AuditAction::DeleteDraft,
+                connection: None,
+                entity_type: Some("AssetVersion".into()),
+                entity_id: Some(version_id),
+                summary: "Deleted draft".into(),
+                details: json!({ "playlistId": playlist_id }),
+            },
+        );
+    }
+    tray::refresh(&app);
+    badge::refresh(&app);
+    Ok(deleted)
+}
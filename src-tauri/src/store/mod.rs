@@ -0,0 +1,103 @@
+//! Native SQLite store for data that must survive webview storage resets.
+//!
+//! Each subsystem owns its tables in a submodule; the schema is advanced with
+//! ordered migrations tracked through SQLite's `user_version` pragma.
+
+pub mod audit;
+pub mod drafts;
+pub mod mutations;
+pub mod note_bases;
+pub mod profiles;
+pub mod publish_jobs;
+pub mod recovery;
+pub mod revisions;
+pub mod templates;
+pub mod vault;
+pub mod version_thumbnails;
+pub mod watches;
+
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::Connection;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{Error, Result};
+
+/// Ordered schema migrations. Never edit an entry once released; append a new one.
+const MIGRATIONS: &[&str] = &[
+    drafts::SCHEMA,
+    publish_jobs::SCHEMA,
+    watches::SCHEMA,
+    profiles::SCHEMA,
+    mutations::SCHEMA,
+    note_bases::SCHEMA,
+    revisions::SCHEMA,
+    version_thumbnails::SCHEMA,
+    templates::SCHEMA,
+    profiles::PROVIDER_SCHEMA,
+    recovery::SCHEMA,
+    profiles::PIN_SCHEMA,
+    audit::SCHEMA,
+    vault::SCHEMA,
+    vault::SEALED_SCHEMA,
+];
+
+/// The database file in the app data directory.
+pub const FILE_NAME: &str = "astranotes.db";
+
+pub struct Store {
+    conn: Mutex<Connection>,
+    vault: vault::Keys,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&conn)?;
+        Ok(Self {
+            vault: vault::Keys::load(&conn)?,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub(crate) fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic while holding the lock leaves SQLite itself consistent
+        self.conn
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn migrate(conn: &Connection) -> Result<()> {
+    let current: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = index + 1;
+        conn.execute_batch(&format!(
+            "BEGIN; {sql} PRAGMA user_version = {version}; COMMIT;"
+        ))?;
+        log::info!("Applied store migration {version}");
+    }
+    Ok(())
+}
+
+/// The managed store, or an error while it is not open: early in startup, or
+/// after the storage stage failed.
+pub(crate) fn state(app: &AppHandle) -> Result<State<'_, Store>> {
+    app.try_state::<Store>()
+        .ok_or_else(|| Error::InvalidInput("The local database is not open".into()))
+}
+
+/// Milliseconds since the Unix epoch, matching the frontend's `Date.now()`.
+pub(crate) fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,289 @@
+//! Durable log of ftrack mutations made while offline, replayed by `sync`.
+
+use rusqlite::{OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{Store, now_millis};
+use crate::error::Result;
+use crate::ftrack::Connection;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE mutations (
+        id          TEXT PRIMARY KEY,
+        server_url  TEXT NOT NULL,
+        api_user    TEXT NOT NULL,
+        kind        TEXT NOT NULL,
+        entity_type TEXT NOT NULL,
+        entity_id   TEXT NOT NULL,
+        operations  TEXT NOT NULL,
+        expected    TEXT,
+        remote      TEXT,
+        status      TEXT NOT NULL,
+        last_error  TEXT,
+        created_at  INTEGER NOT NULL,
+        updated_at  INTEGER NOT NULL
+    );
+    CREATE INDEX mutations_status ON mutations (status, created_at);
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MutationStatus {
+    Pending,
+    Applied,
+    Conflict,
+    Failed,
+}
+
+impl MutationStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Applied => "applied",
+            Self::Conflict => "conflict",
+            Self::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "applied" => Self::Applied,
+            "conflict" => Self::Conflict,
+            "failed" => Self::Failed,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// A change as recorded by the frontend.
+///
+/// `expected` holds the attribute values the change was based on, e.g.
+/// `{ "status_id": "…" }`. If the server no longer has those values when the
+/// mutation is replayed, it is held back as a conflict instead of applied.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewMutation {
+    /// Free-form category shown in the UI, such as `note` or `status`.
+    pub kind: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub operations: Value,
+    #[serde(default)]
+    pub expected: Option<serde_json::Map<String, Value>>,
+    /// Defaults to the active profile.
+    #[serde(default)]
+    pub connection: Option<Connection>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mutation {
+    pub id: String,
+    pub server_url: String,
+    pub api_user: String,
+    pub kind: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub operations: Value,
+    pub expected: Option<serde_json::Map<String, Value>>,
+    /// Server values found when the mutation conflicted.
+    pub remote: Option<Value>,
+    pub status: MutationStatus,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl Mutation {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let operations: String = row.get("operations")?;
+        let expected: Option<String> = row.get("expected")?;
+        let remote: Option<String> = row.get("remote")?;
+        let status: String = row.get("status")?;
+        Ok(Self {
+            id: row.get("id")?,
+            server_url: row.get("server_url")?,
+            api_user: row.get("api_user")?,
+            kind: row.get("kind")?,
+            entity_type: row.get("entity_type")?,
+            entity_id: row.get("entity_id")?,
+            operations: serde_json::from_str(&operations).unwrap_or_default(),
+            expected: expected.and_then(|expected| serde_json::from_str(&expected).ok()),
+            remote: remote.and_then(|remote| serde_json::from_str(&remote).ok()),
+            status: MutationStatus::parse(&status),
+            last_error: row.get("last_error")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    pub fn connection(&self) -> Connection {
+        Connection {
+            server_url: self.server_url.clone(),
+            api_user: self.api_user.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MutationCounts {
+    pub pending: u32,
+    pub conflicts: u32,
+    pub failed: u32,
+}
+
+impl Store {
+    pub fn insert_mutation(
+        &self,
+        connection: &Connection,
+        mutation: NewMutation,
+    ) -> Result<Mutation> {
+        let now = now_millis();
+        let mutation = Mutation {
+            id: uuid::Uuid::new_v4().to_string(),
+            server_url: connection.server_url.clone(),
+            api_user: connection.api_user.clone(),
+            kind: mutation.kind,
+            entity_type: mutation.entity_type,
+            entity_id: mutation.entity_id,
+            operations: mutation.operations,
+            expected: mutation.expected.filter(|expected| !expected.is_empty()),
+            remote: None,
+            status: MutationStatus::Pending,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.conn().execute(
+            "INSERT INTO mutations (id, server_url, api_user, kind, entity_type, entity_id,
+                operations, expected, status, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10)",
+            params![
+                mutation.id,
+                mutation.server_url,
+                mutation.api_user,
+                mutation.kind,
+                mutation.entity_type,
+                mutation.entity_id,
+                mutation.operations.to_string(),
+                mutation
+                    .expected
+                    .as_ref()
+                    .map(|expected| Value::Object(expected.clone()).to_string()),
+                mutation.status.as_str(),
+                now,
+            ],
+        )?;
+        Ok(mutation)
+    }
+
+    /// Lists mutations in the order they were made.
+    pub fn list_mutations(&self) -> Result<Vec<Mutation>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT * FROM mutations ORDER BY created_at, rowid")?;
+        let mutations = stmt
+            .query_map([], Mutation::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(mutations)
+    }
+
+    pub fn get_mutation(&self, id: &str) -> Result<Option<Mutation>> {
+        let mutation = self
+            .conn()
+            .query_row(
+                "SELECT * FROM mutations WHERE id = ?1",
+                params![id],
+                Mutation::from_row,
+            )
+            .optional()?;
+        Ok(mutation)
+    }
+
+    pub fn mutation_counts(&self) -> Result<MutationCounts> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT status, COUNT(*) FROM mutations GROUP BY status")?;
+        let mut rows = stmt.query([])?;
+        let mut counts = MutationCounts::default();
+        while let Some(row) = rows.next()? {
+            let status: String = row.get(0)?;
+            let count: u32 = row.get(1)?;
+            match MutationStatus::parse(&status) {
+                MutationStatus::Pending => counts.pending = count,
+                MutationStatus::Conflict => counts.conflicts = count,
+                MutationStatus::Failed => counts.failed = count,
+                MutationStatus::Applied => {}
+            }
+        }
+        Ok(counts)
+    }
+
+    pub fn update_mutation(
+        &self,
+        id: &str,
+        status: MutationStatus,
+        last_error: Option<&str>,
+        remote: Option<&Value>,
+    ) -> Result<()> {
+        self.conn().execute(
+            "UPDATE mutations SET status = ?2, last_error = ?3, remote = ?4, updated_at = ?5
+             WHERE id = ?1",
+            params![
+                id,
+                status.as_str(),
+                last_error,
+                remote.map(Value::to_string),
+                now_millis()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Queues a conflicted or failed mutation again. With `force`, the
+    /// expected values are dropped so the change overwrites the server's.
+    pub fn requeue_mutation(&self, id: &str, force: bool) -> Result<bool> {
+        let updated = self.conn().execute(
+            "UPDATE mutations SET status = 'pending', last_error = NULL, remote = NULL,
+                expected = CASE WHEN ?2 THEN NULL ELSE expected END, updated_at = ?3
+             WHERE id = ?1 AND status IN ('conflict', 'failed')",
+            params![id, force, now_millis()],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Replaces a conflicted mutation's change and base, and queues it again.
+    pub fn rebase_mutation(
+        &self,
+        id: &str,
+        operations: &Value,
+        expected: &serde_json::Map<String, Value>,
+    ) -> Result<bool> {
+        let updated = self.conn().execute(
+            "UPDATE mutations SET status = 'pending', operations = ?2, expected = ?3,
+                last_error = NULL, remote = NULL, updated_at = ?4
+             WHERE id = ?1 AND status = 'conflict'",
+            params![
+                id,
+                operations.to_string(),
+                Value::Object(expected.clone()).to_string(),
+                now_millis()
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn delete_mutation(&self, id: &str) -> Result<bool> {
+        let deleted = self
+            .conn()
+            .execute("DELETE FROM mutations WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    pub fn clear_applied_mutations(&self) -> Result<usize> {
+        let cleared = self
+            .conn()
+            .execute("DELETE FROM mutations WHERE status = 'applied'", [])?;
+        Ok(cleared)
+    }
+}
@@ -0,0 +1,47 @@
+//! Server content each edited note started from, used to detect conflicts.
+
+use rusqlite::{OptionalExtension, params};
+
+use super::{Store, now_millis};
+use crate::error::Result;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE note_bases (
+        note_id     TEXT PRIMARY KEY,
+        content     TEXT NOT NULL,
+        captured_at INTEGER NOT NULL
+    );
+";
+
+impl Store {
+    pub fn set_note_base(&self, note_id: &str, content: &str) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO note_bases (note_id, content, captured_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (note_id) DO UPDATE SET
+                content = excluded.content,
+                captured_at = excluded.captured_at",
+            params![note_id, content, now_millis()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_note_base(&self, note_id: &str) -> Result<Option<String>> {
+        let content = self
+            .conn()
+            .query_row(
+                "SELECT content FROM note_bases WHERE note_id = ?1",
+                params![note_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(content)
+    }
+
+    pub fn delete_note_base(&self, note_id: &str) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM note_bases WHERE note_id = ?1",
+            params![note_id],
+        )?;
+        Ok(())
+    }
+}
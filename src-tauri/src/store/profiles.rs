@@ -0,0 +1,176 @@
+//! Tracker account profiles. API keys live in the OS keychain, never here.
+
+use rusqlite::{OptionalExtension, Row, params};
+use serde::Serialize;
+
+use super::{Store, now_millis};
+use crate::error::Result;
+use crate::ftrack::Connection;
+use crate::providers::{ProviderKind, shotgrid};
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE profiles (
+        id           TEXT PRIMARY KEY,
+        name         TEXT NOT NULL,
+        server_url   TEXT NOT NULL,
+        api_user     TEXT NOT NULL,
+        active       INTEGER NOT NULL DEFAULT 0,
+        created_at   INTEGER NOT NULL,
+        last_used_at INTEGER,
+        UNIQUE (server_url, api_user)
+    );
+";
+
+/// Profiles predating the provider column are all ftrack accounts.
+pub(super) const PROVIDER_SCHEMA: &str = "
+    ALTER TABLE profiles ADD COLUMN provider TEXT NOT NULL DEFAULT 'ftrack';
+";
+
+pub(super) const PIN_SCHEMA: &str = "
+    ALTER TABLE profiles ADD COLUMN certificate_pin TEXT;
+";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub provider: ProviderKind,
+    pub server_url: String,
+    pub api_user: String,
+    pub active: bool,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    /// `sha256/<base64>` of the server's public key, once pinned.
+    pub certificate_pin: Option<String>,
+}
+
+impl Profile {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            provider: ProviderKind::from_db(&row.get::<_, String>("provider")?),
+            server_url: row.get("server_url")?,
+            api_user: row.get("api_user")?,
+            active: row.get("active")?,
+            created_at: row.get("created_at")?,
+            last_used_at: row.get("last_used_at")?,
+            certificate_pin: row.get("certificate_pin")?,
+        })
+    }
+
+    pub fn connection(&self) -> Connection {
+        Connection {
+            server_url: self.server_url.clone(),
+            api_user: self.api_user.clone(),
+        }
+    }
+
+    /// Keychain entry holding this account's API key or script key.
+    pub fn credential_key(&self) -> String {
+        match self.provider {
+            ProviderKind::Ftrack => self.connection().credential_key(),
+            ProviderKind::ShotGrid => shotgrid::credential_key(&self.server_url, &self.api_user),
+        }
+    }
+}
+
+impl Store {
+    pub fn insert_profile(
+        &self,
+        name: &str,
+        provider: ProviderKind,
+        server_url: &str,
+        api_user: &str,
+    ) -> Result<Profile> {
+        let profile = Profile {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            provider,
+            server_url: server_url.to_string(),
+            api_user: api_user.to_string(),
+            active: false,
+            created_at: now_millis(),
+            last_used_at: None,
+            certificate_pin: None,
+        };
+        self.conn().execute(
+            "INSERT INTO profiles (id, name, provider, server_url, api_user, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                profile.id,
+                profile.name,
+                profile.provider.as_str(),
+                profile.server_url,
+                profile.api_user,
+                profile.created_at
+            ],
+        )?;
+        Ok(profile)
+    }
+
+    pub fn list_profiles(&self) -> Result<Vec<Profile>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT * FROM profiles ORDER BY name COLLATE NOCASE")?;
+        let profiles = stmt
+            .query_map([], Profile::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(profiles)
+    }
+
+    pub fn get_profile(&self, id: &str) -> Result<Option<Profile>> {
+        let profile = self
+            .conn()
+            .query_row(
+                "SELECT * FROM profiles WHERE id = ?1",
+                params![id],
+                Profile::from_row,
+            )
+            .optional()?;
+        Ok(profile)
+    }
+
+    pub fn active_profile(&self) -> Result<Option<Profile>> {
+        let profile = self
+            .conn()
+            .query_row(
+                "SELECT * FROM profiles WHERE active = 1",
+                [],
+                Profile::from_row,
+            )
+            .optional()?;
+        Ok(profile)
+    }
+
+    /// Makes `id` the only active profile. Returns `false` if it does not exist.
+    pub fn set_active_profile(&self, id: &str) -> Result<bool> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let exists = tx.execute(
+            "UPDATE profiles SET active = 1, last_used_at = ?2 WHERE id = ?1",
+            params![id, now_millis()],
+        )? > 0;
+        if exists {
+            tx.execute("UPDATE profiles SET active = 0 WHERE id != ?1", params![id])?;
+        }
+        tx.commit()?;
+        Ok(exists)
+    }
+
+    /// Records or, with `None`, clears a profile's pin. Returns `false` if it does not exist.
+    pub fn set_profile_pin(&self, id: &str, pin: Option<&str>) -> Result<bool> {
+        let updated = self.conn().execute(
+            "UPDATE profiles SET certificate_pin = ?2 WHERE id = ?1",
+            params![id, pin],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn delete_profile(&self, id: &str) -> Result<bool> {
+        let deleted = self
+            .conn()
+            .execute("DELETE FROM profiles WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,385 @@
+//! Persisted note-publish jobs consumed by the background publish queue.
+//!
+//! Operations of a vaulted project are sealed at rest. While its vault is
+//! locked, its jobs are listed with `locked` set and are not run.
+
+use rusqlite::{OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+
+use super::vault::job_aad;
+use super::{Store, now_millis};
+use crate::error::Result;
+
+/// How long a due job of a locked vault waits before it is looked at again.
+const LOCKED_RETRY_MS: i64 = 60_000;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE publish_jobs (
+        id              TEXT PRIMARY KEY,
+        playlist_id     TEXT NOT NULL,
+        version_id      TEXT NOT NULL,
+        server_url      TEXT NOT NULL,
+        api_user        TEXT NOT NULL,
+        operations      TEXT NOT NULL,
+        status          TEXT NOT NULL,
+        attempts        INTEGER NOT NULL DEFAULT 0,
+        last_error      TEXT,
+        next_attempt_at INTEGER NOT NULL,
+        created_at      INTEGER NOT NULL,
+        updated_at      INTEGER NOT NULL
+    );
+    CREATE INDEX publish_jobs_status ON publish_jobs (status, next_attempt_at);
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "running" => Self::Running,
+            "completed" => Self::Completed,
+            "failed" => Self::Failed,
+            "cancelled" => Self::Cancelled,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// A publish job as submitted by the frontend.
+///
+/// `operations` is the ftrack API operation batch (note create, label links,
+/// attachment components) built by the frontend note service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewPublishJob {
+    pub playlist_id: String,
+    pub version_id: String,
+    /// Empty server and user default to the active profile.
+    #[serde(default)]
+    pub server_url: String,
+    #[serde(default)]
+    pub api_user: String,
+    pub operations: serde_json::Value,
+    /// Defaults to the project of the playlist's drafts.
+    #[serde(default)]
+    pub project_id: Option<String>,
+    /// Extra data for automation scripts and pipeline hooks, e.g. playlist and
+    /// label names. Not stored.
+    #[serde(default)]
+    pub context: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishJob {
+    pub id: String,
+    pub playlist_id: String,
+    pub version_id: String,
+    pub server_url: String,
+    pub api_user: String,
+    pub operations: serde_json::Value,
+    pub project_id: Option<String>,
+    /// Set while the job's vault is locked; `operations` is then null.
+    pub locked: bool,
+    pub status: JobStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// Operations as stored, until opened with the vault key.
+    #[serde(skip)]
+    sealed: Option<String>,
+}
+
+impl PublishJob {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let operations: String = row.get("operations")?;
+        let sealed: bool = row.get("sealed")?;
+        let status: String = row.get("status")?;
+        let (operations, sealed) = if sealed {
+            (serde_json::Value::Null, Some(operations))
+        } else {
+            (serde_json::from_str(&operations).unwrap_or_default(), None)
+        };
+        Ok(Self {
+            id: row.get("id")?,
+            playlist_id: row.get("playlist_id")?,
+            version_id: row.get("version_id")?,
+            server_url: row.get("server_url")?,
+            api_user: row.get("api_user")?,
+            operations,
+            project_id: row.get("project_id")?,
+            locked: false,
+            status: JobStatus::parse(&status),
+            attempts: row.get("attempts")?,
+            last_error: row.get("last_error")?,
+            next_attempt_at: row.get("next_attempt_at")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+            sealed,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCounts {
+    pub pending: u32,
+    pub running: u32,
+    pub completed: u32,
+    pub failed: u32,
+}
+
+impl Store {
+    /// Opens sealed operations, or marks the job locked.
+    fn open_publish_job(&self, mut job: PublishJob) -> PublishJob {
+        if let Some(text) = job.sealed.take() {
+            let opened =
+                self.vault
+                    .open_with(job.project_id.as_deref(), &job_aad(&job.id), &text, true);
+            match opened {
+                Some(text) => job.operations = serde_json::from_str(&text).unwrap_or_default(),
+                None => {
+                    job.sealed = Some(text);
+                    job.locked = true;
+                }
+            }
+        }
+        job
+    }
+
+    pub fn insert_publish_jobs(&self, jobs: Vec<NewPublishJob>) -> Result<Vec<PublishJob>> {
+        let now = now_millis();
+        let mut prepared = Vec::with_capacity(jobs.len());
+        for mut job in jobs {
+            let project_id = match job.project_id.take() {
+                Some(project_id) => Some(project_id),
+                None => self.vault_project(&job.playlist_id)?,
+            };
+            let id = uuid::Uuid::new_v4().to_string();
+            let (stored, sealed) = self.vault.seal_with(
+                project_id.as_deref(),
+                &job_aad(&id),
+                &job.operations.to_string(),
+            )?;
+            prepared.push((id, job, project_id, stored, sealed));
+        }
+
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let mut inserted = Vec::with_capacity(prepared.len());
+        for (id, job, project_id, stored, sealed) in prepared {
+            let job = PublishJob {
+                id,
+                playlist_id: job.playlist_id,
+                version_id: job.version_id,
+                server_url: job.server_url,
+                api_user: job.api_user,
+                operations: job.operations,
+                project_id,
+                locked: false,
+                status: JobStatus::Pending,
+                attempts: 0,
+                last_error: None,
+                next_attempt_at: now,
+                created_at: now,
+                updated_at: now,
+                sealed: None,
+            };
+            tx.execute(
+                "INSERT INTO publish_jobs (id, playlist_id, version_id, server_url, api_user,
+                    operations, status, next_attempt_at, created_at, updated_at,
+                    project_id, sealed)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8, ?8, ?9, ?10)",
+                params![
+                    job.id,
+                    job.playlist_id,
+                    job.version_id,
+                    job.server_url,
+                    job.api_user,
+                    stored,
+                    job.status.as_str(),
+                    now,
+                    job.project_id,
+                    sealed,
+                ],
+            )?;
+            inserted.push(job);
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    pub fn list_publish_jobs(&self) -> Result<Vec<PublishJob>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT * FROM publish_jobs ORDER BY created_at, rowid")?;
+        let jobs = stmt
+            .query_map([], PublishJob::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(jobs
+            .into_iter()
+            .map(|job| self.open_publish_job(job))
+            .collect())
+    }
+
+    pub fn publish_job_counts(&self) -> Result<JobCounts> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT status, COUNT(*) FROM publish_jobs GROUP BY status")?;
+        let mut rows = stmt.query([])?;
+        let mut counts = JobCounts::default();
+        while let Some(row) = rows.next()? {
+            let status: String = row.get(0)?;
+            let count: u32 = row.get(1)?;
+            match JobStatus::parse(&status) {
+                JobStatus::Pending => counts.pending = count,
+                JobStatus::Running => counts.running = count,
+                JobStatus::Completed => counts.completed = count,
+                JobStatus::Failed => counts.failed = count,
+                JobStatus::Cancelled => {}
+            }
+        }
+        Ok(counts)
+    }
+
+    pub fn get_publish_job(&self, id: &str) -> Result<Option<PublishJob>> {
+        let job = self
+            .conn()
+            .query_row(
+                "SELECT * FROM publish_jobs WHERE id = ?1",
+                params![id],
+                PublishJob::from_row,
+            )
+            .optional()?;
+        Ok(job.map(|job| self.open_publish_job(job)))
+    }
+
+    /// Claims the oldest pending job that is due, marking it as running. Due
+    /// jobs of locked vaults are pushed back instead.
+    pub fn claim_publish_job(&self) -> Result<Option<PublishJob>> {
+        let now = now_millis();
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let mut job = None;
+        loop {
+            let candidate = tx
+                .query_row(
+                    "SELECT * FROM publish_jobs
+                     WHERE status = 'pending' AND next_attempt_at <= ?1
+                     ORDER BY next_attempt_at, created_at, rowid LIMIT 1",
+                    params![now],
+                    PublishJob::from_row,
+                )
+                .optional()?;
+            let Some(candidate) = candidate.map(|job| self.open_publish_job(job)) else {
+                break;
+            };
+            if !candidate.locked {
+                job = Some(candidate);
+                break;
+            }
+            tx.execute(
+                "UPDATE publish_jobs SET next_attempt_at = ?2 WHERE id = ?1",
+                params![candidate.id, now + LOCKED_RETRY_MS],
+            )?;
+        }
+        let Some(mut job) = job else {
+            tx.commit()?;
+            return Ok(None);
+        };
+        job.status = JobStatus::Running;
+        job.attempts += 1;
+        job.updated_at = now;
+        tx.execute(
+            "UPDATE publish_jobs SET status = 'running', attempts = ?2, updated_at = ?3
+             WHERE id = ?1",
+            params![job.id, job.attempts, now],
+        )?;
+        tx.commit()?;
+        Ok(Some(job))
+    }
+
+    /// Earliest time a pending job becomes due, if any are waiting.
+    pub fn next_publish_attempt_at(&self) -> Result<Option<i64>> {
+        let next = self.conn().query_row(
+            "SELECT MIN(next_attempt_at) FROM publish_jobs WHERE status = 'pending'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(next)
+    }
+
+    pub fn update_publish_job(
+        &self,
+        id: &str,
+        status: JobStatus,
+        last_error: Option<&str>,
+        next_attempt_at: Option<i64>,
+    ) -> Result<()> {
+        let now = now_millis();
+        self.conn().execute(
+            "UPDATE publish_jobs SET status = ?2, last_error = ?3,
+                next_attempt_at = COALESCE(?4, next_attempt_at), updated_at = ?5
+             WHERE id = ?1",
+            params![id, status.as_str(), last_error, next_attempt_at, now],
+        )?;
+        Ok(())
+    }
+
+    /// Puts a failed or cancelled job back in the queue with a fresh attempt budget.
+    pub fn requeue_publish_job(&self, id: &str) -> Result<bool> {
+        let now = now_millis();
+        let updated = self.conn().execute(
+            "UPDATE publish_jobs SET status = 'pending', attempts = 0, last_error = NULL,
+                next_attempt_at = ?2, updated_at = ?2
+             WHERE id = ?1 AND status IN ('failed', 'cancelled')",
+            params![id, now],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn cancel_publish_job(&self, id: &str) -> Result<bool> {
+        let updated = self.conn().execute(
+            "UPDATE publish_jobs SET status = 'cancelled', updated_at = ?2
+             WHERE id = ?1 AND status = 'pending'",
+            params![id, now_millis()],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Jobs left running by a previous session never finished; queue them again.
+    pub fn recover_publish_jobs(&self) -> Result<usize> {
+        let recovered = self.conn().execute(
+            "UPDATE publish_jobs SET status = 'pending', updated_at = ?1
+             WHERE status = 'running'",
+            params![now_millis()],
+        )?;
+        Ok(recovered)
+    }
+
+    pub fn clear_finished_publish_jobs(&self) -> Result<usize> {
+        let cleared = self.conn().execute(
+            "DELETE FROM publish_jobs WHERE status IN ('completed', 'cancelled')",
+            [],
+        )?;
+        Ok(cleared)
+    }
+}
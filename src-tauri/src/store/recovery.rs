@@ -0,0 +1,128 @@
+//! Snapshots of frontend state written while the app runs, kept for
+//! restoring after a crash.
+//!
+//! A few recent snapshots are retained so a snapshot taken mid-way through a
+//! broken state is never the only one left. Snapshots of a vaulted project are
+//! sealed, and none are taken while its vault is locked.
+
+use rusqlite::{OptionalExtension, params};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::vault::SNAPSHOT_AAD;
+use super::{Store, now_millis};
+use crate::error::Result;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE recovery_snapshots (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        state      TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+";
+
+const KEEP_SNAPSHOTS: i64 = 5;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoverySnapshot {
+    pub id: i64,
+    /// Null while `locked`.
+    pub state: Value,
+    pub project_id: Option<String>,
+    /// Set while the snapshot's vault is locked.
+    pub locked: bool,
+    pub created_at: i64,
+}
+
+impl Store {
+    /// Returns `None` without saving while the project's vault is locked.
+    pub fn save_recovery_snapshot(
+        &self,
+        state: &str,
+        project_id: Option<&str>,
+    ) -> Result<Option<i64>> {
+        if self.vault.is_locked(project_id) {
+            return Ok(None);
+        }
+        let (state, sealed) = self.vault.seal_with(project_id, SNAPSHOT_AAD, state)?;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO recovery_snapshots (state, created_at, project_id, sealed)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![state, now_millis(), project_id, sealed],
+        )?;
+        let id = tx.last_insert_rowid();
+        tx.execute(
+            "DELETE FROM recovery_snapshots WHERE id <= ?1",
+            params![id - KEEP_SNAPSHOTS],
+        )?;
+        tx.commit()?;
+        Ok(Some(id))
+    }
+
+    /// The newest snapshot whose state still parses. A sealed snapshot of a
+    /// locked vault is returned as `locked`, to be fetched again once unlocked.
+    pub fn latest_recovery_snapshot(&self) -> Result<Option<RecoverySnapshot>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, state, created_at, project_id, sealed FROM recovery_snapshots
+             ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, bool>(4)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, state, created_at, project_id, sealed) = row?;
+            if sealed && self.vault.is_locked(project_id.as_deref()) {
+                return Ok(Some(RecoverySnapshot {
+                    id,
+                    state: Value::Null,
+                    project_id,
+                    locked: true,
+                    created_at,
+                }));
+            }
+            let Some(state) =
+                self.vault
+                    .open_with(project_id.as_deref(), SNAPSHOT_AAD, &state, sealed)
+            else {
+                log::warn!("Skipping recovery snapshot {id}, which does not open with its key");
+                continue;
+            };
+            match serde_json::from_str(&state) {
+                Ok(state) => {
+                    return Ok(Some(RecoverySnapshot {
+                        id,
+                        state,
+                        project_id,
+                        locked: false,
+                        created_at,
+                    }));
+                }
+                Err(err) => log::warn!("Skipping unreadable recovery snapshot {id}: {err}"),
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn clear_recovery_snapshots(&self) -> Result<()> {
+        self.conn().execute("DELETE FROM recovery_snapshots", [])?;
+        Ok(())
+    }
+
+    pub fn has_recovery_snapshot(&self) -> Result<bool> {
+        let found = self
+            .conn()
+            .query_row("SELECT 1 FROM recovery_snapshots LIMIT 1", [], |_| Ok(()))
+            .optional()?;
+        Ok(found.is_some())
+    }
+}
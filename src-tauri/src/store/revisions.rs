@@ -0,0 +1,235 @@
+//! Saved revisions of each note draft, so deleted text can be recovered.
+//!
+//! Drafts have no note ID until they are published, so revisions are keyed
+//! by playlist and version like the drafts themselves.
+
+use rusqlite::{OptionalExtension, Row, params};
+use serde::Serialize;
+use tauri::State;
+
+use super::{Store, now_millis};
+use crate::error::{Error, Result};
+use crate::text_diff::{DiffSpan, SpanKind, word_diff};
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE draft_revisions (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        playlist_id TEXT NOT NULL,
+        version_id  TEXT NOT NULL,
+        content     TEXT NOT NULL,
+        label_id    TEXT,
+        published   INTEGER NOT NULL DEFAULT 0,
+        created_at  INTEGER NOT NULL
+    );
+    CREATE INDEX draft_revisions_draft ON draft_revisions (playlist_id, version_id, id);
+";
+
+/// Oldest unpublished revisions beyond this many per draft are discarded.
+const MAX_REVISIONS: u32 = 200;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    pub id: i64,
+    pub playlist_id: String,
+    pub version_id: String,
+    pub content: String,
+    pub label_id: Option<String>,
+    /// Whether this revision was sent to ftrack.
+    pub published: bool,
+    pub created_at: i64,
+    /// The revision is in a locked vault; `content` is empty until it is unlocked.
+    pub locked: bool,
+}
+
+impl Revision {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<(Self, Option<String>, bool)> {
+        let revision = Self {
+            id: row.get("id")?,
+            playlist_id: row.get("playlist_id")?,
+            version_id: row.get("version_id")?,
+            content: row.get("content")?,
+            label_id: row.get("label_id")?,
+            published: row.get("published")?,
+            created_at: row.get("created_at")?,
+            locked: false,
+        };
+        Ok((revision, row.get("project_id")?, row.get("sealed")?))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionDiff {
+    pub from: Revision,
+    pub to: Revision,
+    pub spans: Vec<DiffSpan>,
+    pub inserted_words: usize,
+    pub deleted_words: usize,
+}
+
+impl Store {
+    /// Opens the text of a revision read with [`Revision::from_row`].
+    fn open_revision(
+        &self,
+        (mut revision, project_id, sealed): (Revision, Option<String>, bool),
+    ) -> Revision {
+        match self.vault.open(
+            project_id.as_deref(),
+            &revision.playlist_id,
+            &revision.version_id,
+            &revision.content,
+            sealed,
+        ) {
+            Some(content) => revision.content = content,
+            None => {
+                revision.content.clear();
+                revision.locked = true;
+            }
+        }
+        revision
+    }
+
+    /// Records `content` as a new revision unless it matches the latest one.
+    pub(super) fn record_revision(
+        &self,
+        playlist_id: &str,
+        version_id: &str,
+        content: &str,
+        label_id: Option<&str>,
+        project_id: Option<&str>,
+    ) -> Result<()> {
+        if content.is_empty() {
+            return Ok(());
+        }
+        let (stored, sealed) = self
+            .vault
+            .seal(project_id, playlist_id, version_id, content)?;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let latest = tx
+            .query_row(
+                "SELECT * FROM draft_revisions
+                 WHERE playlist_id = ?1 AND version_id = ?2
+                 ORDER BY id DESC LIMIT 1",
+                params![playlist_id, version_id],
+                Revision::from_row,
+            )
+            .optional()?
+            .map(|latest| self.open_revision(latest));
+        if latest.is_some_and(|latest| {
+            !latest.locked && latest.content == content && latest.label_id.as_deref() == label_id
+        }) {
+            return Ok(());
+        }
+        tx.execute(
+            "INSERT INTO draft_revisions (playlist_id, version_id, content, label_id, created_at,
+                project_id, sealed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                playlist_id,
+                version_id,
+                stored,
+                label_id,
+                now_millis(),
+                project_id,
+                sealed
+            ],
+        )?;
+        tx.execute(
+            "DELETE FROM draft_revisions WHERE id IN (
+                SELECT id FROM draft_revisions
+                WHERE playlist_id = ?1 AND version_id = ?2 AND published = 0
+                ORDER BY id DESC LIMIT -1 OFFSET ?3
+             )",
+            params![playlist_id, version_id, MAX_REVISIONS],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Flags the latest revision of a draft as the one that was published.
+    pub(super) fn mark_revision_published(
+        &self,
+        playlist_id: &str,
+        version_id: &str,
+    ) -> Result<()> {
+        self.conn().execute(
+            "UPDATE draft_revisions SET published = 1 WHERE id = (
+                SELECT MAX(id) FROM draft_revisions WHERE playlist_id = ?1 AND version_id = ?2
+             )",
+            params![playlist_id, version_id],
+        )?;
+        Ok(())
+    }
+
+    /// Lists revisions of one draft, newest first.
+    pub fn list_revisions(&self, playlist_id: &str, version_id: &str) -> Result<Vec<Revision>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM draft_revisions
+             WHERE playlist_id = ?1 AND version_id = ?2
+             ORDER BY id DESC",
+        )?;
+        let revisions = stmt
+            .query_map(params![playlist_id, version_id], Revision::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(revisions
+            .into_iter()
+            .map(|revision| self.open_revision(revision))
+            .collect())
+    }
+
+    pub fn get_revision(&self, id: i64) -> Result<Option<Revision>> {
+        let revision = self
+            .conn()
+            .query_row(
+                "SELECT * FROM draft_revisions WHERE id = ?1",
+                params![id],
+                Revision::from_row,
+            )
+            .optional()?;
+        Ok(revision.map(|revision| self.open_revision(revision)))
+    }
+}
+
+fn count_words(spans: &[DiffSpan], kind: SpanKind) -> usize {
+    spans
+        .iter()
+        .filter(|span| span.kind == kind)
+        .map(|span| span.text.split_whitespace().count())
+        .sum()
+}
+
+#[tauri::command]
+pub fn list_note_revisions(
+    store: State<'_, Store>,
+    playlist_id: String,
+    version_id: String,
+) -> Result<Vec<Revision>> {
+    store.list_revisions(&playlist_id, &version_id)
+}
+
+/// Word-level diff from revision `a` to revision `b`.
+#[tauri::command]
+pub fn diff_note_revisions(store: State<'_, Store>, a: i64, b: i64) -> Result<RevisionDiff> {
+    let revision = |id| {
+        store
+            .get_revision(id)?
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown revision {id}")))
+    };
+    let (from, to) = (revision(a)?, revision(b)?);
+    if from.locked || to.locked {
+        return Err(Error::InvalidInput(
+            "Unlock the project's vault to compare these revisions".into(),
+        ));
+    }
+    let spans = word_diff(&from.content, &to.content);
+    Ok(RevisionDiff {
+        inserted_words: count_words(&spans, SpanKind::Insert),
+        deleted_words: count_words(&spans, SpanKind::Delete),
+        from,
+        to,
+        spans,
+    })
+}
@@ -0,0 +1,119 @@
+//! Reusable note templates.
+
+use rusqlite::{OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+
+use super::{Store, now_millis};
+use crate::error::Result;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE note_templates (
+        id          TEXT PRIMARY KEY,
+        name        TEXT NOT NULL UNIQUE,
+        description TEXT,
+        body        TEXT NOT NULL,
+        strict      INTEGER NOT NULL DEFAULT 0,
+        created_at  INTEGER NOT NULL,
+        updated_at  INTEGER NOT NULL
+    );
+";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub body: String,
+    /// Refuse to expand when a token has no value, instead of leaving it in place.
+    pub strict: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// A template as created or edited by the user. Saving with an existing
+/// `id` updates it, saving with an existing `name` replaces that template.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateDraft {
+    #[serde(default, skip_serializing)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub body: String,
+    #[serde(default)]
+    pub strict: bool,
+}
+
+impl NoteTemplate {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            description: row.get("description")?,
+            body: row.get("body")?,
+            strict: row.get("strict")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+impl Store {
+    pub fn save_template(&self, draft: &TemplateDraft) -> Result<NoteTemplate> {
+        let now = now_millis();
+        let id = match &draft.id {
+            Some(id) => id.clone(),
+            None => self
+                .conn()
+                .query_row(
+                    "SELECT id FROM note_templates WHERE name = ?1",
+                    params![draft.name],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        };
+        self.conn().execute(
+            "INSERT INTO note_templates (id, name, description, body, strict, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+             ON CONFLICT (id) DO UPDATE SET
+                name = excluded.name,
+                description = excluded.description,
+                body = excluded.body,
+                strict = excluded.strict,
+                updated_at = excluded.updated_at",
+            params![id, draft.name, draft.description, draft.body, draft.strict, now],
+        )?;
+        Ok(self.get_template(&id)?.expect("template was just saved"))
+    }
+
+    pub fn get_template(&self, id: &str) -> Result<Option<NoteTemplate>> {
+        let template = self
+            .conn()
+            .query_row(
+                "SELECT * FROM note_templates WHERE id = ?1",
+                params![id],
+                NoteTemplate::from_row,
+            )
+            .optional()?;
+        Ok(template)
+    }
+
+    pub fn list_templates(&self) -> Result<Vec<NoteTemplate>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT * FROM note_templates ORDER BY name COLLATE NOCASE")?;
+        let templates = stmt
+            .query_map([], NoteTemplate::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(templates)
+    }
+
+    pub fn delete_template(&self, id: &str) -> Result<bool> {
+        let deleted = self
+            .conn()
+            .execute("DELETE FROM note_templates WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,485 @@
+//! Optional per-project encryption of draft text at rest.
+//!
+//! A vault's random 256-bit key lives in the platform keychain, never in the
+//! database. Draft and revision text for the project is sealed with
+//! ChaCha20-Poly1305 before it reaches SQLite, bound to its playlist and
+//! version so sealed text cannot be moved between drafts. Vaults start
+//! locked: until unlocked, their drafts are listed with empty content and
+//! `locked` set, and saving into them fails.
+//!
+//! Recovery snapshots and queued publish jobs of a vaulted project are sealed
+//! the same way, and its notes are kept out of the search index.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use tauri::State;
+
+use super::{Store, now_millis};
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::search::SearchIndex;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE vaults (
+        project_id  TEXT PRIMARY KEY,
+        check_value TEXT NOT NULL,
+        created_at  INTEGER NOT NULL
+    );
+    ALTER TABLE drafts ADD COLUMN project_id TEXT;
+    ALTER TABLE drafts ADD COLUMN sealed INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE draft_revisions ADD COLUMN project_id TEXT;
+    ALTER TABLE draft_revisions ADD COLUMN sealed INTEGER NOT NULL DEFAULT 0;
+";
+
+/// Draft text also reaches these tables, so they are sealed too.
+pub(super) const SEALED_SCHEMA: &str = "
+    ALTER TABLE recovery_snapshots ADD COLUMN project_id TEXT;
+    ALTER TABLE recovery_snapshots ADD COLUMN sealed INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE publish_jobs ADD COLUMN project_id TEXT;
+    ALTER TABLE publish_jobs ADD COLUMN sealed INTEGER NOT NULL DEFAULT 0;
+";
+
+const KEY_LEN: usize = 32;
+/// Sealed with the key on creation, to tell a wrong key from damaged rows.
+const CHECK_TEXT: &str = "astranotes-vault";
+
+type Key = [u8; KEY_LEN];
+
+/// Vault projects, with the key of each unlocked one.
+pub(super) struct Keys {
+    vaults: Mutex<HashMap<String, Option<Key>>>,
+}
+
+impl Keys {
+    pub(super) fn load(conn: &Connection) -> Result<Self> {
+        let mut stmt = conn.prepare("SELECT project_id FROM vaults")?;
+        let vaults = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|project| project.map(|project| (project, None)))
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Self {
+            vaults: Mutex::new(vaults),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Option<Key>>> {
+        self.vaults
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The key to seal text with, `None` for projects without a vault.
+    fn key_for(&self, project_id: Option<&str>) -> Result<Option<Key>> {
+        let Some(project_id) = project_id else {
+            return Ok(None);
+        };
+        match self.lock().get(project_id) {
+            None => Ok(None),
+            Some(Some(key)) => Ok(Some(*key)),
+            Some(None) => Err(locked(project_id)),
+        }
+    }
+
+    /// Seals `text` if the project has a vault. Returns the stored text and
+    /// whether it is sealed.
+    pub(super) fn seal(
+        &self,
+        project_id: Option<&str>,
+        playlist_id: &str,
+        version_id: &str,
+        text: &str,
+    ) -> Result<(String, bool)> {
+        self.seal_with(project_id, &aad(playlist_id, version_id), text)
+    }
+
+    /// [`Self::seal`] for text not bound to one draft.
+    pub(super) fn seal_with(
+        &self,
+        project_id: Option<&str>,
+        aad: &str,
+        text: &str,
+    ) -> Result<(String, bool)> {
+        match self.key_for(project_id)? {
+            Some(key) => Ok((seal(&key, aad, text)?, true)),
+            None => Ok((text.to_string(), false)),
+        }
+    }
+
+    /// Opens stored text. Returns `None` while the vault is locked, or if
+    /// the text does not open with its key.
+    pub(super) fn open(
+        &self,
+        project_id: Option<&str>,
+        playlist_id: &str,
+        version_id: &str,
+        text: &str,
+        sealed: bool,
+    ) -> Option<String> {
+        let opened = self.open_with(project_id, &aad(playlist_id, version_id), text, sealed);
+        if sealed && opened.is_none() && self.is_unlocked(project_id) {
+            log::warn!("Sealed draft for version {version_id} does not open with its vault key");
+        }
+        opened
+    }
+
+    /// [`Self::open`] for text sealed with [`Self::seal_with`].
+    pub(super) fn open_with(
+        &self,
+        project_id: Option<&str>,
+        aad: &str,
+        text: &str,
+        sealed: bool,
+    ) -> Option<String> {
+        if !sealed {
+            return Some(text.to_string());
+        }
+        let key = (*self.lock().get(project_id?)?)?;
+        open(&key, aad, text)
+    }
+
+    fn is_unlocked(&self, project_id: Option<&str>) -> bool {
+        project_id.is_some_and(|id| matches!(self.lock().get(id), Some(Some(_))))
+    }
+
+    /// Whether the project has a vault that is locked.
+    pub(super) fn is_locked(&self, project_id: Option<&str>) -> bool {
+        project_id.is_some_and(|id| matches!(self.lock().get(id), Some(None)))
+    }
+}
+
+fn locked(project_id: &str) -> Error {
+    Error::InvalidInput(format!("The vault for project {project_id} is locked"))
+}
+
+fn credential_key(project_id: &str) -> String {
+    format!("vault-{project_id}")
+}
+
+fn aad(playlist_id: &str, version_id: &str) -> String {
+    format!("{playlist_id}\n{version_id}")
+}
+
+fn cipher(key: &Key) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, key).expect("key has the cipher's length"))
+}
+
+fn crypto_error(_: ring::error::Unspecified) -> Error {
+    Error::InvalidInput("Encryption failed".into())
+}
+
+/// Returns base64 of a random nonce followed by the ciphertext and tag.
+fn seal(key: &Key, aad: &str, text: &str) -> Result<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(crypto_error)?;
+    let mut sealed = text.as_bytes().to_vec();
+    cipher(key)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(crypto_error)?;
+    let mut out = nonce.to_vec();
+    out.append(&mut sealed);
+    Ok(STANDARD.encode(out))
+}
+
+fn open(key: &Key, aad: &str, sealed: &str) -> Option<String> {
+    let mut sealed = STANDARD.decode(sealed).ok()?;
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let mut ciphertext = sealed.split_off(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(&sealed).ok()?;
+    let text = cipher(key)
+        .open_in_place(nonce, Aad::from(aad.as_bytes()), &mut ciphertext)
+        .ok()?;
+    String::from_utf8(text.to_vec()).ok()
+}
+
+fn check_aad(project_id: &str) -> String {
+    format!("vault\n{project_id}")
+}
+
+pub(super) fn job_aad(job_id: &str) -> String {
+    format!("publish\n{job_id}")
+}
+
+pub(super) const SNAPSHOT_AAD: &str = "recovery";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatus {
+    pub project_id: String,
+    pub locked: bool,
+    /// Drafts stored in the vault.
+    pub drafts: u32,
+    pub created_at: i64,
+}
+
+/// Tables holding a project's text: the text column, and SQL for the
+/// associated data each row is sealed with, matching `aad`, `job_aad` and
+/// `SNAPSHOT_AAD`.
+const SEALED_TABLES: [(&str, &str, &str); 4] = [
+    ("drafts", "content", "playlist_id || char(10) || version_id"),
+    (
+        "draft_revisions",
+        "content",
+        "playlist_id || char(10) || version_id",
+    ),
+    ("publish_jobs", "operations", "'publish' || char(10) || id"),
+    ("recovery_snapshots", "state", "'recovery'"),
+];
+
+/// Rewrites the stored text of a project through `map`, which is given each
+/// row's associated data and text.
+fn reseal(
+    tx: &rusqlite::Transaction<'_>,
+    project_id: &str,
+    sealed: bool,
+    map: impl Fn(&str, &str) -> Result<String>,
+) -> Result<()> {
+    for (table, column, aad) in SEALED_TABLES {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT rowid, {aad}, {column} FROM {table} WHERE project_id = ?1 AND sealed = ?2"
+        ))?;
+        let rows = stmt
+            .query_map(params![project_id, !sealed], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (rowid, aad, text) in rows {
+            let text = map(&aad, &text)?;
+            tx.execute(
+                &format!("UPDATE {table} SET {column} = ?2, sealed = ?3 WHERE rowid = ?1"),
+                params![rowid, text, sealed],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Assigns rows saved without a project to it before sealing: those of
+/// `playlist_ids`, and of playlists that already have drafts in the project.
+/// Snapshots saved without a project may hold its drafts and are dropped.
+fn adopt(tx: &rusqlite::Transaction<'_>, project_id: &str, playlist_ids: &[String]) -> Result<()> {
+    for table in ["drafts", "draft_revisions", "publish_jobs"] {
+        tx.execute(
+            &format!(
+                "UPDATE {table} SET project_id = ?1 WHERE project_id IS NULL AND playlist_id IN
+                    (SELECT playlist_id FROM drafts WHERE project_id = ?1)"
+            ),
+            params![project_id],
+        )?;
+        for playlist_id in playlist_ids {
+            tx.execute(
+                &format!(
+                    "UPDATE {table} SET project_id = ?1
+                     WHERE project_id IS NULL AND playlist_id = ?2"
+                ),
+                params![project_id, playlist_id],
+            )?;
+        }
+    }
+    tx.execute(
+        "DELETE FROM recovery_snapshots WHERE project_id IS NULL",
+        [],
+    )?;
+    Ok(())
+}
+
+impl Store {
+    pub fn list_vaults(&self) -> Result<Vec<VaultStatus>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT v.project_id, v.created_at,
+                (SELECT COUNT(*) FROM drafts d WHERE d.project_id = v.project_id) AS drafts
+             FROM vaults v ORDER BY v.created_at",
+        )?;
+        let keys = self.vault.lock();
+        let vaults = stmt
+            .query_map([], |row| {
+                let project_id: String = row.get("project_id")?;
+                Ok(VaultStatus {
+                    locked: !matches!(keys.get(&project_id), Some(Some(_))),
+                    project_id,
+                    drafts: row.get("drafts")?,
+                    created_at: row.get("created_at")?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(vaults)
+    }
+
+    /// Creates a vault for a project and seals its existing drafts, first
+    /// adopting drafts of `playlist_ids` that were saved without a project.
+    /// The vault is left unlocked.
+    pub fn enable_vault(&self, project_id: &str, playlist_ids: &[String]) -> Result<()> {
+        if self.vault.lock().contains_key(project_id) {
+            return Err(Error::InvalidInput(format!(
+                "Project {project_id} already has a vault"
+            )));
+        }
+        let mut key = [0u8; KEY_LEN];
+        SystemRandom::new().fill(&mut key).map_err(crypto_error)?;
+        let check_value = seal(&key, &check_aad(project_id), CHECK_TEXT)?;
+
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO vaults (project_id, check_value, created_at) VALUES (?1, ?2, ?3)",
+            params![project_id, check_value, now_millis()],
+        )?;
+        adopt(&tx, project_id, playlist_ids)?;
+        reseal(&tx, project_id, true, |aad, text| seal(&key, aad, text))?;
+        // Stored last so a failed insert leaves no orphaned key behind
+        credentials::store(&credential_key(project_id), &STANDARD.encode(key))?;
+        if let Err(err) = tx.commit() {
+            if let Err(err) = credentials::delete(&credential_key(project_id)) {
+                log::warn!("Failed to remove the key of an unsaved vault: {err}");
+            }
+            return Err(err.into());
+        }
+        self.vault.lock().insert(project_id.to_string(), Some(key));
+        log::info!("Enabled the draft vault for project {project_id}");
+        Ok(())
+    }
+
+    /// Decrypts a project's drafts back to plain text and deletes its vault
+    /// key. The vault must be unlocked.
+    pub fn disable_vault(&self, project_id: &str) -> Result<()> {
+        let key = self
+            .vault
+            .key_for(Some(project_id))?
+            .ok_or_else(|| Error::InvalidInput(format!("Project {project_id} has no vault")))?;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        reseal(&tx, project_id, false, |aad, text| {
+            open(&key, aad, text).ok_or_else(|| {
+                Error::InvalidInput(format!(
+                    "Stored text of project {project_id} does not open with the vault key"
+                ))
+            })
+        })?;
+        tx.execute(
+            "DELETE FROM vaults WHERE project_id = ?1",
+            params![project_id],
+        )?;
+        tx.commit()?;
+        self.vault.lock().remove(project_id);
+        credentials::delete(&credential_key(project_id))?;
+        log::info!("Disabled the draft vault for project {project_id}");
+        Ok(())
+    }
+
+    /// Fetches the vault key from the keychain, which may ask the user to
+    /// confirm, and keeps it in memory until the vault is locked.
+    pub fn unlock_vault(&self, project_id: &str) -> Result<()> {
+        if !self.vault.lock().contains_key(project_id) {
+            return Err(Error::InvalidInput(format!(
+                "Project {project_id} has no vault"
+            )));
+        }
+        let missing = || {
+            Error::InvalidInput(format!(
+                "The key for the vault of project {project_id} is missing from the keychain"
+            ))
+        };
+        let encoded = credentials::get(&credential_key(project_id))?.ok_or_else(missing)?;
+        let key: Key = STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(missing)?;
+        let check_value: String = self.conn().query_row(
+            "SELECT check_value FROM vaults WHERE project_id = ?1",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+        if open(&key, &check_aad(project_id), &check_value).as_deref() != Some(CHECK_TEXT) {
+            return Err(Error::InvalidInput(format!(
+                "The keychain holds the wrong key for the vault of project {project_id}"
+            )));
+        }
+        self.vault.lock().insert(project_id.to_string(), Some(key));
+        Ok(())
+    }
+
+    /// The vaulted project a playlist's drafts belong to, if any.
+    pub fn vault_project(&self, playlist_id: &str) -> Result<Option<String>> {
+        let project = self
+            .conn()
+            .query_row(
+                "SELECT d.project_id FROM drafts d JOIN vaults v ON v.project_id = d.project_id
+                 WHERE d.playlist_id = ?1 LIMIT 1",
+                params![playlist_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(project)
+    }
+
+    /// Playlists with drafts in a project.
+    pub fn project_playlists(&self, project_id: &str) -> Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT DISTINCT playlist_id FROM drafts WHERE project_id = ?1")?;
+        let playlists = stmt
+            .query_map(params![project_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(playlists)
+    }
+
+    /// Forgets the key of one vault, or of every vault with `None`.
+    pub fn lock_vault(&self, project_id: Option<&str>) {
+        let mut vaults = self.vault.lock();
+        for (project, key) in vaults.iter_mut() {
+            if project_id.is_none_or(|id| id == project) {
+                *key = None;
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_vaults(store: State<'_, Store>) -> Result<Vec<VaultStatus>> {
+    store.list_vaults()
+}
+
+#[tauri::command]
+pub fn enable_vault(
+    store: State<'_, Store>,
+    index: State<'_, SearchIndex>,
+    project_id: String,
+    playlist_ids: Option<Vec<String>>,
+) -> Result<()> {
+    store.enable_vault(&project_id, playlist_ids.as_deref().unwrap_or_default())?;
+    // Notes indexed before the vault existed would stay searchable in plain text
+    index.remove_playlists(&store.project_playlists(&project_id)?)
+}
+
+#[tauri::command]
+pub fn disable_vault(store: State<'_, Store>, project_id: String) -> Result<()> {
+    store.disable_vault(&project_id)
+}
+
+#[tauri::command]
+pub fn unlock_vault(store: State<'_, Store>, project_id: String) -> Result<()> {
+    store.unlock_vault(&project_id)
+}
+
+#[tauri::command]
+pub fn lock_vault(store: State<'_, Store>, project_id: Option<String>) {
+    store.lock_vault(project_id.as_deref());
+}
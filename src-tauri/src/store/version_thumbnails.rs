@@ -0,0 +1,113 @@
+//! Thumbnail component and perceptual hashes for each version in a playlist.
+
+use std::collections::HashSet;
+
+use rusqlite::{Row, params};
+use serde::Deserialize;
+
+use super::{Store, now_millis};
+use crate::error::Result;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE version_thumbnails (
+        playlist_id  TEXT NOT NULL,
+        version_id   TEXT NOT NULL,
+        component_id TEXT NOT NULL,
+        dhash        INTEGER,
+        phash        INTEGER,
+        updated_at   INTEGER NOT NULL,
+        PRIMARY KEY (playlist_id, version_id)
+    );
+";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionThumbnail {
+    pub version_id: String,
+    pub component_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct HashedThumbnail {
+    pub version_id: String,
+    pub component_id: String,
+    /// `(dhash, phash)`, once computed from the cached thumbnail.
+    pub hashes: Option<(u64, u64)>,
+}
+
+impl HashedThumbnail {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let dhash: Option<i64> = row.get("dhash")?;
+        let phash: Option<i64> = row.get("phash")?;
+        Ok(Self {
+            version_id: row.get("version_id")?,
+            component_id: row.get("component_id")?,
+            // SQLite integers are signed; the bits are what matter
+            hashes: dhash.zip(phash).map(|(d, p)| (d as u64, p as u64)),
+        })
+    }
+}
+
+impl Store {
+    /// Replaces the versions recorded for `playlist_id`. Hashes are kept for
+    /// versions whose thumbnail component did not change.
+    pub fn set_version_thumbnails(
+        &self,
+        playlist_id: &str,
+        versions: &[VersionThumbnail],
+    ) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let now = now_millis();
+        for version in versions {
+            tx.execute(
+                "INSERT INTO version_thumbnails (playlist_id, version_id, component_id, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (playlist_id, version_id) DO UPDATE SET
+                    dhash = CASE WHEN component_id = excluded.component_id THEN dhash END,
+                    phash = CASE WHEN component_id = excluded.component_id THEN phash END,
+                    component_id = excluded.component_id,
+                    updated_at = excluded.updated_at",
+                params![playlist_id, version.version_id, version.component_id, now],
+            )?;
+        }
+        let current: HashSet<&str> = versions.iter().map(|v| v.version_id.as_str()).collect();
+        let stale = {
+            let mut stmt =
+                tx.prepare("SELECT version_id FROM version_thumbnails WHERE playlist_id = ?1")?;
+            stmt.query_map(params![playlist_id], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?
+                .into_iter()
+                .filter(|version_id| !current.contains(version_id.as_str()))
+                .collect::<Vec<_>>()
+        };
+        for version_id in stale {
+            tx.execute(
+                "DELETE FROM version_thumbnails WHERE playlist_id = ?1 AND version_id = ?2",
+                params![playlist_id, version_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn list_version_thumbnails(&self, playlist_id: &str) -> Result<Vec<HashedThumbnail>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM version_thumbnails WHERE playlist_id = ?1 ORDER BY version_id",
+        )?;
+        let rows = stmt
+            .query_map(params![playlist_id], HashedThumbnail::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Stores hashes for every version using `component_id`, in any playlist.
+    pub fn set_thumbnail_hashes(&self, component_id: &str, dhash: u64, phash: u64) -> Result<()> {
+        self.conn().execute(
+            "UPDATE version_thumbnails SET dhash = ?2, phash = ?3 WHERE component_id = ?1",
+            params![component_id, dhash as i64, phash as i64],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+//! Directories watched for newly landed renders and deliveries.
+
+use rusqlite::{Row, params};
+use serde::Serialize;
+
+use super::{Store, now_millis};
+use crate::error::Result;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE watches (
+        id         TEXT PRIMARY KEY,
+        path       TEXT NOT NULL,
+        pattern    TEXT,
+        recursive  INTEGER NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Watch {
+    pub id: String,
+    pub path: String,
+    /// Regex matched against file names; `None` reports every new file.
+    pub pattern: Option<String>,
+    pub recursive: bool,
+    pub created_at: i64,
+}
+
+impl Watch {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            path: row.get("path")?,
+            pattern: row.get("pattern")?,
+            recursive: row.get("recursive")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+impl Store {
+    pub fn insert_watch(
+        &self,
+        path: &str,
+        pattern: Option<&str>,
+        recursive: bool,
+    ) -> Result<Watch> {
+        let watch = Watch {
+            id: uuid::Uuid::new_v4().to_string(),
+            path: path.to_string(),
+            pattern: pattern.map(str::to_string),
+            recursive,
+            created_at: now_millis(),
+        };
+        self.conn().execute(
+            "INSERT INTO watches (id, path, pattern, recursive, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                watch.id,
+                watch.path,
+                watch.pattern,
+                watch.recursive,
+                watch.created_at
+            ],
+        )?;
+        Ok(watch)
+    }
+
+    pub fn list_watches(&self) -> Result<Vec<Watch>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT * FROM watches ORDER BY created_at")?;
+        let watches = stmt
+            .query_map([], Watch::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(watches)
+    }
+
+    pub fn delete_watch(&self, id: &str) -> Result<bool> {
+        let deleted = self
+            .conn()
+            .execute("DELETE FROM watches WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,267 @@
+//! Conflict detection and three-way merge for edited notes.
+//!
+//! When an existing note is opened for editing, the frontend records the
+//! server content as its base. Note mutations carry that base as their
+//! expected `content`, so the replay worker spots notes that someone else
+//! changed in the meantime. The conflict is then shown as a word-level merge
+//! of base, ours and theirs, with non-overlapping edits already combined.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use similar::DiffOp;
+use tauri::{AppHandle, State};
+
+use super::{SyncEngine, emit_status};
+use crate::error::{Error, Result};
+use crate::store::Store;
+use crate::store::mutations::{Mutation, MutationStatus, NewMutation};
+use crate::text_diff::{diff_ops, tokenize};
+
+const NOTE_ENTITY: &str = "Note";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Side {
+    Ours,
+    Theirs,
+    /// Both sides made the same change.
+    Both,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum MergeChunk {
+    Stable {
+        text: String,
+    },
+    Changed {
+        side: Side,
+        base: String,
+        text: String,
+    },
+    Conflict {
+        base: String,
+        ours: String,
+        theirs: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteConflict {
+    pub note_id: String,
+    pub mutation_id: String,
+    pub base: String,
+    pub ours: String,
+    /// `None` when the note was deleted on the server.
+    pub theirs: Option<String>,
+    pub chunks: Vec<MergeChunk>,
+    /// The merged content, when no chunk is a real conflict.
+    pub merged: Option<String>,
+}
+
+/// For each base token, the index of the matching token in `other`.
+fn matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut matched = vec![None; base.len()];
+    for op in diff_ops(base, other) {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for offset in 0..len {
+                matched[old_index + offset] = Some(new_index + offset);
+            }
+        }
+    }
+    matched
+}
+
+pub fn merge(base: &str, ours: &str, theirs: &str) -> Vec<MergeChunk> {
+    let (base, ours, theirs) = (tokenize(base), tokenize(ours), tokenize(theirs));
+    let (ours_matches, theirs_matches) = (matches(&base, &ours), matches(&base, &theirs));
+
+    let mut chunks = Vec::new();
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        // Next base token kept, in place, by both sides
+        let anchor =
+            (i..base.len()).find_map(|index| match (ours_matches[index], theirs_matches[index]) {
+                (Some(o), Some(t)) if o >= j && t >= k => Some((index, o, t)),
+                _ => None,
+            });
+        let (next_i, next_j, next_k) = anchor.unwrap_or((base.len(), ours.len(), theirs.len()));
+
+        let base_text = base[i..next_i].concat();
+        let ours_text = ours[j..next_j].concat();
+        let theirs_text = theirs[k..next_k].concat();
+        if !(base_text.is_empty() && ours_text.is_empty() && theirs_text.is_empty()) {
+            chunks.push(if ours_text == theirs_text {
+                MergeChunk::Changed {
+                    side: Side::Both,
+                    base: base_text,
+                    text: ours_text,
+                }
+            } else if ours_text == base_text {
+                MergeChunk::Changed {
+                    side: Side::Theirs,
+                    base: base_text,
+                    text: theirs_text,
+                }
+            } else if theirs_text == base_text {
+                MergeChunk::Changed {
+                    side: Side::Ours,
+                    base: base_text,
+                    text: ours_text,
+                }
+            } else {
+                MergeChunk::Conflict {
+                    base: base_text,
+                    ours: ours_text,
+                    theirs: theirs_text,
+                }
+            });
+        }
+
+        let Some((anchor_i, _, _)) = anchor else {
+            break;
+        };
+        match chunks.last_mut() {
+            Some(MergeChunk::Stable { text }) => text.push_str(base[anchor_i]),
+            _ => chunks.push(MergeChunk::Stable {
+                text: base[anchor_i].to_string(),
+            }),
+        }
+        (i, j, k) = (next_i + 1, next_j + 1, next_k + 1);
+    }
+    chunks
+}
+
+fn merged_text(chunks: &[MergeChunk]) -> Option<String> {
+    chunks.iter().try_fold(String::new(), |mut merged, chunk| {
+        match chunk {
+            MergeChunk::Stable { text } | MergeChunk::Changed { text, .. } => merged.push_str(text),
+            MergeChunk::Conflict { .. } => return None,
+        }
+        Some(merged)
+    })
+}
+
+/// The `content` written by the first update in `operations`.
+fn updated_content(operations: &Value) -> Option<&str> {
+    operations.as_array()?.iter().find_map(|operation| {
+        (operation.get("action")?.as_str()? == "update")
+            .then(|| operation.get("data")?.get("content")?.as_str())
+            .flatten()
+    })
+}
+
+/// Uses the recorded base as the expected content of a note edit, unless
+/// the frontend already set its own expectations.
+pub(super) fn attach_base(store: &Store, mutation: &mut NewMutation) -> Result<()> {
+    if mutation.entity_type != NOTE_ENTITY
+        || mutation.expected.is_some()
+        || updated_content(&mutation.operations).is_none()
+    {
+        return Ok(());
+    }
+    if let Some(base) = store.get_note_base(&mutation.entity_id)? {
+        let mut expected = Map::new();
+        expected.insert("content".into(), Value::String(base));
+        mutation.expected = Some(expected);
+    }
+    Ok(())
+}
+
+/// Forgets the base once the edit made from it has been applied.
+pub(super) fn applied(store: &Store, mutation: &Mutation) -> Result<()> {
+    if mutation.entity_type == NOTE_ENTITY {
+        store.delete_note_base(&mutation.entity_id)?;
+    }
+    Ok(())
+}
+
+fn conflicted_mutation(store: &Store, note_id: &str) -> Result<Option<Mutation>> {
+    Ok(store.list_mutations()?.into_iter().find(|mutation| {
+        mutation.status == MutationStatus::Conflict
+            && mutation.entity_type == NOTE_ENTITY
+            && mutation.entity_id == note_id
+    }))
+}
+
+fn note_conflict(mutation: Mutation) -> Option<NoteConflict> {
+    let base = mutation
+        .expected
+        .as_ref()?
+        .get("content")?
+        .as_str()?
+        .to_string();
+    let ours = updated_content(&mutation.operations)?.to_string();
+    let theirs = mutation
+        .remote
+        .as_ref()
+        .and_then(|remote| remote.get("content")?.as_str())
+        .map(str::to_string);
+    let chunks = merge(&base, &ours, theirs.as_deref().unwrap_or_default());
+    let merged = theirs.as_ref().and_then(|_| merged_text(&chunks));
+    Some(NoteConflict {
+        note_id: mutation.entity_id,
+        mutation_id: mutation.id,
+        base,
+        ours,
+        theirs,
+        chunks,
+        merged,
+    })
+}
+
+/// Records the server content a note edit starts from.
+#[tauri::command]
+pub fn set_note_base(store: State<'_, Store>, note_id: String, content: String) -> Result<()> {
+    store.set_note_base(&note_id, &content)
+}
+
+#[tauri::command]
+pub fn get_note_conflict(store: State<'_, Store>, note_id: String) -> Result<Option<NoteConflict>> {
+    Ok(conflicted_mutation(&store, &note_id)?.and_then(note_conflict))
+}
+
+/// Replaces the conflicted edit with `content` (typically the merge result)
+/// based on the server's current text, and queues it again.
+#[tauri::command]
+pub fn resolve_note_conflict(
+    app: AppHandle,
+    store: State<'_, Store>,
+    engine: State<'_, SyncEngine>,
+    note_id: String,
+    content: String,
+) -> Result<()> {
+    let conflict = conflicted_mutation(&store, &note_id)?
+        .and_then(note_conflict)
+        .ok_or_else(|| Error::InvalidInput(format!("No conflict for note {note_id}")))?;
+    let theirs = conflict
+        .theirs
+        .ok_or_else(|| Error::InvalidInput(format!("Note {note_id} was deleted on the server")))?;
+    let mutation = store
+        .get_mutation(&conflict.mutation_id)?
+        .ok_or_else(|| Error::InvalidInput(format!("No conflict for note {note_id}")))?;
+
+    let mut operations = mutation.operations;
+    for operation in operations.as_array_mut().into_iter().flatten() {
+        let is_update = operation.get("action").and_then(Value::as_str) == Some("update");
+        if let Some(data) = operation.get_mut("data").filter(|_| is_update)
+            && let Some(old) = data.get_mut("content").filter(|old| old.is_string())
+        {
+            *old = Value::String(content.clone());
+        }
+    }
+    let mut expected = mutation.expected.unwrap_or_default();
+    expected.insert("content".into(), Value::String(theirs.clone()));
+
+    store.rebase_mutation(&mutation.id, &operations, &expected)?;
+    store.set_note_base(&note_id, &theirs)?;
+    engine.wake();
+    emit_status(&app);
+    Ok(())
+}
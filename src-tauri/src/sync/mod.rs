@@ -0,0 +1,196 @@
+//! Offline mode: mutations are written to a durable log first and replayed
+//! against ftrack whenever the server is reachable.
+//!
+//! Every change goes through the log, online or not, so the frontend has a
+//! single path for edits. Changes whose base values no longer match the
+//! server are held back as conflicts for the user to resolve.
+
+pub mod conflicts;
+mod reachability;
+mod worker;
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use crate::error::{Error, Result};
+use crate::profiles;
+use crate::store::Store;
+use crate::store::mutations::{Mutation, MutationCounts, NewMutation};
+
+pub const STATUS_EVENT: &str = "sync:status";
+pub const CONFLICT_EVENT: &str = "sync:conflict";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Connectivity {
+    Unknown,
+    Online,
+    Offline,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub connectivity: Connectivity,
+    #[serde(flatten)]
+    pub counts: MutationCounts,
+    pub last_sync_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictResolution {
+    /// Apply the local change over the server's values.
+    Overwrite,
+    /// Drop the local change.
+    Discard,
+}
+
+pub struct SyncEngine {
+    wake: Notify,
+    connectivity: Mutex<Connectivity>,
+    last_sync_at: Mutex<Option<i64>>,
+}
+
+impl Default for SyncEngine {
+    fn default() -> Self {
+        Self {
+            wake: Notify::new(),
+            connectivity: Mutex::new(Connectivity::Unknown),
+            last_sync_at: Mutex::new(None),
+        }
+    }
+}
+
+impl SyncEngine {
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    pub fn connectivity(&self) -> Connectivity {
+        *self
+            .connectivity
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn status(&self, store: &Store) -> SyncStatus {
+        let counts = store.mutation_counts().unwrap_or_else(|err| {
+            log::warn!("Failed to count mutations: {err}");
+            MutationCounts::default()
+        });
+        SyncStatus {
+            connectivity: self.connectivity(),
+            counts,
+            last_sync_at: *self
+                .last_sync_at
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        }
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    app.manage(SyncEngine::default());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { worker::run(app).await });
+}
+
+pub(crate) fn emit_status(app: &AppHandle) {
+    let (Some(engine), Some(store)) = (app.try_state::<SyncEngine>(), app.try_state::<Store>())
+    else {
+        return;
+    };
+    let status = engine.status(&store);
+    if let Err(err) = app.emit(STATUS_EVENT, status) {
+        log::warn!("Failed to emit sync status: {err}");
+    }
+}
+
+fn is_identifier(value: &str) -> bool {
+    value
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic())
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Records a change and replays it as soon as the server is reachable.
+#[tauri::command]
+pub fn queue_mutation(
+    app: AppHandle,
+    store: State<'_, Store>,
+    engine: State<'_, SyncEngine>,
+    mut mutation: NewMutation,
+) -> Result<Mutation> {
+    // Both end up in a query expression when checking for conflicts
+    let valid_id = !mutation.entity_id.is_empty()
+        && mutation
+            .entity_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let valid_attributes = mutation
+        .expected
+        .iter()
+        .flat_map(|expected| expected.keys())
+        .all(|key| is_identifier(key));
+    if !is_identifier(&mutation.entity_type) || !valid_id || !valid_attributes {
+        return Err(Error::InvalidInput(format!(
+            "Invalid mutation target {} {}",
+            mutation.entity_type, mutation.entity_id
+        )));
+    }
+
+    let connection = profiles::resolve(&store, mutation.connection.clone())?;
+    conflicts::attach_base(&store, &mut mutation)?;
+    let mutation = store.insert_mutation(&connection, mutation)?;
+    engine.wake();
+    emit_status(&app);
+    Ok(mutation)
+}
+
+#[tauri::command]
+pub fn list_mutations(store: State<'_, Store>) -> Result<Vec<Mutation>> {
+    store.list_mutations()
+}
+
+#[tauri::command]
+pub fn get_sync_status(store: State<'_, Store>, engine: State<'_, SyncEngine>) -> SyncStatus {
+    engine.status(&store)
+}
+
+/// Retries pending mutations now instead of waiting for the next probe.
+#[tauri::command]
+pub fn sync_now(engine: State<'_, SyncEngine>) {
+    engine.wake();
+}
+
+#[tauri::command]
+pub fn resolve_mutation_conflict(
+    app: AppHandle,
+    store: State<'_, Store>,
+    engine: State<'_, SyncEngine>,
+    id: String,
+    resolution: ConflictResolution,
+) -> Result<()> {
+    let resolved = match resolution {
+        ConflictResolution::Overwrite => store.requeue_mutation(&id, true)?,
+        ConflictResolution::Discard => store.delete_mutation(&id)?,
+    };
+    if !resolved {
+        return Err(Error::InvalidInput(format!("Unknown mutation {id}")));
+    }
+    engine.wake();
+    emit_status(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn clear_applied_mutations(app: AppHandle, store: State<'_, Store>) -> Result<usize> {
+    let cleared = store.clear_applied_mutations()?;
+    emit_status(&app);
+    Ok(cleared)
+}
@@ -0,0 +1,17 @@
+//! Cheap check for whether an ftrack server can be reached at all.
+
+use std::time::Duration;
+
+use crate::ftrack::Connection;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Any HTTP response counts; only transport failures mean offline.
+pub(super) async fn is_reachable(client: &reqwest::Client, connection: &Connection) -> bool {
+    client
+        .head(connection.base_url())
+        .timeout(TIMEOUT)
+        .send()
+        .await
+        .is_ok()
+}
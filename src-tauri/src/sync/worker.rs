@@ -0,0 +1,220 @@
+//! Replays the mutation log in order, one server at a time.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{CONFLICT_EVENT, Connectivity, SyncEngine, conflicts, emit_status, reachability};
+use crate::ftrack::{self, ApiError};
+use crate::network;
+use crate::power;
+use crate::store::audit::{self, AuditAction, NewAuditEntry};
+use crate::store::mutations::{Mutation, MutationStatus};
+use crate::store::{Store, now_millis};
+
+/// How often to probe while changes are waiting for the server.
+const RETRY_INTERVAL: Duration = Duration::from_secs(15);
+const IDLE_POLL: Duration = Duration::from_secs(60);
+
+enum Replay {
+    Applied,
+    Conflict(Value),
+    Failed(String),
+    /// Rate limited or a server error; try again on the next pass.
+    Deferred,
+    Unreachable,
+}
+
+pub(super) async fn run(app: AppHandle) {
+    loop {
+        // Woken again once the system is awake and back online
+        let waiting = !power::held(&app) && sync_pass(&app, &network::client()).await;
+        let engine = app.state::<SyncEngine>();
+        let delay = if waiting { RETRY_INTERVAL } else { IDLE_POLL };
+        tokio::select! {
+            _ = engine.wake.notified() => {}
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+/// Replays every pending mutation it can. Returns whether any are still waiting.
+async fn sync_pass(app: &AppHandle, client: &reqwest::Client) -> bool {
+    let store = app.state::<Store>();
+    let mutations = match store.list_mutations() {
+        Ok(mutations) => mutations,
+        Err(err) => {
+            log::error!("Failed to read mutation log: {err}");
+            return true;
+        }
+    };
+
+    // Later changes to a conflicted entity wait until the conflict is resolved
+    let mut blocked: HashSet<(String, String)> = mutations
+        .iter()
+        .filter(|mutation| mutation.status == MutationStatus::Conflict)
+        .map(|mutation| (mutation.entity_type.clone(), mutation.entity_id.clone()))
+        .collect();
+    // Servers to skip for the rest of this pass, to keep mutations in order
+    let mut unreachable: HashSet<String> = HashSet::new();
+    let mut probed: HashSet<String> = HashSet::new();
+    let mut waiting = false;
+    let mut applied = false;
+
+    for mutation in mutations
+        .into_iter()
+        .filter(|mutation| mutation.status == MutationStatus::Pending)
+    {
+        let connection = mutation.connection();
+        let server = connection.base_url().to_string();
+        let entity = (mutation.entity_type.clone(), mutation.entity_id.clone());
+        if unreachable.contains(&server) || blocked.contains(&entity) {
+            waiting |= unreachable.contains(&server);
+            continue;
+        }
+        if probed.insert(server.clone()) && !reachability::is_reachable(client, &connection).await {
+            set_connectivity(app, Connectivity::Offline);
+            unreachable.insert(server);
+            waiting = true;
+            continue;
+        }
+        set_connectivity(app, Connectivity::Online);
+
+        let result = match replay(client, &mutation).await {
+            Replay::Applied => {
+                applied = true;
+                audit::record(app, applied_entry(&mutation));
+                store
+                    .update_mutation(&mutation.id, MutationStatus::Applied, None, None)
+                    .and_then(|()| conflicts::applied(&store, &mutation))
+            }
+            Replay::Conflict(remote) => {
+                log::info!(
+                    "Mutation {} conflicts with changes on the server",
+                    mutation.id
+                );
+                blocked.insert(entity);
+                let result = store.update_mutation(
+                    &mutation.id,
+                    MutationStatus::Conflict,
+                    None,
+                    Some(&remote),
+                );
+                if let Ok(Some(conflict)) = store.get_mutation(&mutation.id) {
+                    let _ = app.emit(CONFLICT_EVENT, conflict);
+                }
+                result
+            }
+            Replay::Failed(message) => {
+                log::error!("Mutation {} rejected: {message}", mutation.id);
+                store.update_mutation(&mutation.id, MutationStatus::Failed, Some(&message), None)
+            }
+            Replay::Deferred => {
+                unreachable.insert(server);
+                waiting = true;
+                Ok(())
+            }
+            Replay::Unreachable => {
+                set_connectivity(app, Connectivity::Offline);
+                unreachable.insert(server);
+                waiting = true;
+                Ok(())
+            }
+        };
+        if let Err(err) = result {
+            log::error!("Failed to record mutation {}: {err}", mutation.id);
+        }
+        emit_status(app);
+    }
+
+    if applied {
+        *app.state::<SyncEngine>()
+            .last_sync_at
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(now_millis());
+        emit_status(app);
+    }
+    waiting
+}
+
+fn applied_entry(mutation: &Mutation) -> NewAuditEntry {
+    let action = if mutation.kind == "status" {
+        AuditAction::StatusChange
+    } else {
+        AuditAction::Update
+    };
+    NewAuditEntry {
+        action,
+        connection: Some(mutation.connection()),
+        entity_type: Some(mutation.entity_type.clone()),
+        entity_id: Some(mutation.entity_id.clone()),
+        summary: format!(
+            "Synced {} change to {}",
+            mutation.kind, mutation.entity_type
+        ),
+        details: json!({ "mutationId": mutation.id, "operations": mutation.operations }),
+    }
+}
+
+async fn replay(client: &reqwest::Client, mutation: &Mutation) -> Replay {
+    let connection = mutation.connection();
+    if let Some(expected) = &mutation.expected {
+        let attributes = expected.keys().cloned().collect::<Vec<_>>().join(", ");
+        let expression = format!(
+            "select {attributes} from {} where id is \"{}\"",
+            mutation.entity_type, mutation.entity_id
+        );
+        let operations = json!([{ "action": "query", "expression": expression }]);
+        let remote = match ftrack::call(client, &connection, &operations).await {
+            Ok(results) => results
+                .into_iter()
+                .next()
+                .and_then(|result| result.get("data")?.as_array()?.first().cloned()),
+            Err(err) => return classify(err),
+        };
+        let Some(remote) = remote else {
+            // Deleted on the server
+            return Replay::Conflict(Value::Null);
+        };
+        let changed = expected
+            .iter()
+            .any(|(key, value)| remote.get(key).unwrap_or(&Value::Null) != value);
+        if changed {
+            let remote = expected
+                .keys()
+                .map(|key| (key.clone(), remote.get(key).cloned().unwrap_or(Value::Null)))
+                .collect();
+            return Replay::Conflict(Value::Object(remote));
+        }
+    }
+
+    match ftrack::call(client, &connection, &mutation.operations).await {
+        Ok(_) => Replay::Applied,
+        Err(err) => classify(err),
+    }
+}
+
+fn classify(err: ApiError) -> Replay {
+    match err {
+        ApiError::Transport(_) => Replay::Unreachable,
+        err if err.is_retryable() => Replay::Deferred,
+        err => Replay::Failed(err.to_string()),
+    }
+}
+
+fn set_connectivity(app: &AppHandle, connectivity: Connectivity) {
+    let engine = app.state::<SyncEngine>();
+    let changed = {
+        let mut current = engine
+            .connectivity
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::replace(&mut *current, connectivity) != connectivity
+    };
+    if changed {
+        log::info!("ftrack connectivity: {connectivity:?}");
+        emit_status(app);
+    }
+}
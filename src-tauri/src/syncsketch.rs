@@ -0,0 +1,313 @@
+//! SyncSketch review comments, in both directions.
+//!
+//! Vendors often review in SyncSketch while the studio works in its tracker.
+//! Here a session's comments, with their frame numbers and sketch images,
+//! are imported into drafts through [`review_import`]. Published notes can
+//! also be posted back as comments on the matching items. Authentication
+//! uses a username and API key, stored together in the keychain.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::{Value, json};
+use tauri::State;
+
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::network;
+use crate::providers::id;
+use crate::review_import::{self, ImportSummary, ReviewComment};
+use crate::store::Store;
+use crate::timecode::format_timecode;
+
+const API_URL: &str = "https://www.syncsketch.com/api/v1";
+const CREDENTIAL_KEY: &str = "syncsketch-api-key";
+const PAGE_SIZE: usize = 200;
+
+/// Session links look like `https://syncsketch.com/sketch/<uuid>/#<item>`.
+static SESSION_UUID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"/sketch/([0-9a-zA-Z-]+)").expect("valid session link pattern"));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSketchItem {
+    pub id: String,
+    pub name: String,
+    pub fps: Option<f64>,
+    pub comments: Vec<ReviewComment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSketchSession {
+    pub id: String,
+    pub name: String,
+    pub items: Vec<SyncSketchItem>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushSummary {
+    pub posted: usize,
+    /// Notes already on the item, or on versions without a mapped item.
+    pub skipped: usize,
+}
+
+struct Client {
+    http: reqwest::Client,
+    authorization: String,
+}
+
+impl Client {
+    fn new() -> Result<Self> {
+        let credentials = credentials::get(CREDENTIAL_KEY)?
+            .ok_or_else(|| Error::InvalidInput("Sign in to SyncSketch first".into()))?;
+        Ok(Self::with_credentials(&credentials))
+    }
+
+    /// `credentials` is `username:api_key`, as SyncSketch expects it.
+    fn with_credentials(credentials: &str) -> Self {
+        Self {
+            http: network::client(),
+            authorization: format!("apikey {credentials}"),
+        }
+    }
+
+    async fn send(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Value> {
+        let mut request = self
+            .http
+            .request(method, format!("{API_URL}{path}"))
+            .header(reqwest::header::AUTHORIZATION, &self.authorization);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body: Value = response.json().await.unwrap_or_default();
+            let message = body["error"]
+                .as_str()
+                .or(body["detail"].as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("SyncSketch responded with {status}"));
+            return Err(Error::Provider(message));
+        }
+        Ok(response.json().await.unwrap_or_default())
+    }
+
+    /// Lists every object of a TastyPie resource, following `meta.next`.
+    async fn list(&self, resource: &str, filters: &str) -> Result<Vec<Value>> {
+        let mut objects = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = self
+                .send(
+                    Method::GET,
+                    &format!("/{resource}/?{filters}&limit={PAGE_SIZE}&offset={offset}"),
+                    None,
+                )
+                .await?;
+            let batch = page["objects"].as_array().cloned().unwrap_or_default();
+            if batch.is_empty() {
+                break;
+            }
+            offset += batch.len();
+            objects.extend(batch);
+            if page["meta"]["next"].is_null() {
+                break;
+            }
+        }
+        Ok(objects)
+    }
+}
+
+/// `creator` is either a nested user or a resource URI, depending on the endpoint.
+fn author(object: &Value) -> Option<String> {
+    let creator = &object["creator"];
+    let full_name = [&creator["first_name"], &creator["last_name"]]
+        .iter()
+        .filter_map(|part| part.as_str())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(full_name)
+        .filter(|name| !name.is_empty())
+        .or_else(|| creator["username"].as_str().map(str::to_string))
+}
+
+fn comment(object: &Value, fps: Option<f64>) -> ReviewComment {
+    let frame = object["frame"].as_i64();
+    let is_sketch = object["type"] == "sketch";
+    ReviewComment {
+        id: id(object),
+        author: author(object),
+        text: object["text"].as_str().unwrap_or_default().to_string(),
+        frame,
+        timecode: frame
+            .zip(fps)
+            .map(|(frame, fps)| format_timecode(frame, fps, false)),
+        created_at: object["created"].as_str().map(str::to_string),
+        annotation: is_sketch
+            .then(|| object["data"].clone())
+            .filter(|data| !data.is_null()),
+        image_url: is_sketch
+            .then(|| object["thumbnail_url"].as_str().map(str::to_string))
+            .flatten(),
+        replies: Vec::new(),
+    }
+}
+
+/// Accepts a numeric review ID or a session link.
+async fn review(client: &Client, session: &str) -> Result<Value> {
+    let session = session.trim();
+    if !session.is_empty() && session.chars().all(|c| c.is_ascii_digit()) {
+        return client
+            .send(Method::GET, &format!("/review/{session}/"), None)
+            .await;
+    }
+    let uuid = SESSION_UUID
+        .captures(session)
+        .map(|captures| captures[1].to_string())
+        .ok_or_else(|| Error::InvalidInput(format!("Not a SyncSketch session: {session}")))?;
+    client
+        .list("review", &format!("uuid={uuid}"))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::Provider(format!("SyncSketch session {uuid} not found")))
+}
+
+async fn fetch(client: &Client, session: &str) -> Result<SyncSketchSession> {
+    let review = review(client, session).await?;
+    let review_id = id(&review);
+    let items = client
+        .list("item", &format!("reviews__id={review_id}&active=1"))
+        .await?;
+    let mut result = Vec::new();
+    for item in items {
+        let item_id = id(&item);
+        let fps = item["fps"].as_f64().filter(|fps| *fps > 0.0);
+        let frames = client
+            .list("frame", &format!("item__id={item_id}&active=1"))
+            .await?;
+        let mut comments: Vec<_> = frames.iter().map(|frame| comment(frame, fps)).collect();
+        comments.sort_by_key(|comment| comment.frame.unwrap_or_default());
+        result.push(SyncSketchItem {
+            id: item_id,
+            name: item["name"].as_str().unwrap_or_default().to_string(),
+            fps,
+            comments,
+        });
+    }
+    Ok(SyncSketchSession {
+        id: review_id,
+        name: review["name"].as_str().unwrap_or_default().to_string(),
+        items: result,
+    })
+}
+
+/// Checks the credentials against the API and stores them.
+#[tauri::command]
+pub async fn set_syncsketch_credentials(username: String, api_key: String) -> Result<String> {
+    let credentials = format!("{}:{}", username.trim(), api_key.trim());
+    let user = Client::with_credentials(&credentials)
+        .send(Method::GET, "/person/connected_user/", None)
+        .await?;
+    credentials::store(CREDENTIAL_KEY, &credentials)?;
+    Ok(user["username"]
+        .as_str()
+        .unwrap_or(username.trim())
+        .to_string())
+}
+
+#[tauri::command]
+pub fn clear_syncsketch_credentials() -> Result<bool> {
+    credentials::delete(CREDENTIAL_KEY)
+}
+
+#[tauri::command]
+pub async fn fetch_syncsketch_session(session: String) -> Result<SyncSketchSession> {
+    fetch(&Client::new()?, &session).await
+}
+
+#[tauri::command]
+pub async fn import_syncsketch_session(
+    store: State<'_, Store>,
+    session: String,
+    playlist_id: String,
+    mapping: HashMap<String, String>,
+) -> Result<ImportSummary> {
+    let session = fetch(&Client::new()?, &session).await?;
+    let comments: Vec<_> = session
+        .items
+        .into_iter()
+        .map(|item| (item.id, item.comments))
+        .collect();
+    let summary = review_import::into_drafts(&store, &playlist_id, &mapping, &comments)?;
+    log::info!(
+        "Imported {} SyncSketch comments from {} into playlist {playlist_id}",
+        summary.imported,
+        session.name
+    );
+    Ok(summary)
+}
+
+/// Posts the playlist's published notes as comments on the SyncSketch items
+/// their versions map to. `mapping` goes from version ID to item ID. Notes
+/// whose text is already on the item are not posted again.
+#[tauri::command]
+pub async fn push_syncsketch_notes(
+    store: State<'_, Store>,
+    session: String,
+    playlist_id: String,
+    mapping: HashMap<String, String>,
+) -> Result<PushSummary> {
+    let client = Client::new()?;
+    let review_id = id(&review(&client, &session).await?);
+    let published: Vec<_> = store
+        .list_drafts(Some(&playlist_id))?
+        .into_iter()
+        .filter(|draft| draft.status == "published" && !draft.content.is_empty())
+        .collect();
+
+    let mut summary = PushSummary::default();
+    let mut existing: HashMap<String, Vec<String>> = HashMap::new();
+    for draft in published {
+        let Some(item_id) = mapping.get(&draft.version_id) else {
+            summary.skipped += 1;
+            continue;
+        };
+        if !existing.contains_key(item_id) {
+            let texts = client
+                .list("frame", &format!("item__id={item_id}&active=1"))
+                .await?
+                .iter()
+                .filter_map(|frame| frame["text"].as_str().map(str::to_string))
+                .collect();
+            existing.insert(item_id.clone(), texts);
+        }
+        let texts = existing.get_mut(item_id).expect("inserted above");
+        if texts.contains(&draft.content) {
+            summary.skipped += 1;
+            continue;
+        }
+        client
+            .send(
+                Method::POST,
+                "/frame/",
+                Some(&json!({
+                    "item": format!("/api/v1/item/{item_id}/"),
+                    "revision": format!("/api/v1/review/{review_id}/"),
+                    "text": draft.content,
+                    "frame": 0,
+                })),
+            )
+            .await?;
+        texts.push(draft.content);
+        summary.posted += 1;
+    }
+    Ok(summary)
+}
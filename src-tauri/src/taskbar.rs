@@ -0,0 +1,134 @@
+//! Job progress on the Windows taskbar button and the macOS dock icon.
+//!
+//! Several jobs can run at once, e.g. a publish batch while media downloads.
+//! Each reports under its own source key, in whatever unit suits it: bytes
+//! for transfers, counts for everything else. The bar shows the average of
+//! their fractions and is cleared when the last source finishes.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    done: u64,
+    total: u64,
+    paused: bool,
+}
+
+#[derive(Default)]
+pub struct TaskbarProgress {
+    sources: Mutex<HashMap<String, Entry>>,
+    /// Last state applied, so frequent byte updates only touch the OS on change.
+    applied: Mutex<Option<(u64, bool)>>,
+}
+
+impl TaskbarProgress {
+    fn apply(&self, app: &AppHandle) {
+        let state = {
+            let sources = self
+                .sources
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            (!sources.is_empty()).then(|| {
+                let fraction = sources
+                    .values()
+                    .map(|entry| match entry.total {
+                        0 => 0.0,
+                        total => entry.done.min(total) as f64 / total as f64,
+                    })
+                    .sum::<f64>()
+                    / sources.len() as f64;
+                let paused = sources.values().all(|entry| entry.paused);
+                ((fraction * 100.0).round() as u64, paused)
+            })
+        };
+
+        let mut applied = self
+            .applied
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *applied == state {
+            return;
+        }
+        *applied = state;
+        let Some(window) = app.get_webview_window("main") else {
+            return;
+        };
+        let progress = match state {
+            Some((percent, paused)) => ProgressBarState {
+                status: Some(if paused {
+                    ProgressBarStatus::Paused
+                } else {
+                    ProgressBarStatus::Normal
+                }),
+                progress: Some(percent),
+            },
+            None => ProgressBarState {
+                status: Some(ProgressBarStatus::None),
+                progress: None,
+            },
+        };
+        if let Err(err) = window.set_progress_bar(progress) {
+            log::warn!("Failed to set taskbar progress: {err}");
+        }
+    }
+
+    fn set(&self, source: &str, entry: Option<Entry>) {
+        let mut sources = self
+            .sources
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match entry {
+            Some(entry) => {
+                sources.insert(source.to_string(), entry);
+            }
+            None => {
+                sources.remove(source);
+            }
+        }
+    }
+}
+
+/// Reports progress for `source`; a `total` of 0 shows as not started.
+pub fn update(app: &AppHandle, source: &str, done: u64, total: u64) {
+    set(app, source, done, total, false);
+}
+
+/// Keeps `source` on the bar without counting it as running.
+pub fn pause(app: &AppHandle, source: &str, done: u64, total: u64) {
+    set(app, source, done, total, true);
+}
+
+fn set(app: &AppHandle, source: &str, done: u64, total: u64, paused: bool) {
+    let progress = app.state::<TaskbarProgress>();
+    progress.set(
+        source,
+        Some(Entry {
+            done,
+            total,
+            paused,
+        }),
+    );
+    progress.apply(app);
+}
+
+/// Removes `source`, clearing the bar once nothing else is running.
+pub fn finish(app: &AppHandle, source: &str) {
+    let progress = app.state::<TaskbarProgress>();
+    progress.set(source, None);
+    progress.apply(app);
+}
+
+/// Progress for jobs the frontend runs itself, e.g. a bulk thumbnail refresh.
+#[tauri::command]
+pub fn set_taskbar_progress(app: AppHandle, source: String, done: u64, total: u64) {
+    update(&app, &source, done, total);
+}
+
+#[tauri::command]
+pub fn clear_taskbar_progress(app: AppHandle, source: String) {
+    finish(&app, &source);
+}
@@ -0,0 +1,293 @@
+//! Crash reporting configuration.
+//!
+//! The Sentry DSN is resolved at runtime, in order, from `telemetry.json` in
+//! the app config directory, the `SENTRY_TAURI` environment variable, and the
+//! value embedded at build time. Without a DSN, crash reporting stays off.
+//!
+//! The frontend reports through the native client as well, via the `sentry_*`
+//! commands, so its breadcrumbs and messages share the native release and
+//! session. Every event passes a scrubber before it leaves the machine: note
+//! text is dropped from breadcrumb data and extras, and URLs, e-mail
+//! addresses and credentials are redacted from messages.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, LazyLock};
+
+use regex::Regex;
+use sentry::protocol::{Breadcrumb, Event, Map, User, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::logging;
+use crate::paths;
+use crate::settings;
+
+const SETTINGS_FILE: &str = "telemetry.json";
+
+/// Breadcrumb data and extras under these keys may hold note text.
+const NOTE_KEYS: &[&str] = &[
+    "content", "note", "notes", "noteText", "text", "draft", "body",
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static ERROR_SAMPLE_RATE: AtomicU32 = AtomicU32::new(f32::to_bits(1.0));
+static MESSAGE_SAMPLE_RATE: AtomicU32 = AtomicU32::new(f32::to_bits(1.0));
+
+static URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\b(?:https?|wss?)://[^\s"'<>]+"#).expect("valid URL pattern")
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetrySettings {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub dsn: Option<String>,
+    /// Share of error and crash events sent, from 0 to 1.
+    #[serde(default = "default_sample_rate")]
+    pub error_sample_rate: f32,
+    /// Share of message-only events sent, mostly from the frontend.
+    #[serde(default = "default_sample_rate")]
+    pub message_sample_rate: f32,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            dsn: None,
+            error_sample_rate: default_sample_rate(),
+            message_sample_rate: default_sample_rate(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_sample_rate() -> f32 {
+    1.0
+}
+
+fn load_rate(rate: &AtomicU32) -> f32 {
+    f32::from_bits(rate.load(Ordering::Relaxed))
+}
+
+fn store_rate(rate: &AtomicU32, value: f32) {
+    rate.store(value.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+}
+
+fn sampled(rate: f32) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    // 24 random bits are plenty to compare against a rate
+    let random = (uuid::Uuid::new_v4().as_u128() >> 104) as f32 / (1u32 << 24) as f32;
+    random < rate
+}
+
+fn scrub_text(text: &str) -> String {
+    URL.replace_all(&logging::scrub(text), "<url>").into_owned()
+}
+
+fn scrub_data(data: &mut Map<String, Value>) {
+    data.retain(|key, _| !NOTE_KEYS.contains(&key.as_str()));
+    for value in data.values_mut() {
+        if let Value::String(text) = value {
+            *text = scrub_text(text);
+        }
+    }
+}
+
+fn scrub_breadcrumb(breadcrumb: &mut Breadcrumb) {
+    breadcrumb.message = breadcrumb.message.as_deref().map(scrub_text);
+    scrub_data(&mut breadcrumb.data);
+}
+
+/// Strips note text, server URLs and personal data from an outgoing event.
+fn scrub_event(mut event: Event<'static>) -> Event<'static> {
+    event.message = event.message.as_deref().map(scrub_text);
+    if let Some(entry) = &mut event.logentry {
+        entry.message = scrub_text(&entry.message);
+        entry.params.clear();
+    }
+    for exception in &mut event.exception.values {
+        exception.value = exception.value.as_deref().map(scrub_text);
+    }
+    for breadcrumb in &mut event.breadcrumbs.values {
+        scrub_breadcrumb(breadcrumb);
+    }
+    scrub_data(&mut event.extra);
+    event.request = None;
+    event.server_name = None;
+    event
+}
+
+fn load_settings() -> TelemetrySettings {
+    paths::load_standalone_json(SETTINGS_FILE).unwrap_or_else(|err| {
+        log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}");
+        TelemetrySettings::default()
+    })
+}
+
+fn save_settings(settings: &TelemetrySettings) -> Result<()> {
+    paths::save_standalone_json(SETTINGS_FILE, settings)
+}
+
+fn resolve_dsn(settings: &TelemetrySettings) -> Option<String> {
+    let non_empty = |value: String| {
+        let value = value.trim().trim_matches('"').to_string();
+        (!value.is_empty()).then_some(value)
+    };
+    settings
+        .dsn
+        .clone()
+        .and_then(non_empty)
+        .or_else(|| std::env::var("SENTRY_TAURI").ok().and_then(non_empty))
+        .or_else(|| {
+            option_env!("SENTRY_TAURI")
+                .map(str::to_string)
+                .and_then(non_empty)
+        })
+}
+
+/// Keeps the Sentry client running while it is held, as managed state.
+pub struct Guard {
+    _client: sentry::ClientInitGuard,
+}
+
+/// Starts the Sentry client if telemetry is enabled and a valid DSN is configured.
+///
+/// The returned guard must be held for the lifetime of the process.
+pub fn init() -> Option<Guard> {
+    let settings = load_settings();
+    ENABLED.store(settings.enabled, Ordering::Relaxed);
+    store_rate(&ERROR_SAMPLE_RATE, settings.error_sample_rate);
+    store_rate(&MESSAGE_SAMPLE_RATE, settings.message_sample_rate);
+    if !settings.enabled {
+        return None;
+    }
+
+    let dsn = match resolve_dsn(&settings)?.parse() {
+        Ok(dsn) => dsn,
+        Err(err) => {
+            log::warn!("Crash reporting disabled, invalid Sentry DSN: {err}");
+            return None;
+        }
+    };
+
+    let client = sentry::init(sentry::ClientOptions {
+        dsn: Some(dsn),
+        release: sentry::release_name!(),
+        // Checked per event so settings changes take effect without a restart
+        before_send: Some(Arc::new(|event| {
+            if !ENABLED.load(Ordering::Relaxed) {
+                return None;
+            }
+            let rate = if event.exception.values.is_empty() {
+                load_rate(&MESSAGE_SAMPLE_RATE)
+            } else {
+                load_rate(&ERROR_SAMPLE_RATE)
+            };
+            sampled(rate).then(|| scrub_event(event))
+        })),
+        auto_session_tracking: true,
+        ..Default::default()
+    });
+    Some(Guard { _client: client })
+}
+
+#[tauri::command]
+pub fn get_telemetry_settings() -> TelemetrySettings {
+    let mut settings = load_settings();
+    settings.enabled = ENABLED.load(Ordering::Relaxed);
+    settings.error_sample_rate = load_rate(&ERROR_SAMPLE_RATE);
+    settings.message_sample_rate = load_rate(&MESSAGE_SAMPLE_RATE);
+    settings
+}
+
+/// Persists the telemetry preference. Returns `true` when a restart is needed
+/// for it to take effect, i.e. reporting was enabled but no client is running.
+#[tauri::command]
+pub fn set_telemetry_enabled(enabled: bool) -> Result<bool> {
+    if settings::is_locked(SETTINGS_FILE, "enabled") {
+        return Err(Error::InvalidInput(
+            "Crash reporting is managed by your administrator".into(),
+        ));
+    }
+    let mut settings = load_settings();
+    settings.enabled = enabled;
+    save_settings(&settings)?;
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    let client_running = sentry::Hub::current().client().is_some();
+    Ok(enabled && !client_running)
+}
+
+#[tauri::command]
+pub fn set_telemetry_sample_rates(error_sample_rate: f32, message_sample_rate: f32) -> Result<()> {
+    for rate in [error_sample_rate, message_sample_rate] {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(Error::InvalidInput(format!(
+                "Sample rate {rate} must be between 0 and 1"
+            )));
+        }
+    }
+    let mut settings = load_settings();
+    settings.error_sample_rate = error_sample_rate;
+    settings.message_sample_rate = message_sample_rate;
+    save_settings(&settings)?;
+    store_rate(&ERROR_SAMPLE_RATE, error_sample_rate);
+    store_rate(&MESSAGE_SAMPLE_RATE, message_sample_rate);
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontendBreadcrumb {
+    pub category: Option<String>,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub level: Option<sentry::Level>,
+    #[serde(default)]
+    pub data: Map<String, Value>,
+}
+
+#[tauri::command]
+pub fn sentry_add_breadcrumb(breadcrumb: FrontendBreadcrumb) {
+    sentry::add_breadcrumb(Breadcrumb {
+        category: breadcrumb.category,
+        message: breadcrumb.message,
+        level: breadcrumb.level.unwrap_or(sentry::Level::Info),
+        data: breadcrumb.data,
+        ..Default::default()
+    });
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontendUser {
+    pub id: Option<String>,
+    pub username: Option<String>,
+}
+
+/// Sets the user on every later event, or clears it with `None`. Only the ID
+/// and a scrubbed username are kept; e-mail and IP address never are.
+#[tauri::command]
+pub fn sentry_set_user(user: Option<FrontendUser>) {
+    let user = user.map(|user| User {
+        id: user.id,
+        username: user.username.as_deref().map(scrub_text),
+        ..Default::default()
+    });
+    sentry::configure_scope(|scope| scope.set_user(user));
+}
+
+/// Captures a frontend message, returning the event ID when it was sent.
+#[tauri::command]
+pub fn sentry_capture_message(message: String, level: Option<sentry::Level>) -> Option<String> {
+    let id = sentry::capture_message(&message, level.unwrap_or(sentry::Level::Info));
+    (!id.is_nil()).then(|| id.to_string())
+}
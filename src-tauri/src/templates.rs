@@ -0,0 +1,241 @@
+//! Note templates with `{token}` expansion.
+//!
+//! Tokens are looked up in a context object sent by the frontend, by their
+//! snake_case or camelCase name. `{date}`, `{time}` and `{datetime}` are
+//! always available, `{frame_range}` falls back to `frameIn`-`frameOut`, and
+//! a `:spec` suffix formats the value: a strftime pattern for dates, or a
+//! zero-padded width for numbers (`{version:03}`). `{{` and `}}` are literal
+//! braces. Templates can be shared as JSON files.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use chrono::format::{Item, StrftimeItems};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::error::{Error, Result};
+use crate::export::write_atomically;
+use crate::store::Store;
+use crate::store::templates::{NoteTemplate, TemplateDraft};
+
+const FILE_FORMAT: &str = "astranotes-note-template";
+const FILE_VERSION: u32 = 1;
+
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{|\}\}|\{(\w+)(?::([^{}]*))?\}").expect("valid token pattern")
+});
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandedTemplate {
+    pub text: String,
+    /// Tokens left unexpanded because the context had no value for them.
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateFile {
+    format: String,
+    version: u32,
+    #[serde(flatten)]
+    template: TemplateDraft,
+}
+
+fn camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn format_date(now: &chrono::DateTime<chrono::Local>, spec: &str) -> Result<String> {
+    let items: Vec<Item<'_>> = StrftimeItems::new(spec).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(Error::InvalidInput(format!("Invalid date format: {spec}")));
+    }
+    let mut out = String::new();
+    let _ = write!(out, "{}", now.format_with_items(items.into_iter()));
+    Ok(out)
+}
+
+fn format_value(value: &Value, spec: Option<&str>) -> Option<String> {
+    let text = match value {
+        Value::Null => return None,
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values
+            .iter()
+            .filter_map(|value| format_value(value, None))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Number(number) => match (
+            spec.and_then(|spec| spec.parse::<usize>().ok()),
+            number.as_i64(),
+        ) {
+            (Some(width), Some(number)) => return Some(format!("{number:0width$}")),
+            _ => number.to_string(),
+        },
+        value => value.to_string(),
+    };
+    Some(text)
+}
+
+fn lookup(
+    name: &str,
+    spec: Option<&str>,
+    context: &HashMap<String, Value>,
+    now: &chrono::DateTime<chrono::Local>,
+) -> Result<Option<String>> {
+    let value = context.get(name).or_else(|| context.get(&camel_case(name)));
+    if let Some(value) = value {
+        return Ok(format_value(value, spec));
+    }
+    let builtin = match name {
+        "date" => Some(format_date(now, spec.unwrap_or("%Y-%m-%d"))?),
+        "time" => Some(format_date(now, spec.unwrap_or("%H:%M"))?),
+        "datetime" => Some(format_date(now, spec.unwrap_or("%Y-%m-%d %H:%M"))?),
+        "frame_range" | "frameRange" => {
+            let bound = |key: &str| context.get(key).and_then(|value| format_value(value, spec));
+            bound("frameIn")
+                .or_else(|| bound("frame_in"))
+                .zip(bound("frameOut").or_else(|| bound("frame_out")))
+                .map(|(first, last)| format!("{first}-{last}"))
+        }
+        _ => None,
+    };
+    Ok(builtin)
+}
+
+/// Replaces every token in `body` from `context`.
+pub fn expand(body: &str, context: &HashMap<String, Value>) -> Result<ExpandedTemplate> {
+    let now = chrono::Local::now();
+    let mut missing = Vec::new();
+    let mut error = None;
+    let text = TOKEN
+        .replace_all(body, |caps: &Captures<'_>| {
+            let Some(name) = caps.get(1) else {
+                // `{{` or `}}`
+                return caps[0][..1].to_string();
+            };
+            match lookup(
+                name.as_str(),
+                caps.get(2).map(|spec| spec.as_str()),
+                context,
+                &now,
+            ) {
+                Ok(Some(value)) => value,
+                Ok(None) => {
+                    if !missing.iter().any(|token| token == name.as_str()) {
+                        missing.push(name.as_str().to_string());
+                    }
+                    caps[0].to_string()
+                }
+                Err(err) => {
+                    error.get_or_insert(err);
+                    caps[0].to_string()
+                }
+            }
+        })
+        .into_owned();
+    if let Some(err) = error {
+        return Err(err);
+    }
+    Ok(ExpandedTemplate { text, missing })
+}
+
+fn validate(draft: &TemplateDraft) -> Result<()> {
+    if draft.name.trim().is_empty() {
+        return Err(Error::InvalidInput("Template name is required".into()));
+    }
+    // Catch bad date formats at save time rather than on first use
+    expand(&draft.body, &HashMap::new()).map(|_| ())
+}
+
+#[tauri::command]
+pub fn list_templates(store: State<'_, Store>) -> Result<Vec<NoteTemplate>> {
+    store.list_templates()
+}
+
+#[tauri::command]
+pub fn save_template(store: State<'_, Store>, template: TemplateDraft) -> Result<NoteTemplate> {
+    validate(&template)?;
+    store.save_template(&template)
+}
+
+#[tauri::command]
+pub fn delete_template(store: State<'_, Store>, template_id: String) -> Result<bool> {
+    store.delete_template(&template_id)
+}
+
+/// Expands a stored template. Strict templates fail if any token is missing.
+#[tauri::command]
+pub fn expand_template(
+    store: State<'_, Store>,
+    template_id: String,
+    context: HashMap<String, Value>,
+) -> Result<ExpandedTemplate> {
+    let template = store
+        .get_template(&template_id)?
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown template: {template_id}")))?;
+    let expanded = expand(&template.body, &context)?;
+    if template.strict && !expanded.missing.is_empty() {
+        return Err(Error::InvalidInput(format!(
+            "Template \"{}\" needs values for: {}",
+            template.name,
+            expanded.missing.join(", ")
+        )));
+    }
+    Ok(expanded)
+}
+
+#[tauri::command]
+pub fn export_template(store: State<'_, Store>, template_id: String, path: PathBuf) -> Result<()> {
+    let template = store
+        .get_template(&template_id)?
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown template: {template_id}")))?;
+    let file = TemplateFile {
+        format: FILE_FORMAT.into(),
+        version: FILE_VERSION,
+        template: TemplateDraft {
+            id: None,
+            name: template.name,
+            description: template.description,
+            body: template.body,
+            strict: template.strict,
+        },
+    };
+    write_atomically(&path, |temp| {
+        std::fs::write(temp, serde_json::to_vec_pretty(&file)?)?;
+        Ok(())
+    })
+}
+
+/// Imports a shared template file, replacing any template with the same name.
+#[tauri::command]
+pub fn import_template(store: State<'_, Store>, path: PathBuf) -> Result<NoteTemplate> {
+    let file: TemplateFile = serde_json::from_slice(&std::fs::read(&path)?)?;
+    if file.format != FILE_FORMAT || file.version > FILE_VERSION {
+        return Err(Error::InvalidInput(format!(
+            "{} is not a supported note template",
+            path.display()
+        )));
+    }
+    let mut template = file.template;
+    template.id = None;
+    validate(&template)?;
+    store.save_template(&template)
+}
@@ -0,0 +1,84 @@
+//! Word-level text diffs shared by conflict merging and revision history.
+
+use serde::Serialize;
+use similar::{Algorithm, DiffOp, capture_diff_slices};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpanKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSpan {
+    pub kind: SpanKind,
+    pub text: String,
+}
+
+/// Splits text into alternating runs of whitespace and non-whitespace, so
+/// diffs work on words but joining the tokens restores the text exactly.
+pub fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (index, c) in text.char_indices() {
+        let space = c.is_whitespace();
+        if in_space.is_some_and(|in_space| in_space != space) {
+            tokens.push(&text[start..index]);
+            start = index;
+        }
+        in_space = Some(space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+pub fn diff_ops(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    capture_diff_slices(Algorithm::Myers, old, new)
+}
+
+/// Diffs `old` against `new`, merging adjacent tokens of the same kind.
+pub fn word_diff(old: &str, new: &str) -> Vec<DiffSpan> {
+    let (old, new) = (tokenize(old), tokenize(new));
+    let mut spans: Vec<DiffSpan> = Vec::new();
+    let mut push = |kind: SpanKind, tokens: &[&str]| {
+        if tokens.is_empty() {
+            return;
+        }
+        match spans.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(&tokens.concat()),
+            _ => spans.push(DiffSpan {
+                kind,
+                text: tokens.concat(),
+            }),
+        }
+    };
+    for op in diff_ops(&old, &new) {
+        match op {
+            DiffOp::Equal { old_index, len, .. } => {
+                push(SpanKind::Equal, &old[old_index..old_index + len])
+            }
+            DiffOp::Delete {
+                old_index, old_len, ..
+            } => push(SpanKind::Delete, &old[old_index..old_index + old_len]),
+            DiffOp::Insert {
+                new_index, new_len, ..
+            } => push(SpanKind::Insert, &new[new_index..new_index + new_len]),
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => {
+                push(SpanKind::Delete, &old[old_index..old_index + old_len]);
+                push(SpanKind::Insert, &new[new_index..new_index + new_len]);
+            }
+        }
+    }
+    spans
+}
@@ -0,0 +1,713 @@
+//! Disk-backed LRU cache for ftrack thumbnails.
+//!
+//! Thumbnails are stored as `<component_id>.<ext>` in the app cache directory.
+//! A file's modification time doubles as its last-access time, so the cache
+//! survives restarts without a separate index.
+//!
+//! Which project and playlists each thumbnail was fetched for is kept in
+//! `.owners.json` beside them. `cache.json` in the app config directory
+//! caps how much each project may keep and pins playlists whose thumbnails
+//! are never evicted; thumbnails fetched before they had owners count as no
+//! project's. Sizes are kept with the owners and in a running total, so a
+//! fetch only walks the directory when a limit is crossed.
+//!
+//! Cached thumbnails are also served over `astra-thumb://component/<id>`, so
+//! `<img>` tags can point at the cache directly instead of going through the
+//! asset scope or base64 blobs.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use reqwest::header::{CONTENT_TYPE, HeaderValue};
+use serde::{Deserialize, Serialize};
+use tauri::http::{Request, StatusCode, header};
+use tauri::ipc::Response;
+use tauri::{AppHandle, Manager, State, UriSchemeContext, UriSchemeResponder, Wry};
+
+use crate::color::ColorManager;
+use crate::color::lut::Lut;
+use crate::error::{Error, Result};
+use crate::export::write_atomically;
+use crate::media;
+use crate::network;
+use crate::paths;
+
+pub const SCHEME: &str = "astra-thumb";
+
+const DEFAULT_MAX_BYTES: u64 = 512 * 1024 * 1024;
+const MIB: u64 = 1024 * 1024;
+const SETTINGS_FILE: &str = "cache.json";
+const OWNERS_FILE: &str = ".owners.json";
+/// Owners are written at most this often while thumbnails stream in.
+const OWNERS_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+/// Component IDs never change content, so the webview may keep them for a day.
+const CACHE_CONTROL: &str = "private, max-age=86400";
+const EXTENSIONS: [&str; 4] = ["jpg", "png", "webp", "gif"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CacheSettings {
+    /// Thumbnail megabytes each project may keep, by project ID.
+    pub project_quota_mb: HashMap<String, u64>,
+    /// Playlists whose thumbnails are never evicted.
+    pub pinned_playlists: BTreeSet<String>,
+}
+
+/// What a thumbnail was fetched for.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Owner {
+    project_id: Option<String>,
+    playlist_ids: BTreeSet<String>,
+    bytes: u64,
+}
+
+struct Owners {
+    by_component: HashMap<String, Owner>,
+    dirty: bool,
+    saved_at: Instant,
+}
+
+/// Shared handle to the cache. Cheap to clone into blocking tasks.
+#[derive(Clone)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    max_bytes: Arc<AtomicU64>,
+    temp_counter: Arc<AtomicU64>,
+    /// Bytes of every cached thumbnail, reconciled with the disk on eviction.
+    total_bytes: Arc<AtomicU64>,
+    settings: Arc<Mutex<CacheSettings>>,
+    owners: Arc<Mutex<Owners>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedThumbnail {
+    pub component_id: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// `astra-thumb://` URL for `<img>` tags.
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvictionResult {
+    pub evicted: usize,
+    pub freed_bytes: u64,
+    pub remaining_bytes: u64,
+}
+
+/// Thumbnails kept for one project; `project_id` is `None` for those
+/// without one.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectUsage {
+    pub project_id: Option<String>,
+    pub thumbnails: usize,
+    pub bytes: u64,
+    /// Part of `bytes` held by pinned playlists.
+    pub pinned_bytes: u64,
+    pub quota_bytes: Option<u64>,
+}
+
+struct Entry {
+    component_id: String,
+    path: PathBuf,
+    size: u64,
+    accessed: SystemTime,
+}
+
+impl ThumbnailCache {
+    /// Opens the cache in `dir` with the owners recorded there and the
+    /// quotas and pins from the app config.
+    pub fn load(app: &AppHandle, dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let mut by_component: HashMap<String, Owner> = paths::read_json(&dir.join(OWNERS_FILE))
+            .unwrap_or_else(|err| {
+                log::warn!("Ignoring unreadable {OWNERS_FILE}: {err}");
+                HashMap::new()
+            });
+        let settings = load_settings(app).unwrap_or_else(|err| {
+            log::warn!("Ignoring invalid {SETTINGS_FILE}: {err}");
+            CacheSettings::default()
+        });
+        let cache = Self {
+            dir,
+            max_bytes: Arc::new(AtomicU64::new(DEFAULT_MAX_BYTES)),
+            temp_counter: Arc::new(AtomicU64::new(0)),
+            total_bytes: Arc::new(AtomicU64::new(0)),
+            settings: Arc::new(Mutex::new(settings)),
+            owners: Arc::new(Mutex::new(Owners {
+                by_component: HashMap::new(),
+                dirty: false,
+                saved_at: Instant::now(),
+            })),
+        };
+        let entries = cache.entries()?;
+        for entry in &entries {
+            if let Some(owner) = by_component.get_mut(&entry.component_id) {
+                owner.bytes = entry.size;
+            }
+        }
+        cache.owners().by_component = by_component;
+        cache.total_bytes.store(
+            entries.iter().map(|entry| entry.size).sum(),
+            Ordering::Relaxed,
+        );
+        Ok(cache)
+    }
+
+    fn settings(&self) -> std::sync::MutexGuard<'_, CacheSettings> {
+        self.settings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn owners(&self) -> std::sync::MutexGuard<'_, Owners> {
+        self.owners
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Records that `component_id` was wanted for the project and playlist.
+    pub fn tag(&self, component_id: &str, project_id: Option<&str>, playlist_id: Option<&str>) {
+        if project_id.is_none() && playlist_id.is_none() {
+            return;
+        }
+        let mut owners = self.owners();
+        let owner = owners
+            .by_component
+            .entry(component_id.to_string())
+            .or_default();
+        let mut changed = false;
+        if let Some(project_id) = project_id
+            && owner.project_id.as_deref() != Some(project_id)
+        {
+            owner.project_id = Some(project_id.to_string());
+            changed = true;
+        }
+        if let Some(playlist_id) = playlist_id {
+            changed |= owner.playlist_ids.insert(playlist_id.to_string());
+        }
+        owners.dirty |= changed;
+        self.save_owners_if_due(&mut owners);
+    }
+
+    fn save_owners_if_due(&self, owners: &mut Owners) {
+        if owners.dirty && owners.saved_at.elapsed() >= OWNERS_SAVE_INTERVAL {
+            self.save_owners(owners);
+        }
+    }
+
+    fn save_owners(&self, owners: &mut Owners) {
+        let result = write_atomically(&self.dir.join(OWNERS_FILE), |temp| {
+            fs::write(temp, serde_json::to_vec(&owners.by_component)?)?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => owners.dirty = false,
+            Err(err) => log::warn!("Failed to save thumbnail owners: {err}"),
+        }
+        owners.saved_at = Instant::now();
+    }
+
+    /// Writes owners recorded since the last save.
+    pub fn flush(&self) {
+        let mut owners = self.owners();
+        if owners.dirty {
+            self.save_owners(&mut owners);
+        }
+    }
+
+    pub fn cache_settings(&self) -> CacheSettings {
+        self.settings().clone()
+    }
+
+    /// Applies new quotas and pins, trimming projects now over their quota.
+    pub fn set_cache_settings(&self, settings: CacheSettings) -> Result<()> {
+        let projects: Vec<String> = settings.project_quota_mb.keys().cloned().collect();
+        *self.settings() = settings;
+        for project_id in projects {
+            self.enforce_quota(&project_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_bytes(&self, max_bytes: u64) {
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+    }
+
+    /// Returns the cached thumbnail for `component_id` and marks it as recently used.
+    pub fn lookup(&self, component_id: &str) -> Result<Option<CachedThumbnail>> {
+        validate_component_id(component_id)?;
+        for ext in EXTENSIONS {
+            let path = self.dir.join(format!("{component_id}.{ext}"));
+            if let Ok(meta) = fs::metadata(&path) {
+                touch(&path);
+                return Ok(Some(CachedThumbnail {
+                    component_id: component_id.to_string(),
+                    path,
+                    size_bytes: meta.len(),
+                    url: thumbnail_url(component_id),
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Downloads `url` into the cache unless `component_id` is already cached,
+    /// recording it for the project and playlist either way. With a `lut`,
+    /// the thumbnail is color corrected and stored as PNG.
+    pub async fn fetch(
+        &self,
+        url: &str,
+        component_id: &str,
+        lut: Option<Arc<Lut>>,
+        project_id: Option<&str>,
+        playlist_id: Option<&str>,
+    ) -> Result<CachedThumbnail> {
+        if let Some(hit) = self.lookup(component_id)? {
+            self.tag(component_id, project_id, playlist_id);
+            return Ok(hit);
+        }
+
+        let response = network::client()
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut ext = extension_for(response.headers().get(CONTENT_TYPE));
+        let mut bytes = response.bytes().await?.to_vec();
+        if let Some(lut) = lut {
+            bytes = tauri::async_runtime::spawn_blocking(move || -> Result<Vec<u8>> {
+                let mut image = image::load_from_memory(&bytes)?.into_rgb8();
+                lut.apply_to_image(&mut image);
+                let mut png = std::io::Cursor::new(Vec::new());
+                image.write_to(&mut png, image::ImageFormat::Png)?;
+                Ok(png.into_inner())
+            })
+            .await??;
+            ext = "png";
+        }
+
+        // Write to a temp file first so a half-written thumbnail is never served
+        let path = self.dir.join(format!("{component_id}.{ext}"));
+        let n = self.temp_counter.fetch_add(1, Ordering::Relaxed);
+        let temp = self.dir.join(format!(".{component_id}.{n}.part"));
+        let size = bytes.len() as u64;
+        let cache = self.clone();
+        let target = path.clone();
+        let component = component_id.to_string();
+        let project = project_id.map(str::to_string);
+        let playlist = playlist_id.map(str::to_string);
+        tauri::async_runtime::spawn_blocking(move || {
+            fs::write(&temp, &bytes)?;
+            fs::rename(&temp, &target)?;
+            cache.tag(&component, project.as_deref(), playlist.as_deref());
+            cache.record_size(&component, size);
+            cache.trim_if_over(project.as_deref())
+        })
+        .await??;
+
+        Ok(CachedThumbnail {
+            component_id: component_id.to_string(),
+            path,
+            size_bytes: size,
+            url: thumbnail_url(component_id),
+        })
+    }
+
+    /// Counts a newly written thumbnail in the totals.
+    fn record_size(&self, component_id: &str, size: u64) {
+        // A replaced file leaves its old size in the total until the next eviction walk
+        self.total_bytes.fetch_add(size, Ordering::Relaxed);
+        if let Some(owner) = self.owners().by_component.get_mut(component_id) {
+            owner.bytes = size;
+        }
+    }
+
+    /// Trims the project to its quota and the cache to its limit, walking the
+    /// directory only once the running totals are over.
+    fn trim_if_over(&self, project_id: Option<&str>) -> Result<()> {
+        if let Some(project_id) = project_id {
+            let quota = self.settings().project_quota_mb.get(project_id).copied();
+            if let Some(quota_mb) = quota {
+                let used: u64 = self
+                    .owners()
+                    .by_component
+                    .values()
+                    .filter(|owner| owner.project_id.as_deref() == Some(project_id))
+                    .map(|owner| owner.bytes)
+                    .sum();
+                if used > quota_mb.saturating_mul(MIB) {
+                    self.enforce_quota(project_id)?;
+                }
+            }
+        }
+        let max_bytes = self.max_bytes.load(Ordering::Relaxed);
+        if self.total_bytes.load(Ordering::Relaxed) > max_bytes {
+            self.evict(max_bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Removes least recently used thumbnails until the cache fits in
+    /// `max_bytes`. Pinned playlists' thumbnails are kept even if it does not.
+    pub fn evict(&self, max_bytes: u64) -> Result<EvictionResult> {
+        let entries = self.entries()?;
+        // Forget owners of thumbnails removed behind our back, and refresh sizes
+        let present: HashMap<&str, u64> = entries
+            .iter()
+            .map(|entry| (entry.component_id.as_str(), entry.size))
+            .collect();
+        let mut owners = self.owners();
+        let before = owners.by_component.len();
+        owners.by_component.retain(|component_id, owner| {
+            match present.get(component_id.as_str()) {
+                Some(size) => {
+                    owner.bytes = *size;
+                    true
+                }
+                None => false,
+            }
+        });
+        owners.dirty |= owners.by_component.len() != before;
+        drop(owners);
+        let result = self.evict_entries(entries, max_bytes)?;
+        // Every thumbnail was just seen, so the running total can be corrected
+        self.total_bytes
+            .store(result.remaining_bytes, Ordering::Relaxed);
+        Ok(result)
+    }
+
+    /// Trims a project's thumbnails to its quota, if it has one.
+    fn enforce_quota(&self, project_id: &str) -> Result<()> {
+        let Some(quota_mb) = self.settings().project_quota_mb.get(project_id).copied() else {
+            return Ok(());
+        };
+        let owners = self.owners();
+        let entries: Vec<Entry> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| {
+                owners
+                    .by_component
+                    .get(&entry.component_id)
+                    .is_some_and(|owner| owner.project_id.as_deref() == Some(project_id))
+            })
+            .collect();
+        drop(owners);
+        let evicted = self.evict_entries(entries, quota_mb.saturating_mul(MIB))?;
+        if evicted.evicted > 0 {
+            log::info!(
+                "Evicted {} thumbnails of project {project_id} to fit its quota",
+                evicted.evicted
+            );
+        }
+        Ok(())
+    }
+
+    fn evict_entries(&self, mut entries: Vec<Entry>, max_bytes: u64) -> Result<EvictionResult> {
+        entries.sort_by_key(|entry| entry.accessed);
+        let pinned = self.settings().pinned_playlists.clone();
+        let mut owners = self.owners();
+
+        let mut remaining: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut result = EvictionResult {
+            evicted: 0,
+            freed_bytes: 0,
+            remaining_bytes: remaining,
+        };
+
+        for entry in entries {
+            if remaining <= max_bytes {
+                break;
+            }
+            let is_pinned = owners
+                .by_component
+                .get(&entry.component_id)
+                .is_some_and(|owner| !owner.playlist_ids.is_disjoint(&pinned));
+            if is_pinned {
+                continue;
+            }
+            match fs::remove_file(&entry.path) {
+                Ok(()) => {
+                    remaining -= entry.size;
+                    let _ = self.total_bytes.fetch_update(
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                        |total| Some(total.saturating_sub(entry.size)),
+                    );
+                    result.evicted += 1;
+                    result.freed_bytes += entry.size;
+                    owners.dirty |= owners.by_component.remove(&entry.component_id).is_some();
+                }
+                Err(err) => log::warn!("Failed to evict {}: {err}", entry.path.display()),
+            }
+        }
+        self.save_owners_if_due(&mut owners);
+
+        result.remaining_bytes = remaining;
+        Ok(result)
+    }
+
+    /// Thumbnail usage by project, largest first.
+    pub fn usage_by_project(&self) -> Result<Vec<ProjectUsage>> {
+        let entries = self.entries()?;
+        let settings = self.settings().clone();
+        let owners = self.owners();
+        let mut usage: HashMap<Option<String>, ProjectUsage> = HashMap::new();
+        for entry in entries {
+            let owner = owners.by_component.get(&entry.component_id);
+            let project_id = owner.and_then(|owner| owner.project_id.clone());
+            let project = usage
+                .entry(project_id.clone())
+                .or_insert_with(|| ProjectUsage {
+                    quota_bytes: project_id
+                        .as_ref()
+                        .and_then(|id| settings.project_quota_mb.get(id))
+                        .map(|mb| mb.saturating_mul(MIB)),
+                    project_id,
+                    thumbnails: 0,
+                    bytes: 0,
+                    pinned_bytes: 0,
+                });
+            project.thumbnails += 1;
+            project.bytes += entry.size;
+            if owner
+                .is_some_and(|owner| !owner.playlist_ids.is_disjoint(&settings.pinned_playlists))
+            {
+                project.pinned_bytes += entry.size;
+            }
+        }
+        let mut usage: Vec<ProjectUsage> = usage.into_values().collect();
+        usage.sort_by_key(|project| std::cmp::Reverse(project.bytes));
+        Ok(usage)
+    }
+
+    pub fn size_bytes(&self) -> Result<u64> {
+        Ok(self.total_bytes.load(Ordering::Relaxed))
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            // Partial downloads and the owners file
+            let hidden = dir_entry.file_name().to_string_lossy().starts_with('.');
+            let meta = dir_entry.metadata()?;
+            if hidden || !meta.is_file() {
+                continue;
+            }
+            let Some(component_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            entries.push(Entry {
+                component_id: component_id.to_string(),
+                path,
+                size: meta.len(),
+                accessed: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+fn load_settings(app: &AppHandle) -> Result<CacheSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn validate_component_id(component_id: &str) -> Result<()> {
+    let valid = !component_id.is_empty()
+        && component_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!(
+            "Invalid component id: {component_id}"
+        )))
+    }
+}
+
+fn extension_for(content_type: Option<&HeaderValue>) -> &'static str {
+    match content_type.and_then(|value| value.to_str().ok()) {
+        Some(value) if value.starts_with("image/png") => "png",
+        Some(value) if value.starts_with("image/webp") => "webp",
+        Some(value) if value.starts_with("image/gif") => "gif",
+        _ => "jpg",
+    }
+}
+
+fn thumbnail_url(component_id: &str) -> String {
+    // Windows webviews only allow custom schemes through the http://<scheme>.localhost form
+    if cfg!(windows) {
+        format!("http://{SCHEME}.localhost/component/{component_id}")
+    } else {
+        format!("{SCHEME}://component/{component_id}")
+    }
+}
+
+/// Splits a protocol URI into `(kind, id)`. Windows puts the scheme in the
+/// host as `http://astra-thumb.localhost/<kind>/<id>`, elsewhere the kind is
+/// the host: `astra-thumb://<kind>/<id>`.
+fn parse_uri(request: &Request<Vec<u8>>) -> Option<(String, String)> {
+    let uri = request.uri();
+    let path = uri.path().trim_matches('/');
+    let host = uri.host().unwrap_or_default();
+    let (kind, id) = if host.starts_with(SCHEME) || host.is_empty() || host == "localhost" {
+        path.split_once('/')?
+    } else {
+        (host, path)
+    };
+    Some((kind.to_string(), id.to_string()))
+}
+
+fn status(status: StatusCode) -> tauri::http::Response<Vec<u8>> {
+    tauri::http::Response::builder()
+        .status(status)
+        .body(Vec::new())
+        .unwrap_or_default()
+}
+
+async fn serve(
+    app: &AppHandle,
+    request: &Request<Vec<u8>>,
+) -> Result<tauri::http::Response<Vec<u8>>> {
+    let Some((kind, id)) = parse_uri(request) else {
+        return Ok(status(StatusCode::BAD_REQUEST));
+    };
+    if kind != "component" {
+        return Ok(status(StatusCode::NOT_FOUND));
+    }
+    let Some(thumbnail) = app.state::<ThumbnailCache>().lookup(&id).ok().flatten() else {
+        return Ok(status(StatusCode::NOT_FOUND));
+    };
+
+    let etag = format!("\"{id}-{}\"", thumbnail.size_bytes);
+    let not_modified = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value == etag);
+    let response = tauri::http::Response::builder()
+        .header(header::CACHE_CONTROL, CACHE_CONTROL)
+        .header(header::ETAG, &etag)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+    if not_modified {
+        return Ok(response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Vec::new())
+            .unwrap_or_default());
+    }
+    let body = tokio::fs::read(&thumbnail.path).await?;
+    Ok(response
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, media::content_type(&thumbnail.path))
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(body)
+        .unwrap_or_default())
+}
+
+/// Protocol handler passed to `register_asynchronous_uri_scheme_protocol`.
+pub fn handle(
+    ctx: UriSchemeContext<'_, Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let response = serve(&app, &request).await.unwrap_or_else(|err| {
+            log::warn!("Failed to serve {}: {err}", request.uri());
+            status(StatusCode::INTERNAL_SERVER_ERROR)
+        });
+        responder.respond(response);
+    });
+}
+
+fn touch(path: &Path) {
+    let result = fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    if let Err(err) = result {
+        log::debug!("Failed to update access time for {}: {err}", path.display());
+    }
+}
+
+#[tauri::command]
+pub async fn cache_thumbnail(
+    cache: State<'_, ThumbnailCache>,
+    color: State<'_, ColorManager>,
+    url: String,
+    component_id: String,
+    project_id: Option<String>,
+    playlist_id: Option<String>,
+) -> Result<CachedThumbnail> {
+    let lut = color.transform_for(project_id.as_deref())?;
+    cache
+        .fetch(
+            &url,
+            &component_id,
+            lut,
+            project_id.as_deref(),
+            playlist_id.as_deref(),
+        )
+        .await
+}
+
+#[tauri::command]
+pub fn get_cached_thumbnail(
+    cache: State<'_, ThumbnailCache>,
+    component_id: String,
+) -> Result<Option<CachedThumbnail>> {
+    cache.lookup(&component_id)
+}
+
+/// Trims the cache to `max_bytes` and keeps that as the limit for future inserts.
+#[tauri::command]
+pub fn evict_cache(cache: State<'_, ThumbnailCache>, max_bytes: u64) -> Result<EvictionResult> {
+    cache.set_max_bytes(max_bytes);
+    cache.evict(max_bytes)
+}
+
+#[tauri::command]
+pub fn get_cache_settings(cache: State<'_, ThumbnailCache>) -> CacheSettings {
+    cache.cache_settings()
+}
+
+/// Saves per-project quotas and pinned playlists, trimming any project
+/// already over its new quota.
+#[tauri::command]
+pub async fn set_cache_settings(app: AppHandle, settings: CacheSettings) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &settings)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<ThumbnailCache>().set_cache_settings(settings)
+    })
+    .await?
+}
+
+/// Returns the cached image bytes for `component_id` as a raw response.
+#[tauri::command]
+pub async fn read_cached_thumbnail(
+    cache: State<'_, ThumbnailCache>,
+    component_id: String,
+) -> Result<Response> {
+    let Some(thumbnail) = cache.lookup(&component_id)? else {
+        return Err(Error::InvalidInput(format!(
+            "Thumbnail {component_id} is not cached"
+        )));
+    };
+    Ok(Response::new(tokio::fs::read(&thumbnail.path).await?))
+}
@@ -0,0 +1,274 @@
+//! Bounded, prioritised thumbnail prefetching.
+//!
+//! The frontend reports which versions are on screen and which are close to
+//! it whenever the playlist scrolls. Each report replaces the previous one:
+//! queued thumbnails that are no longer wanted are dropped and their in-flight
+//! downloads aborted, visible items are fetched before nearby ones, and at most
+//! [`MAX_CONCURRENT`] downloads run at a time, or [`THROTTLED_CONCURRENT`]
+//! while throttled on battery. Results are announced with
+//! `thumbnails:prefetched`.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use crate::color::ColorManager;
+use crate::color::lut::Lut;
+use crate::error::Result;
+use crate::power;
+use crate::thumbnail_cache::{CachedThumbnail, ThumbnailCache};
+
+pub const PREFETCHED_EVENT: &str = "thumbnails:prefetched";
+const MAX_CONCURRENT: usize = 6;
+const THROTTLED_CONCURRENT: usize = 2;
+const THROTTLE_POLL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrefetchPriority {
+    /// On screen now.
+    #[default]
+    Visible,
+    /// Just outside the viewport, likely to scroll in next.
+    Nearby,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchItem {
+    pub version_id: String,
+    pub component_id: String,
+    pub url: String,
+    #[serde(default)]
+    pub priority: PrefetchPriority,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Prefetched {
+    version_id: String,
+    component_id: String,
+    thumbnail: Option<CachedThumbnail>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchStatus {
+    pub queued: usize,
+    pub in_flight: usize,
+}
+
+struct InFlight {
+    id: u64,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: VecDeque<PrefetchItem>,
+    in_flight: HashMap<String, InFlight>,
+    lut: Option<Arc<Lut>>,
+    project_id: Option<String>,
+    playlist_id: Option<String>,
+    next_id: u64,
+}
+
+#[derive(Default)]
+pub struct ThumbnailPrefetcher {
+    queue: Mutex<Queue>,
+    wake: Notify,
+}
+
+impl ThumbnailPrefetcher {
+    fn queue(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Replaces the wanted set, cancelling whatever fell out of it.
+    fn replace(
+        &self,
+        mut items: Vec<PrefetchItem>,
+        lut: Option<Arc<Lut>>,
+        project_id: Option<String>,
+        playlist_id: Option<String>,
+    ) {
+        // Stable, so the frontend's order within each priority is kept
+        items.sort_by_key(|item| item.priority);
+        let mut seen = HashSet::new();
+        items.retain(|item| seen.insert(item.component_id.clone()));
+
+        let mut queue = self.queue();
+        queue.in_flight.retain(|component_id, in_flight| {
+            let wanted = seen.contains(component_id);
+            if !wanted {
+                in_flight.task.abort();
+            }
+            wanted
+        });
+        let in_flight: HashSet<_> = queue.in_flight.keys().cloned().collect();
+        queue.pending = items
+            .into_iter()
+            .filter(|item| !in_flight.contains(&item.component_id))
+            .collect();
+        queue.lut = lut;
+        queue.project_id = project_id;
+        queue.playlist_id = playlist_id;
+        drop(queue);
+        self.wake.notify_one();
+    }
+
+    fn clear(&self) {
+        let mut queue = self.queue();
+        queue.pending.clear();
+        for (_, in_flight) in queue.in_flight.drain() {
+            in_flight.task.abort();
+        }
+    }
+
+    /// Starts the next queued download holding `permit` until it ends, or
+    /// hands the permit back when nothing is queued.
+    fn start_next(
+        &self,
+        app: &AppHandle,
+        permit: OwnedSemaphorePermit,
+    ) -> std::result::Result<(), OwnedSemaphorePermit> {
+        // The lock is held while spawning so the task cannot finish before it is registered
+        let mut queue = self.queue();
+        let Some(item) = queue.pending.pop_front() else {
+            return Err(permit);
+        };
+        let id = queue.next_id;
+        queue.next_id += 1;
+        let lut = queue.lut.clone();
+        let project_id = queue.project_id.clone();
+        let playlist_id = queue.playlist_id.clone();
+        let component_id = item.component_id.clone();
+        let app = app.clone();
+        let task = tauri::async_runtime::spawn(async move {
+            let result = app
+                .state::<ThumbnailCache>()
+                .fetch(
+                    &item.url,
+                    &item.component_id,
+                    lut,
+                    project_id.as_deref(),
+                    playlist_id.as_deref(),
+                )
+                .await;
+            drop(permit);
+            let prefetcher = app.state::<ThumbnailPrefetcher>();
+            let mut queue = prefetcher.queue();
+            if queue
+                .in_flight
+                .get(&item.component_id)
+                .is_some_and(|in_flight| in_flight.id == id)
+            {
+                queue.in_flight.remove(&item.component_id);
+            }
+            drop(queue);
+
+            let (thumbnail, error) = match result {
+                Ok(thumbnail) => (Some(thumbnail), None),
+                Err(err) => {
+                    log::debug!("Thumbnail prefetch for {} failed: {err}", item.component_id);
+                    (None, Some(err.to_string()))
+                }
+            };
+            let prefetched = Prefetched {
+                version_id: item.version_id,
+                component_id: item.component_id,
+                thumbnail,
+                error,
+            };
+            if let Err(err) = app.emit(PREFETCHED_EVENT, &prefetched) {
+                log::warn!("Failed to emit prefetched thumbnail: {err}");
+            }
+        });
+        queue.in_flight.insert(component_id, InFlight { id, task });
+        Ok(())
+    }
+}
+
+async fn run(app: AppHandle) {
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT));
+    let prefetcher = app.state::<ThumbnailPrefetcher>();
+    loop {
+        let Ok(mut permit) = semaphore.clone().acquire_owned().await else {
+            return;
+        };
+        if power::throttled(&app) && prefetcher.queue().in_flight.len() >= THROTTLED_CONCURRENT {
+            drop(permit);
+            tokio::time::sleep(THROTTLE_POLL).await;
+            continue;
+        }
+        while let Err(returned) = prefetcher.start_next(&app, permit) {
+            permit = returned;
+            prefetcher.wake.notified().await;
+        }
+    }
+}
+
+/// Starts the prefetch dispatcher.
+pub fn init(app: &AppHandle) {
+    app.manage(ThumbnailPrefetcher::default());
+    tauri::async_runtime::spawn(run(app.clone()));
+}
+
+/// Replaces the thumbnails to prefetch. Items already cached are reported
+/// straight away; anything from an earlier call that is not listed is cancelled.
+#[tauri::command]
+pub fn set_thumbnail_prefetch(
+    app: AppHandle,
+    prefetcher: State<'_, ThumbnailPrefetcher>,
+    cache: State<'_, ThumbnailCache>,
+    color: State<'_, ColorManager>,
+    items: Vec<PrefetchItem>,
+    project_id: Option<String>,
+    playlist_id: Option<String>,
+) -> Result<()> {
+    let lut = color.transform_for(project_id.as_deref())?;
+    let mut wanted = Vec::with_capacity(items.len());
+    for item in items {
+        match cache.lookup(&item.component_id)? {
+            Some(thumbnail) => {
+                cache.tag(
+                    &item.component_id,
+                    project_id.as_deref(),
+                    playlist_id.as_deref(),
+                );
+                let prefetched = Prefetched {
+                    version_id: item.version_id,
+                    component_id: item.component_id,
+                    thumbnail: Some(thumbnail),
+                    error: None,
+                };
+                app.emit(PREFETCHED_EVENT, &prefetched)?;
+            }
+            None => wanted.push(item),
+        }
+    }
+    prefetcher.replace(wanted, lut, project_id, playlist_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn cancel_thumbnail_prefetch(prefetcher: State<'_, ThumbnailPrefetcher>) {
+    prefetcher.clear();
+}
+
+#[tauri::command]
+pub fn get_thumbnail_prefetch_status(prefetcher: State<'_, ThumbnailPrefetcher>) -> PrefetchStatus {
+    let queue = prefetcher.queue();
+    PrefetchStatus {
+        queued: queue.pending.len(),
+        in_flight: queue.in_flight.len(),
+    }
+}
@@ -0,0 +1,281 @@
+//! SMPTE timecode and frame-rate math.
+//!
+//! Rates may be given as a number or as a `num/den` ratio. The NTSC rates
+//! (23.976, 29.97, 59.94 and so on) are snapped to their exact `n*1000/1001`
+//! values, so seconds computed from frames do not drift over a long reel.
+//! Timecode counts frames at the nominal base, e.g. 24 for 23.976, and
+//! drop-frame, written with a `;` before the frames, applies to 29.97 and
+//! 59.94 only.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::media::{frames, metadata};
+
+/// Whole rates with an NTSC variant running 1000/1001 slower.
+const NTSC_BASES: [f64; 6] = [24.0, 30.0, 48.0, 60.0, 120.0, 240.0];
+
+/// A frame rate from the frontend: `24`, `23.976` or `"24000/1001"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum FrameRate {
+    Fps(f64),
+    Text(String),
+}
+
+impl FrameRate {
+    pub(crate) fn fps(&self) -> Result<f64> {
+        match self {
+            Self::Fps(fps) => validate_rate(*fps),
+            Self::Text(text) => parse_rate(text),
+        }
+    }
+}
+
+fn validate_rate(fps: f64) -> Result<f64> {
+    if fps.is_finite() && fps > 0.0 && fps <= 1000.0 {
+        Ok(exact_rate(fps))
+    } else {
+        Err(Error::InvalidInput(format!("Invalid frame rate {fps}")))
+    }
+}
+
+/// Parses `24`, `23.976` or `24000/1001`.
+pub(crate) fn parse_rate(text: &str) -> Result<f64> {
+    let invalid = || Error::InvalidInput(format!("Invalid frame rate {text:?}"));
+    let fps = match text.trim().split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.trim().parse().map_err(|_| invalid())?;
+            let den: f64 = den.trim().parse().map_err(|_| invalid())?;
+            if den == 0.0 {
+                return Err(invalid());
+            }
+            num / den
+        }
+        None => text.trim().parse().map_err(|_| invalid())?,
+    };
+    validate_rate(fps)
+}
+
+/// Snaps rounded NTSC rates such as 29.97 to their exact value.
+pub(crate) fn exact_rate(fps: f64) -> f64 {
+    NTSC_BASES
+        .iter()
+        .map(|base| base * 1000.0 / 1001.0)
+        .find(|ntsc| (fps - ntsc).abs() < 0.005)
+        .unwrap_or(fps)
+}
+
+/// Frames per second rounded to the nominal timecode base, e.g. 24 for 23.976.
+fn timecode_base(rate: f64) -> i64 {
+    (rate.round() as i64).max(1)
+}
+
+/// Whether `rate` is one drop-frame timecode is defined for.
+pub(crate) fn supports_drop_frame(rate: f64) -> bool {
+    let base = timecode_base(rate);
+    base % 30 == 0 && (exact_rate(rate) - base as f64).abs() > 0.001
+}
+
+/// Formats a frame count as SMPTE timecode. Drop-frame only applies to
+/// 29.97 and 59.94 and is written with a `;` before the frames.
+pub(crate) fn format_timecode(frames: i64, rate: f64, drop_frame: bool) -> String {
+    let base = timecode_base(rate);
+    let drop_frame = drop_frame && base % 30 == 0;
+    let mut frames = frames.max(0);
+    if drop_frame {
+        // Re-insert the frame numbers skipped at each non-tenth minute
+        let dropped = base / 15;
+        let per_ten_minutes = base * 600 - dropped * 9;
+        let per_minute = base * 60 - dropped;
+        let tens = frames / per_ten_minutes;
+        let rest = frames % per_ten_minutes;
+        frames += dropped * 9 * tens;
+        if rest > dropped {
+            frames += dropped * ((rest - dropped) / per_minute);
+        }
+    }
+    let ff = frames % base;
+    let ss = frames / base % 60;
+    let mm = frames / (base * 60) % 60;
+    let hh = frames / (base * 3600) % 24;
+    let separator = if drop_frame { ';' } else { ':' };
+    format!("{hh:02}:{mm:02}:{ss:02}{separator}{ff:02}")
+}
+
+/// Parses `HH:MM:SS:FF` (or `;` drop-frame) timecode into a frame count.
+pub(crate) fn parse_timecode(timecode: &str, rate: f64) -> Result<i64> {
+    let invalid = || Error::InvalidInput(format!("Invalid timecode: {timecode}"));
+    let drop_frame = timecode.contains(';');
+    let parts: Vec<i64> = timecode
+        .split([':', ';'])
+        .map(|part| part.trim().parse().map_err(|_| invalid()))
+        .collect::<Result<_>>()?;
+    let [hh, mm, ss, ff] = parts[..] else {
+        return Err(invalid());
+    };
+    let base = timecode_base(rate);
+    if [hh, mm, ss, ff].iter().any(|part| *part < 0) || mm >= 60 || ss >= 60 || ff >= base {
+        return Err(invalid());
+    }
+    let mut frames = ((hh * 60 + mm) * 60 + ss) * base + ff;
+    if drop_frame && base % 30 == 0 {
+        // Frames 0 and 1 (0-3 at 59.94) do not exist at non-tenth minutes
+        if ss == 0 && mm % 10 != 0 && ff < base / 15 {
+            return Err(invalid());
+        }
+        let total_minutes = hh * 60 + mm;
+        frames -= base / 15 * (total_minutes - total_minutes / 10);
+    }
+    Ok(frames)
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimecodeSource {
+    Exr,
+    Dpx,
+    Movie,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaTimecode {
+    pub source: TimecodeSource,
+    pub timecode: String,
+    pub drop_frame: bool,
+    pub frame_rate: Option<f64>,
+    /// The timecode as a frame count, when the rate is known.
+    pub frame: Option<i64>,
+}
+
+impl MediaTimecode {
+    fn new(source: TimecodeSource, timecode: String, frame_rate: Option<f64>) -> Self {
+        let frame_rate = frame_rate.map(exact_rate);
+        Self {
+            source,
+            drop_frame: timecode.contains(';'),
+            frame: frame_rate.and_then(|rate| parse_timecode(&timecode, rate).ok()),
+            timecode,
+            frame_rate,
+        }
+    }
+}
+
+/// Reads the start timecode of a QuickTime or MXF from its `tmcd` track or
+/// container tags.
+async fn movie_timecode(app: &AppHandle, path: &Path) -> Result<Option<MediaTimecode>> {
+    let output = frames::run(
+        app,
+        "ffprobe",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-show_entries".into(),
+            "format_tags=timecode:stream=codec_type,r_frame_rate:stream_tags=timecode".into(),
+            "-of".into(),
+            "json".into(),
+            path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    let probe: Value = serde_json::from_slice(&output.stdout)?;
+    let streams = probe["streams"].as_array().cloned().unwrap_or_default();
+    let frame_rate = streams
+        .iter()
+        .find(|stream| stream["codec_type"] == "video")
+        .and_then(|stream| stream["r_frame_rate"].as_str())
+        .and_then(|rate| parse_rate(rate).ok());
+    let timecode = streams
+        .iter()
+        .map(|stream| &stream["tags"]["timecode"])
+        .chain([&probe["format"]["tags"]["timecode"]])
+        .find_map(Value::as_str);
+    Ok(timecode
+        .map(|timecode| MediaTimecode::new(TimecodeSource::Movie, timecode.into(), frame_rate)))
+}
+
+/// `rate` as fps, or 29.97 with drop-frame by default when `drop_frame` is unset.
+fn resolve(rate: &FrameRate, drop_frame: Option<bool>) -> Result<(f64, bool)> {
+    let fps = rate.fps()?;
+    let drop_frame = drop_frame.unwrap_or_else(|| supports_drop_frame(fps));
+    if drop_frame && !supports_drop_frame(fps) {
+        return Err(Error::InvalidInput(format!(
+            "Drop-frame timecode is not defined at {fps:.3} fps"
+        )));
+    }
+    Ok((fps, drop_frame))
+}
+
+#[tauri::command]
+pub fn frames_to_timecode(
+    frames: i64,
+    rate: FrameRate,
+    drop_frame: Option<bool>,
+) -> Result<String> {
+    let (fps, drop_frame) = resolve(&rate, drop_frame)?;
+    Ok(format_timecode(frames, fps, drop_frame))
+}
+
+#[tauri::command]
+pub fn timecode_to_frames(timecode: String, rate: FrameRate) -> Result<i64> {
+    parse_timecode(&timecode, rate.fps()?)
+}
+
+/// Seconds of real time at the start of `frames`.
+#[tauri::command]
+pub fn frames_to_seconds(frames: i64, rate: FrameRate) -> Result<f64> {
+    Ok(frames as f64 / rate.fps()?)
+}
+
+/// The frame showing at `seconds`.
+#[tauri::command]
+pub fn seconds_to_frames(seconds: f64, rate: FrameRate) -> Result<i64> {
+    if !seconds.is_finite() {
+        return Err(Error::InvalidInput(format!("Invalid time {seconds}")));
+    }
+    // A hair of tolerance so frame starts computed in floating point land on their frame
+    Ok((seconds * rate.fps()? + 1e-6).floor() as i64)
+}
+
+/// Re-expresses `timecode` at another rate, keeping the same point in time.
+#[tauri::command]
+pub fn convert_timecode(
+    timecode: String,
+    from_rate: FrameRate,
+    to_rate: FrameRate,
+    drop_frame: Option<bool>,
+) -> Result<String> {
+    let from = from_rate.fps()?;
+    let (to, drop_frame) = resolve(&to_rate, drop_frame)?;
+    let seconds = parse_timecode(&timecode, from)? as f64 / from;
+    let frames = (seconds * to + 1e-6).floor() as i64;
+    Ok(format_timecode(frames, to, drop_frame))
+}
+
+/// Reads the timecode of an EXR or DPX frame, or the start timecode of a
+/// movie. `None` when the file carries none.
+#[tauri::command]
+pub async fn read_media_timecode(app: AppHandle, path: PathBuf) -> Result<Option<MediaTimecode>> {
+    if !path.is_file() {
+        return Err(Error::InvalidInput(format!("{} not found", path.display())));
+    }
+    let source = match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("exr") => TimecodeSource::Exr,
+        Some("dpx") => TimecodeSource::Dpx,
+        _ => return movie_timecode(&app, &path).await,
+    };
+    let meta = tauri::async_runtime::spawn_blocking(move || metadata::read(&path)).await??;
+    Ok(meta
+        .timecode
+        .map(|timecode| MediaTimecode::new(source, timecode, meta.frame_rate)))
+}
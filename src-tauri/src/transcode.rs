@@ -0,0 +1,579 @@
+//! Playable previews for clients and note attachments.
+//!
+//! Transcodes queue up and run one at a time, since a single ffmpeg encode
+//! already keeps every core busy. ffmpeg comes from `ASTRANOTES_FFMPEG_DIR`
+//! or `PATH`, like the other media tools. Two presets cover what
+//! coordinators send out: H.264 at 1080p with a burn-in, and ProRes 422
+//! Proxy at the source resolution for editorial. The burn-in is the
+//! project's watermark template, or the version name when there is none.
+//! Progress is read from ffmpeg's `-progress` output and reported as
+//! `transcode:progress`. Outputs go to `transcodes/` in the app cache unless
+//! the request names a file, ready to add to a delivery. With a note ID, the
+//! output is uploaded as an attachment of that note once encoded.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tokio::sync::Semaphore;
+
+use crate::error::{Error, Result};
+use crate::media::frames;
+use crate::paths;
+use crate::store::now_millis;
+use crate::taskbar;
+use crate::transfers::Priority;
+use crate::uploads::{self, UploadMeta};
+use crate::watermark::{self, WatermarkRequest};
+
+pub const PROGRESS_EVENT: &str = "transcode:progress";
+const OUTPUT_DIR: &str = "transcodes";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const PREVIEW_WIDTH: u32 = 1920;
+const PREVIEW_HEIGHT: u32 = 1080;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TranscodePreset {
+    /// H.264 at 1080p with a burn-in, letterboxed to fit.
+    H264BurnIn,
+    /// ProRes 422 Proxy at the source resolution.
+    ProresProxy,
+}
+
+impl TranscodePreset {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::H264BurnIn => "mp4",
+            Self::ProresProxy => "mov",
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::H264BurnIn => "preview",
+            Self::ProresProxy => "proxy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscodeRequest {
+    /// Any clip ffmpeg can open.
+    pub path: PathBuf,
+    pub preset: TranscodePreset,
+    /// Output file; defaults to `transcodes/` in the app cache.
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+    /// Burnt in when the project has no watermark template; defaults to the
+    /// file name.
+    #[serde(default)]
+    pub version_name: Option<String>,
+    /// Selects the watermark template and fills its tokens.
+    #[serde(default)]
+    pub watermark: WatermarkRequest,
+    /// Uploads the output as an attachment of this note.
+    #[serde(default)]
+    pub note_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TranscodeStatus {
+    Queued,
+    Running,
+    Uploading,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TranscodeStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscodeJob {
+    pub id: String,
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub preset: TranscodePreset,
+    pub status: TranscodeStatus,
+    /// 0 to 1; stays at 0 when the source's duration is unknown.
+    pub progress: f64,
+    pub error: Option<String>,
+    /// Attachment component, once uploaded.
+    pub component_id: Option<String>,
+    pub created_at: i64,
+}
+
+struct Job {
+    request: TranscodeRequest,
+    cancelled: AtomicBool,
+    child: Mutex<Option<CommandChild>>,
+    snapshot: Mutex<TranscodeJob>,
+    last_emit: Mutex<Instant>,
+}
+
+impl Job {
+    fn lock(&self) -> std::sync::MutexGuard<'_, TranscodeJob> {
+        self.snapshot
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn snapshot(&self) -> TranscodeJob {
+        self.lock().clone()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Applies `f` and emits progress. Progress from ffmpeg passes `throttle`.
+    fn update(&self, app: &AppHandle, throttle: bool, f: impl FnOnce(&mut TranscodeJob)) {
+        let snapshot = {
+            let mut snapshot = self.lock();
+            f(&mut snapshot);
+            snapshot.clone()
+        };
+        if throttle {
+            let mut last_emit = self
+                .last_emit
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if last_emit.elapsed() < PROGRESS_INTERVAL {
+                return;
+            }
+            *last_emit = Instant::now();
+        }
+        let source = format!("transcode:{}", snapshot.id);
+        match snapshot.status {
+            TranscodeStatus::Running => {
+                taskbar::update(app, &source, (snapshot.progress * 1000.0) as u64, 1000)
+            }
+            TranscodeStatus::Queued | TranscodeStatus::Uploading => {}
+            _ => taskbar::finish(app, &source),
+        }
+        if let Err(err) = app.emit(PROGRESS_EVENT, snapshot) {
+            log::warn!("Failed to emit transcode progress: {err}");
+        }
+    }
+}
+
+pub struct Transcodes {
+    jobs: Mutex<Vec<Arc<Job>>>,
+    /// The encode slot; tokio hands permits out in the order they were asked for.
+    slot: Semaphore,
+}
+
+impl Default for Transcodes {
+    fn default() -> Self {
+        Self {
+            jobs: Mutex::default(),
+            slot: Semaphore::new(1),
+        }
+    }
+}
+
+impl Transcodes {
+    fn jobs(&self) -> std::sync::MutexGuard<'_, Vec<Arc<Job>>> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+async fn probe_duration(app: &AppHandle, path: &Path) -> Result<f64> {
+    let output = frames::run(
+        app,
+        "ffprobe",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-show_entries".into(),
+            "format=duration".into(),
+            "-of".into(),
+            "default=noprint_wrappers=1:nokey=1".into(),
+            path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    let duration = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    duration
+        .parse::<f64>()
+        .ok()
+        .filter(|duration| duration.is_finite() && *duration > 0.0)
+        .ok_or_else(|| Error::Media(format!("Unreadable duration {duration:?}")))
+}
+
+/// Renders the burn-in as a transparent 1080p overlay.
+async fn burn_in(app: &AppHandle, request: &TranscodeRequest, dir: &Path) -> Result<PathBuf> {
+    let name = request.version_name.clone().unwrap_or_else(|| {
+        request
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    let watermark = watermark::burn_in(app, &request.watermark, &name)?;
+    let overlay = dir.join(format!("{}.png", uuid::Uuid::new_v4().simple()));
+    let target = overlay.clone();
+    tauri::async_runtime::spawn_blocking(move || -> Result<()> {
+        let layer = watermark.apply(RgbaImage::new(PREVIEW_WIDTH, PREVIEW_HEIGHT))?;
+        layer.save_with_format(&target, image::ImageFormat::Png)?;
+        Ok(())
+    })
+    .await??;
+    Ok(overlay)
+}
+
+fn encoder_args(preset: TranscodePreset, overlay: Option<&Path>) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    match (preset, overlay) {
+        (TranscodePreset::H264BurnIn, Some(overlay)) => {
+            args.extend([
+                "-i".into(),
+                overlay.to_string_lossy().into_owned(),
+                "-filter_complex".into(),
+                format!(
+                    "[0:v]scale={PREVIEW_WIDTH}:{PREVIEW_HEIGHT}:force_original_aspect_ratio=decrease,\
+                     pad={PREVIEW_WIDTH}:{PREVIEW_HEIGHT}:(ow-iw)/2:(oh-ih)/2,setsar=1[base];\
+                     [base][1:v]overlay=0:0:format=auto,format=yuv420p[v]"
+                ),
+                "-map".into(),
+                "[v]".into(),
+            ]);
+        }
+        (TranscodePreset::H264BurnIn, None) => {
+            args.extend([
+                "-vf".into(),
+                format!(
+                    "scale={PREVIEW_WIDTH}:{PREVIEW_HEIGHT}:force_original_aspect_ratio=decrease,\
+                     pad={PREVIEW_WIDTH}:{PREVIEW_HEIGHT}:(ow-iw)/2:(oh-ih)/2,setsar=1,format=yuv420p"
+                ),
+                "-map".into(),
+                "0:v:0".into(),
+            ]);
+        }
+        (TranscodePreset::ProresProxy, _) => {
+            args.extend(["-map".into(), "0:v:0".into()]);
+        }
+    }
+    args.extend(["-map".into(), "0:a?".into()]);
+    let codec: &[&str] = match preset {
+        TranscodePreset::H264BurnIn => &[
+            "-c:v",
+            "libx264",
+            "-crf",
+            "20",
+            "-preset",
+            "medium",
+            "-c:a",
+            "aac",
+            "-b:a",
+            "192k",
+            "-movflags",
+            "+faststart",
+        ],
+        TranscodePreset::ProresProxy => &[
+            "-c:v",
+            "prores_ks",
+            "-profile:v",
+            "0",
+            "-pix_fmt",
+            "yuv422p10le",
+            "-vendor",
+            "apl0",
+            "-c:a",
+            "pcm_s16le",
+        ],
+    };
+    args.extend(codec.iter().map(|arg| arg.to_string()));
+    args
+}
+
+/// Runs ffmpeg into the output's partial file, reporting progress.
+async fn encode(app: &AppHandle, job: &Job, output: &Path) -> Result<()> {
+    let request = &job.request;
+    let duration = match probe_duration(app, &request.path).await {
+        Ok(duration) => Some(duration),
+        Err(err) => {
+            log::warn!(
+                "Transcoding {} without progress: {err}",
+                request.path.display()
+            );
+            None
+        }
+    };
+    let work_dir = paths::cache_dir(app)?.join(OUTPUT_DIR);
+    tokio::fs::create_dir_all(&work_dir).await?;
+    if let Some(parent) = output.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let overlay = match request.preset {
+        TranscodePreset::H264BurnIn => Some(burn_in(app, request, &work_dir).await?),
+        TranscodePreset::ProresProxy => None,
+    };
+
+    let partial = watermark::partial_path(output);
+    let mut args: Vec<String> = vec![
+        "-v".into(),
+        "error".into(),
+        "-nostats".into(),
+        "-progress".into(),
+        "pipe:1".into(),
+        "-y".into(),
+        "-i".into(),
+        request.path.to_string_lossy().into_owned(),
+    ];
+    args.extend(encoder_args(request.preset, overlay.as_deref()));
+    args.push(partial.to_string_lossy().into_owned());
+
+    let result = async {
+        let (mut events, child) = app
+            .shell()
+            .command(frames::tool("ffmpeg"))
+            .args(args)
+            .spawn()
+            .map_err(|err| Error::Media(format!("Failed to run ffmpeg: {err}")))?;
+        *job.child
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(child);
+        // A cancel that landed before the child was stored has nothing to kill
+        if job.is_cancelled() {
+            kill(job);
+        }
+        let mut last_line = String::new();
+        while let Some(event) = events.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => {
+                    let line = String::from_utf8_lossy(&line);
+                    let micros = line
+                        .trim()
+                        .strip_prefix("out_time_us=")
+                        .and_then(|value| value.parse::<f64>().ok());
+                    if let (Some(micros), Some(duration)) = (micros, duration) {
+                        let progress = (micros / 1_000_000.0 / duration).clamp(0.0, 1.0);
+                        job.update(app, true, |snapshot| snapshot.progress = progress);
+                    }
+                }
+                CommandEvent::Stderr(line) => {
+                    let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                    if !line.is_empty() {
+                        last_line = line;
+                    }
+                }
+                CommandEvent::Terminated(status) if status.code == Some(0) => return Ok(()),
+                CommandEvent::Terminated(_) if job.is_cancelled() => {
+                    return Err(Error::Media("Cancelled".into()));
+                }
+                CommandEvent::Terminated(_) => {
+                    return Err(Error::Media(format!("ffmpeg failed: {last_line}")));
+                }
+                _ => {}
+            }
+        }
+        Err(Error::Media("ffmpeg exited unexpectedly".into()))
+    }
+    .await;
+    job.child
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take();
+    if let Some(overlay) = overlay {
+        let _ = tokio::fs::remove_file(overlay).await;
+    }
+    if let Err(err) = result {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(err);
+    }
+    tokio::fs::rename(&partial, output).await?;
+    Ok(())
+}
+
+fn kill(job: &Job) {
+    let child = job
+        .child
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take();
+    if let Some(child) = child
+        && let Err(err) = child.kill()
+    {
+        log::warn!("Failed to stop ffmpeg: {err}");
+    }
+}
+
+async fn run(app: AppHandle, job: Arc<Job>) {
+    let transcodes = app.state::<Transcodes>();
+    let Ok(_permit) = transcodes.slot.acquire().await else {
+        return;
+    };
+    if job.is_cancelled() {
+        return;
+    }
+    job.update(&app, false, |snapshot| {
+        snapshot.status = TranscodeStatus::Running
+    });
+    let output = job.snapshot().output;
+    let mut result = encode(&app, &job, &output).await;
+    if result.is_ok()
+        && !job.is_cancelled()
+        && let Some(note_id) = job.request.note_id.clone()
+    {
+        job.update(&app, false, |snapshot| {
+            snapshot.status = TranscodeStatus::Uploading;
+            snapshot.progress = 1.0;
+        });
+        let meta = UploadMeta {
+            connection: None,
+            note_id: Some(note_id),
+            name: None,
+            priority: Priority::Normal,
+        };
+        result = uploads::upload_attachment(app.clone(), output.clone(), meta)
+            .await
+            .map(|upload| {
+                job.lock().component_id = Some(upload.component_id);
+            });
+    }
+    job.update(&app, false, |snapshot| match result {
+        _ if job.is_cancelled() => snapshot.status = TranscodeStatus::Cancelled,
+        Ok(()) => {
+            snapshot.status = TranscodeStatus::Completed;
+            snapshot.progress = 1.0;
+        }
+        Err(err) => {
+            log::warn!("Transcode of {} failed: {err}", snapshot.source.display());
+            snapshot.status = TranscodeStatus::Failed;
+            snapshot.error = Some(err.to_string());
+        }
+    });
+}
+
+fn default_output(app: &AppHandle, request: &TranscodeRequest) -> Result<PathBuf> {
+    let stem = request
+        .path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "clip".into());
+    Ok(paths::cache_dir(app)?.join(OUTPUT_DIR).join(format!(
+        "{stem}_{}.{}",
+        request.preset.suffix(),
+        request.preset.extension()
+    )))
+}
+
+/// Queues a transcode; it starts once the jobs ahead of it finish.
+#[tauri::command]
+pub fn enqueue_transcode(
+    app: AppHandle,
+    transcodes: State<'_, Transcodes>,
+    request: TranscodeRequest,
+) -> Result<TranscodeJob> {
+    if !request.path.is_file() {
+        return Err(Error::InvalidInput(format!(
+            "{} not found",
+            request.path.display()
+        )));
+    }
+    let output = match &request.output {
+        Some(output) => output.clone(),
+        None => default_output(&app, &request)?,
+    };
+    if output == request.path {
+        return Err(Error::InvalidInput(
+            "A transcode cannot overwrite its source".into(),
+        ));
+    }
+    let mut jobs = transcodes.jobs();
+    if jobs.iter().any(|job| {
+        let snapshot = job.snapshot();
+        !snapshot.status.is_finished() && snapshot.output == output
+    }) {
+        return Err(Error::InvalidInput(format!(
+            "{} is already being transcoded",
+            output.display()
+        )));
+    }
+    let job = Arc::new(Job {
+        snapshot: Mutex::new(TranscodeJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: request.path.clone(),
+            output,
+            preset: request.preset,
+            status: TranscodeStatus::Queued,
+            progress: 0.0,
+            error: None,
+            component_id: None,
+            created_at: now_millis(),
+        }),
+        request,
+        cancelled: AtomicBool::new(false),
+        child: Mutex::default(),
+        last_emit: Mutex::new(Instant::now()),
+    });
+    jobs.push(job.clone());
+    drop(jobs);
+    job.update(&app, false, |_| {});
+    let snapshot = job.snapshot();
+    tauri::async_runtime::spawn(run(app, job));
+    Ok(snapshot)
+}
+
+/// Transcodes in the order they were queued, finished ones included.
+#[tauri::command]
+pub fn list_transcodes(transcodes: State<'_, Transcodes>) -> Vec<TranscodeJob> {
+    transcodes.jobs().iter().map(|job| job.snapshot()).collect()
+}
+
+/// Stops a running transcode, or drops it from the queue.
+#[tauri::command]
+pub fn cancel_transcode(
+    app: AppHandle,
+    transcodes: State<'_, Transcodes>,
+    id: String,
+) -> Result<()> {
+    let job = transcodes
+        .jobs()
+        .iter()
+        .find(|job| job.lock().id == id)
+        .cloned()
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown transcode: {id}")))?;
+    match job.snapshot().status {
+        status if status.is_finished() => return Ok(()),
+        TranscodeStatus::Uploading => {
+            return Err(Error::InvalidInput(
+                "The transcode is done and already uploading".into(),
+            ));
+        }
+        _ => {}
+    }
+    job.cancelled.store(true, Ordering::Relaxed);
+    kill(&job);
+    if job.snapshot().status == TranscodeStatus::Queued {
+        job.update(&app, false, |snapshot| {
+            snapshot.status = TranscodeStatus::Cancelled
+        });
+    }
+    Ok(())
+}
+
+/// Forgets finished transcodes. Their output files are kept.
+#[tauri::command]
+pub fn clear_finished_transcodes(transcodes: State<'_, Transcodes>) {
+    transcodes
+        .jobs()
+        .retain(|job| !job.snapshot().status.is_finished());
+}
@@ -0,0 +1,235 @@
+//! Local speech-to-text for voice notes.
+//!
+//! Audio never leaves the machine: it is resampled to 16 kHz mono WAV with
+//! ffmpeg and fed to a whisper.cpp `whisper-cli` binary with a ggml model the
+//! studio provides. Both paths are configured per machine in
+//! `transcription.json`; the binary otherwise comes from `PATH`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::process::CommandEvent;
+
+use crate::error::{Error, Result};
+use crate::media::frames;
+use crate::paths;
+
+const SETTINGS_FILE: &str = "transcription.json";
+pub const PROGRESS_EVENT: &str = "transcription:progress";
+
+static PROGRESS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"progress\s*=\s*(\d+)%").expect("valid progress pattern"));
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TranscriptionSettings {
+    pub whisper_path: Option<PathBuf>,
+    /// A ggml model file, e.g. `ggml-base.en.bin`.
+    pub model_path: Option<PathBuf>,
+    pub threads: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TranscriptionProgress {
+    path: PathBuf,
+    percent: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    pub path: PathBuf,
+    pub language: String,
+    pub text: String,
+}
+
+fn load_settings(app: &AppHandle) -> Result<TranscriptionSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn emit_progress(app: &AppHandle, path: &Path, percent: u32) {
+    let progress = TranscriptionProgress {
+        path: path.to_path_buf(),
+        percent,
+    };
+    if let Err(err) = app.emit(PROGRESS_EVENT, progress) {
+        log::warn!("Failed to emit transcription progress: {err}");
+    }
+}
+
+/// `auto` or an ISO 639-1 code such as `en`.
+fn validate_language(language: &str) -> Result<()> {
+    let valid = language == "auto"
+        || ((2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase()));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!("Invalid language: {language}")))
+    }
+}
+
+async fn run_whisper(
+    app: &AppHandle,
+    settings: &TranscriptionSettings,
+    model: &Path,
+    audio: &Path,
+    wav: &Path,
+    output_base: &Path,
+    language: &str,
+) -> Result<()> {
+    let binary = settings
+        .whisper_path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("whisper-cli"));
+    let mut args = vec![
+        "-m".to_string(),
+        model.to_string_lossy().into_owned(),
+        "-f".into(),
+        wav.to_string_lossy().into_owned(),
+        "-l".into(),
+        language.to_string(),
+        "-otxt".into(),
+        "-of".into(),
+        output_base.to_string_lossy().into_owned(),
+        "-pp".into(),
+    ];
+    if let Some(threads) = settings.threads {
+        args.extend(["-t".into(), threads.to_string()]);
+    }
+
+    let (mut events, _child) = app
+        .shell()
+        .command(binary.to_string_lossy().into_owned())
+        .args(args)
+        .spawn()
+        .map_err(|err| {
+            Error::Media(format!(
+                "Could not start {}: {err}. Check the whisper path in settings.",
+                binary.display()
+            ))
+        })?;
+
+    let mut last_line = String::new();
+    while let Some(event) = events.recv().await {
+        match event {
+            CommandEvent::Stderr(line) | CommandEvent::Stdout(line) => {
+                let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                if let Some(percent) = PROGRESS
+                    .captures(&line)
+                    .and_then(|captures| captures[1].parse().ok())
+                {
+                    emit_progress(app, audio, percent);
+                } else if !line.is_empty() {
+                    last_line = line;
+                }
+            }
+            CommandEvent::Terminated(status) if status.code == Some(0) => return Ok(()),
+            CommandEvent::Terminated(_) => {
+                return Err(Error::Media(format!("whisper failed: {last_line}")));
+            }
+            _ => {}
+        }
+    }
+    Err(Error::Media("whisper exited unexpectedly".into()))
+}
+
+#[tauri::command]
+pub fn get_transcription_settings(app: AppHandle) -> Result<TranscriptionSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_transcription_settings(app: AppHandle, settings: TranscriptionSettings) -> Result<()> {
+    paths::save_json(&app, SETTINGS_FILE, &settings)
+}
+
+/// Transcribes any audio ffmpeg can read. `language` defaults to detection.
+#[tauri::command]
+pub async fn transcribe_audio(
+    app: AppHandle,
+    path: PathBuf,
+    language: Option<String>,
+) -> Result<Transcript> {
+    if !path.is_file() {
+        return Err(Error::InvalidInput(format!("{} not found", path.display())));
+    }
+    let language = language.unwrap_or_else(|| "auto".into());
+    validate_language(&language)?;
+    let settings = load_settings(&app)?;
+    let model = settings
+        .model_path
+        .clone()
+        .filter(|model| model.is_file())
+        .ok_or_else(|| {
+            Error::InvalidInput("No whisper model configured. Set one in settings.".into())
+        })?;
+
+    let dir = paths::cache_dir(&app)?.join("transcription");
+    fs::create_dir_all(&dir)?;
+    let output_base = dir.join(uuid::Uuid::new_v4().to_string());
+    let wav = output_base.with_extension("wav");
+    let text_path = output_base.with_extension("txt");
+
+    emit_progress(&app, &path, 0);
+    let result = async {
+        frames::run(
+            &app,
+            "ffmpeg",
+            vec![
+                "-v".into(),
+                "error".into(),
+                "-y".into(),
+                "-i".into(),
+                path.to_string_lossy().into_owned(),
+                "-ar".into(),
+                "16000".into(),
+                "-ac".into(),
+                "1".into(),
+                "-c:a".into(),
+                "pcm_s16le".into(),
+                wav.to_string_lossy().into_owned(),
+            ],
+        )
+        .await?;
+        run_whisper(
+            &app,
+            &settings,
+            &model,
+            &path,
+            &wav,
+            &output_base,
+            &language,
+        )
+        .await?;
+        Ok::<_, Error>(fs::read_to_string(&text_path)?)
+    }
+    .await;
+    for temp in [&wav, &text_path] {
+        if let Err(err) = fs::remove_file(temp)
+            && err.kind() != std::io::ErrorKind::NotFound
+        {
+            log::warn!("Failed to remove {}: {err}", temp.display());
+        }
+    }
+    let text = result?;
+    emit_progress(&app, &path, 100);
+
+    // whisper writes one segment per line
+    let text = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(Transcript {
+        path,
+        language,
+        text,
+    })
+}
@@ -0,0 +1,447 @@
+//! Global bandwidth management for downloads and uploads.
+//!
+//! Every transfer that moves review media registers with the
+//! [`TransferManager`] and asks it for each chunk before sending or writing
+//! it, so the app as a whole stays under one budget and an on-set connection
+//! is never saturated. The budget comes from `transfers.json`: a default cap,
+//! overridden by the first schedule window open at the local time, e.g. full
+//! speed after 7pm. Active transfers split the budget by priority, and a
+//! transfer may carry a cap of its own on top. `transfers:changed` reports
+//! the cap in effect and every transfer's share whenever either changes.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::paths;
+
+const SETTINGS_FILE: &str = "transfers.json";
+pub const CHANGED_EVENT: &str = "transfers:changed";
+/// How often schedule windows are checked for opening or closing.
+const TICK: Duration = Duration::from_secs(30);
+/// Transfers idle for longer, such as paused downloads, hold no share.
+const ACTIVE_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    /// Relative share of the budget while transfers compete for it.
+    fn weight(self) -> u64 {
+        match self {
+            Self::High => 4,
+            Self::Normal => 2,
+            Self::Low => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferKind {
+    Download,
+    Upload,
+}
+
+/// Local `HH:MM` times; a window may span midnight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleWindow {
+    pub start: String,
+    pub end: String,
+    /// Weekdays the window opens on, from 0 (Sunday) to 6; empty is every day.
+    #[serde(default)]
+    pub days: Vec<u8>,
+    /// Cap while the window is open; `None` lifts it.
+    #[serde(default)]
+    pub max_bytes_per_second: Option<u64>,
+}
+
+impl ScheduleWindow {
+    fn parse(&self) -> Result<(NaiveTime, NaiveTime)> {
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| {
+                Error::InvalidInput(format!("Schedule window time {time} is not HH:MM"))
+            })
+        };
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    fn opens_on(&self, weekday: u32) -> bool {
+        self.days.is_empty() || self.days.iter().any(|&day| u32::from(day) == weekday)
+    }
+
+    fn contains(&self, now: DateTime<Local>) -> bool {
+        let Ok((start, end)) = self.parse() else {
+            return false;
+        };
+        let (time, today) = (now.time(), now.weekday().num_days_from_sunday());
+        if start <= end {
+            self.opens_on(today) && start <= time && time < end
+        } else if time >= start {
+            self.opens_on(today)
+        } else {
+            // Past midnight, the window belongs to the day it opened on
+            time < end && self.opens_on((today + 6) % 7)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TransferSettings {
+    /// Cap outside every schedule window; `None` is unlimited.
+    pub max_bytes_per_second: Option<u64>,
+    /// Checked in order; the first open window sets the cap.
+    pub schedule: Vec<ScheduleWindow>,
+}
+
+impl TransferSettings {
+    fn validate(&self) -> Result<()> {
+        for window in &self.schedule {
+            window.parse()?;
+            if let Some(day) = window.days.iter().find(|&&day| day > 6) {
+                return Err(Error::InvalidInput(format!(
+                    "Weekday {day} must be between 0 (Sunday) and 6"
+                )));
+            }
+        }
+        let caps = self
+            .schedule
+            .iter()
+            .map(|window| window.max_bytes_per_second);
+        if std::iter::once(self.max_bytes_per_second)
+            .chain(caps)
+            .any(|cap| cap == Some(0))
+        {
+            return Err(Error::InvalidInput(
+                "Bandwidth caps must be above zero".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The cap in effect at `now`, and the index of the window setting it.
+    fn limit_at(&self, now: DateTime<Local>) -> Limit {
+        match self.schedule.iter().position(|window| window.contains(now)) {
+            Some(index) => Limit {
+                max_bytes_per_second: self.schedule[index].max_bytes_per_second,
+                window: Some(index),
+            },
+            None => Limit {
+                max_bytes_per_second: self.max_bytes_per_second,
+                window: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Limit {
+    max_bytes_per_second: Option<u64>,
+    window: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferSnapshot {
+    pub id: String,
+    pub kind: TransferKind,
+    pub label: String,
+    pub priority: Priority,
+    pub bytes_transferred: u64,
+    /// The transfer's own cap, e.g. a delivery target's.
+    pub max_bytes_per_second: Option<u64>,
+    /// Its share of the budget right now; `None` while unlimited.
+    pub allotted_bytes_per_second: Option<u64>,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferStatus {
+    pub max_bytes_per_second: Option<u64>,
+    /// Index of the schedule window setting the cap, if one is open.
+    pub window: Option<usize>,
+    pub transfers: Vec<TransferSnapshot>,
+}
+
+struct Entry {
+    kind: TransferKind,
+    label: String,
+    priority: Priority,
+    max_bytes_per_second: Option<u64>,
+    bytes: u64,
+    /// When the next chunk may go, pacing the transfer to its share.
+    next: Instant,
+    last_active: Option<Instant>,
+}
+
+impl Entry {
+    fn is_active(&self, now: Instant) -> bool {
+        self.last_active
+            .is_some_and(|at| now.duration_since(at) < ACTIVE_WINDOW)
+    }
+}
+
+/// Bytes per second `id` may move under `limit`, weighted against the other
+/// active transfers and capped by its own limit.
+fn share(
+    transfers: &HashMap<String, Entry>,
+    id: &str,
+    limit: Option<u64>,
+    now: Instant,
+) -> Option<u64> {
+    let entry = transfers.get(id)?;
+    let global = limit.map(|limit| {
+        let total: u64 = transfers
+            .iter()
+            .filter(|(other, transfer)| *other == id || transfer.is_active(now))
+            .map(|(_, transfer)| transfer.priority.weight())
+            .sum();
+        (limit * entry.priority.weight() / total.max(1)).max(1)
+    });
+    match (global, entry.max_bytes_per_second) {
+        (Some(global), Some(own)) => Some(global.min(own)),
+        (global, own) => global.or(own),
+    }
+}
+
+#[derive(Default)]
+pub struct TransferManager {
+    settings: Mutex<TransferSettings>,
+    transfers: Mutex<HashMap<String, Entry>>,
+    /// Last cap reported, to notice schedule windows opening and closing.
+    reported: Mutex<Limit>,
+}
+
+impl TransferManager {
+    pub fn load(app: &AppHandle) -> Result<Self> {
+        let settings: TransferSettings = paths::load_json(app, SETTINGS_FILE)?;
+        Ok(Self {
+            reported: Mutex::new(settings.limit_at(Local::now())),
+            settings: Mutex::new(settings),
+            ..Default::default()
+        })
+    }
+
+    fn settings(&self) -> std::sync::MutexGuard<'_, TransferSettings> {
+        self.settings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.transfers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn limit(&self) -> Limit {
+        self.settings().limit_at(Local::now())
+    }
+
+    fn status(&self) -> TransferStatus {
+        let limit = self.limit();
+        let transfers = self.lock();
+        let now = Instant::now();
+        let mut snapshots: Vec<_> = transfers
+            .iter()
+            .map(|(id, entry)| TransferSnapshot {
+                id: id.clone(),
+                kind: entry.kind,
+                label: entry.label.clone(),
+                priority: entry.priority,
+                bytes_transferred: entry.bytes,
+                max_bytes_per_second: entry.max_bytes_per_second,
+                allotted_bytes_per_second: share(&transfers, id, limit.max_bytes_per_second, now),
+                active: entry.is_active(now),
+            })
+            .collect();
+        snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+        TransferStatus {
+            max_bytes_per_second: limit.max_bytes_per_second,
+            window: limit.window,
+            transfers: snapshots,
+        }
+    }
+
+    fn emit(&self, app: &AppHandle) {
+        if let Err(err) = app.emit(CHANGED_EVENT, self.status()) {
+            log::warn!("Failed to emit transfer status: {err}");
+        }
+    }
+
+    /// Lets every transfer go again at once, e.g. after the cap was raised.
+    fn reset_pacing(&self) {
+        let now = Instant::now();
+        for entry in self.lock().values_mut() {
+            entry.next = now;
+        }
+    }
+
+    /// Counts `bytes` against `id` and returns how long to wait before moving them.
+    fn reserve(&self, id: &str, bytes: u64) -> Duration {
+        let limit = self.limit().max_bytes_per_second;
+        let mut transfers = self.lock();
+        let now = Instant::now();
+        let Some(entry) = transfers.get_mut(id) else {
+            return Duration::ZERO;
+        };
+        entry.bytes += bytes;
+        entry.last_active = Some(now);
+        let rate = share(&transfers, id, limit, now);
+        let Some(entry) = transfers.get_mut(id) else {
+            return Duration::ZERO;
+        };
+        let Some(rate) = rate else {
+            entry.next = now;
+            return Duration::ZERO;
+        };
+        let start = entry.next.max(now);
+        entry.next = start + Duration::from_secs_f64(bytes as f64 / rate as f64);
+        start - now
+    }
+}
+
+/// A registered transfer, removed from the manager when dropped.
+pub(crate) struct Transfer {
+    app: AppHandle,
+    id: String,
+}
+
+impl Transfer {
+    /// Waits until `bytes` more may be moved, counting them as moved.
+    pub(crate) async fn take(&self, bytes: u64) {
+        let wait = match self.app.try_state::<TransferManager>() {
+            Some(manager) => manager.reserve(&self.id, bytes),
+            None => return,
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl Drop for Transfer {
+    fn drop(&mut self) {
+        if let Some(manager) = self.app.try_state::<TransferManager>() {
+            manager.lock().remove(&self.id);
+            manager.emit(&self.app);
+        }
+    }
+}
+
+/// Registers a transfer under `id`, capped at `max_bytes_per_second` on top
+/// of the global budget when set.
+pub(crate) fn register(
+    app: &AppHandle,
+    id: String,
+    kind: TransferKind,
+    label: String,
+    priority: Priority,
+    max_bytes_per_second: Option<u64>,
+) -> Transfer {
+    if let Some(manager) = app.try_state::<TransferManager>() {
+        manager.lock().insert(
+            id.clone(),
+            Entry {
+                kind,
+                label,
+                priority,
+                max_bytes_per_second: max_bytes_per_second.filter(|&cap| cap > 0),
+                bytes: 0,
+                next: Instant::now(),
+                last_active: None,
+            },
+        );
+        manager.emit(app);
+    }
+    Transfer {
+        app: app.clone(),
+        id,
+    }
+}
+
+/// Reports schedule windows opening and closing.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(TICK).await;
+            let manager = app.state::<TransferManager>();
+            let limit = manager.limit();
+            let changed = {
+                let mut reported = manager
+                    .reported
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                std::mem::replace(&mut *reported, limit) != limit
+            };
+            if changed {
+                match limit.max_bytes_per_second {
+                    Some(cap) => log::info!("Transfer bandwidth capped at {cap} bytes/s"),
+                    None => log::info!("Transfer bandwidth cap lifted"),
+                }
+                manager.reset_pacing();
+                manager.emit(&app);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_transfer_settings(manager: State<'_, TransferManager>) -> TransferSettings {
+    manager.settings().clone()
+}
+
+#[tauri::command]
+pub fn set_transfer_settings(
+    app: AppHandle,
+    manager: State<'_, TransferManager>,
+    settings: TransferSettings,
+) -> Result<()> {
+    settings.validate()?;
+    paths::save_json(&app, SETTINGS_FILE, &settings)?;
+    let limit = settings.limit_at(Local::now());
+    *manager.settings() = settings;
+    *manager
+        .reported
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = limit;
+    manager.reset_pacing();
+    manager.emit(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_transfers(manager: State<'_, TransferManager>) -> TransferStatus {
+    manager.status()
+}
+
+#[tauri::command]
+pub fn set_transfer_priority(
+    app: AppHandle,
+    manager: State<'_, TransferManager>,
+    id: String,
+    priority: Priority,
+) -> Result<()> {
+    manager
+        .lock()
+        .get_mut(&id)
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown transfer: {id}")))?
+        .priority = priority;
+    manager.emit(&app);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! System tray icon with the unpublished-note count and quick actions.
+//!
+//! Menu actions are forwarded to the frontend as events; the count is read
+//! from the native draft store whenever drafts change.
+
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::store::Store;
+
+const TRAY_ID: &str = "main";
+
+pub const PUBLISH_ALL_EVENT: &str = "tray:publish-all";
+pub const SYNC_PLAYLISTS_EVENT: &str = "tray:sync-playlists";
+
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let publish_all = MenuItem::with_id(app, "publish-all", "Publish All", true, None::<&str>)?;
+    let sync_playlists =
+        MenuItem::with_id(app, "sync-playlists", "Sync Playlists", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &publish_all,
+            &sync_playlists,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .tooltip("AstraNotes")
+        .on_menu_event(|app, event| match event.id().as_ref() {
+            "publish-all" => emit(app, PUBLISH_ALL_EVENT),
+            "sync-playlists" => emit(app, SYNC_PLAYLISTS_EVENT),
+            "quit" => app.exit(0),
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                crate::focus_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    refresh(app);
+    Ok(())
+}
+
+/// Re-reads the unpublished draft count and updates the tray tooltip and title.
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let count = match app.state::<Store>().count_unpublished_drafts() {
+        Ok(count) => count,
+        Err(err) => {
+            log::warn!("Failed to count unpublished drafts: {err}");
+            return;
+        }
+    };
+
+    let tooltip = match count {
+        0 => "AstraNotes".to_string(),
+        1 => "AstraNotes - 1 unpublished note".to_string(),
+        n => format!("AstraNotes - {n} unpublished notes"),
+    };
+    // The title renders next to the icon on macOS and Linux; ignored on Windows
+    let title = (count > 0).then(|| count.to_string());
+    if let Err(err) = tray.set_tooltip(Some(tooltip)).and(tray.set_title(title)) {
+        log::warn!("Failed to update tray: {err}");
+    }
+}
+
+fn emit(app: &AppHandle, event: &str) {
+    if let Err(err) = app.emit(event, ()) {
+        log::warn!("Failed to emit {event}: {err}");
+    }
+}
@@ -0,0 +1,494 @@
+//! Update channels, background checks and installs.
+//!
+//! Stable builds come from the endpoint in `tauri.conf.json`. The beta and
+//! nightly channels read the `latest.json` of GitHub releases tagged `beta`
+//! and `nightly`; studios can point any channel at a mirror in `updates.json`.
+//! A background task checks on an interval and emits `updates:available`
+//! with the release notes, so the frontend can show the changelog before
+//! anything is downloaded. Only newer versions are offered, so moving from
+//! beta back to stable takes effect with the next stable release.
+//!
+//! Each installed bundle is kept under the app data directory together with
+//! the version it replaced, so a bad release can be rolled back without
+//! waiting on a fix. Pinning a version stops background checks and only ever
+//! offers that exact release, fetched from its tagged GitHub release.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::error::{Error, Result};
+use crate::network;
+use crate::paths;
+use crate::settings;
+
+const SETTINGS_FILE: &str = "updates.json";
+const HISTORY_FILE: &str = "history.json";
+const BUNDLES_DIR: &str = "updates";
+pub const AVAILABLE_EVENT: &str = "updates:available";
+pub const PROGRESS_EVENT: &str = "updates:progress";
+pub const INSTALLED_EVENT: &str = "updates:installed";
+
+const RELEASES_URL: &str = "https://github.com/matteoveglia/AstraNotes/releases";
+const DEFAULT_INTERVAL_HOURS: u64 = 12;
+/// Leave startup alone before the first background check.
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(30);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+    Nightly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UpdateSettings {
+    pub channel: UpdateChannel,
+    pub auto_check: bool,
+    pub check_interval_hours: u64,
+    /// Per-channel endpoint overrides, e.g. a studio mirror.
+    pub endpoints: HashMap<UpdateChannel, Vec<String>>,
+    pub pinned_version: Option<String>,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            channel: UpdateChannel::Stable,
+            auto_check: true,
+            check_interval_hours: DEFAULT_INTERVAL_HOURS,
+            endpoints: HashMap::new(),
+            pinned_version: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+/// The bundle replaced by the last install, used for rollback.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct History {
+    previous: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Updates {
+    pending: Mutex<Option<(Update, UpdateInfo)>>,
+    announced: Mutex<Option<String>>,
+}
+
+pub(crate) fn load_settings(app: &AppHandle) -> Result<UpdateSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn save_settings(app: &AppHandle, settings: &UpdateSettings) -> Result<()> {
+    paths::save_json(app, SETTINGS_FILE, settings)
+}
+
+fn bundles_dir(app: &AppHandle) -> Result<PathBuf> {
+    Ok(paths::data_dir(app)?.join(BUNDLES_DIR))
+}
+
+fn load_history(app: &AppHandle) -> Result<History> {
+    paths::read_json(&bundles_dir(app)?.join(HISTORY_FILE))
+}
+
+/// Records the replaced version and drops bundles no longer needed for rollback.
+fn record_install(app: &AppHandle, installed: &str, previous: &str) -> Result<()> {
+    let dir = bundles_dir(app)?;
+    fs::create_dir_all(&dir)?;
+    let history = History {
+        previous: Some(previous.to_string()),
+    };
+    paths::write_json(&dir.join(HISTORY_FILE), &history)?;
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let path = entry.path();
+        let keep = path.extension().is_none_or(|ext| ext != "bundle")
+            || [installed, previous]
+                .iter()
+                .any(|version| path.file_stem() == Some(version.as_ref()));
+        if !keep && let Err(err) = fs::remove_file(&path) {
+            log::warn!(
+                "Failed to remove old update bundle {}: {err}",
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn bundle_path(app: &AppHandle, version: &str) -> Result<PathBuf> {
+    Ok(bundles_dir(app)?.join(format!("{version}.bundle")))
+}
+
+fn validate_version(version: &str) -> Result<()> {
+    let valid = !version.is_empty()
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!("Invalid version: {version}")))
+    }
+}
+
+/// An updater that only accepts `version`, from that version's tagged release.
+fn release_updater(app: &AppHandle, version: &str) -> Result<tauri_plugin_updater::Updater> {
+    let url = format!("{RELEASES_URL}/download/v{version}/latest.json");
+    let endpoint = url
+        .parse()
+        .map_err(|err| Error::InvalidInput(format!("Invalid update endpoint {url}: {err}")))?;
+    let version = version.to_string();
+    Ok(app
+        .updater_builder()
+        .configure_client(network::configure)
+        .endpoints(vec![endpoint])?
+        .version_comparator(move |_, remote| remote.version.to_string() == version)
+        .build()?)
+}
+
+/// Endpoints for the configured channel; `None` means the bundled config.
+fn endpoints(settings: &UpdateSettings) -> Result<Option<Vec<Url>>> {
+    let urls = match settings.endpoints.get(&settings.channel) {
+        Some(urls) if !urls.is_empty() => urls.clone(),
+        _ => match settings.channel {
+            UpdateChannel::Stable => return Ok(None),
+            UpdateChannel::Beta => vec![format!("{RELEASES_URL}/download/beta/latest.json")],
+            UpdateChannel::Nightly => vec![format!("{RELEASES_URL}/download/nightly/latest.json")],
+        },
+    };
+    urls.iter()
+        .map(|url| {
+            url.parse()
+                .map_err(|err| Error::InvalidInput(format!("Invalid update endpoint {url}: {err}")))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// Checks the configured channel and keeps any update found for [`install_update`].
+pub async fn check(app: &AppHandle) -> Result<Option<UpdateInfo>> {
+    let settings = load_settings(app)?;
+    let current = app.package_info().version.to_string();
+    let update = match &settings.pinned_version {
+        Some(pinned) if *pinned == current => None,
+        Some(pinned) => release_updater(app, pinned)?.check().await?,
+        None => {
+            let mut builder = app.updater_builder().configure_client(network::configure);
+            if let Some(endpoints) = endpoints(&settings)? {
+                builder = builder.endpoints(endpoints)?;
+            }
+            builder.build()?.check().await?
+        }
+    };
+
+    let updates = app
+        .try_state::<Updates>()
+        .ok_or_else(|| Error::InvalidInput("Updates are still starting".into()))?;
+    let mut pending = updates
+        .pending
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(update) = update else {
+        *pending = None;
+        return Ok(None);
+    };
+    let info = UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        channel: settings.channel,
+        notes: update.body.clone(),
+        date: update.date.map(|date| date.to_string()),
+    };
+    *pending = Some((update, info.clone()));
+    Ok(Some(info))
+}
+
+/// Emits `updates:available` once per version found in the background.
+fn announce(app: &AppHandle, info: &UpdateInfo) {
+    let Some(updates) = app.try_state::<Updates>() else {
+        return;
+    };
+    let mut announced = updates
+        .announced
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if announced.as_deref() == Some(info.version.as_str()) {
+        return;
+    }
+    *announced = Some(info.version.clone());
+    if let Err(err) = app.emit(AVAILABLE_EVENT, info) {
+        log::warn!("Failed to emit update availability: {err}");
+    }
+}
+
+/// Starts the background check loop.
+pub fn init(app: &AppHandle) {
+    app.manage(Updates::default());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_CHECK_DELAY).await;
+        loop {
+            let settings = load_settings(&app).unwrap_or_default();
+            if settings.auto_check && settings.pinned_version.is_none() {
+                match check(&app).await {
+                    Ok(Some(info)) => announce(&app, &info),
+                    Ok(None) => {}
+                    Err(err) => log::warn!("Background update check failed: {err}"),
+                }
+            }
+            let hours = settings.check_interval_hours.max(1);
+            tokio::time::sleep(Duration::from_secs(hours * 60 * 60)).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_update_settings(app: AppHandle) -> Result<UpdateSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_update_settings(app: AppHandle, settings: UpdateSettings) -> Result<()> {
+    endpoints(&settings)?;
+    if let Some(pinned) = &settings.pinned_version {
+        validate_version(pinned)?;
+    }
+    save_settings(&app, &settings)
+}
+
+/// Switches channel and checks it straight away.
+#[tauri::command]
+pub async fn set_update_channel(
+    app: AppHandle,
+    channel: UpdateChannel,
+) -> Result<Option<UpdateInfo>> {
+    if settings::is_locked(SETTINGS_FILE, "channel") {
+        return Err(Error::InvalidInput(
+            "The update channel is managed by your administrator".into(),
+        ));
+    }
+    let mut settings = load_settings(&app)?;
+    settings.channel = channel;
+    save_settings(&app, &settings)?;
+    log::info!("Update channel set to {channel:?}");
+    check(&app).await
+}
+
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<Option<UpdateInfo>> {
+    check(&app).await
+}
+
+fn decode_base64(value: &str) -> Result<String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(value.trim())
+        .map_err(|err| Error::InvalidInput(format!("Invalid update signature encoding: {err}")))?;
+    String::from_utf8(bytes)
+        .map_err(|_| Error::InvalidInput("Update signature is not valid UTF-8".into()))
+}
+
+/// Checks `bytes` against the release signature and the bundled pubkey, as
+/// the updater does for fresh downloads.
+fn verify_bundle(app: &AppHandle, update: &Update, bytes: &[u8]) -> Result<()> {
+    let pubkey = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .ok_or_else(|| Error::InvalidInput("No updater pubkey is configured".into()))?;
+    let invalid = |err: minisign_verify::Error| {
+        Error::InvalidInput(format!("Update bundle failed verification: {err}"))
+    };
+    let pubkey = PublicKey::decode(&decode_base64(pubkey)?).map_err(invalid)?;
+    let signature = Signature::decode(&decode_base64(&update.signature)?).map_err(invalid)?;
+    pubkey.verify(bytes, &signature, true).map_err(invalid)
+}
+
+/// A bundle kept from an earlier install, if it is still signed by the
+/// release. One that fails verification is deleted.
+fn cached_bundle(app: &AppHandle, update: &Update, path: &Path) -> Option<Vec<u8>> {
+    let bytes = fs::read(path).ok()?;
+    match verify_bundle(app, update, &bytes) {
+        Ok(()) => Some(bytes),
+        Err(err) => {
+            log::warn!("Discarding cached update bundle {}: {err}", path.display());
+            if let Err(err) = fs::remove_file(path) {
+                log::warn!("Failed to remove {}: {err}", path.display());
+            }
+            None
+        }
+    }
+}
+
+/// Installs `update`, reusing a bundle kept from an earlier install when
+/// there is one and its signature still checks out. Downloads emit
+/// `updates:progress` and are kept for rollback.
+async fn install(app: &AppHandle, update: Update, info: &UpdateInfo) -> Result<()> {
+    let cached = bundle_path(app, &update.version)?;
+    let bytes = match cached_bundle(app, &update, &cached) {
+        Some(bytes) => bytes,
+        None => {
+            let mut downloaded = 0u64;
+            let mut last_emit = Instant::now();
+            let bytes = update
+                .download(
+                    |chunk, total| {
+                        downloaded += chunk as u64;
+                        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+                            last_emit = Instant::now();
+                            let _ = app.emit(PROGRESS_EVENT, Progress { downloaded, total });
+                        }
+                    },
+                    || {},
+                )
+                .await?;
+            if let Some(parent) = cached.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if let Err(err) = fs::write(&cached, &bytes) {
+                log::warn!("Failed to keep update bundle {}: {err}", cached.display());
+            }
+            bytes
+        }
+    };
+    update.install(bytes)?;
+    log::info!(
+        "Installed update {} over {}",
+        update.version,
+        update.current_version
+    );
+    if let Err(err) = record_install(app, &update.version, &update.current_version) {
+        log::warn!("Failed to record update history: {err}");
+    }
+    app.emit(INSTALLED_EVENT, info)?;
+    Ok(())
+}
+
+/// Installs the update found by the last check. Restarts the app when
+/// `restart` is set.
+#[tauri::command]
+pub async fn install_update(
+    app: AppHandle,
+    updates: State<'_, Updates>,
+    restart: Option<bool>,
+) -> Result<()> {
+    let pending = updates
+        .pending
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    let Some((update, info)) = pending else {
+        return Err(Error::InvalidInput(
+            "No update to install; check first".into(),
+        ));
+    };
+    if let Some(pinned) = load_settings(&app)?.pinned_version
+        && pinned != update.version
+    {
+        return Err(Error::InvalidInput(format!(
+            "Updates are pinned to {pinned}; unpin to install {}",
+            update.version
+        )));
+    }
+
+    install(&app, update, &info).await?;
+    if restart.unwrap_or(false) {
+        app.restart();
+    }
+    Ok(())
+}
+
+/// Reinstalls the version replaced by the last update and pins it, so the
+/// background check does not offer the release that was rolled back.
+#[tauri::command]
+pub async fn rollback_update(app: AppHandle, restart: Option<bool>) -> Result<UpdateInfo> {
+    let Some(previous) = load_history(&app)?.previous else {
+        return Err(Error::InvalidInput(
+            "No previous version to roll back to".into(),
+        ));
+    };
+    let current = app.package_info().version.to_string();
+    if previous == current {
+        return Err(Error::InvalidInput(format!("Already running {previous}")));
+    }
+    let Some(update) = release_updater(&app, &previous)?.check().await? else {
+        return Err(Error::InvalidInput(format!(
+            "Release {previous} is no longer available"
+        )));
+    };
+
+    let mut settings = load_settings(&app)?;
+    settings.pinned_version = Some(previous.clone());
+    save_settings(&app, &settings)?;
+    let info = UpdateInfo {
+        version: update.version.clone(),
+        current_version: current,
+        channel: settings.channel,
+        notes: update.body.clone(),
+        date: update.date.map(|date| date.to_string()),
+    };
+    install(&app, update, &info).await?;
+    log::info!("Rolled back to {previous}");
+    if restart.unwrap_or(false) {
+        app.restart();
+    }
+    Ok(info)
+}
+
+/// Pins updates to `version`, or unpins with `None`. While pinned, background
+/// checks stop and a manual check only offers the pinned release.
+#[tauri::command]
+pub fn pin_version(
+    app: AppHandle,
+    updates: State<'_, Updates>,
+    version: Option<String>,
+) -> Result<()> {
+    let version = version.map(|version| version.trim().trim_start_matches('v').to_string());
+    if let Some(version) = &version {
+        validate_version(version)?;
+    }
+    let mut settings = load_settings(&app)?;
+    settings.pinned_version = version;
+    save_settings(&app, &settings)?;
+    *updates
+        .pending
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    match &settings.pinned_version {
+        Some(version) => log::info!("Updates pinned to {version}"),
+        None => log::info!("Updates unpinned"),
+    }
+    Ok(())
+}
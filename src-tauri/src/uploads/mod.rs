@@ -0,0 +1,380 @@
+//! Chunked uploads of note attachments to the ftrack server location.
+//!
+//! Files are streamed from disk part by part, so large QuickTimes never have
+//! to be loaded into memory. Each part is retried independently.
+
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use reqwest::Body;
+use reqwest::header::{CONTENT_LENGTH, ETAG, HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tauri::{AppHandle, Emitter};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use crate::clipboard;
+use crate::error::{Error, Result};
+use crate::ftrack::{self, Connection, SERVER_LOCATION_ID};
+use crate::network;
+use crate::profiles;
+use crate::store;
+use crate::transfers::{self, Priority, Transfer, TransferKind};
+
+pub const PROGRESS_EVENT: &str = "uploads:progress";
+
+const PART_ATTEMPTS: u32 = 3;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadMeta {
+    /// Defaults to the active profile.
+    #[serde(flatten)]
+    pub connection: Option<Connection>,
+    /// Links the uploaded component to this note when set.
+    #[serde(default)]
+    pub note_id: Option<String>,
+    /// Component name; defaults to the file stem.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UploadStatus {
+    Uploading,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadProgress {
+    pub upload_id: String,
+    pub path: PathBuf,
+    pub sent_bytes: u64,
+    pub total_bytes: u64,
+    pub status: UploadStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResult {
+    pub upload_id: String,
+    pub component_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadMetadata {
+    url: Option<String>,
+    #[serde(default)]
+    headers: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    urls: Vec<SignedPart>,
+    upload_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignedPart {
+    part_number: u32,
+    signed_url: String,
+}
+
+struct Progress {
+    app: AppHandle,
+    upload_id: String,
+    path: PathBuf,
+    total: u64,
+    sent: AtomicU64,
+    last_emit: Mutex<Instant>,
+    throttle: Transfer,
+}
+
+impl Progress {
+    fn set(&self, sent: u64) {
+        self.sent.store(sent, Ordering::Relaxed);
+    }
+
+    fn advance(&self, bytes: u64) {
+        self.sent.fetch_add(bytes, Ordering::Relaxed);
+        let mut last_emit = self
+            .last_emit
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            *last_emit = Instant::now();
+            drop(last_emit);
+            self.emit(UploadStatus::Uploading);
+        }
+    }
+
+    fn emit(&self, status: UploadStatus) {
+        let event = UploadProgress {
+            upload_id: self.upload_id.clone(),
+            path: self.path.clone(),
+            sent_bytes: self.sent.load(Ordering::Relaxed).min(self.total),
+            total_bytes: self.total,
+            status,
+        };
+        if let Err(err) = self.app.emit(PROGRESS_EVENT, event) {
+            log::warn!("Failed to emit upload progress: {err}");
+        }
+    }
+}
+
+/// Uploads `path` as a new ftrack component and returns its ID.
+#[tauri::command]
+pub async fn upload_attachment(
+    app: AppHandle,
+    path: PathBuf,
+    meta: UploadMeta,
+) -> Result<UploadResult> {
+    let store = store::state(&app)?;
+    let connection = profiles::resolve(&store, meta.connection.clone())?;
+    let size = tokio::fs::metadata(&path).await?.len();
+    let upload_id = uuid::Uuid::new_v4().to_string();
+    let label = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let throttle = transfers::register(
+        &app,
+        format!("upload:{upload_id}"),
+        TransferKind::Upload,
+        label,
+        meta.priority,
+        None,
+    );
+    let progress = Arc::new(Progress {
+        app,
+        upload_id,
+        path: path.clone(),
+        total: size,
+        sent: AtomicU64::new(0),
+        last_emit: Mutex::new(Instant::now()),
+        throttle,
+    });
+    progress.emit(UploadStatus::Uploading);
+
+    match upload(&path, size, &connection, &meta, progress.clone()).await {
+        Ok(component_id) => {
+            progress.set(size);
+            progress.emit(UploadStatus::Completed);
+            clipboard::remove_pasted(&progress.app, &path);
+            Ok(UploadResult {
+                upload_id: progress.upload_id.clone(),
+                component_id,
+            })
+        }
+        Err(err) => {
+            log::error!("Upload of {} failed: {err}", path.display());
+            progress.emit(UploadStatus::Failed);
+            Err(err)
+        }
+    }
+}
+
+async fn upload(
+    path: &Path,
+    size: u64,
+    connection: &Connection,
+    meta: &UploadMeta,
+    progress: Arc<Progress>,
+) -> Result<String> {
+    let client = network::client();
+    let component_id = uuid::Uuid::new_v4().to_string();
+    let name = meta.name.clone().unwrap_or_else(|| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "attachment".to_string())
+    });
+    let file_type = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    ftrack::call(
+        &client,
+        connection,
+        &json!([{
+            "action": "create",
+            "entity_type": "FileComponent",
+            "entity_data": {
+                "id": component_id,
+                "name": name,
+                "file_type": file_type,
+                "size": size,
+            },
+        }]),
+    )
+    .await?;
+
+    let results = ftrack::call(
+        &client,
+        connection,
+        &json!([{
+            "action": "get_upload_metadata",
+            "component_id": component_id,
+            "file_name": format!("{name}{file_type}"),
+            "file_size": size,
+        }]),
+    )
+    .await?;
+    let metadata: UploadMetadata = results
+        .into_iter()
+        .next()
+        .and_then(|value| serde_json::from_value(value).ok())
+        .ok_or_else(|| Error::Upload("Invalid upload metadata from ftrack".into()))?;
+
+    match (metadata.upload_id, metadata.url) {
+        (Some(upload_id), _) if !metadata.urls.is_empty() => {
+            let parts = upload_parts(&client, path, size, &metadata.urls, &progress).await?;
+            ftrack::call(
+                &client,
+                connection,
+                &json!([{
+                    "action": "complete_multipart_upload",
+                    "component_id": component_id,
+                    "upload_id": upload_id,
+                    "parts": parts,
+                }]),
+            )
+            .await?;
+        }
+        (_, Some(url)) => {
+            let headers = header_map(&metadata.headers)?;
+            put_range(&client, &url, &headers, path, 0, size, &progress).await?;
+        }
+        _ => return Err(Error::Upload("Upload metadata has no target URL".into())),
+    }
+
+    let mut operations = vec![json!({
+        "action": "create",
+        "entity_type": "ComponentLocation",
+        "entity_data": {
+            "component_id": component_id,
+            "location_id": SERVER_LOCATION_ID,
+            "resource_identifier": component_id,
+        },
+    })];
+    if let Some(note_id) = &meta.note_id {
+        operations.push(json!({
+            "action": "create",
+            "entity_type": "NoteComponent",
+            "entity_data": { "note_id": note_id, "component_id": component_id },
+        }));
+    }
+    ftrack::call(&client, connection, &Value::Array(operations)).await?;
+
+    Ok(component_id)
+}
+
+async fn upload_parts(
+    client: &reqwest::Client,
+    path: &Path,
+    size: u64,
+    urls: &[SignedPart],
+    progress: &Arc<Progress>,
+) -> Result<Vec<Value>> {
+    let part_size = size.div_ceil(urls.len() as u64);
+    let mut parts = Vec::with_capacity(urls.len());
+    for part in urls {
+        // Part numbers count from 1
+        let Some(index) = part.part_number.checked_sub(1) else {
+            return Err(Error::Upload(format!(
+                "Invalid part number {}",
+                part.part_number
+            )));
+        };
+        let start = u64::from(index) * part_size;
+        let len = part_size.min(size.saturating_sub(start));
+        let e_tag = put_range(
+            client,
+            &part.signed_url,
+            &HeaderMap::new(),
+            path,
+            start,
+            len,
+            progress,
+        )
+        .await?
+        .ok_or_else(|| Error::Upload(format!("No ETag for part {}", part.part_number)))?;
+        parts.push(json!({ "part_number": part.part_number, "e_tag": e_tag }));
+    }
+    Ok(parts)
+}
+
+/// PUTs `len` bytes of `path` from `start`, retrying the whole range on failure.
+/// Returns the unquoted ETag when the server sends one.
+async fn put_range(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderMap,
+    path: &Path,
+    start: u64,
+    len: u64,
+    progress: &Arc<Progress>,
+) -> Result<Option<String>> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        progress.set(start);
+        let mut file = tokio::fs::File::open(path).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        let tracker = progress.clone();
+        let stream = ReaderStream::new(file.take(len)).then(move |chunk| {
+            let tracker = tracker.clone();
+            async move {
+                if let Ok(chunk) = &chunk {
+                    tracker.throttle.take(chunk.len() as u64).await;
+                    tracker.advance(chunk.len() as u64);
+                }
+                chunk
+            }
+        });
+
+        let result = client
+            .put(url)
+            .headers(headers.clone())
+            .header(CONTENT_LENGTH, len)
+            .body(Body::wrap_stream(stream))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match result {
+            Ok(response) => {
+                let e_tag = response
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.trim_matches('"').to_string());
+                return Ok(e_tag);
+            }
+            Err(err) if attempt < PART_ATTEMPTS => {
+                log::warn!("Upload of bytes {start}+{len} failed (attempt {attempt}): {err}");
+                tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+fn header_map(headers: &std::collections::HashMap<String, String>) -> Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|err| Error::Upload(err.to_string()))?;
+        let value = HeaderValue::from_str(value).map_err(|err| Error::Upload(err.to_string()))?;
+        map.insert(name, value);
+    }
+    Ok(map)
+}
@@ -0,0 +1,380 @@
+//! Microphone recording for voice memos attached to notes.
+//!
+//! cpal streams are not `Send` on every platform, so each recording owns a
+//! dedicated thread that opens the default input device, downmixes to mono
+//! and writes a WAV file. Stopping transcodes it to Ogg Opus with the same
+//! ffmpeg used for frame extraction and removes the WAV.
+
+use std::io::{Seek, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::{Error, Result};
+use crate::media::frames;
+use crate::paths;
+
+pub const LEVEL_EVENT: &str = "voice-notes:level";
+pub const STATE_EVENT: &str = "voice-notes:state";
+
+/// How often the level meter is updated while recording.
+const LEVEL_INTERVAL: Duration = Duration::from_millis(100);
+/// Floor for reported levels, so silence does not come out as `-inf`.
+const SILENCE_DB: f32 = -100.0;
+const OPUS_BITRATE: &str = "32k";
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioLevel {
+    /// dBFS over the last interval.
+    pub rms: f32,
+    pub peak: f32,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceNote {
+    pub id: String,
+    pub path: PathBuf,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordingState {
+    recording: bool,
+    id: Option<String>,
+}
+
+struct Captured {
+    wav_path: PathBuf,
+    sample_rate: u32,
+    samples: u64,
+}
+
+/// Running totals for the level meter and duration.
+#[derive(Default)]
+struct Meter {
+    sum_squares: f64,
+    peak: f32,
+    counted: u64,
+    samples: u64,
+}
+
+impl Meter {
+    fn write(
+        &mut self,
+        writer: &mut hound::WavWriter<impl Write + Seek>,
+        chunk: Vec<f32>,
+    ) -> Result<()> {
+        for sample in chunk {
+            let sample = sample.clamp(-1.0, 1.0);
+            writer.write_sample((sample * f32::from(i16::MAX)) as i16)?;
+            self.sum_squares += f64::from(sample * sample);
+            self.peak = self.peak.max(sample.abs());
+            self.counted += 1;
+            self.samples += 1;
+        }
+        Ok(())
+    }
+
+    /// Reads and resets the level since the last call.
+    fn level(&mut self, elapsed: Duration) -> AudioLevel {
+        let rms = if self.counted > 0 {
+            (self.sum_squares / self.counted as f64).sqrt() as f32
+        } else {
+            0.0
+        };
+        let level = AudioLevel {
+            rms: decibels(rms),
+            peak: decibels(self.peak),
+            elapsed_ms: elapsed.as_millis() as u64,
+        };
+        (self.sum_squares, self.peak, self.counted) = (0.0, 0.0, 0);
+        level
+    }
+}
+
+struct Recording {
+    id: String,
+    stop: Arc<AtomicBool>,
+    level: Arc<Mutex<AudioLevel>>,
+    worker: JoinHandle<Result<Captured>>,
+}
+
+#[derive(Default)]
+pub struct VoiceRecorder {
+    active: Mutex<Option<Recording>>,
+}
+
+impl VoiceRecorder {
+    fn take(&self) -> Result<Recording> {
+        self.active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+            .ok_or_else(|| Error::InvalidInput("No voice note is being recorded".into()))
+    }
+}
+
+fn audio_error(err: impl std::fmt::Display) -> Error {
+    Error::Audio(err.to_string())
+}
+
+fn decibels(amplitude: f32) -> f32 {
+    if amplitude > 0.0 {
+        (20.0 * amplitude.log10()).max(SILENCE_DB)
+    } else {
+        SILENCE_DB
+    }
+}
+
+fn emit_state(app: &AppHandle, id: Option<&str>) {
+    let state = RecordingState {
+        recording: id.is_some(),
+        id: id.map(str::to_string),
+    };
+    if let Err(err) = app.emit(STATE_EVENT, state) {
+        log::warn!("Failed to emit voice note state: {err}");
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    chunks: mpsc::Sender<Vec<f32>>,
+) -> Result<Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = usize::from(config.channels.max(1));
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                let mono = data
+                    .chunks(channels)
+                    .map(|frame| {
+                        frame
+                            .iter()
+                            .map(|sample| f32::from_sample(*sample))
+                            .sum::<f32>()
+                            / frame.len() as f32
+                    })
+                    .collect();
+                // The receiver only goes away once recording has stopped
+                let _ = chunks.send(mono);
+            },
+            |err| log::error!("Microphone stream error: {err}"),
+            None,
+        )
+        .map_err(audio_error)
+}
+
+/// Runs on the recording thread until `stop` is set. `ready` is dropped
+/// without a message if the device could not be opened.
+fn record(
+    app: AppHandle,
+    wav_path: PathBuf,
+    stop: Arc<AtomicBool>,
+    level: Arc<Mutex<AudioLevel>>,
+    ready: mpsc::Sender<()>,
+) -> Result<Captured> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| Error::Audio("No microphone available".into()))?;
+    let supported = device.default_input_config().map_err(audio_error)?;
+    let config = supported.config();
+    let sample_rate = config.sample_rate.0;
+
+    let (sender, chunks) = mpsc::channel();
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, sender)?,
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, sender)?,
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, sender)?,
+        SampleFormat::I32 => build_stream::<i32>(&device, &config, sender)?,
+        format => return Err(Error::Audio(format!("Unsupported sample format {format}"))),
+    };
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&wav_path, spec)?;
+    stream.play().map_err(audio_error)?;
+    let _ = ready.send(());
+
+    let started = Instant::now();
+    let mut last_level = Instant::now();
+    let mut meter = Meter::default();
+    while !stop.load(Ordering::Relaxed) {
+        match chunks.recv_timeout(LEVEL_INTERVAL) {
+            Ok(chunk) => meter.write(&mut writer, chunk)?,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if last_level.elapsed() >= LEVEL_INTERVAL {
+            let current = meter.level(started.elapsed());
+            *level
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = current;
+            if let Err(err) = app.emit(LEVEL_EVENT, current) {
+                log::debug!("Failed to emit voice note level: {err}");
+            }
+            last_level = Instant::now();
+        }
+    }
+
+    drop(stream);
+    for chunk in chunks.try_iter() {
+        meter.write(&mut writer, chunk)?;
+    }
+    writer.finalize()?;
+    Ok(Captured {
+        wav_path,
+        sample_rate,
+        samples: meter.samples,
+    })
+}
+
+async fn finish(recording: Recording) -> Result<Captured> {
+    recording.stop.store(true, Ordering::Relaxed);
+    tauri::async_runtime::spawn_blocking(move || {
+        recording
+            .worker
+            .join()
+            .unwrap_or_else(|_| Err(Error::Audio("Recording thread panicked".into())))
+    })
+    .await?
+}
+
+/// Starts recording from the default microphone. Returns the voice note id.
+#[tauri::command]
+pub async fn start_voice_note(
+    app: AppHandle,
+    recorder: State<'_, VoiceRecorder>,
+) -> Result<String> {
+    let mut active = recorder
+        .active
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if active.is_some() {
+        return Err(Error::InvalidInput(
+            "A voice note is already being recorded".into(),
+        ));
+    }
+
+    let dir = paths::data_dir(&app)?.join("voice-notes");
+    std::fs::create_dir_all(&dir)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let wav_path = dir.join(format!("{id}.wav"));
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let level = Arc::new(Mutex::new(AudioLevel::default()));
+    let (ready, started) = mpsc::channel();
+    let worker = {
+        let (app, stop, level) = (app.clone(), stop.clone(), level.clone());
+        std::thread::Builder::new()
+            .name("voice-note".into())
+            .spawn(move || record(app, wav_path, stop, level, ready))?
+    };
+    if started.recv().is_err() {
+        return match worker.join() {
+            Ok(Err(err)) => Err(err),
+            _ => Err(Error::Audio("Failed to start recording".into())),
+        };
+    }
+
+    *active = Some(Recording {
+        id: id.clone(),
+        stop,
+        level,
+        worker,
+    });
+    emit_state(&app, Some(&id));
+    Ok(id)
+}
+
+/// Stops the current recording and encodes it as Ogg Opus.
+#[tauri::command]
+pub async fn stop_voice_note(
+    app: AppHandle,
+    recorder: State<'_, VoiceRecorder>,
+) -> Result<VoiceNote> {
+    let recording = recorder.take()?;
+    let id = recording.id.clone();
+    emit_state(&app, None);
+    let captured = finish(recording).await?;
+
+    let path = captured.wav_path.with_extension("ogg");
+    let encoded = frames::run(
+        &app,
+        "ffmpeg",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-y".into(),
+            "-i".into(),
+            captured.wav_path.to_string_lossy().into_owned(),
+            "-c:a".into(),
+            "libopus".into(),
+            "-b:a".into(),
+            OPUS_BITRATE.into(),
+            "-application".into(),
+            "voip".into(),
+            path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await;
+    if let Err(err) = encoded {
+        log::error!(
+            "Failed to encode voice note {id}, keeping {}",
+            captured.wav_path.display()
+        );
+        return Err(err);
+    }
+    std::fs::remove_file(&captured.wav_path)?;
+
+    Ok(VoiceNote {
+        id,
+        path,
+        duration_ms: captured.samples * 1000 / u64::from(captured.sample_rate.max(1)),
+    })
+}
+
+/// Stops the current recording and discards it.
+#[tauri::command]
+pub async fn cancel_voice_note(app: AppHandle, recorder: State<'_, VoiceRecorder>) -> Result<()> {
+    let recording = recorder.take()?;
+    emit_state(&app, None);
+    let captured = finish(recording).await?;
+    std::fs::remove_file(&captured.wav_path)?;
+    Ok(())
+}
+
+/// The most recent meter reading, or `None` when not recording.
+#[tauri::command]
+pub fn get_voice_note_level(recorder: State<'_, VoiceRecorder>) -> Option<AudioLevel> {
+    recorder
+        .active
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .as_ref()
+        .map(|recording| {
+            *recording
+                .level
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        })
+}
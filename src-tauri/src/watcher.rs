@@ -0,0 +1,355 @@
+//! Watches render and delivery directories for newly landed files.
+//!
+//! Matches are batched for a couple of seconds before being emitted, so a
+//! render writing hundreds of frames produces one `watcher:files-added`
+//! event rather than hundreds.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use regex::Regex;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::store::Store;
+use crate::store::watches::Watch;
+
+pub const FILES_EVENT: &str = "watcher:files-added";
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectedFile {
+    pub path: PathBuf,
+    pub file_name: String,
+    /// Named groups from the watch pattern, e.g. `shot` or `version`.
+    pub captures: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilesAdded {
+    pub watch_id: String,
+    pub watch_path: String,
+    pub files: Vec<DetectedFile>,
+}
+
+struct ActiveWatch {
+    watch: Watch,
+    root: PathBuf,
+    pattern: Option<Regex>,
+}
+
+impl ActiveWatch {
+    fn new(watch: Watch) -> Result<Self> {
+        let pattern = watch
+            .pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|err| Error::InvalidInput(format!("Invalid watch pattern: {err}")))?;
+        Ok(Self {
+            root: PathBuf::from(&watch.path),
+            watch,
+            pattern,
+        })
+    }
+
+    fn detect(&self, path: &Path) -> Option<DetectedFile> {
+        let inside = if self.watch.recursive {
+            path.starts_with(&self.root)
+        } else {
+            path.parent() == Some(self.root.as_path())
+        };
+        if !inside {
+            return None;
+        }
+        let file_name = path.file_name()?.to_str()?;
+        // Skip hidden files and partial writes; the final rename reports the real name
+        if file_name.starts_with('.') || file_name.ends_with(".part") || file_name.ends_with(".tmp")
+        {
+            return None;
+        }
+        let mut captures = BTreeMap::new();
+        if let Some(pattern) = &self.pattern {
+            let caps = pattern.captures(file_name)?;
+            for name in pattern.capture_names().flatten() {
+                if let Some(value) = caps.name(name) {
+                    captures.insert(name.to_string(), value.as_str().to_string());
+                }
+            }
+        }
+        Some(DetectedFile {
+            path: path.to_path_buf(),
+            file_name: file_name.to_string(),
+            captures,
+        })
+    }
+}
+
+/// The mode `root` needs: recursive if any watch on it is. Watching a
+/// directory again replaces its mode, so it must cover every watch.
+fn root_mode<'a>(watches: impl IntoIterator<Item = &'a ActiveWatch>, root: &Path) -> RecursiveMode {
+    if watches
+        .into_iter()
+        .any(|active| active.root == root && active.watch.recursive)
+    {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    }
+}
+
+#[derive(Default)]
+pub struct FileWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    active: Mutex<HashMap<String, ActiveWatch>>,
+    pending: Mutex<HashMap<String, BTreeMap<PathBuf, DetectedFile>>>,
+    /// Set while the system sleeps; matches are held until it wakes.
+    suspended: AtomicBool,
+}
+
+impl FileWatcher {
+    fn handle(&self, event: Event) {
+        let paths: Vec<&PathBuf> = match event.kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                event.paths.iter().collect()
+            }
+            // Renames within the watched tree report both paths; only the new one matters
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                event.paths.get(1).into_iter().collect()
+            }
+            _ => return,
+        };
+
+        let active = self
+            .active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for path in paths {
+            if !path.is_file() {
+                continue;
+            }
+            for (id, watch) in active.iter() {
+                if let Some(file) = watch.detect(path) {
+                    pending
+                        .entry(id.clone())
+                        .or_default()
+                        .insert(file.path.clone(), file);
+                }
+            }
+        }
+    }
+
+    fn start(&self, active: ActiveWatch) -> Result<()> {
+        let mode = {
+            let watches = self
+                .active
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let others = watches
+                .values()
+                .filter(|other| other.watch.id != active.watch.id);
+            root_mode(others.chain([&active]), &active.root)
+        };
+        if let Some(watcher) = self
+            .watcher
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_mut()
+        {
+            watcher.watch(&active.root, mode)?;
+        }
+        self.active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(active.watch.id.clone(), active);
+        Ok(())
+    }
+
+    fn stop(&self, id: &str) {
+        let mut active = self
+            .active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(removed) = active.remove(id) else {
+            return;
+        };
+        let mut watcher = self
+            .watcher
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(watcher) = watcher.as_mut() else {
+            return;
+        };
+        // Another watch may still need the same directory, perhaps no longer recursively
+        if active.values().any(|other| other.root == removed.root) {
+            let mode = root_mode(active.values(), &removed.root);
+            if removed.watch.recursive && mode == RecursiveMode::NonRecursive {
+                let _ = watcher.unwatch(&removed.root);
+                if let Err(err) = watcher.watch(&removed.root, mode) {
+                    log::warn!("Failed to watch {} again: {err}", removed.root.display());
+                }
+            }
+            return;
+        }
+        if let Err(err) = watcher.unwatch(&removed.root) {
+            log::warn!("Failed to unwatch {}: {err}", removed.root.display());
+        }
+    }
+
+    pub fn suspend(&self) {
+        self.suspended.store(true, Ordering::Relaxed);
+    }
+
+    /// Releases held matches and re-registers every watched directory, since
+    /// network mounts often drop their watches across sleep.
+    pub fn resume(&self) {
+        let roots: HashMap<PathBuf, RecursiveMode> = {
+            let active = self
+                .active
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            active
+                .values()
+                .map(|watch| (watch.root.clone(), root_mode(active.values(), &watch.root)))
+                .collect()
+        };
+        if let Some(watcher) = self
+            .watcher
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_mut()
+        {
+            for (root, mode) in roots {
+                let _ = watcher.unwatch(&root);
+                if let Err(err) = watcher.watch(&root, mode) {
+                    log::warn!("Failed to watch {} again: {err}", root.display());
+                }
+            }
+        }
+        self.suspended.store(false, Ordering::Relaxed);
+    }
+
+    fn flush(&self, app: &AppHandle) {
+        if self.suspended.load(Ordering::Relaxed) {
+            return;
+        }
+        let pending = std::mem::take(
+            &mut *self
+                .pending
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+        if pending.is_empty() {
+            return;
+        }
+        let active = self
+            .active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (id, files) in pending {
+            let Some(watch) = active.get(&id) else {
+                continue;
+            };
+            let event = FilesAdded {
+                watch_id: id,
+                watch_path: watch.watch.path.clone(),
+                files: files.into_values().collect(),
+            };
+            if let Err(err) = app.emit(FILES_EVENT, event) {
+                log::warn!("Failed to emit watcher event: {err}");
+            }
+        }
+    }
+}
+
+/// Starts the OS watcher and restores the watches saved by previous sessions.
+pub fn init(app: &AppHandle) -> Result<()> {
+    app.manage(FileWatcher::default());
+    let state = app.state::<FileWatcher>();
+
+    let handle = app.clone();
+    let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+        Ok(event) => handle.state::<FileWatcher>().handle(event),
+        Err(err) => log::warn!("File watcher error: {err}"),
+    })?;
+    *state
+        .watcher
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(watcher);
+
+    for watch in app.state::<Store>().list_watches()? {
+        let path = watch.path.clone();
+        if let Err(err) = ActiveWatch::new(watch).and_then(|active| state.start(active)) {
+            log::warn!("Not watching {path}: {err}");
+        }
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            app.state::<FileWatcher>().flush(&app);
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn add_watch(
+    store: State<'_, Store>,
+    watcher: State<'_, FileWatcher>,
+    path: PathBuf,
+    pattern: Option<String>,
+    recursive: Option<bool>,
+) -> Result<Watch> {
+    if !path.is_dir() {
+        return Err(Error::InvalidInput(format!(
+            "{} is not a directory",
+            path.display()
+        )));
+    }
+    let pattern = pattern.filter(|pattern| !pattern.trim().is_empty());
+    if let Some(pattern) = &pattern {
+        Regex::new(pattern)
+            .map_err(|err| Error::InvalidInput(format!("Invalid watch pattern: {err}")))?;
+    }
+    let watch = store.insert_watch(
+        &path.to_string_lossy(),
+        pattern.as_deref(),
+        recursive.unwrap_or(true),
+    )?;
+    if let Err(err) = ActiveWatch::new(watch.clone()).and_then(|active| watcher.start(active)) {
+        store.delete_watch(&watch.id)?;
+        return Err(err);
+    }
+    Ok(watch)
+}
+
+#[tauri::command]
+pub fn remove_watch(
+    store: State<'_, Store>,
+    watcher: State<'_, FileWatcher>,
+    id: String,
+) -> Result<bool> {
+    watcher.stop(&id);
+    store.delete_watch(&id)
+}
+
+#[tauri::command]
+pub fn list_watches(store: State<'_, Store>) -> Result<Vec<Watch>> {
+    store.list_watches()
+}
@@ -0,0 +1,434 @@
+//! Watermarks burnt into stills and clips sent outside the studio.
+//!
+//! Templates live in `watermarks.json`, one per project with a default for
+//! the rest. A template has burn-in text in a corner and large diagonal text
+//! across the frame, both with `{token}` placeholders expanded as in note
+//! templates: `{user}` falls back to the active profile's user name, and
+//! `{date}`, `{time}` and `{datetime}` give the export time. A token without
+//! a value fails the export rather than leaving a watermark incomplete.
+//!
+//! Clips get the same watermark rendered once as an overlay at their
+//! resolution and composited by ffmpeg, so stills and clips match.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use tiny_skia::{FillRule, Paint, Transform};
+
+use crate::annotations::{self, LINE_HEIGHT};
+use crate::error::{Error, Result};
+use crate::media::frames;
+use crate::paths;
+use crate::reports::text::TextRenderer;
+use crate::store::Store;
+use crate::templates;
+
+const SETTINGS_FILE: &str = "watermarks.json";
+/// Share of the frame diagonal the diagonal text spans.
+const DIAGONAL_SPAN: f32 = 0.7;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    #[default]
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatermarkTemplate {
+    /// Burn-in text, e.g. `{user} {datetime} {project}`; `\n` starts a new line.
+    pub text: Option<String>,
+    pub corner: Corner,
+    /// Text across the frame diagonal, e.g. `CONFIDENTIAL`.
+    pub diagonal: Option<String>,
+    /// `#rrggbb`.
+    pub color: String,
+    pub text_opacity: f32,
+    pub diagonal_opacity: f32,
+    /// Burn-in text height as a share of the frame height.
+    pub text_size: f32,
+}
+
+impl Default for WatermarkTemplate {
+    fn default() -> Self {
+        Self {
+            text: Some("{user}  {datetime}  {project}".to_string()),
+            corner: Corner::default(),
+            diagonal: None,
+            color: "#ffffff".to_string(),
+            text_opacity: 0.8,
+            diagonal_opacity: 0.2,
+            text_size: 0.025,
+        }
+    }
+}
+
+impl WatermarkTemplate {
+    fn validate(&self) -> Result<()> {
+        annotations::parse_color(&self.color)?;
+        for opacity in [self.text_opacity, self.diagonal_opacity] {
+            if !(0.0..=1.0).contains(&opacity) {
+                return Err(Error::InvalidInput(format!(
+                    "Opacity {opacity} must be between 0 and 1"
+                )));
+            }
+        }
+        if !(0.005..=0.2).contains(&self.text_size) {
+            return Err(Error::InvalidInput(format!(
+                "Text size {} must be between 0.005 and 0.2",
+                self.text_size
+            )));
+        }
+        // Catch bad date formats at save time rather than on first export
+        for text in self.text.iter().chain(&self.diagonal) {
+            templates::expand(text, &HashMap::new())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatermarkSettings {
+    /// Used for projects without a template of their own.
+    pub default: Option<WatermarkTemplate>,
+    pub projects: BTreeMap<String, WatermarkTemplate>,
+}
+
+/// What the frontend passes to watermark an export.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatermarkRequest {
+    /// Selects the template; the default one applies without it.
+    #[serde(default)]
+    pub project_id: Option<String>,
+    /// Token values, e.g. `project` or `version`.
+    #[serde(default)]
+    pub context: HashMap<String, Value>,
+}
+
+/// A template with its text expanded, ready to draw.
+pub(crate) struct Watermark {
+    template: WatermarkTemplate,
+    text: Option<String>,
+    diagonal: Option<String>,
+}
+
+fn load_settings(app: &AppHandle) -> Result<WatermarkSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn save_settings(app: &AppHandle, settings: &WatermarkSettings) -> Result<()> {
+    paths::save_json(app, SETTINGS_FILE, settings)
+}
+
+fn expand(text: &str, context: &HashMap<String, Value>) -> Result<String> {
+    let expanded = templates::expand(text, context)?;
+    if !expanded.missing.is_empty() {
+        return Err(Error::InvalidInput(format!(
+            "Watermark has no value for {}",
+            expanded
+                .missing
+                .iter()
+                .map(|token| format!("{{{token}}}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    Ok(expanded.text)
+}
+
+/// Resolves the template for `request` and expands its text, or `None`
+/// when neither the project nor the default has one.
+pub(crate) fn resolve(app: &AppHandle, request: &WatermarkRequest) -> Result<Option<Watermark>> {
+    let mut settings = load_settings(app)?;
+    let template = request
+        .project_id
+        .as_ref()
+        .and_then(|id| settings.projects.remove(id))
+        .or(settings.default);
+    let Some(template) = template else {
+        return Ok(None);
+    };
+
+    let mut context = request.context.clone();
+    if !context.contains_key("user")
+        && let Some(store) = app.try_state::<Store>()
+        && let Ok(Some(profile)) = store.active_profile()
+    {
+        context.insert("user".into(), Value::String(profile.api_user));
+    }
+    Ok(Some(Watermark {
+        text: template
+            .text
+            .as_deref()
+            .map(|text| expand(text, &context))
+            .transpose()?,
+        diagonal: template
+            .diagonal
+            .as_deref()
+            .map(|text| expand(text, &context))
+            .transpose()?,
+        template,
+    }))
+}
+
+/// The watermark for `request`, or `fallback` burnt in with the default
+/// template's look when no template applies.
+pub(crate) fn burn_in(
+    app: &AppHandle,
+    request: &WatermarkRequest,
+    fallback: &str,
+) -> Result<Watermark> {
+    if let Some(watermark) = resolve(app, request)? {
+        return Ok(watermark);
+    }
+    Ok(Watermark {
+        template: WatermarkTemplate::default(),
+        text: Some(fallback.to_string()),
+        diagonal: None,
+    })
+}
+
+fn paint(color: &str, opacity: f32) -> Result<Paint<'static>> {
+    let mut color = annotations::parse_color(color)?;
+    color.apply_opacity(opacity);
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+    Ok(paint)
+}
+
+impl Watermark {
+    /// Draws the watermark over `image`.
+    pub(crate) fn apply(&self, image: RgbaImage) -> Result<RgbaImage> {
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let mut pixmap = annotations::to_pixmap(image)?;
+        let renderer = TextRenderer::new();
+
+        if let Some(text) = &self.diagonal {
+            let lines: Vec<&str> = text.lines().collect();
+            // Sized at 100px, then scaled to span the diagonal
+            let widest = lines
+                .iter()
+                .map(|line| renderer.width(line, 100.0))
+                .fold(0.0, f32::max);
+            if widest > 0.0 {
+                let size = 100.0 * DIAGONAL_SPAN * width.hypot(height) / widest;
+                let block = size * LINE_HEIGHT * lines.len() as f32;
+                let (x, y) = (-widest * size / 100.0 / 2.0, -block / 2.0);
+                if let Some(path) = annotations::outline_text(&renderer, &lines, size, x, y) {
+                    let angle = -(height.atan2(width).to_degrees());
+                    let transform =
+                        Transform::from_rotate(angle).post_translate(width / 2.0, height / 2.0);
+                    pixmap.fill_path(
+                        &path,
+                        &paint(&self.template.color, self.template.diagonal_opacity)?,
+                        FillRule::Winding,
+                        transform,
+                        None,
+                    );
+                }
+            }
+        }
+
+        if let Some(text) = &self.text {
+            let lines: Vec<&str> = text.lines().collect();
+            let size = (height * self.template.text_size).max(10.0);
+            let margin = size;
+            let block_width = lines
+                .iter()
+                .map(|line| renderer.width(line, size))
+                .fold(0.0, f32::max);
+            let block_height = size * LINE_HEIGHT * lines.len() as f32;
+            let x = match self.template.corner {
+                Corner::TopLeft | Corner::BottomLeft => margin,
+                Corner::TopRight | Corner::BottomRight => width - margin - block_width,
+            };
+            let y = match self.template.corner {
+                Corner::TopLeft | Corner::TopRight => margin,
+                Corner::BottomLeft | Corner::BottomRight => height - margin - block_height,
+            };
+            if let Some(path) = annotations::outline_text(&renderer, &lines, size, x, y) {
+                // A soft shadow keeps the text legible on bright plates
+                let shadow = Transform::from_translate(size * 0.06, size * 0.06);
+                pixmap.fill_path(
+                    &path,
+                    &paint("#000000", self.template.text_opacity * 0.6)?,
+                    FillRule::Winding,
+                    shadow,
+                    None,
+                );
+                pixmap.fill_path(
+                    &path,
+                    &paint(&self.template.color, self.template.text_opacity)?,
+                    FillRule::Winding,
+                    Transform::identity(),
+                    None,
+                );
+            }
+        }
+        annotations::from_pixmap(pixmap)
+    }
+}
+
+/// `name.part.ext` next to `path`, so ffmpeg still picks the format from the extension.
+pub(crate) fn partial_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{stem}.part.{}", ext.to_string_lossy())),
+        None => path.with_file_name(format!("{stem}.part")),
+    }
+}
+
+async fn probe_size(app: &AppHandle, path: &Path) -> Result<(u32, u32)> {
+    let output = frames::run(
+        app,
+        "ffprobe",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-select_streams".into(),
+            "v:0".into(),
+            "-show_entries".into(),
+            "stream=width,height".into(),
+            "-of".into(),
+            "csv=p=0".into(),
+            path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    let size = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    size.split_once(',')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height): &(u32, u32)| width > 0 && height > 0)
+        .ok_or_else(|| Error::Media(format!("Unreadable video size {size:?}")))
+}
+
+#[tauri::command]
+pub fn get_watermark_settings(app: AppHandle) -> Result<WatermarkSettings> {
+    load_settings(&app)
+}
+
+/// Sets the template of `project_id`, or the default without one. `None`
+/// removes it.
+#[tauri::command]
+pub fn set_watermark_template(
+    app: AppHandle,
+    project_id: Option<String>,
+    template: Option<WatermarkTemplate>,
+) -> Result<()> {
+    if let Some(template) = &template {
+        template.validate()?;
+    }
+    let mut settings = load_settings(&app)?;
+    match (project_id, template) {
+        (Some(id), Some(template)) => {
+            settings.projects.insert(id, template);
+        }
+        (Some(id), None) => {
+            settings.projects.remove(&id);
+        }
+        (None, template) => settings.default = template,
+    }
+    save_settings(&app, &settings)
+}
+
+/// Writes a watermarked copy of the still at `path` to `out_path`, a PNG or
+/// JPEG by extension.
+#[tauri::command]
+pub async fn watermark_still(
+    app: AppHandle,
+    path: PathBuf,
+    out_path: PathBuf,
+    watermark: WatermarkRequest,
+) -> Result<PathBuf> {
+    let watermark = resolve(&app, &watermark)?
+        .ok_or_else(|| Error::InvalidInput("No watermark template for this project".into()))?;
+    let out = out_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = image::open(&path)?.into_rgba8();
+        annotations::write(&out, watermark.apply(image)?)
+    })
+    .await??;
+    Ok(out_path)
+}
+
+/// Transcodes the clip at `path` to H.264 at `out_path` with the watermark
+/// burnt in, keeping its audio.
+#[tauri::command]
+pub async fn watermark_clip(
+    app: AppHandle,
+    path: PathBuf,
+    out_path: PathBuf,
+    watermark: WatermarkRequest,
+) -> Result<PathBuf> {
+    if !path.is_file() {
+        return Err(Error::InvalidInput(format!("{} not found", path.display())));
+    }
+    let watermark = resolve(&app, &watermark)?
+        .ok_or_else(|| Error::InvalidInput("No watermark template for this project".into()))?;
+    let (width, height) = probe_size(&app, &path).await?;
+
+    let dir = paths::cache_dir(&app)?.join("watermarks");
+    tokio::fs::create_dir_all(&dir).await?;
+    let overlay = dir.join(format!("{}.png", uuid::Uuid::new_v4().simple()));
+    let overlay_path = overlay.clone();
+    tauri::async_runtime::spawn_blocking(move || -> Result<()> {
+        let layer = watermark.apply(RgbaImage::new(width, height))?;
+        layer.save_with_format(&overlay_path, image::ImageFormat::Png)?;
+        Ok(())
+    })
+    .await??;
+
+    if let Some(parent) = out_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let partial = partial_path(&out_path);
+    let result = frames::run(
+        &app,
+        "ffmpeg",
+        vec![
+            "-v".into(),
+            "error".into(),
+            "-y".into(),
+            "-i".into(),
+            path.to_string_lossy().into_owned(),
+            "-i".into(),
+            overlay.to_string_lossy().into_owned(),
+            "-filter_complex".into(),
+            "[0:v][1:v]overlay=0:0:format=auto,format=yuv420p[v]".into(),
+            "-map".into(),
+            "[v]".into(),
+            "-map".into(),
+            "0:a?".into(),
+            "-c:v".into(),
+            "libx264".into(),
+            "-crf".into(),
+            "18".into(),
+            "-preset".into(),
+            "medium".into(),
+            "-c:a".into(),
+            "aac".into(),
+            "-movflags".into(),
+            "+faststart".into(),
+            partial.to_string_lossy().into_owned(),
+        ],
+    )
+    .await;
+    let _ = tokio::fs::remove_file(&overlay).await;
+    if let Err(err) = result {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(err);
+    }
+    tokio::fs::rename(&partial, &out_path).await?;
+    Ok(out_path)
+}
@@ -0,0 +1,324 @@
+//! Optional HTTP listener for webhooks from farm managers, CI and trackers.
+//!
+//! Integrations POST to `/hooks/<source>`. A request has to come from an
+//! allowlisted address and, unless signing is switched off, carry an
+//! `X-Signature-256: sha256=<hex>` HMAC of the body made with the shared
+//! secret. The secret is kept in the keychain. Accepted payloads go to the
+//! frontend as `webhooks:event`, so a farm job finishing can show up as a
+//! notification without anyone polling.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Path, State as Extract};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::net::TcpListener;
+
+use crate::credentials;
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::store::now_millis;
+
+const SETTINGS_FILE: &str = "webhooks.json";
+const SECRET_KEY: &str = "webhook-secret";
+pub const EVENT: &str = "webhooks:event";
+pub const STATUS_EVENT: &str = "webhooks:status";
+
+const DEFAULT_PORT: u16 = 47821;
+const MAX_BODY: usize = 1024 * 1024;
+const SIGNATURE_HEADER: &str = "x-signature-256";
+/// Optional header naming the event, e.g. `render.completed`.
+const EVENT_HEADER: &str = "x-webhook-event";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebhookSettings {
+    pub enabled: bool,
+    /// `127.0.0.1` keeps the listener local; `0.0.0.0` opens it to the LAN.
+    pub bind_address: IpAddr,
+    pub port: u16,
+    /// Networks allowed to post. Empty allows loopback only.
+    pub allowlist: Vec<IpNet>,
+    pub require_signature: bool,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: DEFAULT_PORT,
+            allowlist: Vec::new(),
+            require_signature: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "state")]
+pub enum WebhookStatus {
+    Stopped,
+    Listening { address: SocketAddr },
+    Failed { message: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookEvent {
+    pub source: String,
+    pub event: Option<String>,
+    pub remote_address: IpAddr,
+    pub received_at: i64,
+    /// The body as JSON, or as a string when it is not JSON.
+    pub payload: Value,
+}
+
+pub struct WebhookServer {
+    task: Mutex<Option<JoinHandle<()>>>,
+    status: Mutex<WebhookStatus>,
+}
+
+impl Default for WebhookServer {
+    fn default() -> Self {
+        Self {
+            task: Mutex::new(None),
+            status: Mutex::new(WebhookStatus::Stopped),
+        }
+    }
+}
+
+impl WebhookServer {
+    fn stop(&self) {
+        if let Some(task) = self
+            .task
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+        {
+            task.abort();
+        }
+    }
+}
+
+struct Receiver {
+    app: AppHandle,
+    allowlist: Vec<IpNet>,
+    secret: Option<Vec<u8>>,
+    require_signature: bool,
+}
+
+impl Receiver {
+    fn allows(&self, address: IpAddr) -> bool {
+        let address = match address {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(address, IpAddr::V4),
+            address => address,
+        };
+        if self.allowlist.is_empty() {
+            return address.is_loopback();
+        }
+        self.allowlist.iter().any(|net| net.contains(&address))
+    }
+
+    fn verify(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        let Some(secret) = &self.secret else {
+            return !self.require_signature;
+        };
+        let Some(signature) = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().trim_start_matches("sha256="))
+            .and_then(decode_hex)
+        else {
+            return false;
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+        mac.update(body);
+        mac.verify_slice(&signature).is_ok()
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+async fn receive(
+    Extract(receiver): Extract<Arc<Receiver>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    Path(source): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if !receiver.allows(remote.ip()) {
+        log::warn!("Rejected webhook from {}: not allowlisted", remote.ip());
+        return StatusCode::FORBIDDEN;
+    }
+    if !receiver.verify(&headers, &body) {
+        log::warn!("Rejected webhook from {}: bad signature", remote.ip());
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let payload = serde_json::from_slice(&body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned()));
+    let event = WebhookEvent {
+        source,
+        event: headers
+            .get(EVENT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        remote_address: remote.ip(),
+        received_at: now_millis(),
+        payload,
+    };
+    if let Err(err) = receiver.app.emit(EVENT, &event) {
+        log::warn!("Failed to emit webhook event: {err}");
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+    StatusCode::ACCEPTED
+}
+
+fn load_settings(app: &AppHandle) -> Result<WebhookSettings> {
+    paths::load_json(app, SETTINGS_FILE)
+}
+
+fn set_status(app: &AppHandle, status: WebhookStatus) {
+    let server = app.state::<WebhookServer>();
+    let mut current = server
+        .status
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *current != status {
+        *current = status.clone();
+        if let Err(err) = app.emit(STATUS_EVENT, status) {
+            log::warn!("Failed to emit webhook status: {err}");
+        }
+    }
+}
+
+/// Stops any running listener and starts a new one if `settings` enable it.
+async fn restart(app: &AppHandle, settings: &WebhookSettings) -> Result<()> {
+    let server = app.state::<WebhookServer>();
+    server.stop();
+    if !settings.enabled {
+        set_status(app, WebhookStatus::Stopped);
+        return Ok(());
+    }
+
+    let secret = credentials::get(SECRET_KEY)?.map(String::into_bytes);
+    if secret.is_none() && settings.require_signature {
+        log::warn!(
+            "Webhook signing is required but no secret is set; all requests will be rejected"
+        );
+    }
+    let receiver = Arc::new(Receiver {
+        app: app.clone(),
+        allowlist: settings.allowlist.clone(),
+        secret,
+        require_signature: settings.require_signature,
+    });
+    let router = Router::new()
+        .route("/hooks/{source}", post(receive))
+        .layer(DefaultBodyLimit::max(MAX_BODY))
+        .with_state(receiver);
+
+    let listener = match TcpListener::bind((settings.bind_address, settings.port)).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            set_status(
+                app,
+                WebhookStatus::Failed {
+                    message: err.to_string(),
+                },
+            );
+            return Err(err.into());
+        }
+    };
+    let address = listener.local_addr()?;
+    log::info!("Listening for webhooks on {address}");
+    set_status(app, WebhookStatus::Listening { address });
+
+    let task_app = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let service = router.into_make_service_with_connect_info::<SocketAddr>();
+        if let Err(err) = axum::serve(listener, service).await {
+            log::error!("Webhook listener stopped: {err}");
+            set_status(
+                &task_app,
+                WebhookStatus::Failed {
+                    message: err.to_string(),
+                },
+            );
+        }
+    });
+    *server
+        .task
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(task);
+    Ok(())
+}
+
+/// Starts the listener at launch if it was left enabled.
+pub fn init(app: &AppHandle) {
+    app.manage(WebhookServer::default());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = match load_settings(&app) {
+            Ok(settings) => restart(&app, &settings).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            log::error!("Failed to start webhook listener: {err}");
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_webhook_settings(app: AppHandle) -> Result<WebhookSettings> {
+    load_settings(&app)
+}
+
+/// Saves the settings and restarts the listener with them.
+#[tauri::command]
+pub async fn set_webhook_settings(app: AppHandle, settings: WebhookSettings) -> Result<()> {
+    if settings.port == 0 {
+        return Err(Error::InvalidInput("Webhook port must not be 0".into()));
+    }
+    paths::save_json(&app, SETTINGS_FILE, &settings)?;
+    restart(&app, &settings).await
+}
+
+/// Stores the shared HMAC secret, or removes it when `secret` is `None`.
+#[tauri::command]
+pub async fn set_webhook_secret(app: AppHandle, secret: Option<String>) -> Result<()> {
+    match secret.filter(|secret| !secret.is_empty()) {
+        Some(secret) => credentials::store(SECRET_KEY, &secret)?,
+        None => {
+            credentials::delete(SECRET_KEY)?;
+        }
+    }
+    restart(&app, &load_settings(&app)?).await
+}
+
+#[tauri::command]
+pub fn get_webhook_status(server: State<'_, WebhookServer>) -> WebhookStatus {
+    server
+        .status
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
@@ -0,0 +1,250 @@
+//! Remembers window size, position, maximized state and monitor.
+//!
+//! State is saved per window label in `window-state.json` shortly after a
+//! window is moved or resized. A window is restored onto the monitor it was
+//! last on, matched by name. If that monitor is gone, for instance after
+//! undocking a laptop, the window moves to the monitor under its old centre
+//! or the primary one, and is scaled and clamped into the visible work area.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, Window, WindowEvent,
+};
+
+use crate::error::Result;
+use crate::paths;
+
+const STATE_FILE: &str = "window-state.json";
+/// Moves and resizes arrive in bursts; save once they settle.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bounds {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl Bounds {
+    fn center(&self) -> (i32, i32) {
+        (
+            self.x + self.width as i32 / 2,
+            self.y + self.height as i32 / 2,
+        )
+    }
+}
+
+/// Physical pixels, with the scale factor they were recorded at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowState {
+    bounds: Bounds,
+    scale_factor: f64,
+    monitor: Option<String>,
+    maximized: bool,
+}
+
+#[derive(Default)]
+pub struct WindowStates {
+    states: Mutex<HashMap<String, WindowState>>,
+    save_scheduled: AtomicBool,
+}
+
+impl WindowStates {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, WindowState>> {
+        self.states
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn state_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(paths::config_dir(app)?.join(STATE_FILE))
+}
+
+fn load(app: &AppHandle) -> HashMap<String, WindowState> {
+    state_path(app)
+        .and_then(|path| paths::read_json(&path))
+        .unwrap_or_else(|err| {
+            log::warn!("Ignoring invalid {STATE_FILE}: {err}");
+            HashMap::new()
+        })
+}
+
+fn save(app: &AppHandle) -> Result<()> {
+    let states = app.state::<WindowStates>().lock().clone();
+    paths::write_json(&state_path(app)?, &states)
+}
+
+fn schedule_save(app: &AppHandle) {
+    let states = app.state::<WindowStates>();
+    if states.save_scheduled.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SAVE_DELAY).await;
+        app.state::<WindowStates>()
+            .save_scheduled
+            .store(false, Ordering::Release);
+        if let Err(err) = save(&app) {
+            log::warn!("Failed to save window state: {err}");
+        }
+    });
+}
+
+/// Loads saved state and restores the main window, which starts hidden so
+/// it does not visibly jump into place.
+pub fn init(app: &AppHandle) {
+    app.manage(WindowStates {
+        states: Mutex::new(load(app)),
+        save_scheduled: AtomicBool::new(false),
+    });
+    if let Some(window) = app.get_webview_window("main") {
+        restore(&window);
+        if let Err(err) = window.show() {
+            log::warn!("Failed to show main window: {err}");
+        }
+    }
+}
+
+fn contains(monitor: &Monitor, (x, y): (i32, i32)) -> bool {
+    let area = monitor.work_area();
+    x >= area.position.x
+        && y >= area.position.y
+        && x < area.position.x + area.size.width as i32
+        && y < area.position.y + area.size.height as i32
+}
+
+/// Applies the saved state for `window`'s label, if there is one.
+pub fn restore(window: &WebviewWindow) {
+    let Some(state) = window
+        .app_handle()
+        .state::<WindowStates>()
+        .lock()
+        .get(window.label())
+        .cloned()
+    else {
+        return;
+    };
+    if let Err(err) = apply(window, &state) {
+        log::warn!("Failed to restore window {}: {err}", window.label());
+    }
+}
+
+fn apply(window: &WebviewWindow, state: &WindowState) -> tauri::Result<()> {
+    let monitors = window.available_monitors()?;
+    let by_name = monitors
+        .iter()
+        .find(|monitor| state.monitor.is_some() && monitor.name() == state.monitor.as_ref());
+    let monitor = match by_name.or_else(|| {
+        monitors
+            .iter()
+            .find(|monitor| contains(monitor, state.bounds.center()))
+    }) {
+        Some(monitor) => monitor.clone(),
+        None => match window.primary_monitor()? {
+            Some(monitor) => monitor,
+            None => return Ok(()),
+        },
+    };
+
+    let area = monitor.work_area();
+    let ratio = monitor.scale_factor() / state.scale_factor.max(0.1);
+    let width = ((state.bounds.width as f64 * ratio) as u32).min(area.size.width);
+    let height = ((state.bounds.height as f64 * ratio) as u32).min(area.size.height);
+    let max_x = area.position.x + (area.size.width - width) as i32;
+    let max_y = area.position.y + (area.size.height - height) as i32;
+    let (x, y) = if contains(&monitor, state.bounds.center()) {
+        (
+            state.bounds.x.clamp(area.position.x, max_x),
+            state.bounds.y.clamp(area.position.y, max_y),
+        )
+    } else {
+        // The old position belongs to a monitor that is gone; centre instead
+        (
+            area.position.x + (area.size.width - width) as i32 / 2,
+            area.position.y + (area.size.height - height) as i32 / 2,
+        )
+    };
+
+    window.set_size(PhysicalSize::new(width, height))?;
+    window.set_position(PhysicalPosition::new(x, y))?;
+    if state.maximized {
+        window.maximize()?;
+    }
+    Ok(())
+}
+
+fn capture(window: &Window, previous: Option<&WindowState>) -> tauri::Result<Option<WindowState>> {
+    if window.is_minimized()? {
+        return Ok(None);
+    }
+    let maximized = window.is_maximized()?;
+    let bounds = match (maximized, previous) {
+        // Keep the restored bounds so unmaximizing after a relaunch has somewhere to go
+        (true, Some(previous)) => previous.bounds,
+        _ => {
+            let position = window.outer_position()?;
+            let size = window.inner_size()?;
+            Bounds {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+            }
+        }
+    };
+    Ok(Some(WindowState {
+        bounds,
+        scale_factor: window.scale_factor()?,
+        monitor: window
+            .current_monitor()?
+            .and_then(|monitor| monitor.name().cloned()),
+        maximized,
+    }))
+}
+
+/// Records state changes; called for every window event.
+pub fn track(window: &Window, event: &WindowEvent) {
+    if !matches!(
+        event,
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. }
+    ) {
+        return;
+    }
+    let app = window.app_handle();
+    // Events can arrive before setup has managed the state
+    let Some(states) = app.try_state::<WindowStates>() else {
+        return;
+    };
+    let previous = states.lock().get(window.label()).cloned();
+    match capture(window, previous.as_ref()) {
+        Ok(Some(state)) => {
+            states.lock().insert(window.label().to_string(), state);
+            schedule_save(app);
+        }
+        Ok(None) => {}
+        Err(err) => log::warn!("Failed to read window state: {err}"),
+    }
+}
+
+/// Forgets all saved window state and recentres the main window.
+#[tauri::command]
+pub fn reset_window_state(app: AppHandle) -> Result<()> {
+    app.state::<WindowStates>().lock().clear();
+    save(&app)?;
+    if let Some(window) = app.get_webview_window("main") {
+        window.unmaximize()?;
+        window.center()?;
+    }
+    Ok(())
+}